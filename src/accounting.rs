@@ -0,0 +1,252 @@
+//! Accounting module
+//!
+//! This module measures what each tool call costs, for audit logs, quotas and capacity
+//! planning. The server runs every call with a [`Meter`]: tools count the bytes they
+//! read and write with [`read`] and [`written`], work on the blocking thread pool adds
+//! the CPU time of its thread, and subprocesses started with [`command_output`] add
+//! theirs. CPU time is read from `/proc`, so it is only measured on Linux. The usage of
+//! a call goes with its `tool_call_completed` or `tool_call_failed` event, and totals
+//! per tool are returned by the `server/stats` method.
+//!
+//! The figures are approximate: bytes are only counted by tools that report them, CPU
+//! time is counted in 10 ms ticks, and the CPU time of subprocesses is the growth of the
+//! server's waited-for children, which includes subprocesses of other calls finishing at
+//! the same time.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+tokio::task_local! {
+    static TASK_METER: Meter;
+}
+
+thread_local! {
+    static THREAD_METER: RefCell<Option<Meter>> = const { RefCell::new(None) };
+}
+
+/// Resources used by a tool call, besides its wall time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceUsage {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// CPU time of blocking work and subprocesses, when it could be measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_ms: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    cpu_micros: AtomicU64,
+    cpu_measured: AtomicBool,
+}
+
+/// Collects the resource usage of one tool call
+#[derive(Debug, Clone, Default)]
+pub struct Meter(Arc<Counters>);
+
+impl Meter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run a future with this meter as the current one
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        TASK_METER.scope(self.clone(), future).await
+    }
+
+    /// Run blocking work with this meter as the current one, adding the thread's CPU time
+    pub fn run_blocking<T>(&self, work: impl FnOnce() -> T) -> T {
+        let previous = THREAD_METER.with(|meter| meter.replace(Some(self.clone())));
+        let started = thread_cpu_time();
+        let result = work();
+        if let (Some(started), Some(finished)) = (started, thread_cpu_time()) {
+            self.add_cpu(finished.saturating_sub(started));
+        }
+        THREAD_METER.with(|meter| meter.replace(previous));
+        result
+    }
+
+    pub fn add_read(&self, bytes: u64) {
+        self.0.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_written(&self, bytes: u64) {
+        self.0.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_cpu(&self, time: Duration) {
+        self.0
+            .cpu_micros
+            .fetch_add(time.as_micros() as u64, Ordering::Relaxed);
+        self.0.cpu_measured.store(true, Ordering::Relaxed);
+    }
+
+    /// The usage collected so far
+    pub fn usage(&self) -> ResourceUsage {
+        ResourceUsage {
+            bytes_read: self.0.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.0.bytes_written.load(Ordering::Relaxed),
+            cpu_ms: self
+                .0
+                .cpu_measured
+                .load(Ordering::Relaxed)
+                .then(|| self.0.cpu_micros.load(Ordering::Relaxed) / 1000),
+        }
+    }
+}
+
+/// The meter of the tool call running on this task or thread
+pub fn current() -> Option<Meter> {
+    TASK_METER
+        .try_with(Meter::clone)
+        .ok()
+        .or_else(|| THREAD_METER.with(|meter| meter.borrow().clone()))
+}
+
+/// Count bytes the current tool call read
+pub fn read(bytes: u64) {
+    if let Some(meter) = current() {
+        meter.add_read(bytes);
+    }
+}
+
+/// Count bytes the current tool call wrote
+pub fn written(bytes: u64) {
+    if let Some(meter) = current() {
+        meter.add_written(bytes);
+    }
+}
+
+/// Run a command to completion like [`Command::output`], counting its CPU time
+pub fn command_output(command: &mut Command) -> std::io::Result<Output> {
+    let started = children_cpu_time();
+    let output = command.output();
+    if let (Some(meter), Some(started), Some(finished)) = (current(), started, children_cpu_time())
+    {
+        meter.add_cpu(finished.saturating_sub(started));
+    }
+    output
+}
+
+/// Clock ticks per second of the times in `/proc`, fixed by the kernel ABI
+#[cfg(target_os = "linux")]
+const USER_HZ: u64 = 100;
+
+/// User and system CPU time of the calling thread
+#[cfg(target_os = "linux")]
+fn thread_cpu_time() -> Option<Duration> {
+    proc_cpu_time("/proc/thread-self/stat", 11)
+}
+
+/// User and system CPU time of the server's terminated and waited-for children
+#[cfg(target_os = "linux")]
+fn children_cpu_time() -> Option<Duration> {
+    proc_cpu_time("/proc/self/stat", 13)
+}
+
+/// Sum of the two clock tick counts at `field` of a `/proc` stat file, counting fields
+/// after the command name
+#[cfg(target_os = "linux")]
+fn proc_cpu_time(path: &str, field: usize) -> Option<Duration> {
+    let stat = std::fs::read_to_string(path).ok()?;
+    // The command name may contain spaces, so fields are counted from its closing ')'
+    let mut fields = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .skip(field);
+    let user: u64 = fields.next()?.parse().ok()?;
+    let system: u64 = fields.next()?.parse().ok()?;
+    Some(Duration::from_millis((user + system) * 1000 / USER_HZ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn children_cpu_time() -> Option<Duration> {
+    None
+}
+
+/// Totals of the calls of one tool
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ToolStats {
+    pub calls: u64,
+    /// Calls answered with an error or an `isError` result
+    pub failures: u64,
+    pub wall_ms: u64,
+    pub max_wall_ms: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    /// CPU time of the calls for which it was measured
+    pub cpu_ms: u64,
+}
+
+/// Response from server/stats request
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsResponse {
+    /// When counting started, i.e. the server start
+    pub since: DateTime<Utc>,
+    pub tools: BTreeMap<String, ToolStats>,
+}
+
+/// Resource usage of the server's tool calls, per tool
+#[derive(Debug)]
+pub struct Stats {
+    since: DateTime<Utc>,
+    tools: Mutex<BTreeMap<String, ToolStats>>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            since: Utc::now(),
+            tools: Mutex::default(),
+        }
+    }
+}
+
+impl Stats {
+    /// Add a finished call
+    pub fn record(&self, tool: &str, failed: bool, wall: Duration, usage: &ResourceUsage) {
+        let Ok(mut tools) = self.tools.lock() else {
+            return;
+        };
+        let stats = tools.entry(tool.to_string()).or_default();
+        let wall_ms = wall.as_millis() as u64;
+        stats.calls += 1;
+        stats.failures += u64::from(failed);
+        stats.wall_ms += wall_ms;
+        stats.max_wall_ms = stats.max_wall_ms.max(wall_ms);
+        stats.bytes_read += usage.bytes_read;
+        stats.bytes_written += usage.bytes_written;
+        stats.cpu_ms += usage.cpu_ms.unwrap_or(0);
+    }
+
+    /// When counting started, i.e. the server start
+    pub fn since(&self) -> DateTime<Utc> {
+        self.since
+    }
+
+    /// The totals so far
+    pub fn snapshot(&self) -> StatsResponse {
+        StatsResponse {
+            since: self.since,
+            tools: self
+                .tools
+                .lock()
+                .map(|tools| tools.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Admission control module
+//!
+//! This module sheds load when the server is saturated: a tools/call request is
+//! rejected with a busy error instead of being queued when the number of tool calls
+//! in flight has reached the limit configured in `[admission]`, either for the whole
+//! server or for the calling session. A batch job flooding the server then gets fast
+//! rejections it can retry, while interactive clients keep bounded latency.
+
+use crate::events::{EventKind, ServerEvent};
+use crate::mcp::{JsonRpcError, McpServerState};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// JSON-RPC error code of requests rejected because the server is busy
+pub const SERVER_BUSY: i32 = -32000;
+
+/// Tool calls in flight, server-wide and per session
+#[derive(Debug, Default)]
+pub struct Admission {
+    in_flight: Mutex<InFlight>,
+}
+
+#[derive(Debug, Default)]
+struct InFlight {
+    total: usize,
+    sessions: HashMap<String, usize>,
+}
+
+/// A slot for one tool call, released when dropped
+#[derive(Debug)]
+pub struct Permit {
+    admission: Arc<Admission>,
+    session_id: Option<String>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut in_flight = self.admission.lock();
+        in_flight.total = in_flight.total.saturating_sub(1);
+        if let Some(id) = &self.session_id
+            && let Some(count) = in_flight.sessions.get_mut(id)
+        {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                in_flight.sessions.remove(id);
+            }
+        }
+    }
+}
+
+impl Admission {
+    /// Number of tool calls in flight
+    pub fn in_flight(&self) -> usize {
+        self.lock().total
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, InFlight> {
+        // A panic while holding the lock cannot leave the counters inconsistent
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Admit a tool call or reject it with a busy error
+///
+/// Rejections are reported as `quota_exceeded` server events.
+pub fn admit(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    tool: Option<&str>,
+) -> Result<Permit, JsonRpcError> {
+    let config = &state.config.admission;
+    let mut in_flight = state.admission.lock();
+
+    let session_count = session_id
+        .and_then(|id| in_flight.sessions.get(id))
+        .copied()
+        .unwrap_or(0);
+    let rejection = match (config.max_in_flight, config.max_in_flight_per_session) {
+        (Some(limit), _) if in_flight.total >= limit => Some((
+            format!(
+                "Server busy: {} tool calls in flight (limit {}); retry later",
+                in_flight.total, limit
+            ),
+            in_flight.total,
+            limit,
+        )),
+        (_, Some(limit)) if session_id.is_some() && session_count >= limit => Some((
+            format!(
+                "Server busy: this session has {} tool calls in flight (limit {}); retry later",
+                session_count, limit
+            ),
+            session_count,
+            limit,
+        )),
+        _ => None,
+    };
+
+    if let Some((message, count, limit)) = rejection {
+        drop(in_flight);
+        let mut event = ServerEvent::new(EventKind::QuotaExceeded).message(message.clone());
+        if let Some(tool) = tool {
+            event = event.tool(tool);
+        }
+        state.emit_event(event);
+        return Err(JsonRpcError {
+            code: SERVER_BUSY,
+            message,
+            data: Some(serde_json::json!({ "inFlight": count, "limit": limit })),
+        });
+    }
+
+    in_flight.total += 1;
+    if let Some(id) = session_id {
+        *in_flight.sessions.entry(id.to_string()).or_default() += 1;
+    }
+    Ok(Permit {
+        admission: Arc::clone(&state.admission),
+        session_id: session_id.map(str::to_string),
+    })
+}
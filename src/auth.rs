@@ -0,0 +1,354 @@
+//! Auth module
+//!
+//! This module authenticates the clients of the HTTP transports with the API keys of
+//! the `[[api_key]]` config sections. Once a key is configured, every request to /mcp
+//! and the HTTP+SSE endpoints must present one, as `Authorization: Bearer <key>` or
+//! `X-API-Key: <key>`; other requests are answered 401 with a JSON-RPC error. A key
+//! may be limited to some tools, resources and methods: tools and resources it does
+//! not permit are left out of tools/list and resources/list and refused when called or
+//! read, and other methods, such as prompts/get or server/info, are refused. A session
+//! can only be used with the key that opened it. Webhooks have their own credentials,
+//! but may name a key whose limits then apply to their calls; the unix socket, which
+//! only the server's user can connect to, is not affected.
+
+use crate::config::ApiKeyConfig;
+use crate::mcp::McpServerState;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+
+/// JSON-RPC error code of requests without a valid API key
+pub const UNAUTHORIZED: i32 = -32001;
+
+/// JSON-RPC error code of requests the API key does not permit
+pub const FORBIDDEN: i32 = -32003;
+
+/// Header carrying an API key, as an alternative to a bearer token
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Methods every key may call; tools/call and the resources methods are then limited
+/// to the key's tools and resources
+const BASE_METHODS: &[&str] = &[
+    "initialize",
+    "ping",
+    "tools/list",
+    "tools/call",
+    "resources/list",
+    "resources/read",
+    "resources/subscribe",
+    "resources/unsubscribe",
+];
+
+/// An API key accepted by the server
+#[derive(Debug)]
+pub struct ApiKey {
+    /// Name of the key, for messages and logs
+    pub name: String,
+    key: String,
+    tools: Option<Vec<String>>,
+    resources: Option<Vec<String>>,
+    methods: Option<Vec<String>>,
+}
+
+impl ApiKey {
+    /// Whether the key may list and call `tool`
+    ///
+    /// Patterns are tool names or prefixes ending in `*`, such as `github.*` for the
+    /// tools of an upstream server.
+    pub fn allows(&self, tool: &str) -> bool {
+        self.permits(&self.tools, tool)
+    }
+
+    /// Whether the key may list, read and subscribe to the resource at `uri`
+    ///
+    /// Patterns are URIs or prefixes ending in `*`, such as `file:///tmp/reports/*`.
+    pub fn allows_resource(&self, uri: &str) -> bool {
+        self.permits(&self.resources, uri)
+    }
+
+    /// Whether the key may call `method`, beyond those every key may call
+    ///
+    /// Patterns are method names or prefixes ending in `*`, such as `prompts/*`.
+    pub fn allows_method(&self, method: &str) -> bool {
+        BASE_METHODS.contains(&method) || self.permits(&self.methods, method)
+    }
+
+    /// Whether the key is limited to some tools, resources or methods
+    ///
+    /// A limited key is only permitted what its lists name, so the lists it leaves
+    /// out permit nothing.
+    pub fn is_restricted(&self) -> bool {
+        self.tools.is_some() || self.resources.is_some() || self.methods.is_some()
+    }
+
+    fn permits(&self, patterns: &Option<Vec<String>>, name: &str) -> bool {
+        match patterns {
+            None => !self.is_restricted(),
+            Some(patterns) => patterns
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == pattern,
+                }),
+        }
+    }
+}
+
+/// The API keys accepted by the server; requests are not authenticated without any
+#[derive(Debug, Default)]
+pub struct ApiKeys(Vec<Arc<ApiKey>>);
+
+impl ApiKeys {
+    /// Read the keys of the config, from environment variables where configured
+    pub fn from_config(keys: &[ApiKeyConfig]) -> Result<Self, String> {
+        let mut resolved: Vec<Arc<ApiKey>> = Vec::new();
+        for config in keys {
+            let key = match (&config.key_env, &config.key) {
+                (Some(variable), _) => std::env::var(variable).map_err(|_| {
+                    format!(
+                        "API key '{}': variable '{}' is not set",
+                        config.name, variable
+                    )
+                })?,
+                (None, Some(key)) => key.clone(),
+                (None, None) => {
+                    return Err(format!("API key '{}' has no key or key_env", config.name));
+                }
+            };
+            if key.trim().is_empty() {
+                return Err(format!("API key '{}' is empty", config.name));
+            }
+            if resolved
+                .iter()
+                .any(|other| other.name == config.name || other.key == key)
+            {
+                return Err(format!(
+                    "API key '{}' is configured twice, or shares its key with another",
+                    config.name
+                ));
+            }
+            resolved.push(Arc::new(ApiKey {
+                name: config.name.clone(),
+                key,
+                tools: config.tools.clone(),
+                resources: config.resources.clone(),
+                methods: config.methods.clone(),
+            }));
+        }
+        Ok(Self(resolved))
+    }
+
+    /// Whether no keys are configured, so requests are not authenticated
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The key configured under `name`
+    pub fn named(&self, name: &str) -> Option<Arc<ApiKey>> {
+        self.0.iter().find(|key| key.name == name).cloned()
+    }
+
+    /// The key presented in a request's headers, if it is valid
+    pub fn authenticate(&self, headers: &HeaderMap) -> Option<Arc<ApiKey>> {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| {
+                headers
+                    .get(API_KEY_HEADER)
+                    .and_then(|value| value.to_str().ok())
+            })?
+            .trim();
+        // Every key is compared so the time taken does not tell which one is close
+        self.0.iter().fold(None, |found, key| {
+            if constant_time_eq(presented.as_bytes(), key.key.as_bytes()) {
+                Some(Arc::clone(key))
+            } else {
+                found
+            }
+        })
+    }
+}
+
+/// Middleware rejecting requests without a valid API key
+///
+/// The key is added to the request's extensions for the handlers.
+pub async fn require(
+    State(state): State<McpServerState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if state.api_keys.is_empty() {
+        return next.run(request).await;
+    }
+    match state.api_keys.authenticate(request.headers()) {
+        Some(key) => {
+            request.extensions_mut().insert(key);
+            next.run(request).await
+        }
+        None => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            axum::Json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {
+                    "code": UNAUTHORIZED,
+                    "message": "Unauthorized: missing or invalid API key",
+                },
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// The error of a tools/call the key does not permit
+pub fn tool_forbidden(key: &ApiKey, tool: &str) -> crate::mcp::JsonRpcError {
+    crate::mcp::JsonRpcError {
+        code: FORBIDDEN,
+        message: format!("Tool '{}' is not allowed for API key '{}'", tool, key.name),
+        data: None,
+    }
+}
+
+/// Check that the key permits a request: its method, and the resource it is about
+///
+/// tools/call is checked against the key's tools by the dispatcher, which knows the
+/// upstream tools of a gateway too.
+pub fn check_request(
+    key: &ApiKey,
+    method: &str,
+    params: Option<&serde_json::Value>,
+) -> Result<(), crate::mcp::JsonRpcError> {
+    if !key.allows_method(method) {
+        return Err(crate::mcp::JsonRpcError {
+            code: FORBIDDEN,
+            message: format!(
+                "Method '{}' is not allowed for API key '{}'",
+                method, key.name
+            ),
+            data: None,
+        });
+    }
+    if matches!(
+        method,
+        "resources/read" | "resources/subscribe" | "resources/unsubscribe"
+    ) && let Some(uri) = params
+        .and_then(|params| params.get("uri"))
+        .and_then(serde_json::Value::as_str)
+        && !key.allows_resource(uri)
+    {
+        return Err(crate::mcp::JsonRpcError {
+            code: FORBIDDEN,
+            message: format!(
+                "Resource '{}' is not allowed for API key '{}'",
+                uri, key.name
+            ),
+            data: None,
+        });
+    }
+    Ok(())
+}
+
+/// Check that a session is used with the key that opened it
+pub async fn check_session(
+    state: &McpServerState,
+    session_id: &str,
+    key: Option<&ApiKey>,
+) -> Result<(), crate::mcp::JsonRpcError> {
+    let sessions = state.sessions.read().await;
+    let owner = sessions
+        .get(session_id)
+        .and_then(|session| session.api_key.as_deref());
+    if owner == key.map(|key| key.name.as_str()) {
+        Ok(())
+    } else {
+        Err(crate::mcp::JsonRpcError {
+            code: FORBIDDEN,
+            message: "The session was opened with another API key".to_string(),
+            data: None,
+        })
+    }
+}
+
+/// Compare two byte strings without leaking where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(tools: Option<&[&str]>, resources: Option<&[&str]>, methods: Option<&[&str]>) -> ApiKey {
+        let list = |names: Option<&[&str]>| {
+            names.map(|names| names.iter().map(|name| name.to_string()).collect())
+        };
+        ApiKey {
+            name: "test".to_string(),
+            key: "secret".to_string(),
+            tools: list(tools),
+            resources: list(resources),
+            methods: list(methods),
+        }
+    }
+
+    #[test]
+    fn unrestricted_key_allows_everything() {
+        let key = key(None, None, None);
+        assert!(key.allows("file_read"));
+        assert!(key.allows_resource("file:///tmp/allowed_files/a.txt"));
+        assert!(key.allows_method("server/info"));
+    }
+
+    #[test]
+    fn tool_limited_key_gets_no_resources_or_methods() {
+        let key = key(Some(&["convert"]), None, None);
+        assert!(key.allows("convert"));
+        assert!(!key.allows("file_read"));
+        assert!(!key.allows_resource("file:///tmp/allowed_files/a.txt"));
+        assert!(!key.allows_resource("mcp://server/logs"));
+        assert!(!key.allows_method("server/info"));
+        assert!(!key.allows_method("prompts/get"));
+        assert!(key.allows_method("tools/call"));
+        assert!(key.allows_method("resources/list"));
+    }
+
+    #[test]
+    fn patterns_match_names_and_prefixes() {
+        let key = key(
+            None,
+            Some(&["file:///tmp/allowed_files/logs/*"]),
+            Some(&["prompts/*", "server/info"]),
+        );
+        assert!(!key.allows("convert"));
+        assert!(key.allows_resource("file:///tmp/allowed_files/logs/app.log"));
+        assert!(!key.allows_resource("file:///tmp/allowed_files/secret.txt"));
+        assert!(key.allows_method("prompts/list"));
+        assert!(key.allows_method("server/info"));
+        assert!(!key.allows_method("server/stats"));
+    }
+
+    #[test]
+    fn requests_are_checked_against_method_and_resource() {
+        let key = key(Some(&["convert"]), Some(&["transfer://*"]), None);
+        let read = |uri: &str| serde_json::json!({ "uri": uri });
+        assert!(check_request(&key, "resources/read", Some(&read("transfer://abc"))).is_ok());
+        let error =
+            check_request(&key, "resources/read", Some(&read("file:///etc/passwd"))).unwrap_err();
+        assert_eq!(error.code, FORBIDDEN);
+        assert!(
+            check_request(
+                &key,
+                "resources/subscribe",
+                Some(&read("mcp://server/logs"))
+            )
+            .is_err()
+        );
+        assert!(check_request(&key, "server/info", None).is_err());
+        assert!(check_request(&key, "tools/list", None).is_ok());
+    }
+}
@@ -1,14 +1,48 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand};
+#[cfg(feature = "tui")]
+use mcp::tui::{Event as TuiEvent, Role, Tui};
 use mcp::{
-    ChatSession, McpClient, Ollama, OllamaConfig,
-    ollama::{OllamaFunction, OllamaParameters, OllamaProperty, OllamaTool},
+    ChatSession, McpClient, McpError, McpTool, Ollama, OllamaConfig,
+    client_config::{self, ClientConfig},
+    conversation_store::ConversationStore,
+    deadline::Deadline,
+    elicitation::{ElicitRequest, ElicitResult},
+    exit::{self, ErrorFormat, FailureKind},
+    host_config::{HostConfig, HostTransport},
+    interaction_log::{Interaction, InteractionLog, Redactor},
+    mcp::ToolsCallResponse,
+    oauth::{BrowserAuthorization, FileTokenStore, HeadlessAuthorization, OAuth},
+    ollama::{ChatMessage, ChatResponse, ModelCapabilities, OllamaTool, OllamaToolCall},
+    pacing::Pacing,
+    parse_tool_calls,
+    profile::Profile,
+    render_tools_prompt,
+    replay::{self, Outcome},
+    resources::Resource,
+    sampling::{CreateMessageRequest, OllamaSampler},
+    tls::TlsConfig,
+    tool_prompt::{MissingArgument, missing_arguments, missing_arguments_prompt},
 };
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Times per message the model is asked to complete tool calls lacking arguments
+const MAX_ARGUMENT_FOLLOW_UPS: u32 = 2;
+
+/// Model used when neither --model nor the config names one
+const DEFAULT_MODEL: &str = "llama3";
 
 #[derive(Parser)]
 #[command(name = "mcp-client")]
 #[command(about = "An MCP client for Ollama API")]
 #[command(version = "0.1.0")]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Read prompt from a file
     #[arg(short = 'f', long = "prompt-file")]
     prompt_file: Option<String>,
@@ -17,47 +51,385 @@ struct Args {
     #[arg(short = 'c', long = "converse")]
     converse: bool,
 
-    /// Specify the model to use
-    #[arg(short = 'm', long = "model", default_value = "llama3")]
-    model: String,
+    /// Specify the model to use (default llama3, or the model set by `init`)
+    #[arg(short = 'm', long = "model")]
+    model: Option<String>,
+
+    /// MCP server address (e.g., http://localhost:3000/mcp); defaults to the server set
+    /// by `init` when no other server option is given
+    #[arg(short = 's', long = "mcp-server")]
+    mcp_server: Option<String>,
+
+    /// Use the HTTP+SSE transport; --mcp-server is then the server's SSE endpoint
+    /// (e.g., http://localhost:8080/sse)
+    #[arg(long = "sse", requires = "mcp_server")]
+    sse: bool,
+
+    /// Header sent with every request to --mcp-server, as "Name: value"; repeatable
+    #[arg(long = "header", requires = "mcp_server", conflicts_with = "sse")]
+    header: Vec<String>,
+
+    /// Environment variable holding a bearer token sent to --mcp-server
+    #[arg(
+        long = "bearer-token-env",
+        requires = "mcp_server",
+        conflicts_with = "sse"
+    )]
+    bearer_token_env: Option<String>,
+
+    /// Authorize with the OAuth authorization server of --mcp-server when it asks for
+    /// it, approving in the browser; tokens are kept for later sessions
+    #[arg(long = "oauth", requires = "mcp_server", conflicts_with = "sse")]
+    oauth: bool,
+
+    /// Print the authorization URL and read back the address the browser was
+    /// redirected to, instead of opening a browser on this machine
+    #[arg(long = "oauth-headless", requires = "oauth")]
+    oauth_headless: bool,
+
+    /// Client id registered with the authorization server; without one the client
+    /// registers itself
+    #[arg(long = "oauth-client-id", requires = "oauth")]
+    oauth_client_id: Option<String>,
+
+    /// Scope to ask for (repeatable); defaults to the scopes the server announces
+    #[arg(long = "oauth-scope", requires = "oauth")]
+    oauth_scope: Vec<String>,
+
+    /// PEM file of root certificates trusted for --mcp-server besides the system's
+    #[arg(long = "tls-ca-bundle", requires = "mcp_server")]
+    tls_ca_bundle: Option<String>,
+
+    /// PEM file of the client certificate sent to --mcp-server, for mutual TLS
+    #[arg(long = "tls-client-cert", requires_all = ["mcp_server", "tls_client_key"])]
+    tls_client_cert: Option<String>,
+
+    /// PEM file of the private key (PKCS#8) of --tls-client-cert
+    #[arg(long = "tls-client-key", requires = "tls_client_cert")]
+    tls_client_key: Option<String>,
+
+    /// Accept any certificate of --mcp-server, even self-signed, expired or for
+    /// another host; only for lab environments
+    #[arg(long = "danger-accept-invalid-certs", requires = "mcp_server")]
+    danger_accept_invalid_certs: bool,
+
+    /// Launch an MCP server as a child process and talk to it over stdio
+    /// (e.g., --mcp-command npx -y @modelcontextprotocol/server-everything)
+    #[arg(long = "mcp-command", num_args = 1.., allow_hyphen_values = true, conflicts_with = "mcp_server")]
+    mcp_command: Vec<String>,
+
+    /// Connect to an MCP server listening on a unix domain socket
+    #[arg(long = "mcp-socket", conflicts_with_all = ["mcp_server", "mcp_command"])]
+    mcp_socket: Option<String>,
+
+    /// Connect to a server of an MCP host configuration, such as Claude Desktop's
+    /// claude_desktop_config.json or a project's .mcp.json
+    #[arg(long = "host-config", conflicts_with_all = ["mcp_server", "mcp_command", "mcp_socket"])]
+    host_config: Option<String>,
+
+    /// Name of the --host-config server to connect to, when it lists several
+    #[arg(long = "host-server", requires = "host_config")]
+    host_server: Option<String>,
+
+    /// Describe the tools in the system prompt instead of using native tool calling
+    ///
+    /// For models with weak or no native tool support.
+    #[arg(long = "prompt-tools")]
+    prompt_tools: bool,
+
+    /// Language the model answers in (e.g., German or pt-BR)
+    #[arg(long = "language")]
+    language: Option<String>,
+
+    /// Tone of the model's answers (e.g., formal, friendly or concise)
+    #[arg(long = "tone")]
+    tone: Option<String>,
+
+    /// Use the language and tone saved in a profile; --language and --tone override it
+    #[arg(long = "profile")]
+    profile: Option<String>,
+
+    /// Save the language and tone in effect as a profile for later sessions
+    #[arg(long = "save-profile")]
+    save_profile: Option<String>,
 
-    /// MCP server address (e.g., http://localhost:3000/mcp)
-    #[arg(short = 's', long = "mcp-server", required = true)]
-    mcp_server: String,
+    /// Most requests sent to the model per minute; further requests wait
+    #[arg(long = "max-model-calls-per-minute", value_parser = clap::value_parser!(u32).range(1..))]
+    max_model_calls_per_minute: Option<u32>,
+
+    /// Most tool calls run for one message; further calls are refused
+    #[arg(long = "max-tool-calls-per-turn")]
+    max_tool_calls_per_turn: Option<u32>,
+
+    /// Seconds to answer each message in, including its model requests and tool calls
+    #[arg(long = "deadline", value_parser = clap::value_parser!(u64).range(1..))]
+    deadline: Option<u64>,
+
+    /// Most tokens of conversation sent to the model; older messages are dropped
+    #[arg(long = "context-tokens", value_parser = clap::value_parser!(u64).range(1..))]
+    context_tokens: Option<u64>,
+
+    /// Append every model request and tool call to this JSON lines file
+    #[arg(long = "interaction-log")]
+    interaction_log: Option<String>,
+
+    /// Also redact matches of this regex from the interaction log (repeatable)
+    #[arg(long = "log-redact", requires = "interaction_log")]
+    log_redact: Vec<String>,
+
+    /// Do not save the conversation to the history
+    #[arg(long = "no-history")]
+    no_history: bool,
+
+    /// Show the conversation in a full-screen terminal interface, with panes for the
+    /// answer being generated, the calls awaiting approval and the server's tools
+    #[cfg(feature = "tui")]
+    #[arg(long = "tui", requires = "converse")]
+    tui: bool,
+
+    /// Whether tools of the server may run completions with the model: ask before
+    /// each one, allow them all, or deny them all
+    #[arg(long = "sampling", value_enum, default_value_t = SamplingPolicy::Ask)]
+    sampling: SamplingPolicy,
+
+    /// Do not let tools of the server ask for input during their calls
+    #[arg(long = "no-elicitation")]
+    no_elicitation: bool,
+
+    /// Print the error ending a failed run as text or as one JSON object on stderr;
+    /// the exit code tells the cause either way
+    #[arg(long = "error-format", value_enum, default_value_t = ErrorFormat::Text, global = true)]
+    error_format: ErrorFormat,
+}
+
+/// How sampling requests of the server are answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum SamplingPolicy {
+    Ask,
+    Allow,
+    Deny,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Set up the client: find Ollama and its models and local MCP servers, choose a
+    /// profile, and save them as the defaults of later runs
+    Init,
+    /// Browse the conversations saved by earlier sessions
+    #[command(subcommand)]
+    History(HistoryCommand),
+    /// Print the completion script of a shell (bash, zsh, fish, elvish or powershell)
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the manual page, or write the pages of every subcommand to a directory
+    Manpage {
+        /// Directory to write mcp-client.1 and the pages of the subcommands to, e.g.
+        /// /usr/local/share/man/man1
+        #[arg(long = "out-dir")]
+        out_dir: Option<std::path::PathBuf>,
+    },
+    /// Re-run the tool calls of a session recorded with --interaction-log against the
+    /// MCP server, showing how their results differ from the recorded ones
+    Replay {
+        /// Interaction log recording the session
+        transcript: String,
+
+        /// Id, or start of the id, of the conversation to replay; defaults to the last
+        /// one of the log
+        #[arg(long = "conversation")]
+        conversation: Option<String>,
+
+        /// Only replay tool calls, without sending the recorded model requests again
+        #[arg(long = "skip-model")]
+        skip_model: bool,
+
+        /// Also redact matches of this regex from the results (repeatable), as the
+        /// session did with --log-redact
+        #[arg(long = "redact")]
+        redact: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// List the most recent conversations
+    List {
+        /// Most conversations to list
+        #[arg(short = 'n', long = "limit", default_value_t = 20)]
+        limit: usize,
+    },
+    /// Find the messages containing all the given words
+    Search {
+        #[arg(required = true)]
+        words: Vec<String>,
+
+        /// Most messages to show
+        #[arg(short = 'n', long = "limit", default_value_t = 20)]
+        limit: usize,
+    },
+    /// Print a conversation, given its id or the start of it
+    Open { id: String },
 }
 
 fn main() {
     // Parse command line arguments
-    let args = Args::parse();
+    let mut args: Args = exit::parse_args();
+    exit::set_format(args.error_format);
+    if let Err(e) = ctrlc::set_handler(|| {
+        exit::fail(FailureKind::Aborted, "Interrupted");
+    }) {
+        eprintln!("Warning: could not handle Ctrl-C: {}", e);
+    }
+
+    match &args.command {
+        Some(Command::Completions { shell }) => {
+            if let Err(e) = mcp::manual::print_completions(Args::command(), *shell) {
+                exit::fail(FailureKind::Failed, e);
+            }
+            return;
+        }
+        Some(Command::Manpage { out_dir }) => {
+            let written = match out_dir {
+                Some(dir) => mcp::manual::write_manpages(Args::command(), dir).map(|paths| {
+                    for path in paths {
+                        println!("Wrote {}", path.display());
+                    }
+                }),
+                None => mcp::manual::print_manpage(Args::command()),
+            };
+            if let Err(e) = written {
+                exit::fail(FailureKind::Failed, e);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some(Command::Init) = &args.command {
+        if let Err(e) = run_init() {
+            exit::fail(FailureKind::Failed, e);
+        }
+        return;
+    }
+
+    // Defaults saved by `init` apply to what the command line leaves out
+    let config = ClientConfig::load().unwrap_or_else(|e| exit::fail(FailureKind::Config, e));
+    if args.mcp_server.is_none()
+        && args.mcp_command.is_empty()
+        && args.mcp_socket.is_none()
+        && args.host_config.is_none()
+    {
+        args.mcp_server = config.mcp_server;
+    }
+    if args.profile.is_none() {
+        args.profile = config.profile;
+    }
+    let model = args
+        .model
+        .clone()
+        .or(config.model)
+        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+
+    if let Some(Command::History(command)) = &args.command {
+        if let Err(e) = run_history(command) {
+            exit::fail(FailureKind::Failed, e);
+        }
+        return;
+    }
+    if let Some(Command::Replay {
+        transcript,
+        conversation,
+        skip_model,
+        redact,
+    }) = &args.command
+    {
+        match run_replay(
+            &args,
+            transcript,
+            conversation.as_deref(),
+            *skip_model,
+            redact,
+        ) {
+            Ok(true) => return,
+            Ok(false) => exit::fail(
+                FailureKind::Failed,
+                "The replayed results differ from the recording",
+            ),
+            Err(e) => exit::fail(FailureKind::of_error(e.as_ref(), FailureKind::Failed), e),
+        }
+    }
 
     // Validate that either converse or prompt-file is provided
     if !args.converse && args.prompt_file.is_none() || args.converse && args.prompt_file.is_some() {
-        eprintln!(
-            "Error: You must provide either --converse (-c) or --prompt-file (-f), but not both"
+        exit::fail(
+            FailureKind::Usage,
+            "You must provide either --converse (-c) or --prompt-file (-f), but not both\nUse --help for more information",
         );
-        eprintln!("Use --help for more information");
-        std::process::exit(1);
     }
 
+    let mut profile = match &args.profile {
+        Some(name) => Profile::load(name).unwrap_or_else(|e| exit::fail(FailureKind::Config, e)),
+        None => Profile::default(),
+    };
+    profile.merge(Profile {
+        language: args.language.clone(),
+        tone: args.tone.clone(),
+    });
+    if let Some(name) = &args.save_profile {
+        match profile.save(name) {
+            Ok(path) => println!("Saved profile '{}' to {}", name, path.display()),
+            Err(e) => eprintln!("Failed to save profile '{}': {}", name, e),
+        }
+    }
+    let style_prompt = profile.system_prompt().unwrap_or_else(|e| {
+        exit::fail(
+            FailureKind::Config,
+            format!("Could not render the language and tone prompt: {}", e),
+        )
+    });
+
     if !args.converse {
         let prompt_file = args.prompt_file.unwrap(); // Safe due to validation above
-        let msg = std::fs::read_to_string(&prompt_file).unwrap_or_else(|_| {
-            eprintln!("Error reading prompt file: {}", prompt_file);
-            std::process::exit(1);
+        let msg = std::fs::read_to_string(&prompt_file).unwrap_or_else(|e| {
+            exit::fail(
+                FailureKind::Usage,
+                format!("Could not read prompt file {}: {}", prompt_file, e),
+            )
         });
         println!("Using message from file: {}", msg.trim());
-        let response = Ollama::default(args.model.clone())
-            .chat(&msg, &args.model)
-            .unwrap();
+        let response = Ollama::default(model.clone())
+            .chat(&msg, &model)
+            .unwrap_or_else(|e| exit::fail(FailureKind::Model, e));
         println!("Response: {}", response.message.content);
         return;
     }
 
     // Initialize MCP client and get tool list
-    println!("Connecting to MCP server: {}", args.mcp_server);
-    let mcp_client = McpClient::new(&args.mcp_server);
+    let mcp_client = connect(&args);
+    let console = Console::default();
+    answer_server_requests(&console, &mcp_client, &args, &model);
 
-    let tools = match mcp_client.list_tools() {
+    let server = match mcp_client.initialize() {
+        Ok(server) => {
+            println!(
+                "Connected to {} {} (protocol {})",
+                server.server_info.name, server.server_info.version, server.protocol_version
+            );
+            server
+        }
+        Err(e) => exit::fail(
+            session_failure(&e),
+            format!(
+                "Failed to initialize MCP session: {}\nMake sure the MCP server is running at: {}",
+                e,
+                mcp_client.endpoint()
+            ),
+        ),
+    };
+
+    let mut tools = match mcp_client.list_tools() {
         Ok(tools) => {
             println!(
                 "Successfully retrieved {} tools from MCP server",
@@ -65,22 +437,16 @@ fn main() {
             );
             tools
         }
-        Err(e) => {
-            eprintln!("Failed to get tools from MCP server: {}", e);
-            eprintln!(
-                "Make sure the MCP server is running at: {}",
-                args.mcp_server
-            );
-            std::process::exit(1);
-        }
+        Err(e) => exit::fail(
+            session_failure(&e),
+            format!(
+                "Failed to get tools from MCP server: {}\nMake sure the MCP server is running at: {}",
+                e,
+                mcp_client.endpoint()
+            ),
+        ),
     };
 
-    // Create Ollama configuration
-    let config = OllamaConfig::new(&args.model)
-        .temperature(0.7)
-        .max_tokens(100);
-    println!("Ollama config created: {:?}", config);
-
     if !tools.is_empty() {
         println!("Available tools that could be used by the LLM:");
         for tool in &tools {
@@ -91,113 +457,1318 @@ fn main() {
             );
         }
     }
-    let ollama_tools = vec![OllamaTool::function(OllamaFunction::new(
-        "file_read",
-        "Read the contents of a file from the filesystem. The path must be within /tmp/allowed_files/",
-        OllamaParameters::new()
-            .add_property("path", OllamaProperty::string("The file path to read")),
-    ))];
 
-    let mut session = ChatSession::New(args.model, ollama_tools);
-    println!("Starting conversational mode. Type 'quit' or 'exit' to stop.");
-    println!("Type your message and press Enter:");
+    // Tell the model where files live so it does not guess paths outside the allowed roots
+    let roots = mcp_client.list_roots().unwrap_or_else(|e| {
+        eprintln!("Could not get allowed roots from MCP server: {}", e);
+        Vec::new()
+    });
+
+    if server.capabilities.resources.is_some() {
+        match mcp_client.list_resources() {
+            Ok(resources) => {
+                println!("Available resources (read one with /read <uri>):");
+                print_resources(&console, &resources);
+            }
+            Err(e) => eprintln!("Could not get resources from MCP server: {}", e),
+        }
+    }
+
+    let mut session = ChatSession::new(&model, Vec::new()).with_pacing(&Pacing {
+        max_model_calls_per_minute: args.max_model_calls_per_minute,
+        max_tool_calls_per_turn: args.max_tool_calls_per_turn,
+    });
 
+    // Adapt to what the model supports instead of letting it ignore the tools silently
+    let mut prompt_tools = args.prompt_tools;
+    let mut context_tokens = args.context_tokens;
+    match session.detect_capabilities() {
+        Ok(capabilities) => {
+            println!("Model {}: {}", model, describe_capabilities(&capabilities));
+            if capabilities.tools == Some(false) && !prompt_tools && !tools.is_empty() {
+                println!(
+                    "Warning: {} does not support native tool calling; describing the tools in the system prompt instead (--prompt-tools)",
+                    model
+                );
+                prompt_tools = true;
+            }
+            if let (Some(requested), Some(supported)) =
+                (context_tokens, capabilities.context_length)
+                && requested > supported
+            {
+                println!(
+                    "Warning: {} supports {} tokens of context; using that instead of --context-tokens {}",
+                    model, supported, requested
+                );
+                context_tokens = Some(supported);
+            }
+        }
+        Err(e) => eprintln!("Could not detect what the model supports: {}", e),
+    }
+
+    // In prompt-tools mode the tools are described in the system prompt, not sent natively
+    if !prompt_tools {
+        session.set_tools(tools.iter().map(OllamaTool::from_mcp_tool).collect());
+    }
+    let interaction_log = args.interaction_log.as_ref().map(|path| {
+        let mut redactor = Redactor::default();
+        for pattern in &args.log_redact {
+            if let Err(e) = redactor.add_pattern(pattern) {
+                exit::fail(
+                    FailureKind::Usage,
+                    format!("invalid --log-redact pattern '{}': {}", pattern, e),
+                );
+            }
+        }
+        let log = InteractionLog::open(path).unwrap_or_else(|e| exit::fail(FailureKind::Failed, e));
+        println!(
+            "Recording the conversation {} in {}",
+            log.conversation(),
+            path
+        );
+        log.with_redactor(redactor)
+    });
+    if let Some(log) = &interaction_log {
+        session = session.with_interaction_log(log.clone());
+    }
+    if let Some(tokens) = context_tokens {
+        // Ollama would otherwise truncate the conversation to its default window
+        session = session.with_context_budget(tokens as usize);
+        session.set_context_length(Some(tokens));
+    }
+    if !args.no_history {
+        match ConversationStore::open_default().and_then(|store| {
+            let id = store.start(&model, Some(mcp_client.endpoint()))?;
+            Ok((store, id))
+        }) {
+            Ok((store, id)) => {
+                println!(
+                    "Saving this conversation as {} (see mcp-client history)",
+                    id
+                );
+                session = session.with_conversation_store(store, id);
+            }
+            Err(e) => eprintln!("Could not open the conversation history: {}", e),
+        }
+    }
+    if let Some(style_prompt) = style_prompt {
+        session.add_system_message(style_prompt);
+    }
+    if let Some(instructions) = server.instructions {
+        session.add_system_message(instructions);
+    }
+    if prompt_tools {
+        println!("Describing tools in the system prompt (--prompt-tools)");
+        session.add_system_message(render_tools_prompt(&tools, &roots));
+    } else if !roots.is_empty() {
+        let paths: Vec<_> = roots.iter().map(|root| root.path()).collect();
+        println!("Allowed roots: {}", paths.join(", "));
+        session.add_system_message(format!(
+            "File tools can only access files within these directories: {}. Always pass absolute paths inside them.",
+            paths.join(", ")
+        ));
+    }
+    #[cfg(feature = "tui")]
+    if args.tui {
+        let status = mcp::tui::Status {
+            server: format!("{} {}", server.server_info.name, server.server_info.version),
+            protocol: server.protocol_version.clone(),
+            endpoint: mcp_client.endpoint().to_string(),
+            model: model.clone(),
+            tools: tools.iter().map(|tool| tool.name.clone()).collect(),
+        };
+        if let Err(e) = console.start_tui(status) {
+            exit::fail(
+                FailureKind::Failed,
+                format!("Could not start the terminal interface: {}", e),
+            );
+        }
+    }
+    console.say("Starting conversational mode. Type 'quit' or 'exit' to stop.");
+    console.say(
+        "Type /resources to list the server's resources and /read <uri> to add one to the conversation.",
+    );
+    console.say("Type /subscribe <uri> or /unsubscribe <uri> to follow a resource's changes.");
+    console.say("Type your message and press Enter:");
+
+    // Tools the server announced while the user was typing, applied before the next message
+    let changed_tools: Arc<Mutex<Option<Vec<McpTool>>>> = Arc::default();
+    let lists_changes = server
+        .capabilities
+        .tools
+        .as_ref()
+        .and_then(|tools| tools.get("listChanged"))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+    if lists_changes {
+        let changed_tools = Arc::clone(&changed_tools);
+        // stdio and unix socket servers cannot notify the client, which keeps its tools
+        let _ = mcp_client.on_tools_changed(move |tools| {
+            if let Ok(mut changed) = changed_tools.lock() {
+                *changed = Some(tools);
+            }
+        });
+    }
+
+    // Long-running tools report progress while the model waits for their result
+    let progress_console = console.clone();
+    let following_progress = mcp_client
+        .on_progress(move |progress| {
+            let done = match progress.total {
+                Some(total) if total > 0.0 => format!("{:.0}%", progress.progress / total * 100.0),
+                _ => format!("{}", progress.progress),
+            };
+            match &progress.message {
+                Some(message) => progress_console.progress(format!("{} ({})", message, done)),
+                None => progress_console.progress(done),
+            }
+        })
+        .is_ok();
+
+    let mut following_updates = false;
     loop {
-        print!("> ");
-        use std::io::{self, Write};
-        io::stdout().flush().unwrap();
+        if let Some(new_tools) = changed_tools
+            .lock()
+            .ok()
+            .and_then(|mut changed| changed.take())
+        {
+            console.say(format!(
+                "The server's tools changed, {} tools available",
+                new_tools.len()
+            ));
+            console.tools(&new_tools);
+            if prompt_tools {
+                session.add_system_message(render_tools_prompt(&new_tools, &roots));
+            } else {
+                session.set_tools(new_tools.iter().map(OllamaTool::from_mcp_tool).collect());
+            }
+            tools = new_tools;
+        }
+        for notification in mcp_client.take_notifications() {
+            // Resource updates and progress were reported as they arrived, tool changes above
+            if (following_updates && notification.method == "notifications/resources/updated")
+                || (lists_changes && notification.method == "notifications/tools/list_changed")
+                || (following_progress && notification.method == "notifications/progress")
+            {
+                continue;
+            }
+            console.say(format!("Server notification: {}", notification.method));
+        }
 
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
+        match console.read_message() {
+            // End of input (Ctrl-D) ends the conversation like "quit"
+            Ok(None) => break,
+            Ok(Some(input)) => {
                 let message = input.trim();
                 if message.is_empty() {
                     continue;
                 }
                 if message.eq_ignore_ascii_case("quit") || message.eq_ignore_ascii_case("exit") {
-                    println!("Goodbye!");
+                    console.say("Goodbye!");
                     break;
                 }
+                if run_command(
+                    &console,
+                    &mcp_client,
+                    &mut session,
+                    message,
+                    &mut following_updates,
+                ) {
+                    continue;
+                }
 
-                match session.send(message) {
-                    Ok(response) => {
-                        println!("Assistant: {}", response.message.content);
-
-                        // Handle tool calls if present
-                        if let Some(tool_calls) = response.message.tool_calls {
-                            for tool_call in tool_calls {
-                                println!("Tool call: {}", tool_call.function.name);
-                                println!("Tool call arguments: {}", tool_call.function.arguments);
-
-                                // Execute the tool on the MCP server
-                                match mcp_client.call_tool(
-                                    &tool_call.function.name,
-                                    Some(tool_call.function.arguments),
-                                ) {
-                                    Ok(tool_result) => {
-                                        println!("Tool result: {}", tool_result);
-
-                                        // Send the tool result back to the conversation
-                                        let tool_result_message = format!(
-                                            "Tool '{}' executed successfully. Result: {}",
-                                            tool_call.function.name, tool_result
-                                        );
-
-                                        match session.send(&tool_result_message) {
-                                            Ok(follow_up_response) => {
-                                                println!(
-                                                    "Assistant: {}",
-                                                    follow_up_response.message.content
-                                                );
-                                            }
-                                            Err(e) => {
-                                                println!(
-                                                    "Error sending tool result to assistant: {}",
-                                                    e
-                                                );
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        println!(
-                                            "Error executing tool '{}': {}",
-                                            tool_call.function.name, e
-                                        );
-
-                                        // Send the error back to the conversation
-                                        let error_message = format!(
-                                            "Tool '{}' execution failed: {}",
-                                            tool_call.function.name, e
-                                        );
-
-                                        match session.send(&error_message) {
-                                            Ok(error_response) => {
-                                                println!(
-                                                    "Assistant: {}",
-                                                    error_response.message.content
-                                                );
-                                            }
-                                            Err(e) => {
-                                                println!(
-                                                    "Error sending tool error to assistant: {}",
-                                                    e
-                                                );
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        println!("Error making request to Ollama: {}", e);
-                    }
+                session.begin_turn();
+                let deadline = args
+                    .deadline
+                    .map(|secs| Deadline::after(Duration::from_secs(secs)));
+                session.set_deadline(deadline);
+                Turn {
+                    console: &console,
+                    mcp_client: &mcp_client,
+                    session: &mut session,
+                    tools: &tools,
+                    interaction_log: interaction_log.as_ref(),
+                    deadline,
+                }
+                .run(message);
+            }
+            Err(e) => {
+                console.error(format!("Error reading input: {}", e));
+                break;
+            }
+        }
+    }
+    console.close();
+}
+
+/// Connect to the MCP server given on the command line, exiting on failure
+fn connect(args: &Args) -> McpClient {
+    match (&args.mcp_server, args.mcp_command.split_first()) {
+        _ if args.host_config.is_some() => {
+            let path = args.host_config.as_deref().unwrap_or_default();
+            connect_host_server(path, args.host_server.as_deref()).unwrap_or_else(|e| {
+                exit::fail(FailureKind::of_error(e.as_ref(), FailureKind::Config), e)
+            })
+        }
+        (Some(url), _) if args.sse => {
+            println!("Connecting to MCP server over SSE: {}", url);
+            McpClient::connect_sse_with_tls(url, &tls_config(args)).unwrap_or_else(|e| {
+                exit::fail(
+                    FailureKind::of(&e),
+                    format!("Failed to connect to MCP server: {}", e),
+                )
+            })
+        }
+        (Some(url), _) => {
+            println!("Connecting to MCP server: {}", url);
+            McpClient::new_with_tls(url, tls_config(args))
+                .map_err(Into::into)
+                .and_then(|client| {
+                    with_headers(client, &args.header, args.bearer_token_env.as_deref())
+                })
+                .and_then(|client| with_oauth(client, args))
+                .unwrap_or_else(|e| {
+                    exit::fail(FailureKind::of_error(e.as_ref(), FailureKind::Usage), e)
+                })
+        }
+        (None, Some((command, command_args))) => {
+            println!("Starting MCP server: {}", args.mcp_command.join(" "));
+            McpClient::spawn(command, command_args)
+                .unwrap_or_else(|e| exit::fail(FailureKind::Connection, e))
+        }
+        (None, None) => match &args.mcp_socket {
+            #[cfg(unix)]
+            Some(path) => {
+                println!("Connecting to MCP server on unix socket: {}", path);
+                McpClient::connect_unix(path)
+                    .unwrap_or_else(|e| exit::fail(FailureKind::Connection, e))
+            }
+            #[cfg(not(unix))]
+            Some(_) => exit::fail(
+                FailureKind::Usage,
+                "unix sockets are not supported on this platform",
+            ),
+            None => exit::fail(
+                FailureKind::Usage,
+                "no MCP server given; use --mcp-server, --mcp-command, --mcp-socket or --host-config, or run `mcp-client init`",
+            ),
+        },
+    }
+}
+
+/// Cause of a failure to start the session: anything but refused credentials means
+/// the server could not be used
+fn session_failure(error: &McpError) -> FailureKind {
+    match FailureKind::of(error) {
+        FailureKind::Authorization => FailureKind::Authorization,
+        _ => FailureKind::Connection,
+    }
+}
+
+/// Connect to a server of a host configuration file
+fn connect_host_server(
+    path: &str,
+    name: Option<&str>,
+) -> Result<McpClient, Box<dyn std::error::Error>> {
+    let server = HostConfig::load(path)?.server(name)?.clone();
+    let transport = server.transport()?;
+    println!("Connecting to MCP server from {}: {}", path, transport);
+    let client = match transport {
+        HostTransport::Stdio { command, args, env } => {
+            McpClient::spawn_with_env(&command, &args, env)?
+        }
+        HostTransport::Http { url } => {
+            let mut client = McpClient::new(url);
+            for (name, value) in &server.headers {
+                client = client.with_header(name, value)?;
+            }
+            return Ok(client);
+        }
+        HostTransport::Sse { url } => McpClient::connect_sse(url)?,
+    };
+    if !server.headers.is_empty() {
+        eprintln!("Warning: the server's headers are only sent over Streamable HTTP");
+    }
+    Ok(client)
+}
+
+/// Add the --header and --bearer-token-env headers to an HTTP client
+fn with_headers(
+    mut client: McpClient,
+    headers: &[String],
+    bearer_token_env: Option<&str>,
+) -> Result<McpClient, Box<dyn std::error::Error>> {
+    for header in headers {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid header '{}', expected \"Name: value\"", header))?;
+        client = client.with_header(name.trim(), value.trim())?;
+    }
+    if let Some(var) = bearer_token_env {
+        let token =
+            std::env::var(var).map_err(|_| format!("Environment variable {} is not set", var))?;
+        client = client.with_bearer_token(&token)?;
+    }
+    Ok(client)
+}
+
+/// The TLS options of --mcp-server
+fn tls_config(args: &Args) -> TlsConfig {
+    if args.danger_accept_invalid_certs {
+        eprintln!(
+            "Warning: the server's certificate is not verified, anyone on the network path can read and change the traffic"
+        );
+    }
+    TlsConfig {
+        ca_bundle: args.tls_ca_bundle.as_ref().map(Into::into),
+        client_cert: args.tls_client_cert.as_ref().map(Into::into),
+        client_key: args.tls_client_key.as_ref().map(Into::into),
+        danger_accept_invalid_certs: args.danger_accept_invalid_certs,
+    }
+}
+
+/// Authorize an HTTP client with OAuth if --oauth is given
+fn with_oauth(client: McpClient, args: &Args) -> Result<McpClient, Box<dyn std::error::Error>> {
+    if !args.oauth {
+        return Ok(client);
+    }
+    let mut oauth = if args.oauth_headless {
+        OAuth::new(HeadlessAuthorization::new())
+    } else {
+        OAuth::new(BrowserAuthorization::new()?)
+    }
+    .with_token_store(FileTokenStore::open_default()?)
+    .with_scopes(args.oauth_scope.iter().cloned());
+    if let Some(client_id) = &args.oauth_client_id {
+        oauth = oauth.with_client_id(client_id);
+    }
+    Ok(client.with_oauth(oauth)?)
+}
+
+/// Run a history subcommand against the conversation store
+fn run_history(command: &HistoryCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let store = ConversationStore::open_default()?;
+    match command {
+        HistoryCommand::List { limit } => {
+            let conversations = store.list(*limit)?;
+            if conversations.is_empty() {
+                println!("No saved conversations");
+            }
+            for conversation in conversations {
+                println!(
+                    "{}  {}  {:<20}  {:>3} messages  {}",
+                    &conversation.id[..8],
+                    conversation.updated_at.format("%Y-%m-%d %H:%M"),
+                    conversation.model,
+                    conversation.message_count,
+                    conversation.title.as_deref().unwrap_or("(no messages)")
+                );
+            }
+        }
+        HistoryCommand::Search { words, limit } => {
+            let hits = store.search(&words.join(" "), *limit)?;
+            if hits.is_empty() {
+                println!("No messages found");
+            }
+            for hit in hits {
+                println!(
+                    "{}  {}  {}",
+                    &hit.conversation_id[..8],
+                    hit.created_at.format("%Y-%m-%d %H:%M"),
+                    hit.title.as_deref().unwrap_or_default()
+                );
+                println!("    {}: {}", hit.role, hit.snippet.replace('\n', " "));
+            }
+        }
+        HistoryCommand::Open { id } => {
+            let conversation = store.find(id)?;
+            println!("Conversation {}", conversation.id);
+            println!(
+                "Model {}, started {}",
+                conversation.model,
+                conversation.started_at.format("%Y-%m-%d %H:%M")
+            );
+            if let Some(server) = &conversation.server {
+                println!("MCP server: {}", server);
+            }
+            for stored in store.messages(&conversation.id)? {
+                let message = stored.message;
+                println!();
+                println!(
+                    "[{}] {}:",
+                    stored.created_at.format("%H:%M:%S"),
+                    message.role
+                );
+                println!("{}", message.content);
+                for call in message.tool_calls.unwrap_or_default() {
+                    println!(
+                        "Tool call: {} {}",
+                        call.function.name, call.function.arguments
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ask a question, returning `default` when the answer is empty or input ended
+fn ask(question: &str, default: &str) -> String {
+    use std::io::{self, Write};
+
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    io::stdout().flush().unwrap();
+
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(_) if !answer.trim().is_empty() => answer.trim().to_string(),
+        _ => default.to_string(),
+    }
+}
+
+/// Ask for one of the numbered `options`, by number or typing another value
+fn pick(question: &str, options: &[String], default: &str) -> String {
+    let answer = ask(question, default);
+    match answer.parse::<usize>() {
+        Ok(number) if (1..=options.len()).contains(&number) => options[number - 1].clone(),
+        _ => answer,
+    }
+}
+
+/// Set up the client interactively, saving the choices as the defaults of later runs
+///
+/// The defaults already saved are offered again, so running it twice changes only
+/// what the user changes.
+fn run_init() -> Result<(), Box<dyn std::error::Error>> {
+    let saved = ClientConfig::load()?;
+    println!("Press Enter to accept the value in brackets.");
+
+    println!();
+    println!("Looking for Ollama at http://localhost:11434...");
+    let models = match Ollama::default(DEFAULT_MODEL)
+        .with_timeout(Duration::from_secs(3))
+        .list_models()
+    {
+        Ok(models) if models.is_empty() => {
+            println!(
+                "Ollama has no models yet; pull one with `ollama pull {}`",
+                DEFAULT_MODEL
+            );
+            models
+        }
+        Ok(models) => {
+            println!("Models available on Ollama:");
+            for (index, model) in models.iter().enumerate() {
+                println!("  {}) {}", index + 1, model);
+            }
+            models
+        }
+        Err(e) => {
+            println!("{}", e);
+            println!("Install it from https://ollama.com and start it with `ollama serve`.");
+            Vec::new()
+        }
+    };
+    let default_model = saved
+        .model
+        .clone()
+        .or_else(|| models.first().cloned())
+        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let model = pick("Model", &models, &default_model);
+
+    println!();
+    println!("Looking for MCP servers on local ports...");
+    let servers = client_config::detect_servers(client_config::COMMON_PORTS);
+    if servers.is_empty() {
+        println!(
+            "No MCP server answers at /mcp on ports {}; start one with `mcp-server` or enter its URL.",
+            client_config::COMMON_PORTS
+                .iter()
+                .map(u16::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    for (index, server) in servers.iter().enumerate() {
+        println!(
+            "  {}) {} ({} {})",
+            index + 1,
+            server.url,
+            server.name,
+            server.version
+        );
+    }
+    let urls: Vec<String> = servers.iter().map(|server| server.url.clone()).collect();
+    let default_server = saved
+        .mcp_server
+        .clone()
+        .or_else(|| urls.first().cloned())
+        .unwrap_or_default();
+    let mcp_server = pick("MCP server URL (empty for none)", &urls, &default_server);
+
+    println!();
+    println!("A profile sets the language and tone of the model's answers.");
+    let profiles = Profile::names()?;
+    for (index, name) in profiles.iter().enumerate() {
+        println!("  {}) {}", index + 1, name);
+    }
+    let default_profile = saved.profile.clone().unwrap_or_default();
+    let profile = pick(
+        "Profile (a new name creates it, empty for none)",
+        &profiles,
+        &default_profile,
+    );
+    if !profile.is_empty() && !profiles.contains(&profile) {
+        let answer = |question| Some(ask(question, "")).filter(|answer| !answer.is_empty());
+        let path = Profile {
+            language: answer("Language of the answers, e.g. German (empty for any)"),
+            tone: answer("Tone of the answers, e.g. concise (empty for any)"),
+        }
+        .save(&profile)?;
+        println!("Saved profile '{}' to {}", profile, path.display());
+    }
+
+    let config = ClientConfig {
+        model: Some(model),
+        mcp_server: Some(mcp_server).filter(|url| !url.is_empty()),
+        profile: Some(profile).filter(|name| !name.is_empty()),
+    };
+    let path = config.save()?;
+    println!();
+    println!("Saved the defaults to {}", path.display());
+    match config.mcp_server {
+        Some(_) => println!("Start a conversation with: mcp-client -c"),
+        None => println!("Start a conversation with: mcp-client -c -s <MCP server URL>"),
+    }
+    Ok(())
+}
+
+/// Replay a recorded session, returning whether every replayed step matched
+fn run_replay(
+    args: &Args,
+    transcript: &str,
+    conversation: Option<&str>,
+    skip_model: bool,
+    redact: &[String],
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let transcript = replay::load(transcript, conversation)?;
+    println!("Replaying conversation {}", transcript.conversation);
+    let mcp_client = connect(args);
+    mcp_client.initialize()?;
+    let tools: Vec<OllamaTool> = mcp_client
+        .list_tools()?
+        .iter()
+        .map(OllamaTool::from_mcp_tool)
+        .collect();
+    let mut redactor = Redactor::default();
+    for pattern in redact {
+        redactor.add_pattern(pattern)?;
+    }
+
+    // The recorded conversation so far, as sent to the model
+    let mut messages = Vec::new();
+    let (mut same, mut changed, mut skipped) = (0, 0, 0);
+    for (step, interaction) in transcript.interactions.iter().enumerate() {
+        let (description, outcome) = match interaction {
+            Interaction::Model {
+                model,
+                messages: added,
+                response,
+                tool_calls,
+                error,
+                ..
+            } => {
+                messages.extend(added.iter().cloned());
+                let outcome = if skip_model {
+                    None
+                } else {
+                    let ollama = Ollama::new(OllamaConfig::new(model), tools.clone());
+                    let recorded = match error {
+                        Some(error) => Err(error.as_str()),
+                        None => Ok(tool_calls.as_deref().unwrap_or_default()),
+                    };
+                    Some(replay::replay_model_request(&ollama, &messages, recorded))
+                };
+                if error.is_none() {
+                    messages.push(ChatMessage::assistant_with_tools(
+                        response.clone().unwrap_or_default(),
+                        tool_calls.clone().unwrap_or_default(),
+                    ));
                 }
+                (format!("model request to {}", model), outcome)
+            }
+            Interaction::Tool {
+                tool,
+                arguments,
+                result,
+                error,
+                ..
+            } => {
+                let recorded = match error {
+                    Some(error) => Err(error.as_str()),
+                    None => Ok(result.as_deref().unwrap_or_default()),
+                };
+                let outcome =
+                    replay::replay_tool_call(&mcp_client, &redactor, tool, arguments, recorded);
+                (format!("tool call {}", tool), Some(outcome))
+            }
+        };
+        match outcome {
+            None => {}
+            Some(Outcome::Same) => {
+                same += 1;
+                println!("[{}] {}: same", step + 1, description);
+            }
+            Some(Outcome::Changed(diff)) => {
+                changed += 1;
+                println!("[{}] {}: changed", step + 1, description);
+                for line in diff.lines() {
+                    println!("    {}", line);
+                }
+            }
+            Some(Outcome::Skipped(reason)) => {
+                skipped += 1;
+                println!("[{}] {}: skipped, {}", step + 1, description, reason);
+            }
+        }
+    }
+    println!(
+        "{} steps replayed: {} same, {} changed, {} skipped",
+        same + changed + skipped,
+        same,
+        changed,
+        skipped
+    );
+    Ok(changed == 0)
+}
+
+/// Summary of a model's capabilities, e.g. "tools, no vision, 8192 tokens of context"
+fn describe_capabilities(capabilities: &ModelCapabilities) -> String {
+    let support = |name: &str, supported: Option<bool>| match supported {
+        Some(true) => Some(name.to_string()),
+        Some(false) => Some(format!("no {}", name)),
+        None => None,
+    };
+    let mut parts: Vec<String> = [
+        support("tools", capabilities.tools),
+        support("vision", capabilities.vision),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if let Some(length) = capabilities.context_length {
+        parts.push(format!("{} tokens of context", length));
+    }
+    if parts.is_empty() {
+        "capabilities not reported".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Print resources one per line with their URI
+fn print_resources(console: &Console, resources: &[Resource]) {
+    if resources.is_empty() {
+        console.say("  (none)");
+    }
+    for resource in resources {
+        console.say(format!(
+            "  - {} ({}){}",
+            resource.name,
+            resource.uri,
+            resource
+                .description
+                .as_deref()
+                .map(|description| format!(": {}", description))
+                .unwrap_or_default()
+        ));
+    }
+}
+
+/// Read a resource and add its text to the conversation
+///
+/// Binary contents are not sent to the model; only their size is reported.
+fn read_resource(console: &Console, mcp_client: &McpClient, session: &mut ChatSession, uri: &str) {
+    let contents = match mcp_client.read_resource(uri) {
+        Ok(contents) => contents,
+        Err(e) => {
+            console.error(format!("Error reading resource '{}': {}", uri, e));
+            return;
+        }
+    };
+
+    for part in contents {
+        match (part.text, part.blob) {
+            (Some(text), _) => {
+                console.say(format!("Read {} ({} bytes)", part.uri, text.len()));
+                session.add_system_message(format!("Contents of resource {}:\n{}", part.uri, text));
+            }
+            (None, Some(blob)) => {
+                console.say(format!(
+                    "Skipping binary resource {} ({} base64 characters)",
+                    part.uri,
+                    blob.len()
+                ));
+            }
+            (None, None) => console.say(format!("Resource {} is empty", part.uri)),
+        }
+    }
+}
+
+/// Let the MCP server ask the model for completions and the user for input, as
+/// --sampling and --no-elicitation allow
+fn answer_server_requests(console: &Console, mcp_client: &McpClient, args: &Args, model: &str) {
+    if args.sampling != SamplingPolicy::Deny {
+        let policy = args.sampling;
+        let sampling_console = console.clone();
+        let sampler = OllamaSampler::new(model.to_string()).with_approval(move |request| {
+            policy == SamplingPolicy::Allow || confirm_sampling(&sampling_console, request)
+        });
+        if let Err(e) = mcp_client.on_sampling(move |request| sampler.sample(request)) {
+            exit::fail(FailureKind::Failed, e);
+        }
+    }
+
+    if !args.no_elicitation {
+        let elicitation_console = console.clone();
+        if let Err(e) = mcp_client
+            .on_elicitation(move |request| Ok(answer_elicitation(&elicitation_console, request)))
+        {
+            exit::fail(FailureKind::Failed, e);
+        }
+    }
+}
+
+/// Run a /resources, /read, /subscribe or /unsubscribe command; false if the message
+/// is not one and goes to the model
+fn run_command(
+    console: &Console,
+    mcp_client: &McpClient,
+    session: &mut ChatSession,
+    message: &str,
+    following_updates: &mut bool,
+) -> bool {
+    if message == "/resources" {
+        match mcp_client.list_resources() {
+            Ok(resources) => print_resources(console, &resources),
+            Err(e) => console.error(format!("Error listing resources: {}", e)),
+        }
+    } else if let Some(uri) = message.strip_prefix("/read ") {
+        read_resource(console, mcp_client, session, uri.trim());
+    } else if let Some(uri) = message.strip_prefix("/subscribe ") {
+        if !*following_updates {
+            let updates_console = console.clone();
+            match mcp_client.on_resource_updated(move |uri| {
+                updates_console.say(format!(
+                    "\nResource updated: {} (/read it to see the changes)",
+                    uri
+                ))
+            }) {
+                Ok(()) => *following_updates = true,
+                Err(e) => {
+                    console.error(format!("Error listening for resource updates: {}", e));
+                    return true;
+                }
+            }
+        }
+        match mcp_client.subscribe_resource(uri.trim()) {
+            Ok(()) => console.say(format!("Subscribed to {}", uri.trim())),
+            Err(e) => console.error(format!("Error subscribing to resource: {}", e)),
+        }
+    } else if let Some(uri) = message.strip_prefix("/unsubscribe ") {
+        match mcp_client.unsubscribe_resource(uri.trim()) {
+            Ok(()) => console.say(format!("Unsubscribed from {}", uri.trim())),
+            Err(e) => console.error(format!("Error unsubscribing from resource: {}", e)),
+        }
+    } else {
+        return false;
+    }
+    true
+}
+
+/// One message of the user to the model and the tool calls the model answers it with
+struct Turn<'a> {
+    console: &'a Console,
+    mcp_client: &'a McpClient,
+    session: &'a mut ChatSession,
+    tools: &'a [McpTool],
+    interaction_log: Option<&'a InteractionLog>,
+    deadline: Option<Deadline>,
+}
+
+impl Turn<'_> {
+    /// Send the message and run the tools the model calls, including those it calls
+    /// again with arguments it left out
+    fn run(mut self, message: &str) {
+        let mut queue = VecDeque::from(self.tell(message, "Error making request to Ollama"));
+        let mut argument_follow_ups = 0;
+        while let Some(tool_call) = queue.pop_front() {
+            match self.run_tool_call(tool_call, &mut argument_follow_ups) {
+                ControlFlow::Continue(calls) => queue.extend(calls),
+                ControlFlow::Break(()) => break,
+            }
+        }
+    }
+
+    /// Send a message to the model and show its answer, returning the tools it calls
+    ///
+    /// `failure` introduces the error shown when the model cannot be reached.
+    fn tell(&mut self, message: &str, failure: &str) -> Vec<OllamaToolCall> {
+        match self.console.send(self.session, message) {
+            Ok(response) => {
+                self.console.assistant(&response.message.content);
+                // Fall back to calls written as text by models without native tool calling
+                response
+                    .message
+                    .tool_calls
+                    .or_else(|| parse_tool_calls(&response.message.content))
+                    .unwrap_or_default()
             }
             Err(e) => {
-                println!("Error reading input: {}", e);
+                self.console.error(format!("{}: {}", failure, e));
+                Vec::new()
+            }
+        }
+    }
+
+    /// Run a tool call unless the turn's deadline or budget forbids it, returning the
+    /// calls to run next, or breaking once the deadline passed
+    fn run_tool_call(
+        &mut self,
+        mut tool_call: OllamaToolCall,
+        argument_follow_ups: &mut u32,
+    ) -> ControlFlow<(), Vec<OllamaToolCall>> {
+        let name = tool_call.function.name.clone();
+        self.console.tool_call(&name, &tool_call.function.arguments);
+
+        if self.deadline.is_some_and(|deadline| deadline.is_expired()) {
+            self.console.say(format!(
+                "Tool '{}' was not executed: the deadline for this message passed",
+                name
+            ));
+            return ControlFlow::Break(());
+        }
+
+        // A model that keeps calling tools is stopped once the turn's budget is spent
+        if !self.session.allow_tool_call() {
+            self.console.say(format!(
+                "Tool '{}' was not executed: tool call limit reached for this message",
+                name
+            ));
+            return ControlFlow::Continue(Vec::new());
+        }
+
+        let tool = self.tools.iter().find(|tool| tool.name == name);
+
+        // Required arguments the model left out are asked of the user, who can leave it
+        // to the model to call the tool again instead
+        let missing = tool
+            .map(|tool| missing_arguments(tool, &tool_call.function.arguments))
+            .unwrap_or_default();
+        if !missing.is_empty()
+            && !ask_missing_arguments(
+                self.console,
+                &name,
+                &missing,
+                &mut tool_call.function.arguments,
+            )
+        {
+            if *argument_follow_ups >= MAX_ARGUMENT_FOLLOW_UPS {
+                self.console.say(format!(
+                    "Tool '{}' was not executed: required arguments are missing",
+                    name
+                ));
+                return ControlFlow::Continue(Vec::new());
+            }
+            *argument_follow_ups += 1;
+            // Calls made with the missing arguments run next
+            return ControlFlow::Continue(self.tell(
+                &missing_arguments_prompt(&name, &missing),
+                "Error asking the assistant for the missing arguments",
+            ));
+        }
+
+        // Deprecated tools still run, but the user should know the model uses them
+        if let Some(warning) = tool.and_then(|tool| tool.deprecation_warning()) {
+            self.console.warn(warning);
+        }
+
+        // Tools annotated as requiring approval only run after the user confirms
+        if tool.is_some_and(|tool| tool.requires_approval())
+            && !confirm_tool_call(self.console, &name)
+        {
+            self.tell(
+                &format!(
+                    "Tool '{}' was not executed: the user declined the call.",
+                    name
+                ),
+                "Error sending tool rejection to assistant",
+            );
+            return ControlFlow::Continue(Vec::new());
+        }
+
+        self.call_tool(&name, tool_call.function.arguments);
+        ControlFlow::Continue(Vec::new())
+    }
+
+    /// Execute a tool on the MCP server and send its result or error back to the model
+    fn call_tool(&mut self, name: &str, arguments: serde_json::Value) {
+        self.console.tool_started(name);
+        let started = Instant::now();
+        let tool_result = match self.deadline {
+            Some(deadline) => {
+                self.mcp_client
+                    .call_tool_with_deadline(name, Some(arguments.clone()), deadline)
+            }
+            None => self.mcp_client.call_tool(name, Some(arguments.clone())),
+        };
+        if let Some(log) = self.interaction_log {
+            let outcome = match &tool_result {
+                Ok(result) => Ok(result.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            log.tool_exchange(
+                name,
+                Some(&arguments),
+                outcome.as_deref().map_err(String::as_str),
+                started.elapsed(),
+            );
+        }
+        match tool_result {
+            Ok(tool_result) => {
+                // Tell the model when the tool reported a failure
+                let failure = serde_json::from_value::<ToolsCallResponse>(tool_result.clone())
+                    .ok()
+                    .filter(|response| response.is_error);
+                self.console
+                    .tool_result(name, &tool_result, failure.is_some());
+                let tool_result_message = match failure {
+                    Some(response) => format!("Tool '{}' failed: {}", name, response.text()),
+                    None => format!(
+                        "Tool '{}' executed successfully. Result: {}",
+                        name, tool_result
+                    ),
+                };
+                self.tell(
+                    &tool_result_message,
+                    "Error sending tool result to assistant",
+                );
+            }
+            Err(e) => {
+                self.console.tool_error(name, &e);
+                self.tell(
+                    &format!("Tool '{}' execution failed: {}", name, e),
+                    "Error sending tool error to assistant",
+                );
+            }
+        }
+    }
+}
+
+/// Ask the user to approve a tool call, defaulting to no
+fn confirm_tool_call(console: &Console, name: &str) -> bool {
+    console.confirm(&format!("Allow tool '{}' to run?", name), Vec::new())
+}
+
+/// Show a sampling request of the server and ask the user to approve it, defaulting
+/// to no
+fn confirm_sampling(console: &Console, request: &CreateMessageRequest) -> bool {
+    let mut details = vec!["The MCP server asks the model for a completion:".to_string()];
+    if let Some(prompt) = &request.system_prompt {
+        details.push(format!("  system: {}", prompt));
+    }
+    for message in &request.messages {
+        details.push(match &message.content.text {
+            Some(text) => format!("  {}: {}", message.role, text),
+            None => format!(
+                "  {}: [{} content]",
+                message.role, message.content.content_type
+            ),
+        });
+    }
+    console.confirm(
+        &format!("Allow it (at most {} tokens)?", request.max_tokens),
+        details,
+    )
+}
+
+/// Show an elicitation request of the server and ask the user for each field
+///
+/// The request is declined unless the user agrees to answer, and cancelled when input
+/// ends or a required field is left empty.
+fn answer_elicitation(console: &Console, request: &ElicitRequest) -> ElicitResult {
+    let fields = request.fields();
+    let mut details = vec![format!(
+        "The MCP server asks for input: {}",
+        request.message
+    )];
+    details.extend(fields.iter().map(|field| format!("  {}", field)));
+    if !console.confirm("Answer it?", details) {
+        return ElicitResult::decline();
+    }
+    let mut content = serde_json::Map::new();
+    for field in &fields {
+        loop {
+            let question = format!(
+                "  Value for {} (empty to {}): ",
+                field.name,
+                if field.required { "cancel" } else { "skip" }
+            );
+            let Some(input) = console.ask(&question) else {
+                return ElicitResult::cancel();
+            };
+            if input.trim().is_empty() {
+                if field.required {
+                    return ElicitResult::cancel();
+                }
                 break;
             }
+            match field.parse_value(&input) {
+                Ok(value) => {
+                    content.insert(field.name.clone(), value);
+                    break;
+                }
+                Err(e) => console.say(format!("  {}", e)),
+            }
+        }
+    }
+    ElicitResult::accept(content)
+}
+
+/// Ask the user for the required arguments a tool call lacks, adding them to `arguments`
+///
+/// Returns false when the user leaves a value empty to let the model complete the call.
+fn ask_missing_arguments(
+    console: &Console,
+    name: &str,
+    missing: &[MissingArgument],
+    arguments: &mut serde_json::Value,
+) -> bool {
+    console.say(format!(
+        "Tool '{}' was called without required arguments.",
+        name
+    ));
+    if !arguments.is_object() {
+        *arguments = serde_json::Value::Object(Default::default());
+    }
+    for argument in missing {
+        loop {
+            console.say(format!("  {}", argument));
+            let question = format!("  Value for {} (empty to ask the model): ", argument.name);
+            let Some(input) = console
+                .ask(&question)
+                .filter(|input| !input.trim().is_empty())
+            else {
+                return false;
+            };
+            match argument.parse_value(&input) {
+                Ok(value) => {
+                    arguments[argument.name.as_str()] = value;
+                    break;
+                }
+                Err(e) => console.say(format!("  {}", e)),
+            }
+        }
+    }
+    true
+}
+
+/// Where the conversation is shown and the user answers: stdout and stdin, or the
+/// terminal interface of --tui once it started
+///
+/// Clones share the interface, so callbacks of the MCP client show their messages in
+/// it too.
+#[derive(Clone, Default)]
+struct Console {
+    #[cfg(feature = "tui")]
+    tui: Arc<std::sync::OnceLock<Tui>>,
+}
+
+impl Console {
+    #[cfg(feature = "tui")]
+    fn start_tui(&self, status: mcp::tui::Status) -> std::io::Result<()> {
+        let tui = Tui::start(status)?;
+        if let Err(tui) = self.tui.set(tui) {
+            tui.close();
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "tui")]
+    fn tui(&self) -> Option<&Tui> {
+        self.tui.get()
+    }
+
+    /// Give the terminal back if the interface is running
+    fn close(&self) {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            tui.close();
+        }
+    }
+
+    fn say(&self, text: impl Into<String>) {
+        let text = text.into();
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.line(Role::Notice, text.trim_start_matches('\n'));
+        }
+        println!("{}", text);
+    }
+
+    fn error(&self, text: impl Into<String>) {
+        let text = text.into();
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.line(Role::Warning, text);
+        }
+        println!("{}", text);
+    }
+
+    fn warn(&self, text: impl std::fmt::Display) {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.line(Role::Warning, format!("Warning: {}", text));
+        }
+        eprintln!("Warning: {}", text);
+    }
+
+    fn assistant(&self, text: &str) {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.send(TuiEvent::Answer(text.to_string()));
+        }
+        println!("Assistant: {}", text);
+    }
+
+    fn tool_call(&self, name: &str, arguments: &serde_json::Value) {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.send(TuiEvent::ToolCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            });
+        }
+        println!("Tool call: {}", name);
+        println!("Tool call arguments: {}", arguments);
+    }
+
+    fn tool_started(&self, name: &str) {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            tui.send(TuiEvent::ToolStarted(name.to_string()));
+        }
+        let _ = name;
+    }
+
+    fn tool_result(&self, name: &str, result: &serde_json::Value, failed: bool) {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            let text = serde_json::from_value::<ToolsCallResponse>(result.clone())
+                .map(|response| response.text())
+                .unwrap_or_else(|_| result.to_string());
+            return tui.send(TuiEvent::ToolResult {
+                name: name.to_string(),
+                text,
+                failed,
+            });
+        }
+        let _ = (name, failed);
+        println!("Tool result: {}", result);
+    }
+
+    fn tool_error(&self, name: &str, error: &McpError) {
+        let text = format!("Error executing tool '{}': {}", name, error);
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.send(TuiEvent::ToolResult {
+                name: name.to_string(),
+                text,
+                failed: true,
+            });
+        }
+        println!("{}", text);
+    }
+
+    fn progress(&self, text: impl Into<String>) {
+        let text = text.into();
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.send(TuiEvent::Progress(text));
+        }
+        println!("Tool progress: {}", text);
+    }
+
+    /// Show the tools the server changed to; the lines show them when listed
+    fn tools(&self, tools: &[McpTool]) {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            tui.send(TuiEvent::Tools(
+                tools.iter().map(|tool| tool.name.clone()).collect(),
+            ));
+        }
+        let _ = tools;
+    }
+
+    /// Send a message to the model, streaming its answer into the interface
+    fn send(&self, session: &mut ChatSession, message: &str) -> Result<ChatResponse, McpError> {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return session
+                .send_streaming(message, |text| tui.send(TuiEvent::Chunk(text.to_string())));
+        }
+        session.send(message)
+    }
+
+    /// Read the user's next message; None at the end of input
+    fn read_message(&self) -> std::io::Result<Option<String>> {
+        use std::io::{self, Write};
+
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return Ok(tui.read_line(None));
+        }
+        print!("> ");
+        io::stdout().flush()?;
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            println!();
+            return Ok(None);
+        }
+        Ok(Some(input))
+    }
+
+    /// Ask the user a question; None if the answer could not be read
+    fn ask(&self, question: &str) -> Option<String> {
+        use std::io::{self, Write};
+
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.read_line(Some(question.trim()));
+        }
+        print!("{}", question);
+        io::stdout().flush().ok()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).ok()?;
+        Some(input)
+    }
+
+    /// Ask the user to approve what `details` describe, defaulting to no
+    fn confirm(&self, question: &str, details: Vec<String>) -> bool {
+        #[cfg(feature = "tui")]
+        if let Some(tui) = self.tui() {
+            return tui.confirm(question, details);
+        }
+        for line in details {
+            println!("{}", line);
         }
+        self.ask(&format!("{} [y/N] ", question))
+            .is_some_and(|answer| {
+                matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+            })
     }
 }
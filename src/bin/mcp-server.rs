@@ -1,29 +1,455 @@
-use mcp::file_read;
+use clap::{CommandFactory, Parser, Subcommand};
+use mcp::config::ServerConfig;
+use mcp::exit::{self, ErrorFormat, FailureKind};
+use mcp::host_config::{HostConfig, HostFormat, HostServer};
 use mcp::mcp::McpServer;
+use mcp::{
+    convert, diff, feed_read, file_list, file_read, file_search, generate, json_query, log_tail,
+    prompts, regex_extract, render_template, resources, url_download, xlsx_read,
+};
+use std::str::FromStr;
+
+#[derive(Parser)]
+#[command(name = "mcp-server")]
+#[command(about = "An MCP server exposing local tools")]
+#[command(version = "0.1.0")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to a TOML configuration file enabling optional tools
+    #[arg(short = 'c', long = "config")]
+    config: Option<String>,
+
+    /// Also serve the HTTP+SSE transport (GET /sse) used by older MCP clients
+    #[arg(long = "sse")]
+    sse: bool,
+
+    /// Address to listen on, e.g. 0.0.0.0:8080 to accept connections from the network
+    #[arg(long = "bind", default_value = "127.0.0.1:8080")]
+    bind: std::net::SocketAddr,
+
+    /// PEM certificate to serve HTTPS with, followed by its intermediates
+    #[arg(long = "tls-cert", requires = "tls_key")]
+    tls_cert: Option<std::path::PathBuf>,
+
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1) of the --tls-cert certificate
+    #[arg(long = "tls-key", requires = "tls_cert")]
+    tls_key: Option<std::path::PathBuf>,
+
+    /// Serve on a unix domain socket at this path instead of a TCP port
+    #[cfg(unix)]
+    #[arg(long = "unix-socket", conflicts_with = "sse")]
+    unix_socket: Option<String>,
+
+    /// Print the error ending a failed run as text or as one JSON object on stderr;
+    /// the exit code tells the cause either way
+    #[arg(long = "error-format", value_enum, default_value_t = ErrorFormat::Text, global = true)]
+    error_format: ErrorFormat,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the configuration, allowed directories, upstreams and tools, then exit
+    ///
+    /// Exits with status 1 if any check fails.
+    Check,
+
+    /// Print the configuration registering this server with an MCP host, then exit
+    HostConfig {
+        /// claude-desktop (claude_desktop_config.json) or mcp-json (.mcp.json)
+        #[arg(long = "format", default_value = "mcp-json", value_parser = HostFormat::from_str)]
+        format: HostFormat,
+
+        /// Name of the server in the host
+        #[arg(long = "name", default_value = "mcp-server")]
+        name: String,
+
+        /// URL the host connects to (default http://localhost:8080/mcp, or /sse with --sse)
+        #[arg(long = "url")]
+        url: Option<String>,
+    },
+
+    /// Print the completion script of a shell (bash, zsh, fish, elvish or powershell)
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the manual page, or write the pages of every subcommand to a directory
+    Manpage {
+        /// Directory to write mcp-server.1 and the pages of the subcommands to, e.g.
+        /// /usr/local/share/man/man1
+        #[arg(long = "out-dir")]
+        out_dir: Option<std::path::PathBuf>,
+    },
+
+    /// Run a command under the sandbox policy passed by the server
+    #[command(name = "sandbox-exec", hide = true)]
+    SandboxExec {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() {
+    let args: Args = exit::parse_args();
+    exit::set_format(args.error_format);
+
+    if let Some(Command::SandboxExec { command }) = &args.command {
+        #[cfg(all(feature = "sandbox", target_os = "linux"))]
+        eprintln!("Error: {}", mcp::sandbox::exec(command));
+        #[cfg(not(all(feature = "sandbox", target_os = "linux")))]
+        eprintln!(
+            "Error: cannot run '{}' in a sandbox, this build lacks the 'sandbox' feature",
+            command.join(" ")
+        );
+        std::process::exit(127);
+    }
+
+    match &args.command {
+        Some(Command::Completions { shell }) => {
+            if let Err(e) = mcp::manual::print_completions(Args::command(), *shell) {
+                exit::fail(FailureKind::Failed, e);
+            }
+            return;
+        }
+        Some(Command::Manpage { out_dir }) => {
+            let written = match out_dir {
+                Some(dir) => mcp::manual::write_manpages(Args::command(), dir).map(|paths| {
+                    for path in paths {
+                        println!("Wrote {}", path.display());
+                    }
+                }),
+                None => mcp::manual::print_manpage(Args::command()),
+            };
+            if let Err(e) = written {
+                exit::fail(FailureKind::Failed, e);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some(Command::HostConfig { format, name, url }) = &args.command {
+        let url = url.clone().unwrap_or_else(|| {
+            let path = if args.sse { "sse" } else { "mcp" };
+            format!("http://localhost:8080/{}", path)
+        });
+        let config = HostConfig {
+            mcp_servers: [(name.clone(), HostServer::remote(url, args.sse))].into(),
+        };
+        match config.to_json(*format) {
+            Ok(json) => println!("{}", json),
+            Err(e) => exit::fail(FailureKind::Failed, e),
+        }
+        return;
+    }
+
+    let config = match &args.config {
+        Some(path) => {
+            ServerConfig::load(path).unwrap_or_else(|e| exit::fail(FailureKind::Config, e))
+        }
+        None => ServerConfig::default(),
+    };
+    mcp::server_log::set_capacity(
+        config
+            .log
+            .capacity
+            .unwrap_or(mcp::server_log::DEFAULT_CAPACITY),
+    );
+
     // Create a new MCP server
-    let server = McpServer::new();
+    let mut builder = McpServer::builder().bind(args.bind).config(config.clone());
+    if args.sse {
+        builder = builder.sse();
+    }
+    if let (Some(cert), Some(key)) = (&args.tls_cert, &args.tls_key) {
+        builder = builder.tls(mcp::tls::ServerTlsConfig::new(cert, key));
+    }
+    let mut server = builder
+        .build()
+        .unwrap_or_else(|e| exit::fail(FailureKind::Config, e));
+    if !args.bind.ip().is_loopback() && config.api_key.is_empty() {
+        eprintln!(
+            "Warning: listening on {} without [[api_key]] in the config lets anyone who can reach it call the tools",
+            args.bind
+        );
+    }
+    match mcp::auth::ApiKeys::from_config(&config.api_key) {
+        Ok(keys) => server.set_api_keys(keys),
+        Err(e) => exit::fail(FailureKind::Config, e),
+    }
+    if config.files.read_only {
+        server.set_fs(mcp::vfs::ReadOnlyFs(mcp::vfs::RootedFs::allowed()));
+    }
 
-    // Add the file_read tool using the dedicated module
+    // Add the tools using their dedicated modules
     server.add_tool(file_read::get_tool_definition()).await;
+    server.add_tool(file_list::get_tool_definition()).await;
+    server.add_tool(file_search::get_tool_definition()).await;
+    server.add_tool(json_query::get_tool_definition()).await;
+    server.add_tool(log_tail::get_tool_definition()).await;
+    server.add_tool(regex_extract::get_tool_definition()).await;
+    server.add_tool(diff::get_tool_definition()).await;
+    server
+        .add_tool(render_template::get_tool_definition())
+        .await;
+    server.add_tool(xlsx_read::get_tool_definition()).await;
+    server.add_tool(generate::get_tool_definition()).await;
+    server.add_tool(convert::get_tool_definition()).await;
+    #[cfg(feature = "image")]
+    server
+        .add_tool(mcp::image_info::get_tool_definition())
+        .await;
 
-    println!("MCP server starting with file_read tool...");
-    println!("You can test it with:");
-    println!("curl -X POST http://localhost:8080/mcp \\");
-    println!("  -H 'Content-Type: application/json' \\");
-    println!("  -d '{{\"jsonrpc\":\"2.0\",\"id\":\"1\",\"method\":\"tools/list\"}}'");
-    println!();
-    println!("Or call the file_read tool:");
-    println!("curl -X POST http://localhost:8080/mcp \\");
+    // Optional tools are only registered when explicitly enabled in the config
+    if !config.feed.allowed_domains.is_empty() {
+        server.add_tool(feed_read::get_tool_definition()).await;
+    }
+    if !config.download.allowed_domains.is_empty() {
+        server.add_tool(url_download::get_tool_definition()).await;
+    }
+
+    #[cfg(feature = "clipboard")]
+    {
+        if config.clipboard.read {
+            server
+                .add_tool(mcp::clipboard::get_read_tool_definition())
+                .await;
+        }
+        if config.clipboard.write {
+            server
+                .add_tool(mcp::clipboard::get_write_tool_definition())
+                .await;
+        }
+    }
+    #[cfg(not(feature = "clipboard"))]
+    if config.clipboard.read || config.clipboard.write {
+        eprintln!(
+            "Warning: clipboard tools are enabled in the config but this build lacks the 'clipboard' feature"
+        );
+    }
+
+    #[cfg(feature = "email")]
+    if config.email.enabled {
+        if config.email.host.is_none()
+            || config.email.from.is_none()
+            || config.email.allowed_recipients.is_empty()
+        {
+            exit::fail(
+                FailureKind::Config,
+                "email_send requires host, from and allowed_recipients in the config",
+            );
+        }
+        server.add_tool(mcp::email::get_tool_definition()).await;
+    }
+    #[cfg(not(feature = "email"))]
+    if config.email.enabled {
+        eprintln!(
+            "Warning: the email tool is enabled in the config but this build lacks the 'email' feature"
+        );
+    }
+
+    if config.memory.path.is_some() {
+        server
+            .add_tool(mcp::memory::get_ingest_tool_definition())
+            .await;
+        server
+            .add_tool(mcp::memory::get_search_tool_definition())
+            .await;
+    }
+
+    if config.summarize.model.is_some() {
+        server.add_tool(mcp::summarize::get_tool_definition()).await;
+    }
+
+    if !config.upstream.is_empty() {
+        server.set_gateway(mcp::gateway::Gateway::new(&config.upstream));
+    }
+
+    if let Some(translate) = &config.translate {
+        server.set_translator(mcp::translate::build_translator(translate));
+        server.add_tool(mcp::translate::get_tool_definition()).await;
+    }
+
+    if !config.repository.is_empty() {
+        server
+            .add_tool(mcp::repository::get_list_tool_definition(
+                &config.repository,
+            ))
+            .await;
+        server
+            .add_tool(mcp::repository::get_pr_diff_tool_definition(
+                &config.repository,
+            ))
+            .await;
+        server
+            .add_tool(mcp::repository::get_file_read_tool_definition(
+                &config.repository,
+            ))
+            .await;
+    }
+
+    #[cfg(feature = "postgres")]
+    if config.postgres.url.is_some() || config.postgres.url_env.is_some() {
+        server.add_tool(mcp::pg_query::get_tool_definition()).await;
+    }
+    #[cfg(not(feature = "postgres"))]
+    if config.postgres.url.is_some() || config.postgres.url_env.is_some() {
+        eprintln!(
+            "Warning: a PostgreSQL database is configured but this build lacks the 'postgres' feature"
+        );
+    }
+
+    #[cfg(feature = "screenshot")]
+    if config.screenshot.enabled {
+        server
+            .add_tool(mcp::screenshot::get_tool_definition())
+            .await;
+    }
+    #[cfg(not(feature = "screenshot"))]
+    if config.screenshot.enabled {
+        eprintln!(
+            "Warning: the screenshot tool is enabled in the config but this build lacks the 'screenshot' feature"
+        );
+    }
+
+    #[cfg(feature = "speech")]
+    {
+        let engines = mcp::speech::SpeechEngines::from_config(&config.speech);
+        if engines.tts.is_some() {
+            server
+                .add_tool(mcp::speech::get_tts_tool_definition())
+                .await;
+        }
+        if engines.stt.is_some() {
+            server
+                .add_tool(mcp::speech::get_stt_tool_definition())
+                .await;
+        }
+        server.set_speech_engines(engines);
+    }
+    #[cfg(not(feature = "speech"))]
+    if config.speech.tts.is_some() || config.speech.stt.is_some() {
+        eprintln!(
+            "Warning: speech engines are configured but this build lacks the 'speech' feature"
+        );
+    }
+
+    #[cfg(feature = "ssh")]
+    {
+        if config.ssh_host.iter().any(|h| !h.allowed_paths.is_empty()) {
+            server
+                .add_tool(mcp::ssh::get_file_read_tool_definition(&config.ssh_host))
+                .await;
+        }
+        if config
+            .ssh_host
+            .iter()
+            .any(|h| !h.allowed_commands.is_empty())
+        {
+            server
+                .add_tool(mcp::ssh::get_exec_tool_definition(&config.ssh_host))
+                .await;
+        }
+    }
+    #[cfg(not(feature = "ssh"))]
+    if !config.ssh_host.is_empty() {
+        eprintln!("Warning: SSH hosts are configured but this build lacks the 'ssh' feature");
+    }
+
+    #[cfg(all(feature = "sandbox", target_os = "linux"))]
+    if config.sandbox.enabled {
+        mcp::sandbox::configure(&config.sandbox)
+            .unwrap_or_else(|e| exit::fail(FailureKind::Config, e));
+    }
+    #[cfg(not(all(feature = "sandbox", target_os = "linux")))]
+    if config.sandbox.enabled {
+        eprintln!(
+            "Warning: the sandbox is enabled in the config but this build lacks the 'sandbox' feature or is not for Linux; subprocesses run unrestricted"
+        );
+    }
+
+    for prompt in &config.prompt {
+        server
+            .register_prompt(prompts::PromptTemplate::from(prompt))
+            .await;
+    }
+
+    // Files in the allowed directories and scheduled job results are readable as resources
+    server.register_resources(resources::FileResources).await;
+    server
+        .register_resources(resources::ScheduleResources)
+        .await;
+    if !config.download.allowed_domains.is_empty() {
+        server
+            .register_resources(resources::TransferResources)
+            .await;
+    }
+    server
+        .register_resources(resources::ServerResources {
+            logs: config.log.resource.unwrap_or(true),
+        })
+        .await;
+
+    if let Some(directory) = &config.i18n.catalog_dir {
+        let catalogs = mcp::i18n::load_catalogs(directory)
+            .unwrap_or_else(|e| exit::fail(FailureKind::Config, e));
+        let mut languages: Vec<&str> = catalogs.keys().map(String::as_str).collect();
+        languages.sort_unstable();
+        println!("Loaded translations for: {}", languages.join(", "));
+        server.set_catalogs(catalogs);
+    }
+
+    let journal_path = config.jobs.journal.clone();
+
+    if let Some(Command::Check) = args.command {
+        let report = server.check().await;
+        println!("{}", report);
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
+
+    // Opened after the check, which must not rewrite the journal
+    if let Some(path) = journal_path {
+        let journal = mcp::job_journal::JobJournal::open(path)
+            .unwrap_or_else(|e| exit::fail(FailureKind::Config, e));
+        server.set_job_journal(journal);
+    }
+
+    let tool_names: Vec<String> = server
+        .tools()
+        .await
+        .iter()
+        .map(|t| t.name.clone())
+        .collect();
+    println!("MCP server starting with tools: {}", tool_names.join(", "));
+
+    // Local clients connect with mcp-client --mcp-socket; no TCP port is opened
+    #[cfg(unix)]
+    if let Some(path) = &args.unix_socket {
+        if let Err(e) = server.bind_unix(path).await {
+            exit::fail(FailureKind::Failed, e);
+        }
+        return;
+    }
+
+    println!(
+        "You can test it by opening a session (the response carries an Mcp-Session-Id header):"
+    );
+    println!("curl -i -X POST {} \\", server.endpoint_url());
     println!("  -H 'Content-Type: application/json' \\");
     println!(
-        "  -d '{{\"jsonrpc\":\"2.0\",\"id\":\"2\",\"method\":\"tools/call\",\"params\":{{\"name\":\"file_read\",\"arguments\":{{\"path\":\"Cargo.toml\"}}}}}}'"
+        "  -d '{{\"jsonrpc\":\"2.0\",\"id\":\"1\",\"method\":\"initialize\",\"params\":{{\"protocolVersion\":\"2025-06-18\",\"capabilities\":{{}},\"clientInfo\":{{\"name\":\"curl\",\"version\":\"1.0\"}}}}}}'"
     );
+    println!();
+    println!("Then list the tools within the session:");
+    println!("curl -X POST {} \\", server.endpoint_url());
+    println!("  -H 'Content-Type: application/json' -H 'Mcp-Session-Id: <session id>' \\");
+    println!("  -d '{{\"jsonrpc\":\"2.0\",\"id\":\"2\",\"method\":\"tools/list\"}}'");
 
     // Start the server (this will run indefinitely)
-    server.start().await?;
-
-    Ok(())
+    if let Err(e) = server.start().await {
+        exit::fail(FailureKind::Failed, e);
+    }
 }
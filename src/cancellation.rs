@@ -0,0 +1,108 @@
+//! Cancellation module
+//!
+//! This module aborts tool calls the client gave up on. Every tools/call in flight is
+//! registered under its session and request id with the [`CancellationToken`] its
+//! handler receives; `notifications/cancelled` naming the request cancels the token,
+//! and the server stops waiting for the tool and answers with a "Request cancelled"
+//! error. Async handlers are dropped at their next await point, while blocking work
+//! should check the token between steps.
+
+use crate::mcp::{JsonRpcError, McpServerState, RequestId};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+pub use tokio_util::sync::CancellationToken;
+
+/// Method of cancellation notifications
+pub const CANCELLED_METHOD: &str = "notifications/cancelled";
+
+/// JSON-RPC error code of a cancelled request
+pub const REQUEST_CANCELLED: i32 = -32800;
+
+/// Tool calls in flight, keyed by session id (empty without a session) and request id
+pub type InFlightCalls = Arc<Mutex<HashMap<(String, RequestId), CancellationToken>>>;
+
+/// Parameters of notifications/cancelled
+#[derive(Debug, Deserialize)]
+pub struct CancelledNotification {
+    #[serde(rename = "requestId")]
+    pub request_id: RequestId,
+    pub reason: Option<String>,
+}
+
+/// Registration of a tool call in flight, removed when dropped
+pub struct InFlight {
+    calls: InFlightCalls,
+    key: (String, RequestId),
+    pub token: CancellationToken,
+}
+
+impl InFlight {
+    /// Register the request `request_id` of a session
+    pub fn register(
+        state: &McpServerState,
+        session_id: Option<&str>,
+        request_id: &RequestId,
+    ) -> Self {
+        let key = (
+            session_id.unwrap_or_default().to_string(),
+            request_id.clone(),
+        );
+        let token = CancellationToken::new();
+        if let Ok(mut calls) = state.in_flight.lock() {
+            calls.insert(key.clone(), token.clone());
+        }
+        Self {
+            calls: Arc::clone(&state.in_flight),
+            key,
+            token,
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.remove(&self.key);
+        }
+    }
+}
+
+/// Handle notifications/cancelled from a session
+///
+/// Only requests of the same session can be cancelled; unknown or finished requests
+/// are ignored, as the notification may cross the response.
+pub fn cancel(state: &McpServerState, session_id: &str, params: Option<Value>) {
+    let Some(notification) =
+        params.and_then(|params| serde_json::from_value::<CancelledNotification>(params).ok())
+    else {
+        return;
+    };
+    let request_id = notification.request_id;
+    let token = state.in_flight.lock().ok().and_then(|calls| {
+        calls
+            .get(&(session_id.to_string(), request_id.clone()))
+            .cloned()
+    });
+    if let Some(token) = token {
+        token.cancel();
+        crate::server_log::info(format!(
+            "Request {} cancelled by the client{}",
+            request_id,
+            notification
+                .reason
+                .map(|reason| format!(": {}", reason))
+                .unwrap_or_default()
+        ));
+    }
+}
+
+/// Error answering a cancelled request
+pub fn cancelled_error() -> JsonRpcError {
+    JsonRpcError {
+        code: REQUEST_CANCELLED,
+        message: "Request cancelled".to_string(),
+        data: None,
+    }
+}
@@ -0,0 +1,135 @@
+//! Self-check module
+//!
+//! This module runs the checks behind `mcp-server check`: scheduled jobs and webhooks
+//! refer to tools that exist, the allowed directories are readable, upstream MCP
+//! servers answer, and every registered tool passes its own check (Ollama models
+//! pulled, databases and mail servers reachable). Nothing is modified, so the command
+//! can run in CI or before a deployment goes live.
+
+use crate::mcp::McpServerState;
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+
+/// Outcome of one check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// What was checked, e.g. `tool summarize`
+    pub name: String,
+    /// Why the check failed; `None` when it passed
+    pub error: Option<String>,
+}
+
+/// Outcomes of every check, in the order they ran
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl CheckReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+
+    /// Number of failed checks
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.error.is_some())
+            .count()
+    }
+
+    fn record(&mut self, name: impl Into<String>, result: Result<(), String>) {
+        self.checks.push(CheckResult {
+            name: name.into(),
+            error: result.err(),
+        });
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.error {
+                None => writeln!(f, "ok    {}", check.name)?,
+                Some(error) => writeln!(f, "FAIL  {}: {}", check.name, error)?,
+            }
+        }
+        write!(
+            f,
+            "{} checks, {} failed",
+            self.checks.len(),
+            self.failures()
+        )
+    }
+}
+
+/// Run every check against a configured server
+pub async fn run(state: &McpServerState) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    for job in &state.config.schedule {
+        let result = match crate::scheduler::parse_schedule(&job.cron) {
+            Ok(_) => tool_exists(state, &job.tool).await,
+            Err(e) => Err(e.to_string()),
+        };
+        report.record(format!("schedule {}", job.name), result);
+    }
+    for webhook in &state.config.webhook {
+        report.record(
+            format!("webhook {}", webhook.name),
+            tool_exists(state, &webhook.tool).await,
+        );
+    }
+
+    for root in crate::file_read::ALLOWED_ROOTS {
+        report.record(format!("allowed directory {}", root), readable(root));
+    }
+
+    if let Some(gateway) = &state.gateway {
+        // Listing the tools probes every upstream and records its health
+        gateway.list_tools().await;
+        for upstream in gateway.status().upstreams {
+            let result = match upstream.healthy {
+                Some(true) => Ok(()),
+                _ => Err(upstream.error.unwrap_or_else(|| "No response".to_string())),
+            };
+            report.record(format!("upstream {}", upstream.name), result);
+        }
+    }
+
+    let mut tools: Vec<_> = state
+        .tools
+        .read()
+        .await
+        .values()
+        .map(|tool| Arc::clone(&tool.handler))
+        .collect();
+    tools.sort_by(|a, b| a.name().cmp(b.name()));
+    for tool in tools {
+        report.record(format!("tool {}", tool.name()), tool.check(state).await);
+    }
+
+    report
+}
+
+/// Check that a tool referenced by the configuration is served
+async fn tool_exists(state: &McpServerState, name: &str) -> Result<(), String> {
+    let upstream = state
+        .gateway
+        .as_ref()
+        .is_some_and(|gateway| gateway.owns(name));
+    if upstream || state.get_tool(name).await.is_some() {
+        Ok(())
+    } else {
+        Err(format!("Tool '{}' is not registered", name))
+    }
+}
+
+/// Check that a directory exists and its entries can be listed
+fn readable(path: &str) -> Result<(), String> {
+    fs::read_dir(path)
+        .map(drop)
+        .map_err(|e| format!("Cannot read '{}': {}", path, e))
+}
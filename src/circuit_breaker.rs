@@ -0,0 +1,298 @@
+//! Circuit breaker module
+//!
+//! This module keeps requests away from a backend that keeps failing. After a number
+//! of consecutive failures the circuit opens and requests fail immediately with a
+//! clear error instead of waiting for a timeout. Once the reset timeout has passed a
+//! single probe request is let through (half-open): its success closes the circuit,
+//! its failure opens it again. Requests hold a [`Permit`] that records their outcome,
+//! so a probe abandoned midway, such as a cancelled call, counts as a failure rather
+//! than keeping the circuit half-open.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Consecutive failures after which a circuit opens
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// Time an open circuit waits before letting a probe request through
+pub const DEFAULT_RESET_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Breakers shared by every client of the same endpoint
+static SHARED: Mutex<BTreeMap<String, Arc<CircuitBreaker>>> = Mutex::new(BTreeMap::new());
+
+/// State of a circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests are sent
+    Closed,
+    /// Requests fail immediately
+    Open,
+    /// A probe request decides whether the circuit closes again
+    HalfOpen,
+}
+
+/// Error returned for requests rejected by an open circuit
+#[derive(Debug, Clone)]
+pub struct CircuitOpen {
+    pub name: String,
+    pub failures: u32,
+    /// Time until a probe request is let through; `None` while a probe is in flight
+    pub retry_in: Option<Duration>,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is unavailable after {} consecutive failures",
+            self.name, self.failures
+        )?;
+        match self.retry_in {
+            Some(retry_in) => write!(f, "; retrying in {}s", retry_in.as_secs().max(1)),
+            None => write!(f, "; a probe request is in progress"),
+        }
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// A circuit breaker guarding one backend
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// Backend name, for error messages
+    name: String,
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    inner: Mutex<BreakerState>,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Create a closed circuit
+    pub fn new(name: impl Into<String>, failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            name: name.into(),
+            failure_threshold: failure_threshold.max(1),
+            reset_timeout,
+            inner: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Check whether a request may be sent, returning the permit recording its outcome
+    ///
+    /// When the reset timeout of an open circuit has passed, the caller becomes the
+    /// probe; dropping its permit without recording an outcome opens the circuit again.
+    pub fn allow(&self) -> Result<Permit<'_>, CircuitOpen> {
+        let mut inner = self.lock();
+        let retry_in = match inner.state {
+            CircuitState::Closed => return Ok(Permit::new(self, false)),
+            CircuitState::Open => {
+                let elapsed = inner.opened_at.map(|at| at.elapsed()).unwrap_or_default();
+                if elapsed >= self.reset_timeout {
+                    inner.state = CircuitState::HalfOpen;
+                    return Ok(Permit::new(self, true));
+                }
+                Some(self.reset_timeout - elapsed)
+            }
+            CircuitState::HalfOpen => None,
+        };
+        Err(CircuitOpen {
+            name: self.name.clone(),
+            failures: inner.failures,
+            retry_in,
+        })
+    }
+
+    /// Record a successful request, closing the circuit
+    fn record_success(&self) {
+        let mut inner = self.lock();
+        inner.state = CircuitState::Closed;
+        inner.failures = 0;
+        inner.opened_at = None;
+    }
+
+    /// Record a failed request, opening the circuit at the threshold or after a failed probe
+    fn record_failure(&self) {
+        let mut inner = self.lock();
+        inner.failures = inner.failures.saturating_add(1);
+        if inner.state == CircuitState::HalfOpen || inner.failures >= self.failure_threshold {
+            inner.state = CircuitState::Open;
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Let the next request probe again, without counting the probe's outcome
+    fn release_probe(&self) {
+        let mut inner = self.lock();
+        if inner.state == CircuitState::HalfOpen {
+            inner.state = CircuitState::Open;
+        }
+    }
+
+    /// Current state of the circuit
+    pub fn state(&self) -> CircuitState {
+        self.lock().state
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        // A panic while holding the lock cannot leave the state inconsistent
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Permission to send one request, recording its outcome in the breaker
+#[must_use = "the outcome of the request must be recorded"]
+#[derive(Debug)]
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    /// The request probes a half-open circuit
+    probe: bool,
+    resolved: bool,
+}
+
+impl<'a> Permit<'a> {
+    fn new(breaker: &'a CircuitBreaker, probe: bool) -> Self {
+        Self {
+            breaker,
+            probe,
+            resolved: false,
+        }
+    }
+
+    /// Record that the request succeeded, closing the circuit
+    pub fn record_success(mut self) {
+        self.resolved = true;
+        self.breaker.record_success();
+    }
+
+    /// Record that the request failed
+    pub fn record_failure(mut self) {
+        self.resolved = true;
+        self.breaker.record_failure();
+    }
+
+    /// Record nothing, for a request that ended for reasons of its own, such as the
+    /// caller's deadline; a probe lets the next request probe instead
+    pub fn release(mut self) {
+        self.resolved = true;
+        if self.probe {
+            self.breaker.release_probe();
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        // Without an outcome the backend cannot be trusted again yet
+        if !self.resolved && self.probe {
+            self.breaker.record_failure();
+        }
+    }
+}
+
+/// Get the breaker shared by every client of an endpoint, with the default settings
+///
+/// Used for clients that are created per request, such as the Ollama client.
+pub fn shared(endpoint: &str) -> Arc<CircuitBreaker> {
+    let mut breakers = SHARED.lock().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(breakers.entry(endpoint.to_string()).or_insert_with(|| {
+        Arc::new(CircuitBreaker::new(
+            endpoint,
+            DEFAULT_FAILURE_THRESHOLD,
+            DEFAULT_RESET_TIMEOUT,
+        ))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_breaker(reset_timeout: Duration) -> CircuitBreaker {
+        let breaker = CircuitBreaker::new("backend", 2, reset_timeout);
+        breaker.allow().unwrap().record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.allow().unwrap().record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        breaker
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures_and_rejects_until_the_reset_timeout() {
+        let breaker = CircuitBreaker::new("backend", 2, Duration::from_secs(60));
+        breaker.allow().unwrap().record_failure();
+        breaker.allow().unwrap().record_success();
+        breaker.allow().unwrap().record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        let breaker = open_breaker(Duration::from_secs(60));
+        let rejected = breaker.allow().unwrap_err();
+        assert_eq!(rejected.failures, 2);
+        assert!(rejected.retry_in.is_some());
+    }
+
+    #[test]
+    fn a_successful_probe_closes_the_circuit() {
+        let breaker = open_breaker(Duration::ZERO);
+        let probe = breaker.allow().unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        let rejected = breaker.allow().unwrap_err();
+        assert!(rejected.retry_in.is_none());
+
+        probe.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.allow().unwrap().record_success();
+    }
+
+    #[test]
+    fn a_failed_probe_opens_the_circuit_again() {
+        let breaker = open_breaker(Duration::from_millis(20));
+        std::thread::sleep(Duration::from_millis(20));
+        breaker.allow().unwrap().record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.allow().unwrap_err().retry_in.is_some());
+    }
+
+    #[test]
+    fn an_abandoned_probe_counts_as_a_failure() {
+        let breaker = open_breaker(Duration::ZERO);
+        drop(breaker.allow().unwrap());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        // The next request probes once the reset timeout passed again
+        breaker.allow().unwrap().record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn a_released_probe_lets_the_next_request_probe() {
+        let breaker = open_breaker(Duration::from_millis(20));
+        std::thread::sleep(Duration::from_millis(20));
+        breaker.allow().unwrap().release();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.lock().failures, 2);
+        let probe = breaker.allow().unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        probe.record_success();
+    }
+
+    #[test]
+    fn dropping_a_permit_of_a_closed_circuit_records_nothing() {
+        let breaker = CircuitBreaker::new("backend", 1, Duration::from_secs(60));
+        drop(breaker.allow().unwrap());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
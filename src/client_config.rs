@@ -0,0 +1,122 @@
+//! Client config module
+//!
+//! This module keeps the defaults of mcp-client in `config.toml`, next to the profiles
+//! file: the model, the MCP server and the profile used when the command line does not
+//! name them. The file is written by `mcp-client init`, which finds a local Ollama and
+//! the MCP servers listening on common local ports so new users start from a working
+//! setup instead of a list of flags.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Local ports probed for MCP servers: mcp-server's default, then those of common
+/// SDK examples and dev servers
+pub const COMMON_PORTS: &[u16] = &[8080, 3000, 3001, 8000, 8001, 8081, 9000];
+
+/// Time a probed port has to accept the connection and answer `initialize`
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Defaults of mcp-client
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    /// Model used without --model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// URL of the MCP server used when no server is given on the command line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mcp_server: Option<String>,
+    /// Profile used without --profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+impl ClientConfig {
+    /// Read the config; without a config file every default is unset
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path()?;
+        match fs::read_to_string(&path) {
+            Ok(text) => Ok(toml::from_str(&text)
+                .map_err(|e| format!("Invalid config '{}': {}", path.display(), e))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read '{}': {}", path.display(), e).into()),
+        }
+    }
+
+    /// Write the config, replacing the previous one
+    pub fn save(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self)?)
+            .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+/// Path of the config file
+pub fn path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(crate::profile::path()?.with_file_name("config.toml"))
+}
+
+/// An MCP server found on a local port
+#[derive(Debug, Clone)]
+pub struct DetectedServer {
+    pub url: String,
+    pub name: String,
+    pub version: String,
+}
+
+/// Look for MCP servers answering Streamable HTTP at `/mcp` on local ports
+pub fn detect_servers(ports: &[u16]) -> Vec<DetectedServer> {
+    ports.iter().filter_map(|port| probe(*port)).collect()
+}
+
+/// Open and close a session with the server on a port, if it is an MCP server
+fn probe(port: u16) -> Option<DetectedServer> {
+    TcpStream::connect_timeout(&SocketAddr::from(([127, 0, 0, 1], port)), PROBE_TIMEOUT).ok()?;
+    let url = format!("http://localhost:{}/mcp", port);
+    let client = reqwest::blocking::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .ok()?;
+    let response = client
+        .post(&url)
+        .header(
+            reqwest::header::ACCEPT,
+            "application/json, text/event-stream",
+        )
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": crate::mcp::LATEST_PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "mcp-client", "version": env!("CARGO_PKG_VERSION") }
+            }
+        }))
+        .send()
+        .ok()?;
+    let session = response.headers().get("mcp-session-id").cloned();
+    // Servers may answer with a JSON body or a one-event SSE stream
+    let body = response.text().ok()?;
+    if let Some(session) = session {
+        let _ = client.delete(&url).header("mcp-session-id", session).send();
+    }
+    let json = body
+        .lines()
+        .find_map(|line| line.strip_prefix("data:"))
+        .unwrap_or(&body);
+    let message: serde_json::Value = serde_json::from_str(json.trim()).ok()?;
+    let info = &message["result"]["serverInfo"];
+    Some(DetectedServer {
+        url,
+        name: info["name"].as_str()?.to_string(),
+        version: info["version"].as_str().unwrap_or_default().to_string(),
+    })
+}
@@ -0,0 +1,85 @@
+//! Clipboard tool module
+//!
+//! This module provides read and write access to the desktop clipboard. It is only
+//! compiled with the `clipboard` feature, and the tools are only registered when
+//! the server configuration enables them.
+
+use arboard::Clipboard;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Clipboard read tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClipboardReadRequest {}
+
+/// Clipboard read tool response
+#[derive(Debug, Serialize)]
+pub struct ClipboardReadResponse {
+    pub content: String,
+    pub size: usize,
+}
+
+/// Clipboard write tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClipboardWriteRequest {
+    /// The text to place on the clipboard
+    pub text: String,
+}
+
+/// Clipboard write tool response
+#[derive(Debug, Serialize)]
+pub struct ClipboardWriteResponse {
+    pub size: usize,
+}
+
+/// Execute the clipboard read tool
+pub fn execute_clipboard_read(
+    _request: ClipboardReadRequest,
+) -> Result<ClipboardReadResponse, Box<dyn std::error::Error>> {
+    let mut clipboard =
+        Clipboard::new().map_err(|e| format!("Failed to access clipboard: {}", e))?;
+    let content = clipboard
+        .get_text()
+        .map_err(|e| format!("Failed to read clipboard: {}", e))?;
+
+    Ok(ClipboardReadResponse {
+        size: content.len(),
+        content,
+    })
+}
+
+/// Execute the clipboard write tool
+pub fn execute_clipboard_write(
+    request: ClipboardWriteRequest,
+) -> Result<ClipboardWriteResponse, Box<dyn std::error::Error>> {
+    let mut clipboard =
+        Clipboard::new().map_err(|e| format!("Failed to access clipboard: {}", e))?;
+    let size = request.text.len();
+    clipboard
+        .set_text(request.text)
+        .map_err(|e| format!("Failed to write clipboard: {}", e))?;
+
+    Ok(ClipboardWriteResponse { size })
+}
+
+/// Get the tool definition for the clipboard_read tool
+pub fn get_read_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "clipboard_read".to_string(),
+        description: Some("Read the current text contents of the user's clipboard.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<ClipboardReadRequest>()),
+        ..Default::default()
+    }
+}
+
+/// Get the tool definition for the clipboard_write tool
+pub fn get_write_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "clipboard_write".to_string(),
+        description: Some(
+            "Replace the contents of the user's clipboard with the given text.".to_string(),
+        ),
+        input_schema: Some(crate::schema::input_schema::<ClipboardWriteRequest>()),
+        ..Default::default()
+    }
+}
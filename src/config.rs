@@ -0,0 +1,602 @@
+//! Server configuration module
+//!
+//! This module provides the configuration file format for the MCP server.
+//! Every optional capability is disabled unless the configuration enables it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Service name under which secrets are looked up in the OS keyring
+pub const KEYRING_SERVICE: &str = "mcp-server";
+
+/// Top-level server configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Limits on tool calls in flight, beyond which calls are rejected as busy
+    pub admission: AdmissionConfig,
+    /// API keys required of HTTP clients, each optionally limited to some tools
+    pub api_key: Vec<ApiKeyConfig>,
+    pub clipboard: ClipboardConfig,
+    /// Exchange rates for currency conversion in the convert tool
+    pub currency: CurrencyConfig,
+    /// Downloads by the url_download tool
+    pub download: DownloadConfig,
+    pub email: EmailConfig,
+    /// Destinations for server events such as tool call failures
+    pub event_sink: Vec<EventSinkConfig>,
+    /// Time and concurrency limits of tool calls
+    pub execution: ExecutionConfig,
+    pub feed: FeedConfig,
+    /// Access of the file tools to the allowed directories
+    pub files: FilesConfig,
+    /// Translations of tool descriptions and errors
+    pub i18n: I18nConfig,
+    /// Journal of background jobs (transfers and scheduled runs)
+    pub jobs: JobsConfig,
+    /// Recent log messages kept for the mcp://server/logs resource
+    pub log: LogConfig,
+    pub memory: MemoryConfig,
+    /// Per-consumer outbound notification queues
+    pub notifications: NotificationsConfig,
+    /// Page size of tools/list and resources/list
+    pub pagination: PaginationConfig,
+    pub postgres: PostgresConfig,
+    /// Prompt templates served through prompts/list and prompts/get
+    pub prompt: Vec<PromptConfig>,
+    /// GitHub/GitLab repositories reachable by the repository tools
+    pub repository: Vec<RepositoryConfig>,
+    /// Restrictions on the subprocesses the server starts
+    pub sandbox: SandboxConfig,
+    /// Tools run on a cron schedule
+    pub schedule: Vec<ScheduleConfig>,
+    pub screenshot: ScreenshotConfig,
+    pub speech: SpeechConfig,
+    pub summarize: SummarizeConfig,
+    /// Remote hosts reachable by the SSH tools
+    pub ssh_host: Vec<SshHostConfig>,
+    /// Backend of the translate tool, which is registered when one is configured
+    pub translate: Option<TranslateConfig>,
+    /// MCP servers whose tools are served through this server
+    pub upstream: Vec<UpstreamConfig>,
+    /// Endpoints under /webhooks/{name} that trigger tool calls
+    pub webhook: Vec<WebhookConfig>,
+}
+
+impl ServerConfig {
+    /// Load the configuration from a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config '{}': {}", path.display(), e))?;
+        let config = toml::from_str(&text)
+            .map_err(|e| format!("Invalid config '{}': {}", path.display(), e))?;
+        Ok(config)
+    }
+}
+
+/// Admission control for tools/call requests
+///
+/// Calls beyond a limit are rejected with a busy error instead of queuing. Both
+/// limits are unset (unlimited) by default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdmissionConfig {
+    /// Tool calls in flight across the server
+    pub max_in_flight: Option<usize>,
+    /// Tool calls in flight per session
+    pub max_in_flight_per_session: Option<usize>,
+}
+
+/// An API key accepted from HTTP clients
+///
+/// One of `key` or `key_env` must be set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// Name of the key, for messages and logs
+    pub name: String,
+    /// The key, expected as a bearer token or X-API-Key header
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Environment variable holding the key, preferred over `key`
+    #[serde(default)]
+    pub key_env: Option<String>,
+    /// Tools the key may list and call, by name or prefix ending in `*`
+    ///
+    /// A key setting `tools`, `resources` or `methods` is limited to what they name:
+    /// the ones it leaves out permit nothing. A key setting none of them may do
+    /// anything.
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// Resources the key may list, read and subscribe to, by URI or prefix ending in
+    /// `*`, such as `file:///tmp/allowed_files/reports/*`
+    #[serde(default)]
+    pub resources: Option<Vec<String>>,
+    /// Methods the key may call besides initialize, ping, tools/* and resources/*, by
+    /// name or prefix ending in `*`, such as `prompts/*` or `server/info`
+    #[serde(default)]
+    pub methods: Option<Vec<String>>,
+}
+
+/// Clipboard tool configuration (requires the `clipboard` feature)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipboardConfig {
+    /// Register the clipboard_read tool
+    pub read: bool,
+    /// Register the clipboard_write tool
+    pub write: bool,
+}
+
+/// Exchange rates used by the convert tool for currency conversion
+///
+/// Rates are fetched from `rates_url` when set, otherwise taken from `rates`.
+/// Currency conversion is unavailable when neither is configured.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CurrencyConfig {
+    /// Currency the rates are relative to, e.g. "EUR"
+    pub base: Option<String>,
+    /// Units of each currency per unit of `base`
+    pub rates: BTreeMap<String, f64>,
+    /// JSON endpoint returning `{"base": "...", "rates": {...}}`, e.g. https://api.frankfurter.app/latest
+    pub rates_url: Option<String>,
+    /// How long fetched rates are reused (default 3600)
+    pub cache_secs: Option<u64>,
+}
+
+/// Email tool configuration (requires the `email` feature)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmailConfig {
+    /// Register the email_send tool
+    pub enabled: bool,
+    /// SMTP server host name
+    pub host: Option<String>,
+    /// SMTP port (default 587 with STARTTLS, 465 otherwise)
+    pub port: Option<u16>,
+    /// Use STARTTLS instead of implicit TLS (default true)
+    pub starttls: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Environment variable holding the password, preferred over `password`
+    pub password_env: Option<String>,
+    /// Sender address, e.g. "Assistant <bot@example.com>"
+    pub from: Option<String>,
+    /// Allowed recipients: full addresses or whole domains written as "@example.com"
+    pub allowed_recipients: Vec<String>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: None,
+            port: None,
+            starttls: true,
+            username: None,
+            password: None,
+            password_env: None,
+            from: None,
+            allowed_recipients: Vec::new(),
+        }
+    }
+}
+
+/// A destination for server events
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventSinkConfig {
+    #[serde(rename = "type")]
+    pub kind: EventSinkKind,
+    /// URL events are POSTed to (webhook sinks)
+    #[serde(default)]
+    pub url: Option<String>,
+    /// File events are appended to as JSON lines (file sinks)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Only deliver these kinds of events; all kinds when empty
+    #[serde(default)]
+    pub events: Vec<crate::events::EventKind>,
+    /// Only deliver events about these tools; all events when empty
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+/// Supported event sink types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSinkKind {
+    Webhook,
+    File,
+    Stdout,
+}
+
+/// URL download tool configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DownloadConfig {
+    /// Domains files may be downloaded from (subdomains included); the
+    /// url_download tool is only registered when this list is non-empty
+    pub allowed_domains: Vec<String>,
+    /// Upper bound on the size of a download in bytes (default 10 GiB)
+    pub max_bytes: Option<u64>,
+}
+
+/// Limits on the execution of tool calls, see [`crate::execution`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutionConfig {
+    /// Longest a tool call may run in milliseconds; unlimited when unset
+    pub timeout_ms: Option<u64>,
+    /// Time limits of specific tools in milliseconds, overriding `timeout_ms`
+    pub tool_timeout_ms: HashMap<String, u64>,
+    /// Tool calls executing at once; further calls wait for a slot (unlimited when unset)
+    pub max_concurrent: Option<usize>,
+}
+
+/// Feed reading tool configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedConfig {
+    /// Domains feeds may be fetched from (subdomains included); the feed_read
+    /// tool is only registered when this list is non-empty
+    pub allowed_domains: Vec<String>,
+}
+
+/// File tool configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    /// Refuse every write of the file tools, such as render_template and url_download
+    /// output
+    pub read_only: bool,
+}
+
+/// Localization configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct I18nConfig {
+    /// Directory of `<language>.toml` catalogs, e.g. `de.toml`
+    pub catalog_dir: Option<String>,
+}
+
+/// Background job configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    /// File journaling the state changes of transfers and scheduled runs, so they are
+    /// reported after a restart; jobs are only kept in memory when unset
+    pub journal: Option<String>,
+}
+
+/// Server log configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Messages kept in memory (default 1000)
+    pub capacity: Option<usize>,
+    /// Serve the kept messages as the mcp://server/logs resource (default true); they
+    /// may name files and arguments of other clients' tool calls
+    pub resource: Option<bool>,
+}
+
+/// Memory tool configuration
+///
+/// The memory_ingest and memory_search tools are registered when an index path is
+/// configured.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemoryConfig {
+    /// JSON file holding the vector index
+    pub path: Option<String>,
+    /// Ollama server used for embeddings (default "http://localhost:11434")
+    pub ollama_url: Option<String>,
+    /// Embedding model (default "nomic-embed-text")
+    pub model: Option<String>,
+}
+
+/// Outbound notification queue configuration
+///
+/// Every consumer of server notifications (e.g. an SSE stream) gets its own bounded
+/// queue so a slow consumer cannot make the server buffer without limit.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Notifications held per consumer (default 1000)
+    pub queue_capacity: Option<usize>,
+    /// What a full queue does: "drop_oldest" (default), "coalesce_list_changed" or "disconnect"
+    pub overflow: crate::notification_queue::OverflowPolicy,
+}
+
+/// Pagination of list results
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaginationConfig {
+    /// Items per page of tools/list and resources/list (default 100)
+    pub page_size: Option<usize>,
+}
+
+/// PostgreSQL query tool configuration (requires the `postgres` feature)
+///
+/// The pg_query tool is registered when a connection URL is configured.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostgresConfig {
+    /// Connection URL, e.g. "postgres://reader@localhost/app"
+    pub url: Option<String>,
+    /// Environment variable holding the connection URL, preferred over `url`
+    pub url_env: Option<String>,
+    /// Upper bound on the rows returned per query (default 100)
+    pub max_rows: Option<usize>,
+    /// Connection and statement timeout in seconds (default 10)
+    pub timeout_secs: Option<u64>,
+}
+
+/// A prompt template offered to clients
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptConfig {
+    /// Unique prompt name, e.g. "code_review"
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Arguments the user fills in, available to the message templates
+    #[serde(default)]
+    pub arguments: Vec<crate::prompts::PromptArgument>,
+    /// Messages of the prompt, in order
+    pub messages: Vec<PromptMessageConfig>,
+}
+
+/// A message of a prompt template
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptMessageConfig {
+    /// "user" (default) or "assistant"
+    #[serde(default)]
+    pub role: crate::prompts::Role,
+    /// minijinja template over the prompt arguments
+    pub text: String,
+}
+
+/// A GitHub or GitLab repository reachable by the repository tools
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepositoryConfig {
+    /// Name the tools use to refer to the repository
+    pub name: String,
+    pub provider: RepositoryProvider,
+    /// "owner/name" on GitHub, the full project path on GitLab
+    pub repo: String,
+    /// API base URL for self-hosted instances, e.g. "https://gitlab.example.com/api/v4"
+    #[serde(default)]
+    pub api_url: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Environment variable holding the token, preferred over `token`
+    #[serde(default)]
+    pub token_env: Option<String>,
+    /// OS keyring entry (service "mcp-server") holding the token; requires the `keyring` feature
+    #[serde(default)]
+    pub token_keyring: Option<String>,
+}
+
+/// Supported repository hosting APIs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepositoryProvider {
+    Github,
+    Gitlab,
+}
+
+/// A tool invocation run on a cron schedule
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// Unique job name; the result is stored as `schedule://<name>`
+    pub name: String,
+    /// Cron expression, e.g. "*/15 * * * *" (a leading seconds field is optional)
+    pub cron: String,
+    /// Registered tool to call
+    pub tool: String,
+    /// Arguments passed to the tool
+    #[serde(default)]
+    pub arguments: Option<serde_json::Value>,
+}
+
+/// Sandbox of subprocesses (requires the `sandbox` feature, Linux only)
+///
+/// Applies to stdio upstreams and screenshot utilities. The allowed roots and the
+/// temporary directory are always writable; unset limits are left as inherited.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Run subprocesses in the sandbox
+    pub enabled: bool,
+    /// Paths subprocesses may read and execute from (default /bin, /dev, /etc, /lib,
+    /// /lib64, /proc, /sbin, /usr)
+    pub read_only: Option<Vec<String>>,
+    /// Further paths subprocesses may write to
+    pub read_write: Vec<String>,
+    /// System calls refused with EPERM (default: module, kernel, mount, namespace,
+    /// tracing and reboot calls); an empty list disables syscall filtering
+    pub deny_syscalls: Option<Vec<String>>,
+    /// Address space limit in MiB
+    pub max_memory_mb: Option<u64>,
+    /// CPU time limit in seconds
+    pub max_cpu_secs: Option<u64>,
+    /// Largest file a subprocess may write, in MiB
+    pub max_file_size_mb: Option<u64>,
+    pub max_open_files: Option<u64>,
+    /// Processes of the server's user, counting those already running
+    pub max_processes: Option<u64>,
+}
+
+/// Screenshot tool configuration (requires the `screenshot` feature)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreenshotConfig {
+    /// Register the screenshot tool
+    pub enabled: bool,
+}
+
+/// Speech tool configuration (requires the `speech` feature)
+///
+/// Each tool is registered only when an engine is configured for it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeechConfig {
+    /// Engine backing the tts tool
+    pub tts: Option<SpeechEngineConfig>,
+    /// Engine backing the stt tool
+    pub stt: Option<SpeechEngineConfig>,
+}
+
+/// Configuration of a single speech engine
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpeechEngineConfig {
+    pub engine: SpeechEngineKind,
+    /// Base URL of the engine, e.g. http://localhost:8081
+    pub url: String,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    pub voice: Option<String>,
+}
+
+/// Supported speech engine backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechEngineKind {
+    /// A local whisper.cpp server (speech-to-text only)
+    WhisperCpp,
+    /// An OpenAI-compatible HTTP speech service
+    Http,
+}
+
+/// Summarization tool configuration
+///
+/// The summarize tool is registered when a model is configured.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SummarizeConfig {
+    /// Ollama model used for summaries, e.g. "llama3.2"
+    pub model: Option<String>,
+    /// Ollama server (default "http://localhost:11434")
+    pub ollama_url: Option<String>,
+}
+
+/// A host reachable by the SSH tools (requires the `ssh` feature)
+///
+/// The host key must be present in the known_hosts file. Without `identity_file`
+/// the SSH agent of `SSH_AUTH_SOCK` is used for authentication, on Unix only.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SshHostConfig {
+    /// Name the tools use to refer to the host
+    pub name: String,
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    pub user: String,
+    /// Private key file, e.g. "~/.ssh/id_ed25519"
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// Environment variable holding the key passphrase
+    #[serde(default)]
+    pub passphrase_env: Option<String>,
+    /// known_hosts file used to verify the host (default "~/.ssh/known_hosts")
+    #[serde(default)]
+    pub known_hosts: Option<String>,
+    /// Commands remote_exec may run, matched on their leading words
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+    /// Directories remote_file_read may read from
+    #[serde(default)]
+    pub allowed_paths: Vec<String>,
+}
+
+/// Translation backend configuration
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TranslateConfig {
+    pub engine: TranslatorKind,
+    /// Base URL of the backend (default http://localhost:11434 for Ollama,
+    /// http://localhost:5000 for LibreTranslate)
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Ollama model (default "llama3.2")
+    #[serde(default)]
+    pub model: Option<String>,
+    /// LibreTranslate API key
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+/// Supported translation backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranslatorKind {
+    /// A model served by Ollama
+    Ollama,
+    /// A LibreTranslate-compatible HTTP service
+    LibreTranslate,
+}
+
+/// An upstream MCP server fronted by the gateway
+///
+/// Exactly one of `url` (Streamable HTTP) or `command` (stdio) must be set. Its tools
+/// are listed as `<name>__<tool>`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamConfig {
+    pub name: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Time allowed for each request to the upstream (default 10)
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Consecutive failures after which requests fail fast (default 5)
+    #[serde(default)]
+    pub failure_threshold: Option<u32>,
+    /// Seconds before a failing upstream is probed again (default 30)
+    #[serde(default)]
+    pub reset_secs: Option<u64>,
+}
+
+/// A webhook endpoint mapping incoming payloads to a tool call
+///
+/// At least one of `token`/`token_env` or `secret`/`secret_env` must be set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Endpoint name, served at /webhooks/{name}
+    pub name: String,
+    /// Registered tool to call
+    pub tool: String,
+    /// Argument template; strings may reference payload values as "{{ path.to.value }}"
+    #[serde(default)]
+    pub arguments: Option<serde_json::Value>,
+    /// Shared token expected as a bearer token or X-Webhook-Token header
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Environment variable holding the token, preferred over `token`
+    #[serde(default)]
+    pub token_env: Option<String>,
+    /// HMAC secret for GitHub-style X-Hub-Signature-256 signatures
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Environment variable holding the secret, preferred over `secret`
+    #[serde(default)]
+    pub secret_env: Option<String>,
+    /// Name of an `[[api_key]]` whose tool limits apply to the webhook's calls
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
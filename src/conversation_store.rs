@@ -0,0 +1,303 @@
+//! Conversation store module
+//!
+//! This module keeps the conversations of mcp-client in a SQLite database, so past
+//! work can be found again after the client exits. Each conversation is stored with
+//! its model, server, title (the start of its first user message) and times, and every
+//! message with its role, tool calls and time. Message contents are indexed for full
+//! text search. The database lives in the user's data directory
+//! (`$XDG_DATA_HOME/mcp-client/history.db`, or `~/.local/share/mcp-client/history.db`).
+
+use crate::ollama::ChatMessage;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Row, params};
+use std::path::{Path, PathBuf};
+
+/// Characters of the first user message kept as a conversation's title
+const TITLE_CHARS: usize = 60;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS conversations (
+    id TEXT PRIMARY KEY,
+    model TEXT NOT NULL,
+    server TEXT,
+    title TEXT,
+    started_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+    id INTEGER PRIMARY KEY,
+    conversation_id TEXT NOT NULL REFERENCES conversations(id),
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    tool_calls TEXT,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS messages_by_conversation ON messages(conversation_id, id);
+CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts
+    USING fts5(content, content='messages', content_rowid='id');
+CREATE TRIGGER IF NOT EXISTS messages_indexed AFTER INSERT ON messages BEGIN
+    INSERT INTO messages_fts(rowid, content) VALUES (new.id, new.content);
+END;
+";
+
+/// A stored conversation, without its messages
+#[derive(Debug, Clone)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub model: String,
+    /// The MCP server the client was connected to
+    pub server: Option<String>,
+    pub title: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub message_count: usize,
+}
+
+/// A stored message
+#[derive(Debug, Clone)]
+pub struct StoredMessage {
+    pub message: ChatMessage,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A message matching a search
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub conversation_id: String,
+    pub title: Option<String>,
+    pub role: String,
+    /// Part of the message around the match, with the matched terms in brackets
+    pub snippet: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// SQLite database of conversations
+pub struct ConversationStore {
+    conn: Connection,
+}
+
+impl ConversationStore {
+    /// Open the database at `path`, creating it if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path).map_err(|e| {
+            format!(
+                "Failed to open conversation history '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Open the database in the user's data directory
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::open(default_path()?)
+    }
+
+    /// Start a conversation with `model`, returning its id
+    pub fn start(
+        &self,
+        model: &str,
+        server: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        self.conn.execute(
+            "INSERT INTO conversations (id, model, server, started_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?4)",
+            params![id, model, server, now],
+        )?;
+        Ok(id)
+    }
+
+    /// Add a message to a conversation; the first user message also becomes its title
+    pub fn append(
+        &self,
+        conversation_id: &str,
+        message: &ChatMessage,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Utc::now();
+        let tool_calls = message
+            .tool_calls
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        self.conn.execute(
+            "INSERT INTO messages (conversation_id, role, content, tool_calls, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                conversation_id,
+                message.role,
+                message.content,
+                tool_calls,
+                now
+            ],
+        )?;
+        let title = (message.role == "user").then(|| title(&message.content));
+        self.conn.execute(
+            "UPDATE conversations SET updated_at = ?2, title = COALESCE(title, ?3)
+             WHERE id = ?1",
+            params![conversation_id, now, title],
+        )?;
+        Ok(())
+    }
+
+    /// The most recently updated conversations, newest first
+    pub fn list(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<ConversationSummary>, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {} FROM conversations c
+             ORDER BY c.updated_at DESC
+             LIMIT ?1",
+            SUMMARY_COLUMNS
+        ))?;
+        let conversations = statement
+            .query_map(params![limit as i64], summary)?
+            .collect::<Result<_, _>>()?;
+        Ok(conversations)
+    }
+
+    /// Messages containing every word of `query`, best matches first
+    pub fn search(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>, Box<dyn std::error::Error>> {
+        // Quote the words so characters such as '-' or ':' are not read as FTS5 syntax
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Err("The search query is empty".into());
+        }
+        let mut statement = self.conn.prepare(
+            "SELECT m.conversation_id, c.title, m.role,
+                    snippet(messages_fts, 0, '[', ']', '...', 12), m.created_at
+             FROM messages_fts
+             JOIN messages m ON m.id = messages_fts.rowid
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE messages_fts MATCH ?1
+             ORDER BY rank
+             LIMIT ?2",
+        )?;
+        let hits = statement
+            .query_map(params![terms.join(" "), limit as i64], |row| {
+                Ok(SearchHit {
+                    conversation_id: row.get(0)?,
+                    title: row.get(1)?,
+                    role: row.get(2)?,
+                    snippet: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(hits)
+    }
+
+    /// The conversation whose id is or starts with `id`
+    ///
+    /// A prefix must match a single conversation.
+    pub fn find(&self, id: &str) -> Result<ConversationSummary, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {} FROM conversations c
+             WHERE substr(c.id, 1, length(?1)) = ?1
+             LIMIT 2",
+            SUMMARY_COLUMNS
+        ))?;
+        let mut conversations: Vec<ConversationSummary> = statement
+            .query_map(params![id], summary)?
+            .collect::<Result<_, _>>()?;
+        match conversations.len() {
+            0 => Err(format!("No conversation with id '{}'", id).into()),
+            1 => Ok(conversations.remove(0)),
+            _ => Err(format!("Several conversations start with '{}'", id).into()),
+        }
+    }
+
+    /// The messages of a conversation, in order
+    pub fn messages(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Vec<StoredMessage>, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT role, content, tool_calls, created_at FROM messages
+             WHERE conversation_id = ?1
+             ORDER BY id",
+        )?;
+        let rows = statement
+            .query_map(params![conversation_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, DateTime<Utc>>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut messages = Vec::with_capacity(rows.len());
+        for (role, content, tool_calls, created_at) in rows {
+            messages.push(StoredMessage {
+                message: ChatMessage {
+                    role,
+                    content,
+                    tool_calls: tool_calls
+                        .map(|calls| serde_json::from_str(&calls))
+                        .transpose()?,
+                },
+                created_at,
+            });
+        }
+        Ok(messages)
+    }
+}
+
+/// Columns of [`summary`], selected from `conversations c`
+const SUMMARY_COLUMNS: &str = "c.id, c.model, c.server, c.title, c.started_at, c.updated_at,
+    (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)";
+
+fn summary(row: &Row) -> rusqlite::Result<ConversationSummary> {
+    Ok(ConversationSummary {
+        id: row.get(0)?,
+        model: row.get(1)?,
+        server: row.get(2)?,
+        title: row.get(3)?,
+        started_at: row.get(4)?,
+        updated_at: row.get(5)?,
+        message_count: row.get::<_, i64>(6)? as usize,
+    })
+}
+
+/// Path of the conversation database
+pub fn default_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .ok_or("Cannot locate the home directory for the conversation history")?;
+            PathBuf::from(home).join(".local").join("share")
+        }
+    };
+    Ok(base.join("mcp-client").join("history.db"))
+}
+
+/// Title of a conversation: the first line of its first user message, shortened
+fn title(content: &str) -> String {
+    let line = content.trim().lines().next().unwrap_or_default();
+    if line.chars().count() > TITLE_CHARS {
+        let short: String = line.chars().take(TITLE_CHARS - 3).collect();
+        format!("{}...", short.trim_end())
+    } else {
+        line.to_string()
+    }
+}
@@ -0,0 +1,391 @@
+//! Conversion tool module
+//!
+//! This module converts values between units of length, mass, temperature and data
+//! size, and between currencies when exchange rates are configured, so agents get
+//! exact results instead of doing the arithmetic themselves. Rates fetched from a
+//! rates endpoint are cached for a configurable time.
+
+use crate::config::CurrencyConfig;
+use reqwest::blocking::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default lifetime of fetched exchange rates
+const DEFAULT_CACHE_SECS: u64 = 3600;
+
+/// Results are rounded to this many significant digits to hide floating point noise
+const SIGNIFICANT_DIGITS: i32 = 12;
+
+/// Exchange rates fetched from the configured endpoint and when they were fetched
+static RATES_CACHE: Mutex<Option<(Instant, ExchangeRates)>> = Mutex::new(None);
+
+/// A unit of measurement
+///
+/// A value `v` in this unit is `(v + offset) * factor` in the dimension's base unit.
+struct Unit {
+    symbol: &'static str,
+    /// Alternative names, matched case-insensitively
+    names: &'static [&'static str],
+    dimension: &'static str,
+    factor: f64,
+    offset: f64,
+}
+
+const fn unit(
+    symbol: &'static str,
+    names: &'static [&'static str],
+    dimension: &'static str,
+    factor: f64,
+) -> Unit {
+    Unit {
+        symbol,
+        names,
+        dimension,
+        factor,
+        offset: 0.0,
+    }
+}
+
+/// Supported units; base units are metre, kilogram, kelvin and byte
+#[rustfmt::skip]
+const UNITS: &[Unit] = &[
+    unit("nm", &["nanometer", "nanometre", "nanometers", "nanometres"], "length", 1e-9),
+    unit("µm", &["um", "micrometer", "micrometre", "micron", "microns"], "length", 1e-6),
+    unit("mm", &["millimeter", "millimetre", "millimeters", "millimetres"], "length", 1e-3),
+    unit("cm", &["centimeter", "centimetre", "centimeters", "centimetres"], "length", 1e-2),
+    unit("m", &["meter", "metre", "meters", "metres"], "length", 1.0),
+    unit("km", &["kilometer", "kilometre", "kilometers", "kilometres"], "length", 1e3),
+    unit("in", &["inch", "inches"], "length", 0.0254),
+    unit("ft", &["foot", "feet"], "length", 0.3048),
+    unit("yd", &["yard", "yards"], "length", 0.9144),
+    unit("mi", &["mile", "miles"], "length", 1609.344),
+    unit("nmi", &["nautical mile", "nautical miles"], "length", 1852.0),
+    unit("mg", &["milligram", "milligrams"], "mass", 1e-6),
+    unit("g", &["gram", "grams"], "mass", 1e-3),
+    unit("kg", &["kilogram", "kilograms", "kilo", "kilos"], "mass", 1.0),
+    unit("t", &["tonne", "tonnes", "metric ton", "metric tons"], "mass", 1e3),
+    unit("oz", &["ounce", "ounces"], "mass", 0.028349523125),
+    unit("lb", &["lbs", "pound", "pounds"], "mass", 0.45359237),
+    unit("st", &["stone", "stones"], "mass", 6.35029318),
+    Unit { offset: 273.15, ..unit("°C", &["c", "celsius", "degc"], "temperature", 1.0) },
+    Unit { offset: 459.67, ..unit("°F", &["f", "fahrenheit", "degf"], "temperature", 5.0 / 9.0) },
+    unit("K", &["kelvin", "kelvins"], "temperature", 1.0),
+    unit("°R", &["r", "rankine"], "temperature", 5.0 / 9.0),
+    unit("bit", &["bits"], "data", 0.125),
+    unit("kbit", &["kilobit", "kilobits"], "data", 125.0),
+    unit("Mbit", &["megabit", "megabits"], "data", 125e3),
+    unit("Gbit", &["gigabit", "gigabits"], "data", 125e6),
+    unit("B", &["byte", "bytes"], "data", 1.0),
+    unit("kB", &["KB", "kilobyte", "kilobytes"], "data", 1e3),
+    unit("MB", &["megabyte", "megabytes"], "data", 1e6),
+    unit("GB", &["gigabyte", "gigabytes"], "data", 1e9),
+    unit("TB", &["terabyte", "terabytes"], "data", 1e12),
+    unit("PB", &["petabyte", "petabytes"], "data", 1e15),
+    unit("KiB", &["kibibyte", "kibibytes"], "data", 1024.0),
+    unit("MiB", &["mebibyte", "mebibytes"], "data", 1048576.0),
+    unit("GiB", &["gibibyte", "gibibytes"], "data", 1073741824.0),
+    unit("TiB", &["tebibyte", "tebibytes"], "data", 1099511627776.0),
+    unit("PiB", &["pebibyte", "pebibytes"], "data", 1125899906842624.0),
+];
+
+/// Convert tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ConvertRequest {
+    /// The value to convert
+    pub value: f64,
+    /// Unit or currency of the value, e.g. "km", "pounds", "°F", "GiB" or "USD"
+    pub from: String,
+    /// Unit or currency to convert to
+    pub to: String,
+}
+
+/// Convert tool response
+#[derive(Debug, Serialize)]
+pub struct ConvertResponse {
+    pub value: f64,
+    pub from: String,
+    pub to: String,
+    pub result: f64,
+    /// length, mass, temperature, data or currency
+    pub dimension: String,
+    /// Exchange rate from `from` to `to`, for currency conversions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+    /// Where the exchange rates came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rates_source: Option<String>,
+}
+
+/// Exchange rates relative to a base currency
+#[derive(Debug, Clone)]
+struct ExchangeRates {
+    base: String,
+    rates: BTreeMap<String, f64>,
+    source: String,
+}
+
+impl ExchangeRates {
+    /// Units of `code` per unit of the base currency
+    fn rate(&self, code: &str) -> Option<f64> {
+        if code == self.base {
+            Some(1.0)
+        } else {
+            self.rates.get(code).copied()
+        }
+    }
+}
+
+/// A rates endpoint response
+#[derive(Deserialize)]
+struct RatesResponse {
+    #[serde(alias = "base_code")]
+    base: Option<String>,
+    rates: BTreeMap<String, f64>,
+}
+
+/// Execute the convert tool
+pub fn execute_convert(
+    request: ConvertRequest,
+    currency: &CurrencyConfig,
+) -> Result<ConvertResponse, Box<dyn std::error::Error>> {
+    if !request.value.is_finite() {
+        return Err("value must be a finite number".into());
+    }
+
+    match (find_unit(&request.from)?, find_unit(&request.to)?) {
+        (Some(from), Some(to)) => {
+            if from.dimension != to.dimension {
+                return Err(format!(
+                    "Cannot convert {} ({}) to {} ({})",
+                    from.symbol, from.dimension, to.symbol, to.dimension
+                )
+                .into());
+            }
+            let base = (request.value + from.offset) * from.factor;
+            Ok(ConvertResponse {
+                value: request.value,
+                from: from.symbol.to_string(),
+                to: to.symbol.to_string(),
+                result: round_significant(base / to.factor - to.offset),
+                dimension: from.dimension.to_string(),
+                rate: None,
+                rates_source: None,
+            })
+        }
+        _ if is_currency_code(&request.from) && is_currency_code(&request.to) => {
+            convert_currency(request, currency)
+        }
+        (None, _) => Err(format!("Unknown unit '{}'", request.from).into()),
+        (_, None) => Err(format!("Unknown unit '{}'", request.to).into()),
+    }
+}
+
+/// Convert between currencies using the configured exchange rates
+fn convert_currency(
+    request: ConvertRequest,
+    config: &CurrencyConfig,
+) -> Result<ConvertResponse, Box<dyn std::error::Error>> {
+    let rates = exchange_rates(config)?;
+    let from = request.from.to_ascii_uppercase();
+    let to = request.to.to_ascii_uppercase();
+    let from_rate = rates
+        .rate(&from)
+        .ok_or_else(|| format!("No exchange rate for '{}'", from))?;
+    let to_rate = rates
+        .rate(&to)
+        .ok_or_else(|| format!("No exchange rate for '{}'", to))?;
+
+    let rate = to_rate / from_rate;
+    Ok(ConvertResponse {
+        value: request.value,
+        from,
+        to,
+        result: round_significant(request.value * rate),
+        dimension: "currency".to_string(),
+        rate: Some(round_significant(rate)),
+        rates_source: Some(rates.source),
+    })
+}
+
+/// Get the exchange rates from the rates endpoint (cached) or the static config
+fn exchange_rates(config: &CurrencyConfig) -> Result<ExchangeRates, Box<dyn std::error::Error>> {
+    let Some(url) = &config.rates_url else {
+        if config.rates.is_empty() {
+            return Err("Currency conversion is not configured".into());
+        }
+        let base = config
+            .base
+            .clone()
+            .ok_or("Currency rates are configured without a base currency")?;
+        return Ok(ExchangeRates {
+            base: base.to_ascii_uppercase(),
+            rates: config
+                .rates
+                .iter()
+                .map(|(code, rate)| (code.to_ascii_uppercase(), *rate))
+                .collect(),
+            source: "config".to_string(),
+        });
+    };
+
+    let max_age = Duration::from_secs(config.cache_secs.unwrap_or(DEFAULT_CACHE_SECS));
+    let mut cache = RATES_CACHE
+        .lock()
+        .map_err(|_| "Rates cache lock poisoned")?;
+    if let Some((fetched, rates)) = cache.as_ref()
+        && fetched.elapsed() < max_age
+        && rates.source == *url
+    {
+        return Ok(rates.clone());
+    }
+
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let response = client.get(url).send()?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().unwrap_or_default();
+        return Err(format!("Rates request failed with status {}: {}", status, body).into());
+    }
+    let body: RatesResponse = response
+        .json()
+        .map_err(|e| format!("Invalid rates response from {}: {}", url, e))?;
+    let base = body
+        .base
+        .or_else(|| config.base.clone())
+        .ok_or("Rates response does not name its base currency")?;
+
+    let rates = ExchangeRates {
+        base: base.to_ascii_uppercase(),
+        rates: body
+            .rates
+            .into_iter()
+            .map(|(code, rate)| (code.to_ascii_uppercase(), rate))
+            .collect(),
+        source: url.clone(),
+    };
+    *cache = Some((Instant::now(), rates.clone()));
+    Ok(rates)
+}
+
+/// Look up a unit by symbol or name, falling back to a case-insensitive match
+///
+/// Data size symbols only match in their exact case: "Mb" means megabits to some and
+/// megabytes to others, so a symbol matching one only once case-folded is an error
+/// naming the candidates.
+fn find_unit(name: &str) -> Result<Option<&'static Unit>, String> {
+    let name = name.trim();
+    if let Some(unit) = UNITS
+        .iter()
+        .find(|unit| unit.symbol == name || unit.names.contains(&name))
+    {
+        return Ok(Some(unit));
+    }
+
+    let lower = name.to_lowercase();
+    let candidates: Vec<String> = UNITS
+        .iter()
+        .filter(|unit| unit.dimension == "data")
+        .filter(|unit| {
+            let symbol = unit.symbol.to_lowercase();
+            symbol == lower || symbol == format!("{}it", lower)
+        })
+        .map(|unit| {
+            match unit
+                .names
+                .iter()
+                .find(|n| n.starts_with(char::is_lowercase))
+            {
+                Some(name) => format!("{} ({})", unit.symbol, name),
+                None => unit.symbol.to_string(),
+            }
+        })
+        .collect();
+    if !candidates.is_empty() {
+        return Err(format!(
+            "Ambiguous unit '{}': use {}",
+            name,
+            candidates.join(" or ")
+        ));
+    }
+
+    Ok(UNITS.iter().find(|unit| {
+        unit.symbol.to_lowercase() == lower || unit.names.iter().any(|n| n.to_lowercase() == lower)
+    }))
+}
+
+/// Whether a string looks like an ISO 4217 currency code
+fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Round to `SIGNIFICANT_DIGITS` significant digits
+fn round_significant(value: f64) -> f64 {
+    if value == 0.0 {
+        return value;
+    }
+    let scale = 10f64.powi(SIGNIFICANT_DIGITS - value.abs().log10().ceil() as i32);
+    if !scale.is_finite() {
+        return value;
+    }
+    (value * scale).round() / scale
+}
+
+/// Get the tool definition for the convert tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "convert".to_string(),
+        version: None,
+        description: Some("Convert a value between units of length (mm, m, km, in, ft, mi...), mass (g, kg, oz, lb...), temperature (°C, °F, K) or data size (bit, B, kB, Mbit, MB, KiB, MiB...), or between currencies by ISO 4217 code when exchange rates are configured. Units can be given by symbol or name; data size symbols are case-sensitive.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<ConvertRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(value: f64, from: &str, to: &str) -> Result<ConvertResponse, String> {
+        let request = ConvertRequest {
+            value,
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+        execute_convert(request, &CurrencyConfig::default()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn tells_megabits_from_megabytes() {
+        assert_eq!(convert(100.0, "Mbit", "MB").unwrap().result, 12.5);
+        assert_eq!(
+            convert(100.0, "megabits", "megabytes").unwrap().result,
+            12.5
+        );
+        assert_eq!(convert(1.0, "MB", "kB").unwrap().result, 1000.0);
+        assert_eq!(convert(1.0, "KB", "B").unwrap().result, 1000.0);
+    }
+
+    #[test]
+    fn rejects_data_symbols_in_the_wrong_case() {
+        let error = convert(100.0, "Mb", "MB").unwrap_err();
+        assert!(error.contains("Mbit (megabit)"), "{}", error);
+        assert!(error.contains("MB (megabyte)"), "{}", error);
+        for symbol in ["mb", "b", "kb", "gb", "mib"] {
+            let error = convert(1.0, symbol, "bit").unwrap_err();
+            assert!(error.starts_with("Ambiguous unit"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn matches_other_units_in_any_case() {
+        assert_eq!(convert(1.0, "KM", "M").unwrap().result, 1000.0);
+        assert_eq!(convert(100.0, "Celsius", "°F").unwrap().result, 212.0);
+    }
+}
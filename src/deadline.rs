@@ -0,0 +1,51 @@
+//! Deadline module
+//!
+//! This module carries a caller's time budget through the stack. mcp-client turns
+//! `--deadline` into a [`Deadline`] for each message; the chat session bounds its model
+//! requests by it, tool calls pass the time left to the server as a `timeoutMs` hint in
+//! their `_meta`, and the server stops waiting for the tool once the hint runs out. A
+//! request that must be answered within a minute then ends with what it has instead
+//! of overshooting.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Point in time by which work must be done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Deadline `budget` from now
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now() + budget)
+    }
+
+    /// Time left, zero once the deadline passed
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Time left, or an error once the deadline passed
+    pub fn check(&self) -> Result<Duration, DeadlineExceeded> {
+        match self.remaining() {
+            remaining if remaining.is_zero() => Err(DeadlineExceeded),
+            remaining => Ok(remaining),
+        }
+    }
+}
+
+/// Error of work not started because its deadline passed
+#[derive(Debug, Clone, Copy)]
+pub struct DeadlineExceeded;
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Deadline exceeded")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
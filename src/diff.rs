@@ -0,0 +1,182 @@
+//! Diff tool module
+//!
+//! This module produces unified diffs between two files or two inline texts, so
+//! agents can present precise changes instead of restating whole documents. Input
+//! sizes are bounded and the diff algorithm gives up on an optimal result after a
+//! deadline, keeping worst-case inputs cheap.
+
+use crate::tool_error::ToolErrorPayload;
+use crate::vfs::VirtualFs;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::time::Duration;
+
+/// Default number of unchanged lines shown around each change
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// Upper bound on the number of context lines
+const MAX_CONTEXT_LINES: usize = 100;
+
+/// Default size limit for each side of the diff
+const DEFAULT_MAX_BYTES: u64 = 1024 * 1024;
+
+/// Upper bound on the size limit a caller can request
+const MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// After this long the diff falls back to a coarser, non-minimal result
+const DIFF_DEADLINE: Duration = Duration::from_secs(2);
+
+/// Diff tool request parameters
+///
+/// Each side is read from a file or taken from inline text.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DiffRequest {
+    /// The original file
+    pub old_path: Option<String>,
+    /// The original text, instead of old_path
+    pub old_text: Option<String>,
+    /// The changed file
+    pub new_path: Option<String>,
+    /// The changed text, instead of new_path
+    pub new_text: Option<String>,
+    /// Unchanged lines shown around each change (default 3, max 100)
+    pub context_lines: Option<usize>,
+    /// Size limit in bytes for each side (default 1 MiB, max 10 MiB)
+    pub max_bytes: Option<u64>,
+}
+
+/// Diff tool response
+#[derive(Debug, Serialize)]
+pub struct DiffResponse {
+    pub old_label: String,
+    pub new_label: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Unified diff, empty when both sides are identical
+    pub diff: String,
+}
+
+impl DiffResponse {
+    /// Render the response as compact text for the model
+    pub fn to_text(&self) -> String {
+        if self.diff.is_empty() {
+            return format!(
+                "No differences between {} and {}",
+                self.old_label, self.new_label
+            );
+        }
+        format!(
+            "{} insertion(s), {} deletion(s)\n\n{}",
+            self.insertions, self.deletions, self.diff
+        )
+    }
+}
+
+/// Execute the diff tool
+pub fn execute_diff(
+    request: DiffRequest,
+    fs: &dyn VirtualFs,
+) -> Result<DiffResponse, Box<dyn std::error::Error>> {
+    let context_lines = request
+        .context_lines
+        .unwrap_or(DEFAULT_CONTEXT_LINES)
+        .min(MAX_CONTEXT_LINES);
+    let max_bytes = request
+        .max_bytes
+        .unwrap_or(DEFAULT_MAX_BYTES)
+        .min(MAX_BYTES);
+
+    let (old_label, old) = load_side(fs, "old", request.old_path, request.old_text, max_bytes)?;
+    let (new_label, new) = load_side(fs, "new", request.new_path, request.new_text, max_bytes)?;
+
+    let diff = TextDiff::configure()
+        .timeout(DIFF_DEADLINE)
+        .diff_lines(&old, &new);
+
+    let (mut insertions, mut deletions) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => insertions += 1,
+            ChangeTag::Delete => deletions += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+
+    let unified = if insertions + deletions == 0 {
+        String::new()
+    } else {
+        diff.unified_diff()
+            .context_radius(context_lines)
+            .header(&old_label, &new_label)
+            .missing_newline_hint(true)
+            .to_string()
+    };
+
+    Ok(DiffResponse {
+        old_label,
+        new_label,
+        insertions,
+        deletions,
+        diff: unified,
+    })
+}
+
+/// Read one side of the diff from a file or inline text, returning its label and content
+fn load_side(
+    fs: &dyn VirtualFs,
+    side: &str,
+    path: Option<String>,
+    text: Option<String>,
+    max_bytes: u64,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    match (path, text) {
+        (Some(path), None) => {
+            let checked = crate::file_read::validate_path(fs, &path)?;
+            if fs.metadata(checked)?.len > max_bytes {
+                return Err(ToolErrorPayload::too_large(
+                    format!("File '{}' exceeds the {} byte limit", path, max_bytes),
+                    max_bytes,
+                )
+                .into());
+            }
+            let content = fs
+                .read_to_string(checked)
+                .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+            crate::accounting::read(content.len() as u64);
+            Ok((path, content))
+        }
+        (None, Some(text)) => {
+            if text.len() as u64 > max_bytes {
+                return Err(ToolErrorPayload::too_large(
+                    format!("{}_text exceeds the {} byte limit", side, max_bytes),
+                    max_bytes,
+                )
+                .into());
+            }
+            Ok((side.to_string(), text))
+        }
+        (Some(_), Some(_)) => {
+            Err(format!("Provide either '{0}_path' or '{0}_text', not both", side).into())
+        }
+        (None, None) => Err(format!("Either '{0}_path' or '{0}_text' is required", side).into()),
+    }
+}
+
+/// Get the tool definition for the diff tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "diff".to_string(),
+        version: None,
+        description: Some(format!(
+            "Produce a unified diff between two files within {} or two inline texts. Each side is given as a path or as text.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<DiffRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
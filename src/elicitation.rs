@@ -0,0 +1,249 @@
+//! Elicitation module
+//!
+//! This module implements `elicitation/create`, with which a tool asks the user for
+//! input it needs in the middle of a call, such as a missing setting or a choice
+//! between options, instead of failing or guessing. On the server, [`elicit`] sends a
+//! message and the JSON Schema of the expected answer to the client that called the
+//! running tool, which must have announced the elicitation capability, and checks the
+//! answer against the schema. On the client, a handler shows the request to the user;
+//! [`ElicitRequest::fields`] lists what to ask for and reads the typed values.
+
+use crate::error::McpError;
+use crate::mcp::McpServerState;
+use crate::tool_prompt::MissingArgument;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Method of elicitation requests
+pub const METHOD: &str = "elicitation/create";
+
+/// Parameters of an elicitation request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElicitRequest {
+    /// What the input is for, shown to the user
+    pub message: String,
+    /// Schema of the answer: an object whose properties are strings, numbers,
+    /// integers, booleans or string enums
+    pub requested_schema: Value,
+}
+
+impl ElicitRequest {
+    /// A request for the input described by `requested_schema`
+    pub fn new(message: impl Into<String>, requested_schema: Value) -> Self {
+        Self {
+            message: message.into(),
+            requested_schema,
+        }
+    }
+
+    /// A request for the fields of `T`, with its schema generated like that of tool
+    /// arguments
+    pub fn of<T: JsonSchema>(message: impl Into<String>) -> Self {
+        Self::new(message, crate::schema::input_schema::<T>())
+    }
+
+    /// The fields of the requested schema, required ones first
+    pub fn fields(&self) -> Vec<ElicitField> {
+        let required: Vec<&str> = self
+            .requested_schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let Some(properties) = self
+            .requested_schema
+            .get("properties")
+            .and_then(Value::as_object)
+        else {
+            return Vec::new();
+        };
+        let mut fields: Vec<ElicitField> = properties
+            .iter()
+            .map(|(name, property)| ElicitField {
+                name: name.clone(),
+                kind: property
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                description: property
+                    .get("description")
+                    .or_else(|| property.get("title"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                required: required.contains(&name.as_str()),
+                options: property
+                    .get("enum")
+                    .and_then(Value::as_array)
+                    .map(|options| {
+                        options
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+        fields.sort_by_key(|field| !field.required);
+        fields
+    }
+}
+
+/// A value the user is asked for
+#[derive(Debug, Clone)]
+pub struct ElicitField {
+    pub name: String,
+    /// JSON Schema type of the value, when declared
+    pub kind: Option<String>,
+    pub description: Option<String>,
+    pub required: bool,
+    /// Values allowed for a string enum
+    pub options: Vec<String>,
+}
+
+impl fmt::Display for ElicitField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}{})",
+            self.name,
+            self.kind.as_deref().unwrap_or("any"),
+            if self.required { "" } else { ", optional" }
+        )?;
+        if let Some(description) = &self.description {
+            write!(f, ": {}", description)?;
+        }
+        if !self.options.is_empty() {
+            write!(f, " [{}]", self.options.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl ElicitField {
+    /// Read a value typed by the user as the field's type
+    ///
+    /// Values are parsed like missing tool arguments; enums only accept their options.
+    pub fn parse_value(&self, input: &str) -> Result<Value, String> {
+        let value = MissingArgument {
+            name: self.name.clone(),
+            kind: self.kind.clone(),
+            description: None,
+        }
+        .parse_value(input)?;
+        if !self.options.is_empty()
+            && !value
+                .as_str()
+                .is_some_and(|v| self.options.iter().any(|o| o == v))
+        {
+            return Err(format!(
+                "'{}' is not one of {}",
+                input.trim(),
+                self.options.join(", ")
+            ));
+        }
+        Ok(value)
+    }
+}
+
+/// How the user responded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitAction {
+    /// The user submitted the input
+    Accept,
+    /// The user refused to give the input
+    Decline,
+    /// The user dismissed the request without choosing
+    Cancel,
+}
+
+/// Result of an elicitation request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ElicitResult {
+    pub action: ElicitAction,
+    /// The submitted input, when accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<Map<String, Value>>,
+}
+
+impl ElicitResult {
+    /// The user submitted `content`
+    pub fn accept(content: Map<String, Value>) -> Self {
+        Self {
+            action: ElicitAction::Accept,
+            content: Some(content),
+        }
+    }
+
+    /// The user refused
+    pub fn decline() -> Self {
+        Self {
+            action: ElicitAction::Decline,
+            content: None,
+        }
+    }
+
+    /// The user dismissed the request
+    pub fn cancel() -> Self {
+        Self {
+            action: ElicitAction::Cancel,
+            content: None,
+        }
+    }
+
+    /// The submitted input as `T`, or None unless the user accepted
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<Option<T>, serde_json::Error> {
+        match (&self.content, self.action) {
+            (Some(content), ElicitAction::Accept) => {
+                serde_json::from_value(Value::Object(content.clone())).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Ask the user of the client that called the running tool for input
+///
+/// Only async tools can elicit, as the request goes to the session of the call running
+/// on the current task, see [`crate::server_request`]. Declined and cancelled requests
+/// are results like accepted ones; accepted input not matching the requested schema is
+/// an error.
+pub async fn elicit(
+    state: &McpServerState,
+    request: &ElicitRequest,
+) -> Result<ElicitResult, McpError> {
+    let session_id = crate::server_request::caller().ok_or_else(|| {
+        McpError::protocol("Elicitation is only possible during a tool call made within a session")
+    })?;
+    let supported = state
+        .sessions
+        .read()
+        .await
+        .get(&session_id)
+        .is_some_and(|session| session.client.capabilities.elicitation.is_some());
+    if !supported {
+        return Err(McpError::protocol(
+            "The client does not support elicitation",
+        ));
+    }
+    let validator = jsonschema::validator_for(&request.requested_schema)
+        .map_err(|e| McpError::protocol(format!("Invalid requested schema: {}", e)))?;
+    let result =
+        crate::server_request::request(state, METHOD, Some(serde_json::to_value(request)?)).await?;
+    let result: ElicitResult = serde_json::from_value(result)?;
+    if result.action == ElicitAction::Accept {
+        let content = Value::Object(result.content.clone().unwrap_or_default());
+        if let Some((summary, _)) = crate::mcp::schema_errors(&validator, &content) {
+            return Err(McpError::protocol(format!(
+                "The client's input does not match the requested schema: {}",
+                summary
+            )));
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,159 @@
+//! Email tool module
+//!
+//! This module sends plain-text email through a configured SMTP server. Recipients
+//! must match the configured allowlist, and the tool is annotated as requiring human
+//! approval so hosts confirm every message before it is sent. It is only compiled
+//! with the `email` feature.
+
+use crate::config::EmailConfig;
+use crate::tool_error::ToolErrorPayload;
+use lettre::message::{Mailbox, header::ContentType};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on the number of recipients per message
+const MAX_RECIPIENTS: usize = 10;
+
+/// Upper bound on the size of a message body
+const MAX_BODY_BYTES: usize = 100 * 1024;
+
+/// Email send tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EmailSendRequest {
+    /// Recipient email addresses
+    pub to: Vec<String>,
+    /// The subject line
+    pub subject: String,
+    /// The plain-text message body
+    pub body: String,
+}
+
+/// Email send tool response
+#[derive(Debug, Serialize)]
+pub struct EmailSendResponse {
+    pub recipients: Vec<String>,
+    pub message: String,
+}
+
+/// Execute the email send tool
+pub fn execute_email_send(
+    request: EmailSendRequest,
+    config: &EmailConfig,
+) -> Result<EmailSendResponse, Box<dyn std::error::Error>> {
+    let host = config.host.as_deref().ok_or("No SMTP host configured")?;
+    let from = config
+        .from
+        .as_deref()
+        .ok_or("No sender address configured")?;
+
+    if request.to.is_empty() {
+        return Err("At least one recipient is required".into());
+    }
+    if request.to.len() > MAX_RECIPIENTS {
+        return Err(format!("At most {} recipients are allowed", MAX_RECIPIENTS).into());
+    }
+    if request.body.len() > MAX_BODY_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("Body exceeds the {} byte limit", MAX_BODY_BYTES),
+            MAX_BODY_BYTES as u64,
+        )
+        .into());
+    }
+
+    let mut builder = Message::builder()
+        .from(from.parse::<Mailbox>()?)
+        .subject(request.subject.replace(['\r', '\n'], " "))
+        .header(ContentType::TEXT_PLAIN);
+
+    for recipient in &request.to {
+        let mailbox: Mailbox = recipient
+            .parse()
+            .map_err(|e| format!("Invalid recipient '{}': {}", recipient, e))?;
+        if !is_allowed_recipient(mailbox.email.as_ref(), &config.allowed_recipients) {
+            return Err(ToolErrorPayload::access_denied(format!(
+                "Access denied: recipient '{}' is not allowlisted",
+                mailbox.email
+            ))
+            .into());
+        }
+        builder = builder.to(mailbox);
+    }
+
+    let message = builder.body(request.body)?;
+    let response = build_transport(config, host)?.send(&message)?;
+
+    Ok(EmailSendResponse {
+        recipients: request.to,
+        message: response.message().collect::<Vec<_>>().join(" "),
+    })
+}
+
+/// Check that the SMTP server accepts connections with the configured credentials
+pub fn check(config: &EmailConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let host = config.host.as_deref().ok_or("No SMTP host configured")?;
+    if build_transport(config, host)?.test_connection()? {
+        Ok(())
+    } else {
+        Err(format!("SMTP server {} did not accept the connection", host).into())
+    }
+}
+
+/// Build the SMTP transport for the configured server
+fn build_transport(
+    config: &EmailConfig,
+    host: &str,
+) -> Result<SmtpTransport, Box<dyn std::error::Error>> {
+    let transport = if config.starttls {
+        SmtpTransport::starttls_relay(host)?
+    } else {
+        SmtpTransport::relay(host)?
+    };
+    let default_port = if config.starttls { 587 } else { 465 };
+    let mut transport = transport.port(config.port.unwrap_or(default_port));
+    if let Some(username) = &config.username {
+        let password = match &config.password_env {
+            Some(variable) => std::env::var(variable)
+                .map_err(|_| format!("SMTP password variable '{}' is not set", variable))?,
+            None => config.password.clone().unwrap_or_default(),
+        };
+        transport = transport.credentials(Credentials::new(username.clone(), password));
+    }
+    Ok(transport.build())
+}
+
+/// Check a recipient against the allowlist
+///
+/// Entries are either full addresses (`ops@example.com`) or whole domains (`@example.com`).
+fn is_allowed_recipient(address: &str, allowed: &[String]) -> bool {
+    let address = address.to_ascii_lowercase();
+    allowed.iter().any(|entry| {
+        let entry = entry.to_ascii_lowercase();
+        if entry.starts_with('@') {
+            address.ends_with(&entry)
+        } else {
+            address == entry
+        }
+    })
+}
+
+/// Get the tool definition for the email_send tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "email_send".to_string(),
+        version: None,
+        description: Some("Send a plain-text email to allowlisted recipients. Every message must be approved by the user before it is sent.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<EmailSendRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            title: Some("Send email".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(true),
+            requires_approval: Some(true),
+            ..Default::default()
+        }),
+    }
+}
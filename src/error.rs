@@ -0,0 +1,121 @@
+//! Error module
+//!
+//! This module defines [`McpError`], the error of the MCP client, the Ollama client
+//! and chat session, and the path checks of the file tools. Its variants tell apart a
+//! server that could not be reached ([`TransportError`]), a server breaking the
+//! protocol or answering with a JSON-RPC error, a failed authorization, a failed
+//! tool, a model that is missing or lacks a capability, and messages that could not be
+//! encoded or decoded, so callers can decide to retry, report or give up without
+//! parsing error messages.
+
+use crate::circuit_breaker::CircuitOpen;
+use crate::deadline::DeadlineExceeded;
+use crate::tool_error::ToolErrorPayload;
+use serde_json::Value;
+
+/// Errors of the MCP and Ollama clients
+#[derive(Debug, thiserror::Error)]
+pub enum McpError {
+    /// The server could not be reached or the connection failed
+    #[error(transparent)]
+    Transport(#[from] TransportError),
+    /// The server's messages do not follow the protocol, or the request is not
+    /// possible on this connection
+    #[error("{0}")]
+    Protocol(String),
+    /// The server answered the request with a JSON-RPC error
+    #[error("MCP server error {code}: {message}")]
+    Rpc {
+        code: i32,
+        message: String,
+        data: Option<Value>,
+    },
+    /// The client could not be authorized with the server
+    #[error("{0}")]
+    Authorization(String),
+    /// A tool failed, with its machine-readable code
+    #[error(transparent)]
+    Tool(#[from] ToolErrorPayload),
+    /// The model is not available or cannot do what was asked
+    #[error("{0}")]
+    Model(String),
+    /// A message could not be encoded or decoded
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+    /// The caller's deadline passed
+    #[error(transparent)]
+    Deadline(#[from] DeadlineExceeded),
+}
+
+/// Failures to reach a server or to exchange messages with it
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    /// The HTTP request failed
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    /// The server answered with an HTTP error status
+    #[error("HTTP error {status}: {body}")]
+    Status {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    /// Reading or writing a process, socket, stream or output failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The connection could not be established or was closed
+    #[error("{0}")]
+    Connection(String),
+    /// Requests to the server are held back after repeated failures
+    #[error("server {0}")]
+    CircuitOpen(#[from] CircuitOpen),
+}
+
+impl McpError {
+    /// A protocol error
+    pub fn protocol(message: impl Into<String>) -> Self {
+        Self::Protocol(message.into())
+    }
+
+    /// A connection that could not be established or was closed
+    pub fn connection(message: impl Into<String>) -> Self {
+        Self::Transport(TransportError::Connection(message.into()))
+    }
+
+    /// An authorization failure
+    pub fn authorization(message: impl Into<String>) -> Self {
+        Self::Authorization(message.into())
+    }
+
+    /// Whether the server could not be reached, so the request may succeed later
+    pub fn is_transport(&self) -> bool {
+        matches!(self, Self::Transport(_))
+    }
+}
+
+impl From<reqwest::Error> for McpError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Transport(error.into())
+    }
+}
+
+impl From<std::io::Error> for McpError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Transport(error.into())
+    }
+}
+
+impl From<CircuitOpen> for McpError {
+    fn from(error: CircuitOpen) -> Self {
+        Self::Transport(error.into())
+    }
+}
+
+impl From<crate::mcp::JsonRpcError> for McpError {
+    fn from(error: crate::mcp::JsonRpcError) -> Self {
+        Self::Rpc {
+            code: error.code,
+            message: error.message,
+            data: error.data,
+        }
+    }
+}
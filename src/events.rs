@@ -0,0 +1,161 @@
+//! Server events module
+//!
+//! This module defines the events the server emits about its own activity (tool
+//! calls completing or failing, errors, quota rejections) and delivers them to the
+//! sinks configured by the operator: a webhook URL, a JSON lines file or stdout.
+//! Each sink has its own filter so, for example, only failures of sensitive tools
+//! are forwarded to an alerting webhook.
+
+use crate::config::{EventSinkConfig, EventSinkKind};
+use crate::mcp::McpServerState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Kinds of server events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A tool call finished and returned a result
+    ToolCallCompleted,
+    /// A tool call was rejected or failed with a JSON-RPC error
+    ToolCallFailed,
+    /// A request could not be served because of a server-side error
+    Error,
+    /// A request was rejected because a usage limit was reached
+    QuotaExceeded,
+}
+
+/// An event describing server activity
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerEvent {
+    pub kind: EventKind,
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Bytes and CPU time used by a tool call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<crate::accounting::ResourceUsage>,
+}
+
+impl ServerEvent {
+    /// Create an event of the given kind timestamped now
+    pub fn new(kind: EventKind) -> Self {
+        Self {
+            kind,
+            timestamp: Utc::now(),
+            tool: None,
+            message: None,
+            duration_ms: None,
+            usage: None,
+        }
+    }
+
+    /// Set the tool the event relates to
+    pub fn tool(mut self, tool: impl Into<String>) -> Self {
+        self.tool = Some(tool.into());
+        self
+    }
+
+    /// Set a human readable message
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Set how long the operation took
+    pub fn duration_ms(mut self, duration_ms: u64) -> Self {
+        self.duration_ms = Some(duration_ms);
+        self
+    }
+
+    /// Set the resources a tool call used
+    pub fn usage(mut self, usage: crate::accounting::ResourceUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+}
+
+/// Check an event against a sink's filter
+///
+/// Empty `events` or `tools` lists match everything.
+fn matches(sink: &EventSinkConfig, event: &ServerEvent) -> bool {
+    let kind_matches = sink.events.is_empty() || sink.events.contains(&event.kind);
+    let tool_matches = sink.tools.is_empty()
+        || event
+            .tool
+            .as_ref()
+            .is_some_and(|tool| sink.tools.contains(tool));
+    kind_matches && tool_matches
+}
+
+/// Start a delivery task for every configured sink
+pub fn start(state: &McpServerState) {
+    for sink in state.config.event_sink.iter().cloned() {
+        let mut events = state.events.subscribe();
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        crate::server_log::warn(format!("Event sink dropped {} events", skipped));
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if !matches(&sink, &event) {
+                    continue;
+                }
+                if let Err(e) = deliver(&client, &sink, &event).await {
+                    crate::server_log::warn(format!(
+                        "Failed to deliver event to {:?} sink: {}",
+                        sink.kind, e
+                    ));
+                }
+            }
+        });
+    }
+}
+
+/// Deliver a single event to a sink
+async fn deliver(
+    client: &reqwest::Client,
+    sink: &EventSinkConfig,
+    event: &ServerEvent,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match sink.kind {
+        EventSinkKind::Stdout => {
+            println!("{}", serde_json::to_string(event)?);
+        }
+        EventSinkKind::File => {
+            let path = sink.path.as_deref().ok_or("file sink requires a path")?;
+            let mut line = serde_json::to_vec(event)?;
+            line.push(b'\n');
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(&line).await?;
+        }
+        EventSinkKind::Webhook => {
+            let url = sink.url.as_deref().ok_or("webhook sink requires a url")?;
+            let response = client
+                .post(url)
+                .timeout(std::time::Duration::from_secs(10))
+                .json(event)
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(format!("webhook returned status {}", response.status()).into());
+            }
+        }
+    }
+    Ok(())
+}
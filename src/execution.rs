@@ -0,0 +1,182 @@
+//! Execution limits module
+//!
+//! This module keeps one slow tool from starving the server. The `[execution]` config
+//! section bounds how long a tool call may run, by default and per tool, and how many
+//! tool calls execute at once. Calls beyond the concurrency limit wait for a slot
+//! instead of being rejected as by [`crate::admission`]; while they wait they can
+//! still be cancelled or run out the caller's deadline. A call exceeding its time
+//! limit is cancelled and answered with a `TIMEOUT` tool error. Async handlers stop
+//! at once, while blocking work stops at its next check of the cancellation token, so
+//! its thread may outlive the call. Blocking work started with [`spawn_blocking`]
+//! keeps the call's slot until its thread finishes, so the limit bounds the work
+//! actually running rather than the calls still answered.
+
+use crate::config::ExecutionConfig;
+use crate::mcp::ToolsCallResponse;
+use crate::tool_error::ToolErrorPayload;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
+
+tokio::task_local! {
+    /// Slot of the tool call running on the task, until blocking work takes it over
+    static CALL_SLOT: Mutex<Option<OwnedSemaphorePermit>>;
+}
+
+/// Slots for the tool calls executing at once
+///
+/// The limit is read from the config on first use, as the config does not change once
+/// the server runs.
+#[derive(Debug, Default)]
+pub struct ExecutionSlots(OnceLock<Option<Arc<Semaphore>>>);
+
+impl ExecutionSlots {
+    /// Wait for a free slot; `None` when executions are not limited
+    ///
+    /// The slot is released when the permit is dropped.
+    pub async fn acquire(&self, config: &ExecutionConfig) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self.0.get_or_init(|| {
+            config
+                .max_concurrent
+                .map(|limit| Arc::new(Semaphore::new(limit.max(1))))
+        });
+        let permit = Arc::clone(semaphore.as_ref()?)
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+        Some(permit)
+    }
+}
+
+/// Run a tool call holding its slot
+///
+/// The slot is released when the call finishes or is dropped, unless blocking work
+/// started by the call with [`spawn_blocking`] took it over.
+pub async fn hold<F: Future>(slot: Option<OwnedSemaphorePermit>, call: F) -> F::Output {
+    CALL_SLOT.scope(Mutex::new(slot), call).await
+}
+
+/// Run blocking work of a tool call on the blocking thread pool
+///
+/// The first blocking work of a call takes over its slot and releases it when its
+/// thread finishes, even if the call timed out or was cancelled meanwhile. Tools
+/// should use this rather than [`tokio::task::spawn_blocking`] so that work ignoring
+/// the cancellation token still counts against `max_concurrent`.
+pub fn spawn_blocking<T, F>(work: F) -> JoinHandle<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let slot = CALL_SLOT
+        .try_with(|slot| slot.lock().unwrap_or_else(|e| e.into_inner()).take())
+        .ok()
+        .flatten();
+    tokio::task::spawn_blocking(move || {
+        let _slot = slot;
+        work()
+    })
+}
+
+/// The longest a call of `tool` may run, if limited
+pub fn time_limit(config: &ExecutionConfig, tool: &str) -> Option<Duration> {
+    config
+        .tool_timeout_ms
+        .get(tool)
+        .or(config.timeout_ms.as_ref())
+        .map(|ms| Duration::from_millis(*ms))
+}
+
+/// Result of a call that ran longer than its time limit
+pub fn limit_exceeded(tool: &str, limit: Duration) -> ToolsCallResponse {
+    let payload = ToolErrorPayload::timeout(
+        format!(
+            "Tool '{}' did not finish within the server's limit of {} ms; try a smaller request",
+            tool,
+            limit.as_millis()
+        ),
+        limit,
+    );
+    ToolsCallResponse::error_with_payload(payload.to_string(), &payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::mcp::{McpServerState, ToolFuture, ToolHandler, ToolsCallRequest, dispatch_tool};
+    use crate::progress::ProgressReporter;
+    use serde_json::{Value, json};
+    use std::time::Instant;
+    use tokio_util::sync::CancellationToken;
+
+    /// A tool blocking its thread for a while without checking for cancellation
+    struct Sleep(Duration);
+
+    impl ToolHandler for Sleep {
+        fn name(&self) -> &str {
+            "sleep"
+        }
+
+        fn description(&self) -> &str {
+            "Block a thread"
+        }
+
+        fn input_schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        fn call<'a>(
+            &'a self,
+            _state: &'a McpServerState,
+            _arguments: Option<Value>,
+            _progress: ProgressReporter,
+            _cancel: CancellationToken,
+        ) -> ToolFuture<'a> {
+            let duration = self.0;
+            Box::pin(async move {
+                spawn_blocking(move || std::thread::sleep(duration))
+                    .await
+                    .expect("the sleep does not panic");
+                Ok("slept".to_string().into())
+            })
+        }
+    }
+
+    async fn call(state: &McpServerState) -> ToolsCallResponse {
+        let request = ToolsCallRequest {
+            name: "sleep".to_string(),
+            arguments: None,
+            meta: None,
+        };
+        dispatch_tool(
+            state,
+            request,
+            ProgressReporter::default(),
+            CancellationToken::new(),
+        )
+        .await
+        .expect("the tool is registered")
+    }
+
+    #[tokio::test]
+    async fn timed_out_blocking_work_keeps_its_slot() {
+        let mut config = ServerConfig::default();
+        config.execution.timeout_ms = Some(50);
+        config.execution.max_concurrent = Some(1);
+        let mut state = McpServerState::new();
+        state.config = Arc::new(config);
+        state.register_tool(Sleep(Duration::from_millis(400))).await;
+
+        let started = Instant::now();
+        let first = call(&state).await;
+        assert!(first.is_error);
+        assert!(started.elapsed() < Duration::from_millis(300));
+
+        // The first call's thread still sleeps, so the second waits for it to finish
+        let second = call(&state).await;
+        assert!(second.is_error);
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+}
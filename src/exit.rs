@@ -0,0 +1,168 @@
+//! Exit status module
+//!
+//! This module defines how mcp-client and mcp-server end when they fail. Each cause of
+//! failure exits with its own code, and with `--error-format json` the final error is
+//! printed to stderr as a single JSON object instead of text, so scripts wrapping the
+//! binaries can branch on why a run failed instead of matching error messages:
+//!
+//! | Code | Kind | Cause |
+//! |------|------|-------|
+//! | 1 | `failed` | Any other failure |
+//! | 2 | `usage` | Invalid command line arguments |
+//! | 3 | `config` | Invalid configuration, or a file it names cannot be used |
+//! | 4 | `connection` | The MCP server could not be reached or the session not started |
+//! | 5 | `authorization` | The MCP server refused the client's credentials |
+//! | 6 | `tool` | A tool call failed |
+//! | 7 | `model` | The model is unavailable or failed |
+//! | 130 | `aborted` | The user interrupted the run |
+
+use crate::error::{McpError, TransportError};
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// Format of the final error, set once when the binary starts
+static FORMAT: OnceLock<ErrorFormat> = OnceLock::new();
+
+/// Cause of a failed run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    Failed,
+    Usage,
+    Config,
+    Connection,
+    Authorization,
+    Tool,
+    Model,
+    Aborted,
+}
+
+impl FailureKind {
+    /// Status the process exits with
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Failed => 1,
+            Self::Usage => 2,
+            Self::Config => 3,
+            Self::Connection => 4,
+            Self::Authorization => 5,
+            Self::Tool => 6,
+            Self::Model => 7,
+            // 128 + SIGINT, as shells report a run ended with Ctrl-C
+            Self::Aborted => 130,
+        }
+    }
+
+    /// Cause of an error of the MCP client
+    ///
+    /// Errors of the Ollama client are model failures whatever their variant, so
+    /// callers talking to the model use [`FailureKind::Model`] instead.
+    pub fn of(error: &McpError) -> Self {
+        match error {
+            McpError::Transport(TransportError::Status { status, .. })
+                if *status == reqwest::StatusCode::UNAUTHORIZED
+                    || *status == reqwest::StatusCode::FORBIDDEN =>
+            {
+                Self::Authorization
+            }
+            McpError::Transport(_) => Self::Connection,
+            McpError::Authorization(_) => Self::Authorization,
+            McpError::Tool(_) => Self::Tool,
+            McpError::Model(_) => Self::Model,
+            McpError::Protocol(_)
+            | McpError::Rpc { .. }
+            | McpError::Serialization(_)
+            | McpError::Deadline(_) => Self::Failed,
+        }
+    }
+
+    /// Cause of an error raised while doing something that fails with `default`
+    ///
+    /// Errors of the MCP client are classified by [`FailureKind::of`].
+    pub fn of_error(error: &(dyn std::error::Error + 'static), default: Self) -> Self {
+        match error.downcast_ref::<McpError>() {
+            Some(error) => Self::of(error),
+            None => default,
+        }
+    }
+}
+
+/// How the final error of a failed run is printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorFormat {
+    /// "Error: " followed by the message
+    #[default]
+    Text,
+    /// `{"error": {"kind": ..., "exitCode": ..., "message": ...}}` on one line
+    Json,
+}
+
+/// Print final errors in `format` from now on; only the first call has an effect
+pub fn set_format(format: ErrorFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// The format final errors are printed in
+pub fn format() -> ErrorFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// The final error of a failed run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub exit_code: i32,
+    pub message: String,
+}
+
+impl Failure {
+    /// A failure exiting with the code of its kind
+    pub fn new(kind: FailureKind, message: impl std::fmt::Display) -> Self {
+        Self {
+            kind,
+            exit_code: kind.exit_code(),
+            message: message.to_string(),
+        }
+    }
+
+    /// The failure as printed in `format`
+    pub fn render(&self, format: ErrorFormat) -> String {
+        match format {
+            ErrorFormat::Text => format!("Error: {}", self.message),
+            ErrorFormat::Json => serde_json::json!({ "error": self }).to_string(),
+        }
+    }
+}
+
+/// Print the final error to stderr and exit with the code of its cause
+pub fn fail(kind: FailureKind, message: impl std::fmt::Display) -> ! {
+    let failure = Failure::new(kind, message);
+    eprintln!("{}", failure.render(format()));
+    std::process::exit(failure.exit_code)
+}
+
+/// Parse the command line of a binary with an `--error-format` option
+///
+/// Invalid arguments exit with the usage code. As the format itself was not parsed
+/// then, the error is printed as JSON when the raw arguments ask for it.
+pub fn parse_args<P: clap::Parser>() -> P {
+    P::try_parse().unwrap_or_else(|error| {
+        let args: Vec<String> = std::env::args().collect();
+        let json = args.iter().any(|arg| arg == "--error-format=json")
+            || args
+                .windows(2)
+                .any(|pair| pair[0] == "--error-format" && pair[1] == "json");
+        // Help and version requests are not errors
+        if !json || !error.use_stderr() {
+            error.exit();
+        }
+        set_format(ErrorFormat::Json);
+        let message = error.to_string();
+        let message = message.lines().next().unwrap_or_default();
+        fail(
+            FailureKind::Usage,
+            message.strip_prefix("error: ").unwrap_or(message),
+        )
+    })
+}
@@ -0,0 +1,195 @@
+//! Feed reading tool module
+//!
+//! This module fetches RSS and Atom feeds from allowlisted domains and returns
+//! their most recent items in a compact structured form.
+
+use crate::tool_error::ToolErrorPayload;
+use feed_rs::parser;
+use reqwest::blocking::Client;
+use reqwest::redirect::Policy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::time::Duration;
+
+/// Default number of items returned
+const DEFAULT_ITEMS: usize = 10;
+
+/// Upper bound on the number of items returned
+const MAX_ITEMS: usize = 50;
+
+/// Item summaries longer than this are truncated
+const MAX_SUMMARY_LENGTH: usize = 300;
+
+/// Upper bound on the size of a downloaded feed
+const MAX_FEED_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Feed read tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FeedReadRequest {
+    /// The feed URL
+    pub url: String,
+    /// Maximum number of items to return (default 10, max 50)
+    pub limit: Option<usize>,
+}
+
+/// Feed read tool response
+#[derive(Debug, Serialize)]
+pub struct FeedReadResponse {
+    pub title: Option<String>,
+    pub url: String,
+    pub items: Vec<FeedItem>,
+}
+
+/// A single feed entry
+#[derive(Debug, Serialize)]
+pub struct FeedItem {
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub published: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// Execute the feed read tool
+pub fn execute_feed_read(
+    request: FeedReadRequest,
+    allowed_domains: &[String],
+) -> Result<FeedReadResponse, Box<dyn std::error::Error>> {
+    let url = reqwest::Url::parse(&request.url).map_err(|e| format!("Invalid URL: {}", e))?;
+    check_url(&url, allowed_domains)?;
+
+    let client = allowlisted_client(allowed_domains, Duration::from_secs(30))?;
+    let response = client.get(url.clone()).send()?;
+    if !response.status().is_success() {
+        return Err(format!("Feed request failed with status {}", response.status()).into());
+    }
+
+    let mut body = Vec::new();
+    response.take(MAX_FEED_BYTES + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > MAX_FEED_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("Feed exceeds the {} byte limit", MAX_FEED_BYTES),
+            MAX_FEED_BYTES,
+        )
+        .into());
+    }
+
+    let feed = parser::parse(body.as_slice()).map_err(|e| format!("Invalid feed: {}", e))?;
+    let limit = request.limit.unwrap_or(DEFAULT_ITEMS).min(MAX_ITEMS);
+
+    let mut entries = feed.entries;
+    // Newest first; entries without a date keep their feed order at the end
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.published.or(entry.updated)));
+
+    let items = entries
+        .into_iter()
+        .take(limit)
+        .map(|entry| FeedItem {
+            title: entry.title.map(|t| t.content.trim().to_string()),
+            link: entry.links.first().map(|l| l.href.clone()),
+            published: entry.published.or(entry.updated).map(|d| d.to_rfc3339()),
+            summary: entry
+                .summary
+                .map(|s| s.content)
+                .or_else(|| entry.content.and_then(|c| c.body))
+                .map(|text| summarize(&text))
+                .filter(|text| !text.is_empty()),
+        })
+        .collect();
+
+    Ok(FeedReadResponse {
+        title: feed.title.map(|t| t.content.trim().to_string()),
+        url: request.url,
+        items,
+    })
+}
+
+/// Build an HTTP client whose redirects are followed only while they stay on
+/// allowlisted domains
+pub(crate) fn allowlisted_client(
+    allowed_domains: &[String],
+    timeout: Duration,
+) -> reqwest::Result<Client> {
+    let redirect_domains = allowed_domains.to_vec();
+    Client::builder()
+        .timeout(timeout)
+        .redirect(Policy::custom(move |attempt| {
+            if attempt.previous().len() >= 5 {
+                attempt.error("too many redirects")
+            } else if let Err(e) = check_url(attempt.url(), &redirect_domains) {
+                attempt.error(format!("redirect rejected: {}", e))
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+}
+
+/// Check that a URL uses HTTP(S) and points to an allowlisted domain or one of its subdomains
+pub(crate) fn check_url(url: &reqwest::Url, allowed_domains: &[String]) -> Result<(), String> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("Unsupported URL scheme: {}", url.scheme()));
+    }
+
+    let host = url
+        .host_str()
+        .ok_or("URL has no host")?
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    let allowed = allowed_domains.iter().any(|domain| {
+        let domain = domain.trim_start_matches("*.").to_ascii_lowercase();
+        host == domain || host.ends_with(&format!(".{}", domain))
+    });
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "Access denied: domain '{}' is not allowlisted",
+            host
+        ))
+    }
+}
+
+/// Strip markup from a summary and truncate it
+fn summarize(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                plain.push(' ');
+            }
+            _ if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+
+    let plain = plain
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    let collapsed = plain.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if collapsed.chars().count() <= MAX_SUMMARY_LENGTH {
+        collapsed
+    } else {
+        let truncated: String = collapsed.chars().take(MAX_SUMMARY_LENGTH).collect();
+        format!("{}…", truncated.trim_end())
+    }
+}
+
+/// Get the tool definition for the feed_read tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "feed_read".to_string(),
+        description: Some("Fetch an RSS or Atom feed from an allowlisted domain and return its most recent items (title, link, date and a short summary).".to_string()),
+        input_schema: Some(crate::schema::input_schema::<FeedReadRequest>()),
+        ..Default::default()
+    }
+}
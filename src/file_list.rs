@@ -0,0 +1,317 @@
+//! File listing tool module
+//!
+//! This module lists the entries of a directory, optionally recursively, in pages so
+//! that directories with tens of thousands of entries (such as `node_modules`) come
+//! back in manageable chunks. Entries are sorted by name, modification time or size,
+//! directories first unless asked otherwise, and each page carries the cursor of the
+//! next one. Entries excluded by `.gitignore` and `.ignore` files are left out unless
+//! `no_ignore` is set, see [`crate::gitignore`]. Cursors are offsets into the sorted
+//! listing, so they stay valid as long as the directory and the sort parameters do
+//! not change.
+
+use crate::gitignore::IgnoreStack;
+use crate::vfs::{FileKind, FsMetadata, VirtualFs};
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// Default number of entries per page
+const DEFAULT_PAGE_SIZE: usize = 200;
+
+/// Upper bound on the number of entries per page
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Entries examined at most by a recursive listing; the rest are left out
+const MAX_SCANNED_ENTRIES: usize = 100_000;
+
+/// File list tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FileListRequest {
+    /// The directory to list
+    pub path: String,
+    /// Also list the contents of subdirectories (default false)
+    pub recursive: Option<bool>,
+    /// Sort entries by name, modification time or size (default name)
+    #[schemars(extend("enum" = ["name", "mtime", "size"]))]
+    pub sort: Option<String>,
+    /// Sort in descending order, e.g. newest or largest first (default false)
+    pub descending: Option<bool>,
+    /// List directories before files (default true)
+    pub dirs_first: Option<bool>,
+    /// Include entries excluded by .gitignore and .ignore files, and .git directories
+    /// (default false)
+    pub no_ignore: Option<bool>,
+    /// Entries per page (default 200, max 1000)
+    pub page_size: Option<usize>,
+    /// next_cursor of the previous page, absent for the first page
+    pub cursor: Option<String>,
+}
+
+/// An entry of a listing
+#[derive(Debug, Serialize)]
+pub struct FileListEntry {
+    /// Path relative to the listed directory
+    pub name: String,
+    /// file, dir or other (symbolic links and special files)
+    pub kind: &'static str,
+    /// Size in bytes, for files
+    pub size: Option<u64>,
+    /// Last modification time, RFC 3339
+    pub modified: Option<String>,
+}
+
+/// File list tool response
+#[derive(Debug, Serialize)]
+pub struct FileListResponse {
+    pub path: String,
+    pub entries: Vec<FileListEntry>,
+    /// Index of the first entry of the page in the whole listing
+    pub offset: usize,
+    /// Entries in the whole listing
+    pub total: usize,
+    /// Entries left out because ignore files exclude them
+    pub ignored: usize,
+    /// Whether a recursive listing stopped after examining too many entries
+    pub truncated: bool,
+    /// Cursor of the next page, absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl FileListResponse {
+    /// Render the response as compact text for the model
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Directory: {}\n", self.path);
+        if self.entries.is_empty() {
+            text.push_str(&format!("Entries: none of {}\n", self.total));
+        } else {
+            text.push_str(&format!(
+                "Entries: {}-{} of {}\n",
+                self.offset + 1,
+                self.offset + self.entries.len(),
+                self.total
+            ));
+        }
+        if self.ignored > 0 {
+            text.push_str(&format!(
+                "Ignored: {} entries excluded by .gitignore/.ignore (pass no_ignore to include them)\n",
+                self.ignored
+            ));
+        }
+        if self.truncated {
+            text.push_str(&format!(
+                "Truncated: stopped after {} entries\n",
+                MAX_SCANNED_ENTRIES
+            ));
+        }
+        if let Some(cursor) = &self.next_cursor {
+            text.push_str(&format!("Next cursor: {}\n", cursor));
+        }
+
+        text.push('\n');
+        for entry in &self.entries {
+            let name = match entry.kind {
+                "dir" => format!("{}/", entry.name),
+                _ => entry.name.clone(),
+            };
+            let size = entry.size.map(|size| size.to_string()).unwrap_or_default();
+            text.push_str(&format!(
+                "{:<5} {:>12} {:<20} {}\n",
+                entry.kind,
+                size,
+                entry.modified.as_deref().unwrap_or("-"),
+                name
+            ));
+        }
+        text
+    }
+}
+
+/// How entries are ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Mtime,
+    Size,
+}
+
+/// An entry found while walking the directory
+struct Listed {
+    name: String,
+    path: PathBuf,
+    kind: FileKind,
+    metadata: Option<FsMetadata>,
+}
+
+/// Execute the file list tool
+pub fn execute_file_list(
+    request: FileListRequest,
+    fs: &dyn VirtualFs,
+) -> Result<FileListResponse, Box<dyn std::error::Error>> {
+    let root = crate::file_read::validate_dir(fs, &request.path)?;
+    let sort = match request.sort.as_deref().unwrap_or("name") {
+        "name" => SortKey::Name,
+        "mtime" => SortKey::Mtime,
+        "size" => SortKey::Size,
+        other => {
+            return Err(format!("Unknown sort '{}', expected name, mtime or size", other).into());
+        }
+    };
+    let descending = request.descending.unwrap_or(false);
+    let dirs_first = request.dirs_first.unwrap_or(true);
+    let page_size = request
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = crate::pagination::PaginatedRequest {
+        cursor: request.cursor,
+    }
+    .offset()
+    .map_err(|e| e.message)?;
+
+    let ignore = (!request.no_ignore.unwrap_or(false)).then(|| IgnoreStack::for_dir(fs, root));
+    let mut walk = Walk {
+        fs,
+        root,
+        recursive: request.recursive.unwrap_or(false),
+        listed: Vec::new(),
+        ignored: 0,
+    };
+    let truncated = walk.dir(root, ignore.as_ref())?;
+    let Walk {
+        mut listed,
+        ignored,
+        ..
+    } = walk;
+
+    // Names are enough to sort by name, so only the page's entries are stat'ed then
+    if sort != SortKey::Name {
+        for entry in &mut listed {
+            entry.metadata = fs.metadata(&entry.path).ok();
+        }
+    }
+    listed.sort_by(|a, b| {
+        let kind = if dirs_first {
+            (b.kind == FileKind::Dir).cmp(&(a.kind == FileKind::Dir))
+        } else {
+            Ordering::Equal
+        };
+        let key = match sort {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Mtime => modified(a).cmp(&modified(b)),
+            SortKey::Size => size(a).cmp(&size(b)),
+        }
+        .then_with(|| a.name.cmp(&b.name));
+        kind.then(if descending { key.reverse() } else { key })
+    });
+
+    let total = listed.len();
+    let (page, next_cursor) = crate::pagination::page(listed, offset, page_size);
+    let entries = page
+        .into_iter()
+        .map(|mut entry| {
+            if entry.metadata.is_none() {
+                entry.metadata = fs.metadata(&entry.path).ok();
+            }
+            FileListEntry {
+                size: (entry.kind == FileKind::File)
+                    .then(|| size(&entry))
+                    .flatten(),
+                modified: modified(&entry).map(|time| {
+                    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+                }),
+                kind: match entry.kind {
+                    FileKind::File => "file",
+                    FileKind::Dir => "dir",
+                    FileKind::Other => "other",
+                },
+                name: entry.name,
+            }
+        })
+        .collect();
+
+    Ok(FileListResponse {
+        path: request.path,
+        entries,
+        offset,
+        total,
+        ignored,
+        truncated,
+        next_cursor,
+    })
+}
+
+/// A traversal collecting the entries under a directory
+struct Walk<'a> {
+    fs: &'a dyn VirtualFs,
+    root: &'a Path,
+    recursive: bool,
+    listed: Vec<Listed>,
+    /// Entries left out by ignore files, not counting the contents of ignored directories
+    ignored: usize,
+}
+
+impl Walk<'_> {
+    /// Collect the entries under `dir`, returning whether the scan limit was reached
+    ///
+    /// Symbolic links are listed but not followed, so recursion cannot loop.
+    fn dir(&mut self, dir: &Path, ignore: Option<&IgnoreStack>) -> std::io::Result<bool> {
+        let mut entries = self.fs.read_dir(dir)?;
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in entries {
+            if self.listed.len() >= MAX_SCANNED_ENTRIES {
+                return Ok(true);
+            }
+            let is_dir = entry.kind == FileKind::Dir;
+            if ignore.is_some_and(|ignore| ignore.is_ignored(&entry.path, is_dir)) {
+                self.ignored += 1;
+                continue;
+            }
+            let name = entry
+                .path
+                .strip_prefix(self.root)
+                .unwrap_or(&entry.path)
+                .to_string_lossy()
+                .into_owned();
+            self.listed.push(Listed {
+                name,
+                path: entry.path.clone(),
+                kind: entry.kind,
+                metadata: None,
+            });
+            if self.recursive && is_dir {
+                let ignore = ignore.map(|ignore| ignore.enter(self.fs, &entry.path));
+                // Unreadable subdirectories are listed without their contents
+                if self.dir(&entry.path, ignore.as_ref()).unwrap_or(false) {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn modified(entry: &Listed) -> Option<std::time::SystemTime> {
+    entry
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.modified)
+}
+
+fn size(entry: &Listed) -> Option<u64> {
+    entry.metadata.as_ref().map(|metadata| metadata.len)
+}
+
+/// Get the tool definition for the file_list tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "file_list".to_string(),
+        description: Some(format!(
+            "List the entries of a directory within {}, optionally recursively, with their size and modification time. Entries are sorted by name, mtime or size, directories first, and returned in pages: pass the next cursor of a page to get the following one",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<FileListRequest>()),
+        ..Default::default()
+    }
+}
@@ -3,14 +3,36 @@
 //! This module provides functionality for reading files from the filesystem
 //! as part of the MCP server's tool capabilities.
 
+use crate::cancellation::CancellationToken;
+use crate::error::McpError;
+use crate::progress::ProgressReporter;
+use crate::tool_error::{ToolErrorCode, ToolErrorPayload};
+use crate::vfs::VirtualFs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::borrow::Cow;
+use std::io::Read;
 use std::path::Path;
 
+/// Directories the file tools may access
+///
+/// Advertised to clients through the `fs/roots` method.
+#[cfg(not(windows))]
+pub const ALLOWED_ROOTS: &[&str] = &["/tmp/allowed_files/"];
+#[cfg(windows)]
+pub const ALLOWED_ROOTS: &[&str] = &[r"C:\tmp\allowed_files\"];
+
+/// Files are read this much at a time, reporting progress between chunks
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
 /// File read tool request parameters
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct FileReadRequest {
+    /// The file path to read
     pub path: String,
+    /// Encoding of the file, e.g. latin1 or shift_jis; detected when omitted
+    #[serde(default)]
+    pub encoding: Option<String>,
 }
 
 /// File read tool response
@@ -20,77 +42,398 @@ pub struct FileReadResponse {
     pub path: String,
     pub size: usize,
     pub mime_type: Option<String>,
+    /// Encoding the content was decoded from, e.g. UTF-8 or windows-1252
+    pub encoding: String,
 }
 
 /// Execute the file read tool
+///
+/// The file is read in chunks, reporting the bytes read so far to `progress` and
+/// stopping once `cancel` is cancelled. Text in another encoding than UTF-8 is
+/// transcoded, see [`crate::text_encoding`].
 pub fn execute_file_read(
     request: FileReadRequest,
-) -> Result<FileReadResponse, Box<dyn std::error::Error>> {
-    let path = Path::new(&request.path);
-    if !request.path.starts_with("/tmp/allowed_files/") {
-        return Err("Access denied: File path must be within /tmp/allowed_files/".into());
-    }
+    fs: &dyn VirtualFs,
+    progress: &ProgressReporter,
+    cancel: &CancellationToken,
+) -> Result<FileReadResponse, McpError> {
+    let path = validate_path(fs, &request.path)?;
+
+    // Read the file content
+    let failed = |error: &dyn std::fmt::Display| -> McpError {
+        ToolErrorPayload::new(
+            ToolErrorCode::Failed,
+            format!("Failed to read file '{}': {}", request.path, error),
+        )
+        .into()
+    };
+    let bytes = read_with_progress(fs, path, progress, cancel).map_err(|e| failed(&e))?;
+    let decoded = crate::text_encoding::decode(&bytes, request.encoding.as_deref())
+        .map_err(|e| failed(&e))?;
 
-    // Validate the path
-    if !path.exists() {
-        return Err(format!("File not found: {}", request.path).into());
+    Ok(FileReadResponse {
+        content: decoded.text,
+        size: bytes.len(),
+        mime_type: crate::mime::detect(&bytes, &request.path),
+        encoding: decoded.encoding.to_string(),
+        path: request.path,
+    })
+}
+
+fn read_with_progress(
+    fs: &dyn VirtualFs,
+    path: &Path,
+    progress: &ProgressReporter,
+    cancel: &CancellationToken,
+) -> std::io::Result<Vec<u8>> {
+    let total = fs.metadata(path)?.len;
+    let mut file = fs.open(path)?;
+    let mut content = Vec::with_capacity(total as usize);
+    let mut chunk = vec![0; READ_CHUNK_SIZE];
+    loop {
+        if cancel.is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "read cancelled",
+            ));
+        }
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        content.extend_from_slice(&chunk[..read]);
+        crate::accounting::read(read as u64);
+        progress.report(content.len() as f64, Some(total as f64), None);
     }
+    Ok(content)
+}
 
-    if !path.is_file() {
-        return Err(format!("Path is not a file: {}", request.path).into());
+/// Check that a path is inside the allowed directory and points to an existing file
+///
+/// Shared by every tool that reads files so the access rules stay in one place.
+pub fn validate_path<'a>(fs: &dyn VirtualFs, path: &'a str) -> Result<&'a Path, McpError> {
+    if !is_within_allowed_roots(path) {
+        return Err(access_denied());
     }
 
-    // Read the file content
-    match fs::read_to_string(path) {
-        Ok(content) => {
-            let size = content.len();
-            let mime_type = guess_mime_type(&request.path);
-
-            Ok(FileReadResponse {
-                content,
-                path: request.path,
-                size,
-                mime_type,
-            })
+    let checked = Path::new(path);
+    let metadata = match fs.metadata(checked) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            return Err(access_denied());
+        }
+        Err(_) => {
+            return Err(ToolErrorPayload::not_found(format!("File not found: {}", path)).into());
         }
-        Err(error) => Err(format!("Failed to read file '{}': {}", request.path, error).into()),
+    };
+
+    if !metadata.is_file() {
+        return Err(ToolErrorPayload::new(
+            ToolErrorCode::Failed,
+            format!("Path is not a file: {}", path),
+        )
+        .into());
     }
+
+    Ok(checked)
 }
 
-/// Simple MIME type guessing based on file extension
-fn guess_mime_type(path: &str) -> Option<String> {
-    let path = Path::new(path);
-    match path.extension()?.to_str()? {
-        "txt" => Some("text/plain".to_string()),
-        "md" => Some("text/markdown".to_string()),
-        "rs" => Some("text/x-rust".to_string()),
-        "py" => Some("text/x-python".to_string()),
-        "js" => Some("text/javascript".to_string()),
-        "ts" => Some("text/typescript".to_string()),
-        "json" => Some("application/json".to_string()),
-        "xml" => Some("application/xml".to_string()),
-        "html" => Some("text/html".to_string()),
-        "css" => Some("text/css".to_string()),
-        "yaml" | "yml" => Some("application/x-yaml".to_string()),
-        "toml" => Some("application/toml".to_string()),
-        _ => None,
+/// Check that a path is inside the allowed directory and points to an existing directory
+pub fn validate_dir<'a>(fs: &dyn VirtualFs, path: &'a str) -> Result<&'a Path, McpError> {
+    if !is_within_allowed_roots(path) {
+        return Err(access_denied());
+    }
+
+    let checked = Path::new(path);
+    match fs.metadata(checked) {
+        Ok(metadata) if metadata.is_dir() => Ok(checked),
+        Ok(_) => Err(ToolErrorPayload::new(
+            ToolErrorCode::Failed,
+            format!("Path is not a directory: {}", path),
+        )
+        .into()),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Err(access_denied()),
+        Err(_) => Err(ToolErrorPayload::not_found(format!("Directory not found: {}", path)).into()),
     }
 }
 
+/// Check that a path a tool writes to is inside the allowed directory
+///
+/// The file itself may not exist yet, but its parent directory must.
+pub fn validate_output_path<'a>(fs: &dyn VirtualFs, path: &'a str) -> Result<&'a Path, McpError> {
+    let checked = Path::new(path);
+    if !is_within_allowed_roots(path)
+        || checked
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(access_denied());
+    }
+
+    if fs.metadata(checked).is_ok_and(|metadata| metadata.is_dir()) {
+        return Err(ToolErrorPayload::new(
+            ToolErrorCode::Failed,
+            format!("Path is a directory: {}", path),
+        )
+        .into());
+    }
+    if !checked
+        .parent()
+        .and_then(|parent| fs.metadata(parent).ok())
+        .is_some_and(|metadata| metadata.is_dir())
+    {
+        return Err(ToolErrorPayload::not_found(format!(
+            "Parent directory does not exist: {}",
+            path
+        ))
+        .into());
+    }
+
+    Ok(checked)
+}
+
+/// Whether a path starts with one of the allowed roots
+fn is_within_allowed_roots(path: &str) -> bool {
+    ALLOWED_ROOTS
+        .iter()
+        .any(|root| is_within(Path::new(path), Path::new(root)))
+}
+
+/// Whether `path` is `root` or inside it, comparing whole components
+///
+/// On Windows, paths are compared as the filesystem resolves them: `/` and `\` are
+/// both separators, the `\\?\` prefix of verbatim paths is ignored, drive letters
+/// and UNC shares (`\\server\share`) are prefixes, and case does not matter. Paths
+/// with `..` components are never inside, as they may lead out of the root.
+pub fn is_within(path: &Path, root: &Path) -> bool {
+    if cfg!(windows) {
+        is_within_windows(&path.to_string_lossy(), &root.to_string_lossy())
+    } else {
+        !path
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+            && path.starts_with(root)
+    }
+}
+
+/// [`is_within`] for Windows paths, whatever the platform
+fn is_within_windows(path: &str, root: &str) -> bool {
+    let path = windows_components(path);
+    let root = windows_components(root);
+    !root[0].is_empty() && !path.iter().any(|c| c == "..") && path.starts_with(&root)
+}
+
+/// A Windows path as its prefix (drive or UNC share, empty if it has none) followed
+/// by its components, lowercased
+///
+/// Windows ignores trailing dots and spaces in names, so `allowed.` is `allowed`.
+fn windows_components(path: &str) -> Vec<String> {
+    let path = path.replace('/', "\\");
+    let path = if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", share)
+    } else if let Some(local) = path
+        .strip_prefix(r"\\?\")
+        .or_else(|| path.strip_prefix(r"\\.\"))
+    {
+        local.to_string()
+    } else {
+        path
+    };
+
+    let (prefix, rest) = if let Some(share) = path.strip_prefix(r"\\") {
+        let mut parts = share.splitn(3, '\\');
+        let server = parts.next().unwrap_or_default();
+        let name = parts.next().unwrap_or_default();
+        (
+            format!(r"\\{}\{}", server, name),
+            parts.next().unwrap_or_default(),
+        )
+    } else if path.as_bytes().first().is_some_and(u8::is_ascii_alphabetic)
+        && path.as_bytes().get(1) == Some(&b':')
+    {
+        (path[..2].to_string(), &path[2..])
+    } else {
+        (String::new(), path.as_str())
+    };
+
+    std::iter::once(prefix.as_str())
+        .chain(
+            rest.split('\\')
+                .filter(|c| !c.is_empty() && *c != ".")
+                .map(|c| match c {
+                    ".." => c,
+                    _ => c.trim_end_matches(['.', ' ']),
+                }),
+        )
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The `file://` URI of a path, e.g. `file:///C:/data/` for `C:\data\` on Windows
+pub fn file_uri(path: &str) -> String {
+    if !cfg!(windows) {
+        return format!("file://{}", path);
+    }
+    let path = path.replace('\\', "/");
+    match path.strip_prefix("//") {
+        Some(share) => format!("file://{}", share),
+        None if path.starts_with('/') => format!("file://{}", path),
+        None => format!("file:///{}", path),
+    }
+}
+
+/// The path of a `file://` URI, e.g. `\\server\share\x` for `file://server/share/x` on
+/// Windows
+pub fn path_from_file_uri(uri: &str) -> Cow<'_, str> {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    if !cfg!(windows) {
+        return Cow::Borrowed(path);
+    }
+    match path.strip_prefix('/') {
+        Some(local) if local.as_bytes().get(1) == Some(&b':') => {
+            Cow::Owned(local.replace('/', "\\"))
+        }
+        Some(_) => Cow::Borrowed(path),
+        None if uri.starts_with("file://") && !path.is_empty() => {
+            Cow::Owned(format!(r"\\{}", path.replace('/', "\\")))
+        }
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// The allowed roots as tool descriptions and errors name them, e.g.
+/// `/tmp/allowed_files/`
+pub fn allowed_roots() -> String {
+    ALLOWED_ROOTS.join(" or ")
+}
+
+/// The error returned for paths outside the allowed roots
+fn access_denied() -> McpError {
+    ToolErrorPayload::access_denied(format!(
+        "Access denied: File path must be within {}",
+        allowed_roots()
+    ))
+    .into()
+}
+
 /// Get the tool definition for the file_read tool
 pub fn get_tool_definition() -> crate::mcp::McpTool {
     crate::mcp::McpTool {
         name: "file_read".to_string(),
-        description: Some("Read the contents of a file from the filesystem. The path must be within /tmp/allowed_files/".to_string()),
-        inputSchema: Some(serde_json::json!({
-            "type": "object",
-            "properties": {
-                "path": {
-                    "type": "string",
-                    "description": "The file path to read"
-                }
-            },
-            "required": ["path"]
-        })),
+        description: Some(format!(
+            "Read the contents of a file from the filesystem. The path must be within {}. Text in other encodings, such as latin-1, UTF-16 or Shift_JIS, is converted to UTF-8",
+            allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<FileReadRequest>()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = r"C:\tmp\allowed_files\";
+
+    #[test]
+    fn windows_paths_under_the_root_are_within() {
+        assert!(is_within_windows(r"C:\tmp\allowed_files\a.txt", ROOT));
+        assert!(is_within_windows(r"C:\tmp\allowed_files", ROOT));
+        assert!(is_within_windows(r"C:\tmp\allowed_files\sub\b.txt", ROOT));
+    }
+
+    #[test]
+    fn windows_drive_letters_must_match() {
+        assert!(!is_within_windows(r"D:\tmp\allowed_files\a.txt", ROOT));
+        assert!(!is_within_windows(r"\tmp\allowed_files\a.txt", ROOT));
+        assert!(!is_within_windows(r"tmp\allowed_files\a.txt", ROOT));
+    }
+
+    #[test]
+    fn windows_case_does_not_matter() {
+        assert!(is_within_windows(r"c:\TMP\Allowed_Files\A.TXT", ROOT));
+        assert!(is_within_windows(
+            r"C:\tmp\allowed_files\a.txt",
+            r"c:\TMP\ALLOWED_FILES"
+        ));
+    }
+
+    #[test]
+    fn windows_accepts_either_slash() {
+        assert!(is_within_windows("C:/tmp/allowed_files/a.txt", ROOT));
+        assert!(is_within_windows(r"C:/tmp\allowed_files/sub\a.txt", ROOT));
+        assert!(is_within_windows(
+            r"C:\tmp\allowed_files\a.txt",
+            "C:/tmp/allowed_files/"
+        ));
+    }
+
+    #[test]
+    fn windows_unc_shares_are_prefixes() {
+        let root = r"\\server\share\data";
+        assert!(is_within_windows(r"\\server\share\data\a.txt", root));
+        assert!(is_within_windows(r"\\SERVER\Share\data\a.txt", root));
+        assert!(!is_within_windows(r"\\server\other\data\a.txt", root));
+        assert!(!is_within_windows(r"\\other\share\data\a.txt", root));
+        assert!(!is_within_windows(r"C:\share\data\a.txt", root));
+    }
+
+    #[test]
+    fn windows_verbatim_prefixes_are_ignored() {
+        assert!(is_within_windows(r"\\?\C:\tmp\allowed_files\a.txt", ROOT));
+        assert!(is_within_windows(r"\\.\C:\tmp\allowed_files\a.txt", ROOT));
+        assert!(is_within_windows(
+            r"\\?\UNC\server\share\data\a.txt",
+            r"\\server\share\data"
+        ));
+        assert!(!is_within_windows(r"\\?\D:\tmp\allowed_files\a.txt", ROOT));
+    }
+
+    #[test]
+    fn windows_parent_components_are_refused() {
+        assert!(!is_within_windows(
+            r"C:\tmp\allowed_files\..\secret.txt",
+            ROOT
+        ));
+        assert!(!is_within_windows(
+            r"C:\tmp\allowed_files\sub\..\a.txt",
+            ROOT
+        ));
+        assert!(!is_within_windows(
+            "C:/tmp/allowed_files/../../Windows/win.ini",
+            ROOT
+        ));
+    }
+
+    #[test]
+    fn windows_lookalike_prefixes_are_not_within() {
+        assert!(!is_within_windows(r"C:\tmp\allowed_files2\a.txt", ROOT));
+        assert!(!is_within_windows(r"C:\tmp\allowed_files.bak\a.txt", ROOT));
+        assert!(!is_within_windows(r"C:\tmp\a.txt", ROOT));
+    }
+
+    #[test]
+    fn windows_trailing_dots_and_spaces_are_ignored() {
+        assert!(is_within_windows(r"C:\tmp\allowed_files.\a.txt", ROOT));
+        assert!(is_within_windows(r"C:\tmp\allowed_files \a.txt", ROOT));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn unix_paths_compare_whole_components() {
+        let root = Path::new("/tmp/allowed_files/");
+        assert!(is_within(Path::new("/tmp/allowed_files/a.txt"), root));
+        assert!(!is_within(Path::new("/tmp/allowed_files2/a.txt"), root));
+        assert!(!is_within(
+            Path::new("/tmp/allowed_files/../etc/passwd"),
+            root
+        ));
+        assert!(!is_within(Path::new("/TMP/allowed_files/a.txt"), root));
+    }
+
+    #[test]
+    fn descriptions_name_the_allowed_roots() {
+        let description = get_tool_definition().description.unwrap_or_default();
+        assert!(description.contains(ALLOWED_ROOTS[0]));
     }
 }
@@ -0,0 +1,407 @@
+//! File search tool module
+//!
+//! This module searches the files under a directory for text or a regular expression
+//! and returns the matching files ranked by relevance, so a search over a large
+//! repository surfaces the most useful hits first instead of a flood of matches. A
+//! file's score grows with the number of matches it contains (logarithmically, so one
+//! huge generated file does not bury everything else), with how close it sits to the
+//! searched directory, and with a boost when its name matches the query, most when
+//! the query is its whole name without extension. Only the best `max_results` files
+//! are returned, with the number of files and matches left out. As with `file_list`,
+//! entries excluded by `.gitignore` and `.ignore` files are skipped unless
+//! `no_ignore` is set, and binary or very large files are not searched.
+
+use crate::gitignore::IgnoreStack;
+use crate::vfs::{FileKind, VirtualFs};
+use regex::{Regex, RegexBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Upper bound on the length of a query
+const MAX_QUERY_LENGTH: usize = 1000;
+
+/// Upper bound on the size of a compiled query
+const MAX_COMPILED_SIZE: usize = 1 << 20;
+
+/// Default number of files returned
+const DEFAULT_RESULTS: usize = 20;
+
+/// Upper bound on the number of files returned
+const MAX_RESULTS: usize = 200;
+
+/// Matching lines shown per file
+const MAX_LINES_PER_HIT: usize = 5;
+
+/// Characters of a matching line shown
+const MAX_LINE_CHARS: usize = 200;
+
+/// Files larger than this are matched by name only
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Files examined at most; the rest are left out
+const MAX_SCANNED_FILES: usize = 20_000;
+
+/// Searching stops after this long and ranks what was found so far
+const TIME_LIMIT: Duration = Duration::from_secs(5);
+
+/// Weight of the number of matches in a file's score
+const MATCH_WEIGHT: f64 = 1.0;
+
+/// Score of a file directly in the searched directory, halved one level down and so on
+const PROXIMITY_WEIGHT: f64 = 1.0;
+
+/// Boost of a file whose name contains a match of the query
+const NAME_BOOST: f64 = 2.0;
+
+/// Boost of a file whose name without extension is a match of the query
+const EXACT_NAME_BOOST: f64 = 4.0;
+
+/// File search tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FileSearchRequest {
+    /// The directory to search
+    pub path: String,
+    /// The text to search for, or a regular expression when `regex` is set
+    pub query: String,
+    /// Treat the query as a regular expression (Rust regex syntax, default false)
+    pub regex: Option<bool>,
+    /// Match case-insensitively (default false)
+    pub ignore_case: Option<bool>,
+    /// Only search files with these extensions, e.g. ["rs", "toml"]
+    pub extensions: Option<Vec<String>>,
+    /// Also search entries excluded by .gitignore and .ignore files, and .git
+    /// directories (default false)
+    pub no_ignore: Option<bool>,
+    /// Maximum number of files returned, best first (default 20, max 200)
+    pub max_results: Option<usize>,
+}
+
+/// A matching line of a file
+#[derive(Debug, Serialize)]
+pub struct FileSearchLine {
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+}
+
+/// A file matching the query
+#[derive(Debug, Serialize)]
+pub struct FileSearchHit {
+    /// Path relative to the searched directory
+    pub path: String,
+    /// Relevance of the file; higher is better
+    pub score: f64,
+    /// Matches in the file's contents
+    pub matches: usize,
+    /// Whether the file's name matches the query
+    pub name_match: bool,
+    /// The first matching lines
+    pub lines: Vec<FileSearchLine>,
+}
+
+/// File search tool response
+#[derive(Debug, Serialize)]
+pub struct FileSearchResponse {
+    pub path: String,
+    pub query: String,
+    pub hits: Vec<FileSearchHit>,
+    /// Matching files left out by max_results
+    pub omitted_files: usize,
+    /// Matches in the files left out
+    pub omitted_matches: usize,
+    /// Files not searched because they are binary or too large
+    pub skipped: usize,
+    /// Whether the search stopped at the file or time limit
+    pub truncated: bool,
+}
+
+impl FileSearchResponse {
+    /// Render the response as compact text for the model
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Search: {} in {}\n", self.query, self.path);
+        if self.hits.is_empty() {
+            text.push_str("No matches\n");
+        }
+        for hit in &self.hits {
+            let name = if hit.name_match { ", name matches" } else { "" };
+            text.push_str(&format!(
+                "\n{} (score {:.2}, {} matches{})\n",
+                hit.path, hit.score, hit.matches, name
+            ));
+            for line in &hit.lines {
+                text.push_str(&format!("{:>6}: {}\n", line.line, line.text));
+            }
+        }
+        if self.omitted_files > 0 {
+            text.push_str(&format!(
+                "\n{} more matches in {} files omitted (raise max_results or narrow the query)\n",
+                self.omitted_matches, self.omitted_files
+            ));
+        }
+        if self.skipped > 0 {
+            text.push_str(&format!(
+                "Skipped: {} binary or large files searched by name only\n",
+                self.skipped
+            ));
+        }
+        if self.truncated {
+            text.push_str("Truncated: the search stopped early; search a subdirectory instead\n");
+        }
+        text
+    }
+}
+
+/// Execute the file search tool
+pub fn execute_file_search(
+    request: FileSearchRequest,
+    fs: &dyn VirtualFs,
+) -> Result<FileSearchResponse, Box<dyn std::error::Error>> {
+    let root = crate::file_read::validate_dir(fs, &request.path)?;
+    if request.query.is_empty() {
+        return Err("The query must not be empty".into());
+    }
+    if request.query.len() > MAX_QUERY_LENGTH {
+        return Err(crate::tool_error::ToolErrorPayload::too_large(
+            format!("Query exceeds the {} character limit", MAX_QUERY_LENGTH),
+            MAX_QUERY_LENGTH as u64,
+        )
+        .into());
+    }
+    let pattern = if request.regex.unwrap_or(false) {
+        request.query.clone()
+    } else {
+        regex::escape(&request.query)
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(request.ignore_case.unwrap_or(false))
+        .size_limit(MAX_COMPILED_SIZE)
+        .dfa_size_limit(MAX_COMPILED_SIZE)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))?;
+    let extensions: Option<Vec<String>> = request.extensions.map(|extensions| {
+        extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_ascii_lowercase())
+            .collect()
+    });
+    let limit = request
+        .max_results
+        .unwrap_or(DEFAULT_RESULTS)
+        .clamp(1, MAX_RESULTS);
+
+    let ignore = (!request.no_ignore.unwrap_or(false)).then(|| IgnoreStack::for_dir(fs, root));
+    let mut search = Search {
+        fs,
+        root,
+        regex: &regex,
+        extensions: extensions.as_deref(),
+        started: Instant::now(),
+        scanned: 0,
+        skipped: 0,
+        hits: Vec::new(),
+    };
+    let truncated = search.dir(root, 0, ignore.as_ref())?;
+    let Search {
+        mut hits, skipped, ..
+    } = search;
+
+    hits.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    let omitted = hits.split_off(hits.len().min(limit));
+
+    Ok(FileSearchResponse {
+        path: request.path,
+        query: request.query,
+        hits,
+        omitted_files: omitted.len(),
+        omitted_matches: omitted.iter().map(|hit| hit.matches).sum(),
+        skipped,
+        truncated,
+    })
+}
+
+/// A traversal matching the files under a directory
+struct Search<'a> {
+    fs: &'a dyn VirtualFs,
+    root: &'a Path,
+    regex: &'a Regex,
+    extensions: Option<&'a [String]>,
+    started: Instant,
+    /// Files examined so far
+    scanned: usize,
+    /// Files matched by name only because they are binary or too large
+    skipped: usize,
+    hits: Vec<FileSearchHit>,
+}
+
+impl Search<'_> {
+    /// Search the files under `dir`, `depth` levels below the root, returning whether
+    /// a limit was reached
+    ///
+    /// Symbolic links are not followed, so recursion cannot loop.
+    fn dir(
+        &mut self,
+        dir: &Path,
+        depth: usize,
+        ignore: Option<&IgnoreStack>,
+    ) -> std::io::Result<bool> {
+        let mut entries = self.fs.read_dir(dir)?;
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in entries {
+            if self.scanned >= MAX_SCANNED_FILES || self.started.elapsed() > TIME_LIMIT {
+                return Ok(true);
+            }
+            let is_dir = entry.kind == FileKind::Dir;
+            if ignore.is_some_and(|ignore| ignore.is_ignored(&entry.path, is_dir)) {
+                continue;
+            }
+            match entry.kind {
+                FileKind::Dir => {
+                    let ignore = ignore.map(|ignore| ignore.enter(self.fs, &entry.path));
+                    // Unreadable subdirectories are skipped
+                    if self
+                        .dir(&entry.path, depth + 1, ignore.as_ref())
+                        .unwrap_or(false)
+                    {
+                        return Ok(true);
+                    }
+                }
+                FileKind::File if self.has_extension(&entry.path) => {
+                    self.scanned += 1;
+                    if let Some(hit) = self.file(&entry.path, depth) {
+                        self.hits.push(hit);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+
+    fn has_extension(&self, path: &Path) -> bool {
+        self.extensions.is_none_or(|extensions| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    extensions
+                        .iter()
+                        .any(|wanted| extension.eq_ignore_ascii_case(wanted))
+                })
+        })
+    }
+
+    /// Match a file's name and contents, returning a hit if either matches
+    fn file(&mut self, path: &Path, depth: usize) -> Option<FileSearchHit> {
+        let name = path.file_name()?.to_string_lossy();
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let exact_name = self
+            .regex
+            .find(&stem)
+            .is_some_and(|found| found.start() == 0 && found.end() == stem.len());
+        let name_match = exact_name || self.regex.is_match(&name);
+
+        let (matches, lines) = match self.contents(path) {
+            Some(contents) => search_text(self.regex, &contents),
+            None => {
+                self.skipped += 1;
+                (0, Vec::new())
+            }
+        };
+        if matches == 0 && !name_match {
+            return None;
+        }
+
+        let boost = if exact_name {
+            EXACT_NAME_BOOST
+        } else if name_match {
+            NAME_BOOST
+        } else {
+            0.0
+        };
+        Some(FileSearchHit {
+            path: path
+                .strip_prefix(self.root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned(),
+            score: round(score(matches, depth) + boost),
+            matches,
+            name_match,
+            lines,
+        })
+    }
+
+    /// The text of a file, unless it is unreadable, binary or too large
+    fn contents(&self, path: &Path) -> Option<String> {
+        if self.fs.metadata(path).ok()?.len > MAX_FILE_BYTES {
+            return None;
+        }
+        let bytes = self.fs.read(path).ok()?;
+        crate::accounting::read(bytes.len() as u64);
+        if bytes[..bytes.len().min(crate::mime::SNIFF_LEN)].contains(&0) {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Count the matches in a text and collect the first matching lines
+fn search_text(regex: &Regex, text: &str) -> (usize, Vec<FileSearchLine>) {
+    let mut matches = 0;
+    let mut lines = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let count = regex.find_iter(line).count();
+        if count == 0 {
+            continue;
+        }
+        matches += count;
+        if lines.len() < MAX_LINES_PER_HIT {
+            let trimmed = line.trim();
+            let mut shown: String = trimmed.chars().take(MAX_LINE_CHARS).collect();
+            if shown.len() < trimmed.len() {
+                shown.push('…');
+            }
+            lines.push(FileSearchLine {
+                line: index + 1,
+                text: shown,
+            });
+        }
+    }
+    (matches, lines)
+}
+
+/// The score of a file from its number of matches and its depth below the root
+fn score(matches: usize, depth: usize) -> f64 {
+    MATCH_WEIGHT * (matches as f64).ln_1p() + PROXIMITY_WEIGHT * 0.5f64.powi(depth as i32)
+}
+
+/// Round a score to two decimals for display
+fn round(score: f64) -> f64 {
+    (score * 100.0).round() / 100.0
+}
+
+/// Get the tool definition for the file_search tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "file_search".to_string(),
+        version: None,
+        description: Some(format!(
+            "Search the files under a directory within {} for text or a regular expression. Returns the most relevant files first, ranked by number of matches, closeness to the directory and whether the file name matches, with their first matching lines; pass max_results to see more files",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<FileSearchRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
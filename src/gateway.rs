@@ -0,0 +1,580 @@
+//! Gateway module
+//!
+//! This module lets the server front other MCP servers ("upstreams"). Their tools are
+//! listed next to the local ones under a `<upstream>__` prefix and calls are
+//! forwarded. Tool lists are fetched from all upstreams concurrently with a timeout
+//! per upstream, so one dead upstream only removes its own tools, and the health of
+//! every upstream is reported by the `gateway/status` method. Each upstream has a
+//! circuit breaker, so one that keeps failing is skipped without waiting for timeouts.
+//! A tool offered by several upstreams, such as old and new deployments during a
+//! rollout, is listed once, under the upstream with its newest version, and calls to
+//! it through any of them are sent to that upstream.
+
+use crate::circuit_breaker::{self, CircuitBreaker, CircuitState};
+use crate::config::UpstreamConfig;
+use crate::deadline::Deadline;
+use crate::mcp::{JsonRpcError, McpClient, McpServerState, McpTool};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Separator between the upstream name and the tool name in gateway tool names
+pub const TOOL_SEPARATOR: &str = "__";
+
+/// Default time allowed for one upstream request
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Health of an upstream, as reported by `gateway/status`
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamStatus {
+    pub name: String,
+    /// URL or command line of the upstream
+    pub endpoint: String,
+    /// Whether the last request succeeded; absent until the upstream is first used
+    pub healthy: Option<bool>,
+    /// Error of the last request, if it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Number of tools in the last successful tools/list
+    pub tools: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<DateTime<Utc>>,
+    /// State of the upstream's circuit breaker
+    pub circuit: CircuitState,
+}
+
+/// Response from gateway/status request
+#[derive(Debug, Serialize)]
+pub struct GatewayStatus {
+    pub upstreams: Vec<UpstreamStatus>,
+}
+
+/// An upstream MCP server and its connection
+struct Upstream {
+    config: UpstreamConfig,
+    /// Initialized client, dropped after a failure so the next request reconnects
+    client: Mutex<Option<Arc<McpClient>>>,
+    status: Mutex<UpstreamStatus>,
+    breaker: CircuitBreaker,
+}
+
+impl Upstream {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+
+    /// Get the connected client, connecting and initializing it first if needed
+    fn connect(&self) -> Result<Arc<McpClient>, String> {
+        if let Some(client) = self.client.lock().map_err(|e| e.to_string())?.as_ref() {
+            return Ok(Arc::clone(client));
+        }
+
+        let client = match (&self.config.url, &self.config.command) {
+            (Some(url), None) => McpClient::new(url),
+            (None, Some(command)) => {
+                McpClient::spawn(command, &self.config.args).map_err(|e| e.to_string())?
+            }
+            _ => return Err("Upstream needs exactly one of url or command".to_string()),
+        };
+        client.initialize().map_err(|e| e.to_string())?;
+
+        let client = Arc::new(client);
+        *self.client.lock().map_err(|e| e.to_string())? = Some(Arc::clone(&client));
+        Ok(client)
+    }
+
+    /// Run a request on a blocking thread, bounded by the upstream's timeout
+    ///
+    /// The outcome is recorded in the upstream's status and circuit breaker; a failed
+    /// connection is dropped so the next request reconnects. While the circuit is open
+    /// the request fails immediately.
+    async fn run<T, F>(self: &Arc<Self>, request: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&McpClient) -> Result<T, String> + Send + 'static,
+    {
+        // Dropping the permit, as when the call is cancelled, counts a probe as failed
+        let permit = self.breaker.allow().map_err(|e| e.to_string())?;
+
+        let started = Instant::now();
+        let upstream = Arc::clone(self);
+        let task = tokio::task::spawn_blocking(move || {
+            let result = upstream.connect().and_then(|client| request(&client));
+            // Blocking clients must not be dropped on the async runtime, so a failed
+            // connection is released here
+            if result.is_err()
+                && let Ok(mut client) = upstream.client.lock()
+            {
+                *client = None;
+            }
+            result
+        });
+
+        let result = match tokio::time::timeout(self.timeout(), task).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => Err(format!("Upstream request failed: {}", e)),
+            Err(_) => Err(format!("Timed out after {}s", self.timeout().as_secs())),
+        };
+
+        match &result {
+            Ok(_) => permit.record_success(),
+            Err(_) => permit.record_failure(),
+        }
+        if let Ok(mut status) = self.status.lock() {
+            status.healthy = Some(result.is_ok());
+            status.error = result.as_ref().err().cloned();
+            status.latency_ms = Some(started.elapsed().as_millis() as u64);
+            status.checked_at = Some(Utc::now());
+        }
+        result
+    }
+}
+
+/// Upstream MCP servers fronted by this server
+pub struct Gateway {
+    upstreams: Vec<Arc<Upstream>>,
+    /// Index of the upstream with the newest version of each tool, as last listed
+    newest: Mutex<HashMap<String, usize>>,
+}
+
+impl std::fmt::Debug for Gateway {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.upstreams.iter().map(|upstream| &upstream.config.name))
+            .finish()
+    }
+}
+
+impl Gateway {
+    /// Create a gateway for the configured upstreams; connections are opened on first use
+    pub fn new(configs: &[UpstreamConfig]) -> Self {
+        let upstreams = configs
+            .iter()
+            .map(|config| {
+                let endpoint = match (&config.url, &config.command) {
+                    (Some(url), _) => url.clone(),
+                    (None, Some(command)) => std::iter::once(command)
+                        .chain(&config.args)
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    (None, None) => String::new(),
+                };
+                Arc::new(Upstream {
+                    config: config.clone(),
+                    client: Mutex::new(None),
+                    status: Mutex::new(UpstreamStatus {
+                        name: config.name.clone(),
+                        endpoint,
+                        healthy: None,
+                        error: None,
+                        tools: 0,
+                        latency_ms: None,
+                        checked_at: None,
+                        circuit: CircuitState::Closed,
+                    }),
+                    breaker: CircuitBreaker::new(
+                        &config.name,
+                        config
+                            .failure_threshold
+                            .unwrap_or(circuit_breaker::DEFAULT_FAILURE_THRESHOLD),
+                        config
+                            .reset_secs
+                            .map(Duration::from_secs)
+                            .unwrap_or(circuit_breaker::DEFAULT_RESET_TIMEOUT),
+                    ),
+                })
+            })
+            .collect();
+        Self {
+            upstreams,
+            newest: Mutex::default(),
+        }
+    }
+
+    /// Fetch the tools of every upstream concurrently
+    ///
+    /// Tools are renamed to `<upstream>__<tool>`. Upstreams that fail or time out are
+    /// left out of the list and marked unhealthy. A tool listed several times, by
+    /// several upstreams or by one that is a gateway of its own, is only listed with
+    /// its newest version; calls to it are routed to the upstream offering it.
+    pub async fn list_tools(&self) -> Vec<McpTool> {
+        let requests = self.upstreams.iter().map(|upstream| async move {
+            let result = upstream
+                .run(|client| client.fetch_tools().map_err(|e| e.to_string()))
+                .await;
+            (upstream, result)
+        });
+
+        let mut tools = Vec::new();
+        for (index, (upstream, result)) in futures::future::join_all(requests)
+            .await
+            .into_iter()
+            .enumerate()
+        {
+            let Ok(upstream_tools) = result else {
+                continue;
+            };
+            if let Ok(mut status) = upstream.status.lock() {
+                status.tools = upstream_tools.len();
+            }
+            tools.extend(upstream_tools.into_iter().map(|tool| (index, tool)));
+        }
+
+        let tools = newest_versions(tools);
+        if let Ok(mut newest) = self.newest.lock() {
+            *newest = tools
+                .iter()
+                .map(|(index, tool)| (tool.name.clone(), *index))
+                .collect();
+        }
+        tools
+            .into_iter()
+            .map(|(index, tool)| McpTool {
+                name: format!(
+                    "{}{}{}",
+                    self.upstreams[index].config.name, TOOL_SEPARATOR, tool.name
+                ),
+                ..tool
+            })
+            .collect()
+    }
+
+    /// Whether a tool name belongs to one of the upstreams
+    pub fn owns(&self, name: &str) -> bool {
+        self.route(name).is_some()
+    }
+
+    /// Split a gateway tool name into its upstream and the upstream's tool name
+    ///
+    /// A tool listed by several upstreams goes to the one with its newest version,
+    /// whichever upstream the name was prefixed with.
+    fn route(&self, name: &str) -> Option<(&Arc<Upstream>, String)> {
+        let (upstream_name, tool) = name.split_once(TOOL_SEPARATOR)?;
+        let named = self
+            .upstreams
+            .iter()
+            .position(|upstream| upstream.config.name == upstream_name)?;
+        let index = self
+            .newest
+            .lock()
+            .ok()
+            .and_then(|newest| newest.get(tool).copied())
+            .unwrap_or(named);
+        Some((&self.upstreams[index], tool.to_string()))
+    }
+
+    /// Forward a tool call to its upstream, emitting a server event with the outcome
+    pub async fn call_tool(
+        &self,
+        state: &McpServerState,
+        name: &str,
+        arguments: Option<Value>,
+        deadline: Option<Deadline>,
+    ) -> Result<Value, JsonRpcError> {
+        use crate::events::{EventKind, ServerEvent};
+
+        let Some((upstream, tool)) = self.route(name) else {
+            return Err(JsonRpcError {
+                code: -32601,
+                message: format!("Tool '{}' not found", name),
+                data: None,
+            });
+        };
+
+        let started = Instant::now();
+        let result = upstream
+            .run(move |client| {
+                match deadline {
+                    Some(deadline) => client.call_tool_with_deadline(&tool, arguments, deadline),
+                    None => client.call_tool(&tool, arguments),
+                }
+                .map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| JsonRpcError {
+                code: -32603,
+                message: format!("Upstream '{}' failed: {}", upstream.config.name, e),
+                data: None,
+            });
+
+        let event = match &result {
+            Ok(result) if result.get("isError").and_then(Value::as_bool) == Some(true) => {
+                ServerEvent::new(EventKind::ToolCallFailed).message(
+                    result["content"][0]["text"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                )
+            }
+            Ok(_) => ServerEvent::new(EventKind::ToolCallCompleted),
+            Err(e) => ServerEvent::new(EventKind::ToolCallFailed).message(e.message.clone()),
+        };
+        state.emit_event(
+            event
+                .tool(name)
+                .duration_ms(started.elapsed().as_millis() as u64),
+        );
+        result
+    }
+
+    /// Health of every upstream as of its last request
+    pub fn status(&self) -> GatewayStatus {
+        GatewayStatus {
+            upstreams: self
+                .upstreams
+                .iter()
+                .filter_map(|upstream| {
+                    let mut status = upstream.status.lock().ok()?.clone();
+                    status.circuit = upstream.breaker.state();
+                    Some(status)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Keep the newest version of every tool listed more than once, with the index of the
+/// upstream listing it, in listing order; of equal versions the first listed is kept
+fn newest_versions(tools: Vec<(usize, McpTool)>) -> Vec<(usize, McpTool)> {
+    let mut newest: Vec<(usize, McpTool)> = Vec::with_capacity(tools.len());
+    for (index, tool) in tools {
+        match newest.iter_mut().find(|(_, kept)| kept.name == tool.name) {
+            Some(kept) => {
+                if compare_versions(tool.version.as_deref(), kept.1.version.as_deref()).is_gt() {
+                    *kept = (index, tool);
+                }
+            }
+            None => newest.push((index, tool)),
+        }
+    }
+    newest
+}
+
+/// Order versions as semver does: `1.10.0` after `1.9.2`, comparing numeric parts as
+/// numbers, and a pre-release such as `1.0.0-beta` before its release; build metadata
+/// after `+` is ignored. A tool without a version is older than any with one
+fn compare_versions(a: Option<&str>, b: Option<&str>) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let (a_release, a_pre) = split_version(a);
+            let (b_release, b_pre) = split_version(b);
+            compare_parts(a_release, b_release).then_with(|| match (a_pre, b_pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_parts(a, b),
+            })
+        }
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// The release and pre-release parts of a version, without a leading `v` and build
+/// metadata
+fn split_version(version: &str) -> (&str, Option<&str>) {
+    let version = version.trim_start_matches('v');
+    let version = version.split('+').next().unwrap_or_default();
+    match version.split_once('-') {
+        Some((release, pre)) => (release, Some(pre)),
+        None => (version, None),
+    }
+}
+
+/// Compare dot-separated identifiers: numbers as numbers and before other text, and a
+/// shorter list before a longer one it starts
+fn compare_parts(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let (a, b): (Vec<&str>, Vec<&str>) = (a.split('.').collect(), b.split('.').collect());
+    for (x, y) in a.iter().zip(&b) {
+        let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            _ => x.cmp(y),
+        };
+        if order.is_ne() {
+            return order;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    fn tool(name: &str, version: Option<&str>) -> McpTool {
+        McpTool {
+            name: name.to_string(),
+            version: version.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn numeric_parts_compare_as_numbers() {
+        assert_eq!(
+            compare_versions(Some("1.10.0"), Some("1.9.2")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_versions(Some("v2.0.0"), Some("1.99.99")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_versions(Some("1.2.3"), Some("v1.2.3")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn pre_releases_sort_below_their_release() {
+        assert_eq!(
+            compare_versions(Some("1.0.0-beta"), Some("1.0.0")),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_versions(Some("1.0.0"), Some("1.0.0-rc.1")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_versions(Some("1.0.0-beta"), Some("0.9.9")),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn pre_releases_compare_as_semver() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert_eq!(
+                compare_versions(Some(pair[0]), Some(pair[1])),
+                Ordering::Less,
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn build_metadata_is_ignored() {
+        assert_eq!(
+            compare_versions(Some("1.0.0+build.5"), Some("1.0.0")),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare_versions(Some("1.0.0-beta+exp"), Some("1.0.0")),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn unversioned_tools_are_oldest() {
+        assert_eq!(compare_versions(None, Some("0.0.1-alpha")), Ordering::Less);
+        assert_eq!(compare_versions(None, None), Ordering::Equal);
+    }
+
+    #[test]
+    fn newest_versions_prefer_the_release_over_its_pre_release() {
+        let tools = newest_versions(vec![
+            (0, tool("search", Some("1.0.0"))),
+            (0, tool("fetch", None)),
+            (1, tool("search", Some("1.0.0-beta"))),
+            (1, tool("fetch", Some("0.1.0"))),
+        ]);
+        let versions: Vec<(usize, &str, Option<&str>)> = tools
+            .iter()
+            .map(|(index, tool)| (*index, tool.name.as_str(), tool.version.as_deref()))
+            .collect();
+        assert_eq!(
+            versions,
+            [(0, "search", Some("1.0.0")), (1, "fetch", Some("0.1.0"))]
+        );
+    }
+
+    /// Serve an upstream offering `search` at `version`, answering calls with the
+    /// version, plus the given other tools
+    async fn upstream(version: &'static str, others: &'static [&'static str]) -> String {
+        use axum::{Json, Router, http::StatusCode, response::IntoResponse, routing::post};
+        use serde_json::json;
+
+        let app = Router::new().route(
+            "/mcp",
+            post(move |Json(request): Json<Value>| async move {
+                let result = match request["method"].as_str() {
+                    Some("initialize") => json!({
+                        "protocolVersion": crate::mcp::LATEST_PROTOCOL_VERSION,
+                        "capabilities": {},
+                        "serverInfo": {"name": "upstream", "version": version},
+                    }),
+                    Some("tools/list") => {
+                        let mut tools = vec![json!({"name": "search", "version": version})];
+                        tools.extend(others.iter().map(|name| json!({"name": name})));
+                        json!({ "tools": tools })
+                    }
+                    Some("tools/call") => json!({
+                        "content": [{"type": "text", "text": version}],
+                    }),
+                    _ => return StatusCode::ACCEPTED.into_response(),
+                };
+                Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+                    .into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    fn upstream_config(name: &str, url: &str) -> UpstreamConfig {
+        serde_json::from_value(serde_json::json!({"name": name, "url": url})).unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tools_of_several_upstreams_resolve_to_the_newest_version() {
+        let old = upstream("1.9.2", &["fetch"]).await;
+        let new = upstream("1.10.0", &[]).await;
+        let gateway = Gateway::new(&[upstream_config("old", &old), upstream_config("new", &new)]);
+
+        let names: Vec<String> = gateway
+            .list_tools()
+            .await
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert_eq!(names, ["new__search", "old__fetch"]);
+
+        // Calls through the older upstream's name go to the newer upstream as well
+        let state = McpServerState::new();
+        for name in ["new__search", "old__search"] {
+            let result = gateway.call_tool(&state, name, None, None).await.unwrap();
+            assert_eq!(result["content"][0]["text"], "1.10.0");
+        }
+        let result = gateway
+            .call_tool(&state, "old__fetch", None, None)
+            .await
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "1.9.2");
+    }
+}
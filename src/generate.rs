@@ -0,0 +1,191 @@
+//! Value generation tool module
+//!
+//! This module generates UUIDs, random bytes and passwords from a cryptographically
+//! secure random number generator, so agents use real random values in scaffolding
+//! tasks instead of inventing them. Passwords contain at least one character from
+//! every enabled character class.
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use rand::Rng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on the number of values generated per call
+const MAX_COUNT: usize = 100;
+
+/// Default number of random bytes
+const DEFAULT_BYTES: usize = 32;
+
+/// Upper bound on the number of random bytes
+const MAX_BYTES: usize = 1024;
+
+/// Default password length
+const DEFAULT_PASSWORD_LENGTH: usize = 20;
+
+/// Shortest and longest password lengths accepted
+const PASSWORD_LENGTHS: std::ops::RangeInclusive<usize> = 8..=256;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+
+/// Characters that are easily confused with each other
+const AMBIGUOUS: &str = "0O1lI|";
+
+/// Generate tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GenerateRequest {
+    /// What to generate
+    #[schemars(extend("enum" = ["uuid", "bytes", "password"]))]
+    pub kind: String,
+    /// Number of values to generate (default 1, max 100)
+    pub count: Option<usize>,
+    /// Number of bytes (default 32, max 1024) or password characters (default 20, 8 to 256)
+    pub length: Option<usize>,
+    /// Encoding of random bytes (default hex)
+    #[schemars(extend("enum" = ["hex", "base64", "base64url"]))]
+    pub encoding: Option<String>,
+    /// Include lowercase letters in passwords (default true)
+    pub lowercase: Option<bool>,
+    /// Include uppercase letters in passwords (default true)
+    pub uppercase: Option<bool>,
+    /// Include digits in passwords (default true)
+    pub digits: Option<bool>,
+    /// Include symbols in passwords (default true)
+    pub symbols: Option<bool>,
+    /// Leave out look-alike characters such as 0, O, 1, l and I (default false)
+    pub exclude_ambiguous: Option<bool>,
+}
+
+/// Generate tool response
+#[derive(Debug, Serialize)]
+pub struct GenerateResponse {
+    pub kind: String,
+    pub values: Vec<String>,
+}
+
+/// Execute the generate tool
+pub fn execute_generate(
+    request: GenerateRequest,
+) -> Result<GenerateResponse, Box<dyn std::error::Error>> {
+    let count = request.count.unwrap_or(1);
+    if !(1..=MAX_COUNT).contains(&count) {
+        return Err(format!("count must be between 1 and {}", MAX_COUNT).into());
+    }
+
+    let values = match request.kind.as_str() {
+        "uuid" => (0..count)
+            .map(|_| uuid::Uuid::new_v4().to_string())
+            .collect(),
+        "bytes" => {
+            let length = request.length.unwrap_or(DEFAULT_BYTES);
+            if !(1..=MAX_BYTES).contains(&length) {
+                return Err(format!("length must be between 1 and {}", MAX_BYTES).into());
+            }
+            let encode: fn(&[u8]) -> String = match request.encoding.as_deref().unwrap_or("hex") {
+                "hex" => |bytes| bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+                "base64" => |bytes| STANDARD.encode(bytes),
+                "base64url" => |bytes| URL_SAFE_NO_PAD.encode(bytes),
+                other => {
+                    return Err(format!(
+                        "Unknown encoding '{}', expected hex, base64 or base64url",
+                        other
+                    )
+                    .into());
+                }
+            };
+            let mut rng = rand::rng();
+            (0..count)
+                .map(|_| {
+                    let mut bytes = vec![0u8; length];
+                    rng.fill(bytes.as_mut_slice());
+                    encode(&bytes)
+                })
+                .collect()
+        }
+        "password" => {
+            let classes = password_classes(&request);
+            if classes.is_empty() {
+                return Err("At least one character class must be enabled".into());
+            }
+            let length = request.length.unwrap_or(DEFAULT_PASSWORD_LENGTH);
+            if !PASSWORD_LENGTHS.contains(&length) {
+                return Err(format!(
+                    "length must be between {} and {}",
+                    PASSWORD_LENGTHS.start(),
+                    PASSWORD_LENGTHS.end()
+                )
+                .into());
+            }
+            (0..count)
+                .map(|_| generate_password(&classes, length))
+                .collect()
+        }
+        other => {
+            return Err(
+                format!("Unknown kind '{}', expected uuid, bytes or password", other).into(),
+            );
+        }
+    };
+
+    Ok(GenerateResponse {
+        kind: request.kind,
+        values,
+    })
+}
+
+/// The enabled character classes of a password request
+fn password_classes(request: &GenerateRequest) -> Vec<Vec<char>> {
+    let exclude_ambiguous = request.exclude_ambiguous.unwrap_or(false);
+    [
+        (request.lowercase, LOWERCASE),
+        (request.uppercase, UPPERCASE),
+        (request.digits, DIGITS),
+        (request.symbols, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| enabled.unwrap_or(true))
+    .map(|(_, class)| {
+        class
+            .chars()
+            .filter(|c| !exclude_ambiguous || !AMBIGUOUS.contains(*c))
+            .collect()
+    })
+    .collect()
+}
+
+/// Generate a password with at least one character from every class
+fn generate_password(classes: &[Vec<char>], length: usize) -> String {
+    let mut rng = rand::rng();
+    let all: Vec<char> = classes.concat();
+
+    let mut password: Vec<char> = classes
+        .iter()
+        .filter_map(|class| class.choose(&mut rng).copied())
+        .collect();
+    while password.len() < length {
+        password.extend(all.choose(&mut rng));
+    }
+    password.shuffle(&mut rng);
+
+    password.into_iter().collect()
+}
+
+/// Get the tool definition for the generate tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "generate".to_string(),
+        version: None,
+        description: Some("Generate UUIDs (v4), random bytes (hex, base64 or base64url) or passwords with a cryptographically secure random generator. Use this whenever a task needs real random values, identifiers or secrets.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<GenerateRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            idempotent_hint: Some(false),
+            ..Default::default()
+        }),
+    }
+}
@@ -0,0 +1,197 @@
+//! Gitignore module
+//!
+//! This module decides which paths a directory traversal skips because a `.gitignore`
+//! or `.ignore` file excludes them, so `file_list` and `file_search` leave out build
+//! output, dependencies and other noise when exploring a source tree. The patterns of
+//! each file are matched by the `ignore` crate with git's rules (negation, anchoring,
+//! directory-only patterns and `**`), while the files are read through the server's
+//! [`VirtualFs`]. The files of deeper directories take precedence over those above
+//! them, with `.ignore` over `.gitignore` in the same directory. Ignored directories
+//! are not entered, so their contents cannot be re-included, as with git.
+
+use crate::vfs::VirtualFs;
+use ignore::Match;
+use ignore::gitignore::GitignoreBuilder;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Ignore files read in each directory, by increasing precedence
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Directory always skipped while ignore files are honored
+const GIT_DIR: &str = ".git";
+
+/// The patterns of the ignore files of one directory
+#[derive(Debug)]
+pub struct Gitignore {
+    dir: PathBuf,
+    matcher: ignore::gitignore::Gitignore,
+}
+
+impl Gitignore {
+    /// Parse the contents of an ignore file of `dir`; invalid patterns are skipped
+    pub fn parse(dir: impl Into<PathBuf>, contents: &str) -> Self {
+        let dir = dir.into();
+        let mut builder = GitignoreBuilder::new(&dir);
+        for line in contents.lines() {
+            // An invalid pattern does not invalidate the others
+            let _ = builder.add_line(None, line);
+        }
+        let matcher = builder
+            .build()
+            .unwrap_or_else(|_| ignore::gitignore::Gitignore::empty());
+        Self { dir, matcher }
+    }
+
+    /// Read the ignore files of `dir`, if it has any
+    pub fn load(fs: &dyn VirtualFs, dir: &Path) -> Option<Self> {
+        let contents: Vec<String> = IGNORE_FILES
+            .iter()
+            .filter_map(|name| fs.read_to_string(&dir.join(name)).ok())
+            .collect();
+        (!contents.is_empty()).then(|| Self::parse(dir, &contents.join("\n")))
+    }
+
+    /// Whether the last pattern matching `path` ignores it (`Some(true)`) or
+    /// re-includes it (`Some(false)`); `None` when no pattern matches
+    fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        match self.matcher.matched(relative, is_dir) {
+            Match::None => None,
+            Match::Ignore(_) => Some(true),
+            Match::Whitelist(_) => Some(false),
+        }
+    }
+}
+
+/// The ignore files in effect in a directory: its own and those of its ancestors
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreStack {
+    layers: Vec<Arc<Gitignore>>,
+}
+
+impl IgnoreStack {
+    /// The ignore files applying to `dir`, read from it and its ancestors down from
+    /// the allowed root containing it
+    pub fn for_dir(fs: &dyn VirtualFs, dir: &Path) -> Self {
+        let root = crate::file_read::ALLOWED_ROOTS
+            .iter()
+            .map(Path::new)
+            .find(|root| crate::file_read::is_within(dir, root));
+        let mut stack = Self::default();
+        let mut current = PathBuf::new();
+        for component in dir.components() {
+            current.push(component);
+            if root.is_none_or(|root| crate::file_read::is_within(&current, root)) {
+                stack = stack.enter(fs, &current);
+            }
+        }
+        stack
+    }
+
+    /// The stack for a subdirectory, adding its ignore files
+    pub fn enter(&self, fs: &dyn VirtualFs, dir: &Path) -> Self {
+        match Gitignore::load(fs, dir) {
+            Some(gitignore) => {
+                let mut layers = self.layers.clone();
+                layers.push(Arc::new(gitignore));
+                Self { layers }
+            }
+            None => self.clone(),
+        }
+    }
+
+    /// Whether a traversal should skip `path`
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir && path.file_name().is_some_and(|name| name == GIT_DIR) {
+            return true;
+        }
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.matched(path, is_dir))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+
+    const REPO: &str = "/tmp/allowed_files/repo";
+
+    fn stack(ignore_file: &str) -> IgnoreStack {
+        let fs = MemoryFs::new().with_file(Path::new(REPO).join(".gitignore"), ignore_file);
+        IgnoreStack::for_dir(&fs, Path::new(REPO))
+    }
+
+    fn ignored(stack: &IgnoreStack, path: &str, is_dir: bool) -> bool {
+        stack.is_ignored(&Path::new(REPO).join(path), is_dir)
+    }
+
+    #[test]
+    fn negation_re_includes_earlier_matches() {
+        let stack = stack("*.log\n!keep.log\n");
+        assert!(ignored(&stack, "debug.log", false));
+        assert!(ignored(&stack, "src/debug.log", false));
+        assert!(!ignored(&stack, "keep.log", false));
+        assert!(!ignored(&stack, "src/keep.log", false));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored_to_their_directory() {
+        let stack = stack("/build\ndocs/*.md\ntarget\n");
+        assert!(ignored(&stack, "build", true));
+        assert!(!ignored(&stack, "src/build", true));
+        assert!(ignored(&stack, "docs/intro.md", false));
+        assert!(!ignored(&stack, "src/docs/intro.md", false));
+        // Without a slash, names match at any depth
+        assert!(ignored(&stack, "target", true));
+        assert!(ignored(&stack, "crates/core/target", true));
+    }
+
+    #[test]
+    fn double_stars_match_any_number_of_directories() {
+        let stack = stack("**/generated\nlogs/**\na/**/z\n");
+        assert!(ignored(&stack, "generated", true));
+        assert!(ignored(&stack, "src/deep/generated", true));
+        assert!(ignored(&stack, "logs/2024/app.txt", false));
+        assert!(!ignored(&stack, "logs", true));
+        assert!(ignored(&stack, "a/z", false));
+        assert!(ignored(&stack, "a/b/c/z", false));
+        assert!(!ignored(&stack, "b/a/z", false));
+    }
+
+    #[test]
+    fn trailing_slashes_only_match_directories() {
+        let stack = stack("out/\n");
+        assert!(ignored(&stack, "out", true));
+        assert!(ignored(&stack, "src/out", true));
+        assert!(!ignored(&stack, "out", false));
+    }
+
+    #[test]
+    fn deeper_ignore_files_take_precedence() {
+        let repo = Path::new(REPO);
+        let fs = MemoryFs::new()
+            .with_file(repo.join(".gitignore"), "*.tmp\n")
+            .with_file(repo.join("sub/.gitignore"), "!keep.tmp\nlocal.txt\n")
+            .with_file(repo.join("sub/.ignore"), "!local.txt\n");
+        let stack = IgnoreStack::for_dir(&fs, repo);
+        let sub = stack.enter(&fs, &repo.join("sub"));
+
+        assert!(sub.is_ignored(&repo.join("sub/other.tmp"), false));
+        assert!(!sub.is_ignored(&repo.join("sub/keep.tmp"), false));
+        assert!(stack.is_ignored(&repo.join("keep.tmp"), false));
+        // .ignore overrides .gitignore in the same directory
+        assert!(!sub.is_ignored(&repo.join("sub/local.txt"), false));
+    }
+
+    #[test]
+    fn git_directories_are_always_skipped() {
+        let stack = stack("");
+        assert!(ignored(&stack, ".git", true));
+        assert!(!ignored(&stack, ".gitignore", false));
+    }
+}
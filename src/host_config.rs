@@ -0,0 +1,204 @@
+//! Host configuration module
+//!
+//! This module reads and writes the server lists of MCP hosts, so a configuration
+//! written for one host can be reused with mcp-client, and mcp-server can be added to
+//! other hosts. Claude Desktop's `claude_desktop_config.json` and the `.mcp.json` of
+//! projects share one shape: an `mcpServers` object naming each server with either
+//! the `command`, `args` and `env` of a local server, or the `url` (and `type`,
+//! `http` or `sse`) of a remote one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Host configuration formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostFormat {
+    /// Claude Desktop's `claude_desktop_config.json`, which only starts local servers
+    ClaudeDesktop,
+    /// A project's `.mcp.json`
+    McpJson,
+}
+
+impl std::str::FromStr for HostFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "claude-desktop" => Ok(HostFormat::ClaudeDesktop),
+            "mcp-json" => Ok(HostFormat::McpJson),
+            _ => Err(format!(
+                "Unknown host format '{}', expected claude-desktop or mcp-json",
+                name
+            )),
+        }
+    }
+}
+
+/// The servers of a host configuration file
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HostConfig {
+    #[serde(rename = "mcpServers", default)]
+    pub mcp_servers: BTreeMap<String, HostServer>,
+}
+
+/// One server of a host configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HostServer {
+    /// `stdio`, `http` or `sse`; absent for local servers in most files
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// How to reach a server of a host configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostTransport {
+    /// Start the server and talk to it over stdio
+    Stdio {
+        command: String,
+        args: Vec<String>,
+        env: BTreeMap<String, String>,
+    },
+    /// Streamable HTTP at a URL
+    Http { url: String },
+    /// HTTP+SSE, the URL being the server's SSE stream
+    Sse { url: String },
+}
+
+impl fmt::Display for HostTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostTransport::Stdio { command, args, .. } => {
+                write!(f, "{}", command)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                Ok(())
+            }
+            HostTransport::Http { url } => write!(f, "{}", url),
+            HostTransport::Sse { url } => write!(f, "{} (SSE)", url),
+        }
+    }
+}
+
+impl HostServer {
+    /// A local server started with `command`
+    pub fn stdio(command: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            command: Some(command.into()),
+            args,
+            ..Self::default()
+        }
+    }
+
+    /// A remote server at `url`, over Streamable HTTP or, with `sse`, HTTP+SSE
+    pub fn remote(url: impl Into<String>, sse: bool) -> Self {
+        Self {
+            transport: Some(if sse { "sse" } else { "http" }.to_string()),
+            url: Some(url.into()),
+            ..Self::default()
+        }
+    }
+
+    /// How to reach the server
+    pub fn transport(&self) -> Result<HostTransport, Box<dyn std::error::Error>> {
+        let url = || self.url.clone().ok_or("A remote server needs a url");
+        match self.transport.as_deref() {
+            None | Some("stdio") if self.command.is_some() => Ok(HostTransport::Stdio {
+                command: self.command.clone().unwrap_or_default(),
+                args: self.args.clone(),
+                env: self.env.clone(),
+            }),
+            Some("stdio") => Err("A local server needs a command".into()),
+            None | Some("http" | "streamable-http") => Ok(HostTransport::Http { url: url()? }),
+            Some("sse") => Ok(HostTransport::Sse { url: url()? }),
+            Some(transport) => Err(format!("Unsupported server type '{}'", transport).into()),
+        }
+    }
+
+    /// The entry in `format`
+    ///
+    /// Claude Desktop cannot connect to remote servers itself, so they are bridged
+    /// through `npx mcp-remote`.
+    pub fn for_format(&self, format: HostFormat) -> Self {
+        match (format, &self.url) {
+            (HostFormat::ClaudeDesktop, Some(url)) if self.command.is_none() => {
+                let mut args = vec!["-y".to_string(), "mcp-remote".to_string(), url.clone()];
+                if self.transport.as_deref() == Some("sse") {
+                    args.extend(["--transport".to_string(), "sse-only".to_string()]);
+                }
+                for (name, value) in &self.headers {
+                    args.extend(["--header".to_string(), format!("{}:{}", name, value)]);
+                }
+                Self::stdio("npx", args)
+            }
+            (HostFormat::ClaudeDesktop, _) => Self {
+                transport: None,
+                ..self.clone()
+            },
+            (HostFormat::McpJson, _) => self.clone(),
+        }
+    }
+}
+
+impl HostConfig {
+    /// Read a host configuration file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid host configuration '{}': {}", path.display(), e).into())
+    }
+
+    /// The server named `name`, or the only server when no name is given
+    pub fn server(&self, name: Option<&str>) -> Result<&HostServer, Box<dyn std::error::Error>> {
+        let names = || {
+            self.mcp_servers
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match name {
+            Some(name) => self.mcp_servers.get(name).ok_or_else(|| {
+                format!(
+                    "No server '{}' in the configuration (found: {})",
+                    name,
+                    names()
+                )
+                .into()
+            }),
+            None if self.mcp_servers.len() == 1 => {
+                Ok(self.mcp_servers.values().next().expect("one server"))
+            }
+            None if self.mcp_servers.is_empty() => Err("The configuration has no servers".into()),
+            None => {
+                Err(format!("Several servers are configured, choose one of: {}", names()).into())
+            }
+        }
+    }
+
+    /// The configuration as JSON in `format`
+    pub fn to_json(&self, format: HostFormat) -> Result<String, serde_json::Error> {
+        let config = HostConfig {
+            mcp_servers: self
+                .mcp_servers
+                .iter()
+                .map(|(name, server)| (name.clone(), server.for_format(format)))
+                .collect(),
+        };
+        serde_json::to_string_pretty(&config)
+    }
+}
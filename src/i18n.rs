@@ -0,0 +1,175 @@
+//! Localization module
+//!
+//! This module translates what users of non-English deployments see: tool
+//! descriptions in tools/list and the text of failed tool calls. Translations come
+//! from simple TOML catalogs, one `<language>.toml` file per language in the directory
+//! configured under `[i18n]`. A client picks its language with the `acceptLanguage`
+//! field of the initialize request, written like an HTTP `Accept-Language` header;
+//! anything without a translation stays in English.
+//!
+//! ```toml
+//! # de.toml
+//! [tools.file_read]
+//! description = "Liest den Inhalt einer Datei in /tmp/allowed_files/"
+//!
+//! [errors]
+//! NOT_FOUND = "Nicht gefunden ({message})"
+//! TOO_LARGE = "Die Eingabe überschreitet die Grenze von {limit} Bytes"
+//! ```
+
+use crate::mcp::{McpServerState, McpTool, ToolContent, ToolsCallResponse};
+use crate::tool_error::{ToolErrorCode, ToolErrorPayload};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Language of the built-in texts, which needs no catalog
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Translations for one language
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Catalog {
+    /// Texts of each tool, by tool name
+    pub tools: HashMap<String, ToolTexts>,
+    /// Messages of failed tool calls by error code
+    ///
+    /// `{message}` is replaced with the English message and the entries of the error
+    /// details, such as `{limit}`, with their values.
+    pub errors: HashMap<ToolErrorCode, String>,
+}
+
+/// Translated texts of one tool
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolTexts {
+    pub description: Option<String>,
+}
+
+/// Catalogs by lowercase language tag, e.g. `de` or `pt-br`
+pub type Catalogs = HashMap<String, Arc<Catalog>>;
+
+/// Load every `<language>.toml` catalog in a directory
+pub fn load_catalogs(directory: impl AsRef<Path>) -> Result<Catalogs, Box<dyn std::error::Error>> {
+    let directory = directory.as_ref();
+    let entries = fs::read_dir(directory).map_err(|e| {
+        format!(
+            "Failed to read catalog directory '{}': {}",
+            directory.display(),
+            e
+        )
+    })?;
+
+    let mut catalogs = Catalogs::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let Some(language) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read catalog '{}': {}", path.display(), e))?;
+        let catalog: Catalog = toml::from_str(&text)
+            .map_err(|e| format!("Invalid catalog '{}': {}", path.display(), e))?;
+        catalogs.insert(language.to_ascii_lowercase(), Arc::new(catalog));
+    }
+    Ok(catalogs)
+}
+
+/// Pick the language to serve from an `Accept-Language` value
+///
+/// Languages are tried by decreasing quality; a regional tag such as `de-CH` falls
+/// back to its primary language `de`. Returns `None` when English is preferred over
+/// every available catalog or nothing matches.
+pub fn negotiate(catalogs: &Catalogs, accept_language: &str) -> Option<String> {
+    let mut ranges: Vec<(&str, f32)> = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    // Stable, so equally preferred languages keep their order
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    for (tag, _) in ranges {
+        let tag = tag.to_ascii_lowercase();
+        let primary = tag.split('-').next().unwrap_or_default();
+        for candidate in [tag.as_str(), primary] {
+            if candidate == DEFAULT_LANGUAGE {
+                return None;
+            }
+            if catalogs.contains_key(candidate) {
+                return Some(candidate.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// The catalog of the language a session negotiated, if it has one
+pub async fn session_catalog(
+    state: &McpServerState,
+    session_id: Option<&str>,
+) -> Option<Arc<Catalog>> {
+    let sessions = state.sessions.read().await;
+    let language = sessions.get(session_id?)?.language.as_deref()?;
+    state.catalogs.get(language).cloned()
+}
+
+/// Translate the descriptions of tools that have one in the catalog
+pub fn localize_tools(catalog: &Catalog, tools: &[Arc<McpTool>]) -> Vec<McpTool> {
+    tools
+        .iter()
+        .map(|tool| {
+            let mut tool = McpTool::clone(tool);
+            if let Some(description) = catalog
+                .tools
+                .get(&tool.name)
+                .and_then(|texts| texts.description.clone())
+            {
+                tool.description = Some(description);
+            }
+            tool
+        })
+        .collect()
+}
+
+/// Translate the text of a failed tool call from its error payload
+pub fn localize_error(catalog: &Catalog, response: &mut ToolsCallResponse) {
+    if !response.is_error {
+        return;
+    }
+    let Some(payload) = response
+        .structured_content
+        .clone()
+        .and_then(|content| serde_json::from_value::<ToolErrorPayload>(content).ok())
+    else {
+        return;
+    };
+    let Some(template) = catalog.errors.get(&payload.code) else {
+        return;
+    };
+
+    let mut text = template.replace("{message}", &payload.message);
+    if let Some(Value::Object(details)) = &payload.details {
+        for (key, value) in details {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            text = text.replace(&format!("{{{}}}", key), &value);
+        }
+    }
+    response.content = vec![ToolContent::text(text)];
+}
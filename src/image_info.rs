@@ -0,0 +1,200 @@
+//! Image information tool module
+//!
+//! This module reports an image's format, dimensions, color type and EXIF metadata,
+//! and can attach a downscaled thumbnail as image content, so vision workflows do
+//! not have to send full-resolution files through JSON-RPC. Decoding is bounded by
+//! file size and pixel limits. It is only compiled with the `image` feature.
+
+use crate::tool_error::ToolErrorPayload;
+use crate::vfs::VirtualFs;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufReader, Cursor};
+
+/// Upper bound on the size of an image file
+const MAX_FILE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Upper bound on the width and height of a decoded image
+const MAX_DIMENSION: u32 = 20_000;
+
+/// Default length of the thumbnail's longer edge
+const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+
+/// Upper bound on the length of the thumbnail's longer edge
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// EXIF values longer than this are left out
+const MAX_EXIF_VALUE_LENGTH: usize = 200;
+
+/// Image info tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImageInfoRequest {
+    /// The image file
+    pub path: String,
+    /// Attach a downscaled copy of the image (default false)
+    pub thumbnail: Option<bool>,
+    /// Longer edge of the thumbnail in pixels (default 256, max 1024)
+    pub thumbnail_size: Option<u32>,
+}
+
+/// A downscaled, base64-encoded copy of an image
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Thumbnail {
+    #[serde(skip)]
+    pub data: String,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Image info tool response
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ImageInfoResponse {
+    pub path: String,
+    pub format: String,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub color_type: String,
+    pub file_size: u64,
+    /// EXIF fields by tag name, e.g. "Model" or "DateTimeOriginal"
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub exif: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Thumbnail>,
+}
+
+/// Execute the image info tool
+pub fn execute_image_info(
+    request: ImageInfoRequest,
+    fs: &dyn VirtualFs,
+) -> Result<ImageInfoResponse, Box<dyn std::error::Error>> {
+    let path = crate::file_read::validate_path(fs, &request.path)?;
+    let file_size = fs.metadata(path)?.len;
+    if file_size > MAX_FILE_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("File exceeds the {} byte limit", MAX_FILE_BYTES),
+            MAX_FILE_BYTES,
+        )
+        .into());
+    }
+
+    let mut reader = ImageReader::new(BufReader::new(fs.open(path)?)).with_guessed_format()?;
+    let format = reader
+        .format()
+        .ok_or_else(|| format!("Unsupported image format: {}", request.path))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| format!("Failed to read image '{}': {}", request.path, e))?;
+    let (width, height) = decoder.dimensions();
+    let color_type = format!("{:?}", decoder.color_type());
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    let thumbnail = if request.thumbnail.unwrap_or(false) {
+        let size = request
+            .thumbnail_size
+            .unwrap_or(DEFAULT_THUMBNAIL_SIZE)
+            .clamp(16, MAX_THUMBNAIL_SIZE);
+        let mut image = DynamicImage::from_decoder(decoder)
+            .map_err(|e| format!("Failed to decode image '{}': {}", request.path, e))?;
+        image.apply_orientation(orientation);
+        Some(make_thumbnail(&image, size)?)
+    } else {
+        None
+    };
+
+    let exif = read_exif(fs, path);
+    Ok(ImageInfoResponse {
+        path: request.path,
+        format: format!("{:?}", format).to_lowercase(),
+        mime_type: format.to_mime_type().to_string(),
+        width,
+        height,
+        color_type,
+        file_size,
+        exif,
+        thumbnail,
+    })
+}
+
+/// Downscale an image, encoding it as PNG if it has transparency and JPEG otherwise
+fn make_thumbnail(
+    image: &DynamicImage,
+    size: u32,
+) -> Result<Thumbnail, Box<dyn std::error::Error>> {
+    let small = if image.width() > size || image.height() > size {
+        image.thumbnail(size, size)
+    } else {
+        image.clone()
+    };
+
+    let mut bytes = Cursor::new(Vec::new());
+    let format = if small.color().has_alpha() {
+        small.write_to(&mut bytes, ImageFormat::Png)?;
+        ImageFormat::Png
+    } else {
+        DynamicImage::ImageRgb8(small.to_rgb8()).write_to(&mut bytes, ImageFormat::Jpeg)?;
+        ImageFormat::Jpeg
+    };
+
+    Ok(Thumbnail {
+        data: STANDARD.encode(bytes.into_inner()),
+        mime_type: format.to_mime_type().to_string(),
+        width: small.width(),
+        height: small.height(),
+    })
+}
+
+/// Read the primary image's EXIF fields, empty if the file has none
+fn read_exif(fs: &dyn VirtualFs, path: &std::path::Path) -> BTreeMap<String, String> {
+    let Ok(file) = fs.open(path) else {
+        return BTreeMap::new();
+    };
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return BTreeMap::new();
+    };
+
+    exif.fields()
+        .filter(|field| field.ifd_num == exif::In::PRIMARY && field.tag != exif::Tag::MakerNote)
+        .filter_map(|field| {
+            // Text values are displayed quoted, so they are taken as they are
+            let value = match &field.value {
+                exif::Value::Ascii(parts) => parts
+                    .iter()
+                    .map(|part| String::from_utf8_lossy(part))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => field.display_value().with_unit(&exif).to_string(),
+            };
+            (value.len() <= MAX_EXIF_VALUE_LENGTH).then(|| (field.tag.to_string(), value))
+        })
+        .collect()
+}
+
+/// Get the tool definition for the image_info tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "image_info".to_string(),
+        version: None,
+        description: Some(format!(
+            "Get the format, dimensions, color type and EXIF metadata of an image within {} (PNG, JPEG, GIF, WebP, BMP or TIFF). Optionally attaches a downscaled thumbnail instead of the full-resolution image.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<ImageInfoRequest>()),
+        output_schema: Some(crate::schema::output_schema::<ImageInfoResponse>()),
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
@@ -0,0 +1,291 @@
+//! Interaction log module
+//!
+//! This module records what an agent did for offline evaluation and debugging. Each
+//! model request of a [`crate::ChatSession`] and each tool call of mcp-client becomes
+//! one JSON line in the log file, with a timestamp, the conversation it belongs to,
+//! its duration and, for model requests, the token counts reported by Ollama. Secrets
+//! such as API keys and passwords are redacted before anything is written.
+
+use crate::ollama::{ChatMessage, ChatResponse, OllamaToolCall};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Replacement of redacted text
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Patterns of secrets redacted from every record
+const DEFAULT_PATTERNS: &[&str] = &[
+    r"(?i)\bbearer\s+[a-z0-9._~+/=-]+",
+    r"\b(sk|pk|rk)-[A-Za-z0-9_-]{16,}",
+    r"\bgh[pousr]_[A-Za-z0-9]{20,}",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"(?i)\b(password|passwd|secret|token|api[_-]?key)\s*[=:]\s*\S+",
+];
+
+/// Argument names whose values are redacted whole
+const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+];
+
+/// One line of the interaction log
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Interaction {
+    /// A chat request to the model and its response
+    Model {
+        timestamp: DateTime<Utc>,
+        conversation: String,
+        model: String,
+        /// Messages added to the conversation since the previous request
+        messages: Vec<ChatMessage>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        response: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tool_calls: Option<Vec<OllamaToolCall>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt_tokens: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        completion_tokens: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        duration_ms: u64,
+    },
+    /// A tool call and its result
+    Tool {
+        timestamp: DateTime<Utc>,
+        conversation: String,
+        tool: String,
+        arguments: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        result: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        duration_ms: u64,
+    },
+}
+
+impl Interaction {
+    /// Id of the conversation the record belongs to
+    pub fn conversation(&self) -> &str {
+        match self {
+            Self::Model { conversation, .. } | Self::Tool { conversation, .. } => conversation,
+        }
+    }
+}
+
+/// Removes secrets from logged text and arguments
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_PATTERNS
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("valid default redaction pattern"))
+                .collect(),
+        }
+    }
+}
+
+impl Redactor {
+    /// Also redact the matches of `pattern`
+    pub fn add_pattern(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.patterns.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    /// Replace every match of the patterns in `text`
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for pattern in &self.patterns {
+            if pattern.is_match(&text) {
+                text = pattern.replace_all(&text, REDACTED).into_owned();
+            }
+        }
+        text
+    }
+
+    /// Redact the strings of a JSON value and the values of sensitive keys
+    pub fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.redact(text)),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|item| self.redact_value(item)).collect())
+            }
+            Value::Object(members) => {
+                let mut redacted = serde_json::Map::new();
+                for (key, value) in members {
+                    let value = if SENSITIVE_KEYS.contains(&key.to_ascii_lowercase().as_str()) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        self.redact_value(value)
+                    };
+                    redacted.insert(key.clone(), value);
+                }
+                Value::Object(redacted)
+            }
+            value => value.clone(),
+        }
+    }
+
+    fn redact_message(&self, message: &ChatMessage) -> ChatMessage {
+        ChatMessage {
+            role: message.role.clone(),
+            content: self.redact(&message.content),
+            tool_calls: message
+                .tool_calls
+                .as_ref()
+                .map(|calls| self.redact_tool_calls(calls)),
+        }
+    }
+
+    fn redact_tool_calls(&self, calls: &[OllamaToolCall]) -> Vec<OllamaToolCall> {
+        calls
+            .iter()
+            .map(|call| {
+                let mut call = call.clone();
+                call.function.arguments = self.redact_value(&call.function.arguments);
+                call
+            })
+            .collect()
+    }
+}
+
+/// Writer of an interaction log file
+///
+/// Clones append to the same file under the same conversation id, so the chat session
+/// and the code running its tool calls can both record to it.
+#[derive(Clone)]
+pub struct InteractionLog {
+    file: Arc<Mutex<File>>,
+    conversation: String,
+    redactor: Redactor,
+}
+
+impl InteractionLog {
+    /// Append to the log at `path`, creating it if needed, for a new conversation
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open interaction log '{}': {}", path.display(), e))?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            conversation: uuid::Uuid::new_v4().to_string(),
+            redactor: Redactor::default(),
+        })
+    }
+
+    /// Redact with `redactor` instead of the default patterns
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Id of the conversation the records belong to
+    pub fn conversation(&self) -> &str {
+        &self.conversation
+    }
+
+    /// Record a model request made with `messages` and its outcome
+    pub fn model_exchange(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        outcome: Result<&ChatResponse, &dyn std::error::Error>,
+        duration: Duration,
+    ) {
+        let (response, tool_calls, prompt_tokens, completion_tokens, error) = match outcome {
+            Ok(response) => (
+                Some(self.redactor.redact(&response.message.content)),
+                response
+                    .message
+                    .tool_calls
+                    .as_ref()
+                    .map(|calls| self.redactor.redact_tool_calls(calls)),
+                response.prompt_eval_count,
+                response.eval_count,
+                None,
+            ),
+            Err(e) => (
+                None,
+                None,
+                None,
+                None,
+                Some(self.redactor.redact(&e.to_string())),
+            ),
+        };
+        self.write(&Interaction::Model {
+            timestamp: Utc::now(),
+            conversation: self.conversation.clone(),
+            model: model.to_string(),
+            messages: messages
+                .iter()
+                .map(|message| self.redactor.redact_message(message))
+                .collect(),
+            response,
+            tool_calls,
+            prompt_tokens,
+            completion_tokens,
+            error,
+            duration_ms: duration.as_millis() as u64,
+        });
+    }
+
+    /// Record a tool call and its result or error
+    pub fn tool_exchange(
+        &self,
+        tool: &str,
+        arguments: Option<&Value>,
+        outcome: Result<&str, &str>,
+        duration: Duration,
+    ) {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(self.redactor.redact(result)), None),
+            Err(error) => (None, Some(self.redactor.redact(error))),
+        };
+        self.write(&Interaction::Tool {
+            timestamp: Utc::now(),
+            conversation: self.conversation.clone(),
+            tool: tool.to_string(),
+            arguments: arguments
+                .map(|arguments| self.redactor.redact_value(arguments))
+                .unwrap_or(Value::Null),
+            result,
+            error,
+            duration_ms: duration.as_millis() as u64,
+        });
+    }
+
+    /// Append a record as one line; failures are reported but do not stop the agent
+    fn write(&self, interaction: &Interaction) {
+        let result = serde_json::to_string(interaction)
+            .map_err(|e| e.to_string())
+            .and_then(|mut line| {
+                line.push('\n');
+                let mut file = self.file.lock().map_err(|e| e.to_string())?;
+                file.write_all(line.as_bytes()).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            crate::server_log::error(format!("Failed to write interaction log: {}", e));
+        }
+    }
+}
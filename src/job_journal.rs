@@ -0,0 +1,254 @@
+//! Job journal module
+//!
+//! This module keeps background jobs from vanishing when the server stops unexpectedly.
+//! Every state change of a resumable transfer and every start and end of a scheduled
+//! run is appended to a journal file and synced to disk before the new state is
+//! published, one JSON record per line. When the server starts again, the journal is
+//! replayed: finished jobs are served as they were, transfers that were running become
+//! `interrupted` (calling the tool again resumes them from their partial file) or
+//! `failed` when nothing was kept, and scheduled runs cut short are reported as failed.
+//! The journal is then rewritten with only the latest record of each job.
+
+use crate::mcp::McpServerState;
+use crate::scheduler::ScheduledResult;
+use crate::transfer::{Transfer, TransferStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Error of a scheduled run the server did not finish
+const RUN_INTERRUPTED: &str = "Interrupted by a server restart";
+
+/// A state change of a background job
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "job", rename_all = "snake_case")]
+pub enum JobRecord {
+    /// A transfer with its new status
+    Transfer(Transfer),
+    /// A scheduled run started
+    ScheduleStarted {
+        name: String,
+        uri: String,
+        tool: String,
+        started_at: DateTime<Utc>,
+    },
+    /// A scheduled run finished
+    Schedule(ScheduledResult),
+}
+
+impl JobRecord {
+    /// URI of the job's resource, under which the latest record is kept
+    pub fn uri(&self) -> &str {
+        match self {
+            JobRecord::Transfer(transfer) => &transfer.uri,
+            JobRecord::ScheduleStarted { uri, .. } => uri,
+            JobRecord::Schedule(result) => &result.uri,
+        }
+    }
+
+    /// The state to report for a job whose latest record was written before a restart
+    fn recovered(self) -> Self {
+        match self {
+            JobRecord::Transfer(mut transfer) if transfer.status == TransferStatus::Running => {
+                let kept = transfer
+                    .partial_path
+                    .as_ref()
+                    .and_then(|path| fs::metadata(path).ok())
+                    .map(|metadata| metadata.len());
+                match kept {
+                    Some(bytes) => {
+                        transfer.bytes = bytes;
+                        transfer.status = TransferStatus::Interrupted;
+                        transfer.error = Some(format!(
+                            "{}; call the tool again to resume",
+                            RUN_INTERRUPTED
+                        ));
+                    }
+                    None => {
+                        transfer.status = TransferStatus::Failed;
+                        transfer.error = Some(RUN_INTERRUPTED.to_string());
+                    }
+                }
+                transfer.updated_at = Utc::now();
+                JobRecord::Transfer(transfer)
+            }
+            JobRecord::ScheduleStarted {
+                name,
+                uri,
+                tool,
+                started_at,
+            } => JobRecord::Schedule(ScheduledResult {
+                name,
+                uri,
+                tool,
+                ran_at: started_at,
+                next_run: None,
+                result: None,
+                error: Some(RUN_INTERRUPTED.to_string()),
+            }),
+            record => record,
+        }
+    }
+}
+
+/// Append-only file of job state changes
+#[derive(Debug)]
+pub struct JobJournal {
+    path: PathBuf,
+    file: Mutex<File>,
+    /// Jobs read from the journal, until they are restored on the server
+    recovered: Mutex<Vec<JobRecord>>,
+}
+
+impl JobJournal {
+    /// Open the journal at `path`, replaying and compacting what it holds
+    ///
+    /// Lines that cannot be read, such as one cut short by a crash, are skipped.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut latest: HashMap<String, JobRecord> = HashMap::new();
+        match File::open(path) {
+            Ok(file) => {
+                for (number, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<JobRecord>(&line) {
+                        Ok(record) => {
+                            latest.insert(record.uri().to_string(), record);
+                        }
+                        Err(e) => crate::server_log::warn(format!(
+                            "Warning: skipping line {} of job journal '{}': {}",
+                            number + 1,
+                            path.display(),
+                            e
+                        )),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(
+                    format!("Failed to read job journal '{}': {}", path.display(), e).into(),
+                );
+            }
+        }
+        let mut recovered: Vec<JobRecord> =
+            latest.into_values().map(JobRecord::recovered).collect();
+        recovered.sort_by(|a, b| a.uri().cmp(b.uri()));
+
+        // Rewrite the journal with the recovered state before appending to it
+        let mut compacted = path.as_os_str().to_owned();
+        compacted.push(".tmp");
+        let compacted = PathBuf::from(compacted);
+        {
+            let mut file = File::create(&compacted)?;
+            for record in &recovered {
+                writeln!(file, "{}", serde_json::to_string(record)?)?;
+            }
+            file.sync_all()?;
+        }
+        fs::rename(&compacted, path)?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open job journal '{}': {}", path.display(), e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            recovered: Mutex::new(recovered),
+        })
+    }
+
+    /// Path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a state change, returning once it is on disk
+    pub fn record(&self, record: &JobRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = self.file.lock().map_err(|_| "Job journal lock poisoned")?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Take the jobs read when the journal was opened
+    fn take_recovered(&self) -> Vec<JobRecord> {
+        self.recovered
+            .lock()
+            .map(|mut recovered| std::mem::take(&mut *recovered))
+            .unwrap_or_default()
+    }
+}
+
+/// Journal a state change, if the server keeps a journal
+///
+/// A failure to write is reported but does not stop the job.
+pub fn record(state: &McpServerState, record: &JobRecord) {
+    if let Some(journal) = &state.job_journal
+        && let Err(e) = journal.record(record)
+    {
+        crate::server_log::error(format!(
+            "Failed to write job journal '{}': {}",
+            journal.path().display(),
+            e
+        ));
+    }
+}
+
+/// Serve the jobs recovered from the journal
+pub async fn restore(state: &McpServerState) {
+    let Some(journal) = &state.job_journal else {
+        return;
+    };
+    let recovered = journal.take_recovered();
+    if recovered.is_empty() {
+        return;
+    }
+    let interrupted = recovered
+        .iter()
+        .filter(|record| {
+            let error = match record {
+                JobRecord::Transfer(transfer) => transfer.error.as_deref(),
+                JobRecord::Schedule(result) => result.error.as_deref(),
+                JobRecord::ScheduleStarted { .. } => None,
+            };
+            error.is_some_and(|error| error.starts_with(RUN_INTERRUPTED))
+        })
+        .count();
+    crate::server_log::info(format!(
+        "Recovered {} jobs from {} ({} interrupted)",
+        recovered.len(),
+        journal.path().display(),
+        interrupted
+    ));
+
+    let mut transfers = state.transfers.write().await;
+    let mut scheduled_results = state.scheduled_results.write().await;
+    for record in recovered {
+        match record {
+            JobRecord::Transfer(transfer) => {
+                transfers.insert(transfer.uri.clone(), transfer);
+            }
+            JobRecord::Schedule(result) => {
+                scheduled_results.insert(result.uri.clone(), result);
+            }
+            JobRecord::ScheduleStarted { .. } => {}
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! JSON query tool module
+//!
+//! This module provides a small jq/JSONPath-like query language for selecting
+//! fragments of JSON and YAML documents, so the model only receives the part
+//! of a document it asked about instead of the whole file.
+
+use crate::vfs::VirtualFs;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// JSON query tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct JsonQueryRequest {
+    /// The query expression, e.g. .dependencies.serde or $.items[*].id
+    pub query: String,
+    /// The JSON or YAML file to query
+    pub path: Option<String>,
+    /// An inline JSON or YAML document to query instead of a file
+    pub document: Option<String>,
+    /// The document format, detected automatically when omitted
+    #[schemars(extend("enum" = ["json", "yaml"]))]
+    pub format: Option<String>,
+}
+
+/// JSON query tool response
+#[derive(Debug, Serialize)]
+pub struct JsonQueryResponse {
+    pub query: String,
+    pub source: String,
+    pub matches: Vec<Value>,
+}
+
+/// A single step of a parsed query
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+/// Execute the JSON query tool
+pub fn execute_json_query(
+    request: JsonQueryRequest,
+    fs: &dyn VirtualFs,
+) -> Result<JsonQueryResponse, Box<dyn std::error::Error>> {
+    let (text, source) = match (&request.path, &request.document) {
+        (Some(_), Some(_)) => {
+            return Err("Provide either 'path' or 'document', not both".into());
+        }
+        (Some(path), None) => {
+            let checked = crate::file_read::validate_path(fs, path)?;
+            let text = fs
+                .read_to_string(checked)
+                .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+            crate::accounting::read(text.len() as u64);
+            (text, path.clone())
+        }
+        (None, Some(document)) => (document.clone(), "inline document".to_string()),
+        (None, None) => return Err("Either 'path' or 'document' is required".into()),
+    };
+
+    let format = match request.format.as_deref() {
+        Some(format) => format.to_ascii_lowercase(),
+        None => detect_format(request.path.as_deref(), &text).to_string(),
+    };
+
+    let document: Value = match format.as_str() {
+        "json" => serde_json::from_str(&text).map_err(|e| format!("Invalid JSON: {}", e))?,
+        "yaml" | "yml" => {
+            serde_yaml::from_str(&text).map_err(|e| format!("Invalid YAML: {}", e))?
+        }
+        other => return Err(format!("Unsupported format: {}", other).into()),
+    };
+
+    let segments = parse_query(&request.query)?;
+    let matches = evaluate(&document, &segments);
+
+    Ok(JsonQueryResponse {
+        query: request.query,
+        source,
+        matches,
+    })
+}
+
+/// Guess the document format from the file extension, falling back to the content
+fn detect_format(path: Option<&str>, text: &str) -> &'static str {
+    if let Some(extension) = path.and_then(|p| Path::new(p).extension()?.to_str()) {
+        match extension {
+            "json" => return "json",
+            "yaml" | "yml" => return "yaml",
+            _ => {}
+        }
+    }
+
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        "json"
+    } else {
+        "yaml"
+    }
+}
+
+/// Parse a query expression into segments
+///
+/// Supported syntax: an optional leading `$` or `.`, `.key` / `.*` member access,
+/// and bracket selectors `[0]`, `[-1]`, `[*]`, `[]`, `["key"]` and `['key']`.
+fn parse_query(query: &str) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+    let query = query.trim();
+    let mut chars = query.strip_prefix('$').unwrap_or(query).chars().peekable();
+    let mut segments = Vec::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next == '.' || next == '[' {
+                        break;
+                    }
+                    key.push(next);
+                    chars.next();
+                }
+                match key.as_str() {
+                    "" => {}
+                    "*" => segments.push(Segment::Wildcard),
+                    _ => segments.push(Segment::Key(key)),
+                }
+            }
+            '[' => {
+                // Whitespace is only part of the key inside quotes, and a quoted
+                // selector is always a key
+                let mut inner = String::new();
+                let mut quote = None;
+                let mut quoted = false;
+                loop {
+                    let Some(next) = chars.next() else {
+                        return Err(format!("Unterminated '[' in query: {}", query).into());
+                    };
+                    match (quote, next) {
+                        (None, ']') => break,
+                        (None, '"' | '\'') => {
+                            quote = Some(next);
+                            quoted = true;
+                        }
+                        (Some(q), _) if q == next => quote = None,
+                        (None, c) if c.is_whitespace() => {}
+                        _ => inner.push(next),
+                    }
+                }
+                if quoted {
+                    segments.push(Segment::Key(inner));
+                } else if inner.is_empty() || inner == "*" {
+                    segments.push(Segment::Wildcard);
+                } else if let Ok(index) = inner.parse::<i64>() {
+                    segments.push(Segment::Index(index));
+                } else {
+                    segments.push(Segment::Key(inner));
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ if segments.is_empty() => {
+                // Allow a bare leading key such as `servers.name`
+                let mut key = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next == '.' || next == '[' {
+                        break;
+                    }
+                    key.push(next);
+                    chars.next();
+                }
+                segments.push(Segment::Key(key));
+            }
+            _ => return Err(format!("Unexpected character '{}' in query: {}", c, query).into()),
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Apply the parsed segments to a document, collecting every selected value
+fn evaluate(document: &Value, segments: &[Segment]) -> Vec<Value> {
+    let mut current = vec![document];
+
+    for segment in segments {
+        let mut next = Vec::new();
+        for value in current {
+            match (segment, value) {
+                (Segment::Key(key), Value::Object(map)) => next.extend(map.get(key)),
+                (Segment::Index(index), Value::Array(items)) => {
+                    let resolved = if *index < 0 {
+                        items.len().checked_sub(index.unsigned_abs() as usize)
+                    } else {
+                        Some(*index as usize)
+                    };
+                    next.extend(resolved.and_then(|i| items.get(i)));
+                }
+                (Segment::Wildcard, Value::Array(items)) => next.extend(items.iter()),
+                (Segment::Wildcard, Value::Object(map)) => next.extend(map.values()),
+                _ => {}
+            }
+        }
+        current = next;
+    }
+
+    current.into_iter().cloned().collect()
+}
+
+/// Get the tool definition for the json_query tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "json_query".to_string(),
+        description: Some(format!(
+            "Select a fragment of a JSON or YAML document using a jq/JSONPath-like expression (e.g. .servers[0].name or $.items[*].id). Query a file within {} via 'path', or an inline document via 'document'.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<JsonQueryRequest>()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bracket_whitespace_outside_quotes_is_ignored() {
+        let key = vec![Segment::Key("name".to_string())];
+        assert_eq!(parse_query(r#"[ "name" ]"#).unwrap(), key);
+        assert_eq!(parse_query("[ name ]").unwrap(), key);
+        assert_eq!(parse_query("[ 1 ]").unwrap(), vec![Segment::Index(1)]);
+        assert_eq!(parse_query("[ * ]").unwrap(), vec![Segment::Wildcard]);
+    }
+
+    #[test]
+    fn test_quoted_bracket_is_key() {
+        assert_eq!(
+            parse_query(r#"[" full name "]"#).unwrap(),
+            vec![Segment::Key(" full name ".to_string())]
+        );
+        assert_eq!(
+            parse_query("['0']").unwrap(),
+            vec![Segment::Key("0".to_string())]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! JSON-RPC body module
+//!
+//! This module checks that incoming messages are JSON-RPC 2.0 before the server
+//! handles them. A body that is not JSON is answered with a `-32700` "Parse error" and
+//! a message that is not a valid request, notification or response (not an object,
+//! `jsonrpc` other than "2.0", neither a method nor a result or error, an id that is
+//! neither a string nor an integer) with a `-32600` "Invalid Request", as JSON-RPC
+//! error responses with HTTP status 400. The response carries the message's id when
+//! it could be read, `null` otherwise. Responses answer requests the server sent, see
+//! [`crate::server_request`].
+
+use crate::mcp::{JsonRpcError, JsonRpcResponse, RequestId};
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::Value;
+
+/// JSON-RPC error code of a body that is not JSON
+pub const PARSE_ERROR: i32 = -32700;
+
+/// JSON-RPC error code of a message that is not a valid request
+pub const INVALID_REQUEST: i32 = -32600;
+
+/// A JSON-RPC message from a request body, checked to be a request, notification or
+/// response
+#[derive(Debug)]
+pub struct JsonRpcBody(pub Value);
+
+/// The error response answering a message that could not be handled
+#[derive(Debug)]
+pub struct InvalidMessage(pub Box<JsonRpcResponse>);
+
+impl IntoResponse for InvalidMessage {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, Json(*self.0)).into_response()
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for JsonRpcBody {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let body = Bytes::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        parse(&body)
+            .map(JsonRpcBody)
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+/// Parse and check a message
+pub fn parse(body: &[u8]) -> Result<Value, InvalidMessage> {
+    let message: Value = serde_json::from_slice(body)
+        .map_err(|e| invalid(None, PARSE_ERROR, format!("Parse error: {}", e)))?;
+    validate(&message)?;
+    Ok(message)
+}
+
+/// Check that a message is a JSON-RPC 2.0 request, notification or response
+pub fn validate(message: &Value) -> Result<(), InvalidMessage> {
+    let Some(members) = message.as_object() else {
+        let reason = if message.is_array() {
+            "batches are not supported"
+        } else {
+            "a message must be an object"
+        };
+        return Err(invalid_request(None, reason));
+    };
+    let id = match members.get("id") {
+        None => None,
+        Some(id) => match serde_json::from_value::<RequestId>(id.clone()) {
+            Ok(id) => Some(id),
+            Err(_) => {
+                return Err(invalid_request(None, "id must be a string or an integer"));
+            }
+        },
+    };
+    if members.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(invalid_request(id, "jsonrpc must be \"2.0\""));
+    }
+    if !members.contains_key("method") {
+        return match (
+            id,
+            members.contains_key("result") != members.contains_key("error"),
+        ) {
+            (Some(_), true) => Ok(()),
+            (id, _) => Err(invalid_request(
+                id,
+                "a message needs a method, or an id and a result or error",
+            )),
+        };
+    }
+    if !members.get("method").is_some_and(Value::is_string) {
+        return Err(invalid_request(id, "method must be a string"));
+    }
+    if members
+        .get("params")
+        .is_some_and(|params| !(params.is_object() || params.is_array() || params.is_null()))
+    {
+        return Err(invalid_request(id, "params must be an object or an array"));
+    }
+    Ok(())
+}
+
+/// Error response to a message that is not a valid request
+pub fn invalid_request(id: Option<RequestId>, reason: &str) -> InvalidMessage {
+    invalid(id, INVALID_REQUEST, format!("Invalid Request: {}", reason))
+}
+
+fn invalid(id: Option<RequestId>, code: i32, message: String) -> InvalidMessage {
+    InvalidMessage(Box::new(JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: None,
+        error: Some(JsonRpcError {
+            code,
+            message,
+            data: None,
+        }),
+    }))
+}
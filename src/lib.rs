@@ -1,8 +1,109 @@
+pub mod accounting;
+pub mod admission;
+pub mod auth;
+pub mod cancellation;
+pub mod check;
+pub mod circuit_breaker;
+pub mod client_config;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod config;
+pub mod conversation_store;
+pub mod convert;
+pub mod deadline;
+pub mod diff;
+pub mod elicitation;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod error;
+pub mod events;
+pub mod execution;
+pub mod exit;
+pub mod feed_read;
+pub mod file_list;
 pub mod file_read;
+pub mod file_search;
+pub mod gateway;
+pub mod generate;
+pub mod gitignore;
+pub mod host_config;
+pub mod i18n;
+#[cfg(feature = "image")]
+pub mod image_info;
+pub mod interaction_log;
+pub mod job_journal;
+pub mod json_query;
+pub mod jsonrpc_body;
+pub mod log_tail;
+pub mod manual;
 pub mod mcp;
+pub mod memory;
+pub mod mime;
+pub mod notification_queue;
+pub mod oauth;
 pub mod ollama;
+pub mod pacing;
+pub mod pagination;
+#[cfg(feature = "postgres")]
+pub mod pg_query;
+pub mod profile;
+pub mod progress;
+pub mod prompts;
+pub mod regex_extract;
+pub mod render_template;
+pub mod replay;
+pub mod repository;
+pub mod resources;
+pub mod response_stream;
+pub mod sampling;
+pub mod sandbox;
+pub mod scheduler;
+pub mod schema;
+#[cfg(feature = "screenshot")]
+pub mod screenshot;
+pub mod server_builder;
+pub mod server_info;
+pub mod server_log;
+pub mod server_request;
+pub mod session;
+pub mod shadow;
+#[cfg(feature = "speech")]
+pub mod speech;
+pub mod sse;
+#[cfg(feature = "ssh")]
+pub mod ssh;
+pub mod stdio;
+pub mod summarize;
+pub mod text_encoding;
+pub mod tls;
+pub mod tokenizer;
+pub mod tool_error;
+pub mod tool_prompt;
+pub mod transfer;
+pub mod translate;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(unix)]
+pub mod unix_socket;
+pub mod url_download;
+pub mod vfs;
+pub mod webhook;
+pub mod xlsx_read;
 
 // Re-export for easy access
+pub use convert::{ConvertRequest, ConvertResponse, execute_convert};
+pub use diff::{DiffRequest, DiffResponse, execute_diff};
+pub use error::{McpError, TransportError};
+pub use file_list::{FileListRequest, FileListResponse, execute_file_list};
 pub use file_read::{FileReadRequest, FileReadResponse, execute_file_read};
-pub use mcp::{McpClient, McpServer, McpTool};
+pub use file_search::{FileSearchRequest, FileSearchResponse, execute_file_search};
+pub use generate::{GenerateRequest, GenerateResponse, execute_generate};
+pub use json_query::{JsonQueryRequest, JsonQueryResponse, execute_json_query};
+pub use log_tail::{LogTailRequest, LogTailResponse, execute_log_tail};
+pub use mcp::{McpClient, McpServer, McpTool, RequestId, ToolHandler};
 pub use ollama::{ChatMessage, ChatResponse, ChatSession, Ollama, OllamaConfig};
+pub use progress::ProgressReporter;
+pub use regex_extract::{RegexExtractRequest, RegexExtractResponse, execute_regex_extract};
+pub use render_template::{RenderTemplateRequest, RenderTemplateResponse, execute_render_template};
+pub use tool_prompt::{parse_tool_calls, render_tools_prompt};
+pub use xlsx_read::{XlsxReadRequest, XlsxReadResponse, execute_xlsx_read};
@@ -0,0 +1,446 @@
+//! Log tail tool module
+//!
+//! This module provides compact excerpts from (potentially very large) log files:
+//! head/tail windows, pattern filtering, time ranges and a severity summary.
+//! Files are streamed line by line so memory use stays bounded by the number of
+//! lines returned rather than the size of the file, and at most `MAX_SCAN_BYTES` of
+//! a file are read: the end of it for tails, the start for heads. An unfiltered tail
+//! only reads the end of the file and summarizes the lines it returns.
+
+use crate::vfs::{ReadSeek, VirtualFs};
+use regex::RegexBuilder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, SeekFrom};
+
+/// Default number of lines returned
+const DEFAULT_LINES: usize = 50;
+
+/// Upper bound on the number of lines returned
+const MAX_LINES: usize = 1000;
+
+/// Lines longer than this are truncated in the output
+const MAX_LINE_LENGTH: usize = 500;
+
+/// Bytes of a single line kept for matching; the rest of a longer line is skipped
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Upper bound on the bytes of a file read by one request
+const MAX_SCAN_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Severity levels recognized by the summary, in display order
+const SEVERITIES: [&str; 6] = ["FATAL", "ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+
+/// Log tail tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LogTailRequest {
+    /// The log file to read
+    pub path: String,
+    /// Return the last lines (tail) or the first lines (head)
+    #[schemars(extend("enum" = ["tail", "head"]))]
+    pub mode: Option<String>,
+    /// Maximum number of lines to return (default 50, max 1000)
+    pub lines: Option<usize>,
+    /// Only return lines matching this regular expression
+    pub grep: Option<String>,
+    /// Match grep case-insensitively
+    pub ignore_case: Option<bool>,
+    /// Only include lines logged at or after this timestamp, e.g. 2025-06-01T10:00:00
+    pub since: Option<String>,
+    /// Only include lines logged at or before this timestamp
+    pub until: Option<String>,
+    /// Count lines per severity level (default true): across the scanned range, or
+    /// the returned lines of a tail without grep, since or until
+    pub summarize: Option<bool>,
+}
+
+/// Log tail tool response
+#[derive(Debug, Serialize)]
+pub struct LogTailResponse {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub scanned_lines: usize,
+    pub matched_lines: usize,
+    pub severity_counts: BTreeMap<String, usize>,
+    /// Whether the byte limit stopped the scan before the start (tail) or the end
+    /// (head) of the file
+    pub scan_limited: bool,
+}
+
+impl LogTailResponse {
+    /// Render the response as compact text for the model
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "File: {}\nScanned lines: {}\nMatched lines: {}\nReturned lines: {}\n",
+            self.path,
+            self.scanned_lines,
+            self.matched_lines,
+            self.lines.len()
+        );
+        if self.scan_limited {
+            text.push_str(&format!(
+                "Scan limited to {} MiB of the file\n",
+                MAX_SCAN_BYTES / (1024 * 1024)
+            ));
+        }
+
+        if !self.severity_counts.is_empty() {
+            let summary: Vec<String> = SEVERITIES
+                .iter()
+                .filter_map(|level| {
+                    self.severity_counts
+                        .get(*level)
+                        .map(|count| format!("{}={}", level, count))
+                })
+                .collect();
+            text.push_str(&format!("Severity: {}\n", summary.join(" ")));
+        }
+
+        text.push('\n');
+        for line in &self.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Execute the log tail tool
+pub fn execute_log_tail(
+    request: LogTailRequest,
+    fs: &dyn VirtualFs,
+) -> Result<LogTailResponse, Box<dyn std::error::Error>> {
+    let path = crate::file_read::validate_path(fs, &request.path)?;
+    let limit = request.lines.unwrap_or(DEFAULT_LINES).min(MAX_LINES);
+    let summarize = request.summarize.unwrap_or(true);
+    let head = match request.mode.as_deref().unwrap_or("tail") {
+        "tail" => false,
+        "head" => true,
+        other => return Err(format!("Unknown mode '{}', expected head or tail", other).into()),
+    };
+
+    let pattern = match &request.grep {
+        Some(pattern) => Some(
+            RegexBuilder::new(pattern)
+                .case_insensitive(request.ignore_case.unwrap_or(false))
+                .size_limit(1 << 20)
+                .build()
+                .map_err(|e| format!("Invalid grep pattern: {}", e))?,
+        ),
+        None => None,
+    };
+    let since = request.since.as_deref().map(normalize_timestamp);
+    let until = request.until.as_deref().map(normalize_timestamp);
+    let filtered = pattern.is_some() || since.is_some() || until.is_some();
+
+    let mut file = fs
+        .open(path)
+        .map_err(|e| format!("Failed to open '{}': {}", request.path, e))?;
+
+    // Fast path: a plain tail only reads the end of the file
+    if !head && !filtered {
+        let (lines, scan_limited) = read_last_lines(&mut file, limit)?;
+        let mut severity_counts = BTreeMap::new();
+        if summarize {
+            for level in lines.iter().filter_map(|line| detect_severity(line)) {
+                *severity_counts.entry(level.to_string()).or_insert(0) += 1;
+            }
+        }
+        return Ok(LogTailResponse {
+            path: request.path,
+            scanned_lines: lines.len(),
+            matched_lines: lines.len(),
+            lines,
+            severity_counts,
+            scan_limited,
+        });
+    }
+
+    // A filtered tail reads the last MAX_SCAN_BYTES, from the first full line in them
+    let mut scan_limited = false;
+    let size = file.seek(SeekFrom::End(0))?;
+    let start = if head {
+        0
+    } else {
+        size.saturating_sub(MAX_SCAN_BYTES)
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    let mut scanned_bytes = 0;
+    if start > 0 {
+        scan_limited = true;
+        scanned_bytes += read_line_capped(&mut reader, &mut buffer)?;
+    }
+    let mut selected = VecDeque::with_capacity(limit);
+    let mut severity_counts = BTreeMap::new();
+    let mut scanned_lines = 0;
+    let mut matched_lines = 0;
+    let mut current_timestamp: Option<String> = None;
+
+    loop {
+        if head && scanned_bytes >= MAX_SCAN_BYTES as usize {
+            scan_limited = true;
+            break;
+        }
+        buffer.clear();
+        let read = read_line_capped(&mut reader, &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        scanned_bytes += read;
+        scanned_lines += 1;
+        let line = String::from_utf8_lossy(&buffer);
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        // Continuation lines (e.g. stack traces) inherit the previous timestamp
+        if let Some(timestamp) = extract_timestamp(line) {
+            current_timestamp = Some(timestamp);
+        }
+
+        if let Some(since) = &since {
+            match &current_timestamp {
+                Some(ts) if ts.as_str() >= since.as_str() => {}
+                _ => continue,
+            }
+        }
+        if let Some(until) = &until {
+            match &current_timestamp {
+                Some(ts) if ts.get(..until.len()).unwrap_or(ts) <= until.as_str() => {}
+                _ => continue,
+            }
+        }
+
+        if summarize && let Some(level) = detect_severity(line) {
+            *severity_counts.entry(level.to_string()).or_insert(0) += 1;
+        }
+
+        if let Some(pattern) = &pattern
+            && !pattern.is_match(line)
+        {
+            continue;
+        }
+        matched_lines += 1;
+
+        if head {
+            if selected.len() < limit {
+                selected.push_back(truncate_line(line));
+            } else if !summarize {
+                break;
+            }
+        } else {
+            if selected.len() == limit {
+                selected.pop_front();
+            }
+            if limit > 0 {
+                selected.push_back(truncate_line(line));
+            }
+        }
+    }
+
+    Ok(LogTailResponse {
+        path: request.path,
+        lines: selected.into(),
+        scanned_lines,
+        matched_lines,
+        severity_counts,
+        scan_limited,
+    })
+}
+
+/// Read the next line into `buffer`, keeping at most `MAX_LINE_BYTES` of it
+///
+/// Returns the number of bytes read, including those skipped, and 0 at the end of the
+/// file.
+fn read_line_capped(reader: &mut impl BufRead, buffer: &mut Vec<u8>) -> std::io::Result<usize> {
+    let mut read = 0;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+        let newline = available.iter().position(|&b| b == b'\n');
+        let end = newline.map_or(available.len(), |i| i + 1);
+        let room = MAX_LINE_BYTES.saturating_sub(buffer.len());
+        buffer.extend_from_slice(&available[..end.min(room)]);
+        reader.consume(end);
+        read += end;
+        if newline.is_some() {
+            break;
+        }
+    }
+    crate::accounting::read(read as u64);
+    Ok(read)
+}
+
+/// Read the last `limit` lines of a file by scanning backwards from the end
+///
+/// Returns whether `MAX_SCAN_BYTES` were read before the lines were complete, in which
+/// case the earliest line may be a partial one.
+fn read_last_lines(
+    file: &mut dyn ReadSeek,
+    limit: usize,
+) -> Result<(Vec<String>, bool), Box<dyn std::error::Error>> {
+    const CHUNK_SIZE: u64 = 64 * 1024;
+
+    let end = file.seek(SeekFrom::End(0))?;
+    let mut position = end;
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+    let mut newlines = 0;
+
+    while position > 0 {
+        if end - position >= MAX_SCAN_BYTES {
+            break;
+        }
+        let read_size = CHUNK_SIZE.min(position);
+        position -= read_size;
+        file.seek(SeekFrom::Start(position))?;
+
+        let mut chunk = vec![0; read_size as usize];
+        file.read_exact(&mut chunk)?;
+        crate::accounting::read(read_size);
+        newlines += chunk.iter().filter(|&&b| b == b'\n').count();
+        chunks.push(chunk);
+
+        // One extra newline is needed to know the earliest line is complete
+        if newlines > limit {
+            break;
+        }
+    }
+
+    chunks.reverse();
+    let data = chunks.concat();
+    let text = String::from_utf8_lossy(&data);
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(limit);
+    let lines = lines[start..]
+        .iter()
+        .map(|line| truncate_line(line))
+        .collect();
+    Ok((lines, position > 0))
+}
+
+/// Normalize a timestamp so lexical comparison matches chronological order
+fn normalize_timestamp(value: &str) -> String {
+    value.trim().replacen('T', " ", 1)
+}
+
+/// Extract a leading ISO-8601-like timestamp (`YYYY-MM-DD[T ]HH:MM:SS...`) from a line
+///
+/// A bracketed timestamp such as `[2025-06-01 10:00:00]` is accepted as well.
+fn extract_timestamp(line: &str) -> Option<String> {
+    let candidate = line.trim_start().trim_start_matches('[');
+    let bytes = candidate.as_bytes();
+    let is_date = bytes.len() >= 10
+        && bytes[..4].iter().all(u8::is_ascii_digit)
+        && bytes[4] == b'-'
+        && bytes[5..7].iter().all(u8::is_ascii_digit)
+        && bytes[7] == b'-'
+        && bytes[8..10].iter().all(u8::is_ascii_digit);
+    if !is_date {
+        return None;
+    }
+
+    let end = candidate
+        .char_indices()
+        .skip(10)
+        .find(|(i, c)| {
+            !(c.is_ascii_digit()
+                || matches!(c, ':' | '.' | ',')
+                || (*i == 10 && matches!(c, 'T' | ' ')))
+        })
+        .map(|(i, _)| i)
+        .unwrap_or(candidate.len());
+
+    Some(normalize_timestamp(candidate[..end].trim_end()))
+}
+
+/// Detect the severity level mentioned in a log line
+fn detect_severity(line: &str) -> Option<&'static str> {
+    line.split(|c: char| !c.is_ascii_alphabetic())
+        .find_map(|word| match word.to_ascii_uppercase().as_str() {
+            "FATAL" | "CRITICAL" | "CRIT" | "PANIC" => Some("FATAL"),
+            "ERROR" | "ERR" => Some("ERROR"),
+            "WARN" | "WARNING" => Some("WARN"),
+            "INFO" => Some("INFO"),
+            "DEBUG" => Some("DEBUG"),
+            "TRACE" => Some("TRACE"),
+            _ => None,
+        })
+}
+
+/// Truncate overly long lines to keep the output compact
+fn truncate_line(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_LENGTH {
+        return line.to_string();
+    }
+    let truncated: String = line.chars().take(MAX_LINE_LENGTH).collect();
+    format!("{}… [truncated]", truncated)
+}
+
+/// Get the tool definition for the log_tail tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "log_tail".to_string(),
+        description: Some(format!(
+            "Return a compact excerpt of a log file within {}: the first or last lines, optionally filtered by a regex and a timestamp range, with a count of lines per severity level.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<LogTailRequest>()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+    use std::io::Cursor;
+
+    const PATH: &str = "/tmp/allowed_files/app.log";
+
+    fn request(value: serde_json::Value) -> LogTailRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_plain_tail_summarizes_returned_lines() {
+        let fs = MemoryFs::new().with_file(PATH, "ERROR early\nINFO a\nWARN b\nINFO c\n");
+        let tail = execute_log_tail(
+            request(serde_json::json!({ "path": PATH, "lines": 2 })),
+            &fs,
+        )
+        .unwrap();
+        assert_eq!(tail.lines, vec!["WARN b", "INFO c"]);
+        assert_eq!(tail.scanned_lines, 2);
+        assert_eq!(tail.severity_counts.get("ERROR"), None);
+        assert_eq!(tail.severity_counts.get("WARN"), Some(&1));
+        assert!(!tail.scan_limited);
+    }
+
+    #[test]
+    fn test_read_line_capped_skips_rest_of_long_line() {
+        let mut data = vec![b'x'; MAX_LINE_BYTES * 2];
+        data.extend_from_slice(b"\nnext\n");
+        let mut reader = BufReader::new(Cursor::new(data));
+        let mut buffer = Vec::new();
+
+        let read = read_line_capped(&mut reader, &mut buffer).unwrap();
+        assert_eq!(read, MAX_LINE_BYTES * 2 + 1);
+        assert_eq!(buffer.len(), MAX_LINE_BYTES);
+
+        buffer.clear();
+        assert_eq!(read_line_capped(&mut reader, &mut buffer).unwrap(), 5);
+        assert_eq!(buffer, b"next\n");
+    }
+
+    #[test]
+    fn test_read_last_lines_stops_at_scan_limit() {
+        let mut data = vec![b'x'; MAX_SCAN_BYTES as usize + 1024];
+        data.extend_from_slice(b"\nlast\n");
+        let (lines, limited) = read_last_lines(&mut Cursor::new(data), 5).unwrap();
+        assert!(limited);
+        assert_eq!(lines.last().map(String::as_str), Some("last"));
+    }
+}
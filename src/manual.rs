@@ -0,0 +1,63 @@
+//! Manual module
+//!
+//! This module generates the shell completion scripts and manual pages of mcp-client
+//! and mcp-server from their command line definitions, so every flag is documented and
+//! completed in the terminal without a separate copy to keep in sync. Both binaries
+//! expose it as their `completions` and `manpage` subcommands.
+
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Print the completion script of a binary for `shell` to stdout
+pub fn print_completions(
+    mut command: clap::Command,
+    shell: Shell,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = command.get_name().to_string();
+    // clap_complete panics on write errors, so the script is written out here instead
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(&script)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Print the manual page of a binary to stdout
+pub fn print_manpage(command: clap::Command) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = std::io::stdout().lock();
+    clap_mangen::Man::new(command).render(&mut stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Write the manual pages of a binary and of each of its visible subcommands, such as
+/// `mcp-client.1` and `mcp-client-init.1`, to a directory
+///
+/// Returns the paths of the pages written, creating the directory if needed.
+pub fn write_manpages(
+    command: clap::Command,
+    out_dir: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(out_dir)
+        .map_err(|e| format!("Failed to create '{}': {}", out_dir.display(), e))?;
+    let mut command = command.disable_help_subcommand(true);
+    command.build();
+    let mut paths = Vec::new();
+    write_pages(command, out_dir, &mut paths)
+        .map_err(|e| format!("Failed to write to '{}': {}", out_dir.display(), e))?;
+    Ok(paths)
+}
+
+fn write_pages(
+    command: clap::Command,
+    out_dir: &Path,
+    paths: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    paths.push(clap_mangen::Man::new(command.clone()).generate_to(out_dir)?);
+    for subcommand in command.get_subcommands().filter(|s| !s.is_hide_set()) {
+        write_pages(subcommand.clone(), out_dir, paths)?;
+    }
+    Ok(())
+}
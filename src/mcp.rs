@@ -3,56 +3,345 @@
 //! This module provides functionality for communicating with MCP servers using JSON-RPC 2.0
 //! and implementing MCP servers.
 
-use axum::{Router, extract::State, http::StatusCode, response::Json, routing::post};
+use crate::cancellation::{CancellationToken, InFlight};
+use crate::deadline::Deadline;
+use crate::error::{McpError, TransportError};
+use crate::jsonrpc_body::JsonRpcBody;
+use crate::progress::ProgressReporter;
+use crate::tool_error::{ToolErrorCode, ToolErrorPayload};
+use axum::{
+    Extension, Router,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use serde_json::value::RawValue;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use tower_http::cors::CorsLayer;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use tokio::sync::{RwLock, broadcast};
 use uuid::Uuid;
 
 /// JSON-RPC 2.0 request structure
+///
+/// A message without an id is a notification, which gets no response.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
     pub jsonrpc: String,
-    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<RequestId>,
     pub method: String,
     pub params: Option<Value>,
 }
 
+/// Id of a JSON-RPC request, echoed unchanged in its response
+///
+/// Clients may use strings or integers; `1` and `"1"` are different ids.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(i64),
+    String(String),
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestId::Number(id) => write!(f, "{}", id),
+            RequestId::String(id) => f.write_str(id),
+        }
+    }
+}
+
+impl From<String> for RequestId {
+    fn from(id: String) -> Self {
+        RequestId::String(id)
+    }
+}
+
+impl From<&str> for RequestId {
+    fn from(id: &str) -> Self {
+        RequestId::String(id.to_string())
+    }
+}
+
+impl From<i64> for RequestId {
+    fn from(id: i64) -> Self {
+        RequestId::Number(id)
+    }
+}
+
+impl<'de> Deserialize<'de> for RequestId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl serde::de::Visitor<'_> for IdVisitor {
+            type Value = RequestId;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string or an integer request id")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, id: &str) -> Result<RequestId, E> {
+                Ok(RequestId::String(id.to_string()))
+            }
+
+            fn visit_string<E: serde::de::Error>(self, id: String) -> Result<RequestId, E> {
+                Ok(RequestId::String(id))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, id: i64) -> Result<RequestId, E> {
+                Ok(RequestId::Number(id))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, id: u64) -> Result<RequestId, E> {
+                i64::try_from(id)
+                    .map(RequestId::Number)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(id), &self))
+            }
+        }
+
+        deserializer.deserialize_any(IdVisitor)
+    }
+}
+
 /// JSON-RPC 2.0 response structure
+///
+/// The server uses its own result type so large tool results are serialized straight
+/// into the response body instead of first becoming a `Value` tree.
 #[derive(Debug, Deserialize, Serialize)]
-pub struct JsonRpcResponse {
+pub struct JsonRpcResponse<R = Value> {
     pub jsonrpc: String,
-    pub id: String,
-    #[serde(default)]
-    pub result: Option<Value>,
+    /// Id of the request answered; null when it could not be read
     #[serde(default)]
+    pub id: Option<RequestId>,
+    /// Set on success; an error response has no result member
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<R>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub error: Option<JsonRpcError>,
 }
 
+/// JSON-RPC 2.0 notification structure (a request without an id)
+///
+/// The server also pushes its requests to clients this way, with an id, see
+/// [`crate::server_request`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<RequestId>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
 /// JSON-RPC 2.0 error structure
 #[derive(Debug, Deserialize, Serialize)]
 pub struct JsonRpcError {
     pub code: i32,
     pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub data: Option<Value>,
 }
 
 /// MCP Tool definition
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct McpTool {
     pub name: String,
+    /// Version of the tool's interface, such as `2.1.0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     pub description: Option<String>,
-    pub inputSchema: Option<Value>,
+    #[serde(rename = "inputSchema")]
+    pub input_schema: Option<Value>,
+    /// JSON Schema of the tool's `structuredContent`, which its results then conform to
+    #[serde(
+        rename = "outputSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output_schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+impl McpTool {
+    /// Whether a human must approve each call of this tool before it runs
+    pub fn requires_approval(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|annotations| annotations.requires_approval)
+            .unwrap_or(false)
+    }
+
+    /// Whether the tool is deprecated and should no longer be called
+    pub fn is_deprecated(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|annotations| annotations.deprecated)
+            .unwrap_or(false)
+    }
+
+    /// A warning about calling the tool, if it is deprecated
+    pub fn deprecation_warning(&self) -> Option<String> {
+        if !self.is_deprecated() {
+            return None;
+        }
+        let message = self
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.deprecation_message.as_deref());
+        Some(match message {
+            Some(message) => format!("tool '{}' is deprecated: {}", self.name, message),
+            None => format!("tool '{}' is deprecated", self.name),
+        })
+    }
+}
+
+/// Hints describing a tool's behavior
+///
+/// `requiresApproval` is an extension to the MCP annotations asking hosts to confirm
+/// every call with the user, and `deprecated` one announcing that the tool is going
+/// away, with `deprecationMessage` saying what to use instead.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_approval: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation_message: Option<String>,
 }
 
 /// Response from tools/list request
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ToolsListResponse {
     pub tools: Vec<McpTool>,
+    /// Cursor of the next page, absent on the last one
+    #[serde(
+        rename = "nextCursor",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_cursor: Option<String>,
+}
+
+/// Borrowed page of a tools/list result, serialized by the server
+#[derive(Serialize)]
+struct ToolsList<'a> {
+    tools: Vec<&'a McpTool>,
+    #[serde(rename = "nextCursor", skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+/// A directory the server's file tools may access
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Root {
+    /// `file://` URI of the directory
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Root {
+    /// The directory path of a `file://` root
+    pub fn path(&self) -> std::borrow::Cow<'_, str> {
+        crate::file_read::path_from_file_uri(&self.uri)
+    }
+}
+
+/// Response from fs/roots request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RootsListResponse {
+    pub roots: Vec<Root>,
+}
+
+/// The MCP protocol version this crate implements
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Protocol versions the client and server can negotiate, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Name and version of an MCP client or server
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Implementation {
+    pub name: String,
+    pub version: String,
+}
+
+/// Features a client supports, announced during initialization
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ClientCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roots: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elicitation: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<Value>,
+}
+
+/// Features a server supports, announced during initialization
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ServerCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<Value>,
+}
+
+/// Params of the initialize request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeRequest {
+    pub protocol_version: String,
+    #[serde(default)]
+    pub capabilities: ClientCapabilities,
+    pub client_info: Implementation,
+    /// Languages the user reads, as an HTTP `Accept-Language` value (e.g. `de-CH, de;q=0.9`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_language: Option<String>,
+}
+
+/// Response from initialize request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    /// The protocol version both sides will use
+    pub protocol_version: String,
+    pub capabilities: ServerCapabilities,
+    pub server_info: Implementation,
+    /// Hints for the model on how to use the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
 }
 
 /// Request for tools/call endpoint
@@ -60,375 +349,3455 @@ pub struct ToolsListResponse {
 pub struct ToolsCallRequest {
     pub name: String,
     pub arguments: Option<Value>,
+    #[serde(rename = "_meta", default)]
+    pub meta: Option<crate::progress::RequestMeta>,
 }
 
 /// Response from tools/call request
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ToolsCallResponse {
     pub content: Vec<ToolContent>,
+    /// Machine-readable result; the [`ToolErrorPayload`] of failed calls
+    #[serde(
+        rename = "structuredContent",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub structured_content: Option<Value>,
+    /// Whether the tool failed; the content then describes the failure
+    #[serde(
+        rename = "isError",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub is_error: bool,
 }
 
-/// Tool execution result content
-#[derive(Debug, Serialize)]
+impl ToolsCallResponse {
+    /// A failed call described only by a message, reported with the `FAILED` code
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::from(ToolErrorPayload::new(ToolErrorCode::Failed, message))
+    }
+
+    /// A failed call: the message as text for the model, the payload as structured content
+    pub fn error_with_payload(text: impl Into<String>, payload: &ToolErrorPayload) -> Self {
+        Self {
+            content: vec![ToolContent::text(text)],
+            structured_content: serde_json::to_value(payload).ok(),
+            is_error: true,
+        }
+    }
+
+    /// The text content, one item per line, such as the message of a failed call
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|content| content.text.as_deref())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A result for programs: the value as structured content, and as JSON text for
+    /// clients that only read the content
+    pub fn structured(value: &impl Serialize) -> Result<Self, serde_json::Error> {
+        let value = serde_json::to_value(value)?;
+        Ok(Self {
+            content: vec![ToolContent::text(serde_json::to_string_pretty(&value)?)],
+            structured_content: Some(value),
+            is_error: false,
+        })
+    }
+}
+
+impl From<ToolErrorPayload> for ToolsCallResponse {
+    fn from(payload: ToolErrorPayload) -> Self {
+        Self::error_with_payload(payload.to_string(), &payload)
+    }
+}
+
+impl From<String> for ToolsCallResponse {
+    fn from(text: String) -> Self {
+        Self::from(vec![ToolContent::text(text)])
+    }
+}
+
+impl From<Vec<ToolContent>> for ToolsCallResponse {
+    fn from(content: Vec<ToolContent>) -> Self {
+        Self {
+            content,
+            structured_content: None,
+            is_error: false,
+        }
+    }
+}
+
+/// Tool execution result content: text, image, audio or an embedded resource
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ToolContent {
     #[serde(rename = "type")]
     pub content_type: String,
-    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Base64-encoded payload for non-text content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Contents of an embedded resource, which carries its own MIME type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<crate::resources::ResourceContents>,
+}
+
+impl ToolContent {
+    /// Create text content
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content_type: "text".to_string(),
+            text: Some(text.into()),
+            data: None,
+            mime_type: None,
+            resource: None,
+        }
+    }
+
+    /// Create image content from raw image bytes, encoded as base64
+    pub fn image(data: &[u8], mime_type: impl Into<String>) -> Self {
+        Self::image_base64(STANDARD.encode(data), mime_type)
+    }
+
+    /// Create audio content from raw audio bytes, encoded as base64
+    pub fn audio(data: &[u8], mime_type: impl Into<String>) -> Self {
+        Self::audio_base64(STANDARD.encode(data), mime_type)
+    }
+
+    /// Create image content from data that is already base64-encoded
+    pub fn image_base64(data: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        Self {
+            content_type: "image".to_string(),
+            text: None,
+            data: Some(data.into()),
+            mime_type: Some(mime_type.into()),
+            resource: None,
+        }
+    }
+
+    /// Create audio content from data that is already base64-encoded
+    pub fn audio_base64(data: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        Self {
+            content_type: "audio".to_string(),
+            text: None,
+            data: Some(data.into()),
+            mime_type: Some(mime_type.into()),
+            resource: None,
+        }
+    }
+
+    /// Create embedded resource content, such as a file the tool produced
+    pub fn resource(contents: crate::resources::ResourceContents) -> Self {
+        Self {
+            content_type: "resource".to_string(),
+            text: None,
+            data: None,
+            mime_type: None,
+            resource: Some(contents),
+        }
+    }
+}
+
+/// How an McpClient reaches its server
+enum Transport {
+    /// MCP Streamable HTTP: JSON-RPC over HTTP POST within a session
+    Http {
+        base_url: String,
+        client: Client,
+        /// Session id assigned by the server in the `Mcp-Session-Id` header
+        session_id: Mutex<Option<String>>,
+        /// Headers sent with every request, such as `Authorization`
+        headers: Mutex<reqwest::header::HeaderMap>,
+        /// Acquires the bearer token when the server requires authorization
+        oauth: OnceLock<Box<crate::oauth::OAuth>>,
+        /// TLS options, also for the notification stream and authorization
+        tls: Arc<crate::tls::TlsConfig>,
+    },
+    /// Newline-delimited JSON-RPC over a child process's stdin/stdout
+    Stdio(Mutex<crate::stdio::StdioConnection>),
+    /// HTTP+SSE: messages are posted and answered on a Server-Sent Events stream
+    Sse(crate::sse::SseConnection),
+    /// Newline-delimited JSON-RPC over a unix domain socket
+    #[cfg(unix)]
+    Unix(Mutex<crate::unix_socket::UnixConnection>),
 }
 
 /// MCP Client for communicating with MCP servers
+///
+/// Clones share the connection and session, so one thread can cancel a call another
+/// is waiting for.
+#[derive(Clone)]
 pub struct McpClient {
-    base_url: String,
-    client: Client,
+    inner: Arc<ClientInner>,
+}
+
+/// A tools/call request waiting for its response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingToolCall {
+    pub request_id: RequestId,
+    pub tool: String,
+}
+
+/// State of a client, shared with the threads that handle its notifications
+struct ClientInner {
+    transport: Transport,
+    /// URL or command line of the server, for messages
+    endpoint: String,
+    /// The server's answer to the initialize request
+    server: OnceLock<InitializeResult>,
+    /// Callbacks for the notifications the server pushes
+    listeners: crate::sse::NotificationListeners,
+    /// Notification stream of the Streamable HTTP session, once a callback needs it
+    notification_stream: Mutex<Option<crate::sse::NotificationStream>>,
+    /// Whether tool calls ask for progress notifications
+    wants_progress: AtomicBool,
+    /// Tool called by each pending tools/call request, by request id
+    pending_calls: Mutex<HashMap<RequestId, String>>,
+    /// Handlers of the requests the server sends, shared with the transport
+    requests: crate::server_request::RequestHandlers,
 }
 
 impl McpClient {
-    /// Create a new MCP client
-    pub fn new(base_url: impl Into<String>) -> Self {
+    fn from_inner(inner: ClientInner) -> Self {
         Self {
-            base_url: base_url.into(),
-            client: Client::new(),
+            inner: Arc::new(inner),
         }
     }
 
-    /// Generate a unique request ID
-    fn generate_id() -> String {
-        Uuid::new_v4().to_string()
+    /// Create a new MCP client
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_client(base_url.into(), Client::new(), Default::default())
     }
 
-    /// Make a JSON-RPC request to the MCP server
-    pub fn make_request(
-        &self,
-        method: &str,
-        params: Option<Value>,
-    ) -> Result<JsonRpcResponse, Box<dyn std::error::Error>> {
-        let request = JsonRpcRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Self::generate_id(),
-            method: method.to_string(),
-            params,
-        };
-
-        let response = self.client.post(&self.base_url).json(&request).send()?;
-
-        if response.status().is_success() {
-            let json_response: JsonRpcResponse = response.json()?;
+    /// Create a client for an `https://` server with custom TLS options, such as a
+    /// private CA or a client certificate
+    pub fn new_with_tls(
+        base_url: impl Into<String>,
+        tls: crate::tls::TlsConfig,
+    ) -> Result<Self, McpError> {
+        let client = tls.apply(Client::builder())?.build()?;
+        Ok(Self::with_client(base_url.into(), client, tls))
+    }
 
-            if let Some(error) = &json_response.error {
-                return Err(format!("MCP server error {}: {}", error.code, error.message).into());
-            }
+    fn with_client(base_url: String, client: Client, tls: crate::tls::TlsConfig) -> Self {
+        Self::from_inner(ClientInner {
+            endpoint: base_url.clone(),
+            transport: Transport::Http {
+                base_url,
+                client,
+                session_id: Mutex::new(None),
+                headers: Mutex::default(),
+                oauth: OnceLock::new(),
+                tls: Arc::new(tls),
+            },
+            server: OnceLock::new(),
+            listeners: Default::default(),
+            notification_stream: Mutex::new(None),
+            wants_progress: AtomicBool::new(false),
+            pending_calls: Mutex::default(),
+            requests: Default::default(),
+        })
+    }
 
-            Ok(json_response)
-        } else {
-            let status = response.status();
-            let error_text = response.text()?;
-            Err(format!("HTTP error {}: {}", status, error_text).into())
-        }
+    /// Create a client for an MCP server launched as a child process
+    ///
+    /// Messages are exchanged over the child's stdin/stdout. The child is shut down
+    /// when the client is dropped.
+    pub fn spawn(command: &str, args: &[String]) -> Result<Self, McpError> {
+        Self::spawn_with_env(command, args, std::iter::empty::<(String, String)>())
     }
 
-    /// Get the list of available tools from the MCP server
-    pub fn list_tools(&self) -> Result<Vec<McpTool>, Box<dyn std::error::Error>> {
-        println!("Requesting tool list from MCP server: {}", self.base_url);
+    /// Like [`McpClient::spawn`], setting environment variables of the child, such as
+    /// the `env` of a host configuration
+    pub fn spawn_with_env<K, V>(
+        command: &str,
+        args: &[String],
+        env: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, McpError>
+    where
+        K: AsRef<std::ffi::OsStr>,
+        V: AsRef<std::ffi::OsStr>,
+    {
+        let mut connection = crate::stdio::StdioConnection::spawn_with_env(command, args, env)?;
+        let requests = crate::server_request::RequestHandlers::default();
+        connection.set_request_handlers(requests.clone());
+        Ok(Self::from_inner(ClientInner {
+            endpoint: connection.command().to_string(),
+            transport: Transport::Stdio(Mutex::new(connection)),
+            server: OnceLock::new(),
+            listeners: Default::default(),
+            notification_stream: Mutex::new(None),
+            wants_progress: AtomicBool::new(false),
+            pending_calls: Mutex::default(),
+            requests,
+        }))
+    }
 
-        let response = self.make_request("tools/list", None)?;
+    /// Connect to an MCP server over the HTTP+SSE transport
+    ///
+    /// `url` is the server's SSE stream (e.g. `http://localhost:8080/sse`). Requests are
+    /// posted to the endpoint the server announces on the stream and answered on it,
+    /// and the stream also delivers server notifications, see
+    /// [`McpClient::take_notifications`].
+    pub fn connect_sse(url: impl Into<String>) -> Result<Self, McpError> {
+        Self::connect_sse_with_tls(url, &Default::default())
+    }
 
-        if let Some(result) = response.result {
-            let tools_response: ToolsListResponse = serde_json::from_value(result)?;
-            println!(
-                "Retrieved {} tools from MCP server",
-                tools_response.tools.len()
-            );
+    /// Like [`McpClient::connect_sse`], with custom TLS options
+    pub fn connect_sse_with_tls(
+        url: impl Into<String>,
+        tls: &crate::tls::TlsConfig,
+    ) -> Result<Self, McpError> {
+        let url = url.into();
+        let listeners = crate::sse::NotificationListeners::default();
+        let requests = crate::server_request::RequestHandlers::default();
+        Ok(Self::from_inner(ClientInner {
+            transport: Transport::Sse(crate::sse::SseConnection::connect(
+                &url,
+                tls,
+                Arc::clone(&listeners),
+                requests.clone(),
+            )?),
+            endpoint: url,
+            server: OnceLock::new(),
+            listeners,
+            notification_stream: Mutex::new(None),
+            wants_progress: AtomicBool::new(false),
+            pending_calls: Mutex::default(),
+            requests,
+        }))
+    }
 
-            for tool in &tools_response.tools {
-                println!(
-                    "  - Tool: {} - {}",
-                    tool.name,
-                    tool.description.as_deref().unwrap_or("No description")
-                );
-            }
+    /// Connect to an MCP server listening on a unix domain socket
+    ///
+    /// See [`McpServer::bind_unix`].
+    #[cfg(unix)]
+    pub fn connect_unix(path: impl AsRef<std::path::Path>) -> Result<Self, McpError> {
+        let mut connection = crate::unix_socket::UnixConnection::connect(path)?;
+        let requests = crate::server_request::RequestHandlers::default();
+        connection.set_request_handlers(requests.clone());
+        Ok(Self::from_inner(ClientInner {
+            endpoint: connection.path().to_string(),
+            transport: Transport::Unix(Mutex::new(connection)),
+            server: OnceLock::new(),
+            listeners: Default::default(),
+            notification_stream: Mutex::new(None),
+            wants_progress: AtomicBool::new(false),
+            pending_calls: Mutex::default(),
+            requests,
+        }))
+    }
 
-            Ok(tools_response.tools)
-        } else {
-            Err("No result in tools/list response".into())
-        }
+    /// Send a header with every request to the server
+    ///
+    /// Only Streamable HTTP clients (see [`McpClient::new`]) send headers; the name and
+    /// value must be valid in HTTP. Setting a header again replaces its value.
+    pub fn with_header(self, name: &str, value: &str) -> Result<Self, McpError> {
+        self.set_header(name, value)?;
+        Ok(self)
     }
 
-    /// Call a specific tool on the MCP server
-    pub fn call_tool(
-        &self,
-        name: &str,
-        arguments: Option<Value>,
-    ) -> Result<Value, Box<dyn std::error::Error>> {
-        let params = serde_json::json!({
-            "name": name,
-            "arguments": arguments
-        });
+    /// Authenticate every request with `Authorization: Bearer <token>`
+    pub fn with_bearer_token(self, token: &str) -> Result<Self, McpError> {
+        self.with_header(
+            reqwest::header::AUTHORIZATION.as_str(),
+            &format!("Bearer {}", token),
+        )
+    }
 
-        let response = self.make_request("tools/call", Some(params))?;
+    /// Authorize with the server's authorization server when it asks for it
+    ///
+    /// Only Streamable HTTP clients authorize. Once the server answers 401, `oauth`
+    /// acquires a token, asking the user if it has no valid one, and the request is
+    /// sent again; later requests carry the token, which is refreshed as it expires.
+    pub fn with_oauth(self, mut oauth: crate::oauth::OAuth) -> Result<Self, McpError> {
+        let Transport::Http {
+            oauth: slot, tls, ..
+        } = &self.inner.transport
+        else {
+            return Err(McpError::protocol(format!(
+                "Cannot authorize with {}: only HTTP clients use OAuth",
+                self.inner.endpoint
+            )));
+        };
+        oauth.use_tls(tls)?;
+        slot.set(Box::new(oauth))
+            .map_err(|_| McpError::protocol("The client already uses OAuth"))?;
+        Ok(self)
+    }
 
-        if let Some(result) = response.result {
-            Ok(result)
-        } else {
-            Err("No result in tools/call response".into())
+    fn set_header(&self, name: &str, value: &str) -> Result<(), McpError> {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| McpError::protocol(format!("Invalid header name '{}': {}", name, e)))?;
+        let mut value = reqwest::header::HeaderValue::from_str(value).map_err(|e| {
+            McpError::protocol(format!("Invalid value of header '{}': {}", name, e))
+        })?;
+        if name == reqwest::header::AUTHORIZATION {
+            value.set_sensitive(true);
         }
+        let Transport::Http { headers, .. } = &self.inner.transport else {
+            return Err(McpError::protocol(format!(
+                "Cannot send header '{}' to {}: only HTTP clients send headers",
+                name, self.inner.endpoint
+            )));
+        };
+        headers
+            .lock()
+            .map_err(|_| McpError::connection("Headers lock poisoned"))?
+            .insert(name, value);
+        Ok(())
     }
-}
-
-/// MCP Server state containing registered tools
-#[derive(Debug, Clone)]
-pub struct McpServerState {
-    pub tools: Arc<RwLock<HashMap<String, McpTool>>>,
-}
 
-impl McpServerState {
-    pub fn new() -> Self {
-        Self {
-            tools: Arc::new(RwLock::new(HashMap::new())),
+    /// The headers sent with every HTTP request
+    fn http_headers(&self) -> Result<reqwest::header::HeaderMap, McpError> {
+        match &self.inner.transport {
+            Transport::Http { headers, .. } => Ok(headers
+                .lock()
+                .map_err(|_| McpError::connection("Headers lock poisoned"))?
+                .clone()),
+            _ => Ok(reqwest::header::HeaderMap::new()),
         }
     }
 
-    /// Add a tool to the server
-    pub async fn add_tool(&self, tool: McpTool) {
-        let mut tools = self.tools.write().await;
-        tools.insert(tool.name.clone(), tool);
+    /// URL or command line of the server
+    pub fn endpoint(&self) -> &str {
+        &self.inner.endpoint
     }
 
-    /// Get all tools
-    pub async fn get_tools(&self) -> Vec<McpTool> {
-        let tools = self.tools.read().await;
-        tools.values().cloned().collect()
+    /// A stdio or unix socket connection, locked for one exchange
+    fn lock_connection<T>(connection: &Mutex<T>) -> Result<MutexGuard<'_, T>, McpError> {
+        connection
+            .lock()
+            .map_err(|_| McpError::connection("MCP server connection lock poisoned"))
     }
 
-    /// Get a specific tool by name
-    pub async fn get_tool(&self, name: &str) -> Option<McpTool> {
-        let tools = self.tools.read().await;
-        tools.get(name).cloned()
+    /// POST a message within the session, authorizing the client if the server asks
+    fn post(
+        &self,
+        base_url: &str,
+        client: &Client,
+        session_id: &Mutex<Option<String>>,
+        message: &impl Serialize,
+    ) -> Result<reqwest::blocking::Response, McpError> {
+        let oauth = match &self.inner.transport {
+            Transport::Http { oauth, .. } => oauth.get(),
+            _ => None,
+        };
+        if let Some(oauth) = oauth
+            && let Some(token) = oauth.access_token(base_url)?
+        {
+            self.set_header(
+                reqwest::header::AUTHORIZATION.as_str(),
+                &format!("Bearer {}", token),
+            )?;
+        }
+
+        let mut response = self.send_post(base_url, client, session_id, message)?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED
+            && let Some(oauth) = oauth
+        {
+            let challenge = response
+                .headers()
+                .get(reqwest::header::WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let token = oauth.authorize(base_url, challenge.as_deref())?;
+            self.set_header(
+                reqwest::header::AUTHORIZATION.as_str(),
+                &format!("Bearer {}", token),
+            )?;
+            response = self.send_post(base_url, client, session_id, message)?;
+        }
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            let status = response.status();
+            let body = response.text()?;
+            Err(TransportError::Status { status, body }.into())
+        }
     }
-}
 
-/// Handle JSON-RPC requests
-async fn handle_jsonrpc(
-    State(state): State<McpServerState>,
-    Json(request): Json<JsonRpcRequest>,
-) -> Result<Json<JsonRpcResponse>, StatusCode> {
-    let response = match request.method.as_str() {
-        "tools/list" => {
-            let tools = state.get_tools().await;
-            JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: Some(serde_json::to_value(ToolsListResponse { tools }).unwrap()),
-                error: None,
-            }
+    /// POST a message once, remembering the session id the server assigns
+    fn send_post(
+        &self,
+        base_url: &str,
+        client: &Client,
+        session_id: &Mutex<Option<String>>,
+        message: &impl Serialize,
+    ) -> Result<reqwest::blocking::Response, McpError> {
+        let mut request = client
+            .post(base_url)
+            .headers(self.http_headers()?)
+            .header(
+                reqwest::header::ACCEPT,
+                "application/json, text/event-stream",
+            )
+            .json(message);
+        if let Some(id) = session_id
+            .lock()
+            .map_err(|_| McpError::connection("Session lock poisoned"))?
+            .as_ref()
+        {
+            request = request.header(crate::session::SESSION_HEADER, id);
         }
-        "tools/call" => {
-            if let Some(params) = request.params {
-                match serde_json::from_value::<ToolsCallRequest>(params) {
-                    Ok(call_request) => {
-                        match state.get_tool(&call_request.name).await {
-                            Some(_tool) => {
-                                // Handle specific tools
-                                match call_request.name.as_str() {
-                                    "file_read" => {
-                                        if let Some(arguments) = call_request.arguments {
-                                            match serde_json::from_value::<
-                                                crate::file_read::FileReadRequest,
-                                            >(
-                                                arguments
-                                            ) {
-                                                Ok(file_request) => {
-                                                    match crate::file_read::execute_file_read(
-                                                        file_request,
-                                                    ) {
-                                                        Ok(file_response) => {
-                                                            let result = ToolsCallResponse {
-                                                                content: vec![ToolContent {
-                                                                    content_type: "text"
-                                                                        .to_string(),
-                                                                    text: format!(
-                                                                        "File: {}\nSize: {} bytes\nMIME Type: {}\n\nContent:\n{}",
-                                                                        file_response.path,
-                                                                        file_response.size,
-                                                                        file_response
-                                                                            .mime_type
-                                                                            .as_deref()
-                                                                            .unwrap_or("unknown"),
-                                                                        file_response.content
-                                                                    ),
-                                                                }],
-                                                            };
-                                                            JsonRpcResponse {
-                                                                jsonrpc: "2.0".to_string(),
-                                                                id: request.id,
-                                                                result: Some(
-                                                                    serde_json::to_value(result)
-                                                                        .unwrap(),
-                                                                ),
-                                                                error: None,
-                                                            }
-                                                        }
-                                                        Err(e) => {
-                                                            let result = ToolsCallResponse {
-                                                                content: vec![ToolContent {
-                                                                    content_type: "text"
-                                                                        .to_string(),
-                                                                    text: format!(
-                                                                        "Error reading file: {}",
-                                                                        e
-                                                                    ),
-                                                                }],
-                                                            };
-                                                            JsonRpcResponse {
-                                                                jsonrpc: "2.0".to_string(),
-                                                                id: request.id,
-                                                                result: Some(
-                                                                    serde_json::to_value(result)
-                                                                        .unwrap(),
-                                                                ),
-                                                                error: None,
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                                Err(e) => JsonRpcResponse {
-                                                    jsonrpc: "2.0".to_string(),
-                                                    id: request.id,
-                                                    result: None,
-                                                    error: Some(JsonRpcError {
-                                                        code: -32602,
-                                                        message: format!(
-                                                            "Invalid file_read arguments: {}",
-                                                            e
-                                                        ),
-                                                        data: None,
-                                                    }),
-                                                },
-                                            }
-                                        } else {
-                                            JsonRpcResponse {
-                                                jsonrpc: "2.0".to_string(),
-                                                id: request.id,
-                                                result: None,
-                                                error: Some(JsonRpcError {
-                                                    code: -32602,
-                                                    message: "file_read tool requires arguments"
-                                                        .to_string(),
-                                                    data: None,
-                                                }),
-                                            }
-                                        }
-                                    }
-                                    _ => {
-                                        // Generic tool response for unknown tools
-                                        let result = ToolsCallResponse {
-                                            content: vec![ToolContent {
-                                                content_type: "text".to_string(),
-                                                text: format!(
-                                                    "Tool '{}' executed successfully with arguments: {:?}",
-                                                    call_request.name, call_request.arguments
-                                                ),
-                                            }],
-                                        };
-                                        JsonRpcResponse {
-                                            jsonrpc: "2.0".to_string(),
-                                            id: request.id,
-                                            result: Some(serde_json::to_value(result).unwrap()),
-                                            error: None,
-                                        }
-                                    }
-                                }
-                            }
-                            None => JsonRpcResponse {
-                                jsonrpc: "2.0".to_string(),
-                                id: request.id,
-                                result: None,
-                                error: Some(JsonRpcError {
-                                    code: -32601,
-                                    message: format!("Tool '{}' not found", call_request.name),
-                                    data: None,
-                                }),
-                            },
-                        }
-                    }
-                    Err(e) => JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request.id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32602,
-                            message: format!("Invalid params: {}", e),
-                            data: None,
-                        }),
-                    },
-                }
-            } else {
-                JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request.id,
-                    result: None,
-                    error: Some(JsonRpcError {
-                        code: -32602,
-                        message: "Missing params".to_string(),
-                        data: None,
-                    }),
-                }
-            }
+        if let Some(server) = self.inner.server.get() {
+            request = request.header(
+                crate::session::PROTOCOL_VERSION_HEADER,
+                &server.protocol_version,
+            );
         }
-        _ => JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: None,
-            error: Some(JsonRpcError {
-                code: -32601,
-                message: "Method not found".to_string(),
-                data: None,
-            }),
-        },
-    };
 
-    Ok(Json(response))
-}
+        let response = request.send()?;
+        if let Some(id) = response
+            .headers()
+            .get(crate::session::SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            *session_id
+                .lock()
+                .map_err(|_| McpError::connection("Session lock poisoned"))? = Some(id.to_string());
+        }
+        Ok(response)
+    }
 
-/// MCP Server that handles JSON-RPC requests
-pub struct McpServer {
-    state: McpServerState,
-    port: u16,
-}
+    /// Perform the MCP initialization handshake
+    ///
+    /// Negotiates the protocol version and exchanges capabilities, then confirms with
+    /// `notifications/initialized`. Must be called before any other request. Clients
+    /// answering requests from the server over Streamable HTTP open the session's
+    /// notification stream, which the requests arrive on.
+    pub fn initialize(&self) -> Result<InitializeResult, McpError> {
+        let params = InitializeRequest {
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+            capabilities: ClientCapabilities {
+                sampling: self
+                    .inner
+                    .requests
+                    .contains(crate::sampling::METHOD)
+                    .then(|| serde_json::json!({})),
+                elicitation: self
+                    .inner
+                    .requests
+                    .contains(crate::elicitation::METHOD)
+                    .then(|| serde_json::json!({})),
+                ..Default::default()
+            },
+            client_info: Implementation {
+                name: "mcp-client".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            accept_language: None,
+        };
+        let response = self.make_request("initialize", Some(serde_json::to_value(params)?))?;
+        let result: InitializeResult = match response.result {
+            Some(result) => serde_json::from_value(result)?,
+            None => return Err(McpError::protocol("No result in initialize response")),
+        };
 
-impl McpServer {
-    /// Create a new MCP server
-    pub fn new() -> Self {
-        Self {
-            state: McpServerState::new(),
-            port: 8080,
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&result.protocol_version.as_str()) {
+            return Err(McpError::protocol(format!(
+                "MCP server uses unsupported protocol version {} (supported: {})",
+                result.protocol_version,
+                SUPPORTED_PROTOCOL_VERSIONS.join(", ")
+            )));
+        }
+
+        self.send_notification("notifications/initialized", None)?;
+        let _ = self.inner.server.set(result.clone());
+        if !self.inner.requests.is_empty() {
+            self.open_notification_stream()?;
         }
+        Ok(result)
     }
 
-    /// Create a new MCP server with custom port
-    pub fn with_port(port: u16) -> Self {
-        Self {
-            state: McpServerState::new(),
-            port,
+    /// Answer the requests of `method` the server sends with `handler`
+    ///
+    /// Handlers run on a background thread, except over stdio and unix sockets, where
+    /// they run on the thread waiting for a response. Register them before
+    /// [`McpClient::initialize`], which announces the capabilities they provide.
+    pub fn on_request(
+        &self,
+        method: &str,
+        handler: impl Fn(Option<Value>) -> Result<Value, JsonRpcError> + Send + Sync + 'static,
+    ) -> Result<(), McpError> {
+        if self.inner.server.get().is_some() {
+            return Err(McpError::protocol(
+                "Register request handlers before initializing the client",
+            ));
         }
+        self.inner.requests.insert(method, handler);
+        Ok(())
     }
 
-    /// Add a tool to the server
-    pub async fn add_tool(&self, tool: McpTool) {
-        self.state.add_tool(tool).await;
+    /// Run the LLM completions the server asks for with `handler`, announcing the
+    /// sampling capability
+    ///
+    /// See [`crate::sampling::OllamaSampler`] for a handler using a local model.
+    pub fn on_sampling(
+        &self,
+        handler: impl Fn(
+            &crate::sampling::CreateMessageRequest,
+        ) -> Result<crate::sampling::CreateMessageResult, JsonRpcError>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Result<(), McpError> {
+        self.on_request(crate::sampling::METHOD, move |params| {
+            let request =
+                serde_json::from_value(params.unwrap_or_default()).map_err(|e| JsonRpcError {
+                    code: -32602,
+                    message: format!("Invalid params: {}", e),
+                    data: None,
+                })?;
+            let result = handler(&request)?;
+            serde_json::to_value(result).map_err(|e| JsonRpcError {
+                code: -32603,
+                message: e.to_string(),
+                data: None,
+            })
+        })
     }
 
-    /// Start the MCP server
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let app = Router::new()
-            .route("/mcp", post(handle_jsonrpc))
-            .layer(CorsLayer::permissive())
-            .with_state(self.state.clone());
+    /// Ask the user for the input the server's tools request with `handler`,
+    /// announcing the elicitation capability
+    pub fn on_elicitation(
+        &self,
+        handler: impl Fn(
+            &crate::elicitation::ElicitRequest,
+        ) -> Result<crate::elicitation::ElicitResult, JsonRpcError>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Result<(), McpError> {
+        self.on_request(crate::elicitation::METHOD, move |params| {
+            let request =
+                serde_json::from_value(params.unwrap_or_default()).map_err(|e| JsonRpcError {
+                    code: -32602,
+                    message: format!("Invalid params: {}", e),
+                    data: None,
+                })?;
+            let result = handler(&request)?;
+            serde_json::to_value(result).map_err(|e| JsonRpcError {
+                code: -32603,
+                message: e.to_string(),
+                data: None,
+            })
+        })
+    }
 
-        let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", self.port)).await?;
+    /// The server's initialize result, once the handshake has completed
+    pub fn server(&self) -> Option<&InitializeResult> {
+        self.inner.server.get()
+    }
 
-        println!("Starting MCP server on http://localhost:{}/mcp", self.port);
+    /// Send a JSON-RPC notification, which the server does not answer
+    pub fn send_notification(&self, method: &str, params: Option<Value>) -> Result<(), McpError> {
+        let notification = JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: method.to_string(),
+            params,
+        };
 
-        axum::serve(listener, app).await?;
+        match &self.inner.transport {
+            Transport::Http {
+                base_url,
+                client,
+                session_id,
+                ..
+            } => {
+                self.post(base_url, client, session_id, &notification)?;
+                Ok(())
+            }
+            Transport::Stdio(connection) => {
+                Self::lock_connection(connection)?.notify(&notification)
+            }
+            Transport::Sse(connection) => connection.notify(&notification),
+            #[cfg(unix)]
+            Transport::Unix(connection) => Self::lock_connection(connection)?.notify(&notification),
+        }
+    }
+
+    /// Take the notifications the server pushed since the last call
+    ///
+    /// Notifications are received by the SSE transport, and over Streamable HTTP once
+    /// [`McpClient::on_resource_updated`] opened the session's notification stream;
+    /// other transports return none.
+    pub fn take_notifications(&self) -> Vec<JsonRpcNotification> {
+        match &self.inner.transport {
+            Transport::Sse(connection) => {
+                std::iter::from_fn(|| connection.notifications().try_recv()).collect()
+            }
+            Transport::Http { .. } => match self.inner.notification_stream.lock() {
+                Ok(stream) => match stream.as_ref() {
+                    Some(stream) => {
+                        std::iter::from_fn(|| stream.notifications().try_recv()).collect()
+                    }
+                    None => Vec::new(),
+                },
+                Err(_) => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Call `callback` with the URI of each updated resource the client subscribed to
+    ///
+    /// Callbacks run on a background thread as notifications arrive. Over Streamable
+    /// HTTP this opens the session's notification stream, so the client must be
+    /// initialized; stdio and unix socket connections do not deliver notifications.
+    pub fn on_resource_updated(
+        &self,
+        callback: impl Fn(&str) + Send + 'static,
+    ) -> Result<(), McpError> {
+        self.listen(Box::new(move |notification| {
+            if notification.method == "notifications/resources/updated"
+                && let Some(uri) = notification
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.get("uri"))
+                    .and_then(Value::as_str)
+            {
+                callback(uri);
+            }
+        }))
+    }
+
+    /// Call `callback` with the new tool list whenever the server's tools change
+    ///
+    /// The server announces changes with `notifications/tools/list_changed`; the
+    /// client then fetches tools/list again on a background thread and passes the
+    /// result on. Transports are as for [`McpClient::on_resource_updated`].
+    pub fn on_tools_changed(
+        &self,
+        callback: impl Fn(Vec<McpTool>) + Send + Sync + 'static,
+    ) -> Result<(), McpError> {
+        // A weak reference, as the listeners belong to the client themselves
+        let inner = Arc::downgrade(&self.inner);
+        let callback = Arc::new(callback);
+        self.listen(Box::new(move |notification| {
+            if notification.method != "notifications/tools/list_changed" {
+                return;
+            }
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            let callback = Arc::clone(&callback);
+            // Not on this thread, which must keep reading to receive the response
+            std::thread::spawn(move || match (McpClient { inner }).fetch_tools() {
+                Ok(tools) => callback(tools),
+                Err(e) => eprintln!("Failed to refresh the tool list: {}", e),
+            });
+        }))
+    }
+
+    /// Call `callback` with each progress notification about the client's tool calls
+    ///
+    /// Once a callback is registered, tool calls send a progress token so that
+    /// long-running tools report how far they got. Transports are as for
+    /// [`McpClient::on_resource_updated`].
+    pub fn on_progress(
+        &self,
+        callback: impl Fn(&crate::progress::Progress) + Send + 'static,
+    ) -> Result<(), McpError> {
+        self.listen(Box::new(move |notification| {
+            if notification.method == crate::progress::PROGRESS_METHOD
+                && let Some(progress) = notification
+                    .params
+                    .clone()
+                    .and_then(|params| serde_json::from_value(params).ok())
+            {
+                callback(&progress);
+            }
+        }))?;
+        self.inner.wants_progress.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Add a notification listener, opening the notification stream if needed
+    fn listen(&self, listener: Box<dyn Fn(&JsonRpcNotification) + Send>) -> Result<(), McpError> {
+        if !matches!(
+            self.inner.transport,
+            Transport::Http { .. } | Transport::Sse(_)
+        ) {
+            return Err(McpError::protocol(
+                "Notifications need the Streamable HTTP or HTTP+SSE transport",
+            ));
+        }
+        self.inner
+            .listeners
+            .lock()
+            .map_err(|_| McpError::connection("Notification listeners lock poisoned"))?
+            .push(listener);
+        self.open_notification_stream()
+    }
+
+    /// Open the notification stream of a Streamable HTTP session, unless it is open
+    fn open_notification_stream(&self) -> Result<(), McpError> {
+        if let Transport::Http {
+            base_url,
+            session_id,
+            headers,
+            tls,
+            ..
+        } = &self.inner.transport
+        {
+            let mut stream = self
+                .inner
+                .notification_stream
+                .lock()
+                .map_err(|_| McpError::connection("Notification stream lock poisoned"))?;
+            if stream.is_none() {
+                let id = session_id
+                    .lock()
+                    .map_err(|_| McpError::connection("Session lock poisoned"))?
+                    .clone()
+                    .ok_or_else(|| {
+                        McpError::protocol(
+                            "Initialize the client before listening for notifications",
+                        )
+                    })?;
+                let headers = headers
+                    .lock()
+                    .map_err(|_| McpError::connection("Headers lock poisoned"))?
+                    .clone();
+                *stream = Some(crate::sse::NotificationStream::open(
+                    base_url,
+                    &id,
+                    headers,
+                    tls,
+                    self.inner
+                        .server
+                        .get()
+                        .map(|server| server.protocol_version.as_str()),
+                    Arc::clone(&self.inner.listeners),
+                    self.inner.requests.clone(),
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate a unique request ID
+    fn generate_id() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    /// Make a JSON-RPC request to the MCP server
+    pub fn make_request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<JsonRpcResponse, McpError> {
+        self.request_with_id(Self::generate_id().into(), method, params)
+    }
+
+    fn request_with_id(
+        &self,
+        id: RequestId,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<JsonRpcResponse, McpError> {
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id.clone()),
+            method: method.to_string(),
+            params,
+        };
+
+        let json_response = match &self.inner.transport {
+            Transport::Http {
+                base_url,
+                client,
+                session_id,
+                ..
+            } => {
+                let response = self.post(base_url, client, session_id, &request)?;
+                let is_event_stream = response
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+
+                if is_event_stream {
+                    response_from_event_stream(&response.text()?, &id)?
+                } else {
+                    response.json::<JsonRpcResponse>()?
+                }
+            }
+            Transport::Stdio(connection) => Self::lock_connection(connection)?.request(&request)?,
+            Transport::Sse(connection) => connection.request(&request)?,
+            #[cfg(unix)]
+            Transport::Unix(connection) => Self::lock_connection(connection)?.request(&request)?,
+        };
+
+        if let Some(error) = json_response.error {
+            return Err(error.into());
+        }
+
+        Ok(json_response)
+    }
+
+    /// Get the list of available tools from the MCP server
+    ///
+    /// Pages of a paginated list are requested until the last one, so the list is complete.
+    pub fn list_tools(&self) -> Result<Vec<McpTool>, McpError> {
+        println!(
+            "Requesting tool list from MCP server: {}",
+            self.inner.endpoint
+        );
+
+        let tools = self.fetch_tools()?;
+        println!("Retrieved {} tools from MCP server", tools.len());
+
+        for tool in &tools {
+            println!(
+                "  - Tool: {} - {}",
+                tool.name,
+                tool.description.as_deref().unwrap_or("No description")
+            );
+        }
+
+        Ok(tools)
+    }
+
+    /// Request the tool list without reporting it, following its pages
+    pub(crate) fn fetch_tools(&self) -> Result<Vec<McpTool>, McpError> {
+        let mut tools = Vec::new();
+        self.fetch_pages("tools/list", |result| {
+            let page: ToolsListResponse = serde_json::from_value(result)?;
+            tools.extend(page.tools);
+            Ok(page.next_cursor)
+        })?;
+        Ok(tools)
+    }
+
+    /// Request every page of a paginated list
+    ///
+    /// `add_page` takes the result of each page and returns its `nextCursor`.
+    fn fetch_pages(
+        &self,
+        method: &str,
+        mut add_page: impl FnMut(Value) -> Result<Option<String>, McpError>,
+    ) -> Result<(), McpError> {
+        let mut request = crate::pagination::PaginatedRequest::default();
+        loop {
+            let params = request
+                .cursor
+                .is_some()
+                .then(|| serde_json::to_value(&request))
+                .transpose()?;
+            let response = self.make_request(method, params)?;
+            let result = response
+                .result
+                .ok_or_else(|| McpError::protocol(format!("No result in {} response", method)))?;
+            match add_page(result)? {
+                Some(cursor) if request.cursor.as_ref() == Some(&cursor) => {
+                    return Err(McpError::protocol(format!(
+                        "{} returned the same cursor twice",
+                        method
+                    )));
+                }
+                Some(cursor) => request.cursor = Some(cursor),
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Get the directories the MCP server's file tools may access
+    pub fn list_roots(&self) -> Result<Vec<Root>, McpError> {
+        let response = self.make_request("fs/roots", None)?;
+
+        if let Some(result) = response.result {
+            let roots_response: RootsListResponse = serde_json::from_value(result)?;
+            Ok(roots_response.roots)
+        } else {
+            Err(McpError::protocol("No result in fs/roots response"))
+        }
+    }
+
+    /// Get the resources the MCP server exposes, following the pages of the list
+    pub fn list_resources(&self) -> Result<Vec<crate::resources::Resource>, McpError> {
+        let mut resources = Vec::new();
+        self.fetch_pages("resources/list", |result| {
+            let page: crate::resources::ResourcesListResponse = serde_json::from_value(result)?;
+            resources.extend(page.resources);
+            Ok(page.next_cursor)
+        })?;
+        Ok(resources)
+    }
+
+    /// Read a resource by URI
+    ///
+    /// A resource may have several parts, e.g. the files of a directory.
+    pub fn read_resource(
+        &self,
+        uri: &str,
+    ) -> Result<Vec<crate::resources::ResourceContents>, McpError> {
+        let params = serde_json::to_value(crate::resources::ResourcesReadRequest {
+            uri: uri.to_string(),
+        })?;
+        let response = self.make_request("resources/read", Some(params))?;
+
+        if let Some(result) = response.result {
+            let read_response: crate::resources::ResourcesReadResponse =
+                serde_json::from_value(result)?;
+            Ok(read_response.contents)
+        } else {
+            Err(McpError::protocol("No result in resources/read response"))
+        }
+    }
+
+    /// Subscribe to the updates of a resource
+    ///
+    /// The server then notifies the client when the resource changes, see
+    /// [`McpClient::on_resource_updated`].
+    pub fn subscribe_resource(&self, uri: &str) -> Result<(), McpError> {
+        let params = serde_json::to_value(crate::resources::ResourcesSubscribeRequest {
+            uri: uri.to_string(),
+        })?;
+        self.make_request("resources/subscribe", Some(params))?;
+        Ok(())
+    }
+
+    /// Stop receiving the updates of a resource
+    pub fn unsubscribe_resource(&self, uri: &str) -> Result<(), McpError> {
+        let params = serde_json::to_value(crate::resources::ResourcesSubscribeRequest {
+            uri: uri.to_string(),
+        })?;
+        self.make_request("resources/unsubscribe", Some(params))?;
+        Ok(())
+    }
+
+    /// Get the prompts the MCP server offers
+    pub fn list_prompts(&self) -> Result<Vec<crate::prompts::Prompt>, McpError> {
+        let response = self.make_request("prompts/list", None)?;
+
+        if let Some(result) = response.result {
+            let prompts_response: crate::prompts::PromptsListResponse =
+                serde_json::from_value(result)?;
+            Ok(prompts_response.prompts)
+        } else {
+            Err(McpError::protocol("No result in prompts/list response"))
+        }
+    }
+
+    /// Render a prompt with its arguments
+    pub fn get_prompt(
+        &self,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> Result<crate::prompts::PromptsGetResponse, McpError> {
+        let params = serde_json::to_value(crate::prompts::PromptsGetRequest {
+            name: name.to_string(),
+            arguments,
+        })?;
+        let response = self.make_request("prompts/get", Some(params))?;
+
+        if let Some(result) = response.result {
+            Ok(serde_json::from_value(result)?)
+        } else {
+            Err(McpError::protocol("No result in prompts/get response"))
+        }
+    }
+
+    /// Call a specific tool on the MCP server
+    ///
+    /// A tool failure is not an error: the result has `isError` set, and can be read as
+    /// a [`ToolsCallResponse`] to tell it apart.
+    pub fn call_tool(&self, name: &str, arguments: Option<Value>) -> Result<Value, McpError> {
+        self.send_tool_call(name, arguments, None)
+    }
+
+    /// Call a tool that must finish by `deadline`
+    ///
+    /// The time left is sent to the server as the call's `timeoutMs` hint, and the
+    /// server answers with a `TIMEOUT` tool error if the tool takes longer. Fails
+    /// without calling the tool once the deadline passed.
+    pub fn call_tool_with_deadline(
+        &self,
+        name: &str,
+        arguments: Option<Value>,
+        deadline: Deadline,
+    ) -> Result<Value, McpError> {
+        self.send_tool_call(name, arguments, Some(deadline))
+    }
+
+    fn send_tool_call(
+        &self,
+        name: &str,
+        arguments: Option<Value>,
+        deadline: Option<Deadline>,
+    ) -> Result<Value, McpError> {
+        let params = self.call_params(name, arguments, deadline)?;
+        let pending = self.track_call(name);
+        let response = self.request_with_id(pending.id.clone(), "tools/call", Some(params))?;
+
+        if let Some(result) = response.result {
+            Ok(result)
+        } else {
+            Err(McpError::protocol("No result in tools/call response"))
+        }
+    }
+
+    /// The tool calls waiting for their response, from this client and its clones
+    pub fn pending_tool_calls(&self) -> Vec<PendingToolCall> {
+        match self.inner.pending_calls.lock() {
+            Ok(calls) => calls
+                .iter()
+                .map(|(id, tool)| PendingToolCall {
+                    request_id: id.clone(),
+                    tool: tool.clone(),
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Ask the server to abort a pending request, see [`McpClient::pending_tool_calls`]
+    ///
+    /// The server stops the tool and answers the request with a "Request cancelled"
+    /// error. Over stdio and unix sockets the notification can only be sent once the
+    /// request completed, so cancelling has no effect there.
+    pub fn cancel(&self, request_id: &RequestId) -> Result<(), McpError> {
+        self.send_notification(
+            crate::cancellation::CANCELLED_METHOD,
+            Some(serde_json::json!({
+                "requestId": request_id,
+                "reason": "Cancelled by the client",
+            })),
+        )
+    }
+
+    /// Record a tool call as pending until the returned guard is dropped
+    fn track_call(&self, tool: &str) -> TrackedCall<'_> {
+        let id = RequestId::from(Self::generate_id());
+        if let Ok(mut calls) = self.inner.pending_calls.lock() {
+            calls.insert(id.clone(), tool.to_string());
+        }
+        TrackedCall {
+            calls: &self.inner.pending_calls,
+            id,
+        }
+    }
+
+    /// Parameters of a tools/call request
+    ///
+    /// Carries a fresh progress token if progress is wanted, and the time left before
+    /// the deadline.
+    fn call_params(
+        &self,
+        name: &str,
+        arguments: Option<Value>,
+        deadline: Option<Deadline>,
+    ) -> Result<Value, McpError> {
+        let meta = crate::progress::RequestMeta {
+            progress_token: self
+                .inner
+                .wants_progress
+                .load(Ordering::Relaxed)
+                .then(|| Value::String(Self::generate_id())),
+            timeout_ms: match deadline {
+                Some(deadline) => Some(deadline.check()?.as_millis() as u64),
+                None => None,
+            },
+        };
+        let mut params = serde_json::json!({
+            "name": name,
+            "arguments": arguments
+        });
+        if meta.progress_token.is_some() || meta.timeout_ms.is_some() {
+            params["_meta"] = serde_json::to_value(meta)?;
+        }
+        Ok(params)
+    }
+
+    /// Call a tool and write its output to `writer` instead of returning it
+    ///
+    /// Text content is written as is and image or audio content decoded. Over
+    /// Streamable HTTP the output is written while the response arrives, so results
+    /// larger than memory can be saved to a file; other transports receive the whole
+    /// response first. A tool failure is not an error: its message is written and
+    /// reported with `is_error`.
+    pub fn call_tool_to_writer(
+        &self,
+        name: &str,
+        arguments: Option<Value>,
+        mut writer: impl std::io::Write,
+    ) -> Result<crate::response_stream::StreamedToolResult, McpError> {
+        let Transport::Http {
+            base_url,
+            client,
+            session_id,
+            ..
+        } = &self.inner.transport
+        else {
+            let result = self.call_tool(name, arguments)?;
+            return crate::response_stream::write_result_content(&result, &mut writer);
+        };
+
+        let pending = self.track_call(name);
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(pending.id.clone()),
+            method: "tools/call".to_string(),
+            params: Some(self.call_params(name, arguments, None)?),
+        };
+        let response = self.post(base_url, client, session_id, &request)?;
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+
+        if is_event_stream {
+            // Event streams may interleave notifications, so the response is found first
+            let response = response_from_event_stream(&response.text()?, &pending.id)?;
+            if let Some(error) = response.error {
+                return Err(error.into());
+            }
+            let result = response
+                .result
+                .ok_or_else(|| McpError::protocol("No result in tools/call response"))?;
+            crate::response_stream::write_result_content(&result, &mut writer)
+        } else {
+            crate::response_stream::write_response_content(
+                std::io::BufReader::new(response),
+                &mut writer,
+            )
+        }
+    }
+}
+
+/// A pending tool call, forgotten when dropped
+struct TrackedCall<'a> {
+    calls: &'a Mutex<HashMap<RequestId, String>>,
+    id: RequestId,
+}
+
+impl Drop for TrackedCall<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.remove(&self.id);
+        }
+    }
+}
+
+impl Drop for ClientInner {
+    /// End the HTTP session so the server can release it
+    fn drop(&mut self) {
+        if let Transport::Http {
+            base_url,
+            client,
+            session_id,
+            headers,
+            ..
+        } = &self.transport
+            && let Ok(Some(id)) = session_id.lock().map(|id| id.clone())
+        {
+            let headers = headers.lock().map(|h| h.clone()).unwrap_or_default();
+            let _ = client
+                .delete(base_url)
+                .headers(headers)
+                .header(crate::session::SESSION_HEADER, id)
+                .send();
+        }
+    }
+}
+
+/// Find the response to a request in an SSE response body
+///
+/// Servers may answer a POST with an event stream carrying notifications before the
+/// response; everything but the response is skipped.
+fn response_from_event_stream(body: &str, id: &RequestId) -> Result<JsonRpcResponse, McpError> {
+    for event in body.replace("\r\n", "\n").split("\n\n") {
+        let data: Vec<&str> = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.strip_prefix(' ').unwrap_or(data))
+            .collect();
+        if data.is_empty() {
+            continue;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(&data.join("\n")) else {
+            continue;
+        };
+        if message.get("method").is_some() {
+            continue;
+        }
+        if let Ok(response) = serde_json::from_value::<JsonRpcResponse>(message)
+            && response.id.as_ref() == Some(id)
+        {
+            return Ok(response);
+        }
+    }
+    Err(McpError::protocol(
+        "No response in the MCP server's event stream",
+    ))
+}
+
+/// Future returned by a custom JSON-RPC method handler
+pub type MethodFuture = Pin<Box<dyn Future<Output = Result<Value, JsonRpcError>> + Send>>;
+
+/// Handler of a custom JSON-RPC method, called with the server state and the request params
+pub type MethodHandler = Arc<dyn Fn(McpServerState, Option<Value>) -> MethodFuture + Send + Sync>;
+
+/// Custom JSON-RPC methods registered by applications embedding the server
+#[derive(Clone, Default)]
+pub struct MethodRouter {
+    handlers: HashMap<String, MethodHandler>,
+}
+
+impl MethodRouter {
+    /// Add or replace the handler of a method
+    pub fn register<F, Fut>(&mut self, method: impl Into<String>, handler: F)
+    where
+        F: Fn(McpServerState, Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, JsonRpcError>> + Send + 'static,
+    {
+        let handler: MethodHandler =
+            Arc::new(move |state, params| Box::pin(handler(state, params)));
+        self.handlers.insert(method.into(), handler);
+    }
+
+    /// Get the handler of a method
+    pub fn get(&self, method: &str) -> Option<&MethodHandler> {
+        self.handlers.get(method)
+    }
+}
+
+impl fmt::Debug for MethodRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
+
+/// Outcome of a tool call
+pub type ToolResult = Result<ToolsCallResponse, JsonRpcError>;
+
+/// Future returned by [`ToolHandler::call`]
+pub type ToolFuture<'a> = Pin<Box<dyn Future<Output = ToolResult> + Send + 'a>>;
+
+/// Future returned by [`ToolHandler::check`], failing with a description of the problem
+pub type CheckFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+/// A tool served by the server
+///
+/// Built-in tools and tools registered with [`McpServer::register_tool`] are looked up
+/// by name in the same registry, so an application tool is listed and called exactly
+/// like a built-in one.
+pub trait ToolHandler: Send + Sync {
+    /// Name the tool is listed and called by
+    fn name(&self) -> &str;
+
+    /// Description shown to the model
+    fn description(&self) -> &str;
+
+    /// JSON Schema of the tool arguments
+    fn input_schema(&self) -> Value;
+
+    /// JSON Schema of the structured content of the tool's results, if it returns any
+    ///
+    /// The dispatcher rejects results whose structured content does not match it.
+    fn output_schema(&self) -> Option<Value> {
+        None
+    }
+
+    /// Version of the tool's interface, listed with its definition
+    fn version(&self) -> Option<String> {
+        None
+    }
+
+    /// Hints about the tool's behavior, such as whether calls need approval or the
+    /// tool is deprecated
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        None
+    }
+
+    /// Run the tool with the call arguments
+    ///
+    /// Long-running tools report how far they got through `progress`. `cancel` is
+    /// cancelled when the client cancels the call or its deadline passes; the future
+    /// is then dropped, and blocking work should stop when it sees the token cancelled.
+    /// Start blocking work with [`crate::execution::spawn_blocking`], so that it keeps
+    /// the call's execution slot until it finishes.
+    fn call<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        arguments: Option<Value>,
+        progress: ProgressReporter,
+        cancel: CancellationToken,
+    ) -> ToolFuture<'a>;
+
+    /// Verify that the tool can run, without side effects, for `mcp-server check`
+    ///
+    /// Tools depending on external services should check they are reachable here.
+    fn check<'a>(&'a self, _state: &'a McpServerState) -> CheckFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Definition returned by tools/list
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: self.name().to_string(),
+            version: self.version(),
+            description: Some(self.description().to_string()),
+            input_schema: Some(self.input_schema()),
+            output_schema: self.output_schema(),
+            annotations: self.annotations(),
+        }
+    }
+}
+
+impl fmt::Debug for dyn ToolHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ToolHandler").field(&self.name()).finish()
+    }
+}
+
+/// A tool in the server's registry
+///
+/// The definition is built once at registration and shared, so listing tools does not
+/// copy their schemas.
+#[derive(Debug, Clone)]
+pub struct RegisteredTool {
+    pub definition: Arc<McpTool>,
+    pub handler: Arc<dyn ToolHandler>,
+    /// Compiled input schema; `None` when the tool has none or it does not compile
+    validator: Option<Arc<jsonschema::Validator>>,
+    /// Compiled output schema; `None` when the tool has none or it does not compile
+    output_validator: Option<Arc<jsonschema::Validator>>,
+}
+
+impl RegisteredTool {
+    /// Register a tool, with the errors compiling its schemas, named "input schema" or
+    /// "output schema", for those that are invalid
+    pub(crate) fn compile(tool: impl ToolHandler + 'static) -> (Self, Vec<(&'static str, String)>) {
+        let definition = tool.definition();
+        let mut errors = Vec::new();
+        let mut compile = |kind, schema: Option<&Value>| match schema.map(jsonschema::validator_for)
+        {
+            Some(Ok(validator)) => Some(Arc::new(validator)),
+            Some(Err(e)) => {
+                errors.push((kind, e.to_string()));
+                None
+            }
+            None => None,
+        };
+        let validator = compile("input schema", definition.input_schema.as_ref());
+        let output_validator = compile("output schema", definition.output_schema.as_ref());
+        let registered = Self {
+            definition: Arc::new(definition),
+            handler: Arc::new(tool),
+            validator,
+            output_validator,
+        };
+        (registered, errors)
+    }
+}
+
+/// A tool implemented by a closure, registered with [`McpServer::register_fn`]
+struct FnTool {
+    name: String,
+    description: String,
+    input_schema: Value,
+    handler: Box<dyn Fn(Option<Value>) -> ToolFuture<'static> + Send + Sync>,
+}
+
+impl ToolHandler for FnTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> Value {
+        self.input_schema.clone()
+    }
+
+    fn call<'a>(
+        &'a self,
+        _state: &'a McpServerState,
+        arguments: Option<Value>,
+        _progress: ProgressReporter,
+        _cancel: CancellationToken,
+    ) -> ToolFuture<'a> {
+        (self.handler)(arguments)
+    }
+}
+
+/// A built-in tool, run by the server's own dispatch
+pub(crate) struct BuiltinTool(pub(crate) McpTool);
+
+impl ToolHandler for BuiltinTool {
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    fn description(&self) -> &str {
+        self.0.description.as_deref().unwrap_or_default()
+    }
+
+    fn input_schema(&self) -> Value {
+        self.0.input_schema.clone().unwrap_or_default()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.0.output_schema.clone()
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        self.0.annotations.clone()
+    }
+
+    fn call<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        arguments: Option<Value>,
+        progress: ProgressReporter,
+        cancel: CancellationToken,
+    ) -> ToolFuture<'a> {
+        Box::pin(execute_builtin(
+            state,
+            &self.0.name,
+            arguments,
+            progress,
+            cancel,
+        ))
+    }
+
+    fn check<'a>(&'a self, state: &'a McpServerState) -> CheckFuture<'a> {
+        let config = Arc::clone(&state.config);
+        let name = self.0.name.clone();
+        Box::pin(async move {
+            #[cfg(feature = "ssh")]
+            if name == "remote_file_read" || name == "remote_exec" {
+                return crate::ssh::check(&config.ssh_host)
+                    .await
+                    .map_err(|e| e.to_string());
+            }
+            tokio::task::spawn_blocking(move || {
+                check_builtin(&config, &name).map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| format!("Check failed: {}", e))?
+        })
+    }
+
+    fn definition(&self) -> McpTool {
+        self.0.clone()
+    }
+}
+
+/// Capacity of the server notification channel
+const NOTIFICATION_CAPACITY: usize = 256;
+
+/// MCP Server state containing registered tools
+#[derive(Debug, Clone)]
+pub struct McpServerState {
+    /// Tools by name, built-in and registered by the application
+    pub tools: Arc<RwLock<HashMap<String, RegisteredTool>>>,
+    /// Serialized tools/list pages of the registered tools by offset, cleared when they change
+    tools_list: Arc<Mutex<HashMap<usize, Arc<RawValue>>>>,
+    pub config: Arc<crate::config::ServerConfig>,
+    /// Filesystem the file tools and `file://` resources go through
+    pub fs: Arc<dyn crate::vfs::VirtualFs>,
+    /// Latest results of scheduled jobs, keyed by their `schedule://` URI
+    pub scheduled_results: Arc<RwLock<HashMap<String, crate::scheduler::ScheduledResult>>>,
+    /// Server-to-client notifications, fanned out to every subscriber
+    pub notifications: broadcast::Sender<JsonRpcNotification>,
+    /// Server activity events delivered to the configured event sinks
+    pub events: broadcast::Sender<crate::events::ServerEvent>,
+    #[cfg(feature = "speech")]
+    pub speech: crate::speech::SpeechEngines,
+    pub translator: Option<Arc<dyn crate::translate::Translator>>,
+    /// Streamable HTTP sessions by their `Mcp-Session-Id`
+    pub sessions: Arc<RwLock<HashMap<String, crate::session::Session>>>,
+    /// HTTP+SSE streams by the `sessionId` their messages are posted with
+    pub sse_streams: Arc<RwLock<HashMap<String, crate::sse::SseStream>>>,
+    /// Instructions returned to clients during initialization
+    pub instructions: Option<String>,
+    /// Upstream MCP servers whose tools are served next to the local ones
+    pub gateway: Option<Arc<crate::gateway::Gateway>>,
+    /// Custom JSON-RPC methods, consulted after the built-in ones
+    pub methods: Arc<MethodRouter>,
+    /// Tool calls in flight, for load shedding
+    pub admission: Arc<crate::admission::Admission>,
+    /// Slots for the tool calls executing at once
+    pub executions: Arc<crate::execution::ExecutionSlots>,
+    /// Implementations receiving a copy of the calls of tools, for validation
+    pub shadows: Arc<crate::shadow::Shadows>,
+    /// API keys required of HTTP clients; none means requests are not authenticated
+    pub api_keys: Arc<crate::auth::ApiKeys>,
+    /// Resource usage of tool calls per tool, reported by server/stats
+    pub stats: Arc<crate::accounting::Stats>,
+    /// Resource providers by the URI scheme they serve
+    pub resources: Arc<RwLock<HashMap<String, Arc<dyn crate::resources::ResourceProvider>>>>,
+    /// Translations of tool descriptions and errors, by language
+    pub catalogs: Arc<crate::i18n::Catalogs>,
+    /// Prompts by name
+    pub prompts: Arc<RwLock<HashMap<String, Arc<dyn crate::prompts::PromptProvider>>>>,
+    /// Resumable transfers, keyed by their `transfer://` URI
+    pub transfers: Arc<RwLock<HashMap<String, crate::transfer::Transfer>>>,
+    /// Journal of transfer and scheduled run state changes, kept across restarts
+    pub job_journal: Option<Arc<crate::job_journal::JobJournal>>,
+    /// Progress tokens of the tool calls running
+    pub progress_tokens: crate::progress::ProgressTokens,
+    /// Tool calls running, which the client can cancel
+    pub in_flight: crate::cancellation::InFlightCalls,
+    /// Requests sent to clients by running tools, waiting for their response
+    pub server_requests: crate::server_request::PendingRequests,
+}
+
+impl Default for McpServerState {
+    fn default() -> Self {
+        Self {
+            tools: Arc::default(),
+            tools_list: Arc::default(),
+            config: Arc::default(),
+            fs: Arc::new(crate::vfs::RootedFs::allowed()),
+            scheduled_results: Arc::default(),
+            notifications: broadcast::channel(NOTIFICATION_CAPACITY).0,
+            events: broadcast::channel(NOTIFICATION_CAPACITY).0,
+            #[cfg(feature = "speech")]
+            speech: Default::default(),
+            translator: None,
+            sessions: Arc::default(),
+            sse_streams: Arc::default(),
+            instructions: None,
+            gateway: None,
+            methods: Arc::default(),
+            admission: Arc::default(),
+            executions: Arc::default(),
+            shadows: Arc::default(),
+            api_keys: Arc::default(),
+            stats: Arc::default(),
+            resources: Arc::default(),
+            catalogs: Arc::default(),
+            prompts: Arc::default(),
+            transfers: Arc::default(),
+            job_journal: None,
+            progress_tokens: Arc::default(),
+            in_flight: Arc::default(),
+            server_requests: Arc::default(),
+        }
+    }
+}
+
+impl McpServerState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish a notification to every subscriber
+    ///
+    /// Notifications are dropped when nobody is subscribed.
+    pub fn notify(&self, method: &str, params: Option<Value>) {
+        let _ = self.notifications.send(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: method.to_string(),
+            params,
+        });
+    }
+
+    /// Subscribe to server notifications
+    pub fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.notifications.subscribe()
+    }
+
+    /// Emit a server event to the configured sinks
+    ///
+    /// Events are dropped when no sink is listening.
+    pub fn emit_event(&self, event: crate::events::ServerEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Get the latest result of a scheduled job by its URI
+    pub async fn get_scheduled_result(
+        &self,
+        uri: &str,
+    ) -> Option<crate::scheduler::ScheduledResult> {
+        self.scheduled_results.read().await.get(uri).cloned()
+    }
+
+    /// Add a built-in tool to the server
+    pub async fn add_tool(&self, tool: McpTool) {
+        self.register_tool(BuiltinTool(tool)).await;
+    }
+
+    /// Add or replace a tool implemented by the application
+    pub async fn register_tool(&self, tool: impl ToolHandler + 'static) {
+        let (registered, errors) = RegisteredTool::compile(tool);
+        for (schema, e) in errors {
+            crate::server_log::warn(format!(
+                "Warning: the {} of tool '{}' is invalid and is not enforced: {}",
+                schema, registered.definition.name, e
+            ));
+        }
+        let mut tools = self.tools.write().await;
+        tools.insert(registered.definition.name.clone(), registered);
+        self.lock_tools_list().clear();
+        self.notify("notifications/tools/list_changed", None);
+    }
+
+    /// Remove a tool, returning whether it was registered
+    pub async fn unregister_tool(&self, name: &str) -> bool {
+        let removed = self.tools.write().await.remove(name).is_some();
+        if removed {
+            self.lock_tools_list().clear();
+            self.notify("notifications/tools/list_changed", None);
+        }
+        removed
+    }
+
+    /// Add or replace the provider of a URI scheme's resources
+    pub async fn register_resources(
+        &self,
+        provider: impl crate::resources::ResourceProvider + 'static,
+    ) {
+        let mut resources = self.resources.write().await;
+        resources.insert(provider.scheme().to_string(), Arc::new(provider));
+    }
+
+    /// Add or replace a prompt
+    pub async fn register_prompt(&self, provider: impl crate::prompts::PromptProvider + 'static) {
+        let mut prompts = self.prompts.write().await;
+        prompts.insert(provider.prompt().name, Arc::new(provider));
+    }
+
+    /// Get all tools
+    pub async fn get_tools(&self) -> Vec<Arc<McpTool>> {
+        let tools = self.tools.read().await;
+        tools
+            .values()
+            .map(|tool| Arc::clone(&tool.definition))
+            .collect()
+    }
+
+    /// Get a specific tool by name
+    pub async fn get_tool(&self, name: &str) -> Option<Arc<McpTool>> {
+        let tools = self.tools.read().await;
+        tools.get(name).map(|tool| Arc::clone(&tool.definition))
+    }
+
+    /// Get the handler of a tool by name
+    pub async fn get_tool_handler(&self, name: &str) -> Option<Arc<dyn ToolHandler>> {
+        let tools = self.tools.read().await;
+        tools.get(name).map(|tool| Arc::clone(&tool.handler))
+    }
+
+    /// A page of the tools/list result of the registered tools, serialized once per change
+    async fn tools_list(&self, offset: usize) -> Result<Arc<RawValue>, serde_json::Error> {
+        if let Some(cached) = self.lock_tools_list().get(&offset) {
+            return Ok(Arc::clone(cached));
+        }
+        // Holding the read lock keeps a concurrent registration from being overwritten
+        let tools = self.tools.read().await;
+        let mut definitions: Vec<&McpTool> = tools.values().map(|tool| &*tool.definition).collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        let (tools, next_cursor) =
+            crate::pagination::page(definitions, offset, crate::pagination::page_size(self));
+        let serialized: Arc<RawValue> =
+            serde_json::value::to_raw_value(&ToolsList { tools, next_cursor })?.into();
+        self.lock_tools_list()
+            .insert(offset, Arc::clone(&serialized));
+        Ok(serialized)
+    }
+
+    fn lock_tools_list(&self) -> MutexGuard<'_, HashMap<usize, Arc<RawValue>>> {
+        // A panic while holding the lock cannot leave the cache inconsistent
+        self.tools_list.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Deserialize tool arguments and run a tool, mapping failures the same way for every tool
+///
+/// Missing or malformed arguments are reported as JSON-RPC `-32602` errors, while
+/// errors raised by the tool itself are returned as `isError` results so the model can
+/// see them, with a [`ToolErrorPayload`] classifying the failure.
+fn run_tool<R, T, F>(
+    name: &str,
+    arguments: Option<Value>,
+    error_prefix: &str,
+    execute: F,
+) -> Result<ToolsCallResponse, JsonRpcError>
+where
+    R: serde::de::DeserializeOwned,
+    T: Into<ToolsCallResponse>,
+    F: FnOnce(R) -> Result<T, Box<dyn std::error::Error>>,
+{
+    let tool_request = tool_request(name, arguments)?;
+    Ok(tool_response(execute(tool_request), error_prefix))
+}
+
+/// Deserialize the arguments of a tool call into its request type
+fn tool_request<R: serde::de::DeserializeOwned>(
+    name: &str,
+    arguments: Option<Value>,
+) -> Result<R, JsonRpcError> {
+    let Some(arguments) = arguments else {
+        return Err(JsonRpcError {
+            code: -32602,
+            message: format!("{} tool requires arguments", name),
+            data: None,
+        });
+    };
+
+    serde_json::from_value::<R>(arguments).map_err(|e| JsonRpcError {
+        code: -32602,
+        message: format!("Invalid {} arguments: {}", name, e),
+        data: None,
+    })
+}
+
+/// Turn the outcome of a tool into its response, classifying errors
+fn tool_response<T: Into<ToolsCallResponse>>(
+    result: Result<T, Box<dyn std::error::Error>>,
+    error_prefix: &str,
+) -> ToolsCallResponse {
+    match result {
+        Ok(result) => result.into(),
+        Err(e) => {
+            let payload = ToolErrorPayload::from_error(e);
+            let text = format!("{}: {}", error_prefix, payload);
+            ToolsCallResponse::error_with_payload(text, &payload)
+        }
+    }
+}
+
+/// Run a tool whose IO is async, like [`run_tool`] but on the calling task
+///
+/// For tools built on async clients: they wait on the network without holding a
+/// thread, and are dropped with the call when it is cancelled. Only the SSH tools are
+/// async so far, so it is compiled with them.
+#[cfg(feature = "ssh")]
+async fn run_async_tool<R, T, F, Fut>(
+    name: &str,
+    arguments: Option<Value>,
+    error_prefix: &str,
+    execute: F,
+) -> Result<ToolsCallResponse, JsonRpcError>
+where
+    R: serde::de::DeserializeOwned,
+    T: Into<ToolsCallResponse>,
+    F: FnOnce(R) -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn std::error::Error + Send + Sync>>>,
+{
+    let tool_request = tool_request(name, arguments)?;
+    let result = execute(tool_request).await;
+    Ok(tool_response(
+        result.map_err(|e| e as Box<dyn std::error::Error>),
+        error_prefix,
+    ))
+}
+
+/// Run a tool on the blocking thread pool
+///
+/// Every built-in tool but the SSH tools runs this way, as their filesystem, network
+/// and subprocess IO and their parsing block the thread: on the async runtime, a slow
+/// call would hold up the other requests served by the same worker thread.
+async fn run_blocking_tool<R, T, F>(
+    name: &str,
+    arguments: Option<Value>,
+    error_prefix: &'static str,
+    execute: F,
+) -> Result<ToolsCallResponse, JsonRpcError>
+where
+    R: serde::de::DeserializeOwned,
+    T: Into<ToolsCallResponse>,
+    F: FnOnce(R) -> Result<T, Box<dyn std::error::Error>> + Send + 'static,
+{
+    let name = name.to_string();
+    let meter = crate::accounting::current().unwrap_or_default();
+    crate::execution::spawn_blocking(move || {
+        meter.run_blocking(|| run_tool(&name, arguments, error_prefix, execute))
+    })
+    .await
+    .unwrap_or_else(|e| {
+        Err(JsonRpcError {
+            code: -32603,
+            message: format!("Tool execution failed: {}", e),
+            data: None,
+        })
+    })
+}
+
+/// Execute a registered tool by name, emitting a server event with the outcome
+pub(crate) async fn dispatch_tool(
+    state: &McpServerState,
+    call_request: ToolsCallRequest,
+    progress: ProgressReporter,
+    cancel: CancellationToken,
+) -> Result<ToolsCallResponse, JsonRpcError> {
+    use crate::events::{EventKind, ServerEvent};
+
+    let name = call_request.name;
+    let Some(tool) = state.tools.read().await.get(&name).cloned() else {
+        return Err(JsonRpcError {
+            code: -32601,
+            message: format!("Tool '{}' not found", name),
+            data: None,
+        });
+    };
+    let started = std::time::Instant::now();
+    let meter = crate::accounting::Meter::new();
+    let validated = match &tool.validator {
+        Some(validator) => validate_arguments(validator, &name, call_request.arguments.as_ref()),
+        None => Ok(()),
+    };
+    // The caller's deadline, after which the tool is cancelled
+    let timeout = call_request
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.timeout_ms)
+        .map(std::time::Duration::from_millis);
+    let mut shadow_arguments = None;
+    // The server's limit for the tool, after which it is cancelled as well
+    let limit = crate::execution::time_limit(&state.config.execution, &name);
+    let result = match validated {
+        Ok(()) => {
+            let (tool, meter, cancel, name) = (&tool, &meter, &cancel, &name);
+            let arguments = call_request.arguments;
+            // A copy for the tool's shadow, mirrored once the tool answered
+            shadow_arguments = state.shadows.contains(name).then(|| arguments.clone());
+            let call = async move {
+                // Waiting for a slot counts against the caller's deadline, not the limit
+                let slot = state.executions.acquire(&state.config.execution).await;
+                let call = crate::execution::hold(
+                    slot,
+                    meter.scope(
+                        tool.handler
+                            .call(state, arguments, progress, cancel.clone()),
+                    ),
+                );
+                match limit {
+                    Some(limit) => tokio::time::timeout(limit, call).await.unwrap_or_else(|_| {
+                        cancel.cancel();
+                        Ok(crate::execution::limit_exceeded(name, limit))
+                    }),
+                    None => call.await,
+                }
+            };
+            let expired = async move {
+                match timeout {
+                    Some(timeout) => {
+                        tokio::time::sleep(timeout).await;
+                        timeout
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                result = call => result,
+                () = cancel.cancelled() => Err(crate::cancellation::cancelled_error()),
+                timeout = expired => {
+                    // Tell blocking work to stop, as nobody waits for it any more
+                    cancel.cancel();
+                    Ok(timed_out(name, timeout))
+                }
+            }
+        }
+        Err(e) => Err(e),
+    };
+    let result = match (result, &tool.output_validator) {
+        (Ok(response), Some(validator)) => check_structured_content(validator, &name, response),
+        (result, _) => result,
+    };
+    let elapsed = started.elapsed();
+    if let (Some(arguments), Ok(response)) = (shadow_arguments, &result) {
+        crate::shadow::mirror(state, &name, arguments, response);
+    }
+    let usage = meter.usage();
+    state.stats.record(
+        &name,
+        !matches!(&result, Ok(response) if !response.is_error),
+        elapsed,
+        &usage,
+    );
+
+    let event = match &result {
+        Ok(response) if response.is_error => {
+            ServerEvent::new(EventKind::ToolCallFailed).message(response.text())
+        }
+        Ok(_) => ServerEvent::new(EventKind::ToolCallCompleted),
+        Err(e) => ServerEvent::new(EventKind::ToolCallFailed).message(e.message.clone()),
+    };
+    if event.kind == EventKind::ToolCallFailed {
+        crate::server_log::warn(format!(
+            "Tool call '{}' failed after {} ms: {}",
+            name,
+            elapsed.as_millis(),
+            event.message.as_deref().unwrap_or_default()
+        ));
+    }
+    state.emit_event(
+        event
+            .tool(name)
+            .duration_ms(elapsed.as_millis() as u64)
+            .usage(usage),
+    );
+
+    result
+}
+
+/// Result of a call that outlived the caller's deadline
+fn timed_out(name: &str, timeout: std::time::Duration) -> ToolsCallResponse {
+    let payload = ToolErrorPayload::timeout(
+        format!(
+            "Tool '{}' did not finish within the caller's deadline of {} ms",
+            name,
+            timeout.as_millis()
+        ),
+        timeout,
+    );
+    ToolsCallResponse::error_with_payload(payload.to_string(), &payload)
+}
+
+/// Check the arguments of a tool call against the tool's input schema
+fn validate_arguments(
+    validator: &jsonschema::Validator,
+    tool: &str,
+    arguments: Option<&Value>,
+) -> Result<(), JsonRpcError> {
+    let empty = Value::Object(Default::default());
+    match schema_errors(validator, arguments.unwrap_or(&empty)) {
+        None => Ok(()),
+        Some((summary, data)) => Err(JsonRpcError {
+            code: -32602,
+            message: format!("Invalid arguments for tool '{}': {}", tool, summary),
+            data: Some(data),
+        }),
+    }
+}
+
+/// Check the structured content of a successful result against the tool's output
+/// schema
+///
+/// A tool declaring an output schema must return conforming structured content, so a
+/// result without it or not matching it is an internal error. Results without content
+/// get the structured content as JSON text, for clients that only read the content.
+fn check_structured_content(
+    validator: &jsonschema::Validator,
+    tool: &str,
+    mut response: ToolsCallResponse,
+) -> Result<ToolsCallResponse, JsonRpcError> {
+    if response.is_error {
+        return Ok(response);
+    }
+    let Some(structured) = &response.structured_content else {
+        return Err(JsonRpcError {
+            code: -32603,
+            message: format!(
+                "Tool '{}' declares an output schema but returned no structured content",
+                tool
+            ),
+            data: None,
+        });
+    };
+    if let Some((summary, data)) = schema_errors(validator, structured) {
+        return Err(JsonRpcError {
+            code: -32603,
+            message: format!(
+                "Tool '{}' returned structured content not matching its output schema: {}",
+                tool, summary
+            ),
+            data: Some(data),
+        });
+    }
+    if response.content.is_empty() {
+        let text = serde_json::to_string_pretty(structured).unwrap_or_default();
+        response.content.push(ToolContent::text(text));
+    }
+    Ok(response)
+}
+
+/// The failures of a value against a schema, if any, as a summary and as error data
+///
+/// Every failure is listed in the data with the JSON pointer of the offending value.
+/// Values are masked in the messages so large values are not echoed back.
+pub(crate) fn schema_errors(
+    validator: &jsonschema::Validator,
+    instance: &Value,
+) -> Option<(String, Value)> {
+    let errors: Vec<(String, String)> = validator
+        .iter_errors(instance)
+        .map(|error| {
+            let path = error.instance_path().as_str().to_string();
+            (path, error.masked().to_string())
+        })
+        .collect();
+    if errors.is_empty() {
+        return None;
+    }
+
+    let summary: Vec<String> = errors
+        .iter()
+        .map(|(path, message)| match path.as_str() {
+            "" => message.clone(),
+            path => format!("{}: {}", path, message),
+        })
+        .collect();
+    let data: Vec<Value> = errors
+        .into_iter()
+        .map(|(path, message)| serde_json::json!({ "path": path, "message": message }))
+        .collect();
+    Some((summary.join("; "), serde_json::json!({ "errors": data })))
+}
+
+/// Check the external dependencies of a built-in tool
+fn check_builtin(
+    config: &crate::config::ServerConfig,
+    name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match name {
+        "summarize" => crate::summarize::check(&config.summarize),
+        "memory_ingest" | "memory_search" => crate::memory::check(&config.memory),
+        "repo_list" | "repo_pr_diff" | "repo_file_read" => {
+            crate::repository::check(&config.repository)
+        }
+        #[cfg(feature = "postgres")]
+        "pg_query" => crate::pg_query::check(&config.postgres),
+        #[cfg(feature = "email")]
+        "email_send" => crate::email::check(&config.email),
+        _ => Ok(()),
+    }
+}
+
+/// Execute a built-in tool by name
+async fn execute_builtin(
+    state: &McpServerState,
+    name: &str,
+    arguments: Option<Value>,
+    progress: ProgressReporter,
+    cancel: CancellationToken,
+) -> ToolResult {
+    match name {
+        "file_read" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error reading file",
+                move |request: crate::file_read::FileReadRequest| {
+                    let file_response = crate::file_read::execute_file_read(
+                        request,
+                        fs.as_ref(),
+                        &progress,
+                        &cancel,
+                    )?;
+                    Ok(format!(
+                        "File: {}\nSize: {} bytes\nMIME Type: {}\nEncoding: {}\n\nContent:\n{}",
+                        file_response.path,
+                        file_response.size,
+                        file_response.mime_type.as_deref().unwrap_or("unknown"),
+                        file_response.encoding,
+                        file_response.content
+                    ))
+                },
+            )
+            .await
+        }
+        "file_list" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error listing directory",
+                move |request: crate::file_list::FileListRequest| {
+                    Ok(crate::file_list::execute_file_list(request, fs.as_ref())?.to_text())
+                },
+            )
+            .await
+        }
+        "file_search" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error searching files",
+                move |request: crate::file_search::FileSearchRequest| {
+                    Ok(crate::file_search::execute_file_search(request, fs.as_ref())?.to_text())
+                },
+            )
+            .await
+        }
+        "json_query" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error querying document",
+                move |request: crate::json_query::JsonQueryRequest| {
+                    let query_response =
+                        crate::json_query::execute_json_query(request, fs.as_ref())?;
+                    Ok(serde_json::to_string_pretty(&query_response)?)
+                },
+            )
+            .await
+        }
+        "log_tail" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error reading log",
+                move |request: crate::log_tail::LogTailRequest| {
+                    Ok(crate::log_tail::execute_log_tail(request, fs.as_ref())?.to_text())
+                },
+            )
+            .await
+        }
+        "diff" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                Some(arguments.unwrap_or_else(|| serde_json::json!({}))),
+                "Error computing diff",
+                move |request: crate::diff::DiffRequest| {
+                    Ok(crate::diff::execute_diff(request, fs.as_ref())?.to_text())
+                },
+            )
+            .await
+        }
+        "render_template" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                Some(arguments.unwrap_or_else(|| serde_json::json!({}))),
+                "Error rendering template",
+                move |request: crate::render_template::RenderTemplateRequest| {
+                    let rendered =
+                        crate::render_template::execute_render_template(request, fs.as_ref())?;
+                    Ok(match rendered.content {
+                        Some(content) => content,
+                        None => format!(
+                            "Wrote {} bytes to {}",
+                            rendered.size,
+                            rendered.output_path.unwrap_or_default()
+                        ),
+                    })
+                },
+            )
+            .await
+        }
+        "convert" => {
+            let currency = state.config.currency.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error converting value",
+                move |request: crate::convert::ConvertRequest| {
+                    let converted = crate::convert::execute_convert(request, &currency)?;
+                    Ok(serde_json::to_string_pretty(&converted)?)
+                },
+            )
+            .await
+        }
+        "generate" => {
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error generating values",
+                |request: crate::generate::GenerateRequest| {
+                    let generated = crate::generate::execute_generate(request)?;
+                    Ok(serde_json::to_string_pretty(&generated)?)
+                },
+            )
+            .await
+        }
+        "regex_extract" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error extracting matches",
+                move |request: crate::regex_extract::RegexExtractRequest| {
+                    let extracted =
+                        crate::regex_extract::execute_regex_extract(request, fs.as_ref())?;
+                    Ok(serde_json::to_string_pretty(&extracted)?)
+                },
+            )
+            .await
+        }
+        "xlsx_read" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error reading spreadsheet",
+                move |request: crate::xlsx_read::XlsxReadRequest| {
+                    let sheet = crate::xlsx_read::execute_xlsx_read(request, fs.as_ref())?;
+                    Ok(serde_json::to_string_pretty(&sheet)?)
+                },
+            )
+            .await
+        }
+        "feed_read" => {
+            let allowed_domains = state.config.feed.allowed_domains.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error reading feed",
+                move |request: crate::feed_read::FeedReadRequest| {
+                    let feed = crate::feed_read::execute_feed_read(request, &allowed_domains)?;
+                    Ok(serde_json::to_string_pretty(&feed)?)
+                },
+            )
+            .await
+        }
+        "url_download" => {
+            let config = state.config.download.clone();
+            let state = state.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error downloading file",
+                move |request: crate::url_download::UrlDownloadRequest| {
+                    let downloaded =
+                        crate::url_download::execute_url_download(request, &config, &state)?;
+                    Ok(serde_json::to_string_pretty(&downloaded)?)
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "clipboard")]
+        "clipboard_read" => {
+            run_blocking_tool(
+                name,
+                Some(arguments.unwrap_or_else(|| serde_json::json!({}))),
+                "Error reading clipboard",
+                |request: crate::clipboard::ClipboardReadRequest| {
+                    Ok(crate::clipboard::execute_clipboard_read(request)?.content)
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "clipboard")]
+        "clipboard_write" => {
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error writing clipboard",
+                |request: crate::clipboard::ClipboardWriteRequest| {
+                    let write_response = crate::clipboard::execute_clipboard_write(request)?;
+                    Ok(format!(
+                        "Copied {} bytes to the clipboard",
+                        write_response.size
+                    ))
+                },
+            )
+            .await
+        }
+        "memory_ingest" => {
+            let config = state.config.memory.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error storing memory",
+                move |request: crate::memory::MemoryIngestRequest| {
+                    let stored = crate::memory::execute_memory_ingest(request, &config)?;
+                    Ok(format!(
+                        "Stored {} chunk(s) ({} entries in memory): {}",
+                        stored.ids.len(),
+                        stored.total_entries,
+                        stored.ids.join(", ")
+                    ))
+                },
+            )
+            .await
+        }
+        "memory_search" => {
+            let config = state.config.memory.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error searching memory",
+                move |request: crate::memory::MemorySearchRequest| {
+                    let found = crate::memory::execute_memory_search(request, &config)?;
+                    if found.matches.is_empty() {
+                        return Ok("No matching memories found".to_string());
+                    }
+                    Ok(found
+                        .matches
+                        .iter()
+                        .map(|m| {
+                            format!(
+                                "[{:.3}] {}{}\n{}",
+                                m.score,
+                                m.id,
+                                m.source
+                                    .as_ref()
+                                    .map(|source| format!(" ({})", source))
+                                    .unwrap_or_default(),
+                                m.text
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n\n"))
+                },
+            )
+            .await
+        }
+        "summarize" => {
+            let config = state.config.summarize.clone();
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error summarizing file",
+                move |request: crate::summarize::SummarizeRequest| {
+                    let result =
+                        crate::summarize::execute_summarize(request, &config, fs.as_ref())?;
+                    Ok(format!(
+                        "Summary of {} ({} characters):\n\n{}",
+                        result.path, result.source_chars, result.summary
+                    ))
+                },
+            )
+            .await
+        }
+        "translate" => {
+            let translator = state.translator.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error translating text",
+                move |request: crate::translate::TranslateRequest| {
+                    let translator = translator.ok_or("No translator configured")?;
+                    let result = crate::translate::execute_translate(translator.as_ref(), request)?;
+                    let mut response = ToolsCallResponse::structured(&result)?;
+                    response.content = vec![ToolContent::text(result.to_text())];
+                    Ok(response)
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "postgres")]
+        "pg_query" => {
+            let config = state.config.postgres.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error running query",
+                move |request: crate::pg_query::PgQueryRequest| {
+                    let result = crate::pg_query::execute_pg_query(request, &config)?;
+                    // The rows go to programs as structured content, the table to the model
+                    let mut response = ToolsCallResponse::structured(&result)?;
+                    response.content = vec![ToolContent::text(result.to_markdown())];
+                    Ok(response)
+                },
+            )
+            .await
+        }
+        "repo_list" => {
+            let repositories = state.config.repository.clone();
+            run_blocking_tool(
+                name,
+                Some(arguments.unwrap_or_else(|| serde_json::json!({}))),
+                "Error listing repository items",
+                move |request: crate::repository::RepoListRequest| {
+                    let response = crate::repository::execute_repo_list(request, &repositories)?;
+                    Ok(crate::repository::format_items(&response))
+                },
+            )
+            .await
+        }
+        "repo_pr_diff" => {
+            let repositories = state.config.repository.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error fetching diff",
+                move |request: crate::repository::RepoPrDiffRequest| {
+                    let diff = crate::repository::execute_repo_pr_diff(request, &repositories)?;
+                    Ok(diff.into_text())
+                },
+            )
+            .await
+        }
+        "repo_file_read" => {
+            let repositories = state.config.repository.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error reading repository file",
+                move |request: crate::repository::RepoFileReadRequest| {
+                    let file = crate::repository::execute_repo_file_read(request, &repositories)?;
+                    Ok(file.into_text())
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "ssh")]
+        "remote_file_read" => {
+            let hosts = state.config.ssh_host.clone();
+            run_async_tool(
+                name,
+                arguments,
+                "Error reading remote file",
+                |request: crate::ssh::RemoteFileReadRequest| async move {
+                    let file = crate::ssh::execute_remote_file_read(request, &hosts).await?;
+                    Ok(format!(
+                        "Host: {}\nFile: {}\nSize: {} bytes\n\nContent:\n{}",
+                        file.host, file.path, file.size, file.content
+                    ))
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "ssh")]
+        "remote_exec" => {
+            let hosts = state.config.ssh_host.clone();
+            run_async_tool(
+                name,
+                arguments,
+                "Error running remote command",
+                |request: crate::ssh::RemoteExecRequest| async move {
+                    let result =
+                        crate::ssh::execute_remote_exec(request, &hosts, &progress, &cancel)
+                            .await?;
+                    Ok(format!(
+                        "Host: {}\nCommand: {}\nExit status: {}\n\nOutput:\n{}{}",
+                        result.host,
+                        result.command,
+                        result.exit_status,
+                        result.output,
+                        if result.truncated {
+                            "\n[output truncated]"
+                        } else {
+                            ""
+                        }
+                    ))
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "email")]
+        "email_send" => {
+            let config = state.config.email.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error sending email",
+                move |request: crate::email::EmailSendRequest| {
+                    let sent = crate::email::execute_email_send(request, &config)?;
+                    Ok(format!(
+                        "Email sent to {}: {}",
+                        sent.recipients.join(", "),
+                        sent.message
+                    ))
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "image")]
+        "image_info" => {
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error reading image",
+                move |request: crate::image_info::ImageInfoRequest| {
+                    let mut info = crate::image_info::execute_image_info(request, fs.as_ref())?;
+                    let thumbnail = info.thumbnail.take();
+                    let mut response = ToolsCallResponse::structured(&info)?;
+                    if let Some(thumbnail) = thumbnail {
+                        response.content.push(ToolContent::image_base64(
+                            thumbnail.data,
+                            thumbnail.mime_type,
+                        ));
+                    }
+                    Ok(response)
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "screenshot")]
+        "screenshot" => {
+            run_blocking_tool(
+                name,
+                Some(arguments.unwrap_or_else(|| serde_json::json!({}))),
+                "Error capturing screenshot",
+                |request: crate::screenshot::ScreenshotRequest| {
+                    let screenshot = crate::screenshot::execute_screenshot(request)?;
+                    Ok(vec![ToolContent::image_base64(
+                        screenshot.data,
+                        screenshot.mime_type,
+                    )])
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "speech")]
+        "tts" => {
+            let engine = state.speech.tts.clone();
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error synthesizing speech",
+                move |request: crate::speech::TtsRequest| {
+                    let engine = engine.ok_or("No text-to-speech engine configured")?;
+                    let audio = crate::speech::execute_tts(engine.as_ref(), request)?;
+                    Ok(vec![ToolContent::audio(&audio.data, audio.mime_type)])
+                },
+            )
+            .await
+        }
+        #[cfg(feature = "speech")]
+        "stt" => {
+            let engine = state.speech.stt.clone();
+            let fs = Arc::clone(&state.fs);
+            run_blocking_tool(
+                name,
+                arguments,
+                "Error transcribing speech",
+                move |request: crate::speech::SttRequest| {
+                    let engine = engine.ok_or("No speech-to-text engine configured")?;
+                    Ok(crate::speech::execute_stt(engine.as_ref(), request, fs.as_ref())?.text)
+                },
+            )
+            .await
+        }
+        _ => Ok(ToolsCallResponse::error(format!(
+            "Tool '{}' is not implemented by this server",
+            name
+        ))),
+    }
+}
+
+/// Answer an initialize request, picking the protocol version both sides support
+///
+/// The client's version is accepted when the server supports it; otherwise the server
+/// proposes its latest version and the client decides whether to continue.
+async fn initialize(state: &McpServerState, request: &InitializeRequest) -> InitializeResult {
+    let protocol_version =
+        if SUPPORTED_PROTOCOL_VERSIONS.contains(&request.protocol_version.as_str()) {
+            request.protocol_version.clone()
+        } else {
+            LATEST_PROTOCOL_VERSION.to_string()
+        };
+    InitializeResult {
+        protocol_version,
+        capabilities: ServerCapabilities {
+            tools: Some(serde_json::json!({ "listChanged": true })),
+            resources: (!state.resources.read().await.is_empty())
+                .then(|| serde_json::json!({ "subscribe": true, "listChanged": false })),
+            prompts: (!state.prompts.read().await.is_empty())
+                .then(|| serde_json::json!({ "listChanged": false })),
+            ..Default::default()
+        },
+        server_info: Implementation {
+            name: "mcp-server".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+        instructions: state.instructions.clone(),
+    }
+}
+
+/// Handle JSON-RPC notifications
+///
+/// Notifications never get a response; unknown ones are ignored.
+async fn handle_notification(
+    state: &McpServerState,
+    session_id: &str,
+    notification: JsonRpcNotification,
+) {
+    if notification.method == crate::cancellation::CANCELLED_METHOD {
+        crate::cancellation::cancel(state, session_id, notification.params);
+    } else if notification.method == "notifications/initialized"
+        && let Some(session) = state.sessions.read().await.get(session_id)
+    {
+        crate::server_log::info(format!(
+            "MCP client initialized: {} {} (protocol {})",
+            session.client.client_info.name,
+            session.client.client_info.version,
+            session.protocol_version
+        ));
+    }
+}
+
+/// Room for the JSON-RPC envelope around a result in a response body
+const RESPONSE_OVERHEAD: usize = 128;
+
+/// Outcome of a message handled for a transport that delivers responses itself
+#[derive(Debug)]
+pub struct MessageOutcome {
+    /// Status the Streamable HTTP transport would answer with
+    pub status: StatusCode,
+    /// The response, or the rejection reason for unsuccessful statuses; empty for notifications
+    pub body: String,
+    /// Session opened by an initialize request
+    pub new_session: Option<String>,
+}
+
+/// Handle a JSON-RPC message within a session, as POST /mcp would
+///
+/// Used by the SSE and unix socket transports, which write the response to their own
+/// stream instead of an HTTP response.
+pub async fn handle_message(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    api_key: Option<Arc<crate::auth::ApiKey>>,
+    message: Value,
+) -> MessageOutcome {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = session_id.and_then(|id| HeaderValue::from_str(id).ok()) {
+        headers.insert(crate::session::SESSION_HEADER, value);
+    }
+    let response = match crate::jsonrpc_body::validate(&message) {
+        Ok(()) => {
+            handle_jsonrpc(
+                State(state.clone()),
+                headers,
+                api_key.map(Extension),
+                JsonRpcBody(message),
+            )
+            .await
+        }
+        Err(invalid) => invalid.into_response(),
+    };
+
+    let status = response.status();
+    let new_session = response
+        .headers()
+        .get(crate::session::SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    // The body is taken over without copying; it is always valid UTF-8 JSON or text
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map(|body| {
+            String::from_utf8(Vec::from(body))
+                .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+        })
+        .unwrap_or_default();
+    MessageOutcome {
+        status,
+        body,
+        new_session,
+    }
+}
+
+/// Handle JSON-RPC messages (POST /mcp)
+///
+/// An initialize request opens a session whose id is returned in the `Mcp-Session-Id`
+/// header; every other message must carry it. Messages without an id are
+/// notifications, handled without a response and acknowledged with 202 Accepted, as
+/// are responses to requests the server sent.
+/// Malformed messages are answered with JSON-RPC errors, see [`crate::jsonrpc_body`].
+/// When API keys are configured, `api_key` is the key the request was authenticated
+/// with, see [`crate::auth`].
+async fn handle_jsonrpc(
+    State(state): State<McpServerState>,
+    headers: HeaderMap,
+    api_key: Option<Extension<Arc<crate::auth::ApiKey>>>,
+    JsonRpcBody(message): JsonRpcBody,
+) -> Response {
+    let api_key = api_key.map(|Extension(key)| key);
+    let session_id = if message.get("method").and_then(Value::as_str) == Some("initialize") {
+        None
+    } else {
+        match crate::session::require(&state, &headers).await {
+            Ok(id) => Some(id),
+            Err(rejection) => return rejection.into_response(),
+        }
+    };
+    if let Some(session_id) = &session_id
+        && let Err(error) = crate::auth::check_session(&state, session_id, api_key.as_deref()).await
+    {
+        let response: JsonRpcResponse = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: message
+                .get("id")
+                .and_then(|id| serde_json::from_value(id.clone()).ok()),
+            result: None,
+            error: Some(error),
+        };
+        return (StatusCode::FORBIDDEN, axum::Json(response)).into_response();
+    }
+
+    // Responses to the requests the server sent, see [`crate::server_request`]
+    if message.get("method").is_none() {
+        crate::server_request::complete(&state, session_id.as_deref(), message);
+        return StatusCode::ACCEPTED.into_response();
+    }
+    let request: JsonRpcRequest = match serde_json::from_value(message) {
+        Ok(request) => request,
+        Err(e) => {
+            return crate::jsonrpc_body::invalid_request(None, &e.to_string()).into_response();
+        }
+    };
+    let Some(id) = request.id else {
+        let notification = JsonRpcNotification {
+            jsonrpc: request.jsonrpc,
+            id: None,
+            method: request.method,
+            params: request.params,
+        };
+        handle_notification(
+            &state,
+            session_id.as_deref().unwrap_or_default(),
+            notification,
+        )
+        .await;
+        return StatusCode::ACCEPTED.into_response();
+    };
+
+    if let Some(key) = api_key.as_deref()
+        && let Err(error) =
+            crate::auth::check_request(key, &request.method, request.params.as_ref())
+    {
+        let response: JsonRpcResponse = JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            result: None,
+            error: Some(error),
+        };
+        return axum::Json(response).into_response();
+    }
+
+    let mut new_session = None;
+    let outcome = match request.method.as_str() {
+        "initialize" => match request
+            .params
+            .map(serde_json::from_value::<InitializeRequest>)
+        {
+            Some(Ok(params)) => {
+                let result = initialize(&state, &params).await;
+                new_session = Some(
+                    crate::session::create(
+                        &state,
+                        params,
+                        result.protocol_version.clone(),
+                        api_key.as_ref().map(|key| key.name.clone()),
+                    )
+                    .await,
+                );
+                to_raw(&result)
+            }
+            Some(Err(e)) => Err(JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            }),
+            None => Err(JsonRpcError {
+                code: -32602,
+                message: "Missing params".to_string(),
+                data: None,
+            }),
+        },
+        "ping" => to_raw(&serde_json::json!({})),
+        "tools/list" => match crate::pagination::PaginatedRequest::from_params(request.params)
+            .and_then(|params| params.offset())
+        {
+            Ok(offset) => {
+                list_tools(&state, session_id.as_deref(), api_key.as_deref(), offset).await
+            }
+            Err(error) => Err(error),
+        },
+        "gateway/status" => match &state.gateway {
+            Some(gateway) => to_raw(&gateway.status()),
+            None => Err(JsonRpcError {
+                code: -32601,
+                message: "No upstream servers are configured".to_string(),
+                data: None,
+            }),
+        },
+        "server/stats" => to_raw(&state.stats.snapshot()),
+        "server/info" => {
+            let mut info = crate::server_info::collect(&state).await;
+            if let Some(key) = &api_key {
+                info.tools.retain(|tool| key.allows(&tool.name));
+                info.shadows.retain(|shadow| key.allows(&shadow.tool));
+            }
+            to_raw(&info)
+        }
+        "fs/roots" => {
+            let roots = crate::file_read::ALLOWED_ROOTS
+                .iter()
+                .map(|root| Root {
+                    uri: crate::file_read::file_uri(root),
+                    name: None,
+                })
+                .collect();
+            to_raw(&RootsListResponse { roots })
+        }
+        "tools/call" => {
+            handle_tools_call(
+                &state,
+                session_id.as_deref(),
+                api_key.as_deref(),
+                &id,
+                request.params,
+            )
+            .await
+        }
+        "prompts/list" => to_raw(&crate::prompts::list(&state).await),
+        "prompts/get" => match request
+            .params
+            .map(serde_json::from_value::<crate::prompts::PromptsGetRequest>)
+        {
+            Some(Ok(params)) => crate::prompts::get(&state, &params)
+                .await
+                .and_then(|result| to_raw(&result)),
+            Some(Err(e)) => Err(JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            }),
+            None => Err(JsonRpcError {
+                code: -32602,
+                message: "Missing params".to_string(),
+                data: None,
+            }),
+        },
+        "resources/list" => {
+            match crate::pagination::PaginatedRequest::from_params(request.params) {
+                Ok(params) => crate::resources::list(&state, &params, api_key.as_deref())
+                    .await
+                    .and_then(|result| to_raw(&result)),
+                Err(error) => Err(error),
+            }
+        }
+        "resources/read" => match request
+            .params
+            .map(serde_json::from_value::<crate::resources::ResourcesReadRequest>)
+        {
+            Some(Ok(params)) => crate::resources::read(&state, &params.uri)
+                .await
+                .and_then(|result| to_raw(&result)),
+            Some(Err(e)) => Err(JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            }),
+            None => Err(JsonRpcError {
+                code: -32602,
+                message: "Missing params".to_string(),
+                data: None,
+            }),
+        },
+        "resources/subscribe" | "resources/unsubscribe" => match request
+            .params
+            .map(serde_json::from_value::<crate::resources::ResourcesSubscribeRequest>)
+        {
+            Some(Ok(params)) if request.method == "resources/subscribe" => {
+                crate::resources::subscribe(&state, session_id.as_deref(), &params.uri)
+                    .await
+                    .and_then(|()| to_raw(&serde_json::json!({})))
+            }
+            Some(Ok(params)) => {
+                crate::resources::unsubscribe(&state, session_id.as_deref(), &params.uri).await;
+                to_raw(&serde_json::json!({}))
+            }
+            Some(Err(e)) => Err(JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            }),
+            None => Err(JsonRpcError {
+                code: -32602,
+                message: "Missing params".to_string(),
+                data: None,
+            }),
+        },
+        method => match state.methods.get(method) {
+            Some(handler) => handler(state.clone(), request.params)
+                .await
+                .and_then(|result| to_raw(&result)),
+            None => Err(JsonRpcError {
+                code: -32601,
+                message: "Method not found".to_string(),
+                data: None,
+            }),
+        },
+    };
+
+    let response = match outcome {
+        Ok(result) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            result: Some(result),
+            error: None,
+        },
+        Err(error) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            result: None,
+            error: Some(error),
+        },
+    };
+
+    // Serialize into a buffer sized for the result, which is copied in once
+    let capacity = response.result.as_ref().map_or(0, RpcResult::len_hint);
+    let mut body = Vec::with_capacity(capacity + RESPONSE_OVERHEAD);
+    if let Err(e) = serde_json::to_writer(&mut body, &response) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    }
+    let mut response = ([(header::CONTENT_TYPE, "application/json")], body).into_response();
+    if let Some(id) = new_session
+        && let Ok(value) = HeaderValue::from_str(&id)
+    {
+        response
+            .headers_mut()
+            .insert(crate::session::SESSION_HEADER, value);
+    }
+    response
+}
+/// A page of the tools/list result for a session, starting at `offset`
+///
+/// Tools the API key does not permit are left out.
+async fn list_tools(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    api_key: Option<&crate::auth::ApiKey>,
+    offset: usize,
+) -> Result<RpcResult, JsonRpcError> {
+    let catalog = crate::i18n::session_catalog(state, session_id).await;
+    let restricted = api_key.is_some_and(crate::auth::ApiKey::is_restricted);
+    match (&state.gateway, catalog) {
+        (None, None) if !restricted => state
+            .tools_list(offset)
+            .await
+            .map(RpcResult::Shared)
+            .map_err(serialization_error),
+        // Upstream tools can change at any time and translations differ per
+        // session, so these lists are built per request
+        (gateway, catalog) => {
+            let local = state.get_tools().await;
+            let local = match catalog {
+                Some(catalog) => crate::i18n::localize_tools(&catalog, &local),
+                None => local.iter().map(|tool| McpTool::clone(tool)).collect(),
+            };
+            let upstream = match gateway {
+                Some(gateway) => gateway.list_tools().await,
+                None => Vec::new(),
+            };
+            let mut tools: Vec<&McpTool> = local
+                .iter()
+                .chain(upstream.iter())
+                .filter(|tool| api_key.is_none_or(|key| key.allows(&tool.name)))
+                .collect();
+            tools.sort_by(|a, b| a.name.cmp(&b.name));
+            let (tools, next_cursor) =
+                crate::pagination::page(tools, offset, crate::pagination::page_size(state));
+            to_raw(&ToolsList { tools, next_cursor })
+        }
+    }
+}
+
+/// Handle a tools/call request: API key allowlist and admission control, then a local
+/// or gateway tool
+pub(crate) async fn handle_tools_call(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    api_key: Option<&crate::auth::ApiKey>,
+    request_id: &RequestId,
+    params: Option<Value>,
+) -> Result<RpcResult, JsonRpcError> {
+    let tool = params
+        .as_ref()
+        .and_then(|params| params.get("name"))
+        .and_then(Value::as_str);
+    if let (Some(key), Some(tool)) = (api_key, tool)
+        && !key.allows(tool)
+    {
+        return Err(crate::auth::tool_forbidden(key, tool));
+    }
+    // The permit is held until the call completes
+    let _permit = crate::admission::admit(state, session_id, tool)?;
+    let in_flight = InFlight::register(state, session_id, request_id);
+
+    let Some(params) = params else {
+        return Err(JsonRpcError {
+            code: -32602,
+            message: "Missing params".to_string(),
+            data: None,
+        });
+    };
+    let call_request =
+        serde_json::from_value::<ToolsCallRequest>(params).map_err(|e| JsonRpcError {
+            code: -32602,
+            message: format!("Invalid params: {}", e),
+            data: None,
+        })?;
+
+    if state.tools.read().await.contains_key(&call_request.name) {
+        let token = call_request
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.progress_token.clone());
+        let progress = ProgressReporter::new(state, session_id, token);
+        let mut response = crate::server_request::scope(
+            session_id,
+            dispatch_tool(state, call_request, progress, in_flight.token.clone()),
+        )
+        .await?;
+        if response.is_error
+            && let Some(catalog) = crate::i18n::session_catalog(state, session_id).await
+        {
+            crate::i18n::localize_error(&catalog, &mut response);
+        }
+        return Ok(RpcResult::Tool(response));
+    }
+    match state
+        .gateway
+        .as_ref()
+        .filter(|gateway| gateway.owns(&call_request.name))
+    {
+        Some(gateway) => {
+            // The caller's remaining budget is passed on to the upstream server
+            let deadline = call_request
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.timeout_ms)
+                .map(|ms| Deadline::after(std::time::Duration::from_millis(ms)));
+            let call =
+                gateway.call_tool(state, &call_request.name, call_request.arguments, deadline);
+            tokio::select! {
+                result = call => to_raw(&result?),
+                () = in_flight.token.cancelled() => Err(crate::cancellation::cancelled_error()),
+            }
+        }
+        None => Err(JsonRpcError {
+            code: -32601,
+            message: format!("Tool '{}' not found", call_request.name),
+            data: None,
+        }),
+    }
+}
+
+/// Result of a request, serialized into the response body without an intermediate `Value`
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum RpcResult {
+    /// Tool output, written straight into the body so large contents are copied once
+    Tool(ToolsCallResponse),
+    /// Any other result, already serialized
+    Raw(Box<RawValue>),
+    /// A result serialized once and shared between requests
+    Shared(Arc<RawValue>),
+}
+
+impl RpcResult {
+    /// Approximate serialized length, used to size the response buffer
+    fn len_hint(&self) -> usize {
+        match self {
+            Self::Tool(response) => {
+                let payload: usize = response
+                    .content
+                    .iter()
+                    .map(|content| {
+                        let resource = content.resource.as_ref().map_or(0, |resource| {
+                            resource.text.as_ref().map_or(0, String::len)
+                                + resource.blob.as_ref().map_or(0, String::len)
+                        });
+                        content.text.as_ref().map_or(0, String::len)
+                            + content.data.as_ref().map_or(0, String::len)
+                            + resource
+                            + RESPONSE_OVERHEAD
+                    })
+                    .sum();
+                // Leave room for escaped characters in text
+                payload + payload / 8
+            }
+            Self::Raw(raw) => raw.get().len(),
+            Self::Shared(raw) => raw.get().len(),
+        }
+    }
+}
+
+/// Serialize a result to JSON text, which is copied into the response body as is
+fn to_raw(result: &impl Serialize) -> Result<RpcResult, JsonRpcError> {
+    serde_json::value::to_raw_value(result)
+        .map(RpcResult::Raw)
+        .map_err(serialization_error)
+}
+
+fn serialization_error(e: serde_json::Error) -> JsonRpcError {
+    JsonRpcError {
+        code: -32603,
+        message: format!("Failed to serialize result: {}", e),
+        data: None,
+    }
+}
+
+/// MCP Server that handles JSON-RPC requests
+pub struct McpServer {
+    pub(crate) state: McpServerState,
+    /// Address the HTTP transports listen on
+    pub(crate) addr: std::net::SocketAddr,
+    /// Certificate and key to serve HTTPS with instead of plain HTTP
+    pub(crate) tls: Option<crate::tls::ServerTlsConfig>,
+    /// Whether the HTTP+SSE transport is served next to Streamable HTTP
+    pub(crate) sse: bool,
+    /// Path of the Streamable HTTP endpoint
+    pub(crate) path: String,
+    /// Browser origins allowed to call the HTTP endpoints
+    pub(crate) cors: crate::server_builder::CorsPolicy,
+    /// Largest request body accepted, in bytes
+    pub(crate) max_body_bytes: usize,
+}
+
+impl McpServer {
+    /// Start configuring a server, see [`crate::server_builder`]
+    pub fn builder() -> crate::server_builder::McpServerBuilder {
+        crate::server_builder::McpServerBuilder::default()
+    }
+
+    /// Base URL of the HTTP transports, e.g. `http://localhost:8080`
+    pub fn url(&self) -> String {
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        if self.addr.ip().is_loopback() {
+            format!("{}://localhost:{}", scheme, self.addr.port())
+        } else {
+            format!("{}://{}", scheme, self.addr)
+        }
+    }
+
+    /// URL of the Streamable HTTP endpoint, e.g. `http://localhost:8080/mcp`
+    pub fn endpoint_url(&self) -> String {
+        format!("{}{}", self.url(), self.path)
+    }
+
+    /// Add a tool to the server
+    pub async fn add_tool(&self, tool: McpTool) {
+        self.state.add_tool(tool).await;
+    }
+
+    /// Register a tool implemented by the application
+    ///
+    /// The tool is listed by tools/list and called through tools/call like the
+    /// built-in tools; a tool with the same name as an existing one replaces it.
+    /// Connected sessions are sent `notifications/tools/list_changed`.
+    pub async fn register_tool(&self, tool: impl ToolHandler + 'static) {
+        self.state.register_tool(tool).await;
+    }
+
+    /// Remove a tool while the server runs
+    ///
+    /// Like registering a tool, this sends `notifications/tools/list_changed` to the
+    /// connected sessions. Returns whether a tool of that name was registered.
+    pub async fn unregister_tool(&self, name: &str) -> bool {
+        self.state.unregister_tool(name).await
+    }
+
+    /// Mirror the calls of a tool to a new implementation before it replaces the tool
+    ///
+    /// The shadow is named like the tool it shadows and receives a copy of each call
+    /// the tool answers, in the background; its results are discarded and logged when
+    /// they differ from the tool's, see [`crate::shadow`]. It must have no side effects.
+    pub fn register_shadow(&self, shadow: impl ToolHandler + 'static) {
+        self.state.shadows.register(shadow);
+    }
+
+    /// Stop mirroring a tool's calls, returning whether it had a shadow
+    pub fn unregister_shadow(&self, tool: &str) -> bool {
+        self.state.shadows.unregister(tool)
+    }
+
+    /// Register a tool implemented by a closure called with the call arguments
+    ///
+    /// A shorthand for [`McpServer::register_tool`] for simple tools that need no
+    /// struct or server state.
+    pub async fn register_fn<F, Fut>(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: F,
+    ) where
+        F: Fn(Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ToolResult> + Send + 'static,
+    {
+        self.register_tool(FnTool {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            handler: Box::new(move |arguments| Box::pin(handler(arguments))),
+        })
+        .await;
+    }
+
+    /// Serve the resources of a provider under its URI scheme
+    ///
+    /// Clients discover them with resources/list and read them by URI with
+    /// resources/read; a provider for an already served scheme replaces it.
+    pub async fn register_resources(
+        &self,
+        provider: impl crate::resources::ResourceProvider + 'static,
+    ) {
+        self.state.register_resources(provider).await;
+    }
+
+    /// Serve a prompt through prompts/list and prompts/get
+    ///
+    /// A prompt with the same name as an existing one replaces it.
+    pub async fn register_prompt(&self, provider: impl crate::prompts::PromptProvider + 'static) {
+        self.state.register_prompt(provider).await;
+    }
+
+    /// Set the configuration used by the tools
+    pub fn set_config(&mut self, config: crate::config::ServerConfig) {
+        self.state.config = Arc::new(config);
+    }
+
+    /// Set the engines used by the tts and stt tools
+    #[cfg(feature = "speech")]
+    pub fn set_speech_engines(&mut self, engines: crate::speech::SpeechEngines) {
+        self.state.speech = engines;
+    }
+
+    /// Require one of `keys` of the HTTP clients, see [`crate::auth`]
+    pub fn set_api_keys(&mut self, keys: crate::auth::ApiKeys) {
+        self.state.api_keys = Arc::new(keys);
+    }
+
+    /// Run the file tools on another filesystem, such as a read-only one
+    pub fn set_fs(&mut self, fs: impl crate::vfs::VirtualFs + 'static) {
+        self.state.fs = Arc::new(fs);
+    }
+
+    /// Journal background jobs, and serve those recovered from the journal on start
+    pub fn set_job_journal(&mut self, journal: crate::job_journal::JobJournal) {
+        self.state.job_journal = Some(Arc::new(journal));
+    }
+
+    /// Serve the tools of upstream MCP servers next to the local ones
+    pub fn set_gateway(&mut self, gateway: crate::gateway::Gateway) {
+        self.state.gateway = Some(Arc::new(gateway));
+    }
+
+    /// Set the instructions returned to clients during initialization
+    pub fn set_instructions(&mut self, instructions: impl Into<String>) {
+        self.state.instructions = Some(instructions.into());
+    }
+
+    /// Register a custom JSON-RPC method
+    ///
+    /// Lets applications add non-tool methods such as health checks or admin calls.
+    /// Built-in methods (`initialize`, `tools/list`, `tools/call`, ...) take precedence
+    /// over custom methods of the same name.
+    pub fn register_method<F, Fut>(&mut self, method: impl Into<String>, handler: F)
+    where
+        F: Fn(McpServerState, Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, JsonRpcError>> + Send + 'static,
+    {
+        Arc::make_mut(&mut self.state.methods).register(method, handler);
+    }
+
+    /// Set the translations of tool descriptions and errors, see [`crate::i18n`]
+    pub fn set_catalogs(&mut self, catalogs: crate::i18n::Catalogs) {
+        self.state.catalogs = Arc::new(catalogs);
+    }
+
+    /// Set the translator used by the translate tool
+    pub fn set_translator(&mut self, translator: Arc<dyn crate::translate::Translator>) {
+        self.state.translator = Some(translator);
+    }
+
+    /// Subscribe to server notifications (e.g. scheduled result updates)
+    pub fn subscribe(&self) -> broadcast::Receiver<JsonRpcNotification> {
+        self.state.subscribe()
+    }
+
+    /// Get all registered tools
+    pub async fn tools(&self) -> Vec<Arc<McpTool>> {
+        self.state.get_tools().await
+    }
+
+    /// Check the configuration, allowed directories, upstreams and every tool
+    ///
+    /// Nothing is served; this backs `mcp-server check`.
+    pub async fn check(&self) -> crate::check::CheckReport {
+        crate::check::run(&self.state).await
+    }
+
+    /// Serve on a unix domain socket instead of TCP
+    ///
+    /// Clients exchange newline-delimited JSON-RPC messages with the server, one
+    /// session per connection, and connect with [`McpClient::connect_unix`]. The socket
+    /// is only accessible to the current user. Webhooks and the HTTP transports are not
+    /// served.
+    #[cfg(unix)]
+    pub async fn bind_unix(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.as_ref();
+        crate::events::start(&self.state);
+        crate::job_journal::restore(&self.state).await;
+        crate::scheduler::start(&self.state).map_err(|e| e.to_string())?;
+        crate::resources::watch(&self.state);
+
+        crate::server_log::info(format!(
+            "Starting MCP server on unix socket {}",
+            path.display()
+        ));
+        crate::unix_socket::serve(self.state.clone(), path).await
+    }
+
+    /// Start the MCP server
+    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut app = Router::new().route(
+            &self.path,
+            post(handle_jsonrpc)
+                .get(crate::session::handle_stream)
+                .delete(crate::session::handle_delete),
+        );
+        if self.sse {
+            app = app
+                .route(crate::sse::SSE_PATH, get(crate::sse::handle_connect))
+                .route(crate::sse::MESSAGES_PATH, post(crate::sse::handle_message));
+        }
+        // Webhooks authenticate with their own tokens and signatures
+        let app = app
+            .route_layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                crate::auth::require,
+            ))
+            .route("/webhooks/:name", post(crate::webhook::handle_webhook))
+            .layer(axum::extract::DefaultBodyLimit::max(self.max_body_bytes))
+            .layer(self.cors.layer())
+            .with_state(self.state.clone());
+
+        crate::events::start(&self.state);
+        crate::job_journal::restore(&self.state).await;
+        crate::scheduler::start(&self.state).map_err(|e| e.to_string())?;
+        crate::resources::watch(&self.state);
+
+        // The certificate is loaded first so that a bad one fails before binding
+        let acceptor = self
+            .tls
+            .as_ref()
+            .map(crate::tls::ServerTlsConfig::acceptor)
+            .transpose()?;
+        let listener = tokio::net::TcpListener::bind(self.addr).await?;
+
+        let url = self.url();
+        crate::server_log::info(format!("Starting MCP server on {}", self.endpoint_url()));
+        if self.sse {
+            crate::server_log::info(format!(
+                "Serving the HTTP+SSE transport on {}{}",
+                url,
+                crate::sse::SSE_PATH
+            ));
+        }
+
+        match acceptor {
+            Some(acceptor) => crate::tls::serve(listener, acceptor, app).await?,
+            None => axum::serve(listener, app).await?,
+        }
 
         Ok(())
     }
@@ -0,0 +1,409 @@
+//! Memory tool module
+//!
+//! This module provides a small persistent knowledge base for agents. Text is split
+//! into chunks, embedded with the Ollama embeddings API and stored in a flat vector
+//! index (a JSON file), which is searched by cosine similarity. The index survives
+//! server restarts, so agents can build and query it across sessions.
+
+use crate::config::MemoryConfig;
+use crate::tokenizer::Tokenizer;
+use crate::tool_error::ToolErrorPayload;
+use chrono::{DateTime, Utc};
+use reqwest::blocking::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Ollama server used when none is configured
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Embedding model used when none is configured
+const DEFAULT_MODEL: &str = "nomic-embed-text";
+
+/// Chunks are cut at paragraph or sentence boundaries below this many tokens of the
+/// embedding model
+const MAX_CHUNK_TOKENS: usize = 250;
+
+/// Upper bound on the size of a single ingested text
+const MAX_INGEST_BYTES: usize = 1024 * 1024;
+
+/// Default number of search results
+const DEFAULT_RESULTS: usize = 5;
+
+/// Upper bound on the number of search results
+const MAX_RESULTS: usize = 20;
+
+/// Time allowed for the model check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serializes every read-modify-write of the index file
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Memory ingest tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MemoryIngestRequest {
+    /// The text to remember
+    pub text: String,
+    /// Where the text came from, e.g. a file path or URL
+    pub source: Option<String>,
+}
+
+/// Memory ingest tool response
+#[derive(Debug, Serialize)]
+pub struct MemoryIngestResponse {
+    pub ids: Vec<String>,
+    pub total_entries: usize,
+}
+
+/// Memory search tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MemorySearchRequest {
+    /// What to look for
+    pub query: String,
+    /// Maximum number of results (default 5, max 20)
+    pub limit: Option<usize>,
+    /// Only search text ingested from this source
+    pub source: Option<String>,
+}
+
+/// A single search result
+#[derive(Debug, Serialize)]
+pub struct MemoryMatch {
+    pub id: String,
+    pub score: f32,
+    pub source: Option<String>,
+    pub text: String,
+}
+
+/// Memory search tool response
+#[derive(Debug, Serialize)]
+pub struct MemorySearchResponse {
+    pub matches: Vec<MemoryMatch>,
+}
+
+/// The persisted vector index
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MemoryIndex {
+    /// Embedding model the vectors were produced with
+    model: String,
+    entries: Vec<MemoryEntry>,
+}
+
+/// A stored chunk of text and its embedding
+#[derive(Debug, Serialize, Deserialize)]
+struct MemoryEntry {
+    id: String,
+    text: String,
+    source: Option<String>,
+    created_at: DateTime<Utc>,
+    embedding: Vec<f32>,
+}
+
+/// Ollama /api/embed request
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+/// Ollama /api/embed response
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Execute the memory ingest tool
+pub fn execute_memory_ingest(
+    request: MemoryIngestRequest,
+    config: &MemoryConfig,
+) -> Result<MemoryIngestResponse, Box<dyn std::error::Error>> {
+    if request.text.len() > MAX_INGEST_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("Text exceeds the {} byte limit", MAX_INGEST_BYTES),
+            MAX_INGEST_BYTES as u64,
+        )
+        .into());
+    }
+    let model = config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+    let chunks = chunk_text(&request.text, &*crate::tokenizer::for_model(model));
+    if chunks.is_empty() {
+        return Err("Text is empty".into());
+    }
+
+    let embeddings = embed(config, &chunks)?;
+
+    let path = index_path(config)?;
+    let _guard = INDEX_LOCK
+        .lock()
+        .map_err(|_| "Memory index lock poisoned")?;
+    let mut index = load_index(path, model)?;
+    index.model = model.to_string();
+
+    let now = Utc::now();
+    let mut ids = Vec::with_capacity(chunks.len());
+    for (text, embedding) in chunks.into_iter().zip(embeddings) {
+        let id = uuid::Uuid::new_v4().to_string();
+        ids.push(id.clone());
+        index.entries.push(MemoryEntry {
+            id,
+            text,
+            source: request.source.clone(),
+            created_at: now,
+            embedding,
+        });
+    }
+    save_index(path, &index)?;
+
+    Ok(MemoryIngestResponse {
+        ids,
+        total_entries: index.entries.len(),
+    })
+}
+
+/// Execute the memory search tool
+pub fn execute_memory_search(
+    request: MemorySearchRequest,
+    config: &MemoryConfig,
+) -> Result<MemorySearchResponse, Box<dyn std::error::Error>> {
+    let limit = request
+        .limit
+        .unwrap_or(DEFAULT_RESULTS)
+        .clamp(1, MAX_RESULTS);
+    let model = config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+
+    let index = {
+        let _guard = INDEX_LOCK
+            .lock()
+            .map_err(|_| "Memory index lock poisoned")?;
+        load_index(index_path(config)?, model)?
+    };
+    if index.entries.is_empty() {
+        return Ok(MemorySearchResponse {
+            matches: Vec::new(),
+        });
+    }
+
+    let query = embed(config, &[request.query])?
+        .pop()
+        .ok_or("Embedding API returned no vector")?;
+
+    let mut scored: Vec<(f32, &MemoryEntry)> = index
+        .entries
+        .iter()
+        .filter(|entry| request.source.is_none() || entry.source == request.source)
+        .filter_map(|entry| Some((cosine_similarity(&query, &entry.embedding)?, entry)))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    Ok(MemorySearchResponse {
+        matches: scored
+            .into_iter()
+            .take(limit)
+            .map(|(score, entry)| MemoryMatch {
+                id: entry.id.clone(),
+                score,
+                source: entry.source.clone(),
+                text: entry.text.clone(),
+            })
+            .collect(),
+    })
+}
+
+/// Get the configured index path
+fn index_path(config: &MemoryConfig) -> Result<&Path, Box<dyn std::error::Error>> {
+    config
+        .path
+        .as_deref()
+        .map(Path::new)
+        .ok_or_else(|| "No memory index path configured".into())
+}
+
+/// Load the index, checking it was built with the configured model
+fn load_index(path: &Path, model: &str) -> Result<MemoryIndex, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(MemoryIndex::default());
+    }
+
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read memory index '{}': {}", path.display(), e))?;
+    let index: MemoryIndex = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid memory index '{}': {}", path.display(), e))?;
+
+    if !index.entries.is_empty() && index.model != model {
+        return Err(format!(
+            "Memory index was built with model '{}' but '{}' is configured",
+            index.model, model
+        )
+        .into());
+    }
+
+    Ok(index)
+}
+
+/// Write the index atomically through a temporary file
+fn save_index(path: &Path, index: &MemoryIndex) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, serde_json::to_vec(index)?)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+/// Embed texts with the Ollama embeddings API
+fn embed(
+    config: &MemoryConfig,
+    texts: &[String],
+) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+    let base_url = config.ollama_url.as_deref().unwrap_or(DEFAULT_OLLAMA_URL);
+    let client = Client::builder()
+        .timeout(Duration::from_secs(120))
+        .build()?;
+
+    let response = client
+        .post(format!("{}/api/embed", base_url.trim_end_matches('/')))
+        .json(&EmbedRequest {
+            model: config.model.as_deref().unwrap_or(DEFAULT_MODEL),
+            input: texts,
+        })
+        .send()?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().unwrap_or_default();
+        return Err(format!("Embedding request failed with status {}: {}", status, body).into());
+    }
+
+    let embeddings = response.json::<EmbedResponse>()?.embeddings;
+    if embeddings.len() != texts.len() {
+        return Err(format!(
+            "Embedding API returned {} vectors for {} inputs",
+            embeddings.len(),
+            texts.len()
+        )
+        .into());
+    }
+    Ok(embeddings)
+}
+
+/// Cosine similarity of two vectors, `None` if their dimensions differ or one is zero
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let (dot, norm_a, norm_b) = a
+        .iter()
+        .zip(b)
+        .fold((0.0, 0.0, 0.0), |(dot, na, nb), (x, y)| {
+            (dot + x * y, na + x * x, nb + y * y)
+        });
+    let norm = norm_a.sqrt() * norm_b.sqrt();
+    (norm > 0.0).then(|| dot / norm)
+}
+
+/// Split text into chunks of at most about `MAX_CHUNK_TOKENS` tokens
+///
+/// Paragraphs are kept together where possible; longer paragraphs are split at
+/// sentence ends and, failing that, at whitespace.
+fn chunk_text(text: &str, tokenizer: &dyn Tokenizer) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    let pieces = text
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .flat_map(|paragraph| split_long(paragraph, tokenizer));
+    for piece in pieces {
+        let piece_tokens = tokenizer.count_tokens(&piece);
+        if !current.is_empty() && current_tokens + piece_tokens + 1 > MAX_CHUNK_TOKENS {
+            chunks.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+            current_tokens += 1;
+        }
+        current.push_str(&piece);
+        current_tokens += piece_tokens;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Split a paragraph longer than `MAX_CHUNK_TOKENS` into shorter pieces
+///
+/// Pieces are measured by the sum of their words' tokens, which is close to the
+/// tokens of the whole piece.
+fn split_long(paragraph: &str, tokenizer: &dyn Tokenizer) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for word in paragraph.split_whitespace() {
+        let word_tokens = tokenizer.count_tokens(word);
+        if !current.is_empty() && current_tokens + word_tokens >= MAX_CHUNK_TOKENS {
+            pieces.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+        current_tokens += word_tokens;
+        // Prefer to end pieces at sentence boundaries once they are reasonably long
+        if current_tokens > MAX_CHUNK_TOKENS / 2 && word.ends_with(['.', '!', '?']) {
+            pieces.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+/// Check that the index can be loaded and the embedding model is available
+pub fn check(config: &MemoryConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let model = config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+    load_index(index_path(config)?, model)?;
+
+    crate::ollama::Ollama::default(model)
+        .with_base_url(config.ollama_url.as_deref().unwrap_or(DEFAULT_OLLAMA_URL))
+        .with_timeout(CHECK_TIMEOUT)
+        .check_model()?;
+    Ok(())
+}
+
+/// Get the tool definition for the memory_ingest tool
+pub fn get_ingest_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "memory_ingest".to_string(),
+        description: Some("Store text in the persistent knowledge base so it can be found later with memory_search. Long text is split into chunks.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<MemoryIngestRequest>()),
+        ..Default::default()
+    }
+}
+
+/// Get the tool definition for the memory_search tool
+pub fn get_search_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "memory_search".to_string(),
+        version: None,
+        description: Some("Search the persistent knowledge base for the stored text most similar in meaning to a query.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<MemorySearchRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
@@ -0,0 +1,230 @@
+//! MIME module
+//!
+//! This module classifies files by their content rather than their name alone. The
+//! first bytes are matched against the signatures of common binary formats (images,
+//! archives, documents, media, executables), which win over a mismatched extension.
+//! Text is then classified by its extension, or by its first characters when it has
+//! none (XML, HTML, SVG, JSON, scripts with a shebang), and is plain text otherwise.
+//! Binary content without a known signature is `application/octet-stream` unless its
+//! extension names a binary format.
+
+use crate::vfs::VirtualFs;
+use std::io::Read;
+use std::path::Path;
+
+/// Number of leading bytes examined, enough for every signature and text heuristic
+pub const SNIFF_LEN: usize = 512;
+
+/// Signatures of binary formats: offset, magic bytes and MIME type
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"\x00\x00\x01\x00", "image/x-icon"),
+    (0, b"II*\x00", "image/tiff"),
+    (0, b"MM\x00*", "image/tiff"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"%!PS", "application/postscript"),
+    (0, b"{\\rtf", "application/rtf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"PK\x05\x06", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (0, b"\x7fELF", "application/x-executable"),
+    (0, b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+    (0, b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+    (0, b"\x00asm", "application/wasm"),
+    (0, b"wOFF", "font/woff"),
+    (0, b"wOF2", "font/woff2"),
+    (0, b"OTTO", "font/otf"),
+    (0, b"\x00\x01\x00\x00\x00", "font/ttf"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+];
+
+/// Signatures short enough to begin ordinary text, only trusted for binary content
+const BINARY_SIGNATURES: &[(&[u8], &str)] = &[
+    (b"BM", "image/bmp"),
+    (b"BZh", "application/x-bzip2"),
+    (b"MZ", "application/vnd.microsoft.portable-executable"),
+    (b"ID3", "audio/mpeg"),
+];
+
+/// Signatures of RIFF containers, found at offset 8 after `RIFF` and the size
+const RIFF_TYPES: &[(&[u8], &str)] = &[
+    (b"WEBP", "image/webp"),
+    (b"WAVE", "audio/wav"),
+    (b"AVI ", "video/x-msvideo"),
+];
+
+/// The MIME type of a file from its leading bytes and, as a fallback, its path
+///
+/// Returns `None` only for empty content with an unknown extension.
+pub fn detect(content: &[u8], path: &str) -> Option<String> {
+    let head = &content[..content.len().min(SNIFF_LEN)];
+    if head.is_empty() {
+        return from_extension(path).map(str::to_string);
+    }
+    let text = is_text(head);
+    if let Some(mime) = from_signature(head, text) {
+        return Some(mime.to_string());
+    }
+    let mime = if text {
+        from_extension(path)
+            .filter(|mime| is_text_type(mime))
+            .or_else(|| from_text(head))
+            .unwrap_or("text/plain")
+    } else {
+        from_extension(path)
+            .filter(|mime| !is_text_type(mime))
+            .unwrap_or("application/octet-stream")
+    };
+    Some(mime.to_string())
+}
+
+/// The MIME type of a file, reading only its first [`SNIFF_LEN`] bytes
+///
+/// Unreadable files are classified by their path alone.
+pub fn detect_file(fs: &dyn VirtualFs, path: &Path) -> Option<String> {
+    let name = path.to_string_lossy();
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    match fs.open(path) {
+        Ok(file) => match file.take(SNIFF_LEN as u64).read_to_end(&mut head) {
+            Ok(_) => detect(&head, &name),
+            Err(_) => from_extension(&name).map(str::to_string),
+        },
+        Err(_) => from_extension(&name).map(str::to_string),
+    }
+}
+
+/// The MIME type of a file from its extension alone
+pub fn from_extension(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "sh" => "text/x-shellscript",
+        "js" => "text/javascript",
+        "ts" => "text/typescript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "svg" => "image/svg+xml",
+        "yaml" | "yml" => "application/x-yaml",
+        "toml" => "application/toml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// The MIME type of content starting with the signature of a binary format
+fn from_signature(head: &[u8], text: bool) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    if let Some(&(_, _, mime)) = SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| at(*offset, magic))
+    {
+        return Some(mime);
+    }
+    if !text && let Some(&(_, mime)) = BINARY_SIGNATURES.iter().find(|(magic, _)| at(0, magic)) {
+        return Some(mime);
+    }
+    if at(0, b"RIFF") {
+        return RIFF_TYPES
+            .iter()
+            .find(|(kind, _)| at(8, kind))
+            .map(|(_, mime)| *mime);
+    }
+    // ISO base media files (MP4, QuickTime, HEIF) start with an `ftyp` box
+    if at(4, b"ftyp") {
+        return Some(match head.get(8..12) {
+            Some(b"qt  ") => "video/quicktime",
+            Some(b"heic" | b"heix" | b"mif1") => "image/heic",
+            Some(b"avif") => "image/avif",
+            Some(b"M4A ") => "audio/mp4",
+            _ => "video/mp4",
+        });
+    }
+    None
+}
+
+/// The MIME type of text without a known extension, from its first characters
+fn from_text(head: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let lower = text.to_ascii_lowercase();
+    if let Some(shebang) = text.strip_prefix("#!") {
+        let interpreter = shebang.lines().next().unwrap_or_default();
+        return Some(if interpreter.contains("python") {
+            "text/x-python"
+        } else if interpreter.contains("node") {
+            "text/javascript"
+        } else {
+            "text/x-shellscript"
+        });
+    }
+    if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        return Some("text/html");
+    }
+    if lower.starts_with("<svg") || (lower.starts_with("<?xml") && lower.contains("<svg")) {
+        return Some("image/svg+xml");
+    }
+    if lower.starts_with("<?xml") {
+        return Some("application/xml");
+    }
+    let mut chars = text.chars();
+    match chars.next() {
+        Some('{') if matches!(chars.find(|c| !c.is_whitespace()), Some('"' | '}')) => {
+            Some("application/json")
+        }
+        Some('[')
+            if matches!(
+                chars.find(|c| !c.is_whitespace()),
+                Some('{' | '[' | '"' | ']' | '-' | '0'..='9')
+            ) =>
+        {
+            Some("application/json")
+        }
+        _ => None,
+    }
+}
+
+/// Whether leading bytes look like text: no NUL bytes unless marked as UTF-16
+fn is_text(head: &[u8]) -> bool {
+    head.starts_with(b"\xff\xfe") || head.starts_with(b"\xfe\xff") || !head.contains(&0)
+}
+
+fn is_text_type(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json"
+                | "application/xml"
+                | "application/x-yaml"
+                | "application/toml"
+                | "image/svg+xml"
+        )
+}
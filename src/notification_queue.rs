@@ -0,0 +1,284 @@
+//! Notification queue module
+//!
+//! This module provides a bounded outbound notification queue for each connected
+//! consumer (e.g. an SSE stream). Only the notifications of the consumer's session
+//! are queued, so other sessions' traffic does not use up its capacity. When a
+//! consumer falls behind, the configured overflow policy decides what happens instead
+//! of letting progress and log notifications pile up in memory.
+
+use crate::mcp::{JsonRpcNotification, McpServerState};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, broadcast};
+
+/// Default number of notifications a queue holds
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1000;
+
+/// What a full queue does with a new notification
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued notification
+    #[default]
+    DropOldest,
+    /// Keep a single pending `list_changed` notification per method, and discard the
+    /// oldest other notification when full so `list_changed` signals are not lost
+    CoalesceListChanged,
+    /// Close the queue, disconnecting the consumer
+    Disconnect,
+}
+
+/// A bounded queue of notifications for one consumer
+#[derive(Debug)]
+pub struct NotificationQueue {
+    state: Mutex<QueueState>,
+    ready: Notify,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    items: VecDeque<JsonRpcNotification>,
+    closed: bool,
+    dropped: u64,
+}
+
+impl NotificationQueue {
+    /// Create an empty queue
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            state: Mutex::default(),
+            ready: Notify::new(),
+            capacity: capacity.max(1),
+            policy,
+        }
+    }
+
+    /// Queue a notification, applying the overflow policy when the queue is full
+    ///
+    /// Returns false once the queue is closed.
+    pub fn push(&self, notification: JsonRpcNotification) -> bool {
+        let mut state = self.lock();
+        if state.closed {
+            return false;
+        }
+
+        let list_changed = is_list_changed(&notification);
+        if self.policy == OverflowPolicy::CoalesceListChanged
+            && list_changed
+            && state
+                .items
+                .iter()
+                .any(|queued| queued.method == notification.method)
+        {
+            return true;
+        }
+
+        if state.items.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    state.items.pop_front();
+                }
+                OverflowPolicy::CoalesceListChanged => {
+                    match state
+                        .items
+                        .iter()
+                        .position(|queued| !is_list_changed(queued))
+                    {
+                        Some(index) => {
+                            state.items.remove(index);
+                        }
+                        None if list_changed => {
+                            state.items.pop_front();
+                        }
+                        // Only list_changed signals are queued; the new notification is the one lost
+                        None => {
+                            state.dropped += 1;
+                            return true;
+                        }
+                    }
+                }
+                OverflowPolicy::Disconnect => {
+                    state.closed = true;
+                    state.items.clear();
+                    drop(state);
+                    self.ready.notify_one();
+                    return false;
+                }
+            }
+            state.dropped += 1;
+        }
+
+        state.items.push_back(notification);
+        drop(state);
+        self.ready.notify_one();
+        true
+    }
+
+    /// Wait for the next notification
+    ///
+    /// Returns `None` once the queue is closed and drained.
+    pub async fn recv(&self) -> Option<JsonRpcNotification> {
+        loop {
+            {
+                let mut state = self.lock();
+                if let Some(notification) = state.items.pop_front() {
+                    return Some(notification);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    /// Take the next notification without waiting
+    pub fn try_recv(&self) -> Option<JsonRpcNotification> {
+        self.lock().items.pop_front()
+    }
+
+    /// Close the queue; queued notifications can still be received
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_one();
+    }
+
+    /// Whether the queue is closed
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Number of notifications currently queued
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    /// Whether no notifications are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of notifications discarded because the queue was full or its feed fell
+    /// behind the server's notifications
+    pub fn dropped(&self) -> u64 {
+        self.lock().dropped
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        // A panic while holding the lock cannot leave the queue inconsistent
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether a notification signals that a list (tools, resources, prompts) changed
+fn is_list_changed(notification: &JsonRpcNotification) -> bool {
+    notification.method.ends_with("/list_changed")
+}
+
+/// Open a queue fed with the server notifications delivered to a session
+///
+/// `session_id` gives the session the consumer serves when a notification arrives,
+/// which may only be known once it initialized; notifications are filtered with
+/// [`crate::resources::delivers`] before they count against the queue's capacity.
+/// Capacity and overflow policy come from the `[notifications]` config. The queue
+/// stops being fed when it is closed, including by the `disconnect` policy, or dropped.
+pub fn open<F, Fut>(state: &McpServerState, mut session_id: F) -> Arc<NotificationQueue>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Option<String>> + Send,
+{
+    let config = &state.config.notifications;
+    let queue = Arc::new(NotificationQueue::new(
+        config.queue_capacity.unwrap_or(DEFAULT_QUEUE_CAPACITY),
+        config.overflow,
+    ));
+
+    // The feed only holds a weak reference so it ends when the consumer drops the queue
+    let mut notifications = state.subscribe();
+    let feed = Arc::downgrade(&queue);
+    let state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            let received = notifications.recv().await;
+            let Some(queue) = feed.upgrade() else {
+                break;
+            };
+            match received {
+                Ok(notification) => {
+                    let session_id = session_id().await;
+                    if !crate::resources::delivers(&state, session_id.as_deref(), &notification)
+                        .await
+                    {
+                        continue;
+                    }
+                    if !queue.push(notification) {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => queue.lock().dropped += missed,
+                Err(broadcast::error::RecvError::Closed) => {
+                    queue.close();
+                    break;
+                }
+            }
+        }
+    });
+
+    queue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{NotificationsConfig, ServerConfig};
+
+    fn state(capacity: usize, overflow: OverflowPolicy) -> McpServerState {
+        let mut state = McpServerState::new();
+        state.config = Arc::new(ServerConfig {
+            notifications: NotificationsConfig {
+                queue_capacity: Some(capacity),
+                overflow,
+            },
+            ..Default::default()
+        });
+        state
+    }
+
+    #[tokio::test]
+    async fn other_sessions_notifications_do_not_fill_the_queue() {
+        let state = state(2, OverflowPolicy::Disconnect);
+        let queue = open(&state, || std::future::ready(Some("idle".to_string())));
+
+        // Updates of a resource the session did not subscribe to go to other sessions
+        for _ in 0..5 {
+            state.notify(
+                "notifications/resources/updated",
+                Some(serde_json::json!({ "uri": "file:///tmp/log.txt" })),
+            );
+        }
+        state.notify("notifications/tools/list_changed", None);
+
+        let notification = queue.recv().await.unwrap();
+        assert_eq!(notification.method, "notifications/tools/list_changed");
+        assert!(!queue.is_closed());
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn counts_notifications_the_feed_fell_behind_on() {
+        let state = state(1, OverflowPolicy::DropOldest);
+        let queue = open(&state, || std::future::ready(None));
+
+        // The broadcast channel overflows before the feed gets to run
+        for _ in 0..300 {
+            state.notify("notifications/message", None);
+        }
+        queue.recv().await.unwrap();
+        // 256 fit in the server's channel; the rest were missed
+        assert!(queue.dropped() >= 44, "{}", queue.dropped());
+    }
+}
@@ -0,0 +1,921 @@
+//! OAuth module
+//!
+//! This module implements the client side of the MCP authorization spec for servers
+//! reached over Streamable HTTP. When a server answers 401, the client discovers its
+//! authorization server from the protected resource metadata (RFC 9728) and the
+//! authorization server metadata (RFC 8414), registers itself if it has no client id
+//! (RFC 7591), and runs the authorization code flow with PKCE. Tokens are cached in a
+//! [`TokenStore`] per server, refreshed when they expire and sent as bearer tokens.
+//!
+//! How the user approves the request is up to an [`AuthorizationHandler`]:
+//! [`BrowserAuthorization`] opens the browser and receives the redirect on a loopback
+//! port, [`HeadlessAuthorization`] prints the URL and reads the redirected URL pasted
+//! back, for machines without a browser.
+
+use crate::error::McpError;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::Rng;
+use reqwest::Url;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Seconds before its expiry a token is refreshed
+const EXPIRY_MARGIN_SECS: u64 = 30;
+
+/// How long the browser flow waits for the user to approve the request
+const BROWSER_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Protected resource metadata of an MCP server (RFC 9728)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtectedResourceMetadata {
+    pub resource: Option<String>,
+    #[serde(default)]
+    pub authorization_servers: Vec<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+}
+
+/// Metadata of an authorization server (RFC 8414)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationServerMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registration_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub code_challenge_methods_supported: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes_supported: Vec<String>,
+}
+
+/// Tokens issued to the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenSet {
+    pub access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Seconds since the Unix epoch at which the access token expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl TokenSet {
+    /// Whether the access token has expired or is about to
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| now_secs() + EXPIRY_MARGIN_SECS >= expires_at)
+    }
+}
+
+/// What the client keeps for one server: its registration and tokens
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Credentials {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    /// Redirect URI the client id was registered with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization_server: Option<AuthorizationServerMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<TokenSet>,
+}
+
+/// Where credentials are kept between sessions, by server URL
+pub trait TokenStore: Send + Sync {
+    /// The credentials saved for `resource`, if any
+    fn load(&self, resource: &str) -> Result<Option<Credentials>, McpError>;
+
+    /// Save the credentials of `resource`, replacing earlier ones
+    fn save(&self, resource: &str, credentials: &Credentials) -> Result<(), McpError>;
+}
+
+/// Credentials kept in memory, for the life of the process
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore(Mutex<HashMap<String, Credentials>>);
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self, resource: &str) -> Result<Option<Credentials>, McpError> {
+        let credentials = self
+            .0
+            .lock()
+            .map_err(|_| McpError::authorization("Token store lock poisoned"))?;
+        Ok(credentials.get(resource).cloned())
+    }
+
+    fn save(&self, resource: &str, credentials: &Credentials) -> Result<(), McpError> {
+        self.0
+            .lock()
+            .map_err(|_| McpError::authorization("Token store lock poisoned"))?
+            .insert(resource.to_string(), credentials.clone());
+        Ok(())
+    }
+}
+
+/// Credentials kept in a JSON file only the user can read
+#[derive(Debug)]
+pub struct FileTokenStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileTokenStore {
+    /// Keep credentials in the file at `path`, created on the first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Keep credentials in the user's data directory
+    /// (`$XDG_DATA_HOME/mcp-client/oauth.json`, or `~/.local/share/mcp-client/oauth.json`)
+    pub fn open_default() -> Result<Self, McpError> {
+        let base = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let home = std::env::var_os("HOME")
+                    .or_else(|| std::env::var_os("USERPROFILE"))
+                    .ok_or_else(|| {
+                        McpError::authorization("Cannot locate the home directory for the tokens")
+                    })?;
+                PathBuf::from(home).join(".local").join("share")
+            }
+        };
+        Ok(Self::new(base.join("mcp-client").join("oauth.json")))
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_all(&self) -> Result<HashMap<String, Credentials>, McpError> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self, resource: &str) -> Result<Option<Credentials>, McpError> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| McpError::authorization("Token store lock poisoned"))?;
+        Ok(self.read_all()?.remove(resource))
+    }
+
+    fn save(&self, resource: &str, credentials: &Credentials) -> Result<(), McpError> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| McpError::authorization("Token store lock poisoned"))?;
+        let mut all = self.read_all()?;
+        all.insert(resource.to_string(), credentials.clone());
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // Written to a temporary file first so a crash never leaves half a file
+        let temp = self.path.with_extension("json.tmp");
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        file.write_all(serde_json::to_string_pretty(&all)?.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// How the user approves an authorization request
+///
+/// Implement it to acquire tokens some other way, e.g. through an embedded browser.
+pub trait AuthorizationHandler: Send + Sync {
+    /// The URI the authorization server redirects to once the user has decided
+    fn redirect_uri(&self) -> Result<String, McpError>;
+
+    /// Have the user approve the request at `authorization_url`, returning the URL the
+    /// authorization server redirected to, with its `code` and `state` parameters
+    fn authorize(&self, authorization_url: &str) -> Result<String, McpError>;
+}
+
+/// Approve requests in the user's browser, receiving the redirect on a loopback port
+#[derive(Debug)]
+pub struct BrowserAuthorization {
+    listener: TcpListener,
+    port: u16,
+}
+
+impl BrowserAuthorization {
+    /// Receive the redirect on a port chosen by the system
+    pub fn new() -> Result<Self, McpError> {
+        Self::with_port(0)
+    }
+
+    /// Receive the redirect on `port`, for authorization servers that only accept a
+    /// fixed redirect URI
+    pub fn with_port(port: u16) -> Result<Self, McpError> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        Ok(Self { listener, port })
+    }
+}
+
+impl AuthorizationHandler for BrowserAuthorization {
+    fn redirect_uri(&self) -> Result<String, McpError> {
+        Ok(format!("http://127.0.0.1:{}/callback", self.port))
+    }
+
+    fn authorize(&self, authorization_url: &str) -> Result<String, McpError> {
+        eprintln!(
+            "Opening the browser to authorize the MCP client. If it does not open, visit:\n{}",
+            authorization_url
+        );
+        if let Err(e) = open_browser(authorization_url) {
+            eprintln!("Warning: failed to open the browser: {}", e);
+        }
+
+        let deadline = Instant::now() + BROWSER_TIMEOUT;
+        self.listener.set_nonblocking(true)?;
+        loop {
+            let mut stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    if Instant::now() >= deadline {
+                        return Err(McpError::authorization(
+                            "Timed out waiting for the authorization in the browser",
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+            let mut line = String::new();
+            if std::io::BufReader::new(Read::by_ref(&mut stream))
+                .read_line(&mut line)
+                .is_err()
+            {
+                continue;
+            }
+            // e.g. "GET /callback?code=...&state=... HTTP/1.1"
+            let target = line.split_whitespace().nth(1).unwrap_or_default();
+            if !target.starts_with("/callback?") {
+                let _ = stream.write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+                continue;
+            }
+            let body = "<html><body><p>Authorization complete, you can close this window.</p></body></html>";
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            return Ok(format!("http://127.0.0.1:{}{}", self.port, target));
+        }
+    }
+}
+
+/// Open `url` in the user's default browser
+fn open_browser(url: &str) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = std::process::Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    };
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = std::process::Command::new("xdg-open");
+    command
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Approve requests on another device: the URL is printed and the user pastes back
+/// the address the browser was redirected to, even if that page failed to load
+#[derive(Debug, Clone)]
+pub struct HeadlessAuthorization {
+    redirect_uri: String,
+}
+
+impl HeadlessAuthorization {
+    /// Redirect to `http://127.0.0.1/callback`, which the browser cannot load but
+    /// shows in its address bar
+    pub fn new() -> Self {
+        Self::with_redirect_uri("http://127.0.0.1/callback")
+    }
+
+    /// Redirect to `redirect_uri`, such as a page of the authorization server that
+    /// displays the code
+    pub fn with_redirect_uri(redirect_uri: impl Into<String>) -> Self {
+        Self {
+            redirect_uri: redirect_uri.into(),
+        }
+    }
+}
+
+impl Default for HeadlessAuthorization {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuthorizationHandler for HeadlessAuthorization {
+    fn redirect_uri(&self) -> Result<String, McpError> {
+        Ok(self.redirect_uri.clone())
+    }
+
+    fn authorize(&self, authorization_url: &str) -> Result<String, McpError> {
+        eprintln!(
+            "To authorize the MCP client, visit this URL in a browser:\n{}\n\nThen paste the address the browser was redirected to:",
+            authorization_url
+        );
+        let mut line = String::new();
+        std::io::stdin().lock().read_line(&mut line)?;
+        Ok(line.trim().to_string())
+    }
+}
+
+/// Tokens received from a token endpoint (RFC 6749 section 5.1)
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    scope: Option<String>,
+}
+
+/// An error of a token or registration endpoint (RFC 6749 section 5.2)
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// A client registration (RFC 7591)
+#[derive(Debug, Deserialize)]
+struct RegistrationResponse {
+    client_id: String,
+    client_secret: Option<String>,
+}
+
+/// Authorizes an McpClient with the server's authorization server
+///
+/// See [`McpClient::with_oauth`](crate::mcp::McpClient::with_oauth).
+pub struct OAuth {
+    client: Client,
+    handler: Box<dyn AuthorizationHandler>,
+    store: Box<dyn TokenStore>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    client_name: String,
+    scopes: Vec<String>,
+    /// Credentials of the server, once loaded from the store or acquired
+    credentials: Mutex<Option<Credentials>>,
+}
+
+impl OAuth {
+    /// Authorize through `handler`, keeping tokens in memory
+    pub fn new(handler: impl AuthorizationHandler + 'static) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+            handler: Box::new(handler),
+            store: Box::new(MemoryTokenStore::default()),
+            client_id: None,
+            client_secret: None,
+            client_name: "mcp-client".to_string(),
+            scopes: Vec::new(),
+            credentials: Mutex::new(None),
+        }
+    }
+
+    /// Reach the authorization server with the TLS options of the MCP server's client
+    pub(crate) fn use_tls(&mut self, tls: &crate::tls::TlsConfig) -> Result<(), McpError> {
+        self.client = tls
+            .apply(Client::builder().timeout(Duration::from_secs(30)))?
+            .build()?;
+        Ok(())
+    }
+
+    /// Keep tokens in `store`, e.g. a [`FileTokenStore`] to reuse them across sessions
+    pub fn with_token_store(mut self, store: impl TokenStore + 'static) -> Self {
+        self.store = Box::new(store);
+        self
+    }
+
+    /// Use a client registered beforehand instead of registering dynamically
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Authenticate a confidential client at the token endpoint
+    pub fn with_client_secret(mut self, client_secret: impl Into<String>) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    /// Name shown to the user when the client registers dynamically
+    pub fn with_client_name(mut self, client_name: impl Into<String>) -> Self {
+        self.client_name = client_name.into();
+        self
+    }
+
+    /// Ask for these scopes instead of those the server announces
+    pub fn with_scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// A valid access token for the server at `server_url`, refreshing an expired one,
+    /// or `None` if the server has not asked for authorization yet
+    pub fn access_token(&self, server_url: &str) -> Result<Option<String>, McpError> {
+        let resource = canonical_resource(server_url)?;
+        let mut credentials = self.lock_credentials()?;
+        if credentials.is_none() {
+            *credentials = self.store.load(&resource)?;
+        }
+        let Some(current) = credentials.as_mut() else {
+            return Ok(None);
+        };
+        let Some(tokens) = &current.tokens else {
+            return Ok(None);
+        };
+        if !tokens.is_expired() {
+            return Ok(Some(tokens.access_token.clone()));
+        }
+
+        // A refresh that fails leaves the server to ask for a new authorization
+        match self.refresh(&resource, current) {
+            Ok(tokens) => {
+                let access_token = tokens.access_token.clone();
+                current.tokens = Some(tokens);
+                self.store.save(&resource, current)?;
+                Ok(Some(access_token))
+            }
+            Err(_) => {
+                current.tokens = None;
+                self.store.save(&resource, current)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Acquire a new access token after the server at `server_url` refused a request
+    ///
+    /// `challenge` is the `WWW-Authenticate` header of the refusal. A refresh token is
+    /// tried first, then the user is asked to authorize the client.
+    pub fn authorize(&self, server_url: &str, challenge: Option<&str>) -> Result<String, McpError> {
+        let resource = canonical_resource(server_url)?;
+        let mut guard = self.lock_credentials()?;
+        let mut credentials = match guard.take() {
+            Some(credentials) => credentials,
+            None => self.store.load(&resource)?.unwrap_or_default(),
+        };
+
+        let tokens = match credentials.tokens.take() {
+            Some(tokens) if tokens.refresh_token.is_some() => {
+                credentials.tokens = Some(tokens);
+                match self.refresh(&resource, &credentials) {
+                    Ok(tokens) => tokens,
+                    Err(_) => self.authorize_code(&resource, challenge, &mut credentials)?,
+                }
+            }
+            _ => self.authorize_code(&resource, challenge, &mut credentials)?,
+        };
+        let access_token = tokens.access_token.clone();
+        credentials.tokens = Some(tokens);
+        self.store.save(&resource, &credentials)?;
+        *guard = Some(credentials);
+        Ok(access_token)
+    }
+
+    fn lock_credentials(&self) -> Result<std::sync::MutexGuard<'_, Option<Credentials>>, McpError> {
+        self.credentials
+            .lock()
+            .map_err(|_| McpError::authorization("Credentials lock poisoned"))
+    }
+
+    /// Run the authorization code flow with PKCE, registering the client if needed
+    fn authorize_code(
+        &self,
+        resource: &str,
+        challenge: Option<&str>,
+        credentials: &mut Credentials,
+    ) -> Result<TokenSet, McpError> {
+        let (server, resource_scopes) = self.discover(resource, challenge)?;
+        let redirect_uri = self.handler.redirect_uri()?;
+        self.ensure_client(&server, &redirect_uri, credentials)?;
+        let client_id = credentials.client_id.clone().unwrap_or_default();
+
+        if !server.code_challenge_methods_supported.is_empty()
+            && !server
+                .code_challenge_methods_supported
+                .iter()
+                .any(|m| m == "S256")
+        {
+            return Err(McpError::authorization(format!(
+                "Authorization server {} does not support PKCE with S256",
+                server.issuer
+            )));
+        }
+        let verifier = random_string(64);
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let state = random_string(32);
+
+        let scope = if !self.scopes.is_empty() {
+            self.scopes.join(" ")
+        } else if let Some(scope) = challenge.and_then(|c| challenge_param(c, "scope")) {
+            scope
+        } else {
+            resource_scopes.join(" ")
+        };
+        let mut url = Url::parse(&server.authorization_endpoint).map_err(|e| {
+            McpError::authorization(format!("Invalid authorization endpoint: {}", e))
+        })?;
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &client_id)
+                .append_pair("redirect_uri", &redirect_uri)
+                .append_pair("code_challenge", &code_challenge)
+                .append_pair("code_challenge_method", "S256")
+                .append_pair("state", &state)
+                .append_pair("resource", resource);
+            if !scope.is_empty() {
+                query.append_pair("scope", &scope);
+            }
+        }
+
+        let redirected = self.handler.authorize(url.as_str())?;
+        let code = code_from_redirect(&redirected, &redirect_uri, &state)?;
+        let response = self.token_request(
+            &server,
+            credentials,
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", &redirect_uri),
+                ("code_verifier", &verifier),
+                ("resource", resource),
+            ],
+        )?;
+        Ok(to_token_set(response, None))
+    }
+
+    /// Trade the refresh token for new tokens
+    fn refresh(&self, resource: &str, credentials: &Credentials) -> Result<TokenSet, McpError> {
+        let refresh_token = credentials
+            .tokens
+            .as_ref()
+            .and_then(|t| t.refresh_token.clone())
+            .ok_or_else(|| McpError::authorization("No refresh token"))?;
+        let server = match &credentials.authorization_server {
+            Some(server) => server.clone(),
+            None => self.discover(resource, None)?.0,
+        };
+        let response = self.token_request(
+            &server,
+            credentials,
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+                ("resource", resource),
+            ],
+        )?;
+        Ok(to_token_set(response, Some(refresh_token)))
+    }
+
+    /// POST a form to the token endpoint, authenticating the client
+    fn token_request(
+        &self,
+        server: &AuthorizationServerMetadata,
+        credentials: &Credentials,
+        form: &[(&str, &str)],
+    ) -> Result<TokenResponse, McpError> {
+        let client_id = credentials.client_id.as_deref().unwrap_or_default();
+        let mut request = self.client.post(&server.token_endpoint);
+        let mut form = form.to_vec();
+        match &credentials.client_secret {
+            Some(secret) => request = request.basic_auth(client_id, Some(secret)),
+            None => form.push(("client_id", client_id)),
+        }
+        let response = request.form(&form).send()?;
+        let status = response.status();
+        let body = response.text()?;
+        if !status.is_success() {
+            return Err(McpError::authorization(format!(
+                "Token request to {} failed: {}",
+                server.token_endpoint,
+                endpoint_error(status, &body)
+            )));
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Use the configured client id, a registration made earlier for the same redirect
+    /// URI, or register the client dynamically
+    fn ensure_client(
+        &self,
+        server: &AuthorizationServerMetadata,
+        redirect_uri: &str,
+        credentials: &mut Credentials,
+    ) -> Result<(), McpError> {
+        credentials.authorization_server = Some(server.clone());
+        if let Some(client_id) = &self.client_id {
+            credentials.client_id = Some(client_id.clone());
+            credentials.client_secret = self.client_secret.clone();
+            return Ok(());
+        }
+        if credentials.client_id.is_some()
+            && credentials.redirect_uri.as_deref() == Some(redirect_uri)
+        {
+            return Ok(());
+        }
+        let Some(endpoint) = &server.registration_endpoint else {
+            return Err(McpError::authorization(format!(
+                "Authorization server {} does not support dynamic client registration; configure a client id",
+                server.issuer
+            )));
+        };
+
+        let response = self
+            .client
+            .post(endpoint)
+            .json(&serde_json::json!({
+                "client_name": self.client_name,
+                "redirect_uris": [redirect_uri],
+                "grant_types": ["authorization_code", "refresh_token"],
+                "response_types": ["code"],
+                "token_endpoint_auth_method": "none",
+            }))
+            .send()?;
+        let status = response.status();
+        let body = response.text()?;
+        if !status.is_success() {
+            return Err(McpError::authorization(format!(
+                "Client registration at {} failed: {}",
+                endpoint,
+                endpoint_error(status, &body)
+            )));
+        }
+        let registration: RegistrationResponse = serde_json::from_str(&body)?;
+        credentials.client_id = Some(registration.client_id);
+        credentials.client_secret = registration.client_secret;
+        credentials.redirect_uri = Some(redirect_uri.to_string());
+        Ok(())
+    }
+
+    /// Find the authorization server of `resource` and the scopes it announces
+    ///
+    /// The protected resource metadata is read from the URL in the challenge, or else
+    /// from its well-known locations. Servers without one are taken to be their own
+    /// authorization server.
+    fn discover(
+        &self,
+        resource: &str,
+        challenge: Option<&str>,
+    ) -> Result<(AuthorizationServerMetadata, Vec<String>), McpError> {
+        let resource_url = Url::parse(resource)
+            .map_err(|e| McpError::authorization(format!("Invalid server URL: {}", e)))?;
+        let origin = resource_url.origin().ascii_serialization();
+        let path = resource_url.path().trim_end_matches('/');
+
+        let mut candidates = Vec::new();
+        if let Some(url) = challenge.and_then(|c| challenge_param(c, "resource_metadata")) {
+            candidates.push(url);
+        }
+        if !path.is_empty() {
+            candidates.push(format!(
+                "{}/.well-known/oauth-protected-resource{}",
+                origin, path
+            ));
+        }
+        candidates.push(format!("{}/.well-known/oauth-protected-resource", origin));
+        let metadata: Option<ProtectedResourceMetadata> =
+            candidates.iter().find_map(|url| self.get_json(url));
+
+        let (issuer, scopes) = match metadata {
+            Some(metadata) => {
+                let issuer = metadata
+                    .authorization_servers
+                    .first()
+                    .cloned()
+                    .ok_or_else(|| {
+                        McpError::authorization(format!(
+                            "Server {} names no authorization server",
+                            resource
+                        ))
+                    })?;
+                (issuer, metadata.scopes_supported)
+            }
+            None => (origin, Vec::new()),
+        };
+        Ok((self.authorization_server(&issuer)?, scopes))
+    }
+
+    /// Read the metadata of the authorization server `issuer`, trying the OAuth and
+    /// OpenID Connect well-known locations, or assume the default endpoints
+    fn authorization_server(&self, issuer: &str) -> Result<AuthorizationServerMetadata, McpError> {
+        let url = Url::parse(issuer).map_err(|e| {
+            McpError::authorization(format!("Invalid authorization server '{}': {}", issuer, e))
+        })?;
+        let origin = url.origin().ascii_serialization();
+        let path = url.path().trim_end_matches('/');
+        let candidates = if path.is_empty() {
+            vec![
+                format!("{}/.well-known/oauth-authorization-server", origin),
+                format!("{}/.well-known/openid-configuration", origin),
+            ]
+        } else {
+            vec![
+                format!("{}/.well-known/oauth-authorization-server{}", origin, path),
+                format!("{}/.well-known/openid-configuration{}", origin, path),
+                format!("{}{}/.well-known/openid-configuration", origin, path),
+            ]
+        };
+        if let Some(metadata) = candidates.iter().find_map(|url| self.get_json(url)) {
+            return Ok(metadata);
+        }
+        Ok(AuthorizationServerMetadata {
+            issuer: issuer.to_string(),
+            authorization_endpoint: format!("{}/authorize", origin),
+            token_endpoint: format!("{}/token", origin),
+            registration_endpoint: Some(format!("{}/register", origin)),
+            code_challenge_methods_supported: Vec::new(),
+            scopes_supported: Vec::new(),
+        })
+    }
+
+    /// GET a JSON document, or `None` if it is missing or invalid
+    fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Option<T> {
+        let response = self
+            .client
+            .get(url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response.json().ok()
+    }
+}
+
+impl std::fmt::Debug for OAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuth")
+            .field("client_id", &self.client_id)
+            .field("client_name", &self.client_name)
+            .field("scopes", &self.scopes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The server URL as the resource indicator of RFC 8707: no fragment and no trailing
+/// slash after the host
+fn canonical_resource(server_url: &str) -> Result<String, McpError> {
+    let mut url = Url::parse(server_url)
+        .map_err(|e| McpError::authorization(format!("Invalid server URL: {}", e)))?;
+    url.set_fragment(None);
+    let resource = url.to_string();
+    Ok(match resource.strip_suffix('/') {
+        Some(trimmed) if url.path() == "/" && url.query().is_none() => trimmed.to_string(),
+        _ => resource,
+    })
+}
+
+/// A parameter of a `WWW-Authenticate: Bearer` challenge
+fn challenge_param(challenge: &str, name: &str) -> Option<String> {
+    let params = challenge
+        .trim_start()
+        .strip_prefix("Bearer")
+        .or_else(|| challenge.trim_start().strip_prefix("bearer"))?;
+    let mut rest = params;
+    while let Some(start) = rest.find(name) {
+        let before = rest[..start].chars().last();
+        let after = rest[start + name.len()..].trim_start();
+        rest = &rest[start + name.len()..];
+        if !matches!(before, None | Some(' ' | ',')) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        return Some(match value.strip_prefix('"') {
+            Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+            None => value
+                .split([',', ' '])
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    None
+}
+
+/// The authorization code in the URL the authorization server redirected to, after
+/// checking its state
+fn code_from_redirect(
+    redirected: &str,
+    redirect_uri: &str,
+    state: &str,
+) -> Result<String, McpError> {
+    let url = if redirected.starts_with('?') {
+        Url::parse(&format!("{}{}", redirect_uri, redirected))
+    } else {
+        Url::parse(redirected)
+    }
+    .map_err(|e| McpError::authorization(format!("Invalid redirect URL: {}", e)))?;
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    if let Some(error) = params.get("error") {
+        return Err(McpError::authorization(format!(
+            "Authorization refused: {}{}",
+            error,
+            params
+                .get("error_description")
+                .map(|d| format!(" ({})", d))
+                .unwrap_or_default()
+        )));
+    }
+    if params.get("state").map(String::as_str) != Some(state) {
+        return Err(McpError::authorization(
+            "The redirect does not belong to this authorization request (state mismatch)",
+        ));
+    }
+    params
+        .get("code")
+        .cloned()
+        .ok_or_else(|| McpError::authorization("No authorization code in the redirect URL"))
+}
+
+/// Describe the error answer of a token or registration endpoint
+fn endpoint_error(status: reqwest::StatusCode, body: &str) -> String {
+    match serde_json::from_str::<ErrorResponse>(body) {
+        Ok(error) => match error.error_description {
+            Some(description) => format!("{} ({})", error.error, description),
+            None => error.error,
+        },
+        Err(_) => format!("HTTP error {}: {}", status, body),
+    }
+}
+
+fn to_token_set(response: TokenResponse, previous_refresh_token: Option<String>) -> TokenSet {
+    TokenSet {
+        access_token: response.access_token,
+        refresh_token: response.refresh_token.or(previous_refresh_token),
+        expires_at: response.expires_in.map(|secs| now_secs() + secs),
+        scope: response.scope,
+    }
+}
+
+/// A random string of URL-safe characters, for PKCE verifiers and states
+fn random_string(len: usize) -> String {
+    rand::rng()
+        .sample_iter(rand::distr::Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -2,10 +2,20 @@
 //!
 //! This module provides functionality for working with Ollama models.
 
+use crate::conversation_store::ConversationStore;
+use crate::deadline::Deadline;
+use crate::error::{McpError, TransportError};
+use crate::interaction_log::InteractionLog;
+use crate::mcp::McpTool;
+use crate::pacing::{Pacing, TokenBucket};
+use crate::tokenizer::Tokenizer;
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// A single message in a chat conversation
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -73,6 +83,57 @@ impl OllamaTool {
             function,
         }
     }
+
+    /// Create a function tool from an MCP tool definition
+    ///
+    /// Top-level properties of the tool's input schema are carried over; nested
+    /// schemas are reduced to their type and description.
+    pub fn from_mcp_tool(tool: &McpTool) -> Self {
+        let mut parameters = OllamaParameters::new();
+
+        if let Some(schema) = &tool.input_schema {
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                for (name, property) in properties {
+                    let string_field = |field: &str| {
+                        property
+                            .get(field)
+                            .and_then(Value::as_str)
+                            .map(str::to_string)
+                    };
+                    let values = property
+                        .get("enum")
+                        .and_then(Value::as_array)
+                        .map(|values| {
+                            values
+                                .iter()
+                                .filter_map(Value::as_str)
+                                .map(str::to_string)
+                                .collect()
+                        });
+                    parameters = parameters.add_property(
+                        name.clone(),
+                        OllamaProperty {
+                            prop_type: string_field("type").unwrap_or_else(|| "string".to_string()),
+                            description: string_field("description").unwrap_or_default(),
+                            r#enum: values,
+                        },
+                    );
+                }
+            }
+
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for name in required.iter().filter_map(Value::as_str) {
+                    parameters = parameters.add_required(name);
+                }
+            }
+        }
+
+        Self::function(OllamaFunction::new(
+            tool.name.clone(),
+            tool.description.clone().unwrap_or_default(),
+            parameters,
+        ))
+    }
 }
 
 /// Function definition for Ollama tools
@@ -107,6 +168,12 @@ pub struct OllamaParameters {
     pub required: Vec<String>,
 }
 
+impl Default for OllamaParameters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl OllamaParameters {
     /// Create a new parameters schema
     pub fn new() -> Self {
@@ -199,6 +266,46 @@ pub struct ChatRequest {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tools: Vec<OllamaTool>,
     pub stream: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<ChatOptions>,
+}
+
+/// Model options of a chat request
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChatOptions {
+    /// Context window the model is loaded with, in tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Most tokens to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<u32>,
+    /// Sequences that end the generation when the model produces them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+/// What a model supports, as reported by Ollama's /api/show
+///
+/// Fields are `None` when the Ollama version does not report them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Whether the model is trained to use tool definitions
+    pub tools: Option<bool>,
+    /// Whether the model accepts images
+    pub vision: Option<bool>,
+    /// Tokens the model was trained to attend to
+    pub context_length: Option<u64>,
+}
+
+/// Ollama /api/show response
+#[derive(Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    capabilities: Option<Vec<String>>,
+    #[serde(default)]
+    model_info: HashMap<String, Value>,
 }
 
 /// Response from the /api/chat endpoint
@@ -208,6 +315,10 @@ pub struct ChatResponse {
     pub created_at: String,
     pub message: ChatMessage,
     pub done: bool,
+    /// Why generation stopped: "stop" at the end of the answer or a stop sequence,
+    /// "length" at the token limit
+    #[serde(default)]
+    pub done_reason: Option<String>,
     #[serde(default)]
     pub total_duration: Option<u64>,
     #[serde(default)]
@@ -228,9 +339,10 @@ pub struct Ollama {
     client: Client,
     // Fields copied from OllamaConfig
     model: String,
-    temperature: Option<f32>,
-    max_tokens: Option<u32>,
     tools: Vec<OllamaTool>,
+    timeout: Option<Duration>,
+    /// Temperature and token limit of the config, sent with every request
+    options: Option<ChatOptions>,
 }
 
 impl Ollama {
@@ -240,10 +352,10 @@ impl Ollama {
             base_url: "http://localhost:11434".to_string(),
             client: Client::new(),
             // Copy fields from OllamaConfig
+            options: config.options(),
             model: config.model,
-            temperature: config.temperature,
-            max_tokens: config.max_tokens,
             tools,
+            timeout: None,
         }
     }
     pub fn default(model: impl Into<String>) -> Self {
@@ -251,9 +363,61 @@ impl Ollama {
             base_url: "http://localhost:11434".to_string(),
             client: Client::new(),
             model: model.into(),
-            temperature: None,
-            max_tokens: None,
             tools: Vec::new(),
+            timeout: None,
+            options: None,
+        }
+    }
+
+    /// Use an Ollama server other than the local default
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set a timeout for each request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Generate a completion for a message with a system prompt, using the client's model
+    ///
+    /// # Arguments
+    /// * `system` - Instructions for the model
+    /// * `message` - The message content to send to the model
+    ///
+    /// # Returns
+    /// Result containing the assistant's reply or an error
+    pub fn chat_with_system(
+        &self,
+        system: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Result<String, McpError> {
+        let request_payload = ChatRequest {
+            model: self.model.clone(),
+            messages: vec![ChatMessage::system(system), ChatMessage::user(message)],
+            tools: Vec::new(),
+            stream: false,
+            options: self.options.clone(),
+        };
+
+        let url = format!("{}/api/chat", self.base_url);
+
+        let mut request = self.client.post(&url).json(&request_payload);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = send(&self.base_url, request)?;
+
+        if response.status().is_success() {
+            let chat_response: ChatResponse = response.json()?;
+
+            Ok(chat_response.message.content)
+        } else {
+            let status = response.status();
+            let body = response.text()?;
+            Err(TransportError::Status { status, body }.into())
         }
     }
 
@@ -269,7 +433,7 @@ impl Ollama {
         &self,
         message: impl Into<String>,
         model: impl Into<String>,
-    ) -> Result<ChatResponse, Box<dyn std::error::Error>> {
+    ) -> Result<ChatResponse, McpError> {
         let user_message = ChatMessage::user(message);
 
         let request_payload = ChatRequest {
@@ -277,19 +441,24 @@ impl Ollama {
             messages: vec![user_message],
             tools: self.tools.clone(), // No tools by default
             stream: false,             // Disable streaming for simplicity
+            options: self.options.clone(),
         };
 
         let url = format!("{}/api/chat", self.base_url);
 
-        let response = self.client.post(&url).json(&request_payload).send()?;
+        let response = send(
+            &self.base_url,
+            self.client.post(&url).json(&request_payload),
+        )?;
 
         if response.status().is_success() {
             let chat_response: ChatResponse = response.json()?;
 
             Ok(chat_response)
         } else {
-            let error_text = response.text()?;
-            Err(format!("Request failed with status : {}", error_text).into())
+            let status = response.status();
+            let body = response.text()?;
+            Err(TransportError::Status { status, body }.into())
         }
     }
 
@@ -307,7 +476,7 @@ impl Ollama {
         message: impl Into<String>,
         model: impl Into<String>,
         tools: Vec<OllamaTool>,
-    ) -> Result<ChatResponse, Box<dyn std::error::Error>> {
+    ) -> Result<ChatResponse, McpError> {
         let system_message = ChatMessage::system(
             "You are Granite, developed by IBM. You are a helpful assistant with tools. When a tool is required to answer the user's query, respond only with <|tool_call|> followed by a JSON list of tools used. If a tool does not exist in the provided list of tools, notify the user that you do not have the ability to fulfill the request.<|end_of_text|>",
         );
@@ -318,21 +487,126 @@ impl Ollama {
             messages: vec![system_message, user_message],
             tools,
             stream: false, // Disable streaming for simplicity
+            options: self.options.clone(),
         };
 
         let url = format!("{}/api/chat", self.base_url);
 
-        let response = self.client.post(&url).json(&request_payload).send()?;
+        let response = send(
+            &self.base_url,
+            self.client.post(&url).json(&request_payload),
+        )?;
 
         if response.status().is_success() {
             let chat_response: ChatResponse = response.json()?;
 
             Ok(chat_response)
         } else {
-            let error_text = response.text()?;
-            Err(format!("Request failed with status : {}", error_text).into())
+            let status = response.status();
+            let body = response.text()?;
+            Err(TransportError::Status { status, body }.into())
+        }
+    }
+
+    /// Send a whole conversation to the client's model, offering it the client's tools
+    pub fn chat_messages(&self, messages: Vec<ChatMessage>) -> Result<ChatResponse, McpError> {
+        self.post_chat(messages, self.options.clone())
+    }
+
+    /// Like [`Ollama::chat_messages`], with model options such as the temperature
+    ///
+    /// Options left unset fall back to the temperature and token limit of the config.
+    pub fn chat_with_options(
+        &self,
+        messages: Vec<ChatMessage>,
+        mut options: ChatOptions,
+    ) -> Result<ChatResponse, McpError> {
+        if let Some(defaults) = &self.options {
+            options.temperature = options.temperature.or(defaults.temperature);
+            options.num_predict = options.num_predict.or(defaults.num_predict);
         }
+        self.post_chat(messages, Some(options))
     }
+
+    fn post_chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        options: Option<ChatOptions>,
+    ) -> Result<ChatResponse, McpError> {
+        let request_payload = ChatRequest {
+            model: self.model.clone(),
+            messages,
+            tools: self.tools.clone(),
+            stream: false,
+            options,
+        };
+
+        let url = format!("{}/api/chat", self.base_url);
+
+        let mut request = self.client.post(&url).json(&request_payload);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = send(&self.base_url, request)?;
+
+        if response.status().is_success() {
+            Ok(response.json()?)
+        } else {
+            let status = response.status();
+            let body = response.text()?;
+            Err(TransportError::Status { status, body }.into())
+        }
+    }
+
+    /// Names of the models pulled on the server, such as `llama3:latest`
+    pub fn list_models(&self) -> Result<Vec<String>, McpError> {
+        let url = format!("{}/api/tags", self.base_url);
+        let mut request = self.client.get(&url);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = send(&self.base_url, request).map_err(|e| {
+            McpError::connection(format!("Ollama at {} is unreachable: {}", self.base_url, e))
+        })?;
+        if !response.status().is_success() {
+            return Err(McpError::connection(format!(
+                "Ollama at {} returned {}",
+                self.base_url,
+                response.status()
+            )));
+        }
+
+        let tags: TagsResponse = response.json()?;
+        Ok(tags.models.into_iter().map(|model| model.name).collect())
+    }
+
+    /// Check that the server is reachable and has the client's model
+    pub fn check_model(&self) -> Result<(), McpError> {
+        // Models pulled without a tag are listed as `<name>:latest`
+        let found = self
+            .list_models()?
+            .iter()
+            .any(|name| *name == self.model || name.strip_suffix(":latest") == Some(&self.model));
+        if found {
+            Ok(())
+        } else {
+            Err(McpError::Model(format!(
+                "Model '{}' is not available on Ollama at {}; run `ollama pull {}`",
+                self.model, self.base_url, self.model
+            )))
+        }
+    }
+}
+
+/// Ollama /api/tags response
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<ModelTag>,
+}
+
+#[derive(Deserialize)]
+struct ModelTag {
+    name: String,
 }
 
 /// Configuration for Ollama requests
@@ -364,6 +638,18 @@ impl OllamaConfig {
         self.max_tokens = Some(max_tokens);
         self
     }
+
+    /// The model options requests are sent with, if any are set
+    fn options(&self) -> Option<ChatOptions> {
+        if self.temperature.is_none() && self.max_tokens.is_none() {
+            return None;
+        }
+        Some(ChatOptions {
+            temperature: self.temperature.map(f64::from),
+            num_predict: self.max_tokens,
+            ..Default::default()
+        })
+    }
 }
 
 /// Chat session that maintains conversation history
@@ -373,20 +659,196 @@ pub struct ChatSession {
     pub model: String,
     tools: Vec<OllamaTool>,
     messages: Vec<ChatMessage>,
+    /// Paces model requests, when limited
+    model_calls: Option<TokenBucket>,
+    max_tool_calls_per_turn: Option<u32>,
+    tool_calls_this_turn: u32,
+    deadline: Option<Deadline>,
+    log: Option<InteractionLog>,
+    /// Messages already recorded in the interaction log
+    logged_messages: usize,
+    tokenizer: Arc<dyn Tokenizer>,
+    /// Tokens the conversation is trimmed to before each request
+    context_budget: Option<usize>,
+    options: Option<ChatOptions>,
+    /// Store every message is saved to, with the id of the conversation
+    store: Option<(ConversationStore, String)>,
 }
 
+/// Tokens counted for the role and delimiters of each message
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
 impl ChatSession {
     /// Create a new chat session
-    pub fn New(model: impl Into<String>, tools: Vec<OllamaTool>) -> Self {
+    pub fn new(model: impl Into<String>, tools: Vec<OllamaTool>) -> Self {
+        let model = model.into();
         Self {
             client: Client::new(),
             base_url: "http://localhost:11434".to_string(),
-            model: model.into(),
+            tokenizer: crate::tokenizer::for_model(&model),
+            model,
             tools,
             messages: Vec::new(),
+            model_calls: None,
+            max_tool_calls_per_turn: None,
+            tool_calls_this_turn: 0,
+            deadline: None,
+            log: None,
+            logged_messages: 0,
+            context_budget: None,
+            options: None,
+            store: None,
+        }
+    }
+
+    /// Create a new chat session
+    #[deprecated(note = "renamed to `ChatSession::new`")]
+    #[allow(non_snake_case)]
+    pub fn New(model: impl Into<String>, tools: Vec<OllamaTool>) -> Self {
+        Self::new(model, tools)
+    }
+
+    /// Ask Ollama what the session's model supports
+    pub fn detect_capabilities(&self) -> Result<ModelCapabilities, McpError> {
+        let url = format!("{}/api/show", self.base_url);
+        let request = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "model": self.model }));
+        let response = send(&self.base_url, request)?;
+        if !response.status().is_success() {
+            return Err(McpError::Model(format!(
+                "Ollama could not describe model '{}': {}",
+                self.model,
+                response.text()?
+            )));
+        }
+
+        let show: ShowResponse = response.json()?;
+        let has = |capability: &str| {
+            show.capabilities
+                .as_ref()
+                .map(|capabilities| capabilities.iter().any(|c| c == capability))
+        };
+        // The length is reported under the model's architecture, e.g. llama.context_length
+        let context_length = show
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64());
+        Ok(ModelCapabilities {
+            tools: has("tools"),
+            vision: has("vision"),
+            context_length,
+        })
+    }
+
+    /// Load the model with a context window of `tokens`, instead of Ollama's default
+    pub fn set_context_length(&mut self, tokens: Option<u64>) {
+        self.options = tokens.map(|tokens| ChatOptions {
+            num_ctx: Some(tokens),
+            ..Default::default()
+        });
+    }
+
+    /// Keep the conversation within `max_tokens` of the model's tokenizer
+    ///
+    /// Before each request the oldest user, assistant and tool messages are dropped
+    /// until the conversation fits; system messages and the message being sent are
+    /// kept.
+    pub fn with_context_budget(mut self, max_tokens: usize) -> Self {
+        self.context_budget = Some(max_tokens);
+        self
+    }
+
+    /// Tokens of the conversation so far, as counted by the model's tokenizer
+    pub fn context_tokens(&self) -> usize {
+        self.messages
+            .iter()
+            .map(|message| self.message_tokens(message))
+            .sum()
+    }
+
+    fn message_tokens(&self, message: &ChatMessage) -> usize {
+        self.tokenizer.count_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS
+    }
+
+    /// Drop the oldest messages that can go until the conversation fits the budget
+    fn trim_to_budget(&mut self) {
+        let Some(budget) = self.context_budget else {
+            return;
+        };
+        let mut tokens = self.context_tokens();
+        let mut index = 0;
+        while tokens > budget && index + 1 < self.messages.len() {
+            if self.messages[index].role == "system" {
+                index += 1;
+                continue;
+            }
+            let message = self.messages.remove(index);
+            tokens -= self.message_tokens(&message);
+            if index < self.logged_messages {
+                self.logged_messages -= 1;
+            }
         }
     }
 
+    /// Record every model request and its response in an interaction log
+    pub fn with_interaction_log(mut self, log: InteractionLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Save every message of the session to the conversation `conversation_id`
+    ///
+    /// The store keeps the whole conversation, including messages later dropped to
+    /// fit the context budget.
+    pub fn with_conversation_store(
+        mut self,
+        store: ConversationStore,
+        conversation_id: impl Into<String>,
+    ) -> Self {
+        self.store = Some((store, conversation_id.into()));
+        self
+    }
+
+    /// Limit the rate of model requests and the tool calls of each turn
+    ///
+    /// Once the requests of a minute are spent, [`ChatSession::send`] waits for the
+    /// next one to be allowed instead of failing.
+    pub fn with_pacing(mut self, pacing: &Pacing) -> Self {
+        self.model_calls = pacing
+            .max_model_calls_per_minute
+            .map(TokenBucket::per_minute);
+        self.max_tool_calls_per_turn = pacing.max_tool_calls_per_turn;
+        self
+    }
+
+    /// Start a turn of the conversation, renewing its tool call budget
+    pub fn begin_turn(&mut self) {
+        self.tool_calls_this_turn = 0;
+    }
+
+    /// Bound the following model requests by `deadline`
+    ///
+    /// A request is given only the time left and none is sent once the deadline
+    /// passed; `None` removes the bound.
+    pub fn set_deadline(&mut self, deadline: Option<Deadline>) {
+        self.deadline = deadline;
+    }
+
+    /// Count a tool call of the current turn, returning false once the budget is spent
+    pub fn allow_tool_call(&mut self) -> bool {
+        if self
+            .max_tool_calls_per_turn
+            .is_some_and(|max| self.tool_calls_this_turn >= max)
+        {
+            return false;
+        }
+        self.tool_calls_this_turn += 1;
+        true
+    }
+
     /// Send a message and maintain chat history
     ///
     /// # Arguments
@@ -394,40 +856,190 @@ impl ChatSession {
     ///
     /// # Returns
     /// Result containing the ChatResponse or an error
-    pub fn send(
+    pub fn send(&mut self, message: impl Into<String>) -> Result<ChatResponse, McpError> {
+        self.send_with(message.into(), None)
+    }
+
+    /// Send a message like [`ChatSession::send`], passing the text of the answer to
+    /// `on_text` piece by piece as the model generates it
+    ///
+    /// The response returned is the whole answer, as `send` returns it.
+    pub fn send_streaming(
         &mut self,
         message: impl Into<String>,
-    ) -> Result<ChatResponse, Box<dyn std::error::Error>> {
-        let user_message = ChatMessage::user(message);
-        self.messages.push(user_message);
+        mut on_text: impl FnMut(&str),
+    ) -> Result<ChatResponse, McpError> {
+        self.send_with(message.into(), Some(&mut on_text))
+    }
+
+    fn send_with(
+        &mut self,
+        message: String,
+        on_text: Option<&mut dyn FnMut(&str)>,
+    ) -> Result<ChatResponse, McpError> {
+        if let Some(deadline) = &self.deadline {
+            deadline.check()?;
+        }
+        self.push(ChatMessage::user(message));
+        self.trim_to_budget();
+        if let Some(model_calls) = &mut self.model_calls {
+            model_calls.acquire();
+        }
+        let timeout = self.deadline.map(|deadline| deadline.check()).transpose()?;
 
+        let started = Instant::now();
+        let result = self.request(timeout, on_text);
+        if let Some(log) = &self.log {
+            let sent = self.messages.len() - usize::from(result.is_ok());
+            log.model_exchange(
+                &self.model,
+                &self.messages[self.logged_messages.min(sent)..sent],
+                result.as_ref().map_err(|e| e as &dyn std::error::Error),
+                started.elapsed(),
+            );
+            self.logged_messages = self.messages.len();
+        }
+        result
+    }
+
+    /// Send the conversation to the model, adding its response to the history
+    fn request(
+        &mut self,
+        timeout: Option<Duration>,
+        on_text: Option<&mut dyn FnMut(&str)>,
+    ) -> Result<ChatResponse, McpError> {
         let request_payload = ChatRequest {
             model: self.model.clone(),
             messages: self.messages.clone(),
             tools: self.tools.clone(),
-            stream: false,
+            stream: on_text.is_some(),
+            options: self.options.clone(),
         };
 
         let url = format!("{}/api/chat", self.base_url);
 
-        let response = self.client.post(&url).json(&request_payload).send()?;
+        let response = send_within(
+            &self.base_url,
+            self.client.post(&url).json(&request_payload),
+            timeout,
+        )?;
 
         if response.status().is_success() {
-            let chat_response: ChatResponse = response.json()?;
+            let chat_response = match on_text {
+                Some(on_text) => read_stream(response, on_text)?,
+                None => response.json()?,
+            };
 
             // Add the assistant's response to the message history
-            self.messages.push(chat_response.message.clone());
+            self.push(chat_response.message.clone());
 
             Ok(chat_response)
         } else {
-            let error_text = response.text()?;
-            Err(format!("Request failed with status : {}", error_text).into())
+            let status = response.status();
+            let body = response.text()?;
+            Err(TransportError::Status { status, body }.into())
         }
     }
 
+    /// Replace the tools offered to the model, for the following messages
+    pub fn set_tools(&mut self, tools: Vec<OllamaTool>) {
+        self.tools = tools;
+    }
+
     /// Add a system message to the conversation
     pub fn add_system_message(&mut self, content: impl Into<String>) {
-        let system_message = ChatMessage::system(content);
-        self.messages.push(system_message);
+        self.push(ChatMessage::system(content));
+    }
+
+    /// Add a message to the history and save it; failures to save are reported but do
+    /// not stop the conversation
+    fn push(&mut self, message: ChatMessage) {
+        if let Some((store, conversation_id)) = &self.store
+            && let Err(e) = store.append(conversation_id, &message)
+        {
+            eprintln!("Failed to save message to the conversation history: {}", e);
+        }
+        self.messages.push(message);
+    }
+}
+
+/// Assemble a streamed /api/chat response, one JSON object per line, passing the text
+/// of each piece to `on_text`
+///
+/// Tool calls arrive whole in one of the pieces; the last piece carries the statistics.
+fn read_stream(
+    response: reqwest::blocking::Response,
+    on_text: &mut dyn FnMut(&str),
+) -> Result<ChatResponse, McpError> {
+    let mut content = String::new();
+    let mut tool_calls: Vec<OllamaToolCall> = Vec::new();
+    for line in BufReader::new(response).lines() {
+        let line = line.map_err(TransportError::Io)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut piece: ChatResponse = serde_json::from_str(&line)?;
+        if !piece.message.content.is_empty() {
+            on_text(&piece.message.content);
+            content.push_str(&piece.message.content);
+        }
+        tool_calls.extend(piece.message.tool_calls.take().unwrap_or_default());
+        if piece.done {
+            piece.message.content = content;
+            piece.message.tool_calls = (!tool_calls.is_empty()).then_some(tool_calls);
+            return Ok(piece);
+        }
+    }
+    Err(
+        TransportError::Connection("The model's answer ended before it was done".to_string())
+            .into(),
+    )
+}
+
+/// Send a request through the circuit breaker of the Ollama server
+///
+/// Connection errors, timeouts and 5xx responses count as failures; once the circuit
+/// opens, requests fail immediately instead of waiting for the server.
+fn send(
+    base_url: &str,
+    request: reqwest::blocking::RequestBuilder,
+) -> Result<reqwest::blocking::Response, McpError> {
+    send_within(base_url, request, None)
+}
+
+/// Send a request that must complete within `timeout`, the time left to the caller
+///
+/// Running out of that time is the caller's limit rather than a server failure, so it
+/// does not count against the circuit breaker.
+fn send_within(
+    base_url: &str,
+    mut request: reqwest::blocking::RequestBuilder,
+    timeout: Option<Duration>,
+) -> Result<reqwest::blocking::Response, McpError> {
+    let breaker = crate::circuit_breaker::shared(base_url);
+    let permit = breaker.allow()?;
+
+    if let Some(timeout) = timeout {
+        request = request.timeout(timeout);
+    }
+    match request.send() {
+        Ok(response) => {
+            if response.status().is_server_error() {
+                permit.record_failure();
+            } else {
+                permit.record_success();
+            }
+            Ok(response)
+        }
+        Err(e) if timeout.is_some() && e.is_timeout() => {
+            // A probe cut short by the deadline says nothing about the server, so the
+            // next request probes instead
+            permit.release();
+            Err(crate::deadline::DeadlineExceeded.into())
+        }
+        Err(e) => {
+            permit.record_failure();
+            Err(e.into())
+        }
     }
 }
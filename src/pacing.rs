@@ -0,0 +1,64 @@
+//! Pacing module
+//!
+//! This module keeps the chat loop from flooding the model server. A token bucket
+//! spaces out model requests once a burst of them is spent, and a budget of tool calls
+//! per turn stops a model that keeps asking for tools from spinning the loop. Both
+//! matter when one Ollama instance is shared by several users.
+
+use std::time::{Duration, Instant};
+
+/// Limits on the requests of a chat session
+#[derive(Debug, Clone, Default)]
+pub struct Pacing {
+    /// Model requests allowed per minute, in bursts of up to this many
+    pub max_model_calls_per_minute: Option<u32>,
+    /// Tool calls executed for one user message; further calls are refused
+    pub max_tool_calls_per_turn: Option<u32>,
+}
+
+/// Token bucket refilled at a steady rate
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Bucket allowing `calls` per minute, starting full
+    pub fn per_minute(calls: u32) -> Self {
+        let capacity = f64::from(calls.max(1));
+        Self {
+            capacity,
+            refill_per_second: capacity / 60.0,
+            tokens: capacity,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Take a token, first waiting for one if the bucket is empty
+    ///
+    /// Returns how long the call waited.
+    pub fn acquire(&mut self) -> Duration {
+        self.refill();
+        let wait = if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_second)
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+            self.refill();
+        }
+        self.tokens = (self.tokens - 1.0).max(0.0);
+        wait
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.refilled_at = now;
+    }
+}
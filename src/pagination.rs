@@ -0,0 +1,78 @@
+//! Pagination module
+//!
+//! This module splits the results of tools/list and resources/list into pages, as the
+//! MCP specification allows for large registries. A list request may carry the
+//! `cursor` of the previous page's `nextCursor`; the last page has no `nextCursor`.
+//! Cursors are opaque to clients and encode the offset into the list, whose order is
+//! stable as long as the registry does not change.
+
+use crate::mcp::{JsonRpcError, McpServerState};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Items per page unless `[pagination] page_size` is configured
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Parameters of a paginated list request
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PaginatedRequest {
+    /// `nextCursor` of the previous page, absent for the first page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+impl PaginatedRequest {
+    /// Parse the optional params of a list request
+    pub fn from_params(params: Option<Value>) -> Result<Self, JsonRpcError> {
+        match params.map(serde_json::from_value::<Self>) {
+            Some(Ok(request)) => Ok(request),
+            Some(Err(e)) => Err(JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Offset into the list the page starts at
+    pub fn offset(&self) -> Result<usize, JsonRpcError> {
+        let Some(cursor) = &self.cursor else {
+            return Ok(0);
+        };
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .and_then(|offset| offset.parse().ok())
+            .ok_or_else(|| JsonRpcError {
+                code: -32602,
+                message: format!("Invalid cursor: {}", cursor),
+                data: None,
+            })
+    }
+}
+
+/// Items of a list per page on a server
+pub fn page_size(state: &McpServerState) -> usize {
+    state
+        .config
+        .pagination
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .max(1)
+}
+
+/// The page of `items` starting at `offset`, with the cursor of the next page if any
+pub fn page<T>(items: Vec<T>, offset: usize, page_size: usize) -> (Vec<T>, Option<String>) {
+    let end = offset.saturating_add(page_size);
+    let next_cursor = (end < items.len()).then(|| cursor(end));
+    let page = items.into_iter().skip(offset).take(page_size).collect();
+    (page, next_cursor)
+}
+
+fn cursor(offset: usize) -> String {
+    URL_SAFE_NO_PAD.encode(offset.to_string())
+}
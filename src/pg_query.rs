@@ -0,0 +1,469 @@
+//! PostgreSQL query tool module
+//!
+//! This module runs read-only SQL against the database configured in the server
+//! config. Only a single SELECT, WITH, VALUES or TABLE statement is accepted, without
+//! data-modifying clauses, and it runs inside a READ ONLY transaction with a statement timeout that is always rolled
+//! back. Results are returned as a markdown table plus structured rows. It is only
+//! compiled with the `postgres` feature.
+
+use crate::config::PostgresConfig;
+use postgres::{Client, Config};
+use postgres_native_tls::MakeTlsConnector;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+/// Default number of rows returned
+const DEFAULT_MAX_ROWS: usize = 100;
+
+/// Default statement timeout
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Statements allowed as the first keyword of a query
+const ALLOWED_STATEMENTS: &[&str] = &["select", "with", "values", "table"];
+
+/// Keywords writing data from within a query: data-modifying CTEs, `SELECT INTO` and
+/// row locks such as `FOR UPDATE`
+const WRITE_KEYWORDS: &[&str] = &["insert", "update", "delete", "merge", "into"];
+
+/// Postgres query tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PgQueryRequest {
+    /// The SQL query to run
+    pub sql: String,
+    /// Maximum number of rows to return (capped by the server configuration)
+    pub limit: Option<usize>,
+}
+
+/// Postgres query tool response
+#[derive(Debug, Serialize)]
+pub struct PgQueryResponse {
+    pub columns: Vec<String>,
+    pub rows: Vec<Map<String, Value>>,
+    /// Whether more rows were available than returned
+    pub truncated: bool,
+}
+
+impl PgQueryResponse {
+    /// Render the result as a markdown table
+    pub fn to_markdown(&self) -> String {
+        if self.columns.is_empty() {
+            return "(no columns)".to_string();
+        }
+
+        let mut table = format!(
+            "| {} |\n|{}\n",
+            self.columns
+                .iter()
+                .map(|c| escape_cell(c))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            "---|".repeat(self.columns.len())
+        );
+        for row in &self.rows {
+            let cells: Vec<String> = self
+                .columns
+                .iter()
+                .map(|column| match row.get(column) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(text)) => escape_cell(text),
+                    Some(value) => escape_cell(&value.to_string()),
+                })
+                .collect();
+            table.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+
+        table.push_str(&format!(
+            "\n{} row{}{}",
+            self.rows.len(),
+            if self.rows.len() == 1 { "" } else { "s" },
+            if self.truncated {
+                " (truncated, more rows available)"
+            } else {
+                ""
+            }
+        ));
+        table
+    }
+}
+
+/// Execute the Postgres query tool
+pub fn execute_pg_query(
+    request: PgQueryRequest,
+    config: &PostgresConfig,
+) -> Result<PgQueryResponse, Box<dyn std::error::Error>> {
+    let sql = check_statement(&request.sql)?;
+    let max_rows = config.max_rows.unwrap_or(DEFAULT_MAX_ROWS);
+    let limit = request.limit.unwrap_or(max_rows).min(max_rows);
+    let timeout = Duration::from_secs(config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let mut client = connect(config, timeout)?;
+
+    let mut transaction = client
+        .build_transaction()
+        .read_only(true)
+        .start()
+        .map_err(describe)?;
+    transaction
+        .batch_execute(&format!(
+            "SET LOCAL statement_timeout = {}",
+            timeout.as_millis()
+        ))
+        .map_err(describe)?;
+
+    // Preparing the statement on its own rejects multiple statements and gives the
+    // column order, which JSON objects don't preserve
+    let columns = transaction
+        .prepare(sql)
+        .map_err(describe)?
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+
+    let wrapped = format!(
+        "SELECT row_to_json(q)::text FROM (\n{}\n) AS q LIMIT {}",
+        sql,
+        limit + 1
+    );
+    let mut rows = transaction
+        .query(&wrapped, &[])
+        .map_err(describe)?
+        .iter()
+        .map(|row| Ok(serde_json::from_str(row.try_get::<_, &str>(0)?)?))
+        .collect::<Result<Vec<Map<String, Value>>, Box<dyn std::error::Error>>>()?;
+    transaction.rollback().map_err(describe)?;
+
+    let truncated = rows.len() > limit;
+    rows.truncate(limit);
+
+    Ok(PgQueryResponse {
+        columns,
+        rows,
+        truncated,
+    })
+}
+
+/// Check that the SQL is a single read-only query and strip its trailing semicolon
+///
+/// This is a first line of defense giving clear errors; the READ ONLY transaction
+/// and the extended query protocol (one statement per query) enforce it. Comments,
+/// string literals and quoted identifiers are skipped, so they can neither hide a
+/// second statement nor cause a false alarm; a query with unbalanced parentheses is
+/// rejected, as it could close the subquery it is wrapped in.
+fn check_statement(sql: &str) -> Result<&str, String> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let words = scan(sql)?;
+
+    let Some(keyword) = words.first() else {
+        return Err("SQL statement is empty".to_string());
+    };
+    if !ALLOWED_STATEMENTS.contains(&keyword.as_str()) {
+        return Err(format!(
+            "Access denied: only read-only queries are allowed, got '{}'",
+            keyword.to_ascii_uppercase()
+        ));
+    }
+    if let Some(write) = words
+        .iter()
+        .find(|word| WRITE_KEYWORDS.contains(&word.as_str()))
+    {
+        return Err(format!(
+            "Access denied: only read-only queries are allowed, got '{}'",
+            write.to_ascii_uppercase()
+        ));
+    }
+
+    Ok(sql)
+}
+
+/// The keywords and unquoted identifiers of a single statement, in lowercase
+///
+/// Fails on a statement separator, unbalanced parentheses and unterminated comments,
+/// literals or quoted identifiers.
+fn scan(sql: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut depth = 0usize;
+    let mut chars = sql.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let rest = &sql[start..];
+        match c {
+            '-' if rest.starts_with("--") => {
+                chars.by_ref().find(|(_, c)| *c == '\n');
+            }
+            '/' if rest.starts_with("/*") => {
+                // Block comments nest
+                chars.next();
+                let mut nesting = 1;
+                while nesting > 0 {
+                    match chars.next() {
+                        Some((i, '/')) if sql[i..].starts_with("/*") => {
+                            chars.next();
+                            nesting += 1;
+                        }
+                        Some((i, '*')) if sql[i..].starts_with("*/") => {
+                            chars.next();
+                            nesting -= 1;
+                        }
+                        Some(_) => {}
+                        None => return Err("Unterminated comment in SQL".to_string()),
+                    }
+                }
+            }
+            '\'' => skip_quoted(&mut chars, '\'', false)?,
+            '"' => skip_quoted(&mut chars, '"', false)?,
+            '$' => {
+                // A dollar-quoted string such as $$text$$ or $tag$text$tag$, unlike a
+                // parameter such as $1
+                let tag_end = rest[1..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .map(|i| i + 1)
+                    .filter(|&i| {
+                        rest[i..].starts_with('$')
+                            && !rest[1..].starts_with(|c: char| c.is_ascii_digit())
+                    });
+                if let Some(tag_end) = tag_end {
+                    let tag = &rest[..=tag_end];
+                    let Some(end) = rest[tag.len()..].find(tag) else {
+                        return Err("Unterminated dollar-quoted string in SQL".to_string());
+                    };
+                    let close = start + tag.len() + end + tag.len();
+                    while chars.next_if(|(i, _)| *i < close).is_some() {}
+                }
+            }
+            ';' => return Err("Only a single SQL statement is allowed".to_string()),
+            '(' => depth += 1,
+            ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or("Unbalanced parentheses in SQL")?;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '$')
+                {
+                    end = i + c.len_utf8();
+                }
+                let word = sql[start..end].to_lowercase();
+                // E'...' strings allow backslash escapes
+                if word == "e" && chars.next_if(|(_, c)| *c == '\'').is_some() {
+                    skip_quoted(&mut chars, '\'', true)?;
+                } else {
+                    words.push(word);
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err("Unbalanced parentheses in SQL".to_string());
+    }
+    Ok(words)
+}
+
+/// Skip past the closing quote of a literal or quoted identifier, where a doubled
+/// quote stands for the quote itself
+fn skip_quoted(
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    quote: char,
+    backslash_escapes: bool,
+) -> Result<(), String> {
+    while let Some((_, c)) = chars.next() {
+        if backslash_escapes && c == '\\' {
+            chars.next();
+        } else if c == quote && chars.next_if(|(_, c)| *c == quote).is_none() {
+            return Ok(());
+        }
+    }
+    Err("Unterminated quoted string or identifier in SQL".to_string())
+}
+
+/// Describe a database error with the server's message when there is one
+fn describe(error: postgres::Error) -> String {
+    match error.as_db_error() {
+        Some(db_error) => format!("{}: {}", db_error.severity(), db_error.message()),
+        None => error.to_string(),
+    }
+}
+
+/// Escape a value for use in a markdown table cell
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Connect to the configured database
+fn connect(
+    config: &PostgresConfig,
+    timeout: Duration,
+) -> Result<Client, Box<dyn std::error::Error>> {
+    let url = match &config.url_env {
+        Some(variable) => std::env::var(variable)
+            .map_err(|_| format!("Database URL variable '{}' is not set", variable))?,
+        None => config.url.clone().ok_or("No database URL configured")?,
+    };
+    let mut connection_config: Config = url
+        .parse()
+        .map_err(|e| format!("Invalid database URL: {}", e))?;
+    connection_config.connect_timeout(timeout);
+
+    let tls = MakeTlsConnector::new(native_tls::TlsConnector::new()?);
+    Ok(connection_config.connect(tls).map_err(describe)?)
+}
+
+/// Check that the database accepts connections and queries
+pub fn check(config: &PostgresConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let timeout = Duration::from_secs(config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    connect(config, timeout)?
+        .simple_query("SELECT 1")
+        .map_err(describe)?;
+    Ok(())
+}
+
+/// Get the tool definition for the pg_query tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "pg_query".to_string(),
+        version: None,
+        description: Some("Run a read-only SQL query (a single SELECT, WITH, VALUES or TABLE statement) against the configured PostgreSQL database and return the rows as a markdown table and as structured content.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<PgQueryRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            title: Some("Query PostgreSQL".to_string()),
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn denied(sql: &str) -> String {
+        check_statement(sql).expect_err(sql)
+    }
+
+    #[test]
+    fn read_only_queries_are_allowed() {
+        for sql in [
+            "SELECT 1",
+            "select * from users where name = 'x';",
+            "WITH t AS (SELECT 1 AS n) SELECT n FROM t",
+            "VALUES (1), (2)",
+            "TABLE users",
+            "(SELECT 1) UNION (SELECT 2)",
+            "-- the count\nSELECT count(*) FROM users",
+            "/* a /* nested */ comment */ SELECT 1",
+            "SELECT $1::int",
+            "SELECT CASE WHEN true THEN 1 END",
+        ] {
+            assert!(check_statement(sql).is_ok(), "{}", sql);
+        }
+        assert_eq!(check_statement("SELECT 1;; ").unwrap(), "SELECT 1");
+    }
+
+    #[test]
+    fn writes_and_schema_changes_are_denied() {
+        for sql in [
+            "INSERT INTO users VALUES (1)",
+            "UPDATE users SET admin = true",
+            "DELETE FROM users",
+            "DROP TABLE users",
+            "CREATE TABLE t (id int)",
+            "ALTER TABLE users ADD COLUMN x int",
+            "TRUNCATE users",
+            "GRANT ALL ON users TO public",
+            "COPY users TO '/tmp/users'",
+        ] {
+            assert!(denied(sql).starts_with("Access denied"), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn writes_inside_a_query_are_denied() {
+        for sql in [
+            "WITH d AS (DELETE FROM users RETURNING *) SELECT * FROM d",
+            "WITH i AS (INSERT INTO log VALUES (1) RETURNING 1) SELECT 1",
+            "SELECT * INTO copy FROM users",
+            "SELECT * FROM users FOR UPDATE",
+        ] {
+            assert!(denied(sql).starts_with("Access denied"), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn multiple_statements_are_denied() {
+        for sql in [
+            "SELECT 1; DELETE FROM users",
+            "SELECT 1; COMMIT; DELETE FROM users",
+            "SELECT 1; SET TRANSACTION READ WRITE",
+        ] {
+            assert_eq!(
+                denied(sql),
+                "Only a single SQL statement is allowed",
+                "{}",
+                sql
+            );
+        }
+    }
+
+    #[test]
+    fn transaction_control_is_denied() {
+        for sql in [
+            "COMMIT",
+            "ROLLBACK",
+            "BEGIN",
+            "SET TRANSACTION READ WRITE",
+            "SET SESSION CHARACTERISTICS AS TRANSACTION READ WRITE",
+            "RESET ALL",
+        ] {
+            assert!(denied(sql).starts_with("Access denied"), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn comments_and_literals_cannot_smuggle_statements() {
+        // Statements hidden behind comments are seen for what they are
+        assert!(denied("/* SELECT */ DELETE FROM users").starts_with("Access denied"));
+        assert!(denied("-- SELECT\nDROP TABLE users").starts_with("Access denied"));
+        for sql in [
+            "SELECT 1 --\n; DELETE FROM users",
+            "SELECT 1 /* */; DELETE FROM users",
+            "SELECT '--'; DELETE FROM users",
+            "SELECT 'it''s'; DELETE FROM users",
+            "SELECT E'\\''; DELETE FROM users",
+            "SELECT $$x$$; DELETE FROM users",
+            "SELECT \"a;b\"; DELETE FROM users",
+        ] {
+            assert_eq!(
+                denied(sql),
+                "Only a single SQL statement is allowed",
+                "{}",
+                sql
+            );
+        }
+        // Keywords inside comments, literals and quoted identifiers are harmless
+        for sql in [
+            "SELECT 1 /* ; DELETE FROM users */",
+            "SELECT 'a; DELETE FROM users'",
+            "SELECT $tag$; DROP TABLE users$tag$",
+            "SELECT \"delete\" FROM audit",
+        ] {
+            assert!(check_statement(sql).is_ok(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn queries_cannot_escape_their_wrapper() {
+        for sql in [
+            "SELECT 1) AS q UNION SELECT 2 FROM (SELECT 3",
+            "SELECT 1) AS q; COMMIT; DELETE FROM users; SELECT (1",
+            "SELECT 1 /* unterminated",
+            "SELECT 'unterminated",
+            "SELECT $$unterminated",
+        ] {
+            assert!(check_statement(sql).is_err(), "{}", sql);
+        }
+    }
+}
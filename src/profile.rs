@@ -0,0 +1,111 @@
+//! Client profile module
+//!
+//! This module keeps the response preferences of mcp-client users, the language the
+//! model answers in and its tone, in named profiles so they are set once instead of
+//! every session. Profiles live in `profiles.toml` in the user's configuration
+//! directory (`$XDG_CONFIG_HOME/mcp-client`, or `~/.config/mcp-client`). The
+//! preferences are turned into a system prompt through a minijinja template.
+
+use minijinja::Environment;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// System prompt built from the preferences; empty lines are dropped
+const STYLE_TEMPLATE: &str = "\
+{% if language %}Always answer in {{ language }}, even when the user or a tool result uses another language.{% endif %}
+{% if tone %}Use a {{ tone }} tone in your answers.{% endif %}";
+
+/// Response preferences saved under a name
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Language of the answers, e.g. "German" or "pt-BR"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Tone of the answers, e.g. "formal", "friendly" or "concise"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tone: Option<String>,
+}
+
+/// Contents of the profiles file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProfilesFile {
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Profile {
+    /// Load a saved profile
+    pub fn load(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match read_profiles()?.profiles.remove(name) {
+            Some(profile) => Ok(profile),
+            None => Err(format!("Profile '{}' not found in {}", name, path()?.display()).into()),
+        }
+    }
+
+    /// Save the profile under a name, replacing a profile of the same name
+    pub fn save(&self, name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let mut file = read_profiles()?;
+        file.profiles.insert(name.to_string(), self.clone());
+
+        let path = path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(&file)?)
+            .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Names of the saved profiles, sorted
+    pub fn names() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(read_profiles()?.profiles.into_keys().collect())
+    }
+
+    /// Take the preferences set in `other` over these
+    pub fn merge(&mut self, other: Profile) {
+        if other.language.is_some() {
+            self.language = other.language;
+        }
+        if other.tone.is_some() {
+            self.tone = other.tone;
+        }
+    }
+
+    /// The system prompt asking for these preferences; `None` when none is set
+    pub fn system_prompt(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let rendered = Environment::new().render_str(STYLE_TEMPLATE, self)?;
+        let lines: Vec<&str> = rendered
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        Ok((!lines.is_empty()).then(|| lines.join("\n")))
+    }
+}
+
+/// Path of the profiles file
+pub fn path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .ok_or("Cannot locate the home directory for profiles")?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+    Ok(base.join("mcp-client").join("profiles.toml"))
+}
+
+fn read_profiles() -> Result<ProfilesFile, Box<dyn std::error::Error>> {
+    let path = path()?;
+    match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text)
+            .map_err(|e| format!("Invalid profiles file '{}': {}", path.display(), e).into()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ProfilesFile::default()),
+        Err(e) => Err(format!("Failed to read '{}': {}", path.display(), e).into()),
+    }
+}
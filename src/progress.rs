@@ -0,0 +1,158 @@
+//! Progress module
+//!
+//! This module reports how far long-running tool calls got. A client that wants
+//! updates sends a `progressToken` in the `_meta` of its tools/call request; the tool
+//! reports through the [`ProgressReporter`] it is called with, and the server sends
+//! `notifications/progress` carrying that token to the session that made the call,
+//! instead of leaving the client waiting in silence for minutes.
+
+use crate::mcp::{JsonRpcNotification, McpServerState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Method of progress notifications
+pub const PROGRESS_METHOD: &str = "notifications/progress";
+
+/// Updates closer together than this are dropped, except the final one
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Progress tokens of the calls running, with the session that made each call
+pub type ProgressTokens = Arc<Mutex<HashMap<String, Option<String>>>>;
+
+/// `_meta` of a request
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RequestMeta {
+    /// Token the client wants progress notifications about the request tagged with
+    #[serde(rename = "progressToken", skip_serializing_if = "Option::is_none")]
+    pub progress_token: Option<Value>,
+    /// Milliseconds the caller waits for the result, see [`crate::deadline`]
+    #[serde(rename = "timeoutMs", skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Parameters of a progress notification
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Progress {
+    #[serde(rename = "progressToken")]
+    pub progress_token: Value,
+    /// Work done so far, increasing with every notification
+    pub progress: f64,
+    /// Total work, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Reports the progress of a tool call to the client that made it
+///
+/// Clones report for the same call, so the reporter can be moved to the blocking
+/// thread doing the work. Reporting does nothing when the client did not ask for
+/// progress.
+#[derive(Clone, Default)]
+pub struct ProgressReporter {
+    call: Option<Arc<ProgressCall>>,
+}
+
+struct ProgressCall {
+    token: Value,
+    /// The token as JSON text, its key in [`ProgressTokens`]
+    key: String,
+    notifications: broadcast::Sender<JsonRpcNotification>,
+    tokens: ProgressTokens,
+    last_sent: Mutex<Option<Instant>>,
+}
+
+impl ProgressReporter {
+    /// Reporter for a call made by `session_id` with the given progress token
+    pub fn new(state: &McpServerState, session_id: Option<&str>, token: Option<Value>) -> Self {
+        let Some(token) = token else {
+            return Self::default();
+        };
+        let key = token.to_string();
+        if let Ok(mut tokens) = state.progress_tokens.lock() {
+            tokens.insert(key.clone(), session_id.map(str::to_string));
+        }
+        Self {
+            call: Some(Arc::new(ProgressCall {
+                token,
+                key,
+                notifications: state.notifications.clone(),
+                tokens: Arc::clone(&state.progress_tokens),
+                last_sent: Mutex::new(None),
+            })),
+        }
+    }
+
+    /// Whether the client asked for progress
+    pub fn is_requested(&self) -> bool {
+        self.call.is_some()
+    }
+
+    /// Report `progress` out of `total`, if known, with an optional message
+    ///
+    /// Updates are sent at most a few times a second; one reaching `total` is always
+    /// sent.
+    pub fn report(&self, progress: f64, total: Option<f64>, message: Option<&str>) {
+        let Some(call) = &self.call else {
+            return;
+        };
+        let done = total.is_some_and(|total| progress >= total);
+        if let Ok(mut last_sent) = call.last_sent.lock() {
+            if !done && last_sent.is_some_and(|sent| sent.elapsed() < MIN_INTERVAL) {
+                return;
+            }
+            *last_sent = Some(Instant::now());
+        }
+
+        let params = Progress {
+            progress_token: call.token.clone(),
+            progress,
+            total,
+            message: message.map(str::to_string),
+        };
+        let _ = call.notifications.send(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: PROGRESS_METHOD.to_string(),
+            params: serde_json::to_value(params).ok(),
+        });
+    }
+}
+
+impl Drop for ProgressCall {
+    fn drop(&mut self) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.remove(&self.key);
+        }
+    }
+}
+
+/// Whether a progress notification is sent to a session
+///
+/// The progress of a tool call only reaches the session that made it; notifications
+/// with other tokens, such as those of transfers, reach every session.
+pub fn delivers(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    notification: &JsonRpcNotification,
+) -> bool {
+    let Some(token) = notification
+        .params
+        .as_ref()
+        .and_then(|params| params.get("progressToken"))
+    else {
+        return true;
+    };
+    match state.progress_tokens.lock() {
+        Ok(tokens) => match tokens.get(&token.to_string()) {
+            Some(caller) => caller.as_deref() == session_id,
+            None => true,
+        },
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,273 @@
+//! Prompts module
+//!
+//! This module implements the MCP prompts capability: reusable prompt templates the
+//! user picks in the client (e.g. as slash commands) and fills in with arguments.
+//! `prompts/list` describes every registered prompt and its arguments, and
+//! `prompts/get` renders one into the messages to send to the model. Prompts defined
+//! in the configuration are minijinja templates over their arguments; applications
+//! can register their own [`PromptProvider`].
+
+use crate::config::PromptConfig;
+use crate::mcp::{JsonRpcError, McpServerState, ToolContent};
+use minijinja::{Environment, UndefinedBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Instruction budget for rendering one prompt message
+const RENDER_FUEL: u64 = 1_000_000;
+
+/// A prompt the server offers
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Prompt {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<PromptArgument>,
+}
+
+/// An argument a prompt is filled in with
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Author of a prompt message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Assistant,
+}
+
+/// A message of a rendered prompt
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PromptMessage {
+    pub role: Role,
+    pub content: ToolContent,
+}
+
+/// Response from prompts/list request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PromptsListResponse {
+    pub prompts: Vec<Prompt>,
+}
+
+/// Request for prompts/get endpoint
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PromptsGetRequest {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: HashMap<String, String>,
+}
+
+/// Response from prompts/get request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PromptsGetResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub messages: Vec<PromptMessage>,
+}
+
+/// Future returned by [`PromptProvider::get`]
+pub type PromptFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<PromptsGetResponse, JsonRpcError>> + Send + 'a>>;
+
+/// A prompt served by the server
+///
+/// Providers are registered with [`crate::mcp::McpServer::register_prompt`] under the
+/// name of their prompt; registering the same name again replaces the prompt.
+pub trait PromptProvider: Send + Sync {
+    /// Definition returned by prompts/list
+    fn prompt(&self) -> Prompt;
+
+    /// Render the prompt with the arguments the user filled in
+    ///
+    /// Required arguments have already been checked to be present.
+    fn get<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        arguments: &'a HashMap<String, String>,
+    ) -> PromptFuture<'a>;
+}
+
+impl fmt::Debug for dyn PromptProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PromptProvider")
+            .field(&self.prompt().name)
+            .finish()
+    }
+}
+
+/// List every registered prompt, ordered by name
+pub async fn list(state: &McpServerState) -> PromptsListResponse {
+    let mut prompts: Vec<Prompt> = state
+        .prompts
+        .read()
+        .await
+        .values()
+        .map(|provider| provider.prompt())
+        .collect();
+    prompts.sort_by(|a, b| a.name.cmp(&b.name));
+    PromptsListResponse { prompts }
+}
+
+/// Render a prompt, after checking its required arguments are present
+pub async fn get(
+    state: &McpServerState,
+    request: &PromptsGetRequest,
+) -> Result<PromptsGetResponse, JsonRpcError> {
+    let provider = state.prompts.read().await.get(&request.name).cloned();
+    let Some(provider) = provider else {
+        return Err(JsonRpcError {
+            code: -32602,
+            message: format!("Prompt '{}' not found", request.name),
+            data: None,
+        });
+    };
+
+    let missing: Vec<String> = provider
+        .prompt()
+        .arguments
+        .into_iter()
+        .filter(|argument| argument.required && !request.arguments.contains_key(&argument.name))
+        .map(|argument| argument.name)
+        .collect();
+    if !missing.is_empty() {
+        return Err(JsonRpcError {
+            code: -32602,
+            message: format!(
+                "Missing required arguments for prompt '{}': {}",
+                request.name,
+                missing.join(", ")
+            ),
+            data: Some(serde_json::json!({ "missing": missing })),
+        });
+    }
+
+    provider.get(state, &request.arguments).await
+}
+
+/// A prompt whose messages are minijinja templates over its arguments
+///
+/// Undefined variables are errors, so templates test optional arguments with
+/// `{% if name is defined %}`.
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    prompt: Prompt,
+    messages: Vec<(Role, String)>,
+}
+
+impl PromptTemplate {
+    /// Create a prompt without arguments or messages
+    pub fn new(name: impl Into<String>, description: Option<String>) -> Self {
+        Self {
+            prompt: Prompt {
+                name: name.into(),
+                description,
+                arguments: Vec::new(),
+            },
+            messages: Vec::new(),
+        }
+    }
+
+    /// Add an argument
+    pub fn argument(
+        mut self,
+        name: impl Into<String>,
+        description: Option<String>,
+        required: bool,
+    ) -> Self {
+        self.prompt.arguments.push(PromptArgument {
+            name: name.into(),
+            description,
+            required,
+        });
+        self
+    }
+
+    /// Add a message rendered from a template
+    pub fn message(mut self, role: Role, template: impl Into<String>) -> Self {
+        self.messages.push((role, template.into()));
+        self
+    }
+
+    fn render(&self, arguments: &HashMap<String, String>) -> Result<Vec<PromptMessage>, String> {
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_fuel(Some(RENDER_FUEL));
+        // Only declared arguments reach the template
+        let variables: HashMap<&str, &str> = self
+            .prompt
+            .arguments
+            .iter()
+            .filter_map(|argument| {
+                let value = arguments.get(&argument.name)?;
+                Some((argument.name.as_str(), value.as_str()))
+            })
+            .collect();
+
+        self.messages
+            .iter()
+            .map(|(role, template)| {
+                let text = env
+                    .render_str(template, &variables)
+                    .map_err(|e| e.to_string())?;
+                Ok(PromptMessage {
+                    role: *role,
+                    content: ToolContent::text(text),
+                })
+            })
+            .collect()
+    }
+}
+
+impl From<&PromptConfig> for PromptTemplate {
+    fn from(config: &PromptConfig) -> Self {
+        Self {
+            prompt: Prompt {
+                name: config.name.clone(),
+                description: config.description.clone(),
+                arguments: config.arguments.clone(),
+            },
+            messages: config
+                .messages
+                .iter()
+                .map(|message| (message.role, message.text.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl PromptProvider for PromptTemplate {
+    fn prompt(&self) -> Prompt {
+        self.prompt.clone()
+    }
+
+    fn get<'a>(
+        &'a self,
+        _state: &'a McpServerState,
+        arguments: &'a HashMap<String, String>,
+    ) -> PromptFuture<'a> {
+        Box::pin(async move {
+            let messages = self.render(arguments).map_err(|e| JsonRpcError {
+                code: -32603,
+                message: format!("Failed to render prompt '{}': {}", self.prompt.name, e),
+                data: None,
+            })?;
+            Ok(PromptsGetResponse {
+                description: self.prompt.description.clone(),
+                messages,
+            })
+        })
+    }
+}
@@ -0,0 +1,191 @@
+//! Regex extraction tool module
+//!
+//! This module runs a caller-supplied regular expression over a file or inline text
+//! and returns every match with its capture groups as structured JSON. The regex
+//! engine guarantees matching in linear time, and the pattern size, compiled program
+//! size, input size, number of matches and total run time are all bounded.
+
+use crate::tool_error::ToolErrorPayload;
+use crate::vfs::VirtualFs;
+use regex::RegexBuilder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::time::{Duration, Instant};
+
+/// Upper bound on the length of a pattern
+const MAX_PATTERN_LENGTH: usize = 1000;
+
+/// Upper bound on the size of a compiled pattern
+const MAX_COMPILED_SIZE: usize = 1 << 20;
+
+/// Upper bound on the size of the searched text
+const MAX_INPUT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Default number of matches returned
+const DEFAULT_MATCHES: usize = 100;
+
+/// Upper bound on the number of matches returned
+const MAX_MATCHES: usize = 1000;
+
+/// Matching stops after this long and returns what was found so far
+const TIME_LIMIT: Duration = Duration::from_secs(2);
+
+/// Regex extract tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RegexExtractRequest {
+    /// The regular expression (Rust regex syntax, no backreferences or lookaround)
+    pub pattern: String,
+    /// The file to search
+    pub path: Option<String>,
+    /// Text to search instead of a file
+    pub text: Option<String>,
+    /// Match case-insensitively
+    pub ignore_case: Option<bool>,
+    /// Make ^ and $ match at the start and end of every line
+    pub multi_line: Option<bool>,
+    /// Make . also match newlines
+    pub dot_matches_newline: Option<bool>,
+    /// Maximum number of matches to return (default 100, max 1000)
+    pub max_matches: Option<usize>,
+}
+
+/// A single match and its capture groups
+#[derive(Debug, Serialize)]
+pub struct RegexMatch {
+    #[serde(rename = "match")]
+    pub text: String,
+    /// 1-based line number of the start of the match
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    /// Capture groups keyed by name, or by index for unnamed groups
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub groups: Map<String, Value>,
+}
+
+/// Regex extract tool response
+#[derive(Debug, Serialize)]
+pub struct RegexExtractResponse {
+    pub matches: Vec<RegexMatch>,
+    /// Whether matching stopped at the match or time limit
+    pub truncated: bool,
+}
+
+/// Execute the regex extract tool
+pub fn execute_regex_extract(
+    request: RegexExtractRequest,
+    fs: &dyn VirtualFs,
+) -> Result<RegexExtractResponse, Box<dyn std::error::Error>> {
+    if request.pattern.len() > MAX_PATTERN_LENGTH {
+        return Err(ToolErrorPayload::too_large(
+            format!("Pattern exceeds the {} character limit", MAX_PATTERN_LENGTH),
+            MAX_PATTERN_LENGTH as u64,
+        )
+        .into());
+    }
+
+    let regex = RegexBuilder::new(&request.pattern)
+        .case_insensitive(request.ignore_case.unwrap_or(false))
+        .multi_line(request.multi_line.unwrap_or(false))
+        .dot_matches_new_line(request.dot_matches_newline.unwrap_or(false))
+        .size_limit(MAX_COMPILED_SIZE)
+        .dfa_size_limit(MAX_COMPILED_SIZE)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))?;
+
+    let text = match (request.path, request.text) {
+        (Some(_), Some(_)) => return Err("Provide either 'path' or 'text', not both".into()),
+        (Some(path), None) => {
+            let checked = crate::file_read::validate_path(fs, &path)?;
+            if fs.metadata(checked)?.len > MAX_INPUT_BYTES {
+                return Err(ToolErrorPayload::too_large(
+                    format!("File exceeds the {} byte limit", MAX_INPUT_BYTES),
+                    MAX_INPUT_BYTES,
+                )
+                .into());
+            }
+            let text = fs
+                .read_to_string(checked)
+                .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+            crate::accounting::read(text.len() as u64);
+            text
+        }
+        (None, Some(text)) => {
+            if text.len() as u64 > MAX_INPUT_BYTES {
+                return Err(ToolErrorPayload::too_large(
+                    format!("Text exceeds the {} byte limit", MAX_INPUT_BYTES),
+                    MAX_INPUT_BYTES,
+                )
+                .into());
+            }
+            text
+        }
+        (None, None) => return Err("Either 'path' or 'text' is required".into()),
+    };
+
+    let limit = request
+        .max_matches
+        .unwrap_or(DEFAULT_MATCHES)
+        .clamp(1, MAX_MATCHES);
+    let names: Vec<String> = regex
+        .capture_names()
+        .enumerate()
+        .map(|(i, name)| name.map_or_else(|| i.to_string(), str::to_string))
+        .collect();
+
+    let started = Instant::now();
+    let mut matches = Vec::new();
+    let mut truncated = false;
+    // Line numbers are counted incrementally from the previous match
+    let (mut line, mut line_offset) = (1, 0);
+
+    for captures in regex.captures_iter(&text) {
+        if matches.len() == limit || started.elapsed() > TIME_LIMIT {
+            truncated = true;
+            break;
+        }
+
+        let whole = captures.get(0).expect("group 0 always participates");
+        line += text[line_offset..whole.start()].matches('\n').count();
+        line_offset = whole.start();
+
+        let groups = captures
+            .iter()
+            .zip(&names)
+            .skip(1)
+            .map(|(group, name)| {
+                let value = group.map_or(Value::Null, |g| Value::String(g.as_str().to_string()));
+                (name.clone(), value)
+            })
+            .collect();
+
+        matches.push(RegexMatch {
+            text: whole.as_str().to_string(),
+            line,
+            start: whole.start(),
+            end: whole.end(),
+            groups,
+        });
+    }
+
+    Ok(RegexExtractResponse { matches, truncated })
+}
+
+/// Get the tool definition for the regex_extract tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "regex_extract".to_string(),
+        version: None,
+        description: Some(format!(
+            "Run a regular expression over a file within {} or over provided text and return every match with its line number and capture groups as JSON. Use named groups like (?P<version>\\d+\\.\\d+) for readable output.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<RegexExtractRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
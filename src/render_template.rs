@@ -0,0 +1,153 @@
+//! Template rendering tool module
+//!
+//! This module fills Jinja-style templates (rendered with minijinja) with JSON
+//! variables and returns the result or writes it to a file, so agents can scaffold
+//! configs and boilerplate from a template instead of generating them freehand.
+//! Undefined variables are errors, and rendering is bounded by a fuel limit and
+//! an output size limit.
+
+use crate::tool_error::ToolErrorPayload;
+use crate::vfs::VirtualFs;
+use minijinja::{Environment, UndefinedBehavior};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on the size of a template
+const MAX_TEMPLATE_BYTES: u64 = 1024 * 1024;
+
+/// Upper bound on the size of the rendered output
+const MAX_OUTPUT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Instruction budget for a single render, stopping runaway loops
+const RENDER_FUEL: u64 = 10_000_000;
+
+/// Render template tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RenderTemplateRequest {
+    /// The template file to render
+    pub template_path: Option<String>,
+    /// Template source, instead of template_path
+    pub template: Option<String>,
+    /// Variables available to the template
+    #[serde(default)]
+    pub variables: serde_json::Map<String, serde_json::Value>,
+    /// Write the result to this file instead of returning it
+    pub output_path: Option<String>,
+    /// Replace output_path if it already exists (default false)
+    pub overwrite: Option<bool>,
+}
+
+/// Render template tool response
+#[derive(Debug, Serialize)]
+pub struct RenderTemplateResponse {
+    /// The rendered text, omitted when it was written to a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<String>,
+    pub size: usize,
+}
+
+/// Execute the render template tool
+pub fn execute_render_template(
+    request: RenderTemplateRequest,
+    fs: &dyn VirtualFs,
+) -> Result<RenderTemplateResponse, Box<dyn std::error::Error>> {
+    let (name, source) = match (request.template_path, request.template) {
+        (Some(path), None) => {
+            let checked = crate::file_read::validate_path(fs, &path)?;
+            if fs.metadata(checked)?.len > MAX_TEMPLATE_BYTES {
+                return Err(ToolErrorPayload::too_large(
+                    format!("Template exceeds the {} byte limit", MAX_TEMPLATE_BYTES),
+                    MAX_TEMPLATE_BYTES,
+                )
+                .into());
+            }
+            let source = fs
+                .read_to_string(checked)
+                .map_err(|e| format!("Failed to read template '{}': {}", path, e))?;
+            crate::accounting::read(source.len() as u64);
+            (path, source)
+        }
+        (None, Some(source)) => ("template".to_string(), source),
+        (Some(_), Some(_)) => {
+            return Err("Provide either 'template_path' or 'template', not both".into());
+        }
+        (None, None) => return Err("Either 'template_path' or 'template' is required".into()),
+    };
+
+    // Check the destination before rendering so mistakes surface early
+    let output_path = match &request.output_path {
+        Some(path) => {
+            let checked = crate::file_read::validate_output_path(fs, path)?;
+            if fs.metadata(checked).is_ok() && !request.overwrite.unwrap_or(false) {
+                return Err(format!(
+                    "File already exists: {} (set overwrite to replace it)",
+                    path
+                )
+                .into());
+            }
+            Some(checked)
+        }
+        None => None,
+    };
+
+    // No loader is configured, so templates cannot include other files
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_fuel(Some(RENDER_FUEL));
+    env.set_keep_trailing_newline(true);
+    env.add_template(&name, &source)
+        .map_err(|e| format!("Invalid template: {}", e))?;
+
+    let rendered = env
+        .get_template(&name)?
+        .render(&request.variables)
+        .map_err(|e| format!("Failed to render template: {}", e))?;
+    if rendered.len() > MAX_OUTPUT_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("Output exceeds the {} byte limit", MAX_OUTPUT_BYTES),
+            MAX_OUTPUT_BYTES as u64,
+        )
+        .into());
+    }
+
+    let size = rendered.len();
+    match output_path {
+        Some(path) => {
+            fs.write(path, rendered.as_bytes())
+                .map_err(|e| format!("Failed to write file '{}': {}", path.display(), e))?;
+            crate::accounting::written(size as u64);
+            Ok(RenderTemplateResponse {
+                content: None,
+                output_path: request.output_path,
+                size,
+            })
+        }
+        None => Ok(RenderTemplateResponse {
+            content: Some(rendered),
+            output_path: None,
+            size,
+        }),
+    }
+}
+
+/// Get the tool definition for the render_template tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "render_template".to_string(),
+        version: None,
+        description: Some(format!(
+            "Render a Jinja-style template ({{{{ name }}}}, {{% for %}}, {{% if %}}, filters) from a file within {0} or inline text with JSON variables. Returns the result, or writes it to output_path within {0}. Undefined variables are errors.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<RenderTemplateRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(false),
+            destructive_hint: Some(true),
+            idempotent_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
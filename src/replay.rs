@@ -0,0 +1,202 @@
+//! Replay module
+//!
+//! This module re-runs an agent session recorded in an interaction log against the
+//! current server, as a regression test for changes to the server and its tools. Each
+//! recorded tool call is sent again with the same arguments, and its result is
+//! compared with the recorded one after the same redaction. Model requests can be
+//! re-sent with the recorded conversation to check that the model still asks for the
+//! same tool calls; their text is not compared, as models do not answer word for word
+//! the same. Tool calls whose arguments were redacted cannot be reproduced and are
+//! skipped.
+
+use crate::interaction_log::{Interaction, REDACTED, Redactor};
+use crate::mcp::McpClient;
+use crate::ollama::{ChatMessage, Ollama, OllamaToolCall};
+use serde_json::Value;
+use similar::TextDiff;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::time::Duration;
+
+/// Unchanged lines shown around each difference
+const CONTEXT_LINES: usize = 3;
+
+/// After this long the diff falls back to a coarser, non-minimal result
+const DIFF_DEADLINE: Duration = Duration::from_secs(2);
+
+/// The recorded interactions of one conversation, in order
+#[derive(Debug)]
+pub struct Transcript {
+    pub conversation: String,
+    pub interactions: Vec<Interaction>,
+}
+
+/// How a replayed step compares with the recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Same,
+    /// Unified diff from the recorded to the replayed result
+    Changed(String),
+    /// Why the step could not be replayed
+    Skipped(String),
+}
+
+/// Read the conversation whose id starts with `conversation` from an interaction log,
+/// or the last conversation of the log
+pub fn load(
+    path: impl AsRef<Path>,
+    conversation: Option<&str>,
+) -> Result<Transcript, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open transcript '{}': {}", path.display(), e))?;
+    let mut interactions = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let interaction: Interaction = serde_json::from_str(&line).map_err(|e| {
+            format!(
+                "Line {} of '{}' is not an interaction log record: {}",
+                index + 1,
+                path.display(),
+                e
+            )
+        })?;
+        interactions.push(interaction);
+    }
+
+    let id = match conversation {
+        Some(prefix) => {
+            let mut ids: Vec<&str> = interactions
+                .iter()
+                .map(Interaction::conversation)
+                .filter(|id| id.starts_with(prefix))
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            match ids.as_slice() {
+                [] => return Err(format!("No conversation '{}' in the transcript", prefix).into()),
+                [id] => id.to_string(),
+                _ => {
+                    return Err(format!(
+                        "Several conversations start with '{}'; give more of the id",
+                        prefix
+                    )
+                    .into());
+                }
+            }
+        }
+        None => interactions
+            .last()
+            .ok_or("The transcript has no records")?
+            .conversation()
+            .to_string(),
+    };
+    interactions.retain(|interaction| interaction.conversation() == id);
+    Ok(Transcript {
+        conversation: id,
+        interactions,
+    })
+}
+
+/// Call a recorded tool again and compare its result with the recorded result or error
+pub fn replay_tool_call(
+    client: &McpClient,
+    redactor: &Redactor,
+    tool: &str,
+    arguments: &Value,
+    recorded: Result<&str, &str>,
+) -> Outcome {
+    if is_redacted(arguments) {
+        return Outcome::Skipped("its arguments were redacted in the log".to_string());
+    }
+    let arguments = (!arguments.is_null()).then(|| arguments.clone());
+    let replayed = match client.call_tool(tool, arguments) {
+        Ok(result) => Ok(redactor.redact(&result.to_string())),
+        Err(e) => Err(redactor.redact(&e.to_string())),
+    };
+    let replayed = replayed.as_deref().map_err(String::as_str);
+    if replayed == recorded {
+        return Outcome::Same;
+    }
+    let render = |outcome: Result<&str, &str>| match outcome {
+        Ok(result) => render_result(result),
+        Err(error) => format!("Error: {}\n", error),
+    };
+    Outcome::Changed(diff(&render(recorded), &render(replayed)))
+}
+
+/// Send a recorded conversation to the model again and compare the tool calls it asks
+/// for with the recorded ones
+pub fn replay_model_request(
+    ollama: &Ollama,
+    conversation: &[ChatMessage],
+    recorded: Result<&[OllamaToolCall], &str>,
+) -> Outcome {
+    let replayed = ollama.chat_messages(conversation.to_vec());
+    let describe = |outcome: Result<&[OllamaToolCall], String>| match outcome {
+        Ok([]) => "No tool calls\n".to_string(),
+        Ok(calls) => calls
+            .iter()
+            .map(|call| format!("{} {}\n", call.function.name, call.function.arguments))
+            .collect(),
+        Err(error) => format!("Error: {}\n", error),
+    };
+    let recorded = describe(recorded.map_err(str::to_string));
+    let replayed = describe(match &replayed {
+        Ok(response) => Ok(response.message.tool_calls.as_deref().unwrap_or_default()),
+        Err(e) => Err(e.to_string()),
+    });
+    if recorded == replayed {
+        Outcome::Same
+    } else {
+        Outcome::Changed(diff(&recorded, &replayed))
+    }
+}
+
+/// Whether redaction replaced part of the arguments, which then differ from those sent
+fn is_redacted(arguments: &Value) -> bool {
+    match arguments {
+        Value::String(text) => text.contains(REDACTED),
+        Value::Array(items) => items.iter().any(is_redacted),
+        Value::Object(members) => members.values().any(is_redacted),
+        _ => false,
+    }
+}
+
+/// A tools/call result as lines to diff: the text content as is, other content as JSON
+fn render_result(result: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(result) else {
+        return format!("{}\n", result);
+    };
+    let Some(content) = value.get("content").and_then(Value::as_array) else {
+        return format!("{:#}\n", value);
+    };
+    let mut text = String::new();
+    if value.get("isError").and_then(Value::as_bool) == Some(true) {
+        text.push_str("isError: true\n");
+    }
+    for item in content {
+        match item.get("text").and_then(Value::as_str) {
+            Some(content) => text.push_str(content),
+            None => text.push_str(&item.to_string()),
+        }
+        text.push('\n');
+    }
+    if let Some(structured) = value.get("structuredContent") {
+        text.push_str(&format!("structuredContent: {:#}\n", structured));
+    }
+    text
+}
+
+fn diff(recorded: &str, replayed: &str) -> String {
+    TextDiff::configure()
+        .timeout(DIFF_DEADLINE)
+        .diff_lines(recorded, replayed)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header("recorded", "replayed")
+        .to_string()
+}
@@ -0,0 +1,564 @@
+//! Repository API tools module
+//!
+//! This module talks to the GitHub and GitLab REST APIs for the repositories named in
+//! the server config. It lists issues and pull/merge requests, fetches the diff of a
+//! pull request and reads files at a given ref, so code-review workflows go through
+//! the MCP server with a token the model never sees.
+
+use crate::config::{RepositoryConfig, RepositoryProvider};
+use reqwest::Url;
+use reqwest::blocking::{Client, RequestBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Read;
+use std::time::Duration;
+
+/// Default number of issues or pull requests listed
+const DEFAULT_ITEMS: usize = 20;
+
+/// Upper bound on the number of issues or pull requests listed
+const MAX_ITEMS: usize = 100;
+
+/// Upper bound on the size of a diff or file returned
+const MAX_CONTENT_BYTES: u64 = 512 * 1024;
+
+/// Kind of item listed by the repo_list tool
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    #[default]
+    Issue,
+    /// A GitHub pull request or GitLab merge request
+    Pull,
+}
+
+/// State filter of the repo_list tool
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemState {
+    #[default]
+    Open,
+    Closed,
+    All,
+}
+
+/// Repository list tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RepoListRequest {
+    /// The name of the configured repository (optional when only one is configured)
+    pub repository: Option<String>,
+    /// List issues or pull/merge requests (default issue)
+    #[serde(default)]
+    pub kind: ItemKind,
+    /// Filter by state (default open)
+    #[serde(default)]
+    pub state: ItemState,
+    /// Maximum number of items to return (default 20, max 100)
+    pub limit: Option<usize>,
+}
+
+/// An issue or pull request
+#[derive(Debug, Serialize)]
+pub struct RepoItem {
+    pub number: u64,
+    pub title: String,
+    pub state: String,
+    pub author: Option<String>,
+    pub url: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Repository list tool response
+#[derive(Debug, Serialize)]
+pub struct RepoListResponse {
+    pub repository: String,
+    pub kind: ItemKind,
+    pub items: Vec<RepoItem>,
+}
+
+/// Pull request diff tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RepoPrDiffRequest {
+    /// The name of the configured repository (optional when only one is configured)
+    pub repository: Option<String>,
+    /// The pull or merge request number
+    pub number: u64,
+}
+
+/// Repository file read tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RepoFileReadRequest {
+    /// The name of the configured repository (optional when only one is configured)
+    pub repository: Option<String>,
+    /// The file path within the repository
+    pub path: String,
+    /// Branch, tag or commit (default branch when omitted)
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+}
+
+/// Text content fetched from a repository
+#[derive(Debug, Serialize)]
+pub struct RepoContent {
+    pub repository: String,
+    pub content: String,
+    pub truncated: bool,
+}
+
+impl RepoContent {
+    /// The content, followed by a note when it was truncated
+    pub fn into_text(self) -> String {
+        if self.truncated {
+            format!(
+                "{}\n[truncated at {} bytes]",
+                self.content, MAX_CONTENT_BYTES
+            )
+        } else {
+            self.content
+        }
+    }
+}
+
+/// Execute the repository list tool
+pub fn execute_repo_list(
+    request: RepoListRequest,
+    repositories: &[RepositoryConfig],
+) -> Result<RepoListResponse, Box<dyn std::error::Error>> {
+    let repository = find_repository(repositories, request.repository.as_deref())?;
+    let limit = request.limit.unwrap_or(DEFAULT_ITEMS).clamp(1, MAX_ITEMS);
+    let api = Api::new(repository)?;
+
+    let items = match repository.provider {
+        RepositoryProvider::Github => {
+            let state = match request.state {
+                ItemState::Open => "open",
+                ItemState::Closed => "closed",
+                ItemState::All => "all",
+            };
+            let endpoint = match request.kind {
+                ItemKind::Issue => "issues",
+                ItemKind::Pull => "pulls",
+            };
+            // The issues endpoint also returns pull requests, so ask for extra items
+            let per_page = if request.kind == ItemKind::Issue {
+                MAX_ITEMS
+            } else {
+                limit
+            };
+            let url = api.url(
+                &[endpoint],
+                &[("state", state), ("per_page", &per_page.to_string())],
+            )?;
+            let items: Vec<Value> = api.get(url)?.json()?;
+            items
+                .iter()
+                .filter(|item| request.kind == ItemKind::Pull || item.get("pull_request").is_none())
+                .take(limit)
+                .map(|item| RepoItem {
+                    number: item["number"].as_u64().unwrap_or_default(),
+                    title: string_field(item, "title").unwrap_or_default(),
+                    state: string_field(item, "state").unwrap_or_default(),
+                    author: item["user"]["login"].as_str().map(str::to_string),
+                    url: string_field(item, "html_url"),
+                    updated_at: string_field(item, "updated_at"),
+                })
+                .collect()
+        }
+        RepositoryProvider::Gitlab => {
+            let state = match request.state {
+                ItemState::Open => "opened",
+                ItemState::Closed => "closed",
+                ItemState::All => "all",
+            };
+            let endpoint = match request.kind {
+                ItemKind::Issue => "issues",
+                ItemKind::Pull => "merge_requests",
+            };
+            let url = api.url(
+                &[endpoint],
+                &[("state", state), ("per_page", &limit.to_string())],
+            )?;
+            let items: Vec<Value> = api.get(url)?.json()?;
+            items
+                .iter()
+                .take(limit)
+                .map(|item| RepoItem {
+                    number: item["iid"].as_u64().unwrap_or_default(),
+                    title: string_field(item, "title").unwrap_or_default(),
+                    state: string_field(item, "state").unwrap_or_default(),
+                    author: item["author"]["username"].as_str().map(str::to_string),
+                    url: string_field(item, "web_url"),
+                    updated_at: string_field(item, "updated_at"),
+                })
+                .collect()
+        }
+    };
+
+    Ok(RepoListResponse {
+        repository: repository.name.clone(),
+        kind: request.kind,
+        items,
+    })
+}
+
+/// Execute the pull request diff tool
+pub fn execute_repo_pr_diff(
+    request: RepoPrDiffRequest,
+    repositories: &[RepositoryConfig],
+) -> Result<RepoContent, Box<dyn std::error::Error>> {
+    let repository = find_repository(repositories, request.repository.as_deref())?;
+    let api = Api::new(repository)?;
+    let number = request.number.to_string();
+
+    let (content, truncated) = match repository.provider {
+        RepositoryProvider::Github => {
+            let url = api.url(&["pulls", &number], &[])?;
+            let response = api.get_with(url, |builder| {
+                builder.header("Accept", "application/vnd.github.diff")
+            })?;
+            read_limited(response)?
+        }
+        RepositoryProvider::Gitlab => {
+            let url = api.url(
+                &["merge_requests", &number, "diffs"],
+                &[("per_page", "100")],
+            )?;
+            let files: Vec<Value> = api.get(url)?.json()?;
+            let mut diff = String::new();
+            for file in &files {
+                let old_path = file["old_path"].as_str().unwrap_or_default();
+                let new_path = file["new_path"].as_str().unwrap_or_default();
+                diff.push_str(&format!(
+                    "diff --git a/{old_path} b/{new_path}\n--- a/{old_path}\n+++ b/{new_path}\n{}",
+                    file["diff"].as_str().unwrap_or_default()
+                ));
+                if !diff.ends_with('\n') {
+                    diff.push('\n');
+                }
+            }
+            truncate(diff)
+        }
+    };
+
+    Ok(RepoContent {
+        repository: repository.name.clone(),
+        content,
+        truncated,
+    })
+}
+
+/// Execute the repository file read tool
+pub fn execute_repo_file_read(
+    request: RepoFileReadRequest,
+    repositories: &[RepositoryConfig],
+) -> Result<RepoContent, Box<dyn std::error::Error>> {
+    let repository = find_repository(repositories, request.repository.as_deref())?;
+    let api = Api::new(repository)?;
+    let path = request.path.trim_start_matches('/');
+    if path.is_empty() || path.split('/').any(|part| part == "..") {
+        return Err(format!("Invalid repository path: {}", request.path).into());
+    }
+
+    let response = match repository.provider {
+        RepositoryProvider::Github => {
+            let mut segments = vec!["contents"];
+            segments.extend(path.split('/'));
+            let query: Vec<(&str, &str)> = request
+                .git_ref
+                .as_deref()
+                .map(|git_ref| ("ref", git_ref))
+                .into_iter()
+                .collect();
+            let url = api.url(&segments, &query)?;
+            api.get_with(url, |builder| {
+                builder.header("Accept", "application/vnd.github.raw+json")
+            })?
+        }
+        RepositoryProvider::Gitlab => {
+            let git_ref = request.git_ref.as_deref().unwrap_or("HEAD");
+            let url = api.url(&["repository", "files", path, "raw"], &[("ref", git_ref)])?;
+            api.get(url)?
+        }
+    };
+    let (content, truncated) = read_limited(response)?;
+
+    Ok(RepoContent {
+        repository: repository.name.clone(),
+        content,
+        truncated,
+    })
+}
+
+/// Format a list response as one line per item
+pub fn format_items(response: &RepoListResponse) -> String {
+    let label = match response.kind {
+        ItemKind::Issue => "issues",
+        ItemKind::Pull => "pull requests",
+    };
+    if response.items.is_empty() {
+        return format!("No {} found in {}", label, response.repository);
+    }
+
+    let mut text = format!(
+        "{} {} in {}:\n",
+        response.items.len(),
+        label,
+        response.repository
+    );
+    for item in &response.items {
+        text.push_str(&format!("#{} [{}] {}", item.number, item.state, item.title));
+        if let Some(author) = &item.author {
+            text.push_str(&format!(" (@{})", author));
+        }
+        if let Some(updated_at) = &item.updated_at {
+            text.push_str(&format!(" updated {}", updated_at));
+        }
+        if let Some(url) = &item.url {
+            text.push_str(&format!(" {}", url));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Look up a configured repository, defaulting to the only one
+fn find_repository<'a>(
+    repositories: &'a [RepositoryConfig],
+    name: Option<&str>,
+) -> Result<&'a RepositoryConfig, Box<dyn std::error::Error>> {
+    match (name, repositories) {
+        (Some(name), _) => repositories
+            .iter()
+            .find(|repository| repository.name == name)
+            .ok_or_else(|| format!("Unknown repository '{}'", name).into()),
+        (None, [repository]) => Ok(repository),
+        (None, _) => Err("Several repositories are configured, 'repository' is required".into()),
+    }
+}
+
+/// A client for one repository's REST API
+struct Api<'a> {
+    repository: &'a RepositoryConfig,
+    client: Client,
+    token: Option<String>,
+}
+
+impl<'a> Api<'a> {
+    fn new(repository: &'a RepositoryConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            repository,
+            client: Client::builder()
+                .timeout(Duration::from_secs(30))
+                .user_agent(concat!("mcp-server/", env!("CARGO_PKG_VERSION")))
+                .build()?,
+            token: resolve_token(repository)?,
+        })
+    }
+
+    /// Build an API URL below the repository, encoding each path segment
+    fn url(
+        &self,
+        segments: &[&str],
+        query: &[(&str, &str)],
+    ) -> Result<Url, Box<dyn std::error::Error>> {
+        let (base, prefix) = match self.repository.provider {
+            RepositoryProvider::Github => ("https://api.github.com", "repos"),
+            RepositoryProvider::Gitlab => ("https://gitlab.com/api/v4", "projects"),
+        };
+        let base = self.repository.api_url.as_deref().unwrap_or(base);
+        let mut url = Url::parse(base).map_err(|e| format!("Invalid API URL: {}", e))?;
+
+        {
+            let mut path = url
+                .path_segments_mut()
+                .map_err(|_| "Invalid API URL: cannot be a base")?;
+            path.pop_if_empty().push(prefix);
+            match self.repository.provider {
+                // GitHub addresses repositories as /repos/{owner}/{repo}
+                RepositoryProvider::Github => {
+                    path.extend(self.repository.repo.split('/'));
+                }
+                // GitLab takes the URL-encoded project path as a single segment
+                RepositoryProvider::Gitlab => {
+                    path.push(&self.repository.repo);
+                }
+            }
+            path.extend(segments);
+        }
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        Ok(url)
+    }
+
+    fn get(&self, url: Url) -> Result<reqwest::blocking::Response, Box<dyn std::error::Error>> {
+        self.get_with(url, |builder| builder)
+    }
+
+    /// Send an authenticated GET request and fail on error statuses
+    fn get_with(
+        &self,
+        url: Url,
+        customize: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<reqwest::blocking::Response, Box<dyn std::error::Error>> {
+        let mut builder = self.client.get(url);
+        builder = match (&self.token, self.repository.provider) {
+            (Some(token), RepositoryProvider::Github) => builder
+                .bearer_auth(token)
+                .header("X-GitHub-Api-Version", "2022-11-28"),
+            (Some(token), RepositoryProvider::Gitlab) => builder.header("PRIVATE-TOKEN", token),
+            (None, _) => builder,
+        };
+
+        let response = customize(builder).send()?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            let message = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|error| string_field(&error, "message"))
+                .unwrap_or(body);
+            return Err(format!("API request failed with status {}: {}", status, message).into());
+        }
+        Ok(response)
+    }
+}
+
+/// Check that the token of every repository can be read
+pub fn check(repositories: &[RepositoryConfig]) -> Result<(), Box<dyn std::error::Error>> {
+    for repository in repositories {
+        resolve_token(repository)
+            .map_err(|e| format!("Repository '{}': {}", repository.name, e))?;
+    }
+    Ok(())
+}
+
+/// Read the repository token from the config, an environment variable or the keyring
+fn resolve_token(
+    repository: &RepositoryConfig,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    if let Some(variable) = &repository.token_env {
+        return Ok(Some(std::env::var(variable).map_err(|_| {
+            format!("Repository token variable '{}' is not set", variable)
+        })?));
+    }
+
+    if let Some(entry) = &repository.token_keyring {
+        #[cfg(feature = "keyring")]
+        {
+            let token = keyring::Entry::new(crate::config::KEYRING_SERVICE, entry)?
+                .get_password()
+                .map_err(|e| format!("Failed to read keyring entry '{}': {}", entry, e))?;
+            return Ok(Some(token));
+        }
+        #[cfg(not(feature = "keyring"))]
+        return Err(format!(
+            "Keyring entry '{}' is configured but this build lacks the 'keyring' feature",
+            entry
+        )
+        .into());
+    }
+
+    Ok(repository.token.clone())
+}
+
+/// Read a response body as text, truncated to the content limit
+fn read_limited(
+    response: reqwest::blocking::Response,
+) -> Result<(String, bool), Box<dyn std::error::Error>> {
+    let mut body = Vec::new();
+    response
+        .take(MAX_CONTENT_BYTES + 1)
+        .read_to_end(&mut body)?;
+    Ok(truncate(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Truncate text to the content limit on a character boundary
+fn truncate(mut text: String) -> (String, bool) {
+    if text.len() as u64 <= MAX_CONTENT_BYTES {
+        return (text, false);
+    }
+    let mut end = MAX_CONTENT_BYTES as usize;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    (text, true)
+}
+
+/// Get a string field of a JSON object
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+/// Restrict the repository argument of a tool schema to the configured names
+fn with_repository_names(mut schema: Value, repositories: &[RepositoryConfig]) -> Value {
+    let names: Vec<&str> = repositories.iter().map(|r| r.name.as_str()).collect();
+    schema["properties"]["repository"]["enum"] = serde_json::json!(names);
+    schema
+}
+
+/// Get the tool definition for the repo_list tool
+pub fn get_list_tool_definition(repositories: &[RepositoryConfig]) -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "repo_list".to_string(),
+        version: None,
+        description: Some("List issues or pull/merge requests of a configured GitHub or GitLab repository with their number, state, author and link.".to_string()),
+        input_schema: Some(with_repository_names(
+            crate::schema::input_schema::<RepoListRequest>(),
+            repositories,
+        )),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            title: Some("List issues and pull requests".to_string()),
+            read_only_hint: Some(true),
+            open_world_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Get the tool definition for the repo_pr_diff tool
+pub fn get_pr_diff_tool_definition(repositories: &[RepositoryConfig]) -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "repo_pr_diff".to_string(),
+        version: None,
+        description: Some("Fetch the unified diff of a pull request (GitHub) or merge request (GitLab) in a configured repository.".to_string()),
+        input_schema: Some(with_repository_names(
+            crate::schema::input_schema::<RepoPrDiffRequest>(),
+            repositories,
+        )),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            title: Some("Fetch pull request diff".to_string()),
+            read_only_hint: Some(true),
+            open_world_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Get the tool definition for the repo_file_read tool
+pub fn get_file_read_tool_definition(repositories: &[RepositoryConfig]) -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "repo_file_read".to_string(),
+        version: None,
+        description: Some(
+            "Read a file from a configured GitHub or GitLab repository at a branch, tag or commit."
+                .to_string(),
+        ),
+        input_schema: Some(with_repository_names(
+            crate::schema::input_schema::<RepoFileReadRequest>(),
+            repositories,
+        )),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            title: Some("Read repository file".to_string()),
+            read_only_hint: Some(true),
+            open_world_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
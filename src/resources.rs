@@ -0,0 +1,654 @@
+//! Resources module
+//!
+//! This module implements the MCP resources capability: data the client reads by
+//! URI instead of calling a tool, such as files or the latest result of a scheduled
+//! job. Resources come from providers registered on the server, each serving one URI
+//! scheme; `resources/list` gathers the resources of every provider and
+//! `resources/read` routes a URI to the provider of its scheme. Sessions subscribe to
+//! resources with `resources/subscribe` and are then sent
+//! `notifications/resources/updated` when they change, either by the provider itself
+//! or by a watcher polling the resource's fingerprint.
+
+use crate::mcp::{JsonRpcError, JsonRpcNotification, McpServerState};
+use crate::pagination::PaginatedRequest;
+use crate::vfs::{FileKind, VirtualFs};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// JSON-RPC error code of reads of unknown resources
+pub const RESOURCE_NOT_FOUND: i32 = -32002;
+
+/// Maximum number of files listed by [`FileResources`]
+const MAX_LISTED_FILES: usize = 1000;
+
+/// Interval at which the watcher checks subscribed resources for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Method of the notification sent when a subscribed resource changes
+const UPDATED_METHOD: &str = "notifications/resources/updated";
+
+/// A resource the server exposes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    /// Short name shown to the user
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Contents of a resource, as text or base64-encoded binary data
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Base64-encoded payload for binary resources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+impl ResourceContents {
+    /// Create text contents
+    pub fn text(
+        uri: impl Into<String>,
+        text: impl Into<String>,
+        mime_type: Option<String>,
+    ) -> Self {
+        Self {
+            uri: uri.into(),
+            mime_type,
+            text: Some(text.into()),
+            blob: None,
+        }
+    }
+
+    /// Create binary contents, encoded as base64
+    pub fn blob(uri: impl Into<String>, data: &[u8], mime_type: Option<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            mime_type,
+            text: None,
+            blob: Some(STANDARD.encode(data)),
+        }
+    }
+}
+
+/// Response from resources/list request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourcesListResponse {
+    pub resources: Vec<Resource>,
+    /// Cursor of the next page, absent on the last one
+    #[serde(
+        rename = "nextCursor",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub next_cursor: Option<String>,
+}
+
+/// Request for resources/read endpoint
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourcesReadRequest {
+    pub uri: String,
+}
+
+/// Response from resources/read request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourcesReadResponse {
+    pub contents: Vec<ResourceContents>,
+}
+
+/// Request for resources/subscribe and resources/unsubscribe endpoints
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourcesSubscribeRequest {
+    pub uri: String,
+}
+
+/// Future returned by the methods of [`ResourceProvider`]
+pub type ResourceFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, JsonRpcError>> + Send + 'a>>;
+
+/// A source of resources under one URI scheme
+///
+/// Providers are registered with [`crate::mcp::McpServer::register_resources`]; a
+/// provider for a scheme that is already served replaces the previous one.
+pub trait ResourceProvider: Send + Sync {
+    /// URI scheme of the resources, without `://`, e.g. `file`
+    fn scheme(&self) -> &str;
+
+    /// Resources currently available
+    fn list<'a>(&'a self, state: &'a McpServerState) -> ResourceFuture<'a, Vec<Resource>>;
+
+    /// Read a resource; `uri` always has this provider's scheme
+    fn read<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        uri: &'a str,
+    ) -> ResourceFuture<'a, Vec<ResourceContents>>;
+
+    /// Value that changes whenever the resource does, such as a modification time
+    ///
+    /// The watcher polls it for subscribed resources and notifies their subscribers
+    /// when it changes. Providers that publish `notifications/resources/updated`
+    /// themselves keep the default, `None`, and are not polled.
+    fn fingerprint<'a>(
+        &'a self,
+        _state: &'a McpServerState,
+        _uri: &'a str,
+    ) -> ResourceFuture<'a, Option<String>> {
+        Box::pin(async { Ok(None) })
+    }
+}
+
+impl fmt::Debug for dyn ResourceProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResourceProvider")
+            .field(&self.scheme())
+            .finish()
+    }
+}
+
+/// List a page of the resources of every provider, ordered by scheme
+///
+/// Resources the API key does not permit are left out.
+pub async fn list(
+    state: &McpServerState,
+    request: &PaginatedRequest,
+    api_key: Option<&crate::auth::ApiKey>,
+) -> Result<ResourcesListResponse, JsonRpcError> {
+    let offset = request.offset()?;
+    let mut providers: Vec<_> = state.resources.read().await.values().cloned().collect();
+    providers.sort_by(|a, b| a.scheme().cmp(b.scheme()));
+
+    let mut resources = Vec::new();
+    for provider in providers {
+        resources.extend(
+            provider
+                .list(state)
+                .await?
+                .into_iter()
+                .filter(|resource| api_key.is_none_or(|key| key.allows_resource(&resource.uri))),
+        );
+    }
+    let (resources, next_cursor) =
+        crate::pagination::page(resources, offset, crate::pagination::page_size(state));
+    Ok(ResourcesListResponse {
+        resources,
+        next_cursor,
+    })
+}
+
+/// Read a resource through the provider of its URI scheme
+pub async fn read(
+    state: &McpServerState,
+    uri: &str,
+) -> Result<ResourcesReadResponse, JsonRpcError> {
+    let provider = provider(state, uri).await.ok_or_else(|| not_found(uri))?;
+    let contents = provider.read(state, uri).await?;
+    Ok(ResourcesReadResponse { contents })
+}
+
+/// The provider serving a URI's scheme
+async fn provider(state: &McpServerState, uri: &str) -> Option<Arc<dyn ResourceProvider>> {
+    let (scheme, _) = uri.split_once("://")?;
+    state.resources.read().await.get(scheme).cloned()
+}
+
+/// Subscribe a session to the updates of a resource
+pub async fn subscribe(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    uri: &str,
+) -> Result<(), JsonRpcError> {
+    if provider(state, uri).await.is_none() {
+        return Err(not_found(uri));
+    }
+    let mut sessions = state.sessions.write().await;
+    let session = session_id
+        .and_then(|id| sessions.get_mut(id))
+        .ok_or_else(|| JsonRpcError {
+            code: -32600,
+            message: "Subscribing to resources requires a session".to_string(),
+            data: None,
+        })?;
+    session.subscriptions.insert(uri.to_string());
+    Ok(())
+}
+
+/// Stop sending a session the updates of a resource
+pub async fn unsubscribe(state: &McpServerState, session_id: Option<&str>, uri: &str) {
+    if let Some(session) = state
+        .sessions
+        .write()
+        .await
+        .get_mut(session_id.unwrap_or_default())
+    {
+        session.subscriptions.remove(uri);
+    }
+}
+
+/// Whether a notification is sent to a session
+///
+/// Resource updates only reach the sessions subscribed to the resource, and the
+/// progress of a tool call the session that made it; every other notification reaches
+/// every session.
+pub async fn delivers(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    notification: &JsonRpcNotification,
+) -> bool {
+    if notification.id.is_some() {
+        return crate::server_request::delivers(state, session_id, notification);
+    }
+    if notification.method == crate::progress::PROGRESS_METHOD {
+        return crate::progress::delivers(state, session_id, notification);
+    }
+    if notification.method != UPDATED_METHOD {
+        return true;
+    }
+    let Some(uri) = notification
+        .params
+        .as_ref()
+        .and_then(|params| params.get("uri"))
+        .and_then(|uri| uri.as_str())
+    else {
+        return false;
+    };
+    let sessions = state.sessions.read().await;
+    session_id
+        .and_then(|id| sessions.get(id))
+        .is_some_and(|session| session.subscriptions.contains(uri))
+}
+
+/// Start the watcher notifying subscribers of changed resources
+///
+/// Every couple of seconds, the fingerprint of each subscribed resource is compared
+/// with the previous one; the first check of a resource only records it.
+pub fn watch(state: &McpServerState) {
+    tokio::spawn(run_watcher(state.clone()));
+}
+
+async fn run_watcher(state: McpServerState) {
+    let mut fingerprints: HashMap<String, String> = HashMap::new();
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let uris: HashSet<String> = state
+            .sessions
+            .read()
+            .await
+            .values()
+            .flat_map(|session| session.subscriptions.iter().cloned())
+            .collect();
+        fingerprints.retain(|uri, _| uris.contains(uri));
+
+        for uri in uris {
+            let Some(provider) = provider(&state, &uri).await else {
+                continue;
+            };
+            let Ok(Some(fingerprint)) = provider.fingerprint(&state, &uri).await else {
+                continue;
+            };
+            match fingerprints.insert(uri.clone(), fingerprint.clone()) {
+                Some(previous) if previous != fingerprint => {
+                    state.notify(UPDATED_METHOD, Some(serde_json::json!({ "uri": uri })));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The error returned for URIs no resource is served at
+pub fn not_found(uri: &str) -> JsonRpcError {
+    JsonRpcError {
+        code: RESOURCE_NOT_FOUND,
+        message: format!("Resource not found: {}", uri),
+        data: Some(serde_json::json!({ "uri": uri })),
+    }
+}
+
+/// Files within the allowed directories, as `file://` resources
+///
+/// Listing walks the allowed directories, without following symbolic links, and stops
+/// after the first thousand files; any file within them can be read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileResources;
+
+impl ResourceProvider for FileResources {
+    fn scheme(&self) -> &str {
+        "file"
+    }
+
+    fn list<'a>(&'a self, state: &'a McpServerState) -> ResourceFuture<'a, Vec<Resource>> {
+        let fs = Arc::clone(&state.fs);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let mut resources = Vec::new();
+                for root in crate::file_read::ALLOWED_ROOTS {
+                    list_files(fs.as_ref(), Path::new(root), root, &mut resources);
+                }
+                resources
+            })
+            .await
+            .map_err(|e| internal_error(format!("Failed to list files: {}", e)))
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        uri: &'a str,
+    ) -> ResourceFuture<'a, Vec<ResourceContents>> {
+        let uri = uri.to_string();
+        let fs = Arc::clone(&state.fs);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || read_file(fs.as_ref(), &uri))
+                .await
+                .map_err(|e| internal_error(format!("Failed to read file: {}", e)))?
+        })
+    }
+
+    fn fingerprint<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        uri: &'a str,
+    ) -> ResourceFuture<'a, Option<String>> {
+        let uri = uri.to_string();
+        let fs = Arc::clone(&state.fs);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                // A deleted file is a change too
+                let metadata = file_path(fs.as_ref(), &uri)
+                    .ok()
+                    .and_then(|path| fs.metadata(&path).ok());
+                Some(match metadata {
+                    Some(metadata) => format!("{:?}:{}", metadata.modified, metadata.len),
+                    None => "missing".to_string(),
+                })
+            })
+            .await
+            .map_err(|e| internal_error(format!("Failed to check file: {}", e)))
+        })
+    }
+}
+
+/// Add the files below `directory` to `resources`, named by their path within `root`
+fn list_files(fs: &dyn VirtualFs, directory: &Path, root: &str, resources: &mut Vec<Resource>) {
+    let Ok(mut entries) = fs.read_dir(directory) else {
+        return;
+    };
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    for entry in entries {
+        if resources.len() >= MAX_LISTED_FILES {
+            return;
+        }
+        match entry.kind {
+            FileKind::Dir => list_files(fs, &entry.path, root, resources),
+            FileKind::File => {
+                let path = entry.path.to_string_lossy();
+                resources.push(Resource {
+                    uri: crate::file_read::file_uri(&path),
+                    name: path.strip_prefix(root).unwrap_or(&path).to_string(),
+                    description: None,
+                    mime_type: crate::mime::detect_file(fs, &entry.path),
+                });
+            }
+            FileKind::Other => {}
+        }
+    }
+}
+
+/// The path of a `file://` resource, checked to be within the allowed directories
+fn file_path(fs: &dyn VirtualFs, uri: &str) -> Result<PathBuf, JsonRpcError> {
+    let path = crate::file_read::path_from_file_uri(uri);
+    if Path::new(path.as_ref())
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(not_found(uri));
+    }
+    crate::file_read::validate_path(fs, &path)
+        .map(Path::to_path_buf)
+        .map_err(|e| JsonRpcError {
+            message: e.to_string(),
+            ..not_found(uri)
+        })
+}
+
+/// Read a `file://` resource, as text when it is valid UTF-8
+fn read_file(fs: &dyn VirtualFs, uri: &str) -> Result<Vec<ResourceContents>, JsonRpcError> {
+    let path = file_path(fs, uri)?;
+    let data = fs
+        .read(&path)
+        .map_err(|e| internal_error(format!("Failed to read file '{}': {}", uri, e)))?;
+    let mime_type = crate::mime::detect(&data, &path.to_string_lossy());
+    let contents = match String::from_utf8(data) {
+        Ok(text) => ResourceContents::text(uri, text, mime_type),
+        Err(e) => ResourceContents::blob(uri, e.as_bytes(), mime_type),
+    };
+    Ok(vec![contents])
+}
+
+/// Latest results of scheduled jobs, as `schedule://<name>` resources
+///
+/// A job's resource appears after its first run; its contents are the JSON
+/// [`crate::scheduler::ScheduledResult`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScheduleResources;
+
+impl ResourceProvider for ScheduleResources {
+    fn scheme(&self) -> &str {
+        crate::scheduler::SCHEDULE_URI_SCHEME.trim_end_matches("://")
+    }
+
+    fn list<'a>(&'a self, state: &'a McpServerState) -> ResourceFuture<'a, Vec<Resource>> {
+        Box::pin(async {
+            let results = state.scheduled_results.read().await;
+            let mut resources: Vec<Resource> = results
+                .values()
+                .map(|result| Resource {
+                    uri: result.uri.clone(),
+                    name: result.name.clone(),
+                    description: Some(format!(
+                        "Latest result of the scheduled '{}' tool call",
+                        result.tool
+                    )),
+                    mime_type: Some("application/json".to_string()),
+                })
+                .collect();
+            resources.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(resources)
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        uri: &'a str,
+    ) -> ResourceFuture<'a, Vec<ResourceContents>> {
+        Box::pin(async move {
+            let result = state
+                .get_scheduled_result(uri)
+                .await
+                .ok_or_else(|| not_found(uri))?;
+            let text = serde_json::to_string_pretty(&result)
+                .map_err(|e| internal_error(format!("Failed to serialize result: {}", e)))?;
+            Ok(vec![ResourceContents::text(
+                uri,
+                text,
+                Some("application/json".to_string()),
+            )])
+        })
+    }
+}
+
+/// Resumable transfers, as `transfer://<path>` resources
+///
+/// A transfer's resource appears when it starts; its contents are the JSON
+/// [`crate::transfer::Transfer`], reporting the bytes received so far and the partial
+/// file holding them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransferResources;
+
+impl ResourceProvider for TransferResources {
+    fn scheme(&self) -> &str {
+        crate::transfer::TRANSFER_URI_SCHEME.trim_end_matches("://")
+    }
+
+    fn list<'a>(&'a self, state: &'a McpServerState) -> ResourceFuture<'a, Vec<Resource>> {
+        Box::pin(async {
+            let transfers = state.transfers.read().await;
+            let mut resources: Vec<Resource> = transfers
+                .values()
+                .map(|transfer| Resource {
+                    uri: transfer.uri.clone(),
+                    name: transfer.path.clone(),
+                    description: Some(format!("Transfer of {}", transfer.url)),
+                    mime_type: Some("application/json".to_string()),
+                })
+                .collect();
+            resources.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(resources)
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        uri: &'a str,
+    ) -> ResourceFuture<'a, Vec<ResourceContents>> {
+        Box::pin(async move {
+            let transfer = state
+                .transfers
+                .read()
+                .await
+                .get(uri)
+                .cloned()
+                .ok_or_else(|| not_found(uri))?;
+            let text = serde_json::to_string_pretty(&transfer)
+                .map_err(|e| internal_error(format!("Failed to serialize transfer: {}", e)))?;
+            Ok(vec![ResourceContents::text(
+                uri,
+                text,
+                Some("application/json".to_string()),
+            )])
+        })
+    }
+}
+
+/// Resources about the server itself, under `mcp://server/`
+///
+/// [`crate::server_info::INFO_URI`] describes the server, and
+/// [`crate::server_log::LOG_URI`] holds its recent log messages unless `logs` is
+/// false; subscribers of the log are notified when new messages are logged.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerResources {
+    pub logs: bool,
+}
+
+impl Default for ServerResources {
+    fn default() -> Self {
+        Self { logs: true }
+    }
+}
+
+impl ServerResources {
+    fn serves(&self, uri: &str) -> bool {
+        uri == crate::server_info::INFO_URI || (self.logs && uri == crate::server_log::LOG_URI)
+    }
+}
+
+impl ResourceProvider for ServerResources {
+    fn scheme(&self) -> &str {
+        "mcp"
+    }
+
+    fn list<'a>(&'a self, _state: &'a McpServerState) -> ResourceFuture<'a, Vec<Resource>> {
+        Box::pin(async {
+            let mut resources = vec![Resource {
+                uri: crate::server_info::INFO_URI.to_string(),
+                name: "Server info".to_string(),
+                description: Some(
+                    "Version, features, tools, limits and uptime of the server".to_string(),
+                ),
+                mime_type: Some("application/json".to_string()),
+            }];
+            if self.logs {
+                resources.push(Resource {
+                    uri: crate::server_log::LOG_URI.to_string(),
+                    name: "Server log".to_string(),
+                    description: Some(
+                        "Recent log messages of the server, including failed tool calls"
+                            .to_string(),
+                    ),
+                    mime_type: Some("text/plain".to_string()),
+                });
+            }
+            Ok(resources)
+        })
+    }
+
+    fn read<'a>(
+        &'a self,
+        state: &'a McpServerState,
+        uri: &'a str,
+    ) -> ResourceFuture<'a, Vec<ResourceContents>> {
+        Box::pin(async move {
+            if !self.serves(uri) {
+                return Err(not_found(uri));
+            }
+            if uri == crate::server_info::INFO_URI {
+                let info = crate::server_info::collect(state).await;
+                let text = serde_json::to_string_pretty(&info)
+                    .map_err(|e| internal_error(format!("Failed to serialize info: {}", e)))?;
+                return Ok(vec![ResourceContents::text(
+                    uri,
+                    text,
+                    Some("application/json".to_string()),
+                )]);
+            }
+            let text = crate::server_log::to_text(&crate::server_log::entries());
+            Ok(vec![ResourceContents::text(
+                uri,
+                text,
+                Some("text/plain".to_string()),
+            )])
+        })
+    }
+
+    fn fingerprint<'a>(
+        &'a self,
+        _state: &'a McpServerState,
+        uri: &'a str,
+    ) -> ResourceFuture<'a, Option<String>> {
+        // The info changes every second with the uptime, so it is not watched
+        Box::pin(async move {
+            Ok((self.logs && uri == crate::server_log::LOG_URI)
+                .then(|| crate::server_log::count().to_string()))
+        })
+    }
+}
+
+fn internal_error(message: String) -> JsonRpcError {
+    JsonRpcError {
+        code: -32603,
+        message,
+        data: None,
+    }
+}
@@ -0,0 +1,387 @@
+//! Response streaming module
+//!
+//! This module extracts the content of a tools/call response while it is being read,
+//! for clients that want a tool's output as data rather than as a message for the
+//! model. Text content is unescaped and binary content base64-decoded straight into a
+//! writer as the JSON arrives, so a result larger than memory can go to a file. Only
+//! the small remainder of the response, such as an error, is kept.
+
+use crate::error::McpError;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+/// Outcome of a tool call whose content was written to a writer
+#[derive(Debug, Clone, Default)]
+pub struct StreamedToolResult {
+    /// Bytes written, after decoding
+    pub bytes_written: u64,
+    /// Whether the tool reported a failure; the written content is then its error message
+    pub is_error: bool,
+    pub structured_content: Option<Value>,
+}
+
+/// Write the content of a tools/call JSON-RPC response read from `reader`
+///
+/// The `text` of each content item is written as is and the base64 `data` of images
+/// and audio decoded, in order and without separators. A JSON-RPC error is returned
+/// as an error.
+pub fn write_response_content(
+    reader: impl BufRead,
+    writer: &mut dyn Write,
+) -> Result<StreamedToolResult, McpError> {
+    let mut scanner = Scanner {
+        reader,
+        peeked: None,
+    };
+    let mut writer = CountingWriter { writer, count: 0 };
+    let mut result = StreamedToolResult::default();
+    let mut error = Value::Null;
+
+    scanner.expect(b'{')?;
+    while let Some(key) = scanner.next_key()? {
+        match key.as_str() {
+            "result" => scanner.result(&mut writer, &mut result)?,
+            "error" => error = scanner.value()?,
+            _ => scanner.skip_value()?,
+        }
+    }
+
+    if !error.is_null() {
+        let error: crate::mcp::JsonRpcError = serde_json::from_value(error)?;
+        return Err(error.into());
+    }
+    writer.flush()?;
+    result.bytes_written = writer.count;
+    Ok(result)
+}
+
+/// Write the content of an already parsed tools/call result
+///
+/// Used for transports that deliver responses whole.
+pub fn write_result_content(
+    result: &Value,
+    writer: &mut dyn Write,
+) -> Result<StreamedToolResult, McpError> {
+    let mut writer = CountingWriter { writer, count: 0 };
+    let items = result.get("content").and_then(Value::as_array);
+    for item in items.into_iter().flatten() {
+        if let Some(text) = item.get("text").and_then(Value::as_str) {
+            writer.write_all(text.as_bytes())?;
+        }
+        if let Some(data) = item.get("data").and_then(Value::as_str) {
+            let data = STANDARD
+                .decode(data)
+                .map_err(|e| McpError::protocol(format!("Invalid base64 content: {}", e)))?;
+            writer.write_all(&data)?;
+        }
+    }
+    writer.flush()?;
+    Ok(StreamedToolResult {
+        bytes_written: writer.count,
+        is_error: result
+            .get("isError")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        structured_content: result.get("structuredContent").cloned(),
+    })
+}
+
+/// Counts the bytes passed to a writer
+struct CountingWriter<'a> {
+    writer: &'a mut dyn Write,
+    count: u64,
+}
+
+impl Write for CountingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decodes base64 written to it, passing the bytes on to a writer
+struct Base64Writer<'a, W: Write> {
+    writer: &'a mut W,
+    /// Characters not yet forming a full four-character group
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Base64Writer<'_, W> {
+    fn finish(mut self) -> io::Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        self.decode(&pending)
+    }
+
+    fn decode(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let decoded = STANDARD
+            .decode(chunk)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.writer.write_all(&decoded)
+    }
+}
+
+impl<W: Write> Write for Base64Writer<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let complete = self.pending.len() / 4 * 4;
+        let rest = self.pending.split_off(complete);
+        let chunk = std::mem::replace(&mut self.pending, rest);
+        self.decode(&chunk)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Pull parser over the bytes of a JSON document
+struct Scanner<R: BufRead> {
+    reader: R,
+    peeked: Option<u8>,
+}
+
+impl<R: BufRead> Scanner<R> {
+    /// The `result` object: content items are written, `isError` and
+    /// `structuredContent` kept
+    fn result(
+        &mut self,
+        writer: &mut CountingWriter<'_>,
+        result: &mut StreamedToolResult,
+    ) -> io::Result<()> {
+        if self.peek()? == b'n' {
+            return self.skip_value();
+        }
+        self.expect(b'{')?;
+        while let Some(key) = self.next_key()? {
+            match key.as_str() {
+                "content" => {
+                    self.expect(b'[')?;
+                    while self.next_element()? {
+                        self.content_item(writer)?;
+                    }
+                }
+                "isError" => result.is_error = self.value()?.as_bool().unwrap_or(false),
+                "structuredContent" => result.structured_content = Some(self.value()?),
+                _ => self.skip_value()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn content_item(&mut self, writer: &mut CountingWriter<'_>) -> io::Result<()> {
+        self.expect(b'{')?;
+        while let Some(key) = self.next_key()? {
+            match (key.as_str(), self.peek()?) {
+                ("text", b'"') => self.string(writer)?,
+                ("data", b'"') => {
+                    let mut decoder = Base64Writer {
+                        writer,
+                        pending: Vec::new(),
+                    };
+                    self.string(&mut decoder)?;
+                    decoder.finish()?;
+                }
+                _ => self.skip_value()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        if let Some(byte) = self.peeked.take() {
+            return Ok(byte);
+        }
+        let mut byte = [0];
+        self.reader.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    /// The next byte that is not whitespace, without consuming it
+    fn peek(&mut self) -> io::Result<u8> {
+        loop {
+            let byte = self.byte()?;
+            if !byte.is_ascii_whitespace() {
+                self.peeked = Some(byte);
+                return Ok(byte);
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> io::Result<()> {
+        let found = self.peek()?;
+        self.peeked = None;
+        if found == expected {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "expected '{}', found '{}'",
+                expected as char, found as char
+            )))
+        }
+    }
+
+    /// The key of the next member of an object, after its colon; `None` at its end
+    fn next_key(&mut self) -> io::Result<Option<String>> {
+        match self.peek()? {
+            b'}' => {
+                self.peeked = None;
+                return Ok(None);
+            }
+            b',' => self.peeked = None,
+            _ => {}
+        }
+        let mut key = Vec::new();
+        self.string(&mut key)?;
+        self.expect(b':')?;
+        String::from_utf8(key)
+            .map(Some)
+            .map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Whether the array has another element, positioned at its start
+    fn next_element(&mut self) -> io::Result<bool> {
+        match self.peek()? {
+            b']' => {
+                self.peeked = None;
+                Ok(false)
+            }
+            b',' => {
+                self.peeked = None;
+                Ok(true)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// Unescape a string into `out`
+    fn string(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.expect(b'"')?;
+        let mut buffer = Vec::with_capacity(8 * 1024);
+        loop {
+            match self.byte()? {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = match self.byte()? {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => self.unicode_escape()?,
+                        byte => byte as char,
+                    };
+                    let mut utf8 = [0; 4];
+                    buffer.extend_from_slice(escaped.encode_utf8(&mut utf8).as_bytes());
+                }
+                byte => buffer.push(byte),
+            }
+            if buffer.len() >= 8 * 1024 {
+                out.write_all(&buffer)?;
+                buffer.clear();
+            }
+        }
+        out.write_all(&buffer)
+    }
+
+    /// The character of a `\uXXXX` escape, combining surrogate pairs
+    fn unicode_escape(&mut self) -> io::Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.byte()? != b'\\' || self.byte()? != b'u' {
+                return Err(invalid("unpaired surrogate in string".to_string()));
+            }
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| invalid("invalid unicode escape".to_string()))
+    }
+
+    fn hex4(&mut self) -> io::Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = (self.byte()? as char)
+                .to_digit(16)
+                .ok_or_else(|| invalid("invalid unicode escape".to_string()))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    /// Parse a value, for the small parts of the response that are kept
+    fn value(&mut self) -> io::Result<Value> {
+        let mut raw = Vec::new();
+        self.copy_value(&mut raw)?;
+        serde_json::from_slice(&raw).map_err(|e| invalid(e.to_string()))
+    }
+
+    fn skip_value(&mut self) -> io::Result<()> {
+        self.copy_value(&mut io::sink())
+    }
+
+    /// Copy the JSON text of the next value to `out`
+    fn copy_value(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        match self.peek()? {
+            b'{' | b'[' => {
+                let mut depth = 0usize;
+                loop {
+                    let byte = self.byte()?;
+                    out.write_all(&[byte])?;
+                    match byte {
+                        b'"' => self.copy_string_rest(out)?,
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return Ok(());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            b'"' => {
+                out.write_all(&[self.byte()?])?;
+                self.copy_string_rest(out)
+            }
+            // Numbers, booleans and null end at the next delimiter, left for the caller
+            _ => loop {
+                let byte = self.byte()?;
+                if matches!(byte, b',' | b'}' | b']') || byte.is_ascii_whitespace() {
+                    self.peeked = Some(byte);
+                    return Ok(());
+                }
+                out.write_all(&[byte])?;
+            },
+        }
+    }
+
+    /// Copy the rest of a string whose opening quote was copied
+    fn copy_string_rest(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        loop {
+            let byte = self.byte()?;
+            out.write_all(&[byte])?;
+            match byte {
+                b'\\' => out.write_all(&[self.byte()?])?,
+                b'"' => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid tools/call response: {}", message),
+    )
+}
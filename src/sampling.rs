@@ -0,0 +1,323 @@
+//! Sampling module
+//!
+//! This module implements `sampling/createMessage`, with which a tool asks the host
+//! connected to the server to run an LLM completion for it, so servers can use a model
+//! without an API key or a model of their own. On the server, [`create_message`] sends
+//! the request to the client that called the running tool, which must have announced
+//! the sampling capability. On the client, [`OllamaSampler`] answers the requests with
+//! the crate's Ollama client once its approval hook agrees, as servers should not
+//! spend the user's model without the user knowing.
+
+use crate::error::McpError;
+use crate::mcp::{JsonRpcError, McpServerState, ToolContent};
+use crate::ollama::{ChatMessage, ChatOptions, Ollama};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Method of sampling requests
+pub const METHOD: &str = "sampling/createMessage";
+
+/// JSON-RPC error code of a request the user declined
+pub const DECLINED: i32 = -1;
+
+/// Parameters of a sampling request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageRequest {
+    /// The conversation to complete
+    pub messages: Vec<SamplingMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<ModelPreferences>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Context of the host's MCP servers to include: "none", "thisServer" or
+    /// "allServers"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Most tokens to generate
+    pub max_tokens: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    /// Provider-specific options passed through to the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+impl CreateMessageRequest {
+    /// A request completing `messages` with at most `max_tokens` tokens
+    pub fn new(messages: Vec<SamplingMessage>, max_tokens: u32) -> Self {
+        Self {
+            messages,
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens,
+            stop_sequences: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    /// Set the system prompt
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Set the temperature
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Suggest a model, such as `llama3`, which the host may not have
+    pub fn model_hint(mut self, name: impl Into<String>) -> Self {
+        self.model_preferences
+            .get_or_insert_with(Default::default)
+            .hints
+            .push(ModelHint {
+                name: Some(name.into()),
+            });
+        self
+    }
+}
+
+/// A message of the conversation to complete
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SamplingMessage {
+    /// "user" or "assistant"
+    pub role: String,
+    /// Text, image or audio content
+    pub content: ToolContent,
+}
+
+impl SamplingMessage {
+    /// A text message from the user
+    pub fn user(text: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: ToolContent::text(text),
+        }
+    }
+
+    /// A text message from the assistant
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: ToolContent::text(text),
+        }
+    }
+}
+
+/// What the server would like in a model; the host picks the model
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreferences {
+    /// Models to consider, in order of preference
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<ModelHint>,
+    /// Importance of low cost, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_priority: Option<f64>,
+    /// Importance of low latency, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_priority: Option<f64>,
+    /// Importance of capability, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intelligence_priority: Option<f64>,
+}
+
+/// A model suggested by the server, matched loosely against the host's models
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModelHint {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Result of a sampling request
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    /// "assistant"
+    pub role: String,
+    pub content: ToolContent,
+    /// Model that generated the message
+    pub model: String,
+    /// Why generation stopped, such as "endTurn" or "maxTokens"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+impl CreateMessageResult {
+    /// The generated text, unless the model answered with other content
+    pub fn text(&self) -> Option<&str> {
+        self.content.text.as_deref()
+    }
+}
+
+/// Ask the client that called the running tool to complete a conversation
+///
+/// Only async tools can sample, as the request goes to the session of the call running
+/// on the current task, see [`crate::server_request`]. The client may take a while, as
+/// it may ask its user first, and refuses requests the user declines with a
+/// [`DECLINED`] error.
+pub async fn create_message(
+    state: &McpServerState,
+    request: &CreateMessageRequest,
+) -> Result<CreateMessageResult, McpError> {
+    let session_id = crate::server_request::caller().ok_or_else(|| {
+        McpError::protocol("Sampling is only possible during a tool call made within a session")
+    })?;
+    let supported = state
+        .sessions
+        .read()
+        .await
+        .get(&session_id)
+        .is_some_and(|session| session.client.capabilities.sampling.is_some());
+    if !supported {
+        return Err(McpError::protocol("The client does not support sampling"));
+    }
+    let result =
+        crate::server_request::request(state, METHOD, Some(serde_json::to_value(request)?)).await?;
+    Ok(serde_json::from_value(result)?)
+}
+
+/// The error answering a request the user declined
+pub fn declined() -> JsonRpcError {
+    JsonRpcError {
+        code: DECLINED,
+        message: "The user declined the sampling request".to_string(),
+        data: None,
+    }
+}
+
+/// Decides whether the server may run a sampling request
+type Approval = Box<dyn Fn(&CreateMessageRequest) -> bool + Send + Sync>;
+
+/// Answers sampling requests with a local Ollama model
+///
+/// The model is the sampler's own, unless a model hint of the request names one
+/// pulled on Ollama, such as `llama3` for `llama3:latest`. Only text messages can be
+/// completed. `includeContext` is ignored, as the conversations of the host are not
+/// shared with servers.
+pub struct OllamaSampler {
+    model: String,
+    base_url: Option<String>,
+    approve: Option<Approval>,
+}
+
+impl OllamaSampler {
+    /// Answer with `model`, running every request
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            base_url: None,
+            approve: None,
+        }
+    }
+
+    /// Use an Ollama server other than the local default
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Run only the requests `approve` agrees to, such as after asking the user;
+    /// others are refused with a [`DECLINED`] error
+    pub fn with_approval(
+        mut self,
+        approve: impl Fn(&CreateMessageRequest) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.approve = Some(Box::new(approve));
+        self
+    }
+
+    /// Complete the conversation of a request
+    pub fn sample(
+        &self,
+        request: &CreateMessageRequest,
+    ) -> Result<CreateMessageResult, JsonRpcError> {
+        let mut messages: Vec<ChatMessage> = request
+            .system_prompt
+            .iter()
+            .map(|prompt| ChatMessage::system(prompt.as_str()))
+            .collect();
+        for message in &request.messages {
+            let Some(text) = message.content.text.as_deref() else {
+                return Err(JsonRpcError {
+                    code: -32602,
+                    message: format!(
+                        "Only text messages can be sampled, not {} content",
+                        message.content.content_type
+                    ),
+                    data: None,
+                });
+            };
+            messages.push(match message.role.as_str() {
+                "assistant" => ChatMessage::assistant(text),
+                _ => ChatMessage::user(text),
+            });
+        }
+        if let Some(approve) = &self.approve
+            && !approve(request)
+        {
+            return Err(declined());
+        }
+
+        let model = self.model_for(request);
+        let mut ollama = Ollama::default(&model);
+        if let Some(base_url) = &self.base_url {
+            ollama = ollama.with_base_url(base_url);
+        }
+        let options = ChatOptions {
+            temperature: request.temperature,
+            num_predict: Some(request.max_tokens),
+            stop: request.stop_sequences.clone(),
+            ..Default::default()
+        };
+        let response = ollama
+            .chat_with_options(messages, options)
+            .map_err(|e| JsonRpcError {
+                code: -32603,
+                message: format!("Sampling with model {} failed: {}", model, e),
+                data: None,
+            })?;
+        Ok(CreateMessageResult {
+            role: "assistant".to_string(),
+            content: ToolContent::text(response.message.content),
+            model: response.model,
+            stop_reason: response.done_reason.map(|reason| match reason.as_str() {
+                "length" => "maxTokens".to_string(),
+                "stop" => "endTurn".to_string(),
+                _ => reason,
+            }),
+        })
+    }
+
+    /// The first hinted model pulled on Ollama, or the sampler's model
+    fn model_for(&self, request: &CreateMessageRequest) -> String {
+        let hints: Vec<&str> = request
+            .model_preferences
+            .iter()
+            .flat_map(|preferences| &preferences.hints)
+            .filter_map(|hint| hint.name.as_deref())
+            .collect();
+        if hints.is_empty() {
+            return self.model.clone();
+        }
+        let mut ollama = Ollama::default(&self.model);
+        if let Some(base_url) = &self.base_url {
+            ollama = ollama.with_base_url(base_url);
+        }
+        let Ok(models) = ollama.list_models() else {
+            return self.model.clone();
+        };
+        hints
+            .iter()
+            .find_map(|hint| models.iter().find(|model| model.contains(hint)))
+            .cloned()
+            .unwrap_or_else(|| self.model.clone())
+    }
+}
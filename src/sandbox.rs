@@ -0,0 +1,263 @@
+//! Sandbox module
+//!
+//! This module runs the subprocesses the server starts, such as stdio upstreams and
+//! screenshot utilities, with least privilege on Linux. A sandboxed command is started
+//! through the server's own executable (`mcp-server sandbox-exec`), which applies the
+//! policy to itself and then replaces itself with the command: resource limits first,
+//! then a landlock ruleset restricting the filesystem to the configured paths, then a
+//! seccomp filter refusing dangerous system calls with EPERM. The policy is
+//! process-wide, like the allowed roots, and is only set with the `sandbox` feature.
+//!
+//! Landlock needs Linux 5.13 or later; on older kernels the command still runs, with a
+//! warning, under the resource limits and syscall filter only.
+
+use crate::config::SandboxConfig;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Subcommand of the server executable that applies the policy and runs a command
+pub const EXEC_SUBCOMMAND: &str = "sandbox-exec";
+
+/// Environment variable passing the policy to the sandbox-exec subcommand
+const POLICY_ENV: &str = "MCP_SANDBOX_POLICY";
+
+/// Paths readable by default, enough to run dynamically linked programs
+pub const DEFAULT_READ_ONLY: &[&str] = &[
+    "/bin", "/dev", "/etc", "/lib", "/lib64", "/proc", "/sbin", "/usr",
+];
+
+/// System calls refused by default
+pub const DEFAULT_DENIED_SYSCALLS: &[&str] = &[
+    "add_key",
+    "bpf",
+    "chroot",
+    "delete_module",
+    "finit_module",
+    "init_module",
+    "kexec_load",
+    "keyctl",
+    "mount",
+    "perf_event_open",
+    "pivot_root",
+    "process_vm_readv",
+    "process_vm_writev",
+    "ptrace",
+    "reboot",
+    "request_key",
+    "setns",
+    "swapoff",
+    "swapon",
+    "umount2",
+    "unshare",
+    "userfaultfd",
+];
+
+static POLICY: OnceLock<SandboxConfig> = OnceLock::new();
+
+/// Sandbox the subprocesses started from now on
+///
+/// Fails if the policy names an unknown system call or a policy is already set.
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+pub fn configure(config: &SandboxConfig) -> Result<(), String> {
+    for name in denied_syscalls(config) {
+        if syscall_number(name).is_none() {
+            return Err(format!("Unknown system call '{}' in sandbox", name));
+        }
+    }
+    POLICY
+        .set(config.clone())
+        .map_err(|_| "The sandbox is already configured".to_string())
+}
+
+/// The command to run in place of `command`, applying the sandbox if one is configured
+///
+/// The returned command re-runs the current executable, so the server binary must
+/// handle [`EXEC_SUBCOMMAND`] by calling `exec`.
+pub fn wrap(command: Command) -> std::io::Result<Command> {
+    let Some(policy) = POLICY.get() else {
+        return Ok(command);
+    };
+
+    let mut wrapped = Command::new(std::env::current_exe()?);
+    wrapped
+        .arg(EXEC_SUBCOMMAND)
+        .arg("--")
+        .arg(command.get_program())
+        .args(command.get_args())
+        .env(POLICY_ENV, serde_json::to_string(policy)?);
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => wrapped.env(key, value),
+            None => wrapped.env_remove(key),
+        };
+    }
+    if let Some(dir) = command.get_current_dir() {
+        wrapped.current_dir(dir);
+    }
+    Ok(wrapped)
+}
+
+/// Apply the policy passed by [`wrap`] to this process and replace it with `command`
+///
+/// Only returns if something failed.
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+pub fn exec(command: &[String]) -> Box<dyn std::error::Error> {
+    use std::os::unix::process::CommandExt;
+
+    let Some((program, args)) = command.split_first() else {
+        return "No command to run in the sandbox".into();
+    };
+    let policy: SandboxConfig = match std::env::var(POLICY_ENV)
+        .map_err(|_| format!("{} is not set", POLICY_ENV).into())
+        .and_then(|policy| serde_json::from_str(&policy).map_err(Box::from))
+    {
+        Ok(policy) => policy,
+        Err(e) => return e,
+    };
+    if let Err(e) = apply(&policy) {
+        return format!("Failed to sandbox '{}': {}", program, e).into();
+    }
+
+    let error = Command::new(program)
+        .args(args)
+        .env_remove(POLICY_ENV)
+        .exec();
+    format!("Failed to run '{}' in the sandbox: {}", program, error).into()
+}
+
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+fn denied_syscalls(config: &SandboxConfig) -> Vec<&str> {
+    match &config.deny_syscalls {
+        Some(names) => names.iter().map(String::as_str).collect(),
+        None => DEFAULT_DENIED_SYSCALLS.to_vec(),
+    }
+}
+
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+fn apply(policy: &SandboxConfig) -> Result<(), Box<dyn std::error::Error>> {
+    set_limits(policy)?;
+    restrict_paths(policy)?;
+    filter_syscalls(policy)?;
+    Ok(())
+}
+
+/// Set the configured resource limits, soft and hard
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+fn set_limits(policy: &SandboxConfig) -> std::io::Result<()> {
+    use rlimit::Resource;
+
+    const MIB: u64 = 1024 * 1024;
+    let limits = [
+        (Resource::AS, policy.max_memory_mb.map(|mb| mb * MIB)),
+        (Resource::CPU, policy.max_cpu_secs),
+        (Resource::FSIZE, policy.max_file_size_mb.map(|mb| mb * MIB)),
+        (Resource::NOFILE, policy.max_open_files),
+        (Resource::NPROC, policy.max_processes),
+    ];
+    for (resource, limit) in limits {
+        if let Some(limit) = limit {
+            rlimit::setrlimit(resource, limit, limit)?;
+        }
+    }
+    Ok(())
+}
+
+/// Restrict the filesystem to the configured paths with landlock
+///
+/// Paths that do not exist are skipped.
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+fn restrict_paths(policy: &SandboxConfig) -> Result<(), Box<dyn std::error::Error>> {
+    use landlock::{
+        ABI, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+        path_beneath_rules,
+    };
+
+    let abi = ABI::V5;
+    let read_only: Vec<&str> = match &policy.read_only {
+        Some(paths) => paths.iter().map(String::as_str).collect(),
+        None => DEFAULT_READ_ONLY.to_vec(),
+    };
+    let temp_dir = std::env::temp_dir();
+    let read_write = crate::file_read::ALLOWED_ROOTS
+        .iter()
+        .map(std::path::Path::new)
+        .chain([temp_dir.as_path(), std::path::Path::new("/dev/null")])
+        .chain(policy.read_write.iter().map(std::path::Path::new));
+
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))?
+        .create()?
+        .add_rules(path_beneath_rules(read_only, AccessFs::from_read(abi)))?
+        .add_rules(path_beneath_rules(read_write, AccessFs::from_all(abi)))?
+        .restrict_self()?;
+    match status.ruleset {
+        RulesetStatus::FullyEnforced => {}
+        RulesetStatus::PartiallyEnforced => eprintln!(
+            "Warning: this kernel supports an older landlock, some filesystem access of sandboxed commands is not restricted"
+        ),
+        RulesetStatus::NotEnforced => eprintln!(
+            "Warning: this kernel does not support landlock, filesystem access of sandboxed commands is not restricted"
+        ),
+    }
+    Ok(())
+}
+
+/// Refuse the denied system calls with EPERM
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+fn filter_syscalls(policy: &SandboxConfig) -> Result<(), Box<dyn std::error::Error>> {
+    use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, SeccompRule};
+    use std::collections::BTreeMap;
+
+    let rules: BTreeMap<i64, Vec<SeccompRule>> = denied_syscalls(policy)
+        .into_iter()
+        .filter_map(syscall_number)
+        .map(|number| (number, Vec::new()))
+        .collect();
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        std::env::consts::ARCH.try_into()?,
+    )?;
+    let program: BpfProgram = filter.try_into()?;
+    seccompiler::apply_filter(&program)?;
+    Ok(())
+}
+
+/// Number of a system call the sandbox can refuse
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+fn syscall_number(name: &str) -> Option<i64> {
+    let number = match name {
+        "add_key" => libc::SYS_add_key,
+        "bind" => libc::SYS_bind,
+        "bpf" => libc::SYS_bpf,
+        "chroot" => libc::SYS_chroot,
+        "connect" => libc::SYS_connect,
+        "delete_module" => libc::SYS_delete_module,
+        "finit_module" => libc::SYS_finit_module,
+        "init_module" => libc::SYS_init_module,
+        "kexec_load" => libc::SYS_kexec_load,
+        "keyctl" => libc::SYS_keyctl,
+        "listen" => libc::SYS_listen,
+        "mount" => libc::SYS_mount,
+        "perf_event_open" => libc::SYS_perf_event_open,
+        "pivot_root" => libc::SYS_pivot_root,
+        "process_vm_readv" => libc::SYS_process_vm_readv,
+        "process_vm_writev" => libc::SYS_process_vm_writev,
+        "ptrace" => libc::SYS_ptrace,
+        "reboot" => libc::SYS_reboot,
+        "request_key" => libc::SYS_request_key,
+        "setns" => libc::SYS_setns,
+        "socket" => libc::SYS_socket,
+        "swapoff" => libc::SYS_swapoff,
+        "swapon" => libc::SYS_swapon,
+        "umount2" => libc::SYS_umount2,
+        "unshare" => libc::SYS_unshare,
+        "userfaultfd" => libc::SYS_userfaultfd,
+        _ => return None,
+    };
+    Some(number)
+}
@@ -0,0 +1,150 @@
+//! Scheduler module
+//!
+//! This module runs registered tools on cron schedules defined in the server
+//! configuration. The latest result of each job is stored on the server under a
+//! `schedule://<name>` URI, and a `notifications/resources/updated` notification is
+//! published after every run. Runs are journaled when the server keeps a job journal.
+
+use crate::config::ScheduleConfig;
+use crate::events::{EventKind, ServerEvent};
+use crate::job_journal::JobRecord;
+use crate::mcp::{McpServerState, ToolsCallRequest};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// URI scheme under which scheduled results are stored
+pub const SCHEDULE_URI_SCHEME: &str = "schedule://";
+
+/// The latest result of a scheduled job
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScheduledResult {
+    pub name: String,
+    pub uri: String,
+    pub tool: String,
+    pub ran_at: DateTime<Utc>,
+    pub next_run: Option<DateTime<Utc>>,
+    /// The tools/call result, if the call succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// The JSON-RPC error, if the call failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Get the URI under which a job's result is stored
+pub fn result_uri(name: &str) -> String {
+    format!("{}{}", SCHEDULE_URI_SCHEME, name)
+}
+
+/// Parse a cron expression
+///
+/// Standard five-field expressions (`*/5 * * * *`) are accepted as well as the
+/// six/seven-field form with seconds (and years) understood by the `cron` crate.
+pub fn parse_schedule(expression: &str) -> Result<Schedule, Box<dyn std::error::Error>> {
+    let fields = expression.split_whitespace().count();
+    let expression = if fields == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+
+    Schedule::from_str(&expression)
+        .map_err(|e| format!("Invalid cron expression '{}': {}", expression, e).into())
+}
+
+/// Start every job configured on the server
+///
+/// All schedules are validated before any job starts. Each job runs in its own task
+/// for the lifetime of the runtime.
+pub fn start(state: &McpServerState) -> Result<(), Box<dyn std::error::Error>> {
+    let jobs = state
+        .config
+        .schedule
+        .iter()
+        .map(|job| Ok((job.clone(), parse_schedule(&job.cron)?)))
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+    for (job, schedule) in jobs {
+        crate::server_log::info(format!(
+            "Scheduling tool '{}' as job '{}' ({})",
+            job.tool, job.name, job.cron
+        ));
+        tokio::spawn(run_job(state.clone(), job, schedule));
+    }
+
+    Ok(())
+}
+
+/// Run a single job forever, sleeping until each upcoming time
+async fn run_job(state: McpServerState, job: ScheduleConfig, schedule: Schedule) {
+    let uri = result_uri(&job.name);
+
+    while let Some(next) = schedule.upcoming(Utc).next() {
+        let delay = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(delay).await;
+
+        crate::job_journal::record(
+            &state,
+            &JobRecord::ScheduleStarted {
+                name: job.name.clone(),
+                uri: uri.clone(),
+                tool: job.tool.clone(),
+                started_at: Utc::now(),
+            },
+        );
+        let (result, error) = if state.get_tool(&job.tool).await.is_none() {
+            let message = format!("Tool '{}' not found", job.tool);
+            state.emit_event(
+                ServerEvent::new(EventKind::Error)
+                    .tool(job.tool.clone())
+                    .message(format!("Scheduled job '{}': {}", job.name, message)),
+            );
+            (None, Some(message))
+        } else {
+            let call_request = ToolsCallRequest {
+                name: job.tool.clone(),
+                arguments: job.arguments.clone(),
+                meta: None,
+            };
+            match crate::mcp::dispatch_tool(
+                &state,
+                call_request,
+                Default::default(),
+                Default::default(),
+            )
+            .await
+            {
+                Ok(response) => (serde_json::to_value(response).ok(), None),
+                Err(e) => (None, Some(e.message)),
+            }
+        };
+
+        if let Some(error) = &error {
+            crate::server_log::error(format!("Scheduled job '{}' failed: {}", job.name, error));
+        }
+
+        let scheduled_result = ScheduledResult {
+            name: job.name.clone(),
+            uri: uri.clone(),
+            tool: job.tool.clone(),
+            ran_at: Utc::now(),
+            next_run: schedule.upcoming(Utc).next(),
+            result,
+            error,
+        };
+        crate::job_journal::record(&state, &JobRecord::Schedule(scheduled_result.clone()));
+        state
+            .scheduled_results
+            .write()
+            .await
+            .insert(uri.clone(), scheduled_result);
+
+        state.notify(
+            "notifications/resources/updated",
+            Some(serde_json::json!({ "uri": uri })),
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Tool schema module
+//!
+//! This module generates the `inputSchema` of a tool from its argument struct with
+//! `#[derive(JsonSchema)]`, so the schema always matches what the tool deserializes.
+//! Field doc comments become property descriptions. Optional fields are left out of
+//! `required` instead of being declared nullable, as models expect of tool schemas.
+//! The `outputSchema` of tools returning structured content is generated the same way
+//! from the type they serialize.
+
+use schemars::generate::SchemaSettings;
+use schemars::transform::RecursiveTransform;
+use schemars::{JsonSchema, Schema};
+use serde_json::Value;
+
+/// Generate the input schema of a tool from its argument type
+pub fn input_schema<T: JsonSchema>() -> Value {
+    let mut schema = SchemaSettings::draft2020_12()
+        .with(|settings| {
+            settings.meta_schema = None;
+            settings.inline_subschemas = true;
+        })
+        .with_transform(RecursiveTransform(remove_null))
+        .with_transform(RecursiveTransform(constants_to_enum))
+        .into_generator()
+        .into_root_schema_for::<T>();
+
+    // The tool's name and description already say what the arguments are for
+    schema.remove("title");
+    schema.remove("description");
+    // Some clients reject object schemas without properties
+    if schema.get("properties").is_none() {
+        schema.insert("properties".to_string(), Value::Object(Default::default()));
+    }
+    schema.to_value()
+}
+
+/// Generate the output schema of a tool from the type of its structured content
+///
+/// Unlike arguments, optional fields serialized as `null` keep their nullable type, as
+/// the dispatcher validates results against the schema.
+pub fn output_schema<T: JsonSchema>() -> Value {
+    let mut schema = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .with(|settings| {
+            settings.meta_schema = None;
+            settings.inline_subschemas = true;
+        })
+        .with_transform(RecursiveTransform(constants_to_enum))
+        .into_generator()
+        .into_root_schema_for::<T>();
+
+    schema.remove("title");
+    schema.remove("description");
+    schema.to_value()
+}
+
+/// Turn the `T | null` schema of an `Option<T>` field into the schema of `T`
+fn remove_null(schema: &mut Schema) {
+    if let Some(Value::Array(types)) = schema.get_mut("type") {
+        types.retain(|kind| kind != "null");
+        if types.len() == 1 {
+            let kind = types.remove(0);
+            schema.insert("type".to_string(), kind);
+        }
+    }
+
+    let Some(Value::Array(variants)) = schema.get_mut("anyOf") else {
+        return;
+    };
+    variants.retain(|variant| variant.get("type").and_then(Value::as_str) != Some("null"));
+    if variants.len() == 1
+        && let Some(Value::Object(inner)) = variants.pop()
+    {
+        schema.remove("anyOf");
+        for (key, value) in inner {
+            // The field's own description wins over the one of its type
+            if schema.get(&key).is_none() {
+                schema.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Turn the `oneOf` generated for an enum with documented variants into a plain
+/// `enum`, which tool prompts list as the allowed values
+fn constants_to_enum(schema: &mut Schema) {
+    let Some(Value::Array(variants)) = schema.get("oneOf") else {
+        return;
+    };
+    let mut values = Vec::new();
+    for variant in variants {
+        match (variant.get("const"), variant.get("enum")) {
+            (Some(value), _) => values.push(value.clone()),
+            (None, Some(Value::Array(group))) => values.extend(group.iter().cloned()),
+            _ => return,
+        }
+    }
+    let kind = variants
+        .first()
+        .and_then(|variant| variant.get("type"))
+        .cloned();
+    schema.remove("oneOf");
+    if let Some(kind) = kind {
+        schema.insert("type".to_string(), kind);
+    }
+    schema.insert("enum".to_string(), Value::Array(values));
+}
@@ -0,0 +1,169 @@
+//! Screenshot tool module
+//!
+//! This module captures the screen, a single window or a region and returns it as a
+//! base64-encoded PNG image. Capturing is delegated to the platform's screenshot
+//! utility (`screencapture` on macOS; `grim`, `gnome-screenshot`, `scrot` or
+//! ImageMagick's `import` elsewhere), so no display-server libraries are linked.
+//! It is only compiled with the `screenshot` feature.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// PNG file signature
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Screen region to capture, in pixels
+#[derive(Debug, Deserialize, Clone, Copy, JsonSchema)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Screenshot tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ScreenshotRequest {
+    /// Platform window id to capture instead of the whole screen
+    pub window: Option<String>,
+    /// Region of the screen to capture, in pixels
+    pub region: Option<Region>,
+}
+
+/// Screenshot tool response
+#[derive(Debug, Serialize)]
+pub struct ScreenshotResponse {
+    /// Base64-encoded PNG data
+    pub data: String,
+    pub mime_type: String,
+    pub size: usize,
+}
+
+/// Execute the screenshot tool
+pub fn execute_screenshot(
+    request: ScreenshotRequest,
+) -> Result<ScreenshotResponse, Box<dyn std::error::Error>> {
+    if request.window.is_some() && request.region.is_some() {
+        return Err("Provide either 'window' or 'region', not both".into());
+    }
+
+    let output = std::env::temp_dir().join(format!("mcp-screenshot-{}.png", uuid::Uuid::new_v4()));
+    let result = capture(&request, &output).and_then(|_| {
+        fs::read(&output).map_err(|e| format!("Failed to read screenshot: {}", e).into())
+    });
+    let _ = fs::remove_file(&output);
+    let bytes = result?;
+    crate::accounting::read(bytes.len() as u64);
+
+    if !bytes.starts_with(PNG_SIGNATURE) {
+        return Err("Screenshot utility did not produce a PNG image".into());
+    }
+
+    Ok(ScreenshotResponse {
+        size: bytes.len(),
+        data: STANDARD.encode(&bytes),
+        mime_type: "image/png".to_string(),
+    })
+}
+
+/// Run the first available screenshot utility that supports the request
+fn capture(request: &ScreenshotRequest, output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let output = output.to_string_lossy().to_string();
+    let mut attempted = Vec::new();
+
+    for command in candidate_commands(request, &output) {
+        let program = command.get_program().to_string_lossy().to_string();
+        let mut command = crate::sandbox::wrap(command)
+            .map_err(|e| format!("Failed to sandbox {}: {}", program, e))?;
+        match crate::accounting::command_output(&mut command) {
+            Ok(result) if result.status.success() && Path::new(&output).exists() => return Ok(()),
+            Ok(result) => attempted.push(format!(
+                "{} ({})",
+                program,
+                String::from_utf8_lossy(&result.stderr).trim()
+            )),
+            // Utility not installed, try the next one
+            Err(_) => continue,
+        }
+    }
+
+    if attempted.is_empty() {
+        Err("No supported screenshot utility found (install grim, gnome-screenshot, scrot or ImageMagick)".into())
+    } else {
+        Err(format!("Screenshot failed: {}", attempted.join("; ")).into())
+    }
+}
+
+/// Build the screenshot commands to try for the current platform, in order of preference
+fn candidate_commands(request: &ScreenshotRequest, output: &str) -> Vec<Command> {
+    let mut commands = Vec::new();
+
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("screencapture");
+        command.arg("-x");
+        if let Some(window) = &request.window {
+            command.arg(format!("-l{}", window));
+        }
+        if let Some(r) = request.region {
+            command.arg(format!("-R{},{},{},{}", r.x, r.y, r.width, r.height));
+        }
+        command.arg(output);
+        commands.push(command);
+        return commands;
+    }
+
+    // grim (Wayland) has no notion of window ids
+    if request.window.is_none() {
+        let mut command = Command::new("grim");
+        if let Some(r) = request.region {
+            command.args(["-g", &format!("{},{} {}x{}", r.x, r.y, r.width, r.height)]);
+        }
+        command.arg(output);
+        commands.push(command);
+    }
+
+    // gnome-screenshot only captures the full screen non-interactively
+    if request.window.is_none() && request.region.is_none() {
+        let mut command = Command::new("gnome-screenshot");
+        command.args(["-f", output]);
+        commands.push(command);
+    }
+
+    if request.window.is_none() {
+        let mut command = Command::new("scrot");
+        command.arg("--overwrite");
+        if let Some(r) = request.region {
+            command.args(["-a", &format!("{},{},{},{}", r.x, r.y, r.width, r.height)]);
+        }
+        command.arg(output);
+        commands.push(command);
+    }
+
+    let mut command = Command::new("import");
+    command.args(["-window", request.window.as_deref().unwrap_or("root")]);
+    if let Some(r) = request.region {
+        command.args([
+            "-crop",
+            &format!("{}x{}+{}+{}", r.width, r.height, r.x, r.y),
+        ]);
+    }
+    command.arg(format!("png:{}", output));
+    commands.push(command);
+
+    commands
+}
+
+/// Get the tool definition for the screenshot tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "screenshot".to_string(),
+        description: Some("Capture the screen, a single window or a region of the screen and return it as a PNG image.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<ScreenshotRequest>()),
+        ..Default::default()
+    }
+}
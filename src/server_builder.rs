@@ -0,0 +1,319 @@
+//! Server builder module
+//!
+//! This module configures an [`McpServer`] before it is built: the address it listens
+//! on, HTTPS, the path of the Streamable HTTP endpoint, the browser origins allowed by
+//! CORS, the largest request body, limits on tool calls in flight and the tools it
+//! starts with. Every setting is checked by [`McpServerBuilder::build`], which reports
+//! all the problems found at once instead of the server failing when it starts or
+//! when a client first calls a tool.
+
+use crate::config::ServerConfig;
+use crate::mcp::{McpServer, McpServerState, McpTool, RegisteredTool, ToolHandler};
+use crate::tls::ServerTlsConfig;
+use axum::http::{HeaderValue, Method};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+/// Port listened on unless configured
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Path of the Streamable HTTP endpoint unless configured
+pub const DEFAULT_PATH: &str = "/mcp";
+
+/// Largest request body accepted unless configured, as axum's default
+pub const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Paths served next to the Streamable HTTP endpoint, which it must not shadow
+const RESERVED_PATHS: [&str; 3] = [crate::sse::SSE_PATH, crate::sse::MESSAGES_PATH, "/webhooks"];
+
+/// Longest tool name, as the MCP specification allows
+const MAX_TOOL_NAME_LENGTH: usize = 128;
+
+/// Which browser origins may call the HTTP endpoints
+#[derive(Debug, Clone, Default)]
+pub enum CorsPolicy {
+    /// Any origin, for local development and hosts running in a browser
+    #[default]
+    Permissive,
+    /// Only these origins, e.g. `https://app.example.com`
+    AllowOrigins(Vec<String>),
+    /// No origin; browsers refuse cross-origin calls
+    Disabled,
+}
+
+impl CorsPolicy {
+    /// The layer applying the policy to the router
+    pub(crate) fn layer(&self) -> CorsLayer {
+        match self {
+            CorsPolicy::Permissive => CorsLayer::permissive(),
+            CorsPolicy::AllowOrigins(origins) => CorsLayer::new()
+                .allow_origin(AllowOrigin::list(
+                    origins
+                        .iter()
+                        .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+                ))
+                .allow_methods([Method::GET, Method::POST, Method::DELETE])
+                .allow_headers(Any)
+                // Clients read the session id and protocol version from responses
+                .expose_headers(Any),
+            CorsPolicy::Disabled => CorsLayer::new(),
+        }
+    }
+}
+
+/// The settings of a server that failed validation
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid server settings: {}", .0.join("; "))]
+pub struct BuildError(pub Vec<String>);
+
+/// Settings of an [`McpServer`], checked when it is built
+pub struct McpServerBuilder {
+    addr: SocketAddr,
+    tls: Option<ServerTlsConfig>,
+    sse: bool,
+    path: String,
+    cors: CorsPolicy,
+    max_body_bytes: usize,
+    max_in_flight: Option<usize>,
+    max_in_flight_per_session: Option<usize>,
+    config: ServerConfig,
+    tools: Vec<RegisteredTool>,
+    /// Problems found while registering tools, reported by `build`
+    tool_errors: Vec<String>,
+}
+
+impl Default for McpServerBuilder {
+    fn default() -> Self {
+        Self {
+            addr: (Ipv4Addr::LOCALHOST, DEFAULT_PORT).into(),
+            tls: None,
+            sse: false,
+            path: DEFAULT_PATH.to_string(),
+            cors: CorsPolicy::default(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_in_flight: None,
+            max_in_flight_per_session: None,
+            config: ServerConfig::default(),
+            tools: Vec::new(),
+            tool_errors: Vec::new(),
+        }
+    }
+}
+
+impl McpServerBuilder {
+    /// Listen on this port of the configured address (default 8080)
+    pub fn port(mut self, port: u16) -> Self {
+        self.addr.set_port(port);
+        self
+    }
+
+    /// Listen on another address than the default `127.0.0.1:8080`
+    ///
+    /// Binding a non-loopback address such as `0.0.0.0:8080` exposes the tools to the
+    /// network; require API keys (see [`crate::auth`]) and serve HTTPS with
+    /// [`McpServerBuilder::tls`] unless a reverse proxy terminates TLS in front.
+    pub fn bind(mut self, addr: SocketAddr) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// Serve HTTPS with a certificate and key instead of plain HTTP
+    pub fn tls(mut self, tls: ServerTlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Also serve the HTTP+SSE transport used by older clients
+    ///
+    /// Clients open an SSE stream at `/sse` and post their messages to the
+    /// `/messages` endpoint it announces; responses and server notifications are
+    /// pushed on the stream.
+    pub fn sse(mut self) -> Self {
+        self.sse = true;
+        self
+    }
+
+    /// Serve the Streamable HTTP endpoint at another path than `/mcp`
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Set which browser origins may call the HTTP endpoints (default any)
+    pub fn cors(mut self, cors: CorsPolicy) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// Refuse request bodies larger than this with 413 (default 2 MiB)
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Reject tool calls as busy beyond this many in flight across the server
+    ///
+    /// Overrides `max_in_flight` of the config's `[admission]`.
+    pub fn max_in_flight(mut self, limit: usize) -> Self {
+        self.max_in_flight = Some(limit);
+        self
+    }
+
+    /// Reject tool calls as busy beyond this many in flight per session
+    ///
+    /// Overrides `max_in_flight_per_session` of the config's `[admission]`.
+    pub fn max_in_flight_per_session(mut self, limit: usize) -> Self {
+        self.max_in_flight_per_session = Some(limit);
+        self
+    }
+
+    /// Set the configuration used by the tools
+    pub fn config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Add a built-in tool, such as `file_read::get_tool_definition()`
+    pub fn tool(self, tool: McpTool) -> Self {
+        self.register_tool(crate::mcp::BuiltinTool(tool))
+    }
+
+    /// Register a tool implemented by the application
+    pub fn register_tool(mut self, tool: impl ToolHandler + 'static) -> Self {
+        let (registered, errors) = RegisteredTool::compile(tool);
+        for (schema, e) in errors {
+            self.tool_errors.push(format!(
+                "the {} of tool '{}' is invalid: {}",
+                schema, registered.definition.name, e
+            ));
+        }
+        self.tools.push(registered);
+        self
+    }
+
+    /// Check the settings and build the server
+    pub fn build(self) -> Result<McpServer, BuildError> {
+        let mut problems = self.tool_errors;
+        problems.extend(check_path(&self.path));
+        if self.max_body_bytes == 0 {
+            problems.push("the request body limit must be at least 1 byte".to_string());
+        }
+        match (self.max_in_flight, self.max_in_flight_per_session) {
+            (Some(0), _) | (_, Some(0)) => {
+                problems.push("limits on tool calls in flight must be at least 1".to_string());
+            }
+            (Some(total), Some(session)) if session > total => problems.push(format!(
+                "the limit per session ({}) exceeds the limit across the server ({})",
+                session, total
+            )),
+            _ => {}
+        }
+        if let CorsPolicy::AllowOrigins(origins) = &self.cors {
+            problems.extend(origins.iter().filter_map(|origin| check_origin(origin)));
+        }
+        if let Some(tls) = &self.tls
+            && let Err(e) = tls.acceptor()
+        {
+            problems.push(e.to_string());
+        }
+
+        let mut tools = HashMap::new();
+        for tool in self.tools {
+            let name = tool.definition.name.clone();
+            if let Some(problem) = check_tool_name(&name) {
+                problems.push(problem);
+            }
+            if tools.insert(name.clone(), tool).is_some() {
+                problems.push(format!("tool '{}' is registered twice", name));
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(BuildError(problems));
+        }
+
+        let mut config = self.config;
+        if self.max_in_flight.is_some() {
+            config.admission.max_in_flight = self.max_in_flight;
+        }
+        if self.max_in_flight_per_session.is_some() {
+            config.admission.max_in_flight_per_session = self.max_in_flight_per_session;
+        }
+        let mut state = McpServerState::new();
+        state.tools = Arc::new(RwLock::new(tools));
+        state.config = Arc::new(config);
+        Ok(McpServer {
+            state,
+            addr: self.addr,
+            tls: self.tls,
+            sse: self.sse,
+            path: self.path,
+            cors: self.cors,
+            max_body_bytes: self.max_body_bytes,
+        })
+    }
+}
+
+/// The problem with an endpoint path, if any
+fn check_path(path: &str) -> Option<String> {
+    if !path.starts_with('/') || path.len() < 2 || path.ends_with('/') {
+        return Some(format!(
+            "the endpoint path '{}' must start with / and not end with it",
+            path
+        ));
+    }
+    if path
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, ':' | '*' | '{' | '}' | '?' | '#'))
+    {
+        return Some(format!(
+            "the endpoint path '{}' must not contain spaces or any of : * {{ }} ? #",
+            path
+        ));
+    }
+    RESERVED_PATHS
+        .iter()
+        .find(|reserved| path == **reserved || path.starts_with(&format!("{}/", reserved)))
+        .map(|reserved| {
+            format!(
+                "the endpoint path '{}' conflicts with {}, which the server also serves",
+                path, reserved
+            )
+        })
+}
+
+/// The problem with a CORS origin, if any
+fn check_origin(origin: &str) -> Option<String> {
+    let valid = reqwest::Url::parse(origin).is_ok_and(|url| {
+        matches!(url.scheme(), "http" | "https")
+            && url.host().is_some()
+            && url.origin().ascii_serialization() == origin
+    });
+    (!valid).then(|| {
+        format!(
+            "the CORS origin '{}' must be a scheme and host such as https://app.example.com",
+            origin
+        )
+    })
+}
+
+/// The problem with a tool name, if any
+///
+/// Names are limited to letters, digits, `_`, `-` and `.` so that every host can call
+/// them.
+fn check_tool_name(name: &str) -> Option<String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_TOOL_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    (!valid).then(|| {
+        format!(
+            "the tool name '{}' must be 1 to {} letters, digits, _, - or .",
+            name, MAX_TOOL_NAME_LENGTH
+        )
+    })
+}
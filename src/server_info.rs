@@ -0,0 +1,133 @@
+//! Server info module
+//!
+//! This module describes a running server for fleet inventories: its version, the
+//! optional features it was built with, the tools it serves, the limits it enforces
+//! and how long it has been up. The description is returned by the `server/info`
+//! method and served as the `mcp://server/info` resource, so operators can collect it
+//! from every deployed server with an ordinary MCP client.
+
+use crate::mcp::McpServerState;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// URI of the resource serving the description
+pub const INFO_URI: &str = "mcp://server/info";
+
+/// Optional cargo features and whether this build enables them
+const FEATURES: &[(&str, bool)] = &[
+    ("clipboard", cfg!(feature = "clipboard")),
+    ("email", cfg!(feature = "email")),
+    ("hf-tokenizers", cfg!(feature = "hf-tokenizers")),
+    ("image", cfg!(feature = "image")),
+    ("keyring", cfg!(feature = "keyring")),
+    ("postgres", cfg!(feature = "postgres")),
+    ("sandbox", cfg!(feature = "sandbox")),
+    ("screenshot", cfg!(feature = "screenshot")),
+    ("speech", cfg!(feature = "speech")),
+    ("ssh", cfg!(feature = "ssh")),
+    ("tiktoken", cfg!(feature = "tiktoken")),
+];
+
+/// Response from server/info request
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    pub name: String,
+    pub version: String,
+    /// Optional cargo features the server was built with
+    pub features: Vec<String>,
+    pub started_at: DateTime<Utc>,
+    pub uptime_secs: u64,
+    /// Tools served, sorted by name; upstream tools are listed by gateway/status
+    pub tools: Vec<ToolInfo>,
+    /// Shadow implementations receiving copies of tool calls, and how they compare
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shadows: Vec<crate::shadow::ShadowStats>,
+    /// URI schemes of the resources served
+    pub resource_schemes: Vec<String>,
+    pub limits: Limits,
+}
+
+/// A tool the server serves
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolInfo {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Whether the tool declares it does not modify its environment
+    pub read_only: bool,
+    /// Whether each call must be approved by a human
+    pub requires_approval: bool,
+    pub deprecated: bool,
+}
+
+/// Limits configured on the server; absent values are unlimited
+#[derive(Debug, Clone, Serialize)]
+pub struct Limits {
+    pub max_in_flight: Option<usize>,
+    pub max_in_flight_per_session: Option<usize>,
+    pub max_concurrent_executions: Option<usize>,
+    pub tool_timeout_ms: Option<u64>,
+    /// Time limits of specific tools in milliseconds
+    pub tool_timeouts_ms: BTreeMap<String, u64>,
+    /// Items per page of list results
+    pub page_size: usize,
+    /// Whether HTTP clients must present an API key
+    pub api_keys_required: bool,
+}
+
+/// Describe the server as it currently runs
+pub async fn collect(state: &McpServerState) -> ServerInfo {
+    let mut tools: Vec<ToolInfo> = state
+        .tools
+        .read()
+        .await
+        .values()
+        .map(|tool| ToolInfo {
+            name: tool.definition.name.clone(),
+            version: tool.definition.version.clone(),
+            read_only: tool
+                .definition
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.read_only_hint)
+                .unwrap_or(false),
+            requires_approval: tool.definition.requires_approval(),
+            deprecated: tool.definition.is_deprecated(),
+        })
+        .collect();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut resource_schemes: Vec<String> = state.resources.read().await.keys().cloned().collect();
+    resource_schemes.sort();
+
+    let config = &state.config;
+    let started_at = state.stats.since();
+    ServerInfo {
+        name: "mcp-server".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        features: FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        started_at,
+        uptime_secs: (Utc::now() - started_at).num_seconds().max(0) as u64,
+        tools,
+        shadows: state.shadows.stats(),
+        resource_schemes,
+        limits: Limits {
+            max_in_flight: config.admission.max_in_flight,
+            max_in_flight_per_session: config.admission.max_in_flight_per_session,
+            max_concurrent_executions: config.execution.max_concurrent,
+            tool_timeout_ms: config.execution.timeout_ms,
+            tool_timeouts_ms: config
+                .execution
+                .tool_timeout_ms
+                .iter()
+                .map(|(tool, ms)| (tool.clone(), *ms))
+                .collect(),
+            page_size: crate::pagination::page_size(state),
+            api_keys_required: !state.api_keys.is_empty(),
+        },
+    }
+}
@@ -0,0 +1,142 @@
+//! Server log module
+//!
+//! This module keeps the server's recent log messages in memory, next to writing them
+//! to stdout or stderr as before, so a connected host can read them as the
+//! `mcp://server/logs` resource and diagnose failing tool calls without shell access
+//! to the machine running the server. Tool calls that fail are logged with the
+//! error returned to the client, such as the path a file tool refused. The buffer is
+//! process-wide and bounded; the oldest messages are dropped first.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// URI of the resource serving the log
+pub const LOG_URI: &str = "mcp://server/logs";
+
+/// Messages kept unless configured
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// Characters of a message kept, so one huge error does not crowd out the others
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// Severity of a log message, named as the MCP logging levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A message of the log
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    /// Position of the message since the server started, counting dropped ones
+    pub seq: u64,
+    pub time: DateTime<Utc>,
+    pub level: Level,
+    pub message: String,
+}
+
+struct Buffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+static LOG: Mutex<Buffer> = Mutex::new(Buffer {
+    entries: VecDeque::new(),
+    capacity: DEFAULT_CAPACITY,
+    next_seq: 0,
+});
+
+fn buffer() -> std::sync::MutexGuard<'static, Buffer> {
+    // Entries are only pushed and popped, so a panic cannot leave them inconsistent
+    LOG.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keep at most `capacity` messages; 0 stops keeping them
+pub fn set_capacity(capacity: usize) {
+    let mut buffer = buffer();
+    buffer.capacity = capacity;
+    while buffer.entries.len() > capacity {
+        buffer.entries.pop_front();
+    }
+}
+
+/// Keep a message without printing it
+pub fn record(level: Level, message: impl Into<String>) {
+    let mut message = message.into();
+    if let Some((index, _)) = message.char_indices().nth(MAX_MESSAGE_CHARS) {
+        message.truncate(index);
+        message.push('…');
+    }
+    let mut buffer = buffer();
+    let seq = buffer.next_seq;
+    buffer.next_seq += 1;
+    if buffer.capacity == 0 {
+        return;
+    }
+    if buffer.entries.len() == buffer.capacity {
+        buffer.entries.pop_front();
+    }
+    buffer.entries.push_back(LogEntry {
+        seq,
+        time: Utc::now(),
+        level,
+        message,
+    });
+}
+
+/// Print an informational message to stdout and keep it
+pub fn info(message: impl Into<String>) {
+    let message = message.into();
+    println!("{}", message);
+    record(Level::Info, message);
+}
+
+/// Print a warning to stderr and keep it
+pub fn warn(message: impl Into<String>) {
+    let message = message.into();
+    eprintln!("{}", message);
+    record(Level::Warning, message);
+}
+
+/// Print an error to stderr and keep it
+pub fn error(message: impl Into<String>) {
+    let message = message.into();
+    eprintln!("{}", message);
+    record(Level::Error, message);
+}
+
+/// The kept messages, oldest first
+pub fn entries() -> Vec<LogEntry> {
+    buffer().entries.iter().cloned().collect()
+}
+
+/// Number of messages logged since the server started, which changes with every new one
+pub fn count() -> u64 {
+    buffer().next_seq
+}
+
+/// Render messages as lines of text, one per message
+pub fn to_text(entries: &[LogEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let level = match entry.level {
+                Level::Info => "INFO ",
+                Level::Warning => "WARN ",
+                Level::Error => "ERROR",
+            };
+            format!(
+                "{} {} {}\n",
+                entry.time.to_rfc3339_opts(SecondsFormat::Millis, true),
+                level,
+                entry.message
+            )
+        })
+        .collect()
+}
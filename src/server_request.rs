@@ -0,0 +1,233 @@
+//! Server request module
+//!
+//! This module carries requests in the other direction of the usual flow, from an MCP
+//! server to its client, such as `sampling/createMessage`. On the server, a tool sends
+//! a request to the client that called it: the request is pushed on the streams of the
+//! caller's session like a notification, and the client posts its response back the
+//! way it posts its own requests. On the client, handlers registered by method answer
+//! the requests arriving on any transport; `ping` is always answered, and other
+//! methods are refused with "Method not found".
+
+use crate::error::McpError;
+use crate::mcp::{
+    JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, McpServerState, RequestId,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// How long the server waits for a client to answer, which may involve its user
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+tokio::task_local! {
+    /// Session that made the tool call the current task runs
+    static CALLER: Option<String>;
+}
+
+/// Requests sent to clients and waiting for their response, by request id
+pub type PendingRequests = Arc<Mutex<HashMap<RequestId, PendingRequest>>>;
+
+/// A request waiting for the response of a client
+#[derive(Debug)]
+pub struct PendingRequest {
+    /// Session the request was sent to, the only one whose response is accepted
+    session_id: String,
+    response: oneshot::Sender<JsonRpcResponse>,
+}
+
+fn lock(requests: &PendingRequests) -> MutexGuard<'_, HashMap<RequestId, PendingRequest>> {
+    // Entries are only inserted and removed, so the map cannot be left inconsistent
+    requests.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run a tool call made by `session_id`, so the tool can send requests to its client
+pub async fn scope<F: Future>(session_id: Option<&str>, future: F) -> F::Output {
+    CALLER.scope(session_id.map(str::to_string), future).await
+}
+
+/// Session that made the tool call running on the current task, if any
+///
+/// Blocking work and tasks spawned by the tool do not see it.
+pub fn caller() -> Option<String> {
+    CALLER.try_with(Clone::clone).ok().flatten()
+}
+
+/// Send a request to the client of the current tool call and wait for its result
+///
+/// A JSON-RPC error answered by the client is returned as [`McpError::Rpc`]. The
+/// request is forgotten if the tool call ends first, such as when it is cancelled.
+pub async fn request(
+    state: &McpServerState,
+    method: &str,
+    params: Option<Value>,
+) -> Result<Value, McpError> {
+    let session_id = caller().ok_or_else(|| {
+        McpError::protocol(format!(
+            "{} can only be sent during a tool call made within a session",
+            method
+        ))
+    })?;
+    let id = RequestId::String(format!("server-{}", uuid::Uuid::new_v4()));
+    let (sender, receiver) = oneshot::channel();
+    lock(&state.server_requests).insert(
+        id.clone(),
+        PendingRequest {
+            session_id,
+            response: sender,
+        },
+    );
+    let _pending = Forget {
+        requests: &state.server_requests,
+        id: id.clone(),
+    };
+
+    let sent = state.notifications.send(JsonRpcNotification {
+        jsonrpc: "2.0".to_string(),
+        id: Some(id),
+        method: method.to_string(),
+        params,
+    });
+    if sent.is_err() {
+        return Err(McpError::connection(
+            "The client has no open stream to receive requests on",
+        ));
+    }
+
+    match tokio::time::timeout(RESPONSE_TIMEOUT, receiver).await {
+        Ok(Ok(response)) => match response.error {
+            Some(error) => Err(error.into()),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        },
+        Ok(Err(_)) => Err(McpError::connection(
+            "The client's session ended before it answered",
+        )),
+        Err(_) => Err(McpError::connection(format!(
+            "The client did not answer {} within {} s",
+            method,
+            RESPONSE_TIMEOUT.as_secs()
+        ))),
+    }
+}
+
+/// Removes a pending request however the wait for it ends
+struct Forget<'a> {
+    requests: &'a PendingRequests,
+    id: RequestId,
+}
+
+impl Drop for Forget<'_> {
+    fn drop(&mut self) {
+        lock(self.requests).remove(&self.id);
+    }
+}
+
+/// Pass a response posted by a client to the request waiting for it
+///
+/// Responses to unknown requests, or from another session than the one asked, are
+/// dropped.
+pub(crate) fn complete(state: &McpServerState, session_id: Option<&str>, message: Value) {
+    let Ok(response) = serde_json::from_value::<JsonRpcResponse>(message) else {
+        return;
+    };
+    let Some(id) = &response.id else {
+        return;
+    };
+    let mut pending = lock(&state.server_requests);
+    if pending
+        .get(id)
+        .is_some_and(|request| Some(request.session_id.as_str()) == session_id)
+        && let Some(request) = pending.remove(id)
+    {
+        let _ = request.response.send(response);
+    }
+}
+
+/// Whether a request pushed on the server's streams goes to a session: only to the
+/// session it was sent to
+pub(crate) fn delivers(
+    state: &McpServerState,
+    session_id: Option<&str>,
+    request: &JsonRpcNotification,
+) -> bool {
+    let Some(id) = &request.id else {
+        return false;
+    };
+    lock(&state.server_requests)
+        .get(id)
+        .is_some_and(|pending| Some(pending.session_id.as_str()) == session_id)
+}
+
+/// Fail the requests waiting for a session that ended
+pub(crate) fn close_session(state: &McpServerState, session_id: &str) {
+    lock(&state.server_requests).retain(|_, request| request.session_id != session_id);
+}
+
+/// Answers a request from the server, given its params
+pub type RequestHandler = dyn Fn(Option<Value>) -> Result<Value, JsonRpcError> + Send + Sync;
+
+/// Handlers of the requests a server sends to the client, by method
+///
+/// Clones share the handlers, so the threads reading a connection answer with those
+/// registered after it was opened.
+#[derive(Clone, Default)]
+pub struct RequestHandlers(Arc<Mutex<HashMap<String, Arc<RequestHandler>>>>);
+
+impl fmt::Debug for RequestHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.lock().keys()).finish()
+    }
+}
+
+impl RequestHandlers {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<RequestHandler>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Answer requests of `method` with `handler`, replacing any other handler
+    pub fn insert(
+        &self,
+        method: impl Into<String>,
+        handler: impl Fn(Option<Value>) -> Result<Value, JsonRpcError> + Send + Sync + 'static,
+    ) {
+        self.lock().insert(method.into(), Arc::new(handler));
+    }
+
+    /// Whether requests of `method` are answered
+    pub fn contains(&self, method: &str) -> bool {
+        self.lock().contains_key(method)
+    }
+
+    /// Whether no handler is registered
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// The response to a request from the server
+    ///
+    /// The handler runs on the calling thread, without holding the handlers' lock.
+    pub fn answer(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let handler = self.lock().get(&request.method).cloned();
+        let outcome = match handler {
+            Some(handler) => handler(request.params),
+            None if request.method == "ping" => Ok(serde_json::json!({})),
+            None => Err(JsonRpcError {
+                code: -32601,
+                message: "Method not found".to_string(),
+                data: None,
+            }),
+        };
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            result,
+            error,
+        }
+    }
+}
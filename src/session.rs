@@ -0,0 +1,196 @@
+//! Session module
+//!
+//! This module implements the session handling of the MCP Streamable HTTP transport:
+//! the `initialize` request opens a session whose id is returned in the
+//! `Mcp-Session-Id` header, later requests must carry that header, GET opens an SSE
+//! stream of server notifications and DELETE ends the session. Resource updates are
+//! only streamed to sessions subscribed to the resource.
+
+use crate::mcp::{InitializeRequest, McpServerState, SUPPORTED_PROTOCOL_VERSIONS};
+use crate::notification_queue::{self, NotificationQueue};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::Weak;
+use std::time::{Duration, Instant};
+
+/// Header carrying the session id
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Header carrying the negotiated protocol version on requests after initialization
+pub const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// Sessions without requests for this long are discarded
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Status and message of a rejected request
+pub type Rejection = (StatusCode, String);
+
+/// A client session opened by an initialize request
+#[derive(Debug)]
+pub struct Session {
+    /// The client's initialize params
+    pub client: InitializeRequest,
+    /// The protocol version agreed on during initialization
+    pub protocol_version: String,
+    /// Language of the catalog serving the session; `None` for English
+    pub language: Option<String>,
+    /// URIs of the resources the session subscribed to
+    pub subscriptions: HashSet<String>,
+    /// Name of the API key the session was opened with, see [`crate::auth`]
+    pub api_key: Option<String>,
+    last_seen: Instant,
+    /// Notification queues of the session's open SSE streams, closed with the session
+    streams: Vec<Weak<NotificationQueue>>,
+}
+
+impl Session {
+    fn close_streams(&self) {
+        for queue in self.streams.iter().filter_map(Weak::upgrade) {
+            queue.close();
+        }
+    }
+}
+
+/// Open a session for an initialized client and return its id
+///
+/// Idle sessions are discarded at the same time.
+pub async fn create(
+    state: &McpServerState,
+    client: InitializeRequest,
+    protocol_version: String,
+    api_key: Option<String>,
+) -> String {
+    let id = uuid::Uuid::new_v4().to_string();
+    let language = client
+        .accept_language
+        .as_deref()
+        .and_then(|accept_language| crate::i18n::negotiate(&state.catalogs, accept_language));
+    let mut sessions = state.sessions.write().await;
+    sessions.retain(|_, session| {
+        let active = session.last_seen.elapsed() < SESSION_IDLE_TIMEOUT;
+        if !active {
+            session.close_streams();
+        }
+        active
+    });
+    sessions.insert(
+        id.clone(),
+        Session {
+            client,
+            protocol_version,
+            language,
+            subscriptions: HashSet::new(),
+            api_key,
+            last_seen: Instant::now(),
+            streams: Vec::new(),
+        },
+    );
+    id
+}
+
+/// Check the session and protocol version headers of a request
+///
+/// Returns the session id, or the rejection: 400 without a session id or with an
+/// unsupported protocol version, 404 for unknown or expired sessions.
+pub async fn require(state: &McpServerState, headers: &HeaderMap) -> Result<String, Rejection> {
+    check_protocol_version(headers)?;
+
+    let Some(id) = headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Missing Mcp-Session-Id header; send an initialize request first".to_string(),
+        ));
+    };
+
+    let mut sessions = state.sessions.write().await;
+    match sessions.get_mut(id) {
+        Some(session) if session.last_seen.elapsed() < SESSION_IDLE_TIMEOUT => {
+            session.last_seen = Instant::now();
+            Ok(id.to_string())
+        }
+        _ => Err((StatusCode::NOT_FOUND, "Session not found".to_string())),
+    }
+}
+
+/// Reject requests announcing a protocol version the server does not support
+pub fn check_protocol_version(headers: &HeaderMap) -> Result<(), Rejection> {
+    match headers
+        .get(PROTOCOL_VERSION_HEADER)
+        .map(|value| value.to_str().unwrap_or_default())
+    {
+        Some(version) if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unsupported MCP-Protocol-Version: {}", version),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Open an SSE stream of server notifications for a session (GET /mcp)
+pub async fn handle_stream(State(state): State<McpServerState>, headers: HeaderMap) -> Response {
+    let accepts_sse = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if !accepts_sse {
+        return (
+            StatusCode::NOT_ACCEPTABLE,
+            "GET requests must accept text/event-stream",
+        )
+            .into_response();
+    }
+    let id = match require(&state, &headers).await {
+        Ok(id) => id,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let queue = notification_queue::open(&state, {
+        let id = id.clone();
+        move || std::future::ready(Some(id.clone()))
+    });
+    if let Some(session) = state.sessions.write().await.get_mut(&id) {
+        session.streams.retain(|stream| stream.strong_count() > 0);
+        session.streams.push(std::sync::Arc::downgrade(&queue));
+    }
+
+    let events = futures::stream::unfold(queue, |queue| async move {
+        let notification = queue.recv().await?;
+        let data = serde_json::to_string(&notification).unwrap_or_default();
+        Some((
+            Ok::<_, Infallible>(Event::default().event("message").data(data)),
+            queue,
+        ))
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// End a session (DELETE /mcp)
+pub async fn handle_delete(State(state): State<McpServerState>, headers: HeaderMap) -> Response {
+    let id = match require(&state, &headers).await {
+        Ok(id) => id,
+        Err(rejection) => return rejection.into_response(),
+    };
+    close(&state, &id).await;
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// End a session and close its streams
+pub async fn close(state: &McpServerState, id: &str) {
+    if let Some(session) = state.sessions.write().await.remove(id) {
+        session.close_streams();
+    }
+    crate::server_request::close_session(state, id);
+}
@@ -0,0 +1,163 @@
+//! Shadow tools module
+//!
+//! This module validates a new implementation of a tool against real traffic before it
+//! replaces the current one. A shadow registered for a tool receives a copy of every
+//! call the tool answers: its result is discarded, and when it differs from the
+//! production result (content, structured content or error status) the divergence is
+//! logged to the server log with both results, as are failures of the shadow. Shadows
+//! run in the background after the production call, so they neither delay nor change
+//! its response, and are bounded by the tool's time limit. As they receive the same
+//! arguments, shadows must not have side effects such as writing files.
+
+use crate::mcp::{McpServerState, ToolHandler, ToolsCallResponse};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Time allowed to a shadow call when the tool has no time limit
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Characters of each result shown when logging a divergence
+const MAX_LOGGED_CHARS: usize = 500;
+
+/// A shadow implementation of a tool and how it compares so far
+struct Shadow {
+    handler: Arc<dyn ToolHandler>,
+    calls: AtomicU64,
+    divergences: AtomicU64,
+    failures: AtomicU64,
+}
+
+/// How a shadow compared with its tool since it was registered
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowStats {
+    pub tool: String,
+    /// Calls mirrored to the shadow
+    pub calls: u64,
+    /// Calls for which the shadow returned another result
+    pub divergences: u64,
+    /// Calls for which the shadow failed with a JSON-RPC error or timed out
+    pub failures: u64,
+}
+
+/// The shadows registered on the server, by tool name
+#[derive(Default)]
+pub struct Shadows(Mutex<HashMap<String, Arc<Shadow>>>);
+
+impl std::fmt::Debug for Shadows {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.lock().keys()).finish()
+    }
+}
+
+impl Shadows {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Shadow>>> {
+        // The map is only inserted into and removed from, so it cannot be left inconsistent
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Mirror the calls of the tool named like `shadow` to it, replacing any other shadow
+    pub fn register(&self, shadow: impl ToolHandler + 'static) {
+        let name = shadow.name().to_string();
+        self.lock().insert(
+            name,
+            Arc::new(Shadow {
+                handler: Arc::new(shadow),
+                calls: AtomicU64::new(0),
+                divergences: AtomicU64::new(0),
+                failures: AtomicU64::new(0),
+            }),
+        );
+    }
+
+    /// Stop mirroring a tool's calls, returning whether it had a shadow
+    pub fn unregister(&self, tool: &str) -> bool {
+        self.lock().remove(tool).is_some()
+    }
+
+    /// Whether a tool has a shadow, whose calls then need a copy of the arguments
+    pub fn contains(&self, tool: &str) -> bool {
+        self.lock().contains_key(tool)
+    }
+
+    /// How every shadow compared so far, sorted by tool name
+    pub fn stats(&self) -> Vec<ShadowStats> {
+        let mut stats: Vec<ShadowStats> = self
+            .lock()
+            .iter()
+            .map(|(tool, shadow)| ShadowStats {
+                tool: tool.clone(),
+                calls: shadow.calls.load(Ordering::Relaxed),
+                divergences: shadow.divergences.load(Ordering::Relaxed),
+                failures: shadow.failures.load(Ordering::Relaxed),
+            })
+            .collect();
+        stats.sort_by(|a, b| a.tool.cmp(&b.tool));
+        stats
+    }
+}
+
+/// Run the shadow of a tool in the background with the arguments of a call the tool
+/// answered with `production`
+pub(crate) fn mirror(
+    state: &McpServerState,
+    tool: &str,
+    arguments: Option<Value>,
+    production: &ToolsCallResponse,
+) {
+    let Some(shadow) = state.shadows.lock().get(tool).cloned() else {
+        return;
+    };
+    let expected = comparable(production);
+    let timeout =
+        crate::execution::time_limit(&state.config.execution, tool).unwrap_or(DEFAULT_TIMEOUT);
+    let state = state.clone();
+    let tool = tool.to_string();
+    tokio::spawn(async move {
+        shadow.calls.fetch_add(1, Ordering::Relaxed);
+        let cancel = crate::cancellation::CancellationToken::new();
+        let call = shadow
+            .handler
+            .call(&state, arguments, Default::default(), cancel.clone());
+        let outcome = match tokio::time::timeout(timeout, call).await {
+            Ok(Ok(response)) => Ok(comparable(&response)),
+            Ok(Err(e)) => Err(e.message),
+            Err(_) => {
+                cancel.cancel();
+                Err(format!("did not finish within {} ms", timeout.as_millis()))
+            }
+        };
+        match outcome {
+            Ok(actual) if actual == expected => {}
+            Ok(actual) => {
+                shadow.divergences.fetch_add(1, Ordering::Relaxed);
+                crate::server_log::warn(format!(
+                    "Shadow of tool '{}' diverged: production returned {}, shadow returned {}",
+                    tool,
+                    summary(&expected),
+                    summary(&actual)
+                ));
+            }
+            Err(e) => {
+                shadow.failures.fetch_add(1, Ordering::Relaxed);
+                crate::server_log::warn(format!("Shadow of tool '{}' failed: {}", tool, e));
+            }
+        }
+    });
+}
+
+/// The parts of a result compared between a tool and its shadow
+fn comparable(response: &ToolsCallResponse) -> Value {
+    serde_json::to_value(response).unwrap_or(Value::Null)
+}
+
+fn summary(result: &Value) -> String {
+    let text = result.to_string();
+    match text.char_indices().nth(MAX_LOGGED_CHARS) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text,
+    }
+}
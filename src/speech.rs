@@ -0,0 +1,382 @@
+//! Speech tool module
+//!
+//! This module provides the `tts` (text-to-speech) and `stt` (speech-to-text) tools,
+//! backed by a pluggable [`SpeechEngine`]. Engines for a local whisper.cpp server and
+//! for OpenAI-compatible HTTP speech services are included; applications embedding the
+//! server can supply their own. It is only compiled with the `speech` feature.
+
+use crate::tool_error::ToolErrorPayload;
+use crate::vfs::VirtualFs;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::blocking::{Client, multipart};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// Upper bound on the size of audio accepted for transcription
+const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+/// Audio produced by a text-to-speech engine
+#[derive(Debug, Clone)]
+pub struct SynthesizedAudio {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// A speech backend able to synthesize and/or transcribe audio
+///
+/// Engines only need to implement the directions they support; the default
+/// implementations report the operation as unsupported.
+pub trait SpeechEngine: Send + Sync {
+    /// Human readable engine name used in error messages
+    fn name(&self) -> &str;
+
+    /// Convert text into audio
+    fn synthesize(
+        &self,
+        _text: &str,
+        _voice: Option<&str>,
+    ) -> Result<SynthesizedAudio, Box<dyn std::error::Error>> {
+        Err(format!("{} does not support text-to-speech", self.name()).into())
+    }
+
+    /// Convert audio into text
+    fn transcribe(
+        &self,
+        _audio: &[u8],
+        _mime_type: &str,
+        _language: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        Err(format!("{} does not support speech-to-text", self.name()).into())
+    }
+}
+
+/// The engines used by the speech tools
+#[derive(Clone, Default)]
+pub struct SpeechEngines {
+    pub tts: Option<Arc<dyn SpeechEngine>>,
+    pub stt: Option<Arc<dyn SpeechEngine>>,
+}
+
+impl fmt::Debug for SpeechEngines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpeechEngines")
+            .field("tts", &self.tts.as_ref().map(|engine| engine.name()))
+            .field("stt", &self.stt.as_ref().map(|engine| engine.name()))
+            .finish()
+    }
+}
+
+impl SpeechEngines {
+    /// Build the engines described by the server configuration
+    pub fn from_config(config: &crate::config::SpeechConfig) -> Self {
+        Self {
+            tts: config.tts.as_ref().map(build_engine),
+            stt: config.stt.as_ref().map(build_engine),
+        }
+    }
+}
+
+/// Create an engine from its configuration
+fn build_engine(config: &crate::config::SpeechEngineConfig) -> Arc<dyn SpeechEngine> {
+    match config.engine {
+        crate::config::SpeechEngineKind::WhisperCpp => Arc::new(WhisperCppEngine::new(&config.url)),
+        crate::config::SpeechEngineKind::Http => {
+            let mut engine = HttpSpeechEngine::new(&config.url);
+            engine.api_key = config.api_key.clone();
+            if let Some(model) = &config.model {
+                engine.model = model.clone();
+            }
+            if let Some(voice) = &config.voice {
+                engine.voice = voice.clone();
+            }
+            Arc::new(engine)
+        }
+    }
+}
+
+/// Speech-to-text engine for a local whisper.cpp `server` instance
+pub struct WhisperCppEngine {
+    base_url: String,
+    client: OnceLock<Client>,
+}
+
+impl WhisperCppEngine {
+    /// Create an engine for the whisper.cpp server at `base_url` (e.g. http://localhost:8081)
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: OnceLock::new(),
+        }
+    }
+}
+
+impl SpeechEngine for WhisperCppEngine {
+    fn name(&self) -> &str {
+        "whisper.cpp"
+    }
+
+    fn transcribe(
+        &self,
+        audio: &[u8],
+        mime_type: &str,
+        language: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut form = multipart::Form::new()
+            .part("file", audio_part(audio, mime_type)?)
+            .text("response_format", "json");
+        if let Some(language) = language {
+            form = form.text("language", language.to_string());
+        }
+
+        let url = format!("{}/inference", self.base_url);
+        let response = client(&self.client).post(&url).multipart(form).send()?;
+        parse_transcription(response)
+    }
+}
+
+/// Engine for OpenAI-compatible HTTP speech services (`/v1/audio/speech` and
+/// `/v1/audio/transcriptions`)
+pub struct HttpSpeechEngine {
+    base_url: String,
+    client: OnceLock<Client>,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub voice: String,
+}
+
+impl HttpSpeechEngine {
+    /// Create an engine for the service at `base_url` (e.g. http://localhost:8000)
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: OnceLock::new(),
+            api_key: None,
+            model: "whisper-1".to_string(),
+            voice: "alloy".to_string(),
+        }
+    }
+
+    /// Attach the API key, if any, to a request
+    fn authorize(
+        &self,
+        request: reqwest::blocking::RequestBuilder,
+    ) -> reqwest::blocking::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+}
+
+impl SpeechEngine for HttpSpeechEngine {
+    fn name(&self) -> &str {
+        "HTTP speech service"
+    }
+
+    fn synthesize(
+        &self,
+        text: &str,
+        voice: Option<&str>,
+    ) -> Result<SynthesizedAudio, Box<dyn std::error::Error>> {
+        let url = format!("{}/v1/audio/speech", self.base_url);
+        let body = serde_json::json!({
+            "model": self.model,
+            "input": text,
+            "voice": voice.unwrap_or(&self.voice),
+            "response_format": "wav",
+        });
+        let response = self
+            .authorize(client(&self.client).post(&url).json(&body))
+            .send()?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text()?;
+            return Err(format!("Speech service error {}: {}", status, error_text).into());
+        }
+
+        let mime_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("audio/wav")
+            .to_string();
+        Ok(SynthesizedAudio {
+            data: response.bytes()?.to_vec(),
+            mime_type,
+        })
+    }
+
+    fn transcribe(
+        &self,
+        audio: &[u8],
+        mime_type: &str,
+        language: Option<&str>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut form = multipart::Form::new()
+            .part("file", audio_part(audio, mime_type)?)
+            .text("model", self.model.clone());
+        if let Some(language) = language {
+            form = form.text("language", language.to_string());
+        }
+
+        let url = format!("{}/v1/audio/transcriptions", self.base_url);
+        let response = self
+            .authorize(client(&self.client).post(&url).multipart(form))
+            .send()?;
+        parse_transcription(response)
+    }
+}
+
+/// Get the engine's HTTP client, creating it on first use
+///
+/// The blocking client must not be created on the async runtime, so engines build it
+/// lazily from the blocking pool where the tools run.
+fn client(cell: &OnceLock<Client>) -> &Client {
+    cell.get_or_init(Client::new)
+}
+
+/// Build the multipart file part for an audio upload
+fn audio_part(
+    audio: &[u8],
+    mime_type: &str,
+) -> Result<multipart::Part, Box<dyn std::error::Error>> {
+    let extension = mime_type.rsplit('/').next().unwrap_or("wav");
+    Ok(multipart::Part::bytes(audio.to_vec())
+        .file_name(format!("audio.{}", extension))
+        .mime_str(mime_type)?)
+}
+
+/// Extract the text from a `{"text": ...}` transcription response
+fn parse_transcription(
+    response: reqwest::blocking::Response,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text()?;
+        return Err(format!("Speech service error {}: {}", status, error_text).into());
+    }
+
+    let body: serde_json::Value = response.json()?;
+    body.get("text")
+        .and_then(|text| text.as_str())
+        .map(|text| text.trim().to_string())
+        .ok_or_else(|| "Transcription response did not contain text".into())
+}
+
+/// Text-to-speech tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TtsRequest {
+    /// The text to speak
+    pub text: String,
+    /// Voice name supported by the speech engine
+    pub voice: Option<String>,
+}
+
+/// Speech-to-text tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SttRequest {
+    /// The audio file to transcribe
+    pub path: Option<String>,
+    /// Base64-encoded audio to transcribe instead of a file
+    pub data: Option<String>,
+    /// MIME type of the audio, e.g. audio/wav
+    pub mime_type: Option<String>,
+    /// Spoken language hint, e.g. en
+    pub language: Option<String>,
+}
+
+/// Speech-to-text tool response
+#[derive(Debug, Serialize)]
+pub struct SttResponse {
+    pub text: String,
+}
+
+/// Execute the text-to-speech tool
+pub fn execute_tts(
+    engine: &dyn SpeechEngine,
+    request: TtsRequest,
+) -> Result<SynthesizedAudio, Box<dyn std::error::Error>> {
+    if request.text.trim().is_empty() {
+        return Err("Text must not be empty".into());
+    }
+    engine.synthesize(&request.text, request.voice.as_deref())
+}
+
+/// Execute the speech-to-text tool
+pub fn execute_stt(
+    engine: &dyn SpeechEngine,
+    request: SttRequest,
+    fs: &dyn VirtualFs,
+) -> Result<SttResponse, Box<dyn std::error::Error>> {
+    let audio = match (&request.path, &request.data) {
+        (Some(_), Some(_)) => return Err("Provide either 'path' or 'data', not both".into()),
+        (Some(path), None) => {
+            let checked = crate::file_read::validate_path(fs, path)?;
+            let data = fs
+                .read(checked)
+                .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+            crate::accounting::read(data.len() as u64);
+            data
+        }
+        (None, Some(data)) => STANDARD
+            .decode(data)
+            .map_err(|e| format!("Invalid base64 audio data: {}", e))?,
+        (None, None) => return Err("Either 'path' or 'data' is required".into()),
+    };
+
+    if audio.len() > MAX_AUDIO_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("Audio exceeds the {} byte limit", MAX_AUDIO_BYTES),
+            MAX_AUDIO_BYTES as u64,
+        )
+        .into());
+    }
+
+    let mime_type = request
+        .mime_type
+        .or_else(|| request.path.as_deref().and_then(guess_audio_mime_type))
+        .unwrap_or_else(|| "audio/wav".to_string());
+    let text = engine.transcribe(&audio, &mime_type, request.language.as_deref())?;
+
+    Ok(SttResponse { text })
+}
+
+/// Guess an audio MIME type from the file extension
+fn guess_audio_mime_type(path: &str) -> Option<String> {
+    match std::path::Path::new(path).extension()?.to_str()? {
+        "wav" => Some("audio/wav".to_string()),
+        "mp3" => Some("audio/mpeg".to_string()),
+        "ogg" | "oga" => Some("audio/ogg".to_string()),
+        "flac" => Some("audio/flac".to_string()),
+        "m4a" => Some("audio/mp4".to_string()),
+        "webm" => Some("audio/webm".to_string()),
+        _ => None,
+    }
+}
+
+/// Get the tool definition for the tts tool
+pub fn get_tts_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "tts".to_string(),
+        description: Some("Convert text to speech and return the audio.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<TtsRequest>()),
+        ..Default::default()
+    }
+}
+
+/// Get the tool definition for the stt tool
+pub fn get_stt_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "stt".to_string(),
+        description: Some(format!(
+            "Transcribe speech from an audio file within {} or from base64-encoded audio data.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<SttRequest>()),
+        ..Default::default()
+    }
+}
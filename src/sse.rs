@@ -0,0 +1,517 @@
+//! SSE transport module
+//!
+//! This module implements the MCP HTTP+SSE transport (protocol version 2024-11-05)
+//! still used by many clients and servers. The client opens a Server-Sent Events
+//! stream, the server announces a URL to POST messages to in an `endpoint` event,
+//! and responses and server notifications (tool list changes, progress) are pushed
+//! back on the stream. Both the server side, enabled with `McpServerBuilder::sse`, and
+//! the client side used by `McpClient::connect_sse` live here, along with the client
+//! side of the notification stream of Streamable HTTP sessions, which uses the same
+//! event format.
+
+use crate::error::{McpError, TransportError};
+use crate::jsonrpc_body::JsonRpcBody;
+use crate::mcp::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, McpServerState, RequestId};
+use crate::notification_queue::{self, DEFAULT_QUEUE_CAPACITY, NotificationQueue, OverflowPolicy};
+use crate::server_request::RequestHandlers;
+use crate::session;
+use axum::{
+    Extension,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures::StreamExt;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc as async_mpsc;
+
+/// Path of the SSE stream
+pub const SSE_PATH: &str = "/sse";
+
+/// Path messages are posted to, with the stream id in the `sessionId` query parameter
+pub const MESSAGES_PATH: &str = "/messages";
+
+/// Time allowed for a server to announce its message endpoint
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An open SSE stream on the server
+#[derive(Debug)]
+pub struct SseStream {
+    /// Responses to the client's requests, written to the stream
+    messages: async_mpsc::UnboundedSender<String>,
+    /// Session opened by the client's initialize request
+    session_id: Option<String>,
+}
+
+/// Forgets a stream and ends its session once the client disconnects
+struct StreamGuard {
+    state: McpServerState,
+    id: String,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        let state = self.state.clone();
+        let id = std::mem::take(&mut self.id);
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let stream = state.sse_streams.write().await.remove(&id);
+                if let Some(session_id) = stream.and_then(|stream| stream.session_id) {
+                    session::close(&state, &session_id).await;
+                }
+            });
+        }
+    }
+}
+
+/// Open an SSE stream (GET /sse)
+///
+/// The first event is `endpoint`, carrying the URL the client posts its messages
+/// to. Responses and server notifications follow as `message` events.
+pub async fn handle_connect(State(state): State<McpServerState>) -> Response {
+    let id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = async_mpsc::unbounded_channel();
+    state.sse_streams.write().await.insert(
+        id.clone(),
+        SseStream {
+            messages: sender,
+            session_id: None,
+        },
+    );
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("{}?sessionId={}", MESSAGES_PATH, id));
+    let guard = StreamGuard {
+        state: state.clone(),
+        id: id.clone(),
+    };
+    let responses = futures::stream::unfold((receiver, guard), |(mut receiver, guard)| async {
+        let message = receiver.recv().await?;
+        Some((message, (receiver, guard)))
+    });
+    let queue = notification_queue::open(&state, {
+        let state = state.clone();
+        move || {
+            let state = state.clone();
+            let stream_id = id.clone();
+            async move {
+                state
+                    .sse_streams
+                    .read()
+                    .await
+                    .get(&stream_id)
+                    .and_then(|stream| stream.session_id.clone())
+            }
+        }
+    });
+    let notifications = futures::stream::unfold(queue, |queue| async move {
+        let notification = queue.recv().await?;
+        let data = serde_json::to_string(&notification).unwrap_or_default();
+        Some((data, queue))
+    });
+
+    let events = futures::stream::once(async { endpoint })
+        .chain(
+            futures::stream::select(responses, notifications)
+                .map(|data| Event::default().event("message").data(data)),
+        )
+        .map(Ok::<_, Infallible>);
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Query parameters of a posted message
+#[derive(Debug, Deserialize)]
+pub struct MessageQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+/// Handle a message posted for an SSE stream (POST /messages)
+///
+/// The message is processed like a Streamable HTTP request within the stream's
+/// session; its response is written to the stream and the POST answered with 202.
+pub async fn handle_message(
+    State(state): State<McpServerState>,
+    Query(query): Query<MessageQuery>,
+    api_key: Option<Extension<Arc<crate::auth::ApiKey>>>,
+    JsonRpcBody(message): JsonRpcBody,
+) -> Response {
+    let Some(session_id) = state
+        .sse_streams
+        .read()
+        .await
+        .get(&query.session_id)
+        .map(|stream| stream.session_id.clone())
+    else {
+        return (StatusCode::NOT_FOUND, "SSE stream not found").into_response();
+    };
+
+    let api_key = api_key.map(|Extension(key)| key);
+    let outcome = crate::mcp::handle_message(&state, session_id.as_deref(), api_key, message).await;
+    if !outcome.status.is_success() {
+        return (outcome.status, outcome.body).into_response();
+    }
+
+    let mut streams = state.sse_streams.write().await;
+    let Some(stream) = streams.get_mut(&query.session_id) else {
+        return (StatusCode::NOT_FOUND, "SSE stream not found").into_response();
+    };
+    if outcome.new_session.is_some() {
+        stream.session_id = outcome.new_session;
+    }
+    if !outcome.body.is_empty() {
+        let _ = stream.messages.send(outcome.body);
+    }
+    StatusCode::ACCEPTED.into_response()
+}
+
+/// Callers waiting for the response to a request, by request id
+type Pending = Arc<Mutex<HashMap<RequestId, mpsc::Sender<JsonRpcResponse>>>>;
+
+/// Callbacks run on the reader thread for every notification the server pushes
+pub type NotificationListeners = Arc<Mutex<Vec<Box<dyn Fn(&JsonRpcNotification) + Send>>>>;
+
+/// A connection to an MCP server over the HTTP+SSE transport
+#[derive(Debug)]
+pub struct SseConnection {
+    client: Client,
+    /// URL the server announced for posting messages
+    endpoint: String,
+    pending: Pending,
+    /// Notifications pushed by the server
+    notifications: Arc<NotificationQueue>,
+    /// Tells the reader thread to stop
+    closed: Arc<AtomicBool>,
+}
+
+impl SseConnection {
+    /// Open the server's SSE stream and wait for its message endpoint
+    ///
+    /// Notifications on the stream are passed to `listeners`, and requests from the
+    /// server answered with `requests`.
+    pub fn connect(
+        url: &str,
+        tls: &crate::tls::TlsConfig,
+        listeners: NotificationListeners,
+        requests: RequestHandlers,
+    ) -> Result<Self, McpError> {
+        // The stream stays open for the whole connection, so requests must not time out
+        let client = tls.apply(Client::builder().timeout(None))?.build()?;
+        let response = client
+            .get(url)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()?;
+        if !response.status().is_success() {
+            return Err(McpError::connection(format!(
+                "HTTP error {} opening SSE stream",
+                response.status()
+            )));
+        }
+
+        let pending = Pending::default();
+        let notifications = Arc::new(NotificationQueue::new(
+            DEFAULT_QUEUE_CAPACITY,
+            OverflowPolicy::DropOldest,
+        ));
+        let closed = Arc::new(AtomicBool::new(false));
+        let reader = StreamReader {
+            client: client.clone(),
+            url: response.url().clone(),
+            pending: Arc::clone(&pending),
+            notifications: Arc::clone(&notifications),
+            listeners,
+            requests,
+            closed: Arc::clone(&closed),
+            endpoint: None,
+            headers: Default::default(),
+        };
+        let (endpoint_sender, endpoint_receiver) = mpsc::channel();
+        thread::spawn(move || reader.run(response, endpoint_sender));
+
+        let endpoint = endpoint_receiver
+            .recv_timeout(CONNECT_TIMEOUT)
+            .map_err(|_| {
+                McpError::connection(format!(
+                    "MCP server at {} did not announce a message endpoint",
+                    url
+                ))
+            })?;
+        Ok(Self {
+            client,
+            endpoint,
+            pending,
+            notifications,
+            closed,
+        })
+    }
+
+    /// Post a request and wait for its response on the stream
+    pub fn request(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse, McpError> {
+        let id = request
+            .id
+            .clone()
+            .ok_or_else(|| McpError::protocol("A request needs an id"))?;
+        let (sender, receiver) = mpsc::channel();
+        self.pending
+            .lock()
+            .map_err(|_| McpError::connection("Pending requests lock poisoned"))?
+            .insert(id.clone(), sender);
+
+        // The reader closes the queue before it drops the pending senders, so a
+        // request registered after that is failed here instead of waiting forever
+        let sent = if self.notifications.is_closed() {
+            Err(McpError::connection("MCP server closed the SSE stream"))
+        } else {
+            self.post(request)
+        };
+        if let Err(e) = sent {
+            self.pending
+                .lock()
+                .map_err(|_| McpError::connection("Pending requests lock poisoned"))?
+                .remove(&id);
+            return Err(e);
+        }
+
+        receiver
+            .recv()
+            .map_err(|_| McpError::connection("MCP server closed the SSE stream"))
+    }
+
+    /// Post a notification
+    pub fn notify(&self, notification: &JsonRpcNotification) -> Result<(), McpError> {
+        self.post(notification)
+    }
+
+    /// Notifications pushed by the server, oldest first
+    pub fn notifications(&self) -> &NotificationQueue {
+        &self.notifications
+    }
+
+    fn post(&self, message: &impl Serialize) -> Result<(), McpError> {
+        let response = self.client.post(&self.endpoint).json(message).send()?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let status = response.status();
+            let body = response.text()?;
+            Err(TransportError::Status { status, body }.into())
+        }
+    }
+}
+
+impl Drop for SseConnection {
+    /// Stop the reader thread; it exits with the next event or keep-alive
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The notification stream of a Streamable HTTP session (GET /mcp), on the client
+#[derive(Debug)]
+pub struct NotificationStream {
+    notifications: Arc<NotificationQueue>,
+    /// Tells the reader thread to stop
+    closed: Arc<AtomicBool>,
+}
+
+impl NotificationStream {
+    /// Open the stream of a session, sending the client's `headers` with its `tls`
+    /// options, and read it on its own thread
+    ///
+    /// Requests from the server on the stream are answered with `requests`, posting
+    /// the responses to `url` within the session.
+    pub fn open(
+        url: &str,
+        session_id: &str,
+        mut headers: reqwest::header::HeaderMap,
+        tls: &crate::tls::TlsConfig,
+        protocol_version: Option<&str>,
+        listeners: NotificationListeners,
+        requests: RequestHandlers,
+    ) -> Result<Self, McpError> {
+        let client = tls.apply(Client::builder().timeout(None))?.build()?;
+        let invalid = |e| McpError::protocol(format!("Invalid session header: {}", e));
+        headers.insert(
+            session::SESSION_HEADER,
+            session_id.parse().map_err(invalid)?,
+        );
+        if let Some(version) = protocol_version {
+            headers.insert(
+                session::PROTOCOL_VERSION_HEADER,
+                version.parse().map_err(invalid)?,
+            );
+        }
+        let response = client
+            .get(url)
+            .headers(headers.clone())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()?;
+        if !response.status().is_success() {
+            return Err(McpError::connection(format!(
+                "HTTP error {} opening the notification stream",
+                response.status()
+            )));
+        }
+
+        let notifications = Arc::new(NotificationQueue::new(
+            DEFAULT_QUEUE_CAPACITY,
+            OverflowPolicy::DropOldest,
+        ));
+        let closed = Arc::new(AtomicBool::new(false));
+        let reader = StreamReader {
+            client,
+            url: response.url().clone(),
+            pending: Pending::default(),
+            notifications: Arc::clone(&notifications),
+            listeners,
+            requests,
+            closed: Arc::clone(&closed),
+            // No endpoint is announced on this stream; responses go to the session's URL
+            endpoint: Some(url.to_string()),
+            headers,
+        };
+        thread::spawn(move || reader.run(response, mpsc::channel().0));
+
+        Ok(Self {
+            notifications,
+            closed,
+        })
+    }
+
+    /// Notifications pushed by the server, oldest first
+    pub fn notifications(&self) -> &NotificationQueue {
+        &self.notifications
+    }
+}
+
+impl Drop for NotificationStream {
+    /// Stop the reader thread; it exits with the next event or keep-alive
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Reads the SSE stream on its own thread and dispatches its events
+struct StreamReader {
+    client: Client,
+    /// URL of the stream, which the announced endpoint is relative to
+    url: reqwest::Url,
+    pending: Pending,
+    notifications: Arc<NotificationQueue>,
+    listeners: NotificationListeners,
+    requests: RequestHandlers,
+    closed: Arc<AtomicBool>,
+    /// The announced message endpoint, which responses to the server's requests go to
+    endpoint: Option<String>,
+    /// Headers sent with those responses
+    headers: reqwest::header::HeaderMap,
+}
+
+impl StreamReader {
+    fn run(mut self, response: reqwest::blocking::Response, endpoint: mpsc::Sender<String>) {
+        let mut event = String::new();
+        let mut data = Vec::new();
+
+        for line in BufReader::new(response).lines() {
+            if self.closed.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(line) = line else {
+                break;
+            };
+
+            if line.is_empty() {
+                if !data.is_empty() {
+                    self.dispatch(&event, &data.join("\n"), &endpoint);
+                }
+                event.clear();
+                data.clear();
+            } else if let Some(name) = line.strip_prefix("event:") {
+                event = name.trim().to_string();
+            } else if let Some(value) = line.strip_prefix("data:") {
+                data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+        }
+
+        // Wake up callers still waiting for a response
+        self.notifications.close();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.clear();
+        }
+    }
+
+    fn dispatch(&mut self, event: &str, data: &str, endpoint: &mpsc::Sender<String>) {
+        if event == "endpoint" {
+            if let Ok(url) = self.url.join(data) {
+                self.endpoint = Some(url.to_string());
+                let _ = endpoint.send(url.to_string());
+            }
+            return;
+        }
+
+        let Ok(message) = serde_json::from_str::<Value>(data) else {
+            return;
+        };
+        if message.get("method").is_some() {
+            match message.get("id") {
+                Some(_) => {
+                    let (Some(endpoint), Ok(request)) = (
+                        self.endpoint.clone(),
+                        serde_json::from_value::<JsonRpcRequest>(message),
+                    ) else {
+                        return;
+                    };
+                    let (client, headers, requests) = (
+                        self.client.clone(),
+                        self.headers.clone(),
+                        self.requests.clone(),
+                    );
+                    // Not on this thread, which must keep reading while the handler runs
+                    thread::spawn(move || {
+                        let response = requests.answer(request);
+                        let _ = client
+                            .post(&endpoint)
+                            .headers(headers)
+                            .json(&response)
+                            .send();
+                    });
+                }
+                None => {
+                    if let Ok(notification) = serde_json::from_value::<JsonRpcNotification>(message)
+                    {
+                        if let Ok(listeners) = self.listeners.lock() {
+                            for listener in listeners.iter() {
+                                listener(&notification);
+                            }
+                        }
+                        self.notifications.push(notification);
+                    }
+                }
+            }
+        } else if let Ok(response) = serde_json::from_value::<JsonRpcResponse>(message)
+            && let Some(id) = &response.id
+            && let Some(sender) = self
+                .pending
+                .lock()
+                .ok()
+                .and_then(|mut pending| pending.remove(id))
+        {
+            let _ = sender.send(response);
+        }
+    }
+}
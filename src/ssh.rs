@@ -0,0 +1,652 @@
+//! SSH remote tools module
+//!
+//! This module reads files from and runs commands on named hosts configured in the
+//! server config. Hosts are verified against a known_hosts file, files can only be
+//! read below each host's allowed paths, and commands must start with one of the
+//! host's allowed commands, so the agent never gets an arbitrary shell. Sessions use
+//! the async russh client, so calls wait on the network without holding a thread. It
+//! is only compiled with the `ssh` feature.
+
+use crate::cancellation::CancellationToken;
+use crate::config::SshHostConfig;
+use crate::progress::ProgressReporter;
+use crate::tool_error::ToolErrorPayload;
+use russh::client::{self, Handle};
+use russh::keys::{PrivateKeyWithHashAlg, PublicKey};
+use russh::{ChannelMsg, Disconnect};
+use russh_sftp::client::SftpSession;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+/// Errors of the SSH tools, which run on the async runtime
+type SshError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Timeout for establishing the connection, up to the end of the key exchange
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time an established session may stay silent before it is closed
+const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Upper bound on the size of a file read from a remote host
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Upper bound on the output captured from a remote command
+const MAX_OUTPUT_BYTES: u64 = 1024 * 1024;
+
+/// Remote file read tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteFileReadRequest {
+    /// The name of the configured host
+    pub host: String,
+    /// The absolute file path on the remote host
+    pub path: String,
+}
+
+/// Remote file read tool response
+#[derive(Debug, Serialize)]
+pub struct RemoteFileReadResponse {
+    pub host: String,
+    pub path: String,
+    pub size: u64,
+    pub content: String,
+}
+
+/// Remote exec tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemoteExecRequest {
+    /// The name of the configured host
+    pub host: String,
+    /// The command line to run, e.g. "systemctl status nginx"
+    pub command: String,
+}
+
+/// Remote exec tool response
+#[derive(Debug, Serialize)]
+pub struct RemoteExecResponse {
+    pub host: String,
+    pub command: String,
+    /// -1 when the host reported none, as when the output was cut off
+    pub exit_status: i32,
+    /// Combined stdout and stderr
+    pub output: String,
+    pub truncated: bool,
+}
+
+/// Execute the remote file read tool
+pub async fn execute_remote_file_read(
+    request: RemoteFileReadRequest,
+    hosts: &[SshHostConfig],
+) -> Result<RemoteFileReadResponse, SshError> {
+    let host = find_host(hosts, &request.host)?;
+    check_remote_path(Path::new(&request.path), &host.allowed_paths)
+        .map_err(ToolErrorPayload::access_denied)?;
+
+    let session = connect(host).await?;
+    let channel = session.channel_open_session().await?;
+    channel.request_subsystem(true, "sftp").await?;
+    let sftp = SftpSession::new(channel.into_stream()).await?;
+
+    // Resolve symlinks on the remote side before checking the path again
+    let resolved = sftp
+        .canonicalize(request.path.as_str())
+        .await
+        .map(PathBuf::from)
+        .map_err(|e| {
+            ToolErrorPayload::not_found(format!("File not found: {} ({})", request.path, e))
+        })?;
+    check_remote_path(&resolved, &host.allowed_paths).map_err(ToolErrorPayload::access_denied)?;
+    let resolved_path = resolved.display().to_string();
+
+    let metadata = sftp.metadata(resolved_path.as_str()).await?;
+    if !metadata.file_type().is_file() {
+        return Err(format!("Path is not a file: {}", request.path).into());
+    }
+    let size = metadata.len();
+    if size > MAX_FILE_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("File exceeds the {} byte limit", MAX_FILE_BYTES),
+            MAX_FILE_BYTES,
+        )
+        .into());
+    }
+
+    let mut content = Vec::new();
+    sftp.open(resolved_path.as_str())
+        .await?
+        .take(MAX_FILE_BYTES)
+        .read_to_end(&mut content)
+        .await?;
+    let content = String::from_utf8(content)
+        .map_err(|_| format!("File is not valid UTF-8 text: {}", request.path))?;
+    disconnect(&session).await;
+
+    Ok(RemoteFileReadResponse {
+        host: host.name.clone(),
+        path: resolved_path,
+        size,
+        content,
+    })
+}
+
+/// Execute the remote exec tool
+///
+/// The bytes of output received so far are reported to `progress` while the command
+/// runs, and the channel is closed once `cancel` is cancelled.
+pub async fn execute_remote_exec(
+    request: RemoteExecRequest,
+    hosts: &[SshHostConfig],
+    progress: &ProgressReporter,
+    cancel: &CancellationToken,
+) -> Result<RemoteExecResponse, SshError> {
+    let host = find_host(hosts, &request.host)?;
+    let command = check_command(&request.command, &host.allowed_commands)?;
+
+    let session = connect(host).await?;
+    let mut channel = session.channel_open_session().await?;
+    channel.exec(true, command.as_str()).await?;
+
+    let message = format!("Running '{}' on {}", command, host.name);
+    progress.report(0.0, None, Some(&message));
+    let mut output = Vec::new();
+    let mut exit_status = None;
+    let mut truncated = false;
+    loop {
+        let received = tokio::select! {
+            received = channel.wait() => received,
+            _ = cancel.cancelled() => {
+                let _ = channel.close().await;
+                disconnect(&session).await;
+                return Err(format!("Command '{}' cancelled", command).into());
+            }
+        };
+        // Standard error is merged into the output, as a terminal shows it
+        match received {
+            Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. })
+                if !truncated =>
+            {
+                output.extend_from_slice(&data);
+                progress.report(output.len() as f64, None, Some(&message));
+                if output.len() as u64 > MAX_OUTPUT_BYTES {
+                    output.truncate(MAX_OUTPUT_BYTES as usize);
+                    truncated = true;
+                    channel.close().await?;
+                }
+            }
+            Some(ChannelMsg::ExitStatus {
+                exit_status: status,
+            }) => exit_status = Some(status),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    disconnect(&session).await;
+
+    Ok(RemoteExecResponse {
+        host: host.name.clone(),
+        command,
+        exit_status: exit_status.map_or(-1, |status| status as i32),
+        output: String::from_utf8_lossy(&output).into_owned(),
+        truncated,
+    })
+}
+
+/// Look up a configured host by name
+fn find_host<'a>(hosts: &'a [SshHostConfig], name: &str) -> Result<&'a SshHostConfig, SshError> {
+    hosts
+        .iter()
+        .find(|host| host.name == name)
+        .ok_or_else(|| format!("Unknown host '{}'", name).into())
+}
+
+/// Check that every host can be reached and authenticated against
+pub async fn check(hosts: &[SshHostConfig]) -> Result<(), SshError> {
+    for host in hosts {
+        let session = connect(host)
+            .await
+            .map_err(|e| format!("Host '{}': {}", host.name, e))?;
+        disconnect(&session).await;
+    }
+    Ok(())
+}
+
+/// Client side of a session, verifying the server's host key against known_hosts
+struct KnownHostsCheck {
+    host: String,
+    port: u16,
+    known_hosts: PathBuf,
+}
+
+impl client::Handler for KnownHostsCheck {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, key: &PublicKey) -> Result<bool, Self::Error> {
+        // A key missing from known_hosts fails the handshake with UnknownKey, one
+        // differing from the recorded key with KeyChanged
+        Ok(russh::keys::check_known_hosts_path(
+            &self.host,
+            self.port,
+            key,
+            &self.known_hosts,
+        )?)
+    }
+}
+
+/// Open an authenticated session to a host
+async fn connect(host: &SshHostConfig) -> Result<Handle<KnownHostsCheck>, SshError> {
+    let port = host.port.unwrap_or(22);
+    let known_hosts = expand_home(host.known_hosts.as_deref().unwrap_or("~/.ssh/known_hosts"));
+    // russh treats an unreadable known_hosts like one without the host
+    std::fs::File::open(&known_hosts).map_err(|e| {
+        format!(
+            "Failed to read known_hosts '{}': {}",
+            known_hosts.display(),
+            e
+        )
+    })?;
+
+    let config = Arc::new(client::Config {
+        inactivity_timeout: Some(SESSION_TIMEOUT),
+        ..Default::default()
+    });
+    let handler = KnownHostsCheck {
+        host: host.host.clone(),
+        port,
+        known_hosts,
+    };
+    let connecting = client::connect(config, (host.host.as_str(), port), handler);
+    let mut session = match tokio::time::timeout(CONNECT_TIMEOUT, connecting).await {
+        Ok(Ok(session)) => session,
+        Ok(Err(russh::Error::UnknownKey)) => {
+            return Err(format!("Host key for '{}' is not in known_hosts", host.host).into());
+        }
+        Ok(Err(russh::Error::Keys(russh::keys::Error::KeyChanged { .. }))) => {
+            return Err(format!("Host key for '{}' does not match known_hosts", host.host).into());
+        }
+        Ok(Err(e)) => return Err(format!("Failed to connect to '{}': {}", host.name, e).into()),
+        Err(_) => return Err(format!("Timed out connecting to '{}'", host.name).into()),
+    };
+
+    let authenticated = match &host.identity_file {
+        Some(identity_file) => {
+            let passphrase = match &host.passphrase_env {
+                Some(variable) => Some(std::env::var(variable).map_err(|_| {
+                    format!("SSH key passphrase variable '{}' is not set", variable)
+                })?),
+                None => None,
+            };
+            let key =
+                russh::keys::load_secret_key(expand_home(identity_file), passphrase.as_deref())?;
+            let hash_alg = session.best_supported_rsa_hash().await?.flatten();
+            session
+                .authenticate_publickey(
+                    &host.user,
+                    PrivateKeyWithHashAlg::new(Arc::new(key), hash_alg),
+                )
+                .await?
+                .success()
+        }
+        None => authenticate_with_agent(&mut session, &host.user).await?,
+    };
+
+    if !authenticated {
+        return Err(format!("Authentication to '{}' failed", host.name).into());
+    }
+
+    Ok(session)
+}
+
+/// Try the keys of the SSH agent at `SSH_AUTH_SOCK` in turn
+#[cfg(unix)]
+async fn authenticate_with_agent(
+    session: &mut Handle<KnownHostsCheck>,
+    user: &str,
+) -> Result<bool, SshError> {
+    let mut agent = russh::keys::agent::client::AgentClient::connect_env()
+        .await
+        .map_err(|e| format!("Failed to connect to the SSH agent: {}", e))?;
+    let hash_alg = session.best_supported_rsa_hash().await?.flatten();
+    for key in agent.request_identities().await? {
+        if session
+            .authenticate_publickey_with(user, key, hash_alg, &mut agent)
+            .await?
+            .success()
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// SSH agents are only reached over a Unix socket
+#[cfg(not(unix))]
+async fn authenticate_with_agent(
+    _session: &mut Handle<KnownHostsCheck>,
+    _user: &str,
+) -> Result<bool, SshError> {
+    Err("The SSH agent is not supported on this platform; set identity_file".into())
+}
+
+/// Close a session, ignoring hosts that already dropped the connection
+async fn disconnect(session: &Handle<KnownHostsCheck>) {
+    let _ = session
+        .disconnect(Disconnect::ByApplication, "", "en")
+        .await;
+}
+
+/// Expand a leading `~/` to the user's home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Check that a remote path is absolute, has no `..` and lies below an allowed directory
+fn check_remote_path(path: &Path, allowed_paths: &[String]) -> Result<(), String> {
+    if !path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!(
+            "Access denied: '{}' must be an absolute path without '..'",
+            path.display()
+        ));
+    }
+
+    if allowed_paths
+        .iter()
+        .any(|allowed| path.starts_with(Path::new(allowed)))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Access denied: '{}' is not within an allowed path",
+            path.display()
+        ))
+    }
+}
+
+/// Check a command against the host's allowlist and normalize its whitespace
+///
+/// Commands are run by the remote user's shell, so only plain words are accepted:
+/// no quoting, globbing, redirection, pipes or variable expansion. A command is
+/// allowed when its leading words equal one of the allowlist entries, e.g. the
+/// entry `systemctl status` allows `systemctl status nginx`.
+fn check_command(command: &str, allowed_commands: &[String]) -> Result<String, String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    if words.is_empty() {
+        return Err("Command is empty".to_string());
+    }
+
+    if let Some(c) = command.chars().find(|c| {
+        !(c.is_ascii_alphanumeric() || c.is_ascii_whitespace() || "-_./=:,@+%".contains(*c))
+    }) {
+        return Err(format!("Access denied: character '{}' is not allowed", c));
+    }
+
+    let allowed = allowed_commands.iter().any(|entry| {
+        let prefix: Vec<&str> = entry.split_whitespace().collect();
+        !prefix.is_empty() && words.starts_with(&prefix)
+    });
+
+    let command = words.join(" ");
+    if allowed {
+        Ok(command)
+    } else {
+        Err(format!(
+            "Access denied: '{}' is not an allowed command",
+            command
+        ))
+    }
+}
+
+/// Restrict the host argument of a tool schema to the configured host names
+fn with_host_names(mut schema: serde_json::Value, hosts: &[SshHostConfig]) -> serde_json::Value {
+    let names: Vec<&str> = hosts.iter().map(|host| host.name.as_str()).collect();
+    schema["properties"]["host"]["enum"] = serde_json::json!(names);
+    schema
+}
+
+/// Get the tool definition for the remote_file_read tool
+pub fn get_file_read_tool_definition(hosts: &[SshHostConfig]) -> crate::mcp::McpTool {
+    let hosts: Vec<SshHostConfig> = hosts
+        .iter()
+        .filter(|host| !host.allowed_paths.is_empty())
+        .cloned()
+        .collect();
+
+    crate::mcp::McpTool {
+        name: "remote_file_read".to_string(),
+        version: None,
+        description: Some("Read a text file from a configured remote host over SSH. The path must be within one of the host's allowed paths.".to_string()),
+        input_schema: Some(with_host_names(
+            crate::schema::input_schema::<RemoteFileReadRequest>(),
+            &hosts,
+        )),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            title: Some("Read remote file".to_string()),
+            read_only_hint: Some(true),
+            open_world_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Get the tool definition for the remote_exec tool
+pub fn get_exec_tool_definition(hosts: &[SshHostConfig]) -> crate::mcp::McpTool {
+    let hosts: Vec<SshHostConfig> = hosts
+        .iter()
+        .filter(|host| !host.allowed_commands.is_empty())
+        .cloned()
+        .collect();
+
+    crate::mcp::McpTool {
+        name: "remote_exec".to_string(),
+        version: None,
+        description: Some("Run an allowlisted command on a configured remote host over SSH and return its exit status and output. Commands are plain words only: no pipes, redirection, quoting or variables.".to_string()),
+        input_schema: Some(with_host_names(
+            crate::schema::input_schema::<RemoteExecRequest>(),
+            &hosts,
+        )),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            title: Some("Run remote command".to_string()),
+            read_only_hint: Some(false),
+            open_world_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::McpServerState;
+    use russh::keys::{Algorithm, PrivateKey};
+    use russh::server::{self, Auth, Msg, Session};
+    use russh::{Channel, ChannelId, CryptoVec};
+
+    /// SSH server answering `echo hi` on stdout and stderr with exit status 3
+    struct EchoServer {
+        client_key: PublicKey,
+    }
+
+    impl server::Handler for EchoServer {
+        type Error = russh::Error;
+
+        async fn auth_publickey(
+            &mut self,
+            _user: &str,
+            key: &PublicKey,
+        ) -> Result<Auth, Self::Error> {
+            Ok(if *key == self.client_key {
+                Auth::Accept
+            } else {
+                Auth::reject()
+            })
+        }
+
+        async fn channel_open_session(
+            &mut self,
+            _channel: Channel<Msg>,
+            _session: &mut Session,
+        ) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+
+        async fn exec_request(
+            &mut self,
+            channel: ChannelId,
+            data: &[u8],
+            session: &mut Session,
+        ) -> Result<(), Self::Error> {
+            session.channel_success(channel)?;
+            if data == b"echo hi" {
+                session.data(channel, CryptoVec::from_slice(b"hi\n"))?;
+                session.extended_data(channel, 1, CryptoVec::from_slice(b"warning\n"))?;
+            }
+            session.exit_status_request(channel, 3)?;
+            session.eof(channel)?;
+            session.close(channel)
+        }
+    }
+
+    fn random_key() -> PrivateKey {
+        PrivateKey::random(
+            &mut russh::keys::ssh_key::rand_core::OsRng,
+            Algorithm::Ed25519,
+        )
+        .unwrap()
+    }
+
+    /// Start a server and configure a host for it, with `known_key` in its known_hosts
+    async fn echo_host(dir: &tempfile::TempDir, known_key: Option<&PublicKey>) -> SshHostConfig {
+        let host_key = random_key();
+        let client_key = random_key();
+        let config = Arc::new(server::Config {
+            keys: vec![host_key.clone()],
+            ..Default::default()
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client_public = client_key.public_key().clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = EchoServer {
+                    client_key: client_public.clone(),
+                };
+                if let Ok(session) = server::run_stream(config.clone(), stream, handler).await {
+                    tokio::spawn(session);
+                }
+            }
+        });
+
+        let known_key = known_key.unwrap_or(host_key.public_key());
+        let known_hosts = dir.path().join("known_hosts");
+        std::fs::write(
+            &known_hosts,
+            format!("[127.0.0.1]:{} {}\n", port, known_key.to_openssh().unwrap()),
+        )
+        .unwrap();
+        let identity_file = dir.path().join("id_ed25519");
+        std::fs::write(
+            &identity_file,
+            client_key
+                .to_openssh(Default::default())
+                .unwrap()
+                .as_bytes(),
+        )
+        .unwrap();
+
+        SshHostConfig {
+            name: "echo".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            user: "agent".to_string(),
+            identity_file: Some(identity_file.display().to_string()),
+            passphrase_env: None,
+            known_hosts: Some(known_hosts.display().to_string()),
+            allowed_commands: vec!["echo".to_string()],
+            allowed_paths: Vec::new(),
+        }
+    }
+
+    fn exec(command: &str) -> RemoteExecRequest {
+        RemoteExecRequest {
+            host: "echo".to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_a_command_and_merges_its_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let host = echo_host(&dir, None).await;
+        let progress = ProgressReporter::new(&McpServerState::new(), None, None);
+
+        let result = execute_remote_exec(
+            exec("echo  hi"),
+            &[host],
+            &progress,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.command, "echo hi");
+        assert_eq!(result.output, "hi\nwarning\n");
+        assert_eq!(result.exit_status, 3);
+        assert!(!result.truncated);
+    }
+
+    #[tokio::test]
+    async fn refuses_hosts_whose_key_is_not_known() {
+        let dir = tempfile::tempdir().unwrap();
+        let other_key = random_key();
+        let host = echo_host(&dir, Some(other_key.public_key())).await;
+        let error = check(std::slice::from_ref(&host)).await.unwrap_err();
+        assert!(
+            error.to_string().contains("does not match known_hosts"),
+            "{}",
+            error
+        );
+
+        std::fs::write(host.known_hosts.as_ref().unwrap(), "").unwrap();
+        let error = check(std::slice::from_ref(&host)).await.unwrap_err();
+        assert!(
+            error.to_string().contains("is not in known_hosts"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_commands_outside_the_allowlist_before_connecting() {
+        let host = SshHostConfig {
+            name: "echo".to_string(),
+            host: "unreachable.invalid".to_string(),
+            port: None,
+            user: "agent".to_string(),
+            identity_file: None,
+            passphrase_env: None,
+            known_hosts: None,
+            allowed_commands: vec!["echo".to_string()],
+            allowed_paths: Vec::new(),
+        };
+        let progress = ProgressReporter::new(&McpServerState::new(), None, None);
+
+        for command in ["rm -rf /", "echo hi; rm -rf /", "echo $HOME"] {
+            let error = execute_remote_exec(
+                exec(command),
+                std::slice::from_ref(&host),
+                &progress,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap_err();
+            assert!(error.to_string().starts_with("Access denied"), "{}", error);
+        }
+    }
+}
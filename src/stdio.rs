@@ -0,0 +1,198 @@
+//! stdio transport module
+//!
+//! This module runs an MCP server as a child process and exchanges newline-delimited
+//! JSON-RPC messages with it over stdin/stdout, which is how most published MCP
+//! servers are consumed. The child is shut down when the connection is dropped.
+
+use crate::error::McpError;
+use crate::mcp::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+use crate::server_request::RequestHandlers;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a child may take to exit after its stdin is closed before it is killed
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// A connection to an MCP server running as a child process
+#[derive(Debug)]
+pub struct StdioConnection {
+    child: Child,
+    /// Closed on drop to ask the server to exit
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    /// The command line, for messages
+    command: String,
+    /// Answer the requests the server sends while a request waits for its response
+    requests: RequestHandlers,
+}
+
+impl StdioConnection {
+    /// Launch the server; its stderr is passed through for logging
+    pub fn spawn(command: &str, args: &[String]) -> Result<Self, McpError> {
+        Self::spawn_with_env(command, args, std::iter::empty::<(String, String)>())
+    }
+
+    /// Launch the server with additional environment variables
+    pub fn spawn_with_env<K, V>(
+        command: &str,
+        args: &[String],
+        env: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, McpError>
+    where
+        K: AsRef<std::ffi::OsStr>,
+        V: AsRef<std::ffi::OsStr>,
+    {
+        let mut launch = Command::new(command);
+        launch.args(args).envs(env);
+        let mut child = crate::sandbox::wrap(launch)
+            .map_err(|e| {
+                McpError::connection(format!("Failed to sandbox MCP server '{}': {}", command, e))
+            })?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                McpError::connection(format!("Failed to start MCP server '{}': {}", command, e))
+            })?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| McpError::connection("Child process has no stdin"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| McpError::connection("Child process has no stdout"))?;
+        Ok(Self {
+            child,
+            stdin: Some(stdin),
+            stdout: BufReader::new(stdout),
+            command: std::iter::once(command)
+                .chain(args.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" "),
+            requests: RequestHandlers::default(),
+        })
+    }
+
+    /// The command line the server was started with
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Answer the requests the server sends with `requests`
+    pub fn set_request_handlers(&mut self, requests: RequestHandlers) {
+        self.requests = requests;
+    }
+
+    /// Send a request and wait for the response with the same id
+    ///
+    /// Notifications arriving in the meantime are skipped, and requests from the
+    /// server are answered by the connection's request handlers.
+    pub fn request(&mut self, request: &JsonRpcRequest) -> Result<JsonRpcResponse, McpError> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| McpError::connection("MCP server stdin is closed"))?;
+        exchange(
+            &mut self.stdout,
+            stdin,
+            request,
+            &self.command,
+            &self.requests,
+        )
+    }
+
+    /// Send a notification
+    pub fn notify(&mut self, notification: &JsonRpcNotification) -> Result<(), McpError> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| McpError::connection("MCP server stdin is closed"))?;
+        write_message(stdin, notification)
+    }
+}
+
+/// Send a request over a newline-delimited connection and wait for its response
+///
+/// Shared with the unix socket transport. `peer` names the server in error messages.
+/// Requests from the server are answered with `requests` before reading on.
+pub(crate) fn exchange(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    request: &JsonRpcRequest,
+    peer: &str,
+    requests: &RequestHandlers,
+) -> Result<JsonRpcResponse, McpError> {
+    write_message(writer, request)?;
+
+    loop {
+        let message = read_message(reader, peer)?;
+        if message.get("method").is_some() {
+            if message.get("id").is_some()
+                && let Ok(server_request) = serde_json::from_value::<JsonRpcRequest>(message)
+            {
+                write_message(writer, &requests.answer(server_request))?;
+            }
+            continue;
+        }
+        if let Ok(response) = serde_json::from_value::<JsonRpcResponse>(message)
+            && response.id.is_some()
+            && response.id == request.id
+        {
+            return Ok(response);
+        }
+    }
+}
+
+/// Write one message as a single line
+pub(crate) fn write_message(
+    writer: &mut impl Write,
+    message: &impl serde::Serialize,
+) -> Result<(), McpError> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read the next message, skipping blank lines
+fn read_message(reader: &mut impl BufRead, peer: &str) -> Result<Value, McpError> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(McpError::connection(format!(
+                "MCP server '{}' closed the connection",
+                peer
+            )));
+        }
+        if !line.trim().is_empty() {
+            return serde_json::from_str(&line).map_err(|e| {
+                McpError::protocol(format!("Invalid message from MCP server: {}", e))
+            });
+        }
+    }
+}
+
+impl Drop for StdioConnection {
+    /// Close stdin, give the server a moment to exit, then kill it
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while Instant::now() < deadline {
+            match self.child.try_wait() {
+                Ok(Some(_)) | Err(_) => return,
+                Ok(None) => thread::sleep(Duration::from_millis(50)),
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,199 @@
+//! Summarization tool module
+//!
+//! This module summarizes files server-side with a configured Ollama model and
+//! returns only the summary, so large documents never enter the host model's
+//! context. Documents longer than one model request are summarized in sections
+//! whose summaries are then combined.
+
+use crate::config::SummarizeConfig;
+use crate::ollama::Ollama;
+use crate::tokenizer::Tokenizer;
+use crate::tool_error::ToolErrorPayload;
+use crate::vfs::VirtualFs;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Ollama server used when none is configured
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Upper bound on the size of a summarized file
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Tokens of the document sent to the model per request
+const SECTION_TOKENS: usize = 3_000;
+
+/// Timeout for a single model request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Time allowed for the model check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Summarize tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SummarizeRequest {
+    /// The file path to summarize
+    pub path: String,
+    /// How long the summary should be (default medium)
+    #[schemars(extend("enum" = ["short", "medium", "long"]))]
+    pub length: Option<String>,
+    /// Optional topic or question the summary should concentrate on
+    pub focus: Option<String>,
+}
+
+/// Summarize tool response
+#[derive(Debug, Serialize)]
+pub struct SummarizeResponse {
+    pub path: String,
+    pub summary: String,
+    /// Number of sections the document was split into
+    pub sections: usize,
+    pub source_chars: usize,
+}
+
+/// Execute the summarize tool
+pub fn execute_summarize(
+    request: SummarizeRequest,
+    config: &SummarizeConfig,
+    fs: &dyn VirtualFs,
+) -> Result<SummarizeResponse, Box<dyn std::error::Error>> {
+    let model = config
+        .model
+        .as_deref()
+        .ok_or("No summarization model configured")?;
+    let length = match request.length.as_deref().unwrap_or("medium") {
+        "short" => "in two or three sentences",
+        "medium" => "in one or two short paragraphs",
+        "long" => "in detail, using a few paragraphs or a bullet list",
+        other => {
+            return Err(
+                format!("Unknown length '{}', expected short, medium or long", other).into(),
+            );
+        }
+    };
+
+    let path = crate::file_read::validate_path(fs, &request.path)?;
+    if fs.metadata(path)?.len > MAX_FILE_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("File exceeds the {} byte limit", MAX_FILE_BYTES),
+            MAX_FILE_BYTES,
+        )
+        .into());
+    }
+    let content = fs
+        .read_to_string(path)
+        .map_err(|e| format!("Failed to read file '{}': {}", request.path, e))?;
+    crate::accounting::read(content.len() as u64);
+    if content.trim().is_empty() {
+        return Err(format!("File is empty: {}", request.path).into());
+    }
+
+    let ollama = Ollama::default(model)
+        .with_base_url(config.ollama_url.as_deref().unwrap_or(DEFAULT_OLLAMA_URL))
+        .with_timeout(REQUEST_TIMEOUT);
+    let focus = request
+        .focus
+        .as_deref()
+        .map(|focus| format!(" Concentrate on: {}.", focus))
+        .unwrap_or_default();
+
+    let sections = split_sections(&content, &*crate::tokenizer::for_model(model));
+    let summary = if sections.len() == 1 {
+        ollama.chat_with_system(
+            format!(
+                "Summarize the document the user sends {}.{} Reply with the summary only.",
+                length, focus
+            ),
+            content.as_str(),
+        )?
+    } else {
+        // Summarize each section, then combine the partial summaries
+        let partial = sections
+            .iter()
+            .enumerate()
+            .map(|(i, section)| {
+                ollama.chat_with_system(
+                    format!(
+                        "The user sends section {} of {} of a longer document. Summarize it concisely, keeping facts, names and numbers.{} Reply with the summary only.",
+                        i + 1,
+                        sections.len(),
+                        focus
+                    ),
+                    *section,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        ollama.chat_with_system(
+            format!(
+                "The user sends summaries of consecutive sections of one document. Combine them into a single summary of the whole document {}.{} Reply with the summary only.",
+                length, focus
+            ),
+            partial.join("\n\n"),
+        )?
+    };
+
+    Ok(SummarizeResponse {
+        path: request.path,
+        summary: summary.trim().to_string(),
+        sections: sections.len(),
+        source_chars: content.chars().count(),
+    })
+}
+
+/// Split a document into sections of about `SECTION_TOKENS`, preferring line breaks
+///
+/// Sections are cut by characters, at the document's average characters per token.
+fn split_sections<'a>(content: &'a str, tokenizer: &dyn Tokenizer) -> Vec<&'a str> {
+    let tokens = tokenizer.count_tokens(content).max(1);
+    let section_chars = (SECTION_TOKENS * content.chars().count() / tokens).max(1);
+    let mut sections = Vec::new();
+    let mut rest = content;
+
+    while let Some((limit, _)) = rest.char_indices().nth(section_chars) {
+        let end = match rest[..limit]
+            .rfind("\n\n")
+            .or_else(|| rest[..limit].rfind('\n'))
+        {
+            Some(end) if end > limit / 2 => end,
+            _ => limit,
+        };
+        sections.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    if !rest.trim().is_empty() {
+        sections.push(rest);
+    }
+
+    sections
+}
+
+/// Check that the summarization model is available, without summarizing anything
+pub fn check(config: &SummarizeConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let model = config
+        .model
+        .as_deref()
+        .ok_or("No summarization model configured")?;
+    Ollama::default(model)
+        .with_base_url(config.ollama_url.as_deref().unwrap_or(DEFAULT_OLLAMA_URL))
+        .with_timeout(CHECK_TIMEOUT)
+        .check_model()?;
+    Ok(())
+}
+
+/// Get the tool definition for the summarize tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "summarize".to_string(),
+        version: None,
+        description: Some(format!(
+            "Summarize a text file server-side and return only the summary. Use this instead of reading large documents in full. The path must be within {}",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<SummarizeRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
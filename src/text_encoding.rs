@@ -0,0 +1,53 @@
+//! Text encoding module
+//!
+//! This module turns the bytes of a text file into UTF-8 whatever their encoding. A
+//! byte order mark decides first (UTF-8, UTF-16LE or UTF-16BE), then valid UTF-8 is
+//! taken as is, and anything else is handed to a statistical detector that recognizes
+//! the legacy encodings browsers do, such as windows-1252 (a superset of latin-1),
+//! Shift_JIS, EUC-KR or GBK. Files containing NUL bytes without a UTF-16 byte order
+//! mark are taken to be binary and refused.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+
+/// Text decoded from a file
+#[derive(Debug, Clone)]
+pub struct DecodedText {
+    pub text: String,
+    /// Name of the encoding the text was decoded from, e.g. `UTF-8` or `Shift_JIS`
+    pub encoding: &'static str,
+    /// Whether some bytes were invalid in that encoding and replaced with U+FFFD
+    pub had_errors: bool,
+}
+
+/// Decode `bytes` to UTF-8, in `label` (e.g. `latin1` or `shift_jis`) if given or else
+/// in the detected encoding
+pub fn decode(bytes: &[u8], label: Option<&str>) -> Result<DecodedText, String> {
+    let encoding = match label {
+        Some(label) => Encoding::for_label(label.trim().as_bytes())
+            .ok_or_else(|| format!("Unknown encoding '{}'", label))?,
+        None => detect(bytes)?,
+    };
+    let (text, encoding, had_errors) = encoding.decode(bytes);
+    Ok(DecodedText {
+        text: text.into_owned(),
+        encoding: encoding.name(),
+        had_errors,
+    })
+}
+
+/// The encoding of `bytes`, from its byte order mark or its contents
+pub fn detect(bytes: &[u8]) -> Result<&'static Encoding, String> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Ok(encoding);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Ok(UTF_8);
+    }
+    if bytes.contains(&0) {
+        return Err("File is binary, not text".to_string());
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    Ok(detector.guess(None, true))
+}
@@ -0,0 +1,178 @@
+//! Tokenizer module
+//!
+//! This module counts the tokens of text for a model, for the code that has to keep
+//! requests within a model's context: the chat session's context budget, memory
+//! chunking and the sections of the summarize tool. The tokenizer is picked by model
+//! family (the model name up to its tag, e.g. `llama3.1` for `llama3.1:8b`):
+//!
+//! 1. a Hugging Face `tokenizer.json` saved as `<family>.json` in the tokenizers
+//!    directory (`$MCP_TOKENIZERS_DIR`, or `~/.config/mcp/tokenizers`), with the
+//!    `hf-tokenizers` feature;
+//! 2. the tiktoken encoding of GPT and Llama 3 families, with the `tiktoken` feature;
+//! 3. otherwise an estimate of one token per four characters.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Counts the tokens a model sees for some text
+pub trait Tokenizer: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// What the count is based on, for diagnostics
+    fn name(&self) -> &str;
+}
+
+/// Estimate of one token per four characters, for models without a tokenizer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CharEstimate;
+
+impl Tokenizer for CharEstimate {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+
+    fn name(&self) -> &str {
+        "chars/4 estimate"
+    }
+}
+
+/// A tiktoken byte pair encoding
+#[cfg(feature = "tiktoken")]
+pub struct Tiktoken {
+    name: &'static str,
+    bpe: &'static tiktoken_rs::CoreBPE,
+}
+
+#[cfg(feature = "tiktoken")]
+impl Tokenizer for Tiktoken {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+/// A Hugging Face tokenizer loaded from a `tokenizer.json` file
+#[cfg(feature = "hf-tokenizers")]
+pub struct HfTokenizer {
+    name: String,
+    tokenizer: tokenizers::Tokenizer,
+}
+
+#[cfg(feature = "hf-tokenizers")]
+impl HfTokenizer {
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let tokenizer = tokenizers::Tokenizer::from_file(path)
+            .map_err(|e| format!("Failed to load tokenizer '{}': {}", path.display(), e))?;
+        Ok(Self {
+            name: path.display().to_string(),
+            tokenizer,
+        })
+    }
+}
+
+#[cfg(feature = "hf-tokenizers")]
+impl Tokenizer for HfTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        match self.tokenizer.encode(text, false) {
+            Ok(encoding) => encoding.len(),
+            Err(_) => CharEstimate.count_tokens(text),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Model family of a model name: the name without its tag and namespace, lowercased
+pub fn family(model: &str) -> String {
+    let name = model.split(':').next().unwrap_or(model);
+    let name = name.rsplit('/').next().unwrap_or(name);
+    name.to_ascii_lowercase()
+}
+
+/// The tokenizer for a model, loaded once per family
+pub fn for_model(model: &str) -> Arc<dyn Tokenizer> {
+    static LOADED: OnceLock<Mutex<HashMap<String, Arc<dyn Tokenizer>>>> = OnceLock::new();
+
+    let family = family(model);
+    let loaded = LOADED.get_or_init(Mutex::default);
+    if let Ok(loaded) = loaded.lock()
+        && let Some(tokenizer) = loaded.get(&family)
+    {
+        return Arc::clone(tokenizer);
+    }
+    let tokenizer = load(&family);
+    if let Ok(mut loaded) = loaded.lock() {
+        loaded.insert(family, Arc::clone(&tokenizer));
+    }
+    tokenizer
+}
+
+fn load(family: &str) -> Arc<dyn Tokenizer> {
+    hf_tokenizer(family)
+        .or_else(|| tiktoken(family))
+        .unwrap_or_else(|| Arc::new(CharEstimate))
+}
+
+/// The `<family>.json` tokenizer of the tokenizers directory, if there is one
+#[cfg(feature = "hf-tokenizers")]
+fn hf_tokenizer(family: &str) -> Option<Arc<dyn Tokenizer>> {
+    let path = tokenizers_dir()?.join(format!("{}.json", family));
+    if !path.is_file() {
+        return None;
+    }
+    match HfTokenizer::from_file(&path) {
+        Ok(tokenizer) => Some(Arc::new(tokenizer)),
+        Err(e) => {
+            eprintln!("Warning: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "hf-tokenizers"))]
+fn hf_tokenizer(_family: &str) -> Option<Arc<dyn Tokenizer>> {
+    None
+}
+
+/// Encoding of the families tiktoken covers; Llama 3 extends cl100k, so its counts
+/// are close
+#[cfg(feature = "tiktoken")]
+fn tiktoken(family: &str) -> Option<Arc<dyn Tokenizer>> {
+    let o200k = ["gpt-4o", "gpt-4.1", "gpt-5", "gpt-oss", "o1", "o3", "o4"];
+    let cl100k = ["gpt-4", "gpt-3.5", "llama3", "text-embedding-3"];
+    let (name, bpe) = if o200k.iter().any(|prefix| family.starts_with(prefix)) {
+        ("o200k_base", tiktoken_rs::o200k_base_singleton())
+    } else if cl100k.iter().any(|prefix| family.starts_with(prefix)) {
+        ("cl100k_base", tiktoken_rs::cl100k_base_singleton())
+    } else {
+        return None;
+    };
+    Some(Arc::new(Tiktoken { name, bpe }))
+}
+
+#[cfg(not(feature = "tiktoken"))]
+fn tiktoken(_family: &str) -> Option<Arc<dyn Tokenizer>> {
+    None
+}
+
+/// Directory holding `<family>.json` tokenizer files
+pub fn tokenizers_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("MCP_TOKENIZERS_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?)
+            .join(".config"),
+    };
+    Some(base.join("mcp").join("tokenizers"))
+}
@@ -0,0 +1,125 @@
+//! Tool error module
+//!
+//! This module defines the structured error carried by failed tool calls. Besides the
+//! message shown to the model, an `isError` result holds a payload with a
+//! machine-readable code and whether retrying may succeed, so agents can react to a
+//! missing file or an oversized input without parsing English text. Tools raise these
+//! errors through their usual `Box<dyn Error>` results; any other error is reported
+//! with the `FAILED` code.
+
+use crate::circuit_breaker::CircuitOpen;
+use crate::error::McpError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Machine-readable kind of a tool failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ToolErrorCode {
+    /// The file, record or other target does not exist
+    NotFound,
+    /// The target is outside what the server allows the tool to access
+    AccessDenied,
+    /// An input or output exceeds a size limit
+    TooLarge,
+    /// A backend is temporarily unreachable
+    Unavailable,
+    /// The call did not finish within the time the caller gave it
+    Timeout,
+    /// Any other failure
+    Failed,
+}
+
+impl ToolErrorCode {
+    /// Whether the same call may succeed later without changes
+    pub fn retryable(self) -> bool {
+        matches!(self, Self::Unavailable)
+    }
+}
+
+/// Structured error of a failed tool call, returned as its `structuredContent`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ToolErrorPayload {
+    pub code: ToolErrorCode,
+    pub message: String,
+    pub retryable: bool,
+    /// Code-specific information, such as the exceeded limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ToolErrorPayload {
+    /// Create an error, retryable as its code is
+    pub fn new(code: ToolErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            retryable: code.retryable(),
+            details: None,
+        }
+    }
+
+    /// A missing target
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ToolErrorCode::NotFound, message)
+    }
+
+    /// A target outside the allowed ones
+    pub fn access_denied(message: impl Into<String>) -> Self {
+        Self::new(ToolErrorCode::AccessDenied, message)
+    }
+
+    /// An input or output over `limit` bytes (or characters)
+    pub fn too_large(message: impl Into<String>, limit: u64) -> Self {
+        Self::new(ToolErrorCode::TooLarge, message)
+            .with_details(serde_json::json!({ "limit": limit }))
+    }
+
+    /// A call that did not finish within `timeout`
+    pub fn timeout(message: impl Into<String>, timeout: std::time::Duration) -> Self {
+        Self::new(ToolErrorCode::Timeout, message)
+            .with_details(serde_json::json!({ "timeoutMs": timeout.as_millis() as u64 }))
+    }
+
+    /// Attach code-specific information
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// The payload an error carries, directly or as an [`McpError::Tool`]
+    pub fn of<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a Self> {
+        match error.downcast_ref::<McpError>() {
+            Some(McpError::Tool(payload)) => Some(payload),
+            _ => error.downcast_ref::<Self>(),
+        }
+    }
+
+    /// Classify an error raised by a tool
+    pub fn from_error(error: Box<dyn std::error::Error>) -> Self {
+        if let Some(payload) = Self::of(error.as_ref()) {
+            return payload.clone();
+        }
+        match error.downcast_ref::<CircuitOpen>() {
+            Some(open) => {
+                let payload = Self::new(ToolErrorCode::Unavailable, open.to_string());
+                match open.retry_in {
+                    Some(retry_in) => payload.with_details(
+                        serde_json::json!({ "retryAfterSecs": retry_in.as_secs().max(1) }),
+                    ),
+                    None => payload,
+                }
+            }
+            None => Self::new(ToolErrorCode::Failed, error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ToolErrorPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ToolErrorPayload {}
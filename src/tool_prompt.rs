@@ -0,0 +1,264 @@
+//! Tool prompt module
+//!
+//! This module renders MCP tool definitions into a system-prompt section for models
+//! with weak or no native tool calling support. The model is asked to answer with
+//! the `<|tool_call|>` JSON convention instead of structured `tool_calls`, and
+//! replies in that format are parsed back into tool calls. Calls lacking required
+//! arguments are detected from the tool's schema, so the client can ask for the
+//! missing values instead of sending a call the server will reject.
+
+use crate::mcp::{McpTool, Root};
+use crate::ollama::{OllamaFunctionCall, OllamaToolCall};
+use serde_json::Value;
+use std::fmt::{self, Write};
+
+/// Marker that starts a textual tool call in the model's reply
+pub const TOOL_CALL_MARKER: &str = "<|tool_call|>";
+
+/// Markers recognized when parsing replies; `<tool_call>` is used by Hermes-style templates
+const TOOL_CALL_MARKERS: &[&str] = &[TOOL_CALL_MARKER, "<tool_call>"];
+
+/// Argument descriptions longer than this are shortened
+const MAX_ARGUMENT_DESCRIPTION: usize = 160;
+
+/// Render the tools and allowed roots as a system-prompt section
+pub fn render_tools_prompt(tools: &[McpTool], roots: &[Root]) -> String {
+    let mut prompt = String::from("You have access to the following tools:\n");
+
+    for tool in tools {
+        let _ = write!(prompt, "\n- {}", tool.name);
+        if let Some(description) = &tool.description {
+            let _ = write!(prompt, ": {}", description);
+        }
+        prompt.push('\n');
+        for argument in argument_summaries(tool) {
+            let _ = writeln!(prompt, "    - {}", argument);
+        }
+    }
+
+    if !roots.is_empty() {
+        let paths: Vec<_> = roots.iter().map(Root::path).collect();
+        let _ = write!(
+            prompt,
+            "\nFile tools can only access files within these directories: {}. Always pass absolute paths inside them.\n",
+            paths.join(", ")
+        );
+    }
+
+    let _ = write!(
+        prompt,
+        "\nWhen a tool is required to answer the user's query, respond only with {} followed by a JSON list of calls, for example:\n{}[{{\"name\": \"tool_name\", \"arguments\": {{\"argument\": \"value\"}}}}]\nThe tool results are sent back to you in the next message. Only use the tools listed above.",
+        TOOL_CALL_MARKER, TOOL_CALL_MARKER
+    );
+
+    prompt
+}
+
+/// One line per top-level argument: name, type, whether it is required and its description
+fn argument_summaries(tool: &McpTool) -> Vec<String> {
+    let Some(schema) = &tool.input_schema else {
+        return Vec::new();
+    };
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let kind = property
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("any");
+            let mut summary = format!("{} ({}", name, kind);
+            if required.contains(&name.as_str()) {
+                summary.push_str(", required");
+            }
+            summary.push(')');
+
+            if let Some(values) = property.get("enum").and_then(Value::as_array) {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                let _ = write!(summary, " one of {}", values.join(", "));
+            }
+            if let Some(description) = property.get("description").and_then(Value::as_str) {
+                let _ = write!(summary, ": {}", shorten(description));
+            }
+            summary
+        })
+        .collect()
+}
+
+/// Cut a description at `MAX_ARGUMENT_DESCRIPTION` characters
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(MAX_ARGUMENT_DESCRIPTION) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Extract tool calls written as text in a model reply
+///
+/// Each marker is followed by a JSON list of calls or a single call. A call is
+/// `{"name", "arguments"}` or `{"function": {"name", "arguments"}}`; arguments may
+/// also be given as `parameters` or as a JSON-encoded string. Returns `None` when
+/// the reply contains no parseable call.
+pub fn parse_tool_calls(content: &str) -> Option<Vec<OllamaToolCall>> {
+    let mut calls = Vec::new();
+
+    for marker in TOOL_CALL_MARKERS {
+        for (start, _) in content.match_indices(marker) {
+            let rest = strip_code_fence(&content[start + marker.len()..]);
+            let Some(Ok(value)) = serde_json::Deserializer::from_str(rest)
+                .into_iter::<Value>()
+                .next()
+            else {
+                continue;
+            };
+            match value {
+                Value::Array(items) => calls.extend(items.iter().filter_map(tool_call)),
+                item => calls.extend(tool_call(&item)),
+            }
+        }
+    }
+
+    (!calls.is_empty()).then_some(calls)
+}
+
+/// Turn one JSON call object into a tool call
+fn tool_call(item: &Value) -> Option<OllamaToolCall> {
+    let call = item.get("function").unwrap_or(item);
+    let name = call.get("name")?.as_str()?.to_string();
+    let arguments = match call.get("arguments").or_else(|| call.get("parameters")) {
+        Some(Value::String(encoded)) => serde_json::from_str(encoded).ok()?,
+        Some(arguments) => arguments.clone(),
+        None => Value::Object(Default::default()),
+    };
+
+    Some(OllamaToolCall {
+        function: OllamaFunctionCall { name, arguments },
+    })
+}
+
+/// Skip whitespace and an opening Markdown code fence before the JSON
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim_start();
+    match text.strip_prefix("```") {
+        Some(fenced) => fenced.trim_start_matches("json").trim_start(),
+        None => text,
+    }
+}
+
+/// A required argument missing from a tool call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingArgument {
+    pub name: String,
+    /// JSON Schema type of the argument, when declared
+    pub kind: Option<String>,
+    pub description: Option<String>,
+}
+
+impl fmt::Display for MissingArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})",
+            self.name,
+            self.kind.as_deref().unwrap_or("any")
+        )?;
+        if let Some(description) = &self.description {
+            write!(f, ": {}", shorten(description))?;
+        }
+        Ok(())
+    }
+}
+
+impl MissingArgument {
+    /// Read a value typed by the user as the argument's type
+    ///
+    /// Strings are taken as typed; other types are parsed as JSON, with yes/no also
+    /// accepted for booleans.
+    pub fn parse_value(&self, input: &str) -> Result<Value, String> {
+        let input = input.trim();
+        let value = match self.kind.as_deref() {
+            Some("string") => return Ok(Value::String(input.to_string())),
+            Some("boolean") => match input.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "y" => Value::Bool(true),
+                "false" | "no" | "n" => Value::Bool(false),
+                _ => return Err(format!("'{}' is not true or false", input)),
+            },
+            // Untyped arguments fall back to the text when it is not JSON
+            None => serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.into())),
+            Some(kind) => serde_json::from_str(input)
+                .map_err(|_| format!("'{}' is not a valid {}", input, kind))?,
+        };
+        let matches = match self.kind.as_deref() {
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("number") => value.is_number(),
+            Some("array") => value.is_array(),
+            Some("object") => value.is_object(),
+            _ => true,
+        };
+        if matches {
+            Ok(value)
+        } else {
+            Err(format!(
+                "'{}' is not a valid {}",
+                input,
+                self.kind.as_deref().unwrap_or("value")
+            ))
+        }
+    }
+}
+
+/// The required arguments of `tool` that a call with `arguments` lacks
+///
+/// Arguments set to `null` count as missing.
+pub fn missing_arguments(tool: &McpTool, arguments: &Value) -> Vec<MissingArgument> {
+    let Some(schema) = &tool.input_schema else {
+        return Vec::new();
+    };
+    let Some(required) = schema.get("required").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    required
+        .iter()
+        .filter_map(Value::as_str)
+        .filter(|name| arguments.get(name).is_none_or(Value::is_null))
+        .map(|name| {
+            let property = schema
+                .get("properties")
+                .and_then(|properties| properties.get(name));
+            let field = |key: &str| {
+                property
+                    .and_then(|property| property.get(key))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            };
+            MissingArgument {
+                name: name.to_string(),
+                kind: field("type"),
+                description: field("description"),
+            }
+        })
+        .collect()
+}
+
+/// A message asking the model to call a tool again with the arguments it left out
+pub fn missing_arguments_prompt(tool: &str, missing: &[MissingArgument]) -> String {
+    let mut prompt = format!(
+        "Tool '{}' was not executed: the call is missing required arguments:\n",
+        tool
+    );
+    for argument in missing {
+        let _ = writeln!(prompt, "- {}", argument);
+    }
+    prompt.push_str(
+        "Call the tool again with all required arguments, or ask the user for the values you do not know.",
+    );
+    prompt
+}
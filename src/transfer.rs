@@ -0,0 +1,425 @@
+//! Resumable transfer module
+//!
+//! This module moves large payloads over HTTP without restarting from zero when the
+//! connection drops. Bytes are written to a `<destination>.part` file next to a small
+//! `<destination>.part.json` record of the source; a dropped connection is resumed
+//! with a `Range` request a few times within the same call, and calling the tool again
+//! later picks up the partial file where it stopped. The server tracks every transfer
+//! under a `transfer://` URI whose resource reports how far it got, published with
+//! `notifications/progress` while bytes arrive and `notifications/resources/updated`
+//! when its status changes; status changes also go to the job journal, if any, so a
+//! transfer cut short by a restart is reported as interrupted.
+
+use crate::mcp::McpServerState;
+use crate::tool_error::{ToolErrorCode, ToolErrorPayload};
+use crate::vfs::VirtualFs;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// URI scheme under which transfers are tracked
+pub const TRANSFER_URI_SCHEME: &str = "transfer://";
+
+/// Times a dropped connection is resumed within one call
+const MAX_RESUMES: u32 = 5;
+
+/// Minimum interval between progress notifications of a transfer
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// State of a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    Running,
+    /// Stopped by connection failures; calling the tool again resumes it
+    Interrupted,
+    Completed,
+    Failed,
+}
+
+/// A transfer tracked by the server, the contents of its `transfer://` resource
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Transfer {
+    pub uri: String,
+    pub url: String,
+    /// Final location of the data
+    pub path: String,
+    /// File holding the bytes received so far, until the transfer completes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_path: Option<String>,
+    pub bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    pub status: TransferStatus,
+    pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Get the URI under which the transfer to a destination is tracked
+pub fn transfer_uri(destination: &Path) -> String {
+    format!(
+        "{}{}",
+        TRANSFER_URI_SCHEME,
+        destination.to_string_lossy().trim_start_matches('/')
+    )
+}
+
+/// Outcome of a completed download
+#[derive(Debug, Clone)]
+pub struct Downloaded {
+    pub size: u64,
+    /// Bytes already present from an earlier call
+    pub resumed_from: u64,
+}
+
+/// Source of a partial file, used to check it still matches the remote data
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PartialRecord {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total: Option<u64>,
+}
+
+/// Why one request of a download stopped
+enum Stop {
+    /// The connection failed; resuming may succeed
+    Interrupted(String),
+    Failed(Box<dyn std::error::Error>),
+}
+
+impl<E: Into<Box<dyn std::error::Error>>> From<E> for Stop {
+    fn from(error: E) -> Self {
+        Stop::Failed(error.into())
+    }
+}
+
+/// Path of the partial file of a destination
+pub fn partial_path(destination: &Path) -> PathBuf {
+    let mut path = destination.as_os_str().to_owned();
+    path.push(".part");
+    PathBuf::from(path)
+}
+
+fn record_path(destination: &Path) -> PathBuf {
+    let mut path = destination.as_os_str().to_owned();
+    path.push(".part.json");
+    PathBuf::from(path)
+}
+
+/// Download a URL to a file, resuming an earlier partial download of the same URL
+///
+/// `progress` is called with the bytes received so far and the total size, when the
+/// server reports it. Connection failures are resumed up to a few times; when they
+/// persist, the partial file is kept and the error is `UNAVAILABLE`.
+pub fn download(
+    client: &Client,
+    url: &reqwest::Url,
+    destination: &Path,
+    fs: &dyn VirtualFs,
+    max_bytes: u64,
+    progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<Downloaded, Box<dyn std::error::Error>> {
+    let part = partial_path(destination);
+    let record_path = record_path(destination);
+
+    // A partial file is only reused for the URL it was started from
+    let mut record = fs
+        .read(&record_path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<PartialRecord>(&data).ok())
+        .filter(|record| record.url == url.as_str())
+        .unwrap_or_else(|| PartialRecord {
+            url: url.to_string(),
+            ..Default::default()
+        });
+    // Without a validator, a changed remote file cannot be told apart from the partial one
+    let mut offset = if record.etag.is_some() || record.last_modified.is_some() {
+        fs.metadata(&part).map(|m| m.len).unwrap_or(0)
+    } else {
+        0
+    };
+    let resumed_from = offset;
+
+    let mut resumes = 0;
+    loop {
+        match fetch(
+            client,
+            url,
+            fs,
+            &part,
+            &record_path,
+            &mut record,
+            &mut offset,
+            max_bytes,
+            progress,
+        ) {
+            Ok(()) => break,
+            Err(Stop::Interrupted(reason)) if resumes < MAX_RESUMES => {
+                resumes += 1;
+                std::thread::sleep(Duration::from_secs(u64::from(resumes)));
+                crate::server_log::warn(format!(
+                    "Resuming download of {} at {} bytes after: {}",
+                    url, offset, reason
+                ));
+            }
+            Err(Stop::Interrupted(reason)) => {
+                let total = record
+                    .total
+                    .map(|total| format!(" of {}", total))
+                    .unwrap_or_default();
+                return Err(ToolErrorPayload::new(
+                    ToolErrorCode::Unavailable,
+                    format!(
+                        "Download interrupted after {}{} bytes ({}); call again to resume",
+                        offset, total, reason
+                    ),
+                )
+                .with_details(serde_json::json!({ "bytes": offset, "total": record.total }))
+                .into());
+            }
+            Err(Stop::Failed(e)) => return Err(e),
+        }
+    }
+
+    fs.rename(&part, destination)?;
+    let _ = fs.remove_file(&record_path);
+    Ok(Downloaded {
+        size: offset,
+        resumed_from,
+    })
+}
+
+/// Request the data from `offset` and append it to the partial file
+#[allow(clippy::too_many_arguments)]
+fn fetch(
+    client: &Client,
+    url: &reqwest::Url,
+    fs: &dyn VirtualFs,
+    part: &Path,
+    record_path: &Path,
+    record: &mut PartialRecord,
+    offset: &mut u64,
+    max_bytes: u64,
+    progress: &mut dyn FnMut(u64, Option<u64>),
+) -> Result<(), Stop> {
+    let mut request = client.get(url.clone());
+    if *offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        // Without a matching validator the server sends the whole, changed, data
+        if let Some(validator) = record.etag.as_ref().or(record.last_modified.as_ref()) {
+            request = request.header(IF_RANGE, validator);
+        }
+    }
+    let response = request
+        .send()
+        .map_err(|e| Stop::Interrupted(e.to_string()))?;
+
+    let status = response.status();
+    let total = match status {
+        StatusCode::PARTIAL_CONTENT if *offset > 0 => {
+            let (start, total) = response
+                .headers()
+                .get(CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_content_range)
+                .ok_or("Invalid Content-Range in the response")?;
+            if start != *offset {
+                return Err(
+                    format!("Server resumed at byte {} instead of {}", start, offset).into(),
+                );
+            }
+            total
+        }
+        StatusCode::OK => {
+            // A full response: no range support, or the data changed since
+            *offset = 0;
+            response.content_length()
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE if *offset > 0 && record.total == Some(*offset) => {
+            return Ok(());
+        }
+        status if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+            return Err(Stop::Interrupted(format!("status {}", status)));
+        }
+        status => return Err(format!("Download failed with status {}", status).into()),
+    };
+    if let Some(total) = total
+        && total > max_bytes
+    {
+        return Err(too_large(max_bytes));
+    }
+
+    if *offset == 0 {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        *record = PartialRecord {
+            url: url.to_string(),
+            etag: header(ETAG).filter(|etag| !etag.starts_with("W/")),
+            last_modified: header(LAST_MODIFIED),
+            total,
+        };
+        fs.write(record_path, &serde_json::to_vec(record)?)?;
+    }
+
+    let mut file = fs.create(part, *offset > 0)?;
+    let mut response = response;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = match response.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) => {
+                file.flush()?;
+                return Err(Stop::Interrupted(e.to_string()));
+            }
+        };
+        if *offset + read as u64 > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+        file.write_all(&buffer[..read])?;
+        crate::accounting::read(read as u64);
+        crate::accounting::written(read as u64);
+        *offset += read as u64;
+        progress(*offset, total);
+    }
+    file.sync()?;
+
+    match total {
+        Some(total) if *offset < total => Err(Stop::Interrupted(format!(
+            "connection closed at {} of {} bytes",
+            offset, total
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Parse `bytes <start>-<end>/<total>` into the start and, when known, the total
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+fn too_large(max_bytes: u64) -> Stop {
+    Stop::Failed(
+        ToolErrorPayload::too_large(
+            format!("Download exceeds the {} byte limit", max_bytes),
+            max_bytes,
+        )
+        .into(),
+    )
+}
+
+/// Records a transfer on the server and publishes its progress
+///
+/// Meant for tools running on the blocking thread pool.
+#[derive(Debug)]
+pub struct TransferTracker {
+    state: McpServerState,
+    transfer: Transfer,
+    reported: Instant,
+}
+
+impl TransferTracker {
+    /// Start tracking the transfer of a URL to a destination
+    pub fn start(state: &McpServerState, url: &str, destination: &Path) -> Self {
+        let mut tracker = Self {
+            state: state.clone(),
+            transfer: Transfer {
+                uri: transfer_uri(destination),
+                url: url.to_string(),
+                path: destination.to_string_lossy().into_owned(),
+                partial_path: Some(partial_path(destination).to_string_lossy().into_owned()),
+                bytes: 0,
+                total: None,
+                status: TransferStatus::Running,
+                updated_at: Utc::now(),
+                error: None,
+            },
+            reported: Instant::now(),
+        };
+        tracker.publish(true);
+        tracker
+    }
+
+    /// Record the bytes received so far, notifying at most once a second
+    pub fn progress(&mut self, bytes: u64, total: Option<u64>) {
+        self.transfer.bytes = bytes;
+        self.transfer.total = total;
+        if self.reported.elapsed() >= PROGRESS_INTERVAL {
+            self.reported = Instant::now();
+            self.publish(false);
+        }
+    }
+
+    /// Record the outcome of the transfer
+    pub fn finish(mut self, result: &Result<Downloaded, Box<dyn std::error::Error>>) {
+        match result {
+            Ok(downloaded) => {
+                self.transfer.bytes = downloaded.size;
+                self.transfer.total = Some(downloaded.size);
+                self.transfer.partial_path = None;
+                self.transfer.status = TransferStatus::Completed;
+            }
+            Err(e) => {
+                let retryable =
+                    ToolErrorPayload::of(e.as_ref()).is_some_and(|payload| payload.retryable);
+                self.transfer.status = if retryable {
+                    TransferStatus::Interrupted
+                } else {
+                    TransferStatus::Failed
+                };
+                self.transfer.error = Some(e.to_string());
+            }
+        }
+        self.publish(true);
+    }
+
+    fn publish(&mut self, status_changed: bool) {
+        self.transfer.updated_at = Utc::now();
+        if status_changed {
+            crate::job_journal::record(
+                &self.state,
+                &crate::job_journal::JobRecord::Transfer(self.transfer.clone()),
+            );
+        }
+        let uri = self.transfer.uri.clone();
+        self.state
+            .transfers
+            .blocking_write()
+            .insert(uri.clone(), self.transfer.clone());
+
+        if status_changed {
+            self.state.notify(
+                "notifications/resources/updated",
+                Some(serde_json::json!({ "uri": uri })),
+            );
+        } else {
+            let mut params = serde_json::json!({
+                "progressToken": uri,
+                "progress": self.transfer.bytes,
+            });
+            if let Some(total) = self.transfer.total {
+                params["total"] = total.into();
+            }
+            self.state.notify("notifications/progress", Some(params));
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Translation tool module
+//!
+//! This module provides the `translate` tool, backed by a pluggable [`Translator`].
+//! Translators for a local Ollama model and for LibreTranslate-compatible HTTP
+//! services are included; applications embedding the server can supply their own.
+//! Results carry the detected source language alongside the translation.
+
+use crate::config::{TranslateConfig, TranslatorKind};
+use crate::ollama::Ollama;
+use crate::tool_error::ToolErrorPayload;
+use reqwest::blocking::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Upper bound on the size of the text translated in one call
+const MAX_TEXT_BYTES: usize = 50 * 1024;
+
+/// Timeout for a single translation request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The output of a translator
+#[derive(Debug, Clone, Serialize)]
+pub struct Translation {
+    pub text: String,
+    /// Language of the input, when the translator detected it
+    pub detected_language: Option<String>,
+}
+
+/// A translation backend
+pub trait Translator: Send + Sync + fmt::Debug {
+    /// Human readable translator name used in error messages
+    fn name(&self) -> &str;
+
+    /// Translate text into the target language
+    ///
+    /// Languages are ISO 639-1 codes or names; `source` is detected when omitted.
+    fn translate(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<Translation, Box<dyn std::error::Error>>;
+}
+
+/// Create a translator from its configuration
+pub fn build_translator(config: &TranslateConfig) -> Arc<dyn Translator> {
+    match config.engine {
+        TranslatorKind::Ollama => Arc::new(OllamaTranslator {
+            base_url: config
+                .url
+                .clone()
+                .unwrap_or_else(|| "http://localhost:11434".to_string()),
+            model: config
+                .model
+                .clone()
+                .unwrap_or_else(|| "llama3.2".to_string()),
+        }),
+        TranslatorKind::LibreTranslate => {
+            let mut translator = LibreTranslateTranslator::new(
+                config.url.as_deref().unwrap_or("http://localhost:5000"),
+            );
+            translator.api_key = config.api_key.clone();
+            Arc::new(translator)
+        }
+    }
+}
+
+/// Translator prompting a local Ollama model
+#[derive(Debug)]
+pub struct OllamaTranslator {
+    pub base_url: String,
+    pub model: String,
+}
+
+/// The JSON reply requested from the model
+#[derive(Deserialize)]
+struct ModelTranslation {
+    translation: String,
+    detected_language: Option<String>,
+}
+
+impl Translator for OllamaTranslator {
+    fn name(&self) -> &str {
+        "Ollama translator"
+    }
+
+    fn translate(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<Translation, Box<dyn std::error::Error>> {
+        let from = match source {
+            Some(source) => format!("from language '{}' ", source),
+            None => String::new(),
+        };
+        let system = format!(
+            "Translate the text the user sends {}into language '{}'. Reply only with a JSON object of the form {{\"detected_language\": \"<ISO 639-1 code of the input language>\", \"translation\": \"<translated text>\"}}. Preserve formatting and line breaks and do not add explanations.",
+            from, target
+        );
+
+        let reply = Ollama::default(self.model.clone())
+            .with_base_url(self.base_url.clone())
+            .with_timeout(REQUEST_TIMEOUT)
+            .chat_with_system(system, text)?;
+
+        // Models sometimes wrap the JSON in a code fence or ignore the format
+        let json = reply
+            .find('{')
+            .zip(reply.rfind('}'))
+            .map(|(start, end)| &reply[start..=end]);
+        match json.and_then(|json| serde_json::from_str::<ModelTranslation>(json).ok()) {
+            Some(parsed) => Ok(Translation {
+                text: parsed.translation,
+                detected_language: parsed
+                    .detected_language
+                    .or_else(|| source.map(str::to_string)),
+            }),
+            None => Ok(Translation {
+                text: reply.trim().to_string(),
+                detected_language: source.map(str::to_string),
+            }),
+        }
+    }
+}
+
+/// Translator for LibreTranslate-compatible HTTP services (`POST /translate`)
+#[derive(Debug)]
+pub struct LibreTranslateTranslator {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    client: OnceLock<Client>,
+}
+
+impl LibreTranslateTranslator {
+    /// Create a translator for the service at `base_url` (e.g. http://localhost:5000)
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: None,
+            client: OnceLock::new(),
+        }
+    }
+}
+
+impl Translator for LibreTranslateTranslator {
+    fn name(&self) -> &str {
+        "LibreTranslate"
+    }
+
+    fn translate(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> Result<Translation, Box<dyn std::error::Error>> {
+        let mut body = serde_json::json!({
+            "q": text,
+            "source": source.unwrap_or("auto"),
+            "target": target,
+            "format": "text",
+        });
+        if let Some(api_key) = &self.api_key {
+            body["api_key"] = serde_json::Value::String(api_key.clone());
+        }
+
+        // The blocking client must not be created on the async runtime
+        let client = self.client.get_or_init(|| {
+            Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default()
+        });
+        let response = client
+            .post(format!("{}/translate", self.base_url))
+            .json(&body)
+            .send()?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text()?;
+            return Err(format!("Translation service error {}: {}", status, error_text).into());
+        }
+
+        let body: serde_json::Value = response.json()?;
+        let text = body["translatedText"]
+            .as_str()
+            .ok_or("Translation response did not contain translatedText")?;
+        Ok(Translation {
+            text: text.to_string(),
+            detected_language: body["detectedLanguage"]["language"]
+                .as_str()
+                .or(source)
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Translate tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TranslateRequest {
+    /// The text to translate
+    pub text: String,
+    /// The target language as an ISO 639-1 code, e.g. "en" or "de"
+    pub target: String,
+    /// The source language code; detected automatically when omitted
+    pub source: Option<String>,
+}
+
+/// Translate tool response
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TranslateResponse {
+    pub translation: String,
+    /// Language of the input, when the translator detected it
+    pub detected_language: Option<String>,
+    pub target_language: String,
+}
+
+impl TranslateResponse {
+    /// The translation with the languages it went between, for clients reading text
+    pub fn to_text(&self) -> String {
+        match &self.detected_language {
+            Some(source) => format!(
+                "Translated from {} to {}:\n{}",
+                source, self.target_language, self.translation
+            ),
+            None => format!(
+                "Translated to {}:\n{}",
+                self.target_language, self.translation
+            ),
+        }
+    }
+}
+
+/// Execute the translate tool
+pub fn execute_translate(
+    translator: &dyn Translator,
+    request: TranslateRequest,
+) -> Result<TranslateResponse, Box<dyn std::error::Error>> {
+    if request.text.trim().is_empty() {
+        return Err("Text must not be empty".into());
+    }
+    if request.text.len() > MAX_TEXT_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("Text exceeds the {} byte limit", MAX_TEXT_BYTES),
+            MAX_TEXT_BYTES as u64,
+        )
+        .into());
+    }
+    let target = request.target.trim();
+    if target.is_empty() {
+        return Err("Target language must not be empty".into());
+    }
+
+    let translation = translator.translate(&request.text, request.source.as_deref(), target)?;
+    Ok(TranslateResponse {
+        translation: translation.text,
+        detected_language: translation.detected_language,
+        target_language: target.to_string(),
+    })
+}
+
+/// Get the tool definition for the translate tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "translate".to_string(),
+        version: None,
+        description: Some("Translate text into a target language and detect the language of the input. Returns the translation, detected_language and target_language as structured content.".to_string()),
+        input_schema: Some(crate::schema::input_schema::<TranslateRequest>()),
+        output_schema: Some(crate::schema::output_schema::<TranslateResponse>()),
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Echo;
+
+    impl Translator for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn translate(
+            &self,
+            text: &str,
+            source: Option<&str>,
+            _target: &str,
+        ) -> Result<Translation, Box<dyn std::error::Error>> {
+            Ok(Translation {
+                text: text.to_uppercase(),
+                detected_language: source.is_none().then(|| "de".to_string()),
+            })
+        }
+    }
+
+    fn translate(source: Option<&str>) -> TranslateResponse {
+        let request = TranslateRequest {
+            text: "hallo".to_string(),
+            target: " en ".to_string(),
+            source: source.map(str::to_string),
+        };
+        execute_translate(&Echo, request).unwrap()
+    }
+
+    #[test]
+    fn structured_results_match_the_output_schema() {
+        let schema = get_tool_definition().output_schema.unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        for response in [translate(None), translate(Some("de"))] {
+            let value = serde_json::to_value(&response).unwrap();
+            assert!(validator.is_valid(&value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn text_names_the_detected_language() {
+        assert_eq!(
+            translate(None).to_text(),
+            "Translated from de to en:\nHALLO"
+        );
+        assert_eq!(translate(Some("de")).to_text(), "Translated to en:\nHALLO");
+    }
+}
@@ -0,0 +1,559 @@
+//! TUI module
+//!
+//! This module is the full-screen terminal interface of mcp-client (`--tui`), built on
+//! ratatui. The conversation, the answer the model is generating, the calls waiting
+//! for the user's approval and a sidebar with the server and its tools each get a
+//! pane above the input line. The interface runs on its own thread, which owns the
+//! terminal: the client sends it [`Event`]s as the conversation goes on, and reads the
+//! user's messages and decisions back through a [`Tui`], blocking as it would on stdin.
+
+use crate::exit::FailureKind;
+use ratatui::crossterm::event::{self as term, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often the interface checks for keys and events
+const TICK: Duration = Duration::from_millis(50);
+
+/// Lines scrolled by Page Up and Page Down
+const PAGE: usize = 10;
+
+/// Width of the sidebar
+const SIDEBAR_WIDTH: u16 = 34;
+
+/// Height of the pane showing the answer being generated
+const OUTPUT_HEIGHT: u16 = 8;
+
+/// Who a line of the conversation is from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Assistant,
+    Tool,
+    Notice,
+    Warning,
+}
+
+/// What the client shows in the interface
+#[derive(Debug)]
+pub enum Event {
+    /// A line of the conversation
+    Line(Role, String),
+    /// Text of the answer the model is generating
+    Chunk(String),
+    /// The model's whole answer, replacing the text streamed so far
+    Answer(String),
+    /// The model asked for a tool call
+    ToolCall { name: String, arguments: String },
+    /// A tool call was sent to the server
+    ToolStarted(String),
+    /// A tool call ended
+    ToolResult {
+        name: String,
+        text: String,
+        failed: bool,
+    },
+    /// How far the running tool call got
+    Progress(String),
+    /// The server's tools changed
+    Tools(Vec<String>),
+    /// Something waiting for the user's approval, answered through `reply`
+    Approval {
+        title: String,
+        details: Vec<String>,
+        reply: Sender<bool>,
+    },
+    /// The client waits for the user's next message, or for the answer to a question
+    Ready(Option<String>),
+    /// End the interface and give the terminal back
+    Close,
+}
+
+/// What the sidebar shows about the session
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    /// Name and version of the server
+    pub server: String,
+    pub protocol: String,
+    pub endpoint: String,
+    pub model: String,
+    /// Names of the server's tools
+    pub tools: Vec<String>,
+}
+
+/// Handle of the running interface; clones share it
+#[derive(Clone)]
+pub struct Tui {
+    events: Sender<Event>,
+    input: Arc<Mutex<Receiver<Option<String>>>>,
+    ui: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl Tui {
+    /// Take over the terminal and start the interface
+    pub fn start(status: Status) -> std::io::Result<Self> {
+        let (events, event_receiver) = mpsc::channel();
+        let (input_sender, input) = mpsc::channel();
+        let (started_sender, started) = mpsc::channel();
+        let ui = std::thread::spawn(move || {
+            let terminal = match ratatui::try_init() {
+                Ok(terminal) => terminal,
+                Err(e) => {
+                    ratatui::restore();
+                    let _ = started_sender.send(Err(e));
+                    return;
+                }
+            };
+            let _ = started_sender.send(Ok(()));
+            App::new(status).run(terminal, event_receiver, input_sender);
+            ratatui::restore();
+        });
+        started
+            .recv()
+            .unwrap_or_else(|_| Err(std::io::Error::other("The interface failed to start")))?;
+        Ok(Self {
+            events,
+            input: Arc::new(Mutex::new(input)),
+            ui: Arc::new(Mutex::new(Some(ui))),
+        })
+    }
+
+    /// Show an event; events sent after the interface ended are dropped
+    pub fn send(&self, event: Event) {
+        let _ = self.events.send(event);
+    }
+
+    /// Add a line to the conversation
+    pub fn line(&self, role: Role, text: impl Into<String>) {
+        self.send(Event::Line(role, text.into()));
+    }
+
+    /// Wait for the user's next message, or for the answer to `question`
+    ///
+    /// Returns None once the user ended the conversation with Ctrl-D.
+    pub fn read_line(&self, question: Option<&str>) -> Option<String> {
+        self.send(Event::Ready(question.map(str::to_string)));
+        let input = self.input.lock().unwrap_or_else(|e| e.into_inner());
+        input.recv().ok().flatten()
+    }
+
+    /// Ask the user to approve something, defaulting to no if the interface ended
+    pub fn confirm(&self, title: impl Into<String>, details: Vec<String>) -> bool {
+        let (reply, answer) = mpsc::channel();
+        self.send(Event::Approval {
+            title: title.into(),
+            details,
+            reply,
+        });
+        answer.recv().unwrap_or(false)
+    }
+
+    /// End the interface and wait until the terminal is restored
+    pub fn close(&self) {
+        self.send(Event::Close);
+        let ui = self.ui.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(ui) = ui {
+            let _ = ui.join();
+        }
+    }
+}
+
+/// State of a tool in the sidebar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToolState {
+    Idle,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug)]
+struct ToolStatus {
+    name: String,
+    calls: u32,
+    state: ToolState,
+}
+
+struct Approval {
+    title: String,
+    details: Vec<String>,
+    reply: Sender<bool>,
+}
+
+/// State of the interface, owned by its thread
+struct App {
+    status: Status,
+    tools: Vec<ToolStatus>,
+    conversation: Vec<(Role, String)>,
+    /// Text of the answer being generated
+    output: String,
+    progress: Option<String>,
+    approvals: VecDeque<Approval>,
+    input: String,
+    /// Whether the client waits for input, and the question it asked
+    ready: bool,
+    question: Option<String>,
+    /// Lines the conversation is scrolled back by
+    scroll: usize,
+}
+
+impl App {
+    fn new(mut status: Status) -> Self {
+        let tools = std::mem::take(&mut status.tools);
+        let mut app = Self {
+            status,
+            tools: Vec::new(),
+            conversation: Vec::new(),
+            output: String::new(),
+            progress: None,
+            approvals: VecDeque::new(),
+            input: String::new(),
+            ready: false,
+            question: None,
+            scroll: 0,
+        };
+        app.set_tools(tools);
+        app
+    }
+
+    fn run(
+        mut self,
+        mut terminal: DefaultTerminal,
+        events: Receiver<Event>,
+        input: Sender<Option<String>>,
+    ) {
+        loop {
+            loop {
+                match events.try_recv() {
+                    Ok(Event::Close) | Err(TryRecvError::Disconnected) => return,
+                    Ok(event) => self.apply(event),
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+            if terminal.draw(|frame| self.draw(frame)).is_err() {
+                return;
+            }
+            match term::poll(TICK) {
+                Ok(true) => {
+                    if let Ok(term::Event::Key(key)) = term::read()
+                        && key.kind == KeyEventKind::Press
+                    {
+                        self.key(key, &input);
+                    }
+                }
+                Ok(false) => {}
+                Err(_) => return,
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::Line(role, text) => self.conversation.push((role, text)),
+            Event::Chunk(text) => self.output.push_str(&text),
+            Event::Answer(text) => {
+                self.output.clear();
+                if !text.trim().is_empty() {
+                    self.conversation.push((Role::Assistant, text));
+                }
+            }
+            Event::ToolCall { name, arguments } => {
+                self.conversation
+                    .push((Role::Tool, format!("{} {}", name, arguments)));
+            }
+            Event::ToolStarted(name) => {
+                self.progress = None;
+                self.set_tool_state(&name, ToolState::Running);
+            }
+            Event::ToolResult { name, text, failed } => {
+                self.progress = None;
+                let state = if failed {
+                    ToolState::Failed
+                } else {
+                    ToolState::Succeeded
+                };
+                self.set_tool_state(&name, state);
+                let role = if failed { Role::Warning } else { Role::Tool };
+                self.conversation.push((role, text));
+            }
+            Event::Progress(text) => self.progress = Some(text),
+            Event::Tools(names) => self.set_tools(names),
+            Event::Approval {
+                title,
+                details,
+                reply,
+            } => self.approvals.push_back(Approval {
+                title,
+                details,
+                reply,
+            }),
+            Event::Ready(question) => {
+                if let Some(question) = &question {
+                    self.conversation.push((Role::Notice, question.clone()));
+                }
+                self.ready = true;
+                self.question = question;
+            }
+            Event::Close => {}
+        }
+    }
+
+    /// Replace the tools listed, keeping the calls counted for those still there
+    fn set_tools(&mut self, mut names: Vec<String>) {
+        names.sort();
+        let previous = std::mem::take(&mut self.tools);
+        self.tools = names
+            .into_iter()
+            .map(|name| {
+                let calls = previous
+                    .iter()
+                    .find(|tool| tool.name == name)
+                    .map_or(0, |tool| tool.calls);
+                ToolStatus {
+                    name,
+                    calls,
+                    state: ToolState::Idle,
+                }
+            })
+            .collect();
+    }
+
+    fn set_tool_state(&mut self, name: &str, state: ToolState) {
+        if let Some(tool) = self.tools.iter_mut().find(|tool| tool.name == name) {
+            if state == ToolState::Running {
+                tool.calls += 1;
+            }
+            tool.state = state;
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent, input: &Sender<Option<String>>) {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        if control && key.code == KeyCode::Char('c') {
+            ratatui::restore();
+            crate::exit::fail(FailureKind::Aborted, "Interrupted");
+        }
+        if let Some(approval) = self.approvals.front() {
+            let approved = match key.code {
+                KeyCode::Char('y' | 'Y') => true,
+                KeyCode::Char('n' | 'N') | KeyCode::Esc => false,
+                _ => return,
+            };
+            let _ = approval.reply.send(approved);
+            let line = format!("{} {}", approval.title, if approved { "yes" } else { "no" });
+            self.conversation.push((Role::Notice, line));
+            self.approvals.pop_front();
+            return;
+        }
+        match key.code {
+            KeyCode::Char('d') if control && self.input.is_empty() && self.ready => {
+                self.ready = false;
+                let _ = input.send(None);
+            }
+            KeyCode::Char(c) if !control => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter if self.ready => {
+                // Empty answers to questions are meaningful, empty messages are not
+                if self.question.is_none() && self.input.trim().is_empty() {
+                    return;
+                }
+                let text = std::mem::take(&mut self.input);
+                self.conversation.push((Role::User, text.clone()));
+                self.ready = false;
+                self.question = None;
+                self.scroll = 0;
+                let _ = input.send(Some(text));
+            }
+            KeyCode::PageUp => self.scroll += PAGE,
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(PAGE),
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, sidebar] =
+            Layout::horizontal([Constraint::Min(20), Constraint::Length(SIDEBAR_WIDTH)])
+                .areas(frame.area());
+        let approval_height = self.approvals.front().map_or(0, |approval| {
+            (approval.details.len() as u16 + 3).min(main.height / 3)
+        });
+        let [conversation, output, approvals, input] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(OUTPUT_HEIGHT),
+            Constraint::Length(approval_height),
+            Constraint::Length(3),
+        ])
+        .areas(main);
+        self.draw_conversation(frame, conversation);
+        self.draw_output(frame, output);
+        if approval_height > 0 {
+            self.draw_approval(frame, approvals);
+        }
+        self.draw_input(frame, input);
+        self.draw_sidebar(frame, sidebar);
+    }
+
+    fn draw_conversation(&mut self, frame: &mut Frame, area: Rect) {
+        let mut lines = Vec::new();
+        for (role, text) in &self.conversation {
+            let (label, style) = match role {
+                Role::User => ("you", Style::new().fg(Color::Cyan).bold()),
+                Role::Assistant => ("assistant", Style::new().fg(Color::Green).bold()),
+                Role::Tool => ("tool", Style::new().fg(Color::Yellow)),
+                Role::Notice => ("", Style::new().fg(Color::DarkGray)),
+                Role::Warning => ("", Style::new().fg(Color::Red)),
+            };
+            for (index, text) in text.lines().enumerate() {
+                let mut spans = Vec::new();
+                if index == 0 && !label.is_empty() {
+                    spans.push(Span::styled(format!("{}: ", label), style));
+                }
+                match role {
+                    Role::Notice | Role::Warning => spans.push(Span::styled(text, style)),
+                    _ => spans.push(Span::raw(text)),
+                }
+                lines.push(Line::from(spans));
+            }
+        }
+        let paragraph = Paragraph::new(Text::from(lines)).wrap(Wrap { trim: false });
+        // Keep the end of the conversation in view unless the user scrolled back
+        let height = area.height.saturating_sub(2) as usize;
+        let bottom = paragraph
+            .line_count(area.width.saturating_sub(2))
+            .saturating_sub(height);
+        self.scroll = self.scroll.min(bottom);
+        let title = if self.scroll > 0 {
+            " Conversation (scrolled back, PgDn) "
+        } else {
+            " Conversation "
+        };
+        let offset = u16::try_from(bottom - self.scroll).unwrap_or(u16::MAX);
+        frame.render_widget(
+            paragraph
+                .block(Block::bordered().title(title))
+                .scroll((offset, 0)),
+            area,
+        );
+    }
+
+    fn draw_output(&self, frame: &mut Frame, area: Rect) {
+        let text = if self.output.is_empty() && !self.ready && self.approvals.is_empty() {
+            Text::from("…".dark_gray())
+        } else {
+            Text::from(self.output.as_str())
+        };
+        let paragraph = Paragraph::new(text).wrap(Wrap { trim: false });
+        // Follow the end of the answer as it grows
+        let height = area.height.saturating_sub(2) as usize;
+        let offset = paragraph
+            .line_count(area.width.saturating_sub(2))
+            .saturating_sub(height);
+        frame.render_widget(
+            paragraph
+                .block(Block::bordered().title(" Output "))
+                .scroll((u16::try_from(offset).unwrap_or(u16::MAX), 0)),
+            area,
+        );
+    }
+
+    fn draw_approval(&self, frame: &mut Frame, area: Rect) {
+        let Some(approval) = self.approvals.front() else {
+            return;
+        };
+        let mut lines = vec![Line::from(approval.title.as_str().bold())];
+        lines.extend(approval.details.iter().map(|line| Line::raw(line.as_str())));
+        let title = match self.approvals.len() {
+            1 => " Approval needed: y to allow, n to deny ".to_string(),
+            pending => format!(
+                " Approval needed (1 of {}): y to allow, n to deny ",
+                pending
+            ),
+        };
+        frame.render_widget(
+            Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(Style::new().fg(Color::Yellow)),
+            ),
+            area,
+        );
+    }
+
+    fn draw_input(&self, frame: &mut Frame, area: Rect) {
+        let title = match (&self.question, self.ready) {
+            (Some(_), _) => " Answer (Enter to send) ",
+            (None, true) => " Message (Enter to send, Ctrl-D to quit) ",
+            (None, false) => " Working… (Ctrl-C to abort) ",
+        };
+        // Show the end of input longer than the line
+        let width = area.width.saturating_sub(2) as usize;
+        let length = self.input.chars().count();
+        let shown: String = self
+            .input
+            .chars()
+            .skip(length.saturating_sub(width.saturating_sub(1)))
+            .collect();
+        let cursor = area.x + 1 + shown.chars().count() as u16;
+        frame.render_widget(
+            Paragraph::new(shown).block(Block::bordered().title(title)),
+            area,
+        );
+        if self.ready && self.approvals.is_empty() {
+            frame.set_cursor_position(Position::new(cursor, area.y + 1));
+        }
+    }
+
+    fn draw_sidebar(&self, frame: &mut Frame, area: Rect) {
+        let [server, tools] =
+            Layout::vertical([Constraint::Length(7), Constraint::Min(3)]).areas(area);
+        let mut lines = vec![
+            Line::from(self.status.server.as_str().bold()),
+            Line::raw(format!("protocol {}", self.status.protocol)),
+            Line::raw(self.status.endpoint.as_str()),
+            Line::raw(format!("model {}", self.status.model)),
+        ];
+        if let Some(progress) = &self.progress {
+            lines.push(Line::from(progress.as_str().yellow()));
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Server ")),
+            server,
+        );
+
+        let items: Vec<ListItem> = self
+            .tools
+            .iter()
+            .map(|tool| {
+                let (marker, style) = match tool.state {
+                    ToolState::Idle => ("  ", Style::new()),
+                    ToolState::Running => ("▶ ", Style::new().fg(Color::Yellow)),
+                    ToolState::Succeeded => ("✓ ", Style::new().fg(Color::Green)),
+                    ToolState::Failed => ("✗ ", Style::new().fg(Color::Red)),
+                };
+                let mut spans = vec![Span::styled(marker, style), Span::raw(tool.name.as_str())];
+                if tool.calls > 0 {
+                    spans.push(Span::styled(
+                        format!(" ×{}", tool.calls),
+                        Style::new().add_modifier(Modifier::DIM),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let title = format!(" Tools ({}) ", self.tools.len());
+        frame.render_widget(
+            List::new(items).block(Block::bordered().title(title)),
+            tools,
+        );
+    }
+}
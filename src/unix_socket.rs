@@ -0,0 +1,277 @@
+//! Unix socket transport module
+//!
+//! This module serves MCP on a unix domain socket for local setups that should not
+//! open TCP ports. Messages are newline-delimited JSON-RPC, as over stdio, and each
+//! connection is one session. The socket is created accessible to its owner only, so
+//! other local users cannot call the server's tools: it is bound inside a fresh
+//! directory only the owner can enter, then moved into place.
+
+use crate::error::McpError;
+use crate::jsonrpc_body::InvalidMessage;
+use crate::mcp::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, McpServerState};
+use crate::{notification_queue, session};
+use serde_json::Value;
+use std::fs;
+use std::io::BufReader;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::UnixListener;
+use tokio::sync::mpsc;
+
+/// Permissions of the socket file: read and write for the owner only
+const SOCKET_MODE: u32 = 0o600;
+
+/// Permissions of the directory the socket is bound in
+const BIND_DIR_MODE: u32 = 0o700;
+
+/// Lines waiting to be written to a connection; when full, responses wait and
+/// notifications back up into the connection's notification queue
+const WRITE_CAPACITY: usize = 64;
+
+/// Accept connections on a unix socket until the process exits
+///
+/// A stale socket left by a previous server is replaced; any other existing file at
+/// `path` is an error.
+pub async fn serve(
+    state: McpServerState,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    remove_stale_socket(path)?;
+    let listener = bind_private(path)
+        .map_err(|e| format!("Failed to bind unix socket '{}': {}", path.display(), e))?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle_connection(state.clone(), stream));
+    }
+}
+
+/// Bind a socket at `path` that only its owner can connect to
+///
+/// Binding creates the socket with the process umask, so it is bound in a private
+/// directory next to `path`, restricted, and only then renamed to `path`.
+fn bind_private(path: &Path) -> std::io::Result<UnixListener> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let dir = parent.join(format!(".{}.{}.bind", name, std::process::id()));
+    fs::DirBuilder::new().mode(BIND_DIR_MODE).create(&dir)?;
+
+    let bound = dir.join("socket");
+    let result = UnixListener::bind(&bound).and_then(|listener| {
+        fs::set_permissions(&bound, fs::Permissions::from_mode(SOCKET_MODE))?;
+        fs::rename(&bound, path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&bound);
+    let _ = fs::remove_dir(&dir);
+    result
+}
+
+/// Remove a socket file no server is listening on
+fn remove_stale_socket(path: &Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(format!("'{}' exists and is not a socket", path.display()).into());
+    }
+    if UnixStream::connect(path).is_ok() {
+        return Err(format!("Another server is listening on '{}'", path.display()).into());
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Serve one connection: read messages, write responses and server notifications
+///
+/// Requests are handled concurrently, except initialize, which must open the
+/// session before the next message is read. The session ends with the connection.
+async fn handle_connection(state: McpServerState, stream: tokio::net::UnixStream) {
+    let (reader, mut writer) = stream.into_split();
+    let (sender, mut receiver) = mpsc::channel::<String>(WRITE_CAPACITY);
+    tokio::spawn(async move {
+        while let Some(mut line) = receiver.recv().await {
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let session_id = Arc::new(Mutex::new(None));
+    let queue = notification_queue::open(&state, {
+        let session_id = Arc::clone(&session_id);
+        move || std::future::ready(session_id.lock().unwrap_or_else(|e| e.into_inner()).clone())
+    });
+    let notifications = Arc::clone(&queue);
+    let notification_sender = sender.clone();
+    tokio::spawn(async move {
+        while let Some(notification) = notifications.recv().await {
+            let line = serde_json::to_string(&notification).unwrap_or_default();
+            if notification_sender.send(line).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = match crate::jsonrpc_body::parse(line.as_bytes()) {
+            Ok(message) => message,
+            Err(InvalidMessage(response)) => {
+                let _ = sender
+                    .send(serde_json::to_string(&response).unwrap_or_default())
+                    .await;
+                continue;
+            }
+        };
+
+        let initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+        let task = handle_line(
+            state.clone(),
+            Arc::clone(&session_id),
+            sender.clone(),
+            message,
+        );
+        if initialize {
+            task.await;
+        } else {
+            tokio::spawn(task);
+        }
+    }
+
+    queue.close();
+    let id = session_id.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(id) = id {
+        session::close(&state, &id).await;
+    }
+}
+
+/// Handle one message and send its response, if any
+async fn handle_line(
+    state: McpServerState,
+    session_id: Arc<Mutex<Option<String>>>,
+    sender: mpsc::Sender<String>,
+    message: Value,
+) {
+    let id = message.get("id").cloned();
+    let current = session_id.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    // The socket is only reachable by the server's user, so no API key is required
+    let outcome = crate::mcp::handle_message(&state, current.as_deref(), None, message).await;
+    if let Some(new_session) = outcome.new_session {
+        *session_id.lock().unwrap_or_else(|e| e.into_inner()) = Some(new_session);
+    }
+
+    // Malformed requests are already answered with a JSON-RPC error
+    let reply = if outcome.status.is_success()
+        || serde_json::from_str::<JsonRpcResponse>(&outcome.body).is_ok()
+    {
+        outcome.body
+    } else if let Some(id) = id {
+        error_line(&id, -32600, &outcome.body)
+    } else {
+        return;
+    };
+    if !reply.is_empty() {
+        let _ = sender.send(reply).await;
+    }
+}
+
+/// A JSON-RPC error response as a line
+fn error_line(id: &Value, code: i32, message: &str) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+    .to_string()
+}
+
+/// A connection to an MCP server listening on a unix socket
+#[derive(Debug)]
+pub struct UnixConnection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    /// The socket path, for messages
+    path: String,
+    /// Answer the requests the server sends while a request waits for its response
+    requests: crate::server_request::RequestHandlers,
+}
+
+impl UnixConnection {
+    /// Connect to the server's socket
+    pub fn connect(path: impl AsRef<Path>) -> Result<Self, McpError> {
+        let path = path.as_ref();
+        let stream = UnixStream::connect(path).map_err(|e| {
+            McpError::connection(match e.kind() {
+                std::io::ErrorKind::PermissionDenied => format!(
+                    "Permission denied connecting to MCP socket '{}'; it is only accessible to the user running the server",
+                    path.display()
+                ),
+                _ => format!("Failed to connect to MCP socket '{}': {}", path.display(), e),
+            })
+        })?;
+
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            path: path.display().to_string(),
+            requests: Default::default(),
+        })
+    }
+
+    /// The socket path
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Answer the requests the server sends with `requests`
+    pub fn set_request_handlers(&mut self, requests: crate::server_request::RequestHandlers) {
+        self.requests = requests;
+    }
+
+    /// Send a request and wait for the response with the same id
+    ///
+    /// Requests from the server are answered by the connection's request handlers.
+    pub fn request(&mut self, request: &JsonRpcRequest) -> Result<JsonRpcResponse, McpError> {
+        crate::stdio::exchange(
+            &mut self.reader,
+            &mut self.writer,
+            request,
+            &self.path,
+            &self.requests,
+        )
+    }
+
+    /// Send a notification
+    pub fn notify(&mut self, notification: &JsonRpcNotification) -> Result<(), McpError> {
+        crate::stdio::write_message(&mut self.writer, notification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bind_private_restricts_socket_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mcp.sock");
+
+        let _listener = bind_private(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, SOCKET_MODE);
+        let entries: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        assert!(UnixStream::connect(&path).is_ok());
+    }
+}
@@ -0,0 +1,99 @@
+//! URL download tool module
+//!
+//! This module downloads files from allowlisted domains into the allowed
+//! directories. Downloads go through the resumable transfers of
+//! [`crate::transfer`], so a multi-gigabyte file interrupted by a dropped
+//! connection continues where it stopped instead of starting over.
+
+use crate::config::DownloadConfig;
+use crate::feed_read::{allowlisted_client, check_url};
+use crate::mcp::McpServerState;
+use crate::transfer::TransferTracker;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Default upper bound on the size of a download
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// A stalled connection is dropped, and resumed, after this long
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// URL download tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UrlDownloadRequest {
+    /// The URL to download
+    pub url: String,
+    /// Destination file, within the allowed directories
+    pub path: String,
+    /// Replace path if it already exists (default false)
+    pub overwrite: Option<bool>,
+}
+
+/// URL download tool response
+#[derive(Debug, Serialize)]
+pub struct UrlDownloadResponse {
+    pub url: String,
+    pub path: String,
+    pub size: u64,
+    /// Bytes kept from an earlier, interrupted call
+    pub resumed_from: u64,
+    pub mime_type: Option<String>,
+    /// Resource reporting the state of the transfer
+    pub transfer: String,
+}
+
+/// Execute the URL download tool
+pub fn execute_url_download(
+    request: UrlDownloadRequest,
+    config: &DownloadConfig,
+    state: &McpServerState,
+) -> Result<UrlDownloadResponse, Box<dyn std::error::Error>> {
+    let url = reqwest::Url::parse(&request.url).map_err(|e| format!("Invalid URL: {}", e))?;
+    check_url(&url, &config.allowed_domains)?;
+    let destination = crate::file_read::validate_output_path(state.fs.as_ref(), &request.path)?;
+    if state.fs.metadata(destination).is_ok() && !request.overwrite.unwrap_or(false) {
+        return Err(format!(
+            "File already exists: {} (set overwrite to replace it)",
+            request.path
+        )
+        .into());
+    }
+    let max_bytes = config.max_bytes.unwrap_or(DEFAULT_MAX_BYTES);
+
+    let client = allowlisted_client(&config.allowed_domains, STALL_TIMEOUT)?;
+    let mut tracker = TransferTracker::start(state, url.as_str(), destination);
+    let transfer = crate::transfer::transfer_uri(destination);
+    let result = crate::transfer::download(
+        &client,
+        &url,
+        destination,
+        state.fs.as_ref(),
+        max_bytes,
+        &mut |bytes, total| tracker.progress(bytes, total),
+    );
+    tracker.finish(&result);
+    let downloaded = result?;
+
+    Ok(UrlDownloadResponse {
+        url: request.url,
+        path: request.path.clone(),
+        size: downloaded.size,
+        resumed_from: downloaded.resumed_from,
+        mime_type: crate::mime::detect_file(state.fs.as_ref(), destination),
+        transfer,
+    })
+}
+
+/// Get the tool definition for the url_download tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "url_download".to_string(),
+        description: Some(format!(
+            "Download a file from an allowlisted domain into {}. Interrupted downloads resume where they stopped when called again with the same URL and path; progress is reported on the transfer:// resource of the download.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<UrlDownloadRequest>()),
+        ..Default::default()
+    }
+}
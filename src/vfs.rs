@@ -0,0 +1,560 @@
+//! Virtual filesystem module
+//!
+//! This module defines [`VirtualFs`], the filesystem the file tools read and write
+//! through. The server uses [`RootedFs`], the real filesystem limited to the allowed
+//! roots, which also refuses paths that leave them through `..` or a symbolic link.
+//! [`ReadOnlyFs`] wraps another filesystem and refuses every write, for deployments
+//! where the tools must not change files, and [`MemoryFs`] keeps files in memory so
+//! file tools can be exercised without touching the disk.
+//!
+//! Tools still check paths against the allowed roots themselves, so they report
+//! `access_denied` before the filesystem is asked.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// A file opened for reading
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// A file opened for writing
+pub trait SyncWrite: Write + Send {
+    /// Flush the written data to storage
+    fn sync(&mut self) -> io::Result<()>;
+}
+
+impl SyncWrite for fs::File {
+    fn sync(&mut self) -> io::Result<()> {
+        self.sync_all()
+    }
+}
+
+/// What a path points to, without following a final symbolic link in directory listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Other,
+}
+
+/// Metadata of a file or directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsMetadata {
+    pub kind: FileKind,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl FsMetadata {
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+}
+
+/// An entry of a directory listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsEntry {
+    pub path: PathBuf,
+    pub kind: FileKind,
+}
+
+/// The filesystem behind the file tools
+pub trait VirtualFs: fmt::Debug + Send + Sync {
+    /// Metadata of `path`, following symbolic links
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata>;
+
+    /// Open a file for reading
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek>>;
+
+    /// The entries of a directory, in no particular order
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FsEntry>>;
+
+    /// Open a file for writing, creating it, and truncating it unless `append` is set
+    fn create(&self, path: &Path, append: bool) -> io::Result<Box<dyn SyncWrite>>;
+
+    /// Move a file, replacing the destination
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Read a whole file
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.open(path)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Read a whole file as UTF-8 text
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })
+    }
+
+    /// Replace a file's contents
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.create(path, false)?.write_all(contents)
+    }
+}
+
+/// The real filesystem, limited to a set of root directories
+#[derive(Debug, Clone)]
+pub struct RootedFs {
+    roots: Vec<PathBuf>,
+}
+
+impl RootedFs {
+    pub fn new(roots: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            roots: roots.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The filesystem limited to the allowed roots of the file tools
+    pub fn allowed() -> Self {
+        Self::new(crate::file_read::ALLOWED_ROOTS.iter().copied())
+    }
+
+    /// Check that `path` stays within a root, also once symbolic links are resolved
+    ///
+    /// A path that does not exist yet is checked through its parent directory.
+    fn check<'a>(&self, path: &'a Path) -> io::Result<&'a Path> {
+        let denied = || {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is outside the allowed roots", path.display()),
+            )
+        };
+        if path.components().any(|c| matches!(c, Component::ParentDir))
+            || !self
+                .roots
+                .iter()
+                .any(|root| crate::file_read::is_within(path, root))
+        {
+            return Err(denied());
+        }
+
+        let resolved = match fs::canonicalize(path) {
+            Ok(resolved) => resolved,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                match (path.parent(), path.file_name()) {
+                    (Some(parent), Some(name)) => match fs::canonicalize(parent) {
+                        Ok(parent) => parent.join(name),
+                        // Nothing to resolve, so nothing can escape
+                        Err(_) => return Ok(path),
+                    },
+                    _ => return Err(denied()),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        let inside = self.roots.iter().any(|root| {
+            fs::canonicalize(root)
+                .map(|root| crate::file_read::is_within(&resolved, &root))
+                .unwrap_or(false)
+        });
+        if inside { Ok(path) } else { Err(denied()) }
+    }
+}
+
+impl VirtualFs for RootedFs {
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+        let metadata = fs::metadata(self.check(path)?)?;
+        Ok(FsMetadata {
+            kind: kind_of(metadata.file_type()),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(fs::File::open(self.check(path)?)?))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FsEntry>> {
+        fs::read_dir(self.check(path)?)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(FsEntry {
+                    path: entry.path(),
+                    kind: kind_of(entry.file_type()?),
+                })
+            })
+            .collect()
+    }
+
+    fn create(&self, path: &Path, append: bool) -> io::Result<Box<dyn SyncWrite>> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(self.check(path)?)?;
+        Ok(Box::new(file))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(self.check(from)?, self.check(to)?)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(self.check(path)?)
+    }
+}
+
+fn kind_of(file_type: fs::FileType) -> FileKind {
+    if file_type.is_file() {
+        FileKind::File
+    } else if file_type.is_dir() {
+        FileKind::Dir
+    } else {
+        FileKind::Other
+    }
+}
+
+/// A read-only view of another filesystem
+#[derive(Debug, Clone)]
+pub struct ReadOnlyFs<F>(pub F);
+
+impl<F> ReadOnlyFs<F> {
+    fn refuse(path: &Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is on a read-only filesystem", path.display()),
+        )
+    }
+}
+
+impl<F: VirtualFs> VirtualFs for ReadOnlyFs<F> {
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+        self.0.metadata(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek>> {
+        self.0.open(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FsEntry>> {
+        self.0.read_dir(path)
+    }
+
+    fn create(&self, path: &Path, _append: bool) -> io::Result<Box<dyn SyncWrite>> {
+        Err(Self::refuse(path))
+    }
+
+    fn rename(&self, from: &Path, _to: &Path) -> io::Result<()> {
+        Err(Self::refuse(from))
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        Err(Self::refuse(path))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    File(Arc<Mutex<Vec<u8>>>, SystemTime),
+    Dir,
+}
+
+/// Files and directories kept in memory
+///
+/// Directories are created with the files put in them; the root `/` always exists.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, with the directories leading to it
+    pub fn with_file(self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        let path = path.as_ref();
+        if let Ok(mut nodes) = self.nodes.lock() {
+            for ancestor in path.ancestors().skip(1) {
+                nodes.insert(ancestor.to_path_buf(), Node::Dir);
+            }
+            nodes.insert(
+                path.to_path_buf(),
+                Node::File(Arc::new(Mutex::new(contents.into())), SystemTime::now()),
+            );
+        }
+        self
+    }
+
+    /// Add a directory, with its parents
+    pub fn with_dir(self, path: impl AsRef<Path>) -> Self {
+        if let Ok(mut nodes) = self.nodes.lock() {
+            for ancestor in path.as_ref().ancestors() {
+                nodes.insert(ancestor.to_path_buf(), Node::Dir);
+            }
+        }
+        self
+    }
+
+    fn nodes(&self) -> io::Result<std::sync::MutexGuard<'_, BTreeMap<PathBuf, Node>>> {
+        self.nodes
+            .lock()
+            .map_err(|_| io::Error::other("Memory filesystem lock poisoned"))
+    }
+
+    fn node(&self, path: &Path) -> io::Result<Node> {
+        if path == Path::new("/") {
+            return Ok(Node::Dir);
+        }
+        self.nodes()?
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn file(&self, path: &Path) -> io::Result<Arc<Mutex<Vec<u8>>>> {
+        match self.node(path)? {
+            Node::File(data, _) => Ok(data),
+            Node::Dir => Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                format!("{} is a directory", path.display()),
+            )),
+        }
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} does not exist", path.display()),
+    )
+}
+
+impl VirtualFs for MemoryFs {
+    fn metadata(&self, path: &Path) -> io::Result<FsMetadata> {
+        Ok(match self.node(path)? {
+            Node::File(data, modified) => FsMetadata {
+                kind: FileKind::File,
+                len: data.lock().map(|data| data.len() as u64).unwrap_or(0),
+                modified: Some(modified),
+            },
+            Node::Dir => FsMetadata {
+                kind: FileKind::Dir,
+                len: 0,
+                modified: None,
+            },
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek>> {
+        let data = self.file(path)?;
+        let data = data
+            .lock()
+            .map_err(|_| io::Error::other("Memory file lock poisoned"))?
+            .clone();
+        Ok(Box::new(Cursor::new(data)))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FsEntry>> {
+        if !self.node(path).is_ok_and(|node| matches!(node, Node::Dir)) {
+            return Err(not_found(path));
+        }
+        Ok(self
+            .nodes()?
+            .iter()
+            .filter(|(child, _)| child.parent() == Some(path))
+            .map(|(child, node)| FsEntry {
+                path: child.clone(),
+                kind: match node {
+                    Node::File(..) => FileKind::File,
+                    Node::Dir => FileKind::Dir,
+                },
+            })
+            .collect())
+    }
+
+    fn create(&self, path: &Path, append: bool) -> io::Result<Box<dyn SyncWrite>> {
+        let parent = path.parent().ok_or_else(|| not_found(path))?;
+        if !matches!(self.node(parent)?, Node::Dir) {
+            return Err(not_found(parent));
+        }
+        let data = match self.node(path) {
+            Ok(_) => self.file(path)?,
+            Err(_) => {
+                let data = Arc::new(Mutex::new(Vec::new()));
+                self.nodes()?.insert(
+                    path.to_path_buf(),
+                    Node::File(Arc::clone(&data), SystemTime::now()),
+                );
+                data
+            }
+        };
+        if !append && let Ok(mut data) = data.lock() {
+            data.clear();
+        }
+        Ok(Box::new(MemoryWriter(data)))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let data = self.file(from)?;
+        let mut nodes = self.nodes()?;
+        nodes.remove(from);
+        nodes.insert(to.to_path_buf(), Node::File(data, SystemTime::now()));
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.file(path)?;
+        self.nodes()?.remove(path);
+        Ok(())
+    }
+}
+
+/// Appends to a file of a [`MemoryFs`]
+struct MemoryWriter(Arc<Mutex<Vec<u8>>>);
+
+impl Write for MemoryWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .map_err(|_| io::Error::other("Memory file lock poisoned"))?
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SyncWrite for MemoryWriter {
+    fn sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> MemoryFs {
+        MemoryFs::new()
+            .with_file("/tmp/allowed_files/notes.txt", "notes")
+            .with_file("/etc/passwd", "root:x:0:0")
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn file_tools_refuse_parent_components_before_the_filesystem() {
+        let fs = memory();
+        assert!(crate::file_read::validate_path(&fs, "/tmp/allowed_files/notes.txt").is_ok());
+        assert!(
+            crate::file_read::validate_path(&fs, "/tmp/allowed_files/../../etc/passwd").is_err()
+        );
+        assert!(crate::file_read::validate_path(&fs, "/etc/passwd").is_err());
+        assert!(
+            crate::file_read::validate_output_path(&fs, "/tmp/allowed_files/../escape.txt")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn memory_files_are_read_and_written() {
+        let fs = memory();
+        assert_eq!(
+            fs.read_to_string(Path::new("/tmp/allowed_files/notes.txt"))
+                .unwrap(),
+            "notes"
+        );
+        fs.write(Path::new("/tmp/allowed_files/new.txt"), b"new")
+            .unwrap();
+        assert_eq!(
+            fs.read(Path::new("/tmp/allowed_files/new.txt")).unwrap(),
+            b"new"
+        );
+        let entries = fs.read_dir(Path::new("/tmp/allowed_files")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(fs.write(Path::new("/missing/new.txt"), b"new").is_err());
+    }
+
+    #[test]
+    fn read_only_fs_refuses_writes() {
+        let fs = ReadOnlyFs(memory());
+        let path = Path::new("/tmp/allowed_files/notes.txt");
+        assert_eq!(fs.read_to_string(path).unwrap(), "notes");
+        for result in [
+            fs.create(path, false).map(|_| ()),
+            fs.create(path, true).map(|_| ()),
+            fs.write(path, b"changed"),
+            fs.rename(path, Path::new("/tmp/allowed_files/moved.txt")),
+            fs.remove_file(path),
+        ] {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        }
+        assert_eq!(fs.read_to_string(path).unwrap(), "notes");
+    }
+
+    #[test]
+    fn rooted_fs_refuses_parent_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir(&root).unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        let rooted = RootedFs::new([&root]);
+
+        let escape = root.join("..").join("secret.txt");
+        let error = rooted.open(&escape).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(rooted.create(&escape, false).is_err());
+        assert!(rooted.metadata(&dir.path().join("secret.txt")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rooted_fs_refuses_symlinks_leading_outside() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir(&root).unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        fs::write(root.join("inside.txt"), "inside").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret.txt"), root.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(dir.path(), root.join("parent")).unwrap();
+        let rooted = RootedFs::new([&root]);
+
+        assert_eq!(
+            rooted.read_to_string(&root.join("inside.txt")).unwrap(),
+            "inside"
+        );
+        for path in [
+            root.join("link.txt"),
+            root.join("parent").join("secret.txt"),
+            root.join("parent").join("new.txt"),
+        ] {
+            let error = rooted.read(&path).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                io::ErrorKind::PermissionDenied,
+                "{}",
+                path.display()
+            );
+        }
+        assert!(
+            rooted
+                .write(&root.join("parent").join("new.txt"), b"x")
+                .is_err()
+        );
+        assert!(!dir.path().join("new.txt").exists());
+    }
+}
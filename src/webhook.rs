@@ -0,0 +1,389 @@
+//! Webhook module
+//!
+//! This module exposes `/webhooks/{name}` endpoints that let external systems (CI,
+//! GitHub, monitoring) trigger tool calls. Each configured webhook authenticates the
+//! caller, maps the JSON payload onto the tool arguments through a template and runs
+//! the tool as a JSON-RPC tools/call would: with the limits of the webhook's API key,
+//! if it names one, and subject to the same admission control and execution limits.
+
+use crate::config::WebhookConfig;
+use crate::mcp::{McpServerState, RequestId};
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Json;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+/// Handle a webhook delivery
+pub async fn handle_webhook(
+    State(state): State<McpServerState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, Json<Value>) {
+    let Some(webhook) = state.config.webhook.iter().find(|w| w.name == name) else {
+        return error_response(StatusCode::NOT_FOUND, format!("Unknown webhook '{}'", name));
+    };
+
+    if let Err(message) = authenticate(webhook, &headers, &body) {
+        return error_response(StatusCode::UNAUTHORIZED, message);
+    }
+
+    let payload: Value = if body.is_empty() {
+        Value::Null
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(payload) => payload,
+            Err(e) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid JSON payload: {}", e),
+                );
+            }
+        }
+    };
+
+    let api_key = match &webhook.api_key {
+        Some(key_name) => match state.api_keys.named(key_name) {
+            Some(key) => Some(key),
+            None => {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Webhook '{}' names unknown API key '{}'", name, key_name),
+                );
+            }
+        },
+        None => None,
+    };
+
+    let mut params = serde_json::json!({ "name": webhook.tool });
+    if let Some(template) = &webhook.arguments {
+        params["arguments"] = render_template(template, &payload);
+    }
+    if let Some(key) = &api_key
+        && let Err(e) = crate::auth::check_request(key, "tools/call", Some(&params))
+    {
+        return error_response(StatusCode::FORBIDDEN, e.message);
+    }
+
+    // Each delivery gets its own id, as a client request would
+    let request_id = RequestId::String(uuid::Uuid::new_v4().to_string());
+    match crate::mcp::handle_tools_call(&state, None, api_key.as_deref(), &request_id, Some(params))
+        .await
+    {
+        Ok(result) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "webhook": name, "result": result })),
+        ),
+        Err(e) => {
+            let status = match e.code {
+                crate::admission::SERVER_BUSY => StatusCode::SERVICE_UNAVAILABLE,
+                crate::auth::FORBIDDEN => StatusCode::FORBIDDEN,
+                -32601 => StatusCode::NOT_FOUND,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            error_response(status, e.message)
+        }
+    }
+}
+
+/// Build a JSON error body
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<Value>) {
+    (status, Json(serde_json::json!({ "error": message })))
+}
+
+/// Verify the caller using the webhook's shared token or GitHub-style signature
+///
+/// A token may be sent as `Authorization: Bearer <token>` or `X-Webhook-Token`.
+/// A signature is the `X-Hub-Signature-256: sha256=<hex hmac>` of the raw body.
+/// When both are configured, either one is accepted.
+fn authenticate(webhook: &WebhookConfig, headers: &HeaderMap, body: &[u8]) -> Result<(), String> {
+    let token = resolve_secret(webhook.token.as_deref(), webhook.token_env.as_deref())?;
+    let secret = resolve_secret(webhook.secret.as_deref(), webhook.secret_env.as_deref())?;
+
+    if token.is_none() && secret.is_none() {
+        return Err(format!(
+            "Webhook '{}' has no token or secret configured",
+            webhook.name
+        ));
+    }
+
+    if let Some(token) = &token {
+        let provided = headers
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| {
+                headers
+                    .get("x-webhook-token")
+                    .and_then(|value| value.to_str().ok())
+            });
+        if provided.is_some_and(|provided| constant_time_eq(provided.as_bytes(), token.as_bytes()))
+        {
+            return Ok(());
+        }
+    }
+
+    if let Some(secret) = &secret
+        && let Some(signature) = headers
+            .get("x-hub-signature-256")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("sha256="))
+    {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .map_err(|e| format!("Invalid webhook secret: {}", e))?;
+        mac.update(body);
+        if let Ok(expected) = decode_hex(signature)
+            && mac.verify_slice(&expected).is_ok()
+        {
+            return Ok(());
+        }
+    }
+
+    Err("Missing or invalid webhook credentials".to_string())
+}
+
+/// Read a secret from the config or from the named environment variable
+fn resolve_secret(value: Option<&str>, env: Option<&str>) -> Result<Option<String>, String> {
+    match env {
+        Some(variable) => std::env::var(variable)
+            .map(Some)
+            .map_err(|_| format!("Webhook secret variable '{}' is not set", variable)),
+        None => Ok(value.map(str::to_string)),
+    }
+}
+
+/// Compare two byte strings without leaking where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Decode a lowercase or uppercase hex string
+fn decode_hex(text: &str) -> Result<Vec<u8>, ()> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return Err(());
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| ()))
+        .collect()
+}
+
+/// Fill an argument template with values from the payload
+///
+/// Strings may contain `{{ path }}` placeholders, where `path` is a dot-separated
+/// path into the payload (`repository.full_name`, `commits.0.id`). A string made of a
+/// single placeholder is replaced by the selected value with its JSON type intact;
+/// otherwise values are interpolated as text. Missing values render as null / empty.
+pub fn render_template(template: &Value, payload: &Value) -> Value {
+    match template {
+        Value::String(text) => render_string(text, payload),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_template(item, payload))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), render_template(value, payload)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Render the placeholders in a single template string
+fn render_string(text: &str, payload: &Value) -> Value {
+    let trimmed = text.trim();
+    if let Some(path) = trimmed
+        .strip_prefix("{{")
+        .and_then(|rest| rest.strip_suffix("}}"))
+        && !path.contains("{{")
+    {
+        return lookup(payload, path.trim()).cloned().unwrap_or(Value::Null);
+    }
+
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let path = rest[start + 2..start + end].trim();
+        match lookup(payload, path) {
+            Some(Value::String(value)) => rendered.push_str(value),
+            Some(Value::Null) | None => {}
+            Some(value) => rendered.push_str(&value.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+
+    Value::String(rendered)
+}
+
+/// Select a value from the payload by dot-separated path
+fn lookup<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() || path == "." {
+        return Some(payload);
+    }
+
+    path.split('.').try_fold(payload, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const CONFIG: &str = r#"
+        [admission]
+        max_in_flight = 0
+
+        [[webhook]]
+        name = "signed"
+        tool = "echo"
+        secret = "s3cret"
+
+        [[webhook]]
+        name = "limited"
+        tool = "echo"
+        token = "t0ken"
+        api_key = "ci"
+
+        [[api_key]]
+        name = "ci"
+        key = "ci-key"
+        tools = ["log_tail"]
+    "#;
+
+    fn config() -> ServerConfig {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    fn signature(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        let digest = mac.finalize().into_bytes();
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("sha256={}", hex)
+    }
+
+    fn signed_headers(signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-hub-signature-256", signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn a_valid_signature_is_accepted() {
+        let config = config();
+        let body = br#"{"action":"completed"}"#;
+        let headers = signed_headers(&signature("s3cret", body));
+        assert!(authenticate(&config.webhook[0], &headers, body).is_ok());
+    }
+
+    #[test]
+    fn a_bad_signature_is_refused() {
+        let config = config();
+        let body = br#"{"action":"completed"}"#;
+        for signature in [
+            signature("wrong", body),
+            signature("s3cret", b"{}"),
+            "sha256=not-hex".to_string(),
+            "sha1=0123".to_string(),
+        ] {
+            let headers = signed_headers(&signature);
+            assert!(
+                authenticate(&config.webhook[0], &headers, body).is_err(),
+                "{}",
+                signature
+            );
+        }
+    }
+
+    #[test]
+    fn a_missing_signature_is_refused() {
+        let config = config();
+        let body = br#"{"action":"completed"}"#;
+        assert!(authenticate(&config.webhook[0], &HeaderMap::new(), body).is_err());
+
+        // A token is no substitute for the signature of a webhook without one
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer s3cret".parse().unwrap());
+        assert!(authenticate(&config.webhook[0], &headers, body).is_err());
+    }
+
+    #[test]
+    fn payload_values_are_not_rendered_as_templates() {
+        let payload = json!({
+            "branch": "main\", \"path\": \"/etc/passwd",
+            "title": "{{ secret }}",
+            "secret": "hunter2",
+            "count": 3,
+        });
+        let template = json!({
+            "message": "Build of {{ branch }}: {{ title }}",
+            "branch": "{{ branch }}",
+            "count": "{{ count }}",
+            "missing": "{{ nothing.here }}",
+        });
+        assert_eq!(
+            render_template(&template, &payload),
+            json!({
+                "message": "Build of main\", \"path\": \"/etc/passwd: {{ secret }}",
+                "branch": "main\", \"path\": \"/etc/passwd",
+                "count": 3,
+                "missing": null,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn webhook_calls_go_through_admission_control() {
+        let mut state = McpServerState::new();
+        state.config = Arc::new(config());
+        let body = Bytes::from_static(b"{}");
+        let headers = signed_headers(&signature("s3cret", &body));
+
+        let (status, Json(response)) =
+            handle_webhook(State(state), Path("signed".to_string()), headers, body).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(
+            response["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("Server busy")
+        );
+    }
+
+    #[tokio::test]
+    async fn webhook_calls_are_limited_by_their_api_key() {
+        let mut config = config();
+        config.admission.max_in_flight = None;
+        let mut state = McpServerState::new();
+        state.api_keys = Arc::new(crate::auth::ApiKeys::from_config(&config.api_key).unwrap());
+        state.config = Arc::new(config);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-webhook-token", "t0ken".parse().unwrap());
+
+        let (status, _) = handle_webhook(
+            State(state),
+            Path("limited".to_string()),
+            headers,
+            Bytes::new(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,272 @@
+//! Spreadsheet reading tool module
+//!
+//! This module reads spreadsheets (xlsx, xlsm, xlsb, xls and ods) with calamine and
+//! returns cell ranges as rows of JSON values, so office documents no longer fail as
+//! binary files. Ranges use A1 notation and are clipped to the sheet's used area and
+//! to row and column limits.
+
+use crate::tool_error::ToolErrorPayload;
+use crate::vfs::VirtualFs;
+use calamine::{Data, Reader, open_workbook_auto_from_rs};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Cursor;
+
+/// Upper bound on the size of a spreadsheet file
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Default number of rows returned
+const DEFAULT_ROWS: u32 = 100;
+
+/// Upper bound on the number of rows returned
+const MAX_ROWS: u32 = 1000;
+
+/// Upper bound on the number of columns returned
+const MAX_COLUMNS: u32 = 100;
+
+/// A zero-based (row, column) cell position
+type Cell = (u32, u32);
+
+/// Xlsx read tool request parameters
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct XlsxReadRequest {
+    /// The spreadsheet file to read
+    pub path: String,
+    /// The sheet name (default: the first sheet)
+    pub sheet: Option<String>,
+    /// Cell range in A1 notation, e.g. "A1:D20"
+    pub range: Option<String>,
+    /// Use the first row of the range as column names
+    pub header: Option<bool>,
+    /// Maximum number of rows to return (default 100, max 1000)
+    pub max_rows: Option<u32>,
+}
+
+/// Xlsx read tool response
+#[derive(Debug, Serialize)]
+pub struct XlsxReadResponse {
+    pub path: String,
+    /// Every sheet in the workbook
+    pub sheets: Vec<String>,
+    pub sheet: String,
+    /// Used area of the sheet in A1 notation, absent for an empty sheet
+    pub dimensions: Option<String>,
+    /// The range returned, in A1 notation
+    pub range: Option<String>,
+    /// Header values, or column letters without a header
+    pub columns: Vec<String>,
+    /// Spreadsheet row number of the first returned row
+    pub first_row: u32,
+    pub rows: Vec<Vec<Value>>,
+    /// Whether rows or columns were cut off by the limits
+    pub truncated: bool,
+}
+
+/// Execute the xlsx read tool
+pub fn execute_xlsx_read(
+    request: XlsxReadRequest,
+    fs: &dyn VirtualFs,
+) -> Result<XlsxReadResponse, Box<dyn std::error::Error>> {
+    let path = crate::file_read::validate_path(fs, &request.path)?;
+    if fs.metadata(path)?.len > MAX_FILE_BYTES {
+        return Err(ToolErrorPayload::too_large(
+            format!("File exceeds the {} byte limit", MAX_FILE_BYTES),
+            MAX_FILE_BYTES,
+        )
+        .into());
+    }
+    let max_rows = request.max_rows.unwrap_or(DEFAULT_ROWS).clamp(1, MAX_ROWS);
+    let header = request.header.unwrap_or(false);
+
+    let data = fs
+        .read(path)
+        .map_err(|e| format!("Failed to read spreadsheet '{}': {}", request.path, e))?;
+    crate::accounting::read(data.len() as u64);
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(data))
+        .map_err(|e| format!("Failed to open spreadsheet '{}': {}", request.path, e))?;
+    let sheets = workbook.sheet_names();
+    let sheet = match request.sheet {
+        Some(sheet) if sheets.contains(&sheet) => sheet,
+        Some(sheet) => {
+            return Err(format!(
+                "Unknown sheet '{}', available sheets: {}",
+                sheet,
+                sheets.join(", ")
+            )
+            .into());
+        }
+        None => sheets.first().cloned().ok_or("Workbook has no sheets")?,
+    };
+    let cells = workbook
+        .worksheet_range(&sheet)
+        .map_err(|e| format!("Failed to read sheet '{}': {}", sheet, e))?;
+
+    let mut response = XlsxReadResponse {
+        path: request.path,
+        sheets,
+        sheet,
+        dimensions: None,
+        range: None,
+        columns: Vec::new(),
+        first_row: 1,
+        rows: Vec::new(),
+        truncated: false,
+    };
+    let (Some(used_start), Some(used_end)) = (cells.start(), cells.end()) else {
+        return Ok(response);
+    };
+    response.dimensions = Some(format_range(used_start, used_end));
+
+    // Clip the requested range to the used area; cells outside it are empty
+    let (requested_start, requested_end) = match request.range.as_deref() {
+        Some(range) => parse_range(range)?,
+        None => (used_start, used_end),
+    };
+    let start = (
+        requested_start.0.max(used_start.0),
+        requested_start.1.max(used_start.1),
+    );
+    let mut end = (
+        requested_end.0.min(used_end.0),
+        requested_end.1.min(used_end.1),
+    );
+    if start.0 > end.0 || start.1 > end.1 {
+        return Ok(response);
+    }
+
+    let row_limit = max_rows + u32::from(header);
+    if end.0 - start.0 >= row_limit {
+        end.0 = start.0 + row_limit - 1;
+        response.truncated = true;
+    }
+    if end.1 - start.1 >= MAX_COLUMNS {
+        end.1 = start.1 + MAX_COLUMNS - 1;
+        response.truncated = true;
+    }
+    response.range = Some(format_range(start, end));
+
+    let mut rows = (start.0..=end.0).map(|row| {
+        (start.1..=end.1)
+            .map(|column| {
+                cells
+                    .get_value((row, column))
+                    .map_or(Value::Null, cell_value)
+            })
+            .collect::<Vec<_>>()
+    });
+    response.columns = if header {
+        let names = rows.next().unwrap_or_default();
+        names
+            .into_iter()
+            .zip(start.1..)
+            .map(|(name, column)| match name {
+                Value::Null => column_name(column),
+                Value::String(name) => name,
+                other => other.to_string(),
+            })
+            .collect()
+    } else {
+        (start.1..=end.1).map(column_name).collect()
+    };
+    response.first_row = start.0 + 1 + u32::from(header);
+    response.rows = rows.collect();
+
+    Ok(response)
+}
+
+/// Convert a cell to JSON, rendering dates as ISO 8601 strings
+fn cell_value(cell: &Data) -> Value {
+    match cell {
+        Data::Empty => Value::Null,
+        Data::Int(value) => Value::from(*value),
+        // Spreadsheets store whole numbers as floats
+        Data::Float(value) if value.fract() == 0.0 && value.abs() < 9e15 => {
+            Value::from(*value as i64)
+        }
+        Data::Float(value) => serde_json::Number::from_f64(*value)
+            .map(Value::Number)
+            .unwrap_or(Value::Null),
+        Data::Bool(value) => Value::Bool(*value),
+        Data::String(value) | Data::DateTimeIso(value) | Data::DurationIso(value) => {
+            Value::String(value.clone())
+        }
+        Data::DateTime(value) if !value.is_duration() => match value.as_datetime() {
+            Some(datetime) => Value::String(datetime.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            None => Value::from(value.as_f64()),
+        },
+        Data::DateTime(value) => Value::from(value.as_f64()),
+        Data::Error(error) => Value::String(error.to_string()),
+    }
+}
+
+/// Parse an A1 range such as "B2:F40" or a single cell into zero-based (row, column) corners
+fn parse_range(range: &str) -> Result<(Cell, Cell), Box<dyn std::error::Error>> {
+    let (first, last) = range.split_once(':').unwrap_or((range, range));
+    let (Some(a), Some(b)) = (parse_cell(first), parse_cell(last)) else {
+        return Err(format!(
+            "Invalid range '{}', expected A1 notation like B2:F40",
+            range
+        )
+        .into());
+    };
+    Ok(((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1))))
+}
+
+/// Parse a cell reference such as "$B$2" into a zero-based (row, column)
+fn parse_cell(cell: &str) -> Option<Cell> {
+    let cell = cell.trim().replace('$', "").to_ascii_uppercase();
+    let split = cell.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = cell.split_at(split);
+    if letters.is_empty() || letters.len() > 3 || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    let column = letters
+        .bytes()
+        .fold(0, |acc, b| acc * 26 + u32::from(b - b'A' + 1));
+    let row: u32 = digits.parse().ok()?;
+    (row > 0).then(|| (row - 1, column - 1))
+}
+
+/// Column letters for a zero-based column index
+fn column_name(column: u32) -> String {
+    let mut name = Vec::new();
+    let mut n = column + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        name.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Format zero-based corners as an A1 range
+fn format_range(start: Cell, end: Cell) -> String {
+    format!(
+        "{}{}:{}{}",
+        column_name(start.1),
+        start.0 + 1,
+        column_name(end.1),
+        end.0 + 1
+    )
+}
+
+/// Get the tool definition for the xlsx_read tool
+pub fn get_tool_definition() -> crate::mcp::McpTool {
+    crate::mcp::McpTool {
+        name: "xlsx_read".to_string(),
+        version: None,
+        description: Some(format!(
+            "Read a spreadsheet (xlsx, xlsm, xlsb, xls or ods) within {}. Lists its sheets and returns a cell range as rows of JSON values, with dates as ISO 8601 strings. Without a range the sheet's used area is returned, limited to max_rows rows and 100 columns.",
+            crate::file_read::allowed_roots()
+        )),
+        input_schema: Some(crate::schema::input_schema::<XlsxReadRequest>()),
+        output_schema: None,
+        annotations: Some(crate::mcp::ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        }),
+    }
+}